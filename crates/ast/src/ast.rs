@@ -70,11 +70,11 @@ pub struct Module {
 
     /// 表格列表，（section id 4）
     /// 表格用于储存 `元素`，`表格` 和 `元素` 合在一起通常用于实现
-    /// 函数的间接调用，目前只支持声明或导入 1 个表格
+    /// 函数的间接调用，一个模块可以声明或导入多个表格
     pub tables: Vec<TableType>,
 
     /// 内存块描述列表，（section id 5）
-    /// 目前只支持声明或导入 1 项内存块
+    /// 一个模块可以声明或导入多个内存块（multi-memory）
     pub memory_blocks: Vec<MemoryType>,
 
     /// 全局变量列表，（section id 6）
@@ -321,9 +321,9 @@ pub enum ImportDescriptor {
 /// ## 二进制格式
 ///
 /// table_section = 0x04 + content_length:u32 + <table_type> // 目前一个模块仅支持声明一个表项
-/// table_type = 0x70 + limits
+/// table_type = ref_type:byte + limits
 ///              ^
-///              |--- 0x70 表示该表项存储的是 funcref
+///              |--- 0x70 表示该表项存储的是 funcref，0x6F 表示 externref
 ///
 /// 表项仅用于说明表的容量，真正的内容（即函数索引列表）被存储在元素项里，
 /// 元素段存储的是表的初始化数据。
@@ -332,7 +332,7 @@ pub enum ImportDescriptor {
 ///
 /// (func $f1)
 /// (func $f2)
-/// (table 1 10 funcref)                    ;; 表的类型暂时只能是 `funcref`
+/// (table 1 10 funcref)                    ;; 表的类型可以是 `funcref` 或者 `externref`
 /// (elem (offset (i32.const 1)) $f1 $f2)   ;; 元素项的偏移值需要使用 `const`表达式
 ///
 /// 元素项也可以内联到表段里：
@@ -343,6 +343,8 @@ pub enum ImportDescriptor {
 ///
 #[derive(Debug, PartialEq, Clone)]
 pub struct TableType {
+    /// 表的元素的类型，即 ValueType::FuncRef 或者 ValueType::ExternRef
+    pub ref_type: ValueType,
     pub limit: Limit,
 }
 
@@ -545,17 +547,34 @@ pub enum ExportDescriptor {
 /// ## 二进制格式
 ///
 /// element_section = 0x09 + content_length:u32 + <element_item>
-/// element_item = table_index:u32 + offset_expression + <function_index>
+/// element_item = flag:u32 + ...
+///
+/// 元素项以一个标记（flag）开始，标记的值决定了元素项的模式以及后续内容的格式：
+///
+/// - 0: offset_expression + <function_index>                               ;; 主动模式，表索引为 0
+/// - 1: element_kind:byte + <function_index>                               ;; 被动模式
+/// - 2: table_index:u32 + offset_expression + element_kind:byte + <function_index> ;; 主动模式
+/// - 3: element_kind:byte + <function_index>                               ;; 声明模式
+///
 /// offset_expression = byte{*} + 0x0B  // 表达式（指令列表）以 0x0B 结尾
+/// element_kind 目前只能是 0x00，表示 funcref
+///
+/// 标记 4 ~ 7 对应元素内容为常量表达式（而非函数索引）的格式：
 ///
-/// 元素段里的每个项目的内容由 3 部分组成：
-/// 1. 表的索引，因为目前一个模块只支持一张表，所以它的值恒等于 0；
-/// 2. 表内偏移量，是一个常量表达式；
-/// 3. 函数索引值列表
+/// - 4: offset_expression + <expression>                                   ;; 主动模式，表索引为 0
+/// - 5: ref_type:byte + <expression>                                       ;; 被动模式
+/// - 6: table_index:u32 + offset_expression + ref_type:byte + <expression> ;; 主动模式
+/// - 7: ref_type:byte + <expression>                                       ;; 声明模式
+///
+/// 其中 ref_type 是 0x70（funcref）或者 0x6F（externref），标记 4 的 ref_type 为 funcref，
+/// 每个表达式的值是一个引用，一般是 `ref.func` 或者 `ref.null` 指令。
 ///
 /// ## 文本格式
 ///
 /// (elem (offset (i32.const 1)) $f1 $f2)   ;; 元素项的偏移值需要使用（const）表达式
+/// (elem func $f1 $f2)                     ;; 被动模式
+/// (elem declare func $f1 $f2)             ;; 声明模式
+/// (elem funcref (ref.func $f1) (ref.null func)) ;; 元素内容为表达式
 ///
 /// 元素项也可以内联到表段里：
 ///
@@ -563,16 +582,62 @@ pub enum ExportDescriptor {
 ///
 #[derive(Debug, PartialEq, Clone)]
 pub struct ElementItem {
-    /// 表索引，目前 WebAssembly 标准只支持 0
-    pub table_index: u32,
+    /// 元素项的模式
+    pub mode: ElementMode,
+
+    /// 元素列表
+    /// 元素会从指定的偏移值开始紧密排列，
+    /// 但这一组元素之间并没有必然的关联，只是恰好排列在一起而已。
+    pub items: ElementItems,
+}
 
-    /// 偏移值表达式（指令列表）
-    pub offset_instruction_items: Vec<Instruction>,
+/// 元素项的内容
+#[derive(Debug, PartialEq, Clone)]
+pub enum ElementItems {
+    /// 函数索引列表（标记 0 ~ 3），元素的类型为 funcref
+    FunctionIndices(Vec<u32>),
+
+    /// 常量表达式列表（标记 4 ~ 7）
+    Expressions(
+        /* ref_type */ ValueType,
+        /* expressions */ Vec<Vec<Instruction>>,
+    ),
+}
 
-    /// 函数索引列表
-    /// function_indices 这个列表会从指定的偏移值开始，把一系列函数的索引紧密排列，
-    /// 但这一组函数之间并没有必然的关联，只是恰好排列在一起而已。
-    pub function_indices: Vec<u32>,
+impl ElementItems {
+    /// 元素的数量
+    pub fn len(&self) -> usize {
+        match self {
+            ElementItems::FunctionIndices(function_indices) => function_indices.len(),
+            ElementItems::Expressions(_, expressions) => expressions.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// 元素项的模式
+///
+/// https://webassembly.github.io/spec/core/syntax/modules.html#element-segments
+#[derive(Debug, PartialEq, Clone)]
+pub enum ElementMode {
+    /// 主动模式，在模块实例化时元素项会被复制到指定的表
+    Active {
+        /// 表索引
+        table_index: u32,
+
+        /// 偏移值表达式（指令列表）
+        offset_instruction_items: Vec<Instruction>,
+    },
+
+    /// 被动模式，元素项可以通过 `table.init 指令` 复制到表
+    Passive,
+
+    /// 声明模式，元素项仅用于声明可以被 `ref.func 指令` 引用的函数，
+    /// 不能被复制到表
+    Declarative,
 }
 
 /// # 代码项
@@ -682,16 +747,23 @@ pub struct LocalGroup {
 /// ## 二进制格式
 ///
 /// data_section = 0x0b + content_length:u32 + <data_item>
-/// data_item = memory_block_index:u32 + offset_expression + data:byte{*}
+/// data_item = flag:u32 + ...
+///
+/// 数据项以一个标记（flag）开始，标记的值决定了数据项的模式以及后续内容的格式：
+///
+/// - 0: offset_expression + data:byte{*}                           ;; 主动模式，内存块索引为 0
+/// - 1: data:byte{*}                                               ;; 被动模式
+/// - 2: memory_block_index:u32 + offset_expression + data:byte{*}  ;; 主动模式
+///
 /// offset_expression = = byte{*} + 0x0B  // 表达式（指令列表）以 0x0B 结尾
 ///
-/// 数据段每一项由 3 部分组成：
-/// 1. 内存块索引，因为目前一个模块只能有一块内存，所以这个值恒等于 0
+/// 主动模式的数据项由 3 部分组成：
+/// 1. 内存块索引
 /// 2. 内存偏移值，一个常量表达式
 /// 3. 初始数据
 ///
 /// 数据项示例：
-/// - 00                ;; 内存块索引
+/// - 00                ;; 标记，主动模式且内存块索引为 0
 /// - 41 80 80 c0 00    ;; 偏移值表达式开始，指令是 i32.const(0x41) 0x100000
 /// - 0b                ;; 偏移值表达式结束标记
 /// - 0e                ;; 内容长度 14 字节（0x0e）
@@ -707,18 +779,37 @@ pub struct LocalGroup {
 /// - 十六进制 byte: "\de\ad\be\ef\00"
 /// - Unicode code point: "\u{1234}\u{5678}"
 ///
+/// 被动模式的数据项省略内存块索引和偏移值：
+///
+/// (data "foo")
+///
 #[derive(Debug, PartialEq, Clone)]
 pub struct DataItem {
-    /// 内存块索引，目前 WebAssembly 标准只支持 0
-    pub memory_block_index: u32,
-
-    /// 偏移值表达式（指令列表）
-    pub offset_instruction_items: Vec<Instruction>,
+    /// 数据项的模式
+    pub mode: DataMode,
 
     /// 内容
     pub data: Vec<u8>,
 }
 
+/// 数据项的模式
+///
+/// https://webassembly.github.io/spec/core/syntax/modules.html#data-segments
+#[derive(Debug, PartialEq, Clone)]
+pub enum DataMode {
+    /// 主动模式，在模块实例化时数据会被复制到指定的内存块
+    Active {
+        /// 内存块索引
+        memory_block_index: u32,
+
+        /// 偏移值表达式（指令列表）
+        offset_instruction_items: Vec<Instruction>,
+    },
+
+    /// 被动模式，数据可以通过 `memory.init 指令` 复制到内存块
+    Passive,
+}
+
 // /// 指令项
 // ///
 // /// 指令项包括了指令本身（类型和参数）以及指令的位置等信息
@@ -4,6 +4,8 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use crate::types::ValueType;
+
 /// # WebAssembly 指令列表
 ///
 /// <https://webassembly.github.io/spec/core/syntax/instructions.html>
@@ -54,6 +56,7 @@ pub enum Instruction {
 
     Drop,
     Select,
+    SelectTyped(Vec<ValueType>), // params: (value_types)，目前只能有一个类型
 
     LocalGet(u32),  // params: (local_variable_index)
     LocalSet(u32),  // params: (local_variable_index)
@@ -256,22 +259,30 @@ pub enum Instruction {
     I64TruncSatF32U,
     I64TruncSatF64S,
     I64TruncSatF64U,
+
+    RefNull(ValueType), // params: (ref_type)
+    RefIsNull,
+    RefFunc(u32), // params: (function_index)
 }
 
 /// 流程控制结构块（比如 if/block/loop）跟函数类似
 /// 也可以有参数和返回值，除了可以跟函数一样共享 `类型段`（`Type Secion`）所
-/// 定义的类型，还有内置的 5 种无参数的类型：
+/// 定义的类型，还有内置的 7 种无参数的类型：
 /// - () -> i32
 /// - () -> i64
 /// - () -> f32
 /// - () -> f64
+/// - () -> funcref
+/// - () -> externref
 /// - () -> ()
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub enum BlockType {
     ResultI32, //
     ResultI64, //
     ResultF32, //
     ResultF64, //
+    ResultFuncRef,
+    ResultExternRef,
     ResultEmpty,
     TypeIndex(u32),
 }
@@ -282,10 +293,15 @@ pub enum BlockType {
 /// 二进制格式
 ///
 /// i32.load align:uint32 offset:uint32
+/// i32.load align_with_flag:uint32 mem_block_idx:uint32 offset:uint32
+///
+/// 当 align 的第 6 位（0x40）为 1 时，表示 align 之后跟随着内存块的索引（multi-memory），
+/// 否则内存块的索引为 0。
 ///
 /// 文本格式
 ///
 /// (i32.load offset=0 align=2)
+/// (i32.load 1 offset=0 align=2) ;; 访问索引为 1 的内存块
 ///
 /// 对于文本格式，必须先写 offset 再写 align，且可以省略 `align` 值，
 /// 对于 i32.load/i32.store，默认对齐 4 个字节
@@ -302,8 +318,9 @@ pub enum BlockType {
 ///   比如 align = 2 时，表示对齐 2^2 = 4 个字节
 ///   align 只起提示作用，用于帮助编译器优化机器代码，对实际执行没有影响（对于 wasm 解析器，可以忽略这个值）
 ///   文本格式里 `align` 的值就是字节数，比如文本格式的 8 对应二进制格式的 3 (2^3)。
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Copy)]
 pub struct MemoryArgument {
-    pub align: u32, // 这里记录的是跟二进制格式里所存储的一致的数值，也就是指数。
+    pub align: u32, // 这里记录的是跟二进制格式里所存储的一致的数值，也就是指数（不包括内存块索引标记）。
     pub offset: u32,
+    pub memory_block_index: u32,
 }
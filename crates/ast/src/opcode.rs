@@ -30,6 +30,7 @@ pub const CALL_INDIRECT: u8 = 0x11;
 //
 pub const DROP: u8 = 0x1A;
 pub const SELECT: u8 = 0x1B;
+pub const SELECT_TYPED: u8 = 0x1C;
 //
 // ### 变量类指令
 //
@@ -206,6 +207,12 @@ pub const I32_EXTEND16_S: u8 = 0xC1;
 pub const I64_EXTEND8_S: u8 = 0xC2;
 pub const I64_EXTEND16_S: u8 = 0xC3;
 pub const I64_EXTEND32_S: u8 = 0xC4;
+//
+// ### 引用类指令
+//
+pub const REF_NULL: u8 = 0xD0;
+pub const REF_IS_NULL: u8 = 0xD1;
+pub const REF_FUNC: u8 = 0xD2;
 
 // ## 扩展指令
 //
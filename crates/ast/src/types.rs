@@ -10,14 +10,26 @@ use std::fmt::Display;
 ///
 /// <https://webassembly.github.io/spec/core/syntax/types.html>
 ///
-/// WebAssembly 的数值只支持 4 种基本数据类型
+/// WebAssembly 的数值支持 4 种基本数据类型
 /// i32, i64, f32, f64
+///
+/// 以及 2 种引用类型
+/// funcref, externref
 #[derive(Debug, PartialEq, Clone)]
 pub enum ValueType {
     I32,
     I64,
     F32,
     F64,
+    FuncRef,
+    ExternRef,
+}
+
+impl ValueType {
+    /// 是否引用类型
+    pub fn is_ref_type(&self) -> bool {
+        matches!(self, ValueType::FuncRef | ValueType::ExternRef)
+    }
 }
 
 impl Display for ValueType {
@@ -27,6 +39,8 @@ impl Display for ValueType {
             ValueType::I64 => write!(f, "i64"),
             ValueType::F32 => write!(f, "f32"),
             ValueType::F64 => write!(f, "f64"),
+            ValueType::FuncRef => write!(f, "funcref"),
+            ValueType::ExternRef => write!(f, "externref"),
         }
     }
 }
@@ -36,12 +50,19 @@ impl Display for ValueType {
 /// 部分指令会明确表明需要将整数解析为无符号整数（unsigned integer）进行运算，
 /// 比如 `lt_u` 和 `gt_u` 等，而 `Value` 仅包含了有符号的整数，
 /// 所以进行无符号运算时，需要先转换再运算。
+///
+/// 引用类型的值为 None 时表示空引用（null）。
+/// - FuncRef 的值是函数的索引；
+/// - ExternRef 的值是由宿主定义的不透明句柄（handle），VM 不解析它的含义，
+///   宿主可以用它来关联宿主一侧的对象。
 #[derive(Debug, PartialEq, Clone, Copy)]
 pub enum Value {
     I32(i32),
     I64(i64),
     F32(f32),
     F64(f64),
+    FuncRef(Option<u32>),
+    ExternRef(Option<u32>),
 }
 
 impl Value {
@@ -52,6 +73,31 @@ impl Value {
             Self::I64(_) => ValueType::I64,
             Self::F32(_) => ValueType::F32,
             Self::F64(_) => ValueType::F64,
+            Self::FuncRef(_) => ValueType::FuncRef,
+            Self::ExternRef(_) => ValueType::ExternRef,
+        }
+    }
+
+    /// 构造指定引用类型的空引用
+    ///
+    /// 如果 ref_type 不是引用类型，则返回 None
+    pub fn null_of(ref_type: &ValueType) -> Option<Value> {
+        match ref_type {
+            ValueType::FuncRef => Some(Value::FuncRef(None)),
+            ValueType::ExternRef => Some(Value::ExternRef(None)),
+            _ => None,
+        }
+    }
+
+    /// 构造指定类型的默认值（即数值 0 或者空引用）
+    pub fn default_of(value_type: &ValueType) -> Value {
+        match value_type {
+            ValueType::I32 => Value::I32(0),
+            ValueType::I64 => Value::I64(0),
+            ValueType::F32 => Value::F32(0.0),
+            ValueType::F64 => Value::F64(0.0),
+            ValueType::FuncRef => Value::FuncRef(None),
+            ValueType::ExternRef => Value::ExternRef(None),
         }
     }
 }
@@ -63,6 +109,10 @@ impl Display for Value {
             Value::I64(v) => write!(f, "{}", v),
             Value::F32(v) => write!(f, "{}", v),
             Value::F64(v) => write!(f, "{}", v),
+            Value::FuncRef(Some(v)) => write!(f, "ref.func {}", v),
+            Value::FuncRef(None) => write!(f, "ref.null func"),
+            Value::ExternRef(Some(v)) => write!(f, "ref.extern {}", v),
+            Value::ExternRef(None) => write!(f, "ref.null extern"),
         }
     }
 }
@@ -0,0 +1,21 @@
+(module
+    (memory 1)
+    (table 2 funcref)
+
+    (data $passive "foo")
+    (data (offset (i32.const 100)) "bar")
+
+    (elem $passive_elem func $f0 $f0)
+    (elem (offset (i32.const 1)) $f0)
+    (elem declare func $f0)
+
+    (func $f0
+        (memory.init $passive (i32.const 1) (i32.const 2) (i32.const 3))
+        (data.drop $passive)
+        (memory.copy (i32.const 4) (i32.const 5) (i32.const 6))
+        (memory.fill (i32.const 7) (i32.const 8) (i32.const 9))
+        (table.init $passive_elem (i32.const 1) (i32.const 2) (i32.const 3))
+        (elem.drop $passive_elem)
+        (table.copy (i32.const 4) (i32.const 5) (i32.const 6))
+    )
+)
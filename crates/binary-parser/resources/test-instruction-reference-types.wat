@@ -0,0 +1,24 @@
+(module
+    (table 2 externref)
+
+    (elem (offset (i32.const 0)) funcref (ref.func $f0) (ref.null func))
+    (elem $passive_elem externref (ref.null extern))
+    (elem declare funcref (ref.func $f0))
+
+    (func $f0 (param externref) (result i32)
+        (ref.null extern)
+        (drop)
+        (ref.func $f0)
+        (drop)
+        (select (result externref) (local.get 0) (ref.null extern) (i32.const 1))
+        (ref.is_null)
+        (drop)
+        (table.get (i32.const 1))
+        (drop)
+        (table.set (i32.const 2) (local.get 0))
+        (table.grow (ref.null extern) (i32.const 3))
+        (drop)
+        (table.fill (i32.const 4) (local.get 0) (i32.const 5))
+        (table.size)
+    )
+)
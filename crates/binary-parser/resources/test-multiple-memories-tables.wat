@@ -0,0 +1,15 @@
+(module
+    (memory $m0 1)
+    (memory $m1 2)
+    (table $t0 1 funcref)
+    (table $t1 2 externref)
+
+    (data (memory $m1) (i32.const 0) "foo")
+
+    (func $f0
+        (i32.store8 $m1 offset=4 (i32.const 1) (i32.const 2))
+        (memory.copy $m0 $m1 (i32.const 3) (i32.const 4) (i32.const 5))
+        (table.copy $t0 $t0 (i32.const 6) (i32.const 7) (i32.const 8))
+        (call_indirect $t0 (i32.const 9))
+    )
+)
@@ -54,9 +54,6 @@ pub enum Unsupported {
     UnsupportedImportTag(/* tag */ u8),
     UnsupportedTableTag(/* tag */ u8),
 
-    UnsupportMultipleTable,
-    UnsupportMultipleMemoryBlock,
-
     UnsupportedInstructionOpcode(/* opcode */ u8),
     UnsupportedInstructionExtensionCode(/* opcode */ u8, /* extension_code */ u32),
 
@@ -100,12 +97,6 @@ impl Display for Unsupported {
             Unsupported::UnsupportedTableTag(tag) => {
                 write!(f, "unsupported table tag: {}", tag)
             }
-            Unsupported::UnsupportMultipleTable => {
-                write!(f, "only one table is allowed in a module")
-            }
-            Unsupported::UnsupportMultipleMemoryBlock => {
-                write!(f, "only one memory block is allowed in a module")
-            }
             Unsupported::UnsupportedInstructionOpcode(opcode) => {
                 write!(f, "unsupported instruction opcode: {}", opcode)
             }
@@ -129,6 +120,11 @@ pub enum SyntaxError {
     InvalidCustomNameSectionTag(u8),
     InvalidBlockType(i32),
     InvalidConstantExpressionInstruction(Instruction),
+    InvalidElementItemFlag(u32),
+    InvalidElementKind(u8),
+    InvalidDataItemFlag(u32),
+    InvalidRefType(u8),
+    InvalidSelectTypeCount(usize),
 }
 
 impl Display for SyntaxError {
@@ -156,6 +152,21 @@ impl Display for SyntaxError {
             SyntaxError::InvalidBlockType(value) => {
                 write!(f, "invalid block type: {}", value)
             }
+            SyntaxError::InvalidElementItemFlag(flag) => {
+                write!(f, "invalid element item flag: {}", flag)
+            }
+            SyntaxError::InvalidElementKind(kind) => {
+                write!(f, "invalid element kind: {}", kind)
+            }
+            SyntaxError::InvalidDataItemFlag(flag) => {
+                write!(f, "invalid data item flag: {}", flag)
+            }
+            SyntaxError::InvalidRefType(tag) => {
+                write!(f, "invalid reference type: {}", tag)
+            }
+            SyntaxError::InvalidSelectTypeCount(count) => {
+                write!(f, "invalid number of select types: {}", count)
+            }
         }
     }
 }
@@ -7,10 +7,11 @@ use std::vec;
 
 use anvm_ast::{
     ast::{
-        CodeItem, CustomItem, DataItem, ElementItem, ExportDescriptor, ExportItem,
-        FunctionIndexAndBlockLabelsPair, FunctionIndexAndLocalVariableNamesPair, FunctionType,
-        GlobalItem, GlobalType, ImportDescriptor, ImportItem, IndexNamePair, Limit, LocalGroup,
-        MemoryType, Module, NameCollection, TableType, TypeItem,
+        CodeItem, CustomItem, DataItem, DataMode, ElementItem, ElementItems, ElementMode,
+        ExportDescriptor, ExportItem, FunctionIndexAndBlockLabelsPair,
+        FunctionIndexAndLocalVariableNamesPair, FunctionType, GlobalItem, GlobalType,
+        ImportDescriptor, ImportItem, IndexNamePair, Limit, LocalGroup, MemoryType, Module,
+        NameCollection, TableType, TypeItem,
     },
     instruction::{BlockType, Instruction, MemoryArgument},
     opcode,
@@ -467,6 +468,8 @@ fn continue_parse_value_type(source: &[u8]) -> Result<(ValueType, &[u8]), ParseE
         types::VALUE_TYPE_TAG_I64 => ValueType::I64,
         types::VALUE_TYPE_TAG_F32 => ValueType::F32,
         types::VALUE_TYPE_TAG_F64 => ValueType::F64,
+        types::VALUE_TYPE_TAG_FUNC_REF => ValueType::FuncRef,
+        types::VALUE_TYPE_TAG_EXTERN_REF => ValueType::ExternRef,
         _ => {
             return Err(ParseError::Unsupported(Unsupported::UnsupportedValueTag(
                 tag,
@@ -477,6 +480,20 @@ fn continue_parse_value_type(source: &[u8]) -> Result<(ValueType, &[u8]), ParseE
     Ok((value_type, post_tag))
 }
 
+/// ref_type = 0x70 | 0x6F
+fn continue_parse_ref_type(source: &[u8]) -> Result<(ValueType, &[u8]), ParseError> {
+    let (tag, post_tag) = read_byte(source)?;
+    let ref_type = match tag {
+        types::VALUE_TYPE_TAG_FUNC_REF => ValueType::FuncRef,
+        types::VALUE_TYPE_TAG_EXTERN_REF => ValueType::ExternRef,
+        _ => {
+            return Err(ParseError::SyntaxError(SyntaxError::InvalidRefType(tag)));
+        }
+    };
+
+    Ok((ref_type, post_tag))
+}
+
 /// # 解析导入项
 ///
 /// import_section = 0x02 + content_length:u32 + <import_item>
@@ -547,19 +564,23 @@ fn continue_parse_import_item(source: &[u8]) -> Result<(ImportItem, &[u8]), Pars
     Ok((import_item, remains))
 }
 
-/// table_type = 0x70 + limits
+/// table_type = ref_type:byte + limits
 ///              ^
-///              |--- 0x70 表示该表项存储的是 funcref
+///              |--- 0x70 表示该表项存储的是 funcref，0x6F 表示 externref
 fn continue_parse_table_type(source: &[u8]) -> Result<(TableType, &[u8]), ParseError> {
     let (tag, post_tag) = read_byte(source)?;
-    if tag != types::TABLE_TYPE_TAG_FUNC_REF {
-        Err(ParseError::Unsupported(Unsupported::UnsupportedTableTag(
-            tag,
-        )))
-    } else {
-        let (limit, post_limit) = continue_parse_limit(post_tag)?;
-        Ok((TableType { limit: limit }, post_limit))
-    }
+    let ref_type = match tag {
+        types::TABLE_TYPE_TAG_FUNC_REF => ValueType::FuncRef,
+        types::TABLE_TYPE_TAG_EXTERN_REF => ValueType::ExternRef,
+        _ => {
+            return Err(ParseError::Unsupported(Unsupported::UnsupportedTableTag(
+                tag,
+            )));
+        }
+    };
+
+    let (limit, post_limit) = continue_parse_limit(post_tag)?;
+    Ok((TableType { ref_type, limit }, post_limit))
 }
 
 /// memory_type = limits
@@ -628,21 +649,15 @@ fn parse_function_list_section(source: &[u8]) -> Result<Vec<u32>, ParseError> {
 
 /// # 解析表段
 ///
-/// table_section = 0x04 + content_length:u32 + <table_type> // 目前一个模块仅支持声明一个表项
-/// table_type = 0x70 + limits
+/// table_section = 0x04 + content_length:u32 + <table_type>
+/// table_type = ref_type:byte + limits
 ///              ^
-///              |--- 0x70 表示该表项存储的是 funcref
+///              |--- 0x70 表示该表项存储的是 funcref，0x6F 表示 externref
 ///
-/// 注意，如果一个模块已经导入了一张表，而在表段再次定义一个表，
-/// 则应该视为错误，因为目前 WebAssembly 只允许一张表，
-/// 但在语法解析阶段不抛出错误，留到运行再抛出。
+/// 一个模块可以定义多个表，表的索引从导入的表开始计算。
 fn parse_table_section(source: &[u8]) -> Result<Vec<TableType>, ParseError> {
     let (item_count, post_item_count) = read_u32(source)?;
 
-    if item_count > 1 {
-        return Err(ParseError::Unsupported(Unsupported::UnsupportMultipleTable));
-    }
-
     let mut remains = post_item_count;
     let mut table_types = Vec::<TableType>::with_capacity(item_count as usize);
 
@@ -661,22 +676,13 @@ fn parse_table_section(source: &[u8]) -> Result<Vec<TableType>, ParseError> {
 
 /// # 解析内存段
 ///
-/// memory_section = 0x05 + content_length:u32 + <memory_type> // 目前一个模块仅支持声明一个内存块
+/// memory_section = 0x05 + content_length:u32 + <memory_type>
 /// memory_type = limits
 ///
-/// 注意，如果一个模块已经导入了一个内存块，而在内存段再次定义一个内存块，
-/// 则应该视为错误，因为目前 WebAssembly 只允许一个内存块。
-///
-/// 但在语法解析阶段不抛出错误，留到运行再抛出。
+/// 一个模块可以定义多个内存块（multi-memory），内存块的索引从导入的内存块开始计算。
 fn parse_memory_section(source: &[u8]) -> Result<Vec<MemoryType>, ParseError> {
     let (item_count, post_item_count) = read_u32(source)?;
 
-    if item_count > 1 {
-        return Err(ParseError::Unsupported(
-            Unsupported::UnsupportMultipleMemoryBlock,
-        ));
-    }
-
     let mut remains = post_item_count;
     let mut memory_types = Vec::<MemoryType>::with_capacity(item_count as usize);
 
@@ -836,6 +842,20 @@ fn continue_parse_instruction_item(
         }
         opcode::DROP => Instruction::Drop,
         opcode::SELECT => Instruction::Select,
+        opcode::SELECT_TYPED => {
+            // select_typed = opcode_select_typed + <value_type>
+            let (value_types, post_value_types) = continue_parse_value_types(remains)?;
+            remains = post_value_types;
+
+            // 目前 WebAssembly 规范只允许一个类型
+            if value_types.len() != 1 {
+                return Err(ParseError::SyntaxError(
+                    SyntaxError::InvalidSelectTypeCount(value_types.len()),
+                ));
+            }
+
+            Instruction::SelectTyped(value_types)
+        }
 
         // 变量指令
         opcode::LOCAL_GET => {
@@ -1189,6 +1209,22 @@ fn continue_parse_instruction_item(
         opcode::I64_EXTEND8_S => Instruction::I64Extend8S,
         opcode::I64_EXTEND16_S => Instruction::I64Extend16S,
         opcode::I64_EXTEND32_S => Instruction::I64Extend32S,
+
+        // 引用指令
+        opcode::REF_NULL => {
+            // ref.null = opcode_ref_null + ref_type:byte
+            let (ref_type, post_ref_type) = continue_parse_ref_type(remains)?;
+            remains = post_ref_type;
+            Instruction::RefNull(ref_type)
+        }
+        opcode::REF_IS_NULL => Instruction::RefIsNull,
+        opcode::REF_FUNC => {
+            // ref.func = opcode_ref_func + function_index:u32
+            let (function_index, post_index) = read_u32(remains)?;
+            remains = post_index;
+            Instruction::RefFunc(function_index)
+        }
+
         opcode::EXTENSION_0XFC => {
             let (sub_opcode, post_sub_opcode) = read_u32(remains)?;
             let (extension_instruction, post_extension) = continue_parse_extension_instructions(
@@ -1248,9 +1284,10 @@ fn continue_parse_extension_instructions(
             Instruction::DataDrop(data_index)
         }
         opcode::MEMORY_COPY => {
-            let (source_memory_block_index, post_source_memory_block_index) = read_u32(remains)?;
-            let (dest_memory_block_index, post_dest_memory_block_index) = read_u32(post_source_memory_block_index)?;
-            remains = post_dest_memory_block_index;
+            // 二进制格式里目标内存块索引在前，源内存块索引在后
+            let (dest_memory_block_index, post_dest_memory_block_index) = read_u32(remains)?;
+            let (source_memory_block_index, post_source_memory_block_index) = read_u32(post_dest_memory_block_index)?;
+            remains = post_source_memory_block_index;
             Instruction::MemoryCopy(source_memory_block_index, dest_memory_block_index)
         }
         opcode::MEMORY_FILL => {
@@ -1272,9 +1309,10 @@ fn continue_parse_extension_instructions(
             Instruction::ElementDrop(element_index)
         }
         opcode::TABLE_COPY => {
-            let (source_table_index, post_source_table_index) = read_u32(remains)?;
-            let (dest_table_index, post_dest_table_index) = read_u32(post_source_table_index)?;
-            remains = post_dest_table_index;
+            // 二进制格式里目标表索引在前，源表索引在后
+            let (dest_table_index, post_dest_table_index) = read_u32(remains)?;
+            let (source_table_index, post_source_table_index) = read_u32(post_dest_table_index)?;
+            remains = post_source_table_index;
             Instruction::TableCopy(source_table_index, dest_table_index)
         }
         opcode::TABLE_GROW => {
@@ -1313,6 +1351,8 @@ fn get_block_type(value: i32) -> Result<BlockType, ParseError> {
         types::BLOCK_TYPE_I64 => Ok(BlockType::ResultI64),
         types::BLOCK_TYPE_F32 => Ok(BlockType::ResultF32),
         types::BLOCK_TYPE_F64 => Ok(BlockType::ResultF64),
+        types::BLOCK_TYPE_FUNC_REF => Ok(BlockType::ResultFuncRef),
+        types::BLOCK_TYPE_EXTERN_REF => Ok(BlockType::ResultExternRef),
         types::BLOCK_TYPE_EMPTY => Ok(BlockType::ResultEmpty),
         _ if value >= 0 => Ok(BlockType::TypeIndex(value as u32)),
         _ => Err(ParseError::SyntaxError(SyntaxError::InvalidBlockType(
@@ -1341,6 +1381,7 @@ fn get_block_type(value: i32) -> Result<BlockType, ParseError> {
 /// 文本格式：
 ///
 /// (i32.load offset=200 align=8) ;; 注意先写 offset 后写 align
+/// (i32.load 1 offset=200 align=8) ;; 访问索引为 1 的内存块
 ///
 /// 在文本格式里缺省 `align` 值时，
 /// 对于 i32.load/i32.store，默认对齐 4 个字节
@@ -1348,9 +1389,27 @@ fn get_block_type(value: i32) -> Result<BlockType, ParseError> {
 fn continue_parse_memory_load_and_store_argument(
     source: &[u8],
 ) -> Result<(MemoryArgument, &[u8]), ParseError> {
-    let (align, post_align) = read_u32(source)?;
-    let (offset, post_offset) = read_u32(post_align)?;
-    let memory_argument = MemoryArgument { align, offset };
+    let (align_with_flag, post_align) = read_u32(source)?;
+
+    // align 的第 6 位为 1 时，表示 align 之后跟随着内存块的索引
+    let (align, memory_block_index, post_memory_block_index) =
+        if align_with_flag & types::MEMORY_ARGUMENT_FLAG_MEMORY_BLOCK_INDEX != 0 {
+            let (memory_block_index, post_memory_block_index) = read_u32(post_align)?;
+            (
+                align_with_flag & !types::MEMORY_ARGUMENT_FLAG_MEMORY_BLOCK_INDEX,
+                memory_block_index,
+                post_memory_block_index,
+            )
+        } else {
+            (align_with_flag, 0, post_align)
+        };
+
+    let (offset, post_offset) = read_u32(post_memory_block_index)?;
+    let memory_argument = MemoryArgument {
+        align,
+        offset,
+        memory_block_index,
+    };
     Ok((memory_argument, post_offset))
 }
 
@@ -1421,8 +1480,7 @@ fn parse_start_function_section(source: &[u8]) -> Result<u32, ParseError> {
 /// # 解析元素段
 ///
 /// element_section = 0x09 + content_length:u32 + <element_item>
-/// element_item = table_index:u32 + offset_expression + <function_index>
-/// offset_expression = byte{*} + 0x0B  // 表达式（指令列表）以 0x0B 结尾
+/// element_item = flag:u32 + ...
 fn parse_element_section(source: &[u8]) -> Result<Vec<ElementItem>, ParseError> {
     let (item_count, post_item_count) = read_u32(source)?;
 
@@ -1442,20 +1500,98 @@ fn parse_element_section(source: &[u8]) -> Result<Vec<ElementItem>, ParseError>
     }
 }
 
-/// element_item = table_index:u32 + offset_expression + <function_index>
+/// element_item = flag:u32 + ...
+///
+/// - 0: offset_expression + <function_index>
+/// - 1: element_kind:byte + <function_index>
+/// - 2: table_index:u32 + offset_expression + element_kind:byte + <function_index>
+/// - 3: element_kind:byte + <function_index>
+/// - 4: offset_expression + <expression>
+/// - 5: ref_type:byte + <expression>
+/// - 6: table_index:u32 + offset_expression + ref_type:byte + <expression>
+/// - 7: ref_type:byte + <expression>
+///
 /// offset_expression = byte{*} + 0x0B  // 表达式（指令列表）以 0x0B 结尾
 fn continue_parse_element_item(source: &[u8]) -> Result<(ElementItem, &[u8]), ParseError> {
-    let (table_index, post_index) = read_u32(source)?;
-    let (offset_instruction_items, post_instruction_items) = continue_parse_expression(post_index)?;
-    let (function_indices, post_indices) = read_u32_vec(post_instruction_items)?;
-
-    let element_item = ElementItem {
-        table_index: table_index,
-        offset_instruction_items,
-        function_indices,
+    let (flag, post_flag) = read_u32(source)?;
+
+    if flag > types::ELEMENT_ITEM_FLAG_MAX {
+        return Err(ParseError::SyntaxError(
+            SyntaxError::InvalidElementItemFlag(flag),
+        ));
+    }
+
+    // 标记的低 2 位决定元素项的模式，第 3 位决定元素项的内容是函数索引还是表达式
+    let is_expressions = flag & types::ELEMENT_ITEM_FLAG_EXPRESSIONS != 0;
+
+    // 除了标记 0 和 4，其他标记的元素项在元素内容之前都有 element_kind 或者 ref_type
+    let (mode, has_kind, post_mode) = match flag & !types::ELEMENT_ITEM_FLAG_EXPRESSIONS {
+        types::ELEMENT_ITEM_FLAG_ACTIVE => {
+            let (offset_instruction_items, post_instruction_items) =
+                continue_parse_expression(post_flag)?;
+            let mode = ElementMode::Active {
+                table_index: 0,
+                offset_instruction_items,
+            };
+            (mode, false, post_instruction_items)
+        }
+        types::ELEMENT_ITEM_FLAG_PASSIVE => (ElementMode::Passive, true, post_flag),
+        types::ELEMENT_ITEM_FLAG_ACTIVE_WITH_TABLE_INDEX => {
+            let (table_index, post_index) = read_u32(post_flag)?;
+            let (offset_instruction_items, post_instruction_items) =
+                continue_parse_expression(post_index)?;
+            let mode = ElementMode::Active {
+                table_index,
+                offset_instruction_items,
+            };
+            (mode, true, post_instruction_items)
+        }
+        _ => (ElementMode::Declarative, true, post_flag),
+    };
+
+    let (items, post_items) = if is_expressions {
+        let (ref_type, post_ref_type) = if has_kind {
+            continue_parse_ref_type(post_mode)?
+        } else {
+            (ValueType::FuncRef, post_mode)
+        };
+
+        let (expression_count, post_count) = read_u32(post_ref_type)?;
+        let mut remains = post_count;
+        let mut expressions = Vec::<Vec<Instruction>>::with_capacity(expression_count as usize);
+        for _ in 0..expression_count {
+            let (instruction_items, post_instruction_items) = continue_parse_expression(remains)?;
+            expressions.push(instruction_items);
+            remains = post_instruction_items;
+        }
+
+        (ElementItems::Expressions(ref_type, expressions), remains)
+    } else {
+        let post_kind = if has_kind {
+            continue_parse_element_kind(post_mode)?
+        } else {
+            post_mode
+        };
+
+        let (function_indices, post_indices) = read_u32_vec(post_kind)?;
+        (ElementItems::FunctionIndices(function_indices), post_indices)
     };
 
-    Ok((element_item, post_indices))
+    let element_item = ElementItem { mode, items };
+
+    Ok((element_item, post_items))
+}
+
+/// element_kind 目前只能是 0x00（funcref）
+fn continue_parse_element_kind(source: &[u8]) -> Result<&[u8], ParseError> {
+    let (kind, post_kind) = read_byte(source)?;
+    if kind != types::ELEMENT_KIND_FUNC_REF {
+        Err(ParseError::SyntaxError(SyntaxError::InvalidElementKind(
+            kind,
+        )))
+    } else {
+        Ok(post_kind)
+    }
 }
 
 /// # 解析代码段
@@ -1557,8 +1693,7 @@ fn continue_parse_instruction_items(source: &[u8]) -> Result<Vec<Instruction>, P
 /// # 解析数据段
 ///
 /// data_section = 0x0b + content_length:u32 + <data_item>
-/// data_item = memory_block_index:u32 + offset_expression + data:byte{*}
-/// offset_expression = = byte{*} + 0x0B  // 表达式（指令列表）以 0x0B 结尾
+/// data_item = flag:u32 + ...
 fn parse_data_section(source: &[u8]) -> Result<Vec<DataItem>, ParseError> {
     let (item_count, post_item_count) = read_u32(source)?;
 
@@ -1578,19 +1713,48 @@ fn parse_data_section(source: &[u8]) -> Result<Vec<DataItem>, ParseError> {
     }
 }
 
-/// data_item = memory_block_index:u32 + offset_expression + data:byte{*}
+/// data_item = flag:u32 + ...
+///
+/// - 0: offset_expression + data:byte{*}
+/// - 1: data:byte{*}
+/// - 2: memory_block_index:u32 + offset_expression + data:byte{*}
+///
 /// offset_expression = = byte{*} + 0x0B  // 表达式（指令列表）以 0x0B 结尾
 fn continue_parse_data_item(source: &[u8]) -> Result<(DataItem, &[u8]), ParseError> {
-    let (memory_block_index, post_index) = read_u32(source)?;
-    let (offset_instruction_items, post_instruction_items) = continue_parse_expression(post_index)?;
-    let (data, post_data) = read_byte_vec(post_instruction_items)?;
+    let (flag, post_flag) = read_u32(source)?;
 
-    let data_item = DataItem {
-        memory_block_index,
-        offset_instruction_items,
-        data,
+    let (mode, post_mode) = match flag {
+        types::DATA_ITEM_FLAG_ACTIVE => {
+            let (offset_instruction_items, post_instruction_items) =
+                continue_parse_expression(post_flag)?;
+            let mode = DataMode::Active {
+                memory_block_index: 0,
+                offset_instruction_items,
+            };
+            (mode, post_instruction_items)
+        }
+        types::DATA_ITEM_FLAG_PASSIVE => (DataMode::Passive, post_flag),
+        types::DATA_ITEM_FLAG_ACTIVE_WITH_MEMORY_BLOCK_INDEX => {
+            let (memory_block_index, post_index) = read_u32(post_flag)?;
+            let (offset_instruction_items, post_instruction_items) =
+                continue_parse_expression(post_index)?;
+            let mode = DataMode::Active {
+                memory_block_index,
+                offset_instruction_items,
+            };
+            (mode, post_instruction_items)
+        }
+        _ => {
+            return Err(ParseError::SyntaxError(SyntaxError::InvalidDataItemFlag(
+                flag,
+            )));
+        }
     };
 
+    let (data, post_data) = read_byte_vec(post_mode)?;
+
+    let data_item = DataItem { mode, data };
+
     Ok((data_item, post_data))
 }
 
@@ -1726,10 +1890,11 @@ mod tests {
 
     use anvm_ast::{
         ast::{
-            CodeItem, CustomItem, DataItem, ElementItem, ExportDescriptor, ExportItem,
-            FunctionIndexAndBlockLabelsPair, FunctionIndexAndLocalVariableNamesPair, FunctionType,
-            GlobalItem, GlobalType, ImportDescriptor, ImportItem, IndexNamePair, Limit, LocalGroup,
-            MemoryType, Module, NameCollection, TableType, TypeItem,
+            CodeItem, CustomItem, DataItem, DataMode, ElementItem, ElementItems, ElementMode,
+            ExportDescriptor, ExportItem, FunctionIndexAndBlockLabelsPair,
+            FunctionIndexAndLocalVariableNamesPair, FunctionType, GlobalItem, GlobalType,
+            ImportDescriptor, ImportItem, IndexNamePair, Limit, LocalGroup, MemoryType, Module,
+            NameCollection, TableType, TypeItem,
         },
         instruction::{BlockType, Instruction, MemoryArgument},
         types::ValueType,
@@ -2034,6 +2199,7 @@ mod tests {
             ],
             internal_function_to_type_index_list: vec![1, 1],
             tables: vec![TableType {
+                ref_type: ValueType::FuncRef,
                 limit: Limit::Range(2, 4),
             }],
             memory_blocks: vec![MemoryType {
@@ -2053,14 +2219,18 @@ mod tests {
             start_function_index: Some(3),
             element_items: vec![
                 ElementItem {
-                    table_index: 0,
-                    offset_instruction_items: vec![Instruction::I32Const(1), Instruction::End],
-                    function_indices: vec![2],
+                    mode: ElementMode::Active {
+                        table_index: 0,
+                        offset_instruction_items: vec![Instruction::I32Const(1), Instruction::End],
+                    },
+                    items: ElementItems::FunctionIndices(vec![2]),
                 },
                 ElementItem {
-                    table_index: 0,
-                    offset_instruction_items: vec![Instruction::I32Const(3), Instruction::End],
-                    function_indices: vec![3],
+                    mode: ElementMode::Active {
+                        table_index: 0,
+                        offset_instruction_items: vec![Instruction::I32Const(3), Instruction::End],
+                    },
+                    items: ElementItems::FunctionIndices(vec![3]),
                 },
             ],
             code_items: vec![
@@ -2070,6 +2240,7 @@ mod tests {
                         Instruction::I32Load(MemoryArgument {
                             align: 2,
                             offset: 100,
+                            memory_block_index: 0,
                         }),
                         Instruction::Call(0),
                         Instruction::Call(3),
@@ -2082,10 +2253,12 @@ mod tests {
                         Instruction::I32Load(MemoryArgument {
                             align: 3,
                             offset: 200,
+                            memory_block_index: 0,
                         }),
                         Instruction::I64Load(MemoryArgument {
                             align: 3,
                             offset: 400,
+                            memory_block_index: 0,
                         }),
                         Instruction::End,
                     ],
@@ -2093,13 +2266,17 @@ mod tests {
             ],
             data_items: vec![
                 DataItem {
-                    memory_block_index: 0,
-                    offset_instruction_items: vec![Instruction::I32Const(100), Instruction::End],
+                    mode: DataMode::Active {
+                        memory_block_index: 0,
+                        offset_instruction_items: vec![Instruction::I32Const(100), Instruction::End],
+                    },
                     data: vec![104, 101, 108, 108, 111],
                 },
                 DataItem {
-                    memory_block_index: 0,
-                    offset_instruction_items: vec![Instruction::I32Const(200), Instruction::End],
+                    mode: DataMode::Active {
+                        memory_block_index: 0,
+                        offset_instruction_items: vec![Instruction::I32Const(200), Instruction::End],
+                    },
                     data: vec![80, 96, 112],
                 },
             ],
@@ -2251,6 +2428,7 @@ mod tests {
             }],
             internal_function_to_type_index_list: vec![2, 0],
             tables: vec![TableType {
+                ref_type: ValueType::FuncRef,
                 limit: Limit::Range(2, 4),
             }],
             memory_blocks: vec![MemoryType {
@@ -2261,14 +2439,18 @@ mod tests {
             start_function_index: None,
             element_items: vec![
                 ElementItem {
-                    table_index: 0,
-                    offset_instruction_items: vec![Instruction::I32Const(1), Instruction::End],
-                    function_indices: vec![1],
+                    mode: ElementMode::Active {
+                        table_index: 0,
+                        offset_instruction_items: vec![Instruction::I32Const(1), Instruction::End],
+                    },
+                    items: ElementItems::FunctionIndices(vec![1]),
                 },
                 ElementItem {
-                    table_index: 0,
-                    offset_instruction_items: vec![Instruction::I32Const(3), Instruction::End],
-                    function_indices: vec![2],
+                    mode: ElementMode::Active {
+                        table_index: 0,
+                        offset_instruction_items: vec![Instruction::I32Const(3), Instruction::End],
+                    },
+                    items: ElementItems::FunctionIndices(vec![2]),
                 },
             ],
             code_items: vec![
@@ -2322,13 +2504,17 @@ mod tests {
             ],
             data_items: vec![
                 DataItem {
-                    memory_block_index: 0,
-                    offset_instruction_items: vec![Instruction::I32Const(10), Instruction::End],
+                    mode: DataMode::Active {
+                        memory_block_index: 0,
+                        offset_instruction_items: vec![Instruction::I32Const(10), Instruction::End],
+                    },
                     data: vec![102, 111, 111],
                 },
                 DataItem {
-                    memory_block_index: 0,
-                    offset_instruction_items: vec![Instruction::I32Const(20), Instruction::End],
+                    mode: DataMode::Active {
+                        memory_block_index: 0,
+                        offset_instruction_items: vec![Instruction::I32Const(20), Instruction::End],
+                    },
                     data: vec![98, 97, 114],
                 },
             ],
@@ -2428,8 +2614,10 @@ mod tests {
         assert_eq!(
             module.data_items,
             vec![DataItem {
-                memory_block_index: 0,
-                offset_instruction_items: vec![Instruction::I32Const(100), Instruction::End],
+                mode: DataMode::Active {
+                    memory_block_index: 0,
+                    offset_instruction_items: vec![Instruction::I32Const(100), Instruction::End],
+                },
                 data: vec!['h' as u8, 'e' as u8, 'l' as u8, 'l' as u8, 'o' as u8]
             }]
         );
@@ -2443,11 +2631,13 @@ mod tests {
                     Instruction::I32Const(2),
                     Instruction::I32Load(MemoryArgument {
                         align: 2,
-                        offset: 100
+                        offset: 100,
+                        memory_block_index: 0
                     }),
                     Instruction::I32Store(MemoryArgument {
                         align: 2,
-                        offset: 100
+                        offset: 100,
+                        memory_block_index: 0
                     }),
                     Instruction::MemorySize(0),
                     Instruction::Drop,
@@ -2460,6 +2650,234 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_parse_instruction_bulk_memory() {
+        let binary = get_test_binary_resource("test-instruction-bulk-memory.wasm");
+        let module = parse(&binary).unwrap();
+
+        assert_eq!(
+            module.data_items,
+            vec![
+                DataItem {
+                    mode: DataMode::Passive,
+                    data: b"foo".to_vec()
+                },
+                DataItem {
+                    mode: DataMode::Active {
+                        memory_block_index: 0,
+                        offset_instruction_items: vec![Instruction::I32Const(100), Instruction::End],
+                    },
+                    data: b"bar".to_vec()
+                }
+            ]
+        );
+
+        assert_eq!(
+            module.element_items,
+            vec![
+                ElementItem {
+                    mode: ElementMode::Passive,
+                    items: ElementItems::FunctionIndices(vec![0, 0])
+                },
+                ElementItem {
+                    mode: ElementMode::Active {
+                        table_index: 0,
+                        offset_instruction_items: vec![Instruction::I32Const(1), Instruction::End],
+                    },
+                    items: ElementItems::FunctionIndices(vec![0])
+                },
+                ElementItem {
+                    mode: ElementMode::Declarative,
+                    items: ElementItems::FunctionIndices(vec![0])
+                }
+            ]
+        );
+
+        assert_eq!(
+            module.code_items[0],
+            CodeItem {
+                local_groups: vec![],
+                instruction_items: vec![
+                    Instruction::I32Const(1),
+                    Instruction::I32Const(2),
+                    Instruction::I32Const(3),
+                    Instruction::MemoryInit(0, 0),
+                    Instruction::DataDrop(0),
+                    Instruction::I32Const(4),
+                    Instruction::I32Const(5),
+                    Instruction::I32Const(6),
+                    Instruction::MemoryCopy(0, 0),
+                    Instruction::I32Const(7),
+                    Instruction::I32Const(8),
+                    Instruction::I32Const(9),
+                    Instruction::MemoryFill(0),
+                    Instruction::I32Const(1),
+                    Instruction::I32Const(2),
+                    Instruction::I32Const(3),
+                    Instruction::TableInit(0, 0),
+                    Instruction::ElementDrop(0),
+                    Instruction::I32Const(4),
+                    Instruction::I32Const(5),
+                    Instruction::I32Const(6),
+                    Instruction::TableCopy(0, 0),
+                    Instruction::End
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_instruction_reference_types() {
+        let binary = get_test_binary_resource("test-instruction-reference-types.wasm");
+        let module = parse(&binary).unwrap();
+
+        assert_eq!(
+            module.tables,
+            vec![TableType {
+                ref_type: ValueType::ExternRef,
+                limit: Limit::AtLeast(2),
+            }]
+        );
+
+        assert_eq!(
+            module.element_items,
+            vec![
+                ElementItem {
+                    mode: ElementMode::Active {
+                        table_index: 0,
+                        offset_instruction_items: vec![Instruction::I32Const(0), Instruction::End],
+                    },
+                    items: ElementItems::Expressions(
+                        ValueType::FuncRef,
+                        vec![
+                            vec![Instruction::RefFunc(0), Instruction::End],
+                            vec![Instruction::RefNull(ValueType::FuncRef), Instruction::End]
+                        ]
+                    )
+                },
+                ElementItem {
+                    mode: ElementMode::Passive,
+                    items: ElementItems::Expressions(
+                        ValueType::ExternRef,
+                        vec![vec![Instruction::RefNull(ValueType::ExternRef), Instruction::End]]
+                    )
+                },
+                ElementItem {
+                    mode: ElementMode::Declarative,
+                    items: ElementItems::Expressions(
+                        ValueType::FuncRef,
+                        vec![vec![Instruction::RefFunc(0), Instruction::End]]
+                    )
+                }
+            ]
+        );
+
+        assert_eq!(
+            module.code_items[0],
+            CodeItem {
+                local_groups: vec![],
+                instruction_items: vec![
+                    Instruction::RefNull(ValueType::ExternRef),
+                    Instruction::Drop,
+                    Instruction::RefFunc(0),
+                    Instruction::Drop,
+                    Instruction::LocalGet(0),
+                    Instruction::RefNull(ValueType::ExternRef),
+                    Instruction::I32Const(1),
+                    Instruction::SelectTyped(vec![ValueType::ExternRef]),
+                    Instruction::RefIsNull,
+                    Instruction::Drop,
+                    Instruction::I32Const(1),
+                    Instruction::TableGet(0),
+                    Instruction::Drop,
+                    Instruction::I32Const(2),
+                    Instruction::LocalGet(0),
+                    Instruction::TableSet(0),
+                    Instruction::RefNull(ValueType::ExternRef),
+                    Instruction::I32Const(3),
+                    Instruction::TableGrow(0),
+                    Instruction::Drop,
+                    Instruction::I32Const(4),
+                    Instruction::LocalGet(0),
+                    Instruction::I32Const(5),
+                    Instruction::TableFill(0),
+                    Instruction::TableSize(0),
+                    Instruction::End
+                ]
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_multiple_memories_and_tables() {
+        let binary = get_test_binary_resource("test-multiple-memories-tables.wasm");
+        let module = parse(&binary).unwrap();
+
+        assert_eq!(
+            module.memory_blocks,
+            vec![
+                MemoryType {
+                    limit: Limit::AtLeast(1)
+                },
+                MemoryType {
+                    limit: Limit::AtLeast(2)
+                }
+            ]
+        );
+
+        assert_eq!(
+            module.tables,
+            vec![
+                TableType {
+                    ref_type: ValueType::FuncRef,
+                    limit: Limit::AtLeast(1)
+                },
+                TableType {
+                    ref_type: ValueType::ExternRef,
+                    limit: Limit::AtLeast(2)
+                }
+            ]
+        );
+
+        assert_eq!(
+            module.data_items,
+            vec![DataItem {
+                mode: DataMode::Active {
+                    memory_block_index: 1,
+                    offset_instruction_items: vec![Instruction::I32Const(0), Instruction::End],
+                },
+                data: b"foo".to_vec()
+            }]
+        );
+
+        assert_eq!(
+            module.code_items[0],
+            CodeItem {
+                local_groups: vec![],
+                instruction_items: vec![
+                    Instruction::I32Const(1),
+                    Instruction::I32Const(2),
+                    Instruction::I32Store8(MemoryArgument {
+                        align: 0,
+                        offset: 4,
+                        memory_block_index: 1
+                    }),
+                    Instruction::I32Const(3),
+                    Instruction::I32Const(4),
+                    Instruction::I32Const(5),
+                    Instruction::MemoryCopy(1, 0),
+                    Instruction::I32Const(6),
+                    Instruction::I32Const(7),
+                    Instruction::I32Const(8),
+                    Instruction::TableCopy(0, 0),
+                    Instruction::I32Const(9),
+                    Instruction::CallIndirect(0, 0),
+                    Instruction::End
+                ]
+            }
+        );
+    }
+
     #[test]
     fn test_parse_instruction_flow_control() {
         let s0 = get_test_binary_resource("test-instruction-flow-control.wasm");
@@ -2506,6 +2924,7 @@ mod tests {
         assert_eq!(
             m0.tables,
             vec![TableType {
+                ref_type: ValueType::FuncRef,
                 limit: Limit::Range(3, 3)
             }]
         );
@@ -2513,9 +2932,11 @@ mod tests {
         assert_eq!(
             m0.element_items,
             vec![ElementItem {
-                table_index: 0,
-                offset_instruction_items: vec![Instruction::I32Const(0), Instruction::End],
-                function_indices: vec![1, 1, 1]
+                mode: ElementMode::Active {
+                    table_index: 0,
+                    offset_instruction_items: vec![Instruction::I32Const(0), Instruction::End],
+                },
+                items: ElementItems::FunctionIndices(vec![1, 1, 1])
             }]
         );
 
@@ -35,6 +35,8 @@ pub const VALUE_TYPE_TAG_I32: u8 = 0x7f; // i32
 pub const VALUE_TYPE_TAG_I64: u8 = 0x7E; // i64
 pub const VALUE_TYPE_TAG_F32: u8 = 0x7D; // f32
 pub const VALUE_TYPE_TAG_F64: u8 = 0x7C; // f64
+pub const VALUE_TYPE_TAG_FUNC_REF: u8 = 0x70; // funcref
+pub const VALUE_TYPE_TAG_EXTERN_REF: u8 = 0x6F; // externref
 
 /// 导入项描述 tag
 pub const IMPORT_TAG_FUNCTION: u8 = 0;
@@ -42,8 +44,30 @@ pub const IMPORT_TAG_TABLE: u8 = 1;
 pub const IMPORT_TAG_MEMORY: u8 = 2;
 pub const IMPORT_TAG_GLOBAL: u8 = 3;
 
-/// 表项的 tag，目前只支持 func_ref
+/// 表项的 tag
 pub const TABLE_TYPE_TAG_FUNC_REF: u8 = 0x70;
+pub const TABLE_TYPE_TAG_EXTERN_REF: u8 = 0x6F;
+
+/// 元素项的元素种类（element kind），目前只有 func_ref
+pub const ELEMENT_KIND_FUNC_REF: u8 = 0x00;
+
+/// 元素项的标记
+pub const ELEMENT_ITEM_FLAG_ACTIVE: u32 = 0;
+pub const ELEMENT_ITEM_FLAG_PASSIVE: u32 = 1;
+pub const ELEMENT_ITEM_FLAG_ACTIVE_WITH_TABLE_INDEX: u32 = 2;
+pub const ELEMENT_ITEM_FLAG_DECLARATIVE: u32 = 3;
+/// 元素项的内容为表达式（而非函数索引）时，标记会加上这个值
+pub const ELEMENT_ITEM_FLAG_EXPRESSIONS: u32 = 4;
+pub const ELEMENT_ITEM_FLAG_MAX: u32 = 7;
+
+/// 数据项的标记
+pub const DATA_ITEM_FLAG_ACTIVE: u32 = 0;
+pub const DATA_ITEM_FLAG_PASSIVE: u32 = 1;
+pub const DATA_ITEM_FLAG_ACTIVE_WITH_MEMORY_BLOCK_INDEX: u32 = 2;
+
+/// 内存类指令参数的 align 字段的标记位，
+/// 为 1 时表示 align 之后跟随着内存块的索引（multi-memory）
+pub const MEMORY_ARGUMENT_FLAG_MEMORY_BLOCK_INDEX: u32 = 0x40;
 
 /// 全局变量的可变性 tag，0 == 常量
 pub const GLOBAL_VARIABLE_TAG_IMMUTABLE: u8 = 0;
@@ -58,6 +82,8 @@ pub const BLOCK_TYPE_I32: i32 = -1; // 返回 i32
 pub const BLOCK_TYPE_I64: i32 = -2; // 返回 i64
 pub const BLOCK_TYPE_F32: i32 = -3; // 返回 f32
 pub const BLOCK_TYPE_F64: i32 = -4; // 返回 f64
+pub const BLOCK_TYPE_FUNC_REF: i32 = -16; // 返回 funcref
+pub const BLOCK_TYPE_EXTERN_REF: i32 = -17; // 返回 externref
 pub const BLOCK_TYPE_EMPTY: i32 = -64; // 无返回
 
 pub const EXPORT_TAG_FUNCTION: u8 = 0;
@@ -6,11 +6,12 @@
 
 use anvm_ast::{
     ast::{
-        CodeItem, DataItem, ElementItem, ExportItem, GlobalItem, ImportDescriptor, ImportItem,
-        Limit, MemoryType, Module, TableType, TypeItem,
+        CodeItem, DataItem, DataMode, ElementItem, ElementItems, ElementMode, ExportItem,
+        GlobalItem, ImportDescriptor, ImportItem, Limit, MemoryType, Module, TableType, TypeItem,
     },
     instruction::{BlockType, Instruction, MemoryArgument},
     name_package::NamePackage,
+    types::ValueType,
 };
 use std::fmt::Write;
 
@@ -118,10 +119,10 @@ impl TextFormat for TableType {
 
         match self.limit {
             Limit::AtLeast(min) => {
-                text_fragments.push(format!("{} funcref)", min));
+                text_fragments.push(format!("{} {})", min, self.ref_type));
             }
             Limit::Range(min, max) => {
-                text_fragments.push(format!("{} {} funcref)", min, max));
+                text_fragments.push(format!("{} {} {})", min, max, self.ref_type));
             }
         }
 
@@ -357,6 +358,7 @@ impl TextFormat for BlockType {
         // block (result i64)
         // block (result f32)
         // block (result f64)
+        // block (result funcref)
         //       ^----------^
         // block (type $ft0)
         //       ^---------^
@@ -369,6 +371,8 @@ impl TextFormat for BlockType {
             BlockType::ResultI64 => write!(f, "(result i64)"),
             BlockType::ResultF32 => write!(f, "(result f32)"),
             BlockType::ResultF64 => write!(f, "(result f64)"),
+            BlockType::ResultFuncRef => write!(f, "(result funcref)"),
+            BlockType::ResultExternRef => write!(f, "(result externref)"),
             BlockType::ResultEmpty => write!(f, ""),
 
             // 来自类型表的类型
@@ -386,14 +390,28 @@ impl TextFormat for BlockType {
 impl TextFormat for MemoryArgument {
     fn text(
         &self,
-        _name_package: &NamePackage,
+        name_package: &NamePackage,
         _option_item_index: Option<u32>,
         f: &mut String,
     ) -> std::fmt::Result {
         // 示例
         // i32.load offset=100 align=4
         //          ^----------------^
-        write!(f, "offset={} align={}", self.offset, 2i32.pow(self.align))
+        // i32.load 1 offset=100 align=4
+        //          ^------------------^
+        let memory_block_operand =
+            format_memory_block_operand(name_package, &self.memory_block_index);
+        if memory_block_operand.is_empty() {
+            write!(f, "offset={} align={}", self.offset, 2i32.pow(self.align))
+        } else {
+            write!(
+                f,
+                "{} offset={} align={}",
+                memory_block_operand.trim_start(),
+                self.offset,
+                2i32.pow(self.align)
+            )
+        }
     }
 }
 
@@ -494,17 +512,33 @@ impl TextFormat for Instruction {
                     write!(f, "call {}", function_index)
                 }
             }
-            Instruction::CallIndirect(type_index, _table_index) => {
-                // table_index 暂时用不上
+            Instruction::CallIndirect(type_index, table_index) => {
+                // 表索引为 0 时省略
+                let table_operand = if *table_index == 0 {
+                    "".to_string()
+                } else if let Some(table_name) = name_package.get_table_name(table_index) {
+                    format!(" ${}", table_name)
+                } else {
+                    format!(" {}", table_index)
+                };
+
                 if let Some(type_name) = name_package.get_type_name(type_index) {
-                    write!(f, "call_indirect (type ${})", type_name)
+                    write!(f, "call_indirect{} (type ${})", table_operand, type_name)
                 } else {
-                    write!(f, "call_indirect (type {})", type_index)
+                    write!(f, "call_indirect{} (type {})", table_operand, type_index)
                 }
             }
 
             Instruction::Drop => write!(f, "drop"),
             Instruction::Select => write!(f, "select"),
+            Instruction::SelectTyped(value_types) => {
+                let value_types_text = value_types
+                    .iter()
+                    .map(|value_type| value_type.to_string())
+                    .collect::<Vec<String>>()
+                    .join(" ");
+                write!(f, "select (result {})", value_types_text)
+            }
 
             Instruction::LocalGet(local_variable_index) => {
                 let option_variable_name = {
@@ -685,17 +719,40 @@ impl TextFormat for Instruction {
                 "i64.store32 {}",
                 memory_argument.to_text(name_package, option_item_index)
             ),
-            Instruction::MemorySize(_memory_block_index) => write!(f, "memory.size"), // memory_block_index 暂时用不上
-            Instruction::MemoryGrow(_memory_block_index) => write!(f, "memory.grow"), // memory_block_index 暂时用不上
-            Instruction::MemoryInit(data_index, _memory_block_index) => {
-                write!(f, "memory.init {}", data_index)
-            } // memory_block_index 暂时用不上
+            Instruction::MemorySize(memory_block_index) => write!(
+                f,
+                "memory.size{}",
+                format_memory_block_operand(name_package, memory_block_index)
+            ),
+            Instruction::MemoryGrow(memory_block_index) => write!(
+                f,
+                "memory.grow{}",
+                format_memory_block_operand(name_package, memory_block_index)
+            ),
+            Instruction::MemoryInit(data_index, memory_block_index) => write!(
+                f,
+                "memory.init{} {}",
+                format_memory_block_operand(name_package, memory_block_index),
+                data_index
+            ),
             Instruction::DataDrop(data_index) => write!(f, "data.drop {}", data_index),
-            Instruction::MemoryCopy(_source_memory_block_index, _dest_memory_block_index) => {
-                // memory_block_index 暂时用不上
-                write!(f, "memory.copy")
+            Instruction::MemoryCopy(source_memory_block_index, dest_memory_block_index) => {
+                // 文本格式里目标内存块索引在前，当两个索引均为 0 时省略
+                if *source_memory_block_index == 0 && *dest_memory_block_index == 0 {
+                    write!(f, "memory.copy")
+                } else {
+                    write!(
+                        f,
+                        "memory.copy {} {}",
+                        dest_memory_block_index, source_memory_block_index
+                    )
+                }
             }
-            Instruction::MemoryFill(_memory_block_index) => write!(f, "memory.fill"), // memory_block_index 暂时用不上
+            Instruction::MemoryFill(memory_block_index) => write!(
+                f,
+                "memory.fill{}",
+                format_memory_block_operand(name_package, memory_block_index)
+            ),
 
             Instruction::TableGet(table_index) => write!(f, "table.get {}", table_index),
             Instruction::TableSet(table_index) => write!(f, "table.set {}", table_index),
@@ -704,7 +761,8 @@ impl TextFormat for Instruction {
             }
             Instruction::ElementDrop(element_index) => write!(f, "elem.drop {}", element_index),
             Instruction::TableCopy(source_table_index, dest_table_index) => {
-                write!(f, "table.copy {} {}", source_table_index, dest_table_index)
+                // 文本格式里目标表索引在前
+                write!(f, "table.copy {} {}", dest_table_index, source_table_index)
             }
             Instruction::TableGrow(table_index) => write!(f, "table.grow {}", table_index),
             Instruction::TableSize(table_index) => write!(f, "table.size {}", table_index),
@@ -863,6 +921,16 @@ impl TextFormat for Instruction {
             Instruction::I64TruncSatF32U => write!(f, "i64.trunc_sat_f32_u"),
             Instruction::I64TruncSatF64S => write!(f, "i64.trunc_sat_64_s"),
             Instruction::I64TruncSatF64U => write!(f, "i64.trunc_sat_64_u"),
+
+            Instruction::RefNull(ref_type) => write!(f, "{}", format_ref_null(ref_type)),
+            Instruction::RefIsNull => write!(f, "ref.is_null"),
+            Instruction::RefFunc(function_index) => {
+                if let Some(function_name) = name_package.get_function_name(function_index) {
+                    write!(f, "ref.func ${}", function_name)
+                } else {
+                    write!(f, "ref.func {}", function_index)
+                }
+            }
         }
     }
 }
@@ -1112,6 +1180,10 @@ impl TextFormat for ElementItem {
         // 示例
         // (elem $elem_one (offset (i32.const 1)) $func0 $func1)
         // (elem (;0;) (offset (i32.const 3)) 2 3 4)
+        // (elem (;1;) (table 1) (offset (i32.const 3)) func 2 3 4)
+        // (elem (;2;) func $func0 $func1)
+        // (elem (;3;) declare func $func0 $func1)
+        // (elem (;4;) funcref (ref.func $func0) (ref.null func))
 
         let mut text_fragments: Vec<String> = vec![];
 
@@ -1125,24 +1197,62 @@ impl TextFormat for ElementItem {
             text_fragments.push(format!("(;{};)", element_index));
         }
 
-        let offset_text = format!(
-            "(offset ({}))",
-            format_constant_expression(&self.offset_instruction_items)
-        );
-        text_fragments.push(offset_text);
+        // 元素列表之前的关键字，函数索引列表以 `func` 开始，表达式列表以引用类型开始
+        let items_keyword = match &self.items {
+            ElementItems::FunctionIndices(_) => "func".to_string(),
+            ElementItems::Expressions(ref_type, _) => ref_type.to_string(),
+        };
 
-        let function_indices_text = self
-            .function_indices
-            .iter()
-            .map(
-                |function_index| match name_package.get_function_name(function_index) {
-                    Some(function_name) => format!("${}", function_name),
-                    None => function_index.to_string(),
-                },
-            )
-            .collect::<Vec<String>>()
-            .join(" ");
-        text_fragments.push(format!("{})", function_indices_text));
+        match &self.mode {
+            ElementMode::Active {
+                table_index,
+                offset_instruction_items,
+            } => {
+                // 非 0 的表索引需要明确写出，且函数索引列表需要以 `func` 关键字开始
+                if *table_index != 0 {
+                    text_fragments.push(format!("(table {})", table_index));
+                }
+
+                let offset_text = format_offset_expression(offset_instruction_items);
+                text_fragments.push(offset_text);
+
+                if *table_index != 0 || matches!(self.items, ElementItems::Expressions(..)) {
+                    text_fragments.push(items_keyword);
+                }
+            }
+            ElementMode::Passive => {
+                text_fragments.push(items_keyword);
+            }
+            ElementMode::Declarative => {
+                text_fragments.push("declare".to_string());
+                text_fragments.push(items_keyword);
+            }
+        }
+
+        let items_text = match &self.items {
+            ElementItems::FunctionIndices(function_indices) => function_indices
+                .iter()
+                .map(
+                    |function_index| match name_package.get_function_name(function_index) {
+                        Some(function_name) => format!("${}", function_name),
+                        None => function_index.to_string(),
+                    },
+                )
+                .collect::<Vec<String>>()
+                .join(" "),
+            ElementItems::Expressions(_, expressions) => expressions
+                .iter()
+                .map(|instructions| {
+                    if is_single_instruction_expression(instructions) {
+                        format!("({})", format_constant_expression(instructions))
+                    } else {
+                        format!("(item {})", format_constant_expression(instructions))
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join(" "),
+        };
+        text_fragments.push(format!("{})", items_text));
 
         write!(f, "{}", text_fragments.join(" "))
     }
@@ -1158,6 +1268,7 @@ impl TextFormat for DataItem {
         // 示例
         // (data $name (offset (i32.const 10)) "\11\22\33")
         // (data (;1;) (offset (i32.const 20)) "\aa\bb\cc")
+        // (data (;2;) "\dd\ee")
 
         let mut text_fragments: Vec<String> = vec![];
 
@@ -1171,13 +1282,19 @@ impl TextFormat for DataItem {
             text_fragments.push(format!("(;{};)", data_index));
         }
 
-        // text_fragments.push(self.memory_block_index.to_string());
+        // 被动模式的数据项没有内存块索引以及偏移值
+        if let DataMode::Active {
+            memory_block_index,
+            offset_instruction_items,
+        } = &self.mode
+        {
+            if *memory_block_index != 0 {
+                text_fragments.push(format!("(memory {})", memory_block_index));
+            }
 
-        let offset_text = format!(
-            "(offset ({}))",
-            format_constant_expression(&self.offset_instruction_items)
-        );
-        text_fragments.push(offset_text);
+            let offset_text = format_offset_expression(offset_instruction_items);
+            text_fragments.push(offset_text);
+        }
 
         let bytes_text = self
             .data
@@ -1191,14 +1308,86 @@ impl TextFormat for DataItem {
     }
 }
 
+/// 格式化常量表达式
+///
+/// 常量表达式的指令（不包括末尾的 `end 指令`）以平铺的形式列出
+///
+/// 示例
+/// i32.const 10
+/// global.get 0 i32.const 16 i32.add
 pub fn format_constant_expression(instructions: &[Instruction]) -> String {
-    let first_instruction = instructions.first().unwrap();
-    match first_instruction {
-        Instruction::I32Const(value) => format!("i32.const {}", value),
-        Instruction::I64Const(value) => format!("i64.const {}", value),
-        Instruction::F32Const(value) => format!("f32.const {}", value),
-        Instruction::F64Const(value) => format!("f64.const {}", value),
-        _ => panic!("unsupported constant expression instruction"),
+    instructions
+        .iter()
+        .filter(|instruction| !matches!(instruction, Instruction::End))
+        .map(|instruction| match instruction {
+            Instruction::I32Const(value) => format!("i32.const {}", value),
+            Instruction::I64Const(value) => format!("i64.const {}", value),
+            Instruction::F32Const(value) => format!("f32.const {}", value),
+            Instruction::F64Const(value) => format!("f64.const {}", value),
+            Instruction::RefNull(ref_type) => format_ref_null(ref_type),
+            Instruction::RefFunc(function_index) => format!("ref.func {}", function_index),
+            Instruction::GlobalGet(global_variable_index) => {
+                format!("global.get {}", global_variable_index)
+            }
+            Instruction::I32Add => "i32.add".to_string(),
+            Instruction::I32Sub => "i32.sub".to_string(),
+            Instruction::I32Mul => "i32.mul".to_string(),
+            Instruction::I64Add => "i64.add".to_string(),
+            Instruction::I64Sub => "i64.sub".to_string(),
+            Instruction::I64Mul => "i64.mul".to_string(),
+            _ => panic!("unsupported constant expression instruction"),
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// 格式化数据段和元素段的偏移值常量表达式
+///
+/// 示例
+/// (offset (i32.const 10))
+/// (offset global.get 0 i32.const 16 i32.add)
+fn format_offset_expression(instructions: &[Instruction]) -> String {
+    if is_single_instruction_expression(instructions) {
+        format!("(offset ({}))", format_constant_expression(instructions))
+    } else {
+        format!("(offset {})", format_constant_expression(instructions))
+    }
+}
+
+/// 常量表达式是否只有一个指令（不包括末尾的 `end 指令`）
+fn is_single_instruction_expression(instructions: &[Instruction]) -> bool {
+    instructions
+        .iter()
+        .filter(|instruction| !matches!(instruction, Instruction::End))
+        .count()
+        == 1
+}
+
+/// 格式化内存类指令的内存块索引
+///
+/// 索引为 0 时返回空字符串，否则返回前置一个空格的名称或者索引
+///
+/// 示例
+/// memory.size $mem1
+/// memory.size 1
+fn format_memory_block_operand(name_package: &NamePackage, memory_block_index: &u32) -> String {
+    if *memory_block_index == 0 {
+        "".to_string()
+    } else if let Some(name) = name_package.get_memory_block_name(memory_block_index) {
+        format!(" ${}", name)
+    } else {
+        format!(" {}", memory_block_index)
+    }
+}
+
+/// 示例
+/// ref.null func
+/// ref.null extern
+fn format_ref_null(ref_type: &ValueType) -> String {
+    match ref_type {
+        ValueType::FuncRef => "ref.null func".to_string(),
+        ValueType::ExternRef => "ref.null extern".to_string(),
+        _ => panic!("invalid reference type"),
     }
 }
 
@@ -1206,10 +1395,11 @@ pub fn format_constant_expression(instructions: &[Instruction]) -> String {
 mod tests {
     use anvm_ast::{
         ast::{
-            CodeItem, CustomItem, DataItem, ElementItem, ExportDescriptor, ExportItem,
-            FunctionIndexAndBlockLabelsPair, FunctionIndexAndLocalVariableNamesPair, FunctionType,
-            GlobalItem, GlobalType, ImportDescriptor, ImportItem, IndexNamePair, Limit, LocalGroup,
-            MemoryType, Module, NameCollection, TableType, TypeItem,
+            CodeItem, CustomItem, DataItem, DataMode, ElementItem, ElementItems, ElementMode,
+            ExportDescriptor, ExportItem, FunctionIndexAndBlockLabelsPair,
+            FunctionIndexAndLocalVariableNamesPair, FunctionType, GlobalItem, GlobalType,
+            ImportDescriptor, ImportItem, IndexNamePair, Limit, LocalGroup, MemoryType, Module,
+            NameCollection, TableType, TypeItem,
         },
         instruction::{BlockType, Instruction, MemoryArgument},
         name_package::NamePackage,
@@ -1297,9 +1487,11 @@ mod tests {
             internal_function_to_type_index_list: vec![],
             tables: vec![
                 TableType {
+                    ref_type: ValueType::FuncRef,
                     limit: Limit::Range(1, 8),
                 },
                 TableType {
+                    ref_type: ValueType::FuncRef,
                     limit: Limit::AtLeast(4),
                 },
             ],
@@ -1402,6 +1594,18 @@ mod tests {
                         Instruction::End,
                     ],
                 },
+                GlobalItem {
+                    global_type: GlobalType {
+                        value_type: ValueType::I32,
+                        mutable: false,
+                    },
+                    initialize_instruction_items: vec![
+                        Instruction::GlobalGet(0),
+                        Instruction::I32Const(16),
+                        Instruction::I32Add,
+                        Instruction::End,
+                    ],
+                },
             ],
             export_items: vec![],
             start_function_index: None,
@@ -1421,6 +1625,11 @@ mod tests {
             module.global_items[1].to_text(&name_package, Some(1)),
             "(global (;1;) i64 i64.const 2000)"
         );
+
+        assert_eq!(
+            module.global_items[2].to_text(&name_package, Some(2)),
+            "(global (;2;) i32 global.get 0 i32.const 16 i32.add)"
+        );
     }
 
     #[test]
@@ -1484,6 +1693,7 @@ mod tests {
                     module_name: "share".to_string(),
                     item_name: "main_table".to_string(),
                     import_descriptor: ImportDescriptor::TableType(TableType {
+                        ref_type: ValueType::FuncRef,
                         limit: Limit::Range(1, 4),
                     }),
                 },
@@ -1492,6 +1702,7 @@ mod tests {
                     module_name: "share".to_string(),
                     item_name: "minor_table".to_string(),
                     import_descriptor: ImportDescriptor::TableType(TableType {
+                        ref_type: ValueType::FuncRef,
                         limit: Limit::AtLeast(8),
                     }),
                 },
@@ -1641,6 +1852,7 @@ mod tests {
             Instruction::Call(1), // function 1 有名称 $f1
             Instruction::CallIndirect(0, 0),
             Instruction::CallIndirect(1, 0), // type 1 有名称 $t1
+            Instruction::CallIndirect(0, 1),
             Instruction::LocalGet(0),
             Instruction::LocalSet(1), // function 2 的 local 1 有名称 $l1
             Instruction::LocalTee(2), // function 2 的 local 2 有名称 $l2
@@ -1649,13 +1861,22 @@ mod tests {
             Instruction::I32Load(MemoryArgument {
                 offset: 100,
                 align: 2,
+                memory_block_index: 0,
             }),
             Instruction::I64Load(MemoryArgument {
                 offset: 200,
                 align: 3,
+                memory_block_index: 0,
+            }),
+            Instruction::I32Store(MemoryArgument {
+                offset: 300,
+                align: 2,
+                memory_block_index: 1,
             }),
             Instruction::MemorySize(0),
             Instruction::MemoryGrow(0),
+            Instruction::MemorySize(1),
+            Instruction::MemoryCopy(1, 0),
             Instruction::I32Const(100),
             Instruction::I64Const(200),
             Instruction::F32Const(2.414),
@@ -1683,6 +1904,7 @@ mod tests {
             "call $f1",
             "call_indirect (type 0)",
             "call_indirect (type $t1)",
+            "call_indirect 1 (type 0)",
             "local.get 0",
             "local.set $l1",
             "local.tee $l2",
@@ -1690,8 +1912,11 @@ mod tests {
             "global.set $g1",
             "i32.load offset=100 align=4",
             "i64.load offset=200 align=8",
+            "i32.store 1 offset=300 align=4",
             "memory.size",
             "memory.grow",
+            "memory.size 1",
+            "memory.copy 0 1",
             "i32.const 100",
             "i64.const 200",
             "f32.const 2.414",
@@ -2041,14 +2266,18 @@ mod tests {
             start_function_index: None,
             element_items: vec![
                 ElementItem {
-                    table_index: 0,
-                    offset_instruction_items: vec![Instruction::I32Const(10), Instruction::End],
-                    function_indices: vec![0, 1, 2, 3],
+                    mode: ElementMode::Active {
+                        table_index: 0,
+                        offset_instruction_items: vec![Instruction::I32Const(10), Instruction::End],
+                    },
+                    items: ElementItems::FunctionIndices(vec![0, 1, 2, 3]),
                 },
                 ElementItem {
-                    table_index: 0,
-                    offset_instruction_items: vec![Instruction::I32Const(20), Instruction::End],
-                    function_indices: vec![4],
+                    mode: ElementMode::Active {
+                        table_index: 0,
+                        offset_instruction_items: vec![Instruction::I32Const(20), Instruction::End],
+                    },
+                    items: ElementItems::FunctionIndices(vec![4]),
                 },
             ],
             code_items: vec![],
@@ -2089,15 +2318,31 @@ mod tests {
             code_items: vec![],
             data_items: vec![
                 DataItem {
-                    memory_block_index: 0,
-                    offset_instruction_items: vec![Instruction::I32Const(10), Instruction::End],
+                    mode: DataMode::Active {
+                        memory_block_index: 0,
+                        offset_instruction_items: vec![Instruction::I32Const(10), Instruction::End],
+                    },
                     data: vec![0x11, 0x22, 0x33],
                 },
                 DataItem {
-                    memory_block_index: 0,
-                    offset_instruction_items: vec![Instruction::I32Const(20), Instruction::End],
+                    mode: DataMode::Active {
+                        memory_block_index: 0,
+                        offset_instruction_items: vec![Instruction::I32Const(20), Instruction::End],
+                    },
                     data: vec![0xaa, 0x0b, 0x09],
                 },
+                DataItem {
+                    mode: DataMode::Active {
+                        memory_block_index: 0,
+                        offset_instruction_items: vec![
+                            Instruction::GlobalGet(0),
+                            Instruction::I32Const(8),
+                            Instruction::I32Add,
+                            Instruction::End,
+                        ],
+                    },
+                    data: vec![0x01],
+                },
             ],
         };
 
@@ -2112,5 +2357,10 @@ mod tests {
             module.data_items[1].to_text(&name_package, Some(1)),
             "(data (;1;) (offset (i32.const 20)) \"\\aa\\0b\\09\")"
         );
+
+        assert_eq!(
+            module.data_items[2].to_text(&name_package, Some(2)),
+            "(data (;2;) (offset global.get 0 i32.const 8 i32.add) \"\\01\")"
+        );
     }
 }
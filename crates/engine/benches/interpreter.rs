@@ -0,0 +1,127 @@
+// Copyright (c) 2022 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! # 解释器基准测试
+//!
+//! 使用 `$ cargo bench -p anvm-engine` 运行。
+//!
+//! 为了不引入额外的依赖，这里没有使用基准测试框架，而是简单地多次执行同一个函数，
+//! 然后输出每次执行的平均耗时以及最短耗时。
+
+use std::{
+    env, fs,
+    time::{Duration, Instant},
+};
+
+use anvm_ast::types::Value;
+use anvm_binary_parser::parser;
+use anvm_engine::{engine::Engine, instance::create_instance, object::NamedAstModule, vm::VM};
+
+/// 每个基准测试的执行次数
+const ITERATIONS: u32 = 10;
+
+/// 实例化基准测试的实例化次数
+const INSTANTIATION_COUNT: u32 = 1000;
+
+fn get_test_named_ast_module(filename: &str) -> NamedAstModule {
+    let path_buf = env::current_dir().unwrap().join("resources").join(filename);
+    let bytes = fs::read(&path_buf).unwrap();
+    let ast_module = parser::parse(&bytes).unwrap();
+    NamedAstModule::new("bench", ast_module)
+}
+
+fn create_test_vm(filename: &str) -> VM {
+    create_instance(vec![], &[get_test_named_ast_module(filename)]).unwrap()
+}
+
+fn bench(name: &str, filename: &str, function_index: usize, args: &[Value], expected: &[Value]) {
+    let mut vm = create_test_vm(filename);
+
+    // 预热
+    assert_eq!(
+        vm.eval_function_by_index(0, function_index, args).unwrap(),
+        expected
+    );
+
+    let mut total = Duration::ZERO;
+    let mut fastest = Duration::MAX;
+
+    for _ in 0..ITERATIONS {
+        let start = Instant::now();
+        let results = vm.eval_function_by_index(0, function_index, args).unwrap();
+        let elapsed = start.elapsed();
+
+        assert_eq!(results, expected);
+
+        total += elapsed;
+        fastest = fastest.min(elapsed);
+    }
+
+    println!(
+        "{:<24} average: {:>10.3} ms, fastest: {:>10.3} ms",
+        name,
+        total.as_secs_f64() * 1000.0 / ITERATIONS as f64,
+        fastest.as_secs_f64() * 1000.0
+    );
+}
+
+/// 比较每次都使用 `create_instance` 创建实例，以及编译一次之后使用 `Module::instantiate`
+/// 创建实例的耗时
+fn bench_instantiation(name: &str, filename: &str) {
+    let named_ast_modules = vec![get_test_named_ast_module(filename)];
+
+    let start = Instant::now();
+    for _ in 0..INSTANTIATION_COUNT {
+        create_instance(vec![], &named_ast_modules).unwrap();
+    }
+    let create_instance_elapsed = start.elapsed();
+
+    let start = Instant::now();
+    let module = Engine::new().compile(&[], named_ast_modules).unwrap();
+    let compile_elapsed = start.elapsed();
+
+    let start = Instant::now();
+    for _ in 0..INSTANTIATION_COUNT {
+        module.instantiate(vec![]).unwrap();
+    }
+    let instantiate_elapsed = start.elapsed();
+
+    println!(
+        "{:<24} create_instance: {:>10.3} us, compile: {:>10.3} us, instantiate: {:>10.3} us",
+        name,
+        create_instance_elapsed.as_secs_f64() * 1_000_000.0 / INSTANTIATION_COUNT as f64,
+        compile_elapsed.as_secs_f64() * 1_000_000.0,
+        instantiate_elapsed.as_secs_f64() * 1_000_000.0 / INSTANTIATION_COUNT as f64
+    );
+}
+
+fn main() {
+    bench(
+        "fib(25)",
+        "test-fib.wasm",
+        0,
+        &[Value::I32(25)],
+        &[Value::I32(75025)],
+    );
+
+    bench(
+        "loop sum(1000000)",
+        "test-loop.wasm",
+        0,
+        &[Value::I32(1_000_000)],
+        &[Value::I64(500_000_500_000)],
+    );
+
+    bench(
+        "loop nested(500, 500)",
+        "test-loop.wasm",
+        1,
+        &[Value::I32(500), Value::I32(500)],
+        &[Value::I32(125_000)],
+    );
+
+    bench_instantiation("instantiate lib-rust", "test-lib-rust.wasm");
+}
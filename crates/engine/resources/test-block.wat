@@ -9,26 +9,25 @@
     ;; 测试 return 指令
     (func $1 (result i32)
         (i32.const 1)
-        (block
+        (block (result i32)
             (i32.const 2) ;; got
             (return)
-            (i32.const 3)
         )
-        (i32.const 10)
+        (i32.add)
     )
 
     ;; 测试 return 指令
     (func $2 (result i32)
         (i32.const 1)
-        (block
+        (block (result i32)
             (i32.const 2)
-            (block
+            (block (result i32)
                 (i32.const 3) ;; got
                 (return)
-                (i32.const 4)
             )
+            (i32.add)
         )
-        (i32.const 10)
+        (i32.add)
     )
 
     ;; 测试 br 指令
@@ -37,9 +36,9 @@
         (block
             (i32.const 2)
             (br 0)
-            (i32.const 3)
         ) ;; <-- br to here
-        (i32.const 4) ;; got
+        (i32.const 3)
+        (i32.add) ;; 1 + 3, got
     )
 
     ;; 测试 br 指令
@@ -48,9 +47,9 @@
         (block
             (i32.const 2) ;; got
             (br 1)
-            (i32.const 3)
         )
         (i32.const 4)
+        (i32.add)
     ) ;; <-- br to here
 
     ;; 测试 br 指令
@@ -63,15 +62,17 @@
                 (block
                     (i32.const 4)
                     (br 0)
-                    (i32.const 5)
                 ) ;; <-- br to here
-                (i32.const 11) ;; got
+                (i32.const 8)
+                (i32.add)       ;; 3 + 8, got
                 (return)
             )
-            (i32.const 12)
+            (i32.const 10)
+            (i32.add)           ;; 2 + 10
             (return)
         )
-        (i32.const 13)
+        (i32.const 12)
+        (i32.add)               ;; 1 + 12
     )
 
     ;; 测试 br 指令
@@ -84,15 +85,17 @@
                 (block
                     (i32.const 4)
                     (br 1)
-                    (i32.const 5)
                 )
-                (i32.const 11)
+                (i32.const 8)
+                (i32.add)       ;; 3 + 8
                 (return)
             ) ;; <-- br to here
-            (i32.const 12)  ;; got
+            (i32.const 10)
+            (i32.add)           ;; 2 + 10, got
             (return)
         )
-        (i32.const 13)
+        (i32.const 12)
+        (i32.add)               ;; 1 + 12
     )
 
     ;; 测试 br 指令
@@ -105,15 +108,17 @@
                 (block
                     (i32.const 4)
                     (br 2)
-                    (i32.const 5)
                 )
-                (i32.const 11)
+                (i32.const 8)
+                (i32.add)       ;; 3 + 8
                 (return)
             )
-            (i32.const 12)
+            (i32.const 10)
+            (i32.add)           ;; 2 + 10
             (return)
         ) ;; <-- br to here
-        (i32.const 13) ;; got
+        (i32.const 12)
+        (i32.add)               ;; 1 + 12, got
     )
 
     ;; 测试 br_if
@@ -159,6 +164,7 @@
             (i32.wrap_i64)
             (br 1)
         )
-        (i32.const 99)
+        (i32.wrap_i64)
+        (i32.add)
     )
 )
@@ -0,0 +1,79 @@
+(module
+  (type $type_result_i32 (func (result i32)))
+  (memory 1)
+  (table 4 funcref)
+
+  ;; 数据段 #0，被动模式
+  (data $passive "hello")
+  ;; 数据段 #1，主动模式
+  (data (i32.const 0) "ABCDEFGH")
+
+  ;; 元素段 #0，被动模式
+  (elem $passive_elem func $ten $twenty $thirty)
+  ;; 元素段 #1，主动模式
+  (elem (i32.const 0) $ten)
+  ;; 元素段 #2，声明模式
+  (elem declare func $thirty)
+
+  ;; 函数 #0
+  (func $ten (result i32)
+    (i32.const 10)
+  )
+
+  ;; 函数 #1
+  (func $twenty (result i32)
+    (i32.const 20)
+  )
+
+  ;; 函数 #2
+  (func $thirty (result i32)
+    (i32.const 30)
+  )
+
+  ;; 函数 #3
+  ;; 返回从指定地址开始的 8 个字节
+  (func $load (export "load") (param $address i32) (result i64)
+    (i64.load (local.get $address))
+  )
+
+  ;; 函数 #4
+  (func $memory_init (export "memory_init") (param $dest i32) (param $source i32) (param $count i32)
+    (memory.init $passive (local.get $dest) (local.get $source) (local.get $count))
+  )
+
+  ;; 函数 #5
+  (func $data_drop (export "data_drop")
+    (data.drop $passive)
+  )
+
+  ;; 函数 #6
+  (func $memory_copy (export "memory_copy") (param $dest i32) (param $source i32) (param $count i32)
+    (memory.copy (local.get $dest) (local.get $source) (local.get $count))
+  )
+
+  ;; 函数 #7
+  (func $memory_fill (export "memory_fill") (param $dest i32) (param $value i32) (param $count i32)
+    (memory.fill (local.get $dest) (local.get $value) (local.get $count))
+  )
+
+  ;; 函数 #8
+  (func $table_init (export "table_init") (param $dest i32) (param $source i32) (param $count i32)
+    (table.init $passive_elem (local.get $dest) (local.get $source) (local.get $count))
+  )
+
+  ;; 函数 #9
+  (func $elem_drop (export "elem_drop")
+    (elem.drop $passive_elem)
+  )
+
+  ;; 函数 #10
+  (func $table_copy (export "table_copy") (param $dest i32) (param $source i32) (param $count i32)
+    (table.copy (local.get $dest) (local.get $source) (local.get $count))
+  )
+
+  ;; 函数 #11
+  ;; 调用表里指定位置的函数
+  (func $call_at (export "call_at") (param $index i32) (result i32)
+    (call_indirect (type $type_result_i32) (local.get $index))
+  )
+)
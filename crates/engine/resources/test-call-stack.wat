@@ -0,0 +1,25 @@
+(module
+    ;; 用于测试调用栈的上限
+
+    ;; 无限递归
+    (func $recur (export "recur") (param $n i32) (result i32)
+        (local i64 i64)
+        (call $recur (i32.add (local.get $n) (i32.const 1)))
+    )
+
+    ;; 递归 n 层之后返回 n
+    (func $count (export "count") (param $n i32) (result i32)
+        (if (result i32) (local.get $n)
+            (then
+                (block (result i32)
+                    (call $count (i32.sub (local.get $n) (i32.const 1)))
+                    (i32.const 1)
+                    (i32.add)
+                )
+            )
+            (else
+                (i32.const 0)
+            )
+        )
+    )
+)
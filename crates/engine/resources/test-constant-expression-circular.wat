@@ -0,0 +1,7 @@
+(module
+    ;; 当前模块的名称为 "circular"，导入自身导出的全局变量，
+    ;; 使得全局变量的初始化常量表达式存在循环依赖
+    (import "circular" "g1" (global $imported_g1 i32))
+    (global $g0 (export "g0") i32 (global.get $imported_g1))
+    (global $g1 (export "g1") i32 (i32.add (global.get $imported_g1) (i32.const 1)))
+)
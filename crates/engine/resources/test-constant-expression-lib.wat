@@ -0,0 +1,6 @@
+(module
+    ;; 供其他模块导入的全局变量
+    (global $memory_base (export "memory_base") i32 (i32.const 1024))
+    (global $table_base (export "table_base") i32 (i32.const 2))
+    (global $offset (export "offset") i64 (i64.const 100))
+)
@@ -0,0 +1,52 @@
+(module
+    (import "lib" "memory_base" (global $memory_base i32))
+    (import "lib" "table_base" (global $table_base i32))
+    (import "lib" "offset" (global $offset i64))
+
+    (memory 1)
+    (table 8 funcref)
+
+    ;; 使用导入的全局变量作为初始值
+    (global $g0 i32 (global.get $memory_base))
+
+    ;; 扩展常量表达式
+    (global $g1 i32 (i32.add (global.get $memory_base) (i32.const 16)))
+    (global $g2 i32 (i32.sub (global.get $memory_base) (i32.const 24)))
+    (global $g3 i32 (i32.mul (global.get $table_base) (i32.const 3)))
+    (global $g4 i64 (i64.add (i64.mul (global.get $offset) (i64.const 2)) (i64.const 1)))
+    (global $g5 i64 (i64.sub (i64.const 0) (global.get $offset)))
+
+    ;; 数据段和元素段的偏移值
+    (data (offset (global.get $memory_base)) "\01\02")
+    (data (offset (i32.add (global.get $memory_base) (i32.const 8))) "\03\04")
+    (elem (offset (global.get $table_base)) $f10 $f11)
+
+    (func $f0 (result i32 i32 i32 i32)
+        (global.get $g0)
+        (global.get $g1)
+        (global.get $g2)
+        (global.get $g3)
+    )
+
+    (func $f1 (result i64 i64)
+        (global.get $g4)
+        (global.get $g5)
+    )
+
+    (func $f2 (result i32 i32)
+        (i32.load16_u (i32.const 1024))
+        (i32.load16_u (i32.const 1032))
+    )
+
+    (func $f3 (param $index i32) (result i32)
+        (call_indirect (result i32) (local.get $index))
+    )
+
+    (func $f10 (result i32)
+        (i32.const 10)
+    )
+
+    (func $f11 (result i32)
+        (i32.const 11)
+    )
+)
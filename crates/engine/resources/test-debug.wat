@@ -0,0 +1,29 @@
+(module
+    (import "math" "sub" (func $sub (param i32 i32) (result i32)))
+
+    ;; 用于测试断点以及单步执行
+
+    (func $add (export "add") (param i32 i32) (result i32)
+        (local.get 0)
+        (local.get 1)
+        (i32.add)
+    )
+
+    (func $inc (export "inc") (param i32) (result i32)
+        (local.get 0)
+        (i32.const 1)
+        (call $add)
+    )
+
+    ;; 计算 ((a + 1) - 3) * 2
+    (func $calc (export "calc") (param i32) (result i32)
+        (local.get 0)
+        (call $inc)
+        (block (result i32)
+            (i32.const 3)
+        )
+        (call $sub)
+        (i32.const 2)
+        (i32.mul)
+    )
+)
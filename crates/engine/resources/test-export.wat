@@ -0,0 +1,21 @@
+(module
+    ;; 用于测试宿主通过导出名称访问全局变量、内存块以及表
+
+    (global $counter (export "counter") (mut i32) (i32.const 10))
+    (global $limit (export "limit") i64 (i64.const 100))
+
+    (memory (export "memory") 1)
+    (data (i32.const 16) "hello")
+
+    (table (export "table") 2 funcref)
+    (elem (i32.const 0) $get_counter)
+
+    (func $get_counter (export "get_counter") (result i32)
+        (global.get $counter)
+    )
+
+    ;; call me with (address)
+    (func (export "load") (param $address i32) (result i32)
+        (i32.load (local.get $address))
+    )
+)
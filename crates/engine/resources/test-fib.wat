@@ -0,0 +1,21 @@
+(module
+    ;; 计算 `斐波那契数`（`fib`），用于测试函数调用以及基准测试
+    ;;
+    ;; 0、 1、 1、 2、 3、 5、 8、 13、 21、 34、 55、 89、 144、 233、 377、 610
+
+    ;; 递归版本，每一次调用都会创建调用帧和结构块帧
+    (func $fib (export "fib") (param $n i32) (result i32)
+        (if (result i32)
+            (i32.lt_u (local.get $n) (i32.const 2))
+            (then
+                (local.get $n)
+            )
+            (else
+                (i32.add
+                    (call $fib (i32.sub (local.get $n) (i32.const 1)))
+                    (call $fib (i32.sub (local.get $n) (i32.const 2)))
+                )
+            )
+        )
+    )
+)
@@ -0,0 +1,24 @@
+(module
+    ;; 用于测试燃料计量
+
+    ;; 死循环
+    (func $forever (export "forever")
+        (loop
+            (br 0)
+        )
+    )
+
+    ;; 计算 1 + 2 + ... + n
+    (func $sum (export "sum") (param $n i32) (result i32)
+        (local $s i32)
+        (block
+            (loop
+                (br_if 1 (i32.eqz (local.get $n)))
+                (local.set $s (i32.add (local.get $s) (local.get $n)))
+                (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+                (br 0)
+            )
+        )
+        (local.get $s)
+    )
+)
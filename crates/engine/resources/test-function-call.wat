@@ -1,9 +1,9 @@
 (module
     ;; call return_8, for testing stack call frame
     (func $0 (result i32)
-        (i32.const 1)
+        (i32.const 10)
         (call $return_8)
-        (i32.const 2)
+        (i32.sub)       ;; 10 - 8
     )
 
     ;; call max
@@ -0,0 +1,22 @@
+(module
+    (table $t (export "table") 2 funcref)
+
+    ;; 0
+    (func $forty_two (result i32)
+        (i32.const 42)
+    )
+
+    ;; 1
+    ;; 将函数 $forty_two 的引用写入到表的指定位置
+    (func $store (param i32)
+        (table.set $t (local.get 0) (ref.func $forty_two))
+    )
+
+    ;; 2
+    ;; 返回函数 $forty_two 的引用
+    (func $get_reference (result funcref)
+        (ref.func $forty_two)
+    )
+
+    (elem declare func $forty_two)
+)
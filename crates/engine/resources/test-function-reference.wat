@@ -0,0 +1,23 @@
+(module
+    (import "lib" "table" (table $t 2 funcref))
+
+    (type $result_i32 (func (result i32)))
+
+    ;; 0
+    ;; 函数索引跟模块 lib 的函数 $forty_two 相同
+    (func $seven (result i32)
+        (i32.const 7)
+    )
+
+    ;; 1
+    ;; 间接调用表的指定元素
+    (func $call (param i32) (result i32)
+        (call_indirect $t (type $result_i32) (local.get 0))
+    )
+
+    ;; 2
+    ;; 将函数引用写入到表的指定位置
+    (func $set (param i32) (param funcref)
+        (table.set $t (local.get 0) (local.get 1))
+    )
+)
@@ -0,0 +1,21 @@
+(module
+    ;; 用于测试中断
+
+    ;; 递归调用自身 n 次，然后在多层结构块当中进入死循环
+    (func $nested (export "nested") (param $n i32)
+        (if (local.get $n)
+            (then
+                (call $nested (i32.sub (local.get $n) (i32.const 1)))
+            )
+            (else
+                (block
+                    (block
+                        (loop
+                            (br 0)
+                        )
+                    )
+                )
+            )
+        )
+    )
+)
@@ -0,0 +1,9 @@
+(module
+    ;; 无效的模块：结构块结束时留下多余的操作数
+    (func (result i32)
+        (block
+            (i32.const 1)
+        )
+        (i32.const 2)
+    )
+)
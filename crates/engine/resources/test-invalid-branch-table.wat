@@ -0,0 +1,11 @@
+(module
+    ;; 无效的模块：br_table 各个目标的结果数量不一致
+    (func (param i32) (result i32)
+        (block
+            (i32.const 1)
+            (local.get 0)
+            (br_table 0 1)
+        )
+        (i32.const 2)
+    )
+)
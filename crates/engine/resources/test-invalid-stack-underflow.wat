@@ -0,0 +1,7 @@
+(module
+    ;; 无效的模块：操作数栈的操作数不足
+    (func (result i32)
+        (i32.const 1)
+        (i32.add)
+    )
+)
@@ -0,0 +1,51 @@
+// Copyright (c) 2022 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+// Rust 的浮点数到整数的 `as` 转换是饱和的，编译器会将其编译为 `trunc_sat` 指令
+//
+// 编译命令：
+// rustc --target wasm32-unknown-unknown --crate-type cdylib -O -C strip=symbols \
+//     -o test-lib-rust-trunc-sat.wasm test-lib-rust-trunc-sat.rs
+
+#[no_mangle]
+pub extern "C" fn f32_to_i32(a: f32) -> i32 {
+    a as i32
+}
+
+#[no_mangle]
+pub extern "C" fn f32_to_u32(a: f32) -> u32 {
+    a as u32
+}
+
+#[no_mangle]
+pub extern "C" fn f64_to_i32(a: f64) -> i32 {
+    a as i32
+}
+
+#[no_mangle]
+pub extern "C" fn f64_to_u32(a: f64) -> u32 {
+    a as u32
+}
+
+#[no_mangle]
+pub extern "C" fn f32_to_i64(a: f32) -> i64 {
+    a as i64
+}
+
+#[no_mangle]
+pub extern "C" fn f32_to_u64(a: f32) -> u64 {
+    a as u64
+}
+
+#[no_mangle]
+pub extern "C" fn f64_to_i64(a: f64) -> i64 {
+    a as i64
+}
+
+#[no_mangle]
+pub extern "C" fn f64_to_u64(a: f64) -> u64 {
+    a as u64
+}
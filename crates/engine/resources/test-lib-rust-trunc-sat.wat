@@ -0,0 +1,55 @@
+(module
+  (type (;0;) (func (param f32) (result i32)))
+  (type (;1;) (func (param f32) (result i64)))
+  (type (;2;) (func (param f64) (result i32)))
+  (type (;3;) (func (param f64) (result i64)))
+  (table (;0;) 1 1 funcref)
+  (memory (;0;) 16)
+  (global (;0;) (mut i32) i32.const 1048576)
+  (global (;1;) i32 i32.const 1048576)
+  (global (;2;) i32 i32.const 1048576)
+  (export "memory" (memory 0))
+  (export "f32_to_i32" (func 0))
+  (export "f32_to_i64" (func 1))
+  (export "f32_to_u32" (func 2))
+  (export "f32_to_u64" (func 3))
+  (export "f64_to_i32" (func 4))
+  (export "f64_to_i64" (func 5))
+  (export "f64_to_u32" (func 6))
+  (export "f64_to_u64" (func 7))
+  (export "__data_end" (global 1))
+  (export "__heap_base" (global 2))
+  (func (;0;) (type 0) (param f32) (result i32)
+    local.get 0
+    i32.trunc_sat_f32_s
+  )
+  (func (;1;) (type 1) (param f32) (result i64)
+    local.get 0
+    i64.trunc_sat_f32_s
+  )
+  (func (;2;) (type 0) (param f32) (result i32)
+    local.get 0
+    i32.trunc_sat_f32_u
+  )
+  (func (;3;) (type 1) (param f32) (result i64)
+    local.get 0
+    i64.trunc_sat_f32_u
+  )
+  (func (;4;) (type 2) (param f64) (result i32)
+    local.get 0
+    i32.trunc_sat_f64_s
+  )
+  (func (;5;) (type 3) (param f64) (result i64)
+    local.get 0
+    i64.trunc_sat_f64_s
+  )
+  (func (;6;) (type 2) (param f64) (result i32)
+    local.get 0
+    i32.trunc_sat_f64_u
+  )
+  (func (;7;) (type 3) (param f64) (result i64)
+    local.get 0
+    i64.trunc_sat_f64_u
+  )
+)
+
@@ -0,0 +1,41 @@
+(module
+    ;; 用于测试循环结构块以及基准测试
+
+    ;; 计算 1 + 2 + ... + n
+    ;; 每一次迭代都会经过 `br_if` 和 `br` 跳转到外层的结构块
+    (func $sum (export "sum") (param $n i32) (result i64)
+        (local $result i64)
+        (block $out
+            (loop $top
+                (br_if $out (i32.eqz (local.get $n)))
+                (local.set $result
+                    (i64.add (local.get $result) (i64.extend_i32_u (local.get $n))))
+                (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+                (br $top)
+            )
+        )
+        (local.get $result)
+    )
+
+    ;; 嵌套的循环，内层循环里有一个结构块，用于测试多层跳转
+    ;; 返回 n * m 次迭代里偶数迭代的次数
+    (func $nested (export "nested") (param $n i32) (param $m i32) (result i32)
+        (local $i i32)
+        (local $j i32)
+        (local $count i32)
+        (loop $outer
+            (local.set $j (i32.const 0))
+            (loop $inner
+                (block $skip
+                    (br_if $skip (i32.and (i32.add (local.get $i) (local.get $j)) (i32.const 1)))
+                    (local.set $count (i32.add (local.get $count) (i32.const 1)))
+                )
+                (local.set $j (i32.add (local.get $j) (i32.const 1)))
+                (br_if $inner (i32.lt_u (local.get $j) (local.get $m)))
+            )
+            (local.set $i (i32.add (local.get $i) (i32.const 1)))
+            (br_if $outer (i32.lt_u (local.get $i) (local.get $n)))
+        )
+        (local.get $count)
+    )
+)
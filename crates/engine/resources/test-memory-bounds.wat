@@ -0,0 +1,58 @@
+(module
+    (memory 1)      ;; 页面数为 1，即有效地址的范围为 0..65536
+
+    ;; 测试各种数据宽度的加载指令
+
+    (func $f0 (param $addr i32) (result i32)
+        (local.get $addr)
+        (i32.load)
+    )
+
+    (func $f1 (param $addr i32) (result i64)
+        (local.get $addr)
+        (i64.load)
+    )
+
+    (func $f2 (param $addr i32) (result i32)
+        (local.get $addr)
+        (i32.load8_u)
+    )
+
+    ;; 测试有效地址（offset + addr）超出 u32 的范围，
+    ;; 比如 addr = 0xffffffff 时有效地址为 0x1_0000_0000，不应该回绕为 0
+    (func $f3 (param $addr i32) (result i32)
+        (local.get $addr)
+        (i32.load8_u offset=1)
+    )
+
+    (func $f4 (param $addr i32) (result i32)
+        (local.get $addr)
+        (i32.load8_u offset=0xffffffff)
+    )
+
+    ;; 测试存储指令
+
+    (func $f5 (param $addr i32)
+        (local.get $addr)
+        (i32.const 0x11)
+        (i32.store8)
+    )
+
+    (func $f6 (param $addr i32)
+        (local.get $addr)
+        (i64.const 0x1122334455667788)
+        (i64.store offset=4)
+    )
+
+    (func $f7 (param $addr i32)
+        (local.get $addr)
+        (f64.const 3.14)
+        (f64.store offset=1)
+    )
+
+    ;; 增加 1 个页面
+    (func $f8 (result i32)
+        (i32.const 1)
+        (memory.grow)
+    )
+)
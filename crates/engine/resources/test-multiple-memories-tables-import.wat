@@ -0,0 +1,20 @@
+(module
+    (import "lib" "m1" (memory $shared 1))
+    (import "lib" "t1" (table $shared_table 2 funcref))
+    (memory $local 1)
+
+    (data (memory $local) (i32.const 0) "abcd")
+
+    ;; 0
+    ;; 分别从导入的内存块和模块内定义的内存块读取数据
+    (func $load (param i32) (result i32 i32)
+        (i32.load8_u $shared (local.get 0))
+        (i32.load8_u $local (local.get 0))
+    )
+
+    ;; 1
+    ;; 将导入的表的指定元素设置为空引用
+    (func $clear_shared (param i32)
+        (table.set $shared_table (local.get 0) (ref.null func))
+    )
+)
@@ -0,0 +1,69 @@
+(module
+    (memory $m0 1)
+    (memory $m1 1)
+    (table $t0 1 funcref)
+    (table $t1 2 funcref)
+
+    (data (memory $m1) (i32.const 0) "ABCD")
+    (elem (table $t1) (i32.const 1) func $twenty)
+    (elem (table $t0) (i32.const 0) func $ten)
+
+    (export "m1" (memory $m1))
+    (export "t1" (table $t1))
+
+    ;; 0
+    (func $ten (result i32)
+        (i32.const 10)
+    )
+
+    ;; 1
+    (func $twenty (result i32)
+        (i32.const 20)
+    )
+
+    ;; 2
+    ;; 分别从两个内存块读取同一个地址的数据
+    (func $load (param i32) (result i32 i32)
+        (i32.load8_u $m0 (local.get 0))
+        (i32.load8_u $m1 (local.get 0))
+    )
+
+    ;; 3
+    ;; 写入数据到内存块 $m0
+    (func $store0 (param i32 i32)
+        (i32.store8 $m0 (local.get 0) (local.get 1))
+    )
+
+    ;; 4
+    ;; 从 $m1 复制数据到 $m0
+    (func $copy_1_to_0 (param i32 i32 i32)
+        (memory.copy $m0 $m1 (local.get 0) (local.get 1) (local.get 2))
+    )
+
+    ;; 5
+    (func $sizes (result i32 i32 i32 i32)
+        (memory.grow $m1 (i32.const 1))
+        (drop)
+        (memory.size $m0)
+        (memory.size $m1)
+        (table.size $t0)
+        (table.size $t1)
+    )
+
+    ;; 6
+    ;; 调用表 $t1 的元素
+    (func $call_t1 (param i32) (result i32)
+        (call_indirect $t1 (result i32) (local.get 0))
+    )
+
+    ;; 7
+    ;; 从 $t1 复制元素到 $t0
+    (func $copy_t1_to_t0 (param i32 i32 i32)
+        (table.copy $t0 $t1 (local.get 0) (local.get 1) (local.get 2))
+    )
+
+    ;; 8
+    (func $call_t0 (param i32) (result i32)
+        (call_indirect $t0 (result i32) (local.get 0))
+    )
+)
@@ -0,0 +1,14 @@
+(module
+    ;; 以闭包实现的本地函数
+    (import "host" "accumulate" (func $accumulate (param i32) (result i32)))
+    (import "host" "reenter" (func $reenter (result i32)))
+
+    ;; call me with (n)
+    (func $add_twice (param $n i32) (result i32)
+        (local.get $n)
+        (call $accumulate)
+        (drop)
+        (local.get $n)
+        (call $accumulate)
+    )
+)
@@ -0,0 +1,11 @@
+(module
+    ;; 本地函数通过 VM 调用函数 $guest_countdown，
+    ;; 函数 $guest_countdown 又调用该本地函数，即本地函数被重入
+    (import "host" "countdown" (func $countdown (param i32) (result i32)))
+
+    ;; call me with (n)
+    (func $guest_countdown (export "guest_countdown") (param $n i32) (result i32)
+        (local.get $n)
+        (call $countdown)
+    )
+)
@@ -0,0 +1,86 @@
+(module
+    (table $funcs 3 5 funcref)
+    (elem (table $funcs) (i32.const 0) funcref (ref.func $ten) (ref.null func) (ref.func $thirty))
+    (elem declare func $twenty)
+
+    ;; 0
+    (func $ten (result i32)
+        (i32.const 10)
+    )
+
+    ;; 1
+    (func $twenty (result i32)
+        (i32.const 20)
+    )
+
+    ;; 2
+    (func $thirty (result i32)
+        (i32.const 30)
+    )
+
+    ;; 3
+    ;; 宿主传入的 externref 原样返回
+    (func $echo (param externref) (result externref)
+        (local.get 0)
+    )
+
+    ;; 4
+    (func $is_null_extern (param externref) (result i32)
+        (ref.is_null (local.get 0))
+    )
+
+    ;; 5
+    (func $is_null_func (result i32 i32)
+        (ref.is_null (ref.null func))
+        (ref.is_null (ref.func $twenty))
+    )
+
+    ;; 6
+    ;; 根据条件选择其中一个 externref
+    (func $select_extern (param externref externref i32) (result externref)
+        (select (result externref) (local.get 0) (local.get 1) (local.get 2))
+    )
+
+    ;; 7
+    (func $table_size (result i32)
+        (table.size $funcs)
+    )
+
+    ;; 8
+    ;; 如果元素为空则返回 -1
+    (func $call_at (param i32) (result i32)
+        (if (result i32)
+            (ref.is_null (table.get $funcs (local.get 0)))
+            (then
+                (i32.const -1)
+            )
+            (else
+                (call_indirect $funcs (result i32) (local.get 0))
+            )
+        )
+    )
+
+    ;; 9
+    ;; 将位置 i 设置为 $twenty
+    (func $set_twenty (param i32)
+        (table.set $funcs (local.get 0) (ref.func $twenty))
+    )
+
+    ;; 10
+    ;; 将位置 i 设置为空引用
+    (func $set_null (param i32)
+        (table.set $funcs (local.get 0) (ref.null func))
+    )
+
+    ;; 11
+    ;; 增加 n 个 $thirty 元素，返回原先的大小或者 -1
+    (func $grow (param i32) (result i32)
+        (table.grow $funcs (ref.func $thirty) (local.get 0))
+    )
+
+    ;; 12
+    ;; 从位置 i 开始填充 n 个 $ten
+    (func $fill (param i32 i32)
+        (table.fill $funcs (local.get 0) (ref.func $ten) (local.get 1))
+    )
+)
@@ -0,0 +1,42 @@
+(module
+    ;; 用于测试 VM 快照
+
+    (table $table 2 funcref)
+    (memory $memory 1 4)
+    (global $counter (mut i32) (i32.const 0))
+    (data $greeting "hello")
+    (elem declare func $sum)
+
+    ;; 计算 1 + 2 + ... + n
+    ;; 先增加一个内存页面、设置表的元素以及丢弃数据段，然后在循环里把中间结果
+    ;; 逐个写入新增加的页面，并把全局变量 counter 加 1
+    (func $sum (export "sum") (param $n i32) (result i32)
+        (local $i i32)
+        (local $total i32)
+
+        (drop (memory.grow (i32.const 1)))
+        (table.set (i32.const 1) (ref.func $sum))
+        (data.drop $greeting)
+
+        (block $exit
+            (loop $next
+                (br_if $exit (i32.gt_s (local.get $i) (local.get $n)))
+
+                (local.set $total (i32.add (local.get $total) (local.get $i)))
+                (i32.store
+                    (i32.add (i32.const 65536) (i32.mul (local.get $i) (i32.const 4)))
+                    (local.get $total))
+                (global.set $counter (i32.add (global.get $counter) (i32.const 1)))
+
+                (local.set $i (i32.add (local.get $i) (i32.const 1)))
+                (br $next)
+            )
+        )
+
+        (local.get $total)
+    )
+
+    (func (export "get_counter") (result i32)
+        (global.get $counter)
+    )
+)
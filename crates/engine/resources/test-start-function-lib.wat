@@ -0,0 +1,18 @@
+(module
+    (global $counter (export "counter") (mut i32) (i32.const 0))
+
+    ;; start 函数将计数器设置为 10
+    (func $init
+        (global.set $counter (i32.const 10))
+    )
+
+    (func (export "inc")
+        (global.set $counter (i32.add (global.get $counter) (i32.const 1)))
+    )
+
+    (func (export "get_counter") (result i32)
+        (global.get $counter)
+    )
+
+    (start $init)
+)
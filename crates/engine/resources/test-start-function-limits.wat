@@ -0,0 +1,23 @@
+(module
+    (global $count (mut i32) (i32.const 0))
+
+    ;; 递归 n 层，每层将计数器增加 1
+    (func $recur (param $n i32)
+        (global.set $count (i32.add (global.get $count) (i32.const 1)))
+        (if (local.get $n)
+            (then
+                (call $recur (i32.sub (local.get $n) (i32.const 1)))
+            )
+        )
+    )
+
+    (func $main
+        (call $recur (i32.const 100))
+    )
+
+    (func $get_count (export "get_count") (result i32)
+        (global.get $count)
+    )
+
+    (start $main)
+)
@@ -0,0 +1,7 @@
+(module
+    (func $main
+        (unreachable)
+    )
+
+    (start $main)
+)
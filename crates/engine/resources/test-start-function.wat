@@ -0,0 +1,22 @@
+(module
+    ;; 当前模块导入了模块 "lib" 的函数，所以模块 "lib" 应该先实例化，
+    ;; 即它的 start 函数先执行
+    (import "lib" "inc" (func $inc))
+
+    (memory 1)
+    (global $value (mut i32) (i32.const 0))
+
+    (data (i32.const 100) "\2a")
+
+    ;; start 函数执行时，data 已经填充到内存
+    (func $main
+        (global.set $value (i32.load8_u (i32.const 100)))
+        (call $inc)
+    )
+
+    (func $get_value (result i32)
+        (global.get $value)
+    )
+
+    (start $main)
+)
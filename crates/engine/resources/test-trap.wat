@@ -0,0 +1,70 @@
+(module
+  (type $type_result_i32 (func (result i32)))
+  (table 3 funcref)
+  (elem (i32.const 0) $one $inner)
+
+  ;; 函数 #0
+  (func $one (result i32)
+    (i32.const 1)
+  )
+
+  ;; 函数 #1
+  (func $inner (param i32) (result i32)
+    (local.get 0)
+    (drop)
+    (block
+      (unreachable)
+    )
+    (i32.const 0)
+  )
+
+  ;; 函数 #2
+  (func $middle (export "middle") (param i32) (result i32)
+    (local.get 0)
+    (call $inner)
+  )
+
+  ;; 函数 #3
+  (func $outer (export "outer") (param i32) (result i32)
+    (i32.const 100)
+    (drop)
+    (local.get 0)
+    (call $middle)
+  )
+
+  ;; 函数 #4
+  ;; 没有名称，回溯信息会使用导出名称
+  (func (export "i32_div_s") (param i32 i32) (result i32)
+    (i32.div_s (local.get 0) (local.get 1))
+  )
+
+  ;; 函数 #5
+  (func (export "i32_rem_s") (param i32 i32) (result i32)
+    (i32.rem_s (local.get 0) (local.get 1))
+  )
+
+  ;; 函数 #6
+  (func (export "i64_div_u") (param i64 i64) (result i64)
+    (i64.div_u (local.get 0) (local.get 1))
+  )
+
+  ;; 函数 #7
+  (func (export "i64_rem_s") (param i64 i64) (result i64)
+    (i64.rem_s (local.get 0) (local.get 1))
+  )
+
+  ;; 函数 #8
+  (func (export "i32_trunc_f32_s") (param f32) (result i32)
+    (i32.trunc_f32_s (local.get 0))
+  )
+
+  ;; 函数 #9
+  (func (export "i64_trunc_f64_u") (param f64) (result i64)
+    (i64.trunc_f64_u (local.get 0))
+  )
+
+  ;; 函数 #10
+  (func (export "call_dynamic") (param i32) (result i32)
+    (call_indirect (type $type_result_i32) (local.get 0))
+  )
+)
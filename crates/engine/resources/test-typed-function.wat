@@ -0,0 +1,22 @@
+(module
+    ;; 用于测试带类型的函数句柄
+
+    (func (export "mix") (param $a i32) (param $b i64) (result f64)
+        (f64.add
+            (f64.convert_i32_s (local.get $a))
+            (f64.convert_i64_s (local.get $b))
+        )
+    )
+
+    (func (export "swap") (param $a i32) (param $b f32) (result f32 i32)
+        (local.get $b)
+        (local.get $a)
+    )
+
+    (func (export "answer") (result i64)
+        (i64.const 42)
+    )
+
+    (func (export "nothing")
+    )
+)
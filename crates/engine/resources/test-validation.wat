@@ -0,0 +1,38 @@
+(module
+    ;; 用于测试模块验证器
+    ;; 测试时会将函数的指令序列替换为无效的指令序列
+
+    (import "env" "print" (func $print (param i32)))
+    (import "env" "base" (global $base i32))
+
+    (global $counter (mut i32) (i32.const 0))
+    (global $offset i32 (i32.add (global.get $base) (i32.const 8)))
+
+    (memory 1 2)
+    (table 2 funcref)
+
+    (elem (i32.const 0) $add $inc)
+
+    (func $add (export "add") (param $a i32) (param $b i32) (result i32)
+        (i32.add (local.get $a) (local.get $b))
+    )
+
+    (func $inc (result i32)
+        (global.set $counter (i32.add (global.get $counter) (i32.const 1)))
+        (global.get $counter)
+    )
+
+    (func $sum (export "sum") (param $n i32) (result i64)
+        (local $result i64)
+        (block $out
+            (loop $top
+                (br_if $out (i32.eqz (local.get $n)))
+                (local.set $result
+                    (i64.add (local.get $result) (i64.extend_i32_u (local.get $n))))
+                (local.set $n (i32.sub (local.get $n) (i32.const 1)))
+                (br $top)
+            )
+        )
+        (local.get $result)
+    )
+)
@@ -53,7 +53,10 @@ use anvm_ast::{ast::ImportDescriptor, instruction};
 
 use crate::{
     error::{EngineError, OutOfRange, Unsupported},
-    object::{BlockItem, BranchTarget, Control, FunctionItem, Instruction, NamedAstModule},
+    object::{
+        BlockItem, BranchTarget, Control, FunctionItem, Fused, I32Comparison, Instruction,
+        NamedAstModule,
+    },
 };
 
 /// 将 AST 模块当中的函数指令序列编译为虚拟机能直接解析运行的指令
@@ -370,6 +373,131 @@ pub fn decode(
     Ok(instructions_list)
 }
 
+/// 将指令列表里常见的指令序列合并为超级指令
+///
+/// 这是一个可选的优化过程，用于减少 VM 分派指令的次数，目前合并的指令序列有：
+///
+/// - `local.get; local.get; i32.add`
+/// - `local.get; i32.const; i32.add/i32.sub`
+/// - `local.get; i32.const; i32.add/i32.sub; local.set`
+/// - i32 比较指令之后紧跟着 `br_if`
+///
+/// 超级指令只替换原指令序列的第一条指令，其余指令保持不变，所以返回的指令列表
+/// 跟原指令列表一一对应，即各个函数的起始地址、结构块的位置以及跳转目标均无需改变。
+/// 超级指令所对应的原指令的位置（即原指令的索引）为
+/// `超级指令的地址 + 0..Fused::get_instruction_count()`。
+///
+/// 被合并的指令序列不包含除了末尾的 `br_if` 之外的控制指令，而所有跳转目标以及
+/// 函数调用的返回地址都是控制指令所在的位置或者控制指令的下一条指令，所以跳转目标
+/// 不会落在被合并的指令序列的中间。
+pub fn fuse_instructions(instructions: &[Instruction]) -> Vec<Instruction> {
+    let mut fused_instructions = instructions.to_vec();
+
+    let mut address = 0;
+    while address < instructions.len() {
+        if let Some(fused) = match_fused_instruction(&instructions[address..]) {
+            let instruction_count = fused.get_instruction_count();
+            fused_instructions[address] = Instruction::Fused(fused);
+            address += instruction_count;
+        } else {
+            address += 1;
+        }
+    }
+
+    fused_instructions
+}
+
+/// 尝试把指令列表开头的若干条指令合并为一条超级指令
+///
+/// 较长的指令序列优先匹配。
+fn match_fused_instruction(instructions: &[Instruction]) -> Option<Fused> {
+    match instructions {
+        [
+            Instruction::Sequence(instruction::Instruction::LocalGet(source_index)),
+            Instruction::Sequence(instruction::Instruction::I32Const(value)),
+            Instruction::Sequence(operation),
+            Instruction::Sequence(instruction::Instruction::LocalSet(dest_index)),
+            ..
+        ] => get_i32_add_value(operation, *value).map(|value| {
+            Fused::LocalGetI32ConstI32AddLocalSet(*source_index, value, *dest_index)
+        }),
+        [
+            Instruction::Sequence(instruction::Instruction::LocalGet(first_index)),
+            Instruction::Sequence(instruction::Instruction::LocalGet(second_index)),
+            Instruction::Sequence(instruction::Instruction::I32Add),
+            ..
+        ] => Some(Fused::LocalGetLocalGetI32Add(*first_index, *second_index)),
+        [
+            Instruction::Sequence(instruction::Instruction::LocalGet(index)),
+            Instruction::Sequence(instruction::Instruction::I32Const(value)),
+            Instruction::Sequence(operation),
+            ..
+        ] => get_i32_add_value(operation, *value)
+            .map(|value| Fused::LocalGetI32ConstI32Add(*index, value)),
+        [
+            Instruction::Sequence(comparison_instruction),
+            Instruction::Control(Control::BreakWhenNotEqZero {
+                option_block_index,
+                relative_depth,
+                address,
+            }),
+            ..
+        ] => get_i32_comparison(comparison_instruction).map(|comparison| {
+            Fused::I32CompareBreakWhenNotEqZero {
+                comparison,
+                option_block_index: *option_block_index,
+                relative_depth: *relative_depth,
+                address: *address,
+            }
+        }),
+        [
+            Instruction::Sequence(comparison_instruction),
+            Instruction::Control(Control::RecurWhenNotEqZero {
+                block_index,
+                relative_depth,
+                address,
+            }),
+            ..
+        ] => get_i32_comparison(comparison_instruction).map(|comparison| {
+            Fused::I32CompareRecurWhenNotEqZero {
+                comparison,
+                block_index: *block_index,
+                relative_depth: *relative_depth,
+                address: *address,
+            }
+        }),
+        _ => None,
+    }
+}
+
+/// 把 `i32.const c; i32.add` 和 `i32.const c; i32.sub` 统一转换为加法的加数
+fn get_i32_add_value(operation: &instruction::Instruction, value: i32) -> Option<i32> {
+    match operation {
+        instruction::Instruction::I32Add => Some(value),
+        instruction::Instruction::I32Sub => Some(value.wrapping_neg()),
+        _ => None,
+    }
+}
+
+fn get_i32_comparison(instruction: &instruction::Instruction) -> Option<I32Comparison> {
+    let comparison = match instruction {
+        instruction::Instruction::I32Eqz => I32Comparison::Eqz,
+        instruction::Instruction::I32Eq => I32Comparison::Eq,
+        instruction::Instruction::I32Ne => I32Comparison::Ne,
+        instruction::Instruction::I32LtS => I32Comparison::LtS,
+        instruction::Instruction::I32LtU => I32Comparison::LtU,
+        instruction::Instruction::I32GtS => I32Comparison::GtS,
+        instruction::Instruction::I32GtU => I32Comparison::GtU,
+        instruction::Instruction::I32LeS => I32Comparison::LeS,
+        instruction::Instruction::I32LeU => I32Comparison::LeU,
+        instruction::Instruction::I32GeS => I32Comparison::GeS,
+        instruction::Instruction::I32GeU => I32Comparison::GeU,
+        _ => return None,
+    };
+
+    Some(comparison)
+}
+
 fn get_branch_target(
     function_start_address: usize,
     function_end_address: usize,
@@ -454,7 +582,12 @@ fn get_branch_target(
 /// 详细见：
 /// https://webassembly.github.io/spec/core/valid/instructions.html#constant-expressions
 ///
-/// 目前这里只支持 `t.const 指令`
+/// 另外还支持扩展常量表达式（extended-const）提案所添加的指令：
+///
+/// - i32.add, i32.sub, i32.mul
+/// - i64.add, i64.sub, i64.mul
+///
+/// https://github.com/WebAssembly/extended-const/blob/main/proposals/extended-const/Overview.md
 pub fn decode_constant_expression(
     original_instructions: &[instruction::Instruction],
 ) -> Result<Vec<instruction::Instruction>, EngineError> {
@@ -466,6 +599,15 @@ pub fn decode_constant_expression(
             | instruction::Instruction::I64Const(_)
             | instruction::Instruction::F32Const(_)
             | instruction::Instruction::F64Const(_)
+            | instruction::Instruction::RefNull(_)
+            | instruction::Instruction::RefFunc(_)
+            | instruction::Instruction::GlobalGet(_)
+            | instruction::Instruction::I32Add
+            | instruction::Instruction::I32Sub
+            | instruction::Instruction::I32Mul
+            | instruction::Instruction::I64Add
+            | instruction::Instruction::I64Sub
+            | instruction::Instruction::I64Mul
             | instruction::Instruction::End => inst.to_owned(),
             _ => {
                 return Err(EngineError::Unsupported(
@@ -482,12 +624,12 @@ pub fn decode_constant_expression(
 
 #[cfg(test)]
 mod tests {
-    use super::{decode, NamedAstModule};
+    use super::{decode, fuse_instructions, NamedAstModule};
     use crate::{
         error::{EngineError, NativeTerminate},
         linker,
         native_module::{EmptyModuleContext, NativeModule},
-        object::{BranchTarget, Control, FunctionItem, Instruction},
+        object::{BranchTarget, Control, FunctionItem, Fused, I32Comparison, Instruction},
         vm::VM,
     };
     use anvm_ast::{
@@ -508,9 +650,9 @@ mod tests {
         export_items: Vec<ExportItem>,
         code_items: Vec<CodeItem>,
     ) -> NamedAstModule {
-        NamedAstModule {
-            name: name.to_string(),
-            module: ast::Module {
+        NamedAstModule::new(
+            name,
+            ast::Module {
                 custom_items: vec![],
                 type_items,
                 import_items,
@@ -524,7 +666,7 @@ mod tests {
                 code_items,
                 data_items: vec![],
             },
-        }
+        )
     }
 
     /// 创建最小化的 AST Module
@@ -2021,4 +2163,58 @@ mod tests {
 
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn test_fuse_instructions() {
+        let instructions = vec![
+            // 0
+            Instruction::Control(Control::Block {
+                block_type: BlockType::ResultEmpty,
+                block_index: 1,
+                end_address: 13,
+            }),
+            // 1
+            Instruction::Sequence(instruction::Instruction::LocalGet(0)),
+            Instruction::Sequence(instruction::Instruction::I32Const(1)),
+            Instruction::Sequence(instruction::Instruction::I32Sub),
+            Instruction::Sequence(instruction::Instruction::LocalSet(0)),
+            // 5
+            Instruction::Sequence(instruction::Instruction::LocalGet(0)),
+            Instruction::Sequence(instruction::Instruction::LocalGet(1)),
+            Instruction::Sequence(instruction::Instruction::I32Add),
+            // 8
+            Instruction::Sequence(instruction::Instruction::LocalGet(2)),
+            Instruction::Sequence(instruction::Instruction::I32Const(3)),
+            Instruction::Sequence(instruction::Instruction::I32Mul),
+            // 11
+            Instruction::Sequence(instruction::Instruction::I32LtU),
+            Instruction::Control(Control::BreakWhenNotEqZero {
+                option_block_index: Some(1),
+                relative_depth: 0,
+                address: 13,
+            }),
+            // 13
+            Instruction::Control(Control::End(Some(1))),
+            // 14
+            Instruction::Sequence(instruction::Instruction::I32Eqz),
+            Instruction::Control(Control::End(None)),
+        ];
+
+        let actual = fuse_instructions(&instructions);
+
+        // 指令的数量以及未被合并的指令均保持不变
+        assert_eq!(actual.len(), instructions.len());
+
+        let mut expected = instructions.clone();
+        expected[1] = Instruction::Fused(Fused::LocalGetI32ConstI32AddLocalSet(0, -1, 0));
+        expected[5] = Instruction::Fused(Fused::LocalGetLocalGetI32Add(0, 1));
+        expected[11] = Instruction::Fused(Fused::I32CompareBreakWhenNotEqZero {
+            comparison: I32Comparison::LtU,
+            option_block_index: Some(1),
+            relative_depth: 0,
+            address: 13,
+        });
+
+        assert_eq!(actual, expected);
+    }
 }
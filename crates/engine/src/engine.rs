@@ -0,0 +1,169 @@
+// Copyright (c) 2022 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! # 引擎与已编译的模块
+//!
+//! `instance::create_instance` 每次都会重新链接函数、解码指令以及构建 VMModule，
+//! 当同一组模块需要被实例化很多次时（比如服务器为每个请求创建一个 VM），
+//! 可以先使用 `Engine` 把模块编译为 `Module`，然后通过 `Module::instantiate`
+//! 创建 VM 实例。
+//!
+//! `Module` 把指令、函数信息、类型等不可变的数据放在 `Arc` 里，克隆 `Module` 以及
+//! 实例化时都不会复制这些数据，各个 VM 实例只分配属于自己的表、内存块、全局变量、
+//! 数据段、元素段以及栈，所以实例化的开销只跟状态的大小有关，跟代码的大小无关。
+
+use std::sync::Arc;
+
+use anvm_ast::ast::FunctionType;
+
+use crate::{
+    error::{EngineError, InvalidOperation},
+    instance::{create_vm_modules, instantiate_vm_modules, InstanceConfig},
+    native_module::NativeModule,
+    object::NamedAstModule,
+    validator::validate,
+    vm::VM,
+    vm_module::VMModule,
+};
+
+pub struct Engine {}
+
+impl Engine {
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// 验证并编译模块
+    ///
+    /// 编译之前会先验证所有 AST 模块，遇到第一个无效的模块时返回
+    /// `EngineError::Validation` 错误。
+    ///
+    /// native_modules 仅用于链接导入的本地函数，实例化时需要提供名称、函数名称以及
+    /// 函数类型均相同的本地模块（本地模块的上下文则属于各个 VM 实例）。
+    pub fn compile(
+        &self,
+        native_modules: &[NativeModule],
+        named_ast_modules: Vec<NamedAstModule>,
+    ) -> Result<Module, EngineError> {
+        for named_ast_module in &named_ast_modules {
+            validate(&named_ast_module.module).map_err(|validation_error| {
+                EngineError::Validation(named_ast_module.name.clone(), validation_error)
+            })?;
+        }
+
+        let vm_modules = create_vm_modules(native_modules, &named_ast_modules)?;
+
+        let native_module_signatures = native_modules
+            .iter()
+            .map(NativeModuleSignature::new)
+            .collect::<Vec<NativeModuleSignature>>();
+
+        Ok(Module {
+            named_ast_modules: Arc::new(named_ast_modules),
+            native_module_signatures: Arc::new(native_module_signatures),
+            vm_modules: Arc::new(vm_modules),
+        })
+    }
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 已编译的模块
+///
+/// 克隆 `Module` 只会增加引用计数，可以在多个线程之间共享。
+#[derive(Clone)]
+pub struct Module {
+    /// 实例化时需要根据 AST 模块创建表、内存块、全局变量以及数据段等，
+    /// 并执行各个模块的 start 函数
+    named_ast_modules: Arc<Vec<NamedAstModule>>,
+
+    /// 编译时所使用的本地模块的结构，用于检查实例化时提供的本地模块
+    native_module_signatures: Arc<Vec<NativeModuleSignature>>,
+
+    vm_modules: Arc<Vec<VMModule>>,
+}
+
+impl Module {
+    /// 创建 VM 实例
+    ///
+    /// native_modules 必须跟编译时所使用的本地模块一一对应，否则返回
+    /// `InvalidOperation::NativeModuleMismatch` 错误。
+    pub fn instantiate(&self, native_modules: Vec<NativeModule>) -> Result<VM, EngineError> {
+        self.instantiate_with_config(native_modules, &InstanceConfig::default())
+    }
+
+    /// 按照指定的配置创建 VM 实例
+    ///
+    /// 配置在执行各个模块的 start 函数之前生效。
+    pub fn instantiate_with_config(
+        &self,
+        native_modules: Vec<NativeModule>,
+        config: &InstanceConfig,
+    ) -> Result<VM, EngineError> {
+        let signature_count = self.native_module_signatures.len();
+
+        for native_module_index in 0..native_modules.len().max(signature_count) {
+            let is_match = match (
+                native_modules.get(native_module_index),
+                self.native_module_signatures.get(native_module_index),
+            ) {
+                (Some(native_module), Some(signature)) => {
+                    &NativeModuleSignature::new(native_module) == signature
+                }
+                _ => false,
+            };
+
+            if !is_match {
+                return Err(EngineError::InvalidOperation(
+                    InvalidOperation::NativeModuleMismatch(native_module_index),
+                ));
+            }
+        }
+
+        instantiate_vm_modules(
+            native_modules,
+            &self.named_ast_modules,
+            Arc::clone(&self.vm_modules),
+            config,
+        )
+    }
+
+    pub fn get_named_ast_modules(&self) -> &[NamedAstModule] {
+        &self.named_ast_modules
+    }
+
+    pub fn get_vm_modules(&self) -> &Arc<Vec<VMModule>> {
+        &self.vm_modules
+    }
+}
+
+/// 本地模块当中影响链接结果的信息
+#[derive(Debug, PartialEq)]
+struct NativeModuleSignature {
+    name: String,
+    function_names: Vec<String>,
+    function_types: Vec<FunctionType>,
+}
+
+impl NativeModuleSignature {
+    fn new(native_module: &NativeModule) -> Self {
+        let function_types = native_module
+            .function_to_type_index_list
+            .iter()
+            .map(|type_index| native_module.function_types[*type_index].clone())
+            .collect::<Vec<FunctionType>>();
+
+        Self {
+            name: native_module.name.clone(),
+            function_names: native_module.function_names.clone(),
+            function_types,
+        }
+    }
+}
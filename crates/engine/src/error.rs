@@ -14,6 +14,12 @@ use anvm_ast::{
     types::{Value, ValueType},
 };
 
+use crate::{
+    snapshot::SnapshotError,
+    trap::{Trap, TrapCode},
+    validator::ValidationError,
+};
+
 pub fn make_operand_data_types_mismatch_engine_error(
     instruction_name: &str,
     expected_types: Vec<ValueType>,
@@ -43,6 +49,17 @@ pub enum EngineError {
     TypeMismatch(TypeMismatch),
     InvalidOperation(InvalidOperation),
     NativeTerminate(NativeTerminate),
+    Interrupt(Interrupt),
+    Trap(Trap),
+
+    /// 模块实例化时执行 start 函数遇到了陷阱
+    StartFunctionTrap(/* module_name */ String, Trap),
+
+    /// 模块未通过验证
+    Validation(/* module_name */ String, ValidationError),
+
+    /// 无法恢复 VM 快照
+    Snapshot(SnapshotError),
 }
 
 impl Display for EngineError {
@@ -55,6 +72,21 @@ impl Display for EngineError {
             EngineError::TypeMismatch(s) => write!(f, "{}", s),
             EngineError::InvalidOperation(s) => write!(f, "{}", s),
             EngineError::NativeTerminate(s) => write!(f, "{}", s),
+            EngineError::Interrupt(s) => write!(f, "{}", s),
+            EngineError::Trap(s) => write!(f, "{}", s),
+            EngineError::StartFunctionTrap(module_name, trap) => write!(
+                f,
+                "failed to instantiate module \"{}\", start function {}",
+                module_name, trap
+            ),
+            EngineError::Validation(module_name, validation_error) => write!(
+                f,
+                "module \"{}\" is invalid, {}",
+                module_name, validation_error
+            ),
+            EngineError::Snapshot(snapshot_error) => {
+                write!(f, "failed to restore snapshot, {}", snapshot_error)
+            }
         }
     }
 }
@@ -180,6 +212,9 @@ pub enum OutOfRange {
 
     /// 暂时用不上，仅当支持多内存块时才有此异常
     MemoryBlockIndexOutOfRange(/* memory block index */ usize, /* max */ usize),
+
+    /// 常量表达式里的 `global.get` 指令的全局变量索引超出了范围
+    GlobalVariableIndexOutOfRange(/* global variable index */ usize, /* max */ usize),
 }
 
 impl Display for OutOfRange {
@@ -205,6 +240,11 @@ impl Display for OutOfRange {
                 "the memory block index {} is out of range, maximum {}",
                 memory_block_index, max
             ),
+            OutOfRange::GlobalVariableIndexOutOfRange(global_variable_index, max) => write!(
+                f,
+                "the global variable index {} is out of range, maximum {}",
+                global_variable_index, max
+            ),
         }
     }
 }
@@ -212,12 +252,6 @@ impl Display for OutOfRange {
 #[derive(Debug, PartialEq, Clone)]
 pub enum Unsupported {
     UnsupportedConstantExpressionInstruction(instruction::Instruction),
-
-    /// 当 vm 支持多表格时，需要移除此异常
-    UnsupportedMultipleTable,
-
-    /// 当 vm 支持多内存块时，需要移除此异常
-    UnsupportedMultipleMemoryBlock,
 }
 
 impl Display for Unsupported {
@@ -230,12 +264,6 @@ impl Display for Unsupported {
                     inst
                 )
             }
-            Unsupported::UnsupportedMultipleTable => {
-                write!(f, "does not support multiple tables")
-            }
-            Unsupported::UnsupportedMultipleMemoryBlock => {
-                write!(f, "does not support multiple memory blocks")
-            }
         }
     }
 }
@@ -265,14 +293,6 @@ pub enum TypeMismatch {
         /* expected_type */ ValueType,
         /* actual_type */ ValueType,
     ),
-    DynamicCallNativeFunctionTypeMismatch(
-        /* native_module_index */ usize,
-        /* function_index */ usize,
-    ),
-    DynamicCallFunctionTypeMismatch(
-        /* vm_module_index */ usize,
-        /* function_index */ usize,
-    ),
     FunctionCallArgumentTypeMismatch {
         vm_module_index: usize,
         function_index: usize,
@@ -324,6 +344,21 @@ pub enum TypeMismatch {
         result_type: ValueType,
         value_type: ValueType,
     },
+
+    /// 获取带类型的函数句柄时，函数的签名跟指定的参数和返回值类型不一致
+    TypedFunctionSignatureMismatch(
+        /* module_name */ String,
+        /* function_name */ String,
+    ),
+
+    /// 通过带类型的函数句柄调用函数时，返回值的数量或者数据类型跟句柄的不一致，
+    /// 比如句柄是从另外一个 VM 获取的
+    TypedFunctionResultTypeMismatch {
+        vm_module_index: usize,
+        function_index: usize,
+        result_types: Vec<ValueType>,
+        value_types: Vec<ValueType>,
+    },
 }
 
 impl Display for TypeMismatch {
@@ -385,23 +420,6 @@ impl Display for TypeMismatch {
                     expected_type,
                     actual_type)
             }
-            TypeMismatch::DynamicCallNativeFunctionTypeMismatch(
-                native_module_index,
-                function_index,
-            ) => {
-                write!(
-                    f,
-                    "failed to call native function #{} (module #{}), the type of function does not match",
-                    function_index, native_module_index
-                )
-            }
-            TypeMismatch::DynamicCallFunctionTypeMismatch(vm_module_index, function_index) => {
-                write!(
-                    f,
-                    "failed to call function #{} (module #{}), the type of function does not match",
-                    function_index, vm_module_index
-                )
-            }
             TypeMismatch::FunctionCallArgumentTypeMismatch {
                 vm_module_index,
                 function_index,
@@ -509,6 +527,27 @@ impl Display for TypeMismatch {
                     result_type,
                     value_type)
             }
+            TypeMismatch::TypedFunctionSignatureMismatch(module_name, function_name) => {
+                write!(
+                    f,
+                    "the signature of function \"{}\" (module \"{}\") does not match",
+                    function_name, module_name
+                )
+            }
+            TypeMismatch::TypedFunctionResultTypeMismatch {
+                vm_module_index,
+                function_index,
+                result_types,
+                value_types,
+            } => {
+                write!(f,
+                    "the results of function {} (module {}) do not match the typed function, expected: ({}), actual: ({})",
+                    function_index,
+                    vm_module_index,
+                    result_types.iter().map(|t| t.to_string()).collect::<Vec<String>>().join(", "),
+                    value_types.iter().map(|t| t.to_string()).collect::<Vec<String>>().join(", ")
+                )
+            }
         }
     }
 }
@@ -585,7 +624,31 @@ pub enum InvalidOperation {
         results_count: usize,
         operands_count: usize,
     },
-    Unreachable,
+    InvalidBreakpointAddress {
+        vm_module_index: usize,
+        function_index: usize,
+        address: usize,
+    },
+
+    /// 常量表达式求值之后，操作数栈的值的数量不是 1
+    IncorrectConstantExpressionResultCount(/* values_count */ usize),
+
+    /// 全局变量的初始化常量表达式通过 `global.get` 指令（间接地）引用了自身，
+    /// 无法求值
+    CircularGlobalVariableInitialization(
+        /* module_name */ String,
+        /* global_variable_index */ usize,
+    ),
+
+    /// 以 `FnMut` 闭包实现的本地函数正在执行时又被调用
+    ReentrantNativeFunctionCall {
+        native_module_index: usize,
+        function_index: usize,
+    },
+
+    /// 实例化已编译的模块时所提供的本地模块，跟编译模块时所使用的本地模块
+    /// 的名称、函数名称或者函数类型不一致
+    NativeModuleMismatch(/* native_module_index */ usize),
 }
 
 impl Display for InvalidOperation {
@@ -740,7 +803,104 @@ impl Display for InvalidOperation {
                     results_count,
                     operands_count)
             }
-            InvalidOperation::Unreachable => write!(f, "instruction \"unreachable\" is executed"),
+            InvalidOperation::InvalidBreakpointAddress {
+                vm_module_index,
+                function_index,
+                address,
+            } => {
+                write!(f,
+                    "cannot set breakpoint at address #{} (function #{}, module #{}), the address is not within the function",
+                    address,
+                    function_index,
+                    vm_module_index)
+            }
+            InvalidOperation::IncorrectConstantExpressionResultCount(values_count) => {
+                write!(
+                    f,
+                    "the constant expression should produce exactly one value, actual: {}",
+                    values_count
+                )
+            }
+            InvalidOperation::CircularGlobalVariableInitialization(
+                module_name,
+                global_variable_index,
+            ) => {
+                write!(
+                    f,
+                    "the initializer of global variable #{} (module \"{}\") has circular dependency",
+                    global_variable_index, module_name
+                )
+            }
+            InvalidOperation::ReentrantNativeFunctionCall {
+                native_module_index,
+                function_index,
+            } => {
+                write!(
+                    f,
+                    "failed to call native function #{} (module #{}), the function is already running",
+                    function_index, native_module_index
+                )
+            }
+            InvalidOperation::NativeModuleMismatch(native_module_index) => {
+                write!(
+                    f,
+                    "the native module #{} does not match the one used to compile the module",
+                    native_module_index
+                )
+            }
+        }
+    }
+}
+
+/// VM 的执行被中断
+///
+/// 中断发生在指令执行之前，VM 的状态以及栈均保持不变，
+/// 消除中断的原因之后（比如补充燃料），可以从中断的位置继续执行。
+#[derive(Debug, PartialEq, Clone)]
+pub enum Interrupt {
+    OutOfFuel {
+        vm_module_index: usize,
+        function_index: usize,
+        address: usize,
+        fuel_cost: u64,
+        fuel_remaining: u64,
+    },
+    Interrupted {
+        vm_module_index: usize,
+        function_index: usize,
+        address: usize,
+    },
+}
+
+impl Display for Interrupt {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Interrupt::OutOfFuel {
+                vm_module_index,
+                function_index,
+                address,
+                fuel_cost,
+                fuel_remaining,
+            } => {
+                write!(f,
+                    "out of fuel at address #{} (function #{}, module #{}), required: {}, remaining: {}",
+                    address,
+                    function_index,
+                    vm_module_index,
+                    fuel_cost,
+                    fuel_remaining)
+            }
+            Interrupt::Interrupted {
+                vm_module_index,
+                function_index,
+                address,
+            } => {
+                write!(
+                    f,
+                    "interrupted at address #{} (function #{}, module #{})",
+                    address, function_index, vm_module_index
+                )
+            }
         }
     }
 }
@@ -755,6 +915,11 @@ pub struct NativeTerminate {
 pub enum NativeError {
     Exit(i32),
     Internal(Box<dyn InternalError>),
+
+    /// 本地函数执行时遇到了 WebAssembly 规范所定义的运行时错误，
+    /// 比如访问模块内存时地址超出了范围。
+    /// VM 会将其转换为 `EngineError::Trap`。
+    Trap(TrapCode),
 }
 
 pub trait InternalError: Debug + Display {
@@ -775,6 +940,13 @@ impl Display for NativeTerminate {
                     internal_error.to_string()
                 )
             }
+            NativeError::Trap(trap_code) => {
+                write!(
+                    f,
+                    "native module \"{}\" trap: {}",
+                    self.module_name, trap_code
+                )
+            }
         }
     }
 }
@@ -0,0 +1,99 @@
+// Copyright (c) 2022 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! # 导出项的句柄
+//!
+//! 通过 `VM::get_export_global`、`VM::get_export_memory` 以及 `VM::get_export_table`
+//! 按照模块的导出名称获取全局变量、内存块以及表的句柄，宿主无需自己计算
+//! 它们在 VM 实例列表里的索引。
+//!
+//! 句柄跟 `TypedFunction` 一样只记录索引，不持有 VM，所以读写时需要传入 VM。
+
+use anvm_ast::{ast::GlobalType, types::Value};
+
+use crate::{error::EngineError, vm::VM, vm_memory::VMMemory, vm_table::VMTable};
+
+/// 导出的全局变量
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ExportGlobal {
+    vm_module_index: usize,
+
+    /// 全局变量在模块内的索引（包括导入的全局变量）
+    global_variable_index: usize,
+}
+
+impl ExportGlobal {
+    pub(crate) fn new(vm_module_index: usize, global_variable_index: usize) -> Self {
+        Self {
+            vm_module_index,
+            global_variable_index,
+        }
+    }
+
+    pub fn get_global_type<'a>(&self, vm: &'a VM) -> &'a GlobalType {
+        vm.resource.global_variables[self.get_instance_index(vm)].get_global_type()
+    }
+
+    pub fn get_value(&self, vm: &VM) -> Value {
+        vm.resource.global_variables[self.get_instance_index(vm)].get_value()
+    }
+
+    /// 设置全局变量的值
+    ///
+    /// 跟指令 `global.set` 一样，不可变的全局变量返回
+    /// `InvalidOperation::ImmutableGlobalVariable` 错误，数据类型不符合时返回
+    /// `TypeMismatch::SetGlobalVariableValueTypeMismatch` 错误。
+    pub fn set_value(&self, vm: &mut VM, value: Value) -> Result<(), EngineError> {
+        vm.set_global_variable_value(self.vm_module_index, self.global_variable_index, value)
+    }
+
+    fn get_instance_index(&self, vm: &VM) -> usize {
+        vm.resource.vm_modules[self.vm_module_index].global_variable_indexes
+            [self.global_variable_index]
+    }
+}
+
+/// 导出的内存块
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ExportMemory {
+    /// 内存块在 VM 内存块实例列表里的索引
+    memory_block_index: usize,
+}
+
+impl ExportMemory {
+    pub(crate) fn new(memory_block_index: usize) -> Self {
+        Self { memory_block_index }
+    }
+
+    pub fn get<'a>(&self, vm: &'a VM) -> &'a VMMemory {
+        &vm.resource.memory_blocks[self.memory_block_index]
+    }
+
+    pub fn get_mut<'a>(&self, vm: &'a mut VM) -> &'a mut VMMemory {
+        &mut vm.resource.memory_blocks[self.memory_block_index]
+    }
+}
+
+/// 导出的表
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct ExportTable {
+    /// 表在 VM 表实例列表里的索引
+    table_index: usize,
+}
+
+impl ExportTable {
+    pub(crate) fn new(table_index: usize) -> Self {
+        Self { table_index }
+    }
+
+    pub fn get<'a>(&self, vm: &'a VM) -> &'a VMTable {
+        &vm.resource.tables[self.table_index]
+    }
+
+    pub fn get_mut<'a>(&self, vm: &'a mut VM) -> &'a mut VMTable {
+        &mut vm.resource.tables[self.table_index]
+    }
+}
@@ -0,0 +1,32 @@
+// Copyright (c) 2022 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! # 燃料计量
+//!
+//! 为了防止不受信任的模块（比如包含死循环的模块）无限期地占用宿主，
+//! 可以给 VM 设置一个燃料预算，每执行一条指令都会消耗一定数量的燃料。
+//!
+//! 燃料在指令执行之前扣除，当燃料不足以执行下一条指令时，该指令不会被执行，
+//! VM 返回错误 `EngineError::Interrupt(Interrupt::OutOfFuel {...})`，
+//! 此时 VM 的状态（Status）以及栈（VMStack）均保持不变，宿主补充燃料之后，
+//! 可以调用 `recur`、`recur_without_break` 等方法从中断的位置继续执行。
+//!
+//! 每条指令所消耗的燃料数量由 `指令燃料消耗函数` 决定，宿主可以通过
+//! `VM::set_instruction_cost_function` 方法设置自定义的函数。
+
+use crate::object::Instruction;
+
+/// 指令燃料消耗函数
+///
+/// 返回执行指定指令所需的燃料数量
+pub type InstructionCostFunction = fn(instruction: &Instruction) -> u64;
+
+/// 默认的指令燃料消耗函数
+///
+/// 每条指令均消耗 1 个单位的燃料
+pub fn default_instruction_cost(_instruction: &Instruction) -> u64 {
+    1
+}
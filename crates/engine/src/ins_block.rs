@@ -49,19 +49,13 @@
 //! br_table 后面的整数列表的索引，获取跳转的目标。如果该索引超出了
 //! 列表范围，则跳转目标的 br_table 指令的最末尾一个参数（即默认目标）
 
-use anvm_ast::{
-    instruction::BlockType,
-    types::{check_value_types, Value, ValueType, ValueTypeCheckError},
-};
+use anvm_ast::instruction::BlockType;
 
 use crate::{
-    error::{
-        make_operand_data_types_mismatch_engine_error, EngineError, InvalidOperation, TypeMismatch,
-    },
+    error::{EngineError, InvalidOperation},
     ins_control::ControlResult,
-    object::BranchTarget,
+    object::{BranchTarget, Control, Instruction},
     vm::VM,
-    vm_stack::INFO_SEGMENT_ITEM_COUNT,
 };
 
 /// 处理原 `block/loop 指令`
@@ -101,16 +95,7 @@ pub fn block_and_jump_when_eq_zero(
     // 返回地址应该是结构块最后一条指令 `end 指令` 的下一个指令
     let return_address = end_address + 1;
 
-    let testing = match vm.stack.pop_bool() {
-        Ok(b) => b,
-        Err(v) => {
-            return Err(make_operand_data_types_mismatch_engine_error(
-                "if",
-                vec![ValueType::I32],
-                vec![&v],
-            ))
-        }
-    };
+    let testing = vm.stack.pop_bool()?;
 
     // 执行完 `if 指令` 之后，如果刚才栈顶的数值是：
     //
@@ -155,20 +140,21 @@ fn continue_process_block(
     next_instruction_address: usize,
     return_address: usize,
 ) -> Result<ControlResult, EngineError> {
-    let parameter_types = {
+    // 获取结构块参数的数量
+    let parameters_count = {
         match block_type {
             BlockType::ResultEmpty
             | BlockType::ResultI32
             | BlockType::ResultI64
             | BlockType::ResultF32
-            | BlockType::ResultF64 => {
-                vec![]
-            }
+            | BlockType::ResultF64
+            | BlockType::ResultFuncRef
+            | BlockType::ResultExternRef => 0,
             BlockType::TypeIndex(type_index) => {
                 let vm_module_index = vm.status.vm_module_index;
                 let vm_module = &vm.resource.vm_modules[vm_module_index];
                 let function_type = &vm_module.function_types[*type_index as usize];
-                function_type.params.to_owned()
+                function_type.params.len()
             }
         }
     };
@@ -177,9 +163,7 @@ fn continue_process_block(
     let vm_module_index = vm.status.vm_module_index;
 
     // 判断操作数是否足够当前结构块的参数
-    let parameters_count = parameter_types.len();
-    let stack_size = vm.stack.get_size();
-    let operands_count = stack_size - vm.status.base_pointer - INFO_SEGMENT_ITEM_COUNT;
+    let operands_count = vm.get_operands_count();
     if operands_count < parameters_count {
         return Err(EngineError::InvalidOperation(
             InvalidOperation::NotEnoughOperandForBlockCall {
@@ -192,35 +176,11 @@ fn continue_process_block(
         ));
     }
 
-    // 从栈弹出数据，作为结构块的参数
-    // 跟普通的函数调用栈帧不同，结构块的栈帧没有自己的局部变量段，所以只能
-    // 把实参先弹出来，在创建了控制帧之后，再压入栈。
-    let arguments = vm.stack.pop_values(parameters_count);
-
-    // 核对实参的数据类型和数量
-    match check_value_types(&arguments, &parameter_types) {
-        Err(ValueTypeCheckError::DataTypeMismatch(parameter_index)) => {
-            return Err(EngineError::TypeMismatch(
-                TypeMismatch::BlockCallArgumentTypeMismatch {
-                    vm_module_index,
-                    function_index,
-                    block_index,
-                    parameter_index,
-                    parameter_type: parameter_types[parameter_index].clone(),
-                    value_type: arguments[parameter_index].get_type(),
-                },
-            ));
-        }
-        _ => {
-            // pass
-        }
-    }
-
-    // 压入调用栈
-    vm.push_control_frame(return_address);
-
-    // 压入实参
-    vm.stack.push_values(&arguments);
+    // 结构块的参数已经位于栈顶，它们将直接成为新栈帧的第一批操作数，
+    // 所以这里不需要把实参弹出再重新压入。
+    // 实参的数据类型已经由验证器检查过。
+    // 压入结构块帧
+    vm.push_control_frame(parameters_count, return_address);
 
     // 返回新的状态信息，让调用者更新虚拟机状态
     let control_result = ControlResult::PushStackFrame {
@@ -249,15 +209,15 @@ pub fn process_break(
         Ok(ControlResult::JumpWithinBlock(address))
     } else {
         // 跨层跳转，需要将当前栈帧的操作数作为目标层的返回值，具体步骤：
-        // 1. 根据目标层返回值的数量，暂存当前栈帧相应数量的操作数；
-        // 2. 弹出 `相对深度` 数量的栈帧；
-        // 3. 压入第一步所保留的操作数，作为目标结构块返回值。
+        // 1. 根据目标层返回值的数量，确定栈顶需要保留的操作数的数量；
+        // 2. 弹出 `相对深度` 数量的控制帧，并丢弃这些帧在操作数栈上的数据，
+        //    仅保留栈顶的操作数，作为目标结构块返回值。
 
         let vm_module_index = vm.status.vm_module_index;
         let function_index = vm.status.function_index;
 
-        let target_status = vm.get_status_by_relative_depth(relative_depth);
-        let target_frame_type = &target_status.frame_type;
+        // 目标结构块的类型记录在最外层被弹出的控制帧里
+        let target_frame_type = &vm.stack.get_frame(relative_depth - 1).return_frame_type;
 
         // 获取目标帧的返回值的数量
         //
@@ -273,7 +233,9 @@ pub fn process_break(
                 BlockType::ResultI32
                 | BlockType::ResultI64
                 | BlockType::ResultF32
-                | BlockType::ResultF64 => 1,
+                | BlockType::ResultF64
+                | BlockType::ResultFuncRef
+                | BlockType::ResultExternRef => 1,
                 BlockType::TypeIndex(type_index) => {
                     let vm_module = &vm.resource.vm_modules[vm_module_index];
                     let function_type = &vm_module.function_types[*type_index as usize];
@@ -283,8 +245,7 @@ pub fn process_break(
         };
 
         // 判断操作数是否足够当前函数或结构块用于返回
-        let stack_size = vm.stack.get_size();
-        let operands_count = stack_size - vm.status.base_pointer - INFO_SEGMENT_ITEM_COUNT;
+        let operands_count = vm.get_operands_count();
         if operands_count < results_count {
             if let Some(block_index) = option_block_index {
                 return Err(EngineError::InvalidOperation(
@@ -310,10 +271,10 @@ pub fn process_break(
         }
 
         // 丢弃指定数量的栈帧
-        vm.pop_frames(relative_depth, results_count);
+        let frame = vm.pop_frames(relative_depth, results_count);
 
         Ok(ControlResult::JumpWithinFunction {
-            frame_type: target_status.frame_type,
+            frame_type: frame.return_frame_type,
             address,
         })
     }
@@ -326,16 +287,7 @@ pub fn process_break_when_not_eq_zero(
     relative_depth: usize,
     address: usize,
 ) -> Result<ControlResult, EngineError> {
-    let testing = match vm.stack.pop_bool() {
-        Ok(b) => b,
-        Err(v) => {
-            return Err(make_operand_data_types_mismatch_engine_error(
-                "br_if",
-                vec![ValueType::I32],
-                vec![&v],
-            ))
-        }
-    };
+    let testing = vm.stack.pop_bool()?;
 
     if testing {
         process_break(vm, option_block_index, relative_depth, address)
@@ -357,36 +309,36 @@ pub fn recur(
         ))
     } else {
         // 跨层跳转，需要将当前栈帧的操作数作为目标层的参数，具体步骤：
-        // 1. 根据目标层参数的数量，暂存当前栈帧相应数量的操作数；
-        // 2. 弹出 `相对深度` 数量的栈帧；
-        // 3. 压入第一步所保留的操作数，作为目标结构块的参数。
+        // 1. 根据目标层参数的数量，确定栈顶需要保留的操作数的数量；
+        // 2. 弹出 `相对深度` 数量的控制帧，并丢弃这些帧在操作数栈上的数据，
+        //    仅保留栈顶的操作数，作为目标结构块的参数。
 
         let vm_module_index = vm.status.vm_module_index;
         let function_index = vm.status.function_index;
 
-        let target_status = vm.get_status_by_relative_depth(relative_depth);
-        let target_frame_type = &target_status.frame_type;
+        // 目标结构块的类型记录在最外层被弹出的控制帧里
+        let target_frame_type = &vm.stack.get_frame(relative_depth - 1).return_frame_type;
 
-        // 获取目标帧的参数信息
-        let parameter_types = {
+        // 获取目标帧的参数的数量
+        let parameters_count = {
             match target_frame_type {
                 BlockType::ResultEmpty
                 | BlockType::ResultI32
                 | BlockType::ResultI64
                 | BlockType::ResultF32
-                | BlockType::ResultF64 => vec![],
+                | BlockType::ResultF64
+                | BlockType::ResultFuncRef
+                | BlockType::ResultExternRef => 0,
                 BlockType::TypeIndex(type_index) => {
                     let vm_module = &vm.resource.vm_modules[vm_module_index];
                     let function_type = &vm_module.function_types[*type_index as usize];
-                    function_type.params.to_owned()
+                    function_type.params.len()
                 }
             }
         };
 
         // 判断操作数是否足够当前结构块用于递归调用
-        let parameters_count = parameter_types.len();
-        let stack_size = vm.stack.get_size();
-        let operands_count = stack_size - vm.status.base_pointer - INFO_SEGMENT_ITEM_COUNT;
+        let operands_count = vm.get_operands_count();
         if operands_count < parameters_count {
             return Err(EngineError::InvalidOperation(
                 InvalidOperation::NotEnoughOperandForLoopBlockRecur {
@@ -400,35 +352,10 @@ pub fn recur(
             ));
         }
 
-        let arguments = vm.stack.peek_values(parameters_count);
-
-        // 核对实参的数据类型和数量
-        match check_value_types(arguments, &parameter_types) {
-            Err(ValueTypeCheckError::LengthMismatch) => {
-                unreachable!("arguments count should be match")
-            }
-            Err(ValueTypeCheckError::DataTypeMismatch(parameter_index)) => {
-                return Err(EngineError::TypeMismatch(
-                    TypeMismatch::LoopBlockRecurArgumentTypeMismatch {
-                        vm_module_index,
-                        function_index,
-                        source_block_index: block_index,
-                        relative_depth,
-                        parameter_index,
-                        parameter_type: parameter_types[parameter_index].clone(),
-                        valuetype: arguments[parameter_index].get_type(),
-                    },
-                ));
-            }
-            _ => {
-                // pass
-            }
-        }
-
-        vm.pop_frames(relative_depth, parameters_count);
+        let frame = vm.pop_frames(relative_depth, parameters_count);
 
         Ok(ControlResult::JumpWithinFunction {
-            frame_type: target_status.frame_type,
+            frame_type: frame.return_frame_type,
             address: address + 1,
         })
     }
@@ -441,16 +368,7 @@ pub fn recur_when_not_eq_zero(
     relative_depth: usize,
     address: usize,
 ) -> Result<ControlResult, EngineError> {
-    let testing = match vm.stack.pop_bool() {
-        Ok(b) => b,
-        Err(v) => {
-            return Err(make_operand_data_types_mismatch_engine_error(
-                "br_if",
-                vec![ValueType::I32],
-                vec![&v],
-            ))
-        }
-    };
+    let testing = vm.stack.pop_bool()?;
 
     if testing {
         recur(vm, block_index, relative_depth, address)
@@ -462,25 +380,24 @@ pub fn recur_when_not_eq_zero(
 pub fn branch(
     vm: &mut VM,
     option_block_index: Option<usize>,
-    branch_targets: &[BranchTarget],
-    default_branch_target: &BranchTarget,
 ) -> Result<ControlResult, EngineError> {
-    let branch_index_value = vm.stack.pop();
-
-    let branch_index = if let Value::I32(branch_index) = branch_index_value {
-        branch_index as usize
-    } else {
-        return Err(make_operand_data_types_mismatch_engine_error(
-            "br_table",
-            vec![ValueType::I32],
-            vec![&branch_index_value],
-        ));
-    };
-
-    let actual_branch_target = if branch_index < branch_targets.len() {
-        branch_targets[branch_index].clone()
-    } else {
-        default_branch_target.to_owned()
+    let branch_index = vm.stack.pop_i32()? as u32 as usize;
+
+    // 跳转目标列表保存在模块的原指令里，这里只复制被选中的一个目标，
+    // 以免每次执行都复制整个列表。
+    let actual_branch_target = {
+        let vm_module = &vm.resource.vm_modules[vm.status.vm_module_index];
+        match &vm_module.instructions[vm.status.address] {
+            Instruction::Control(Control::Branch {
+                branch_targets,
+                default_branch_target,
+                ..
+            }) => branch_targets
+                .get(branch_index)
+                .unwrap_or(default_branch_target)
+                .clone(),
+            _ => unreachable!("should be the br_table instruction"),
+        }
     };
 
     match actual_branch_target {
@@ -13,26 +13,24 @@
 //! - f32.const
 //! - f64.const
 
-use anvm_ast::types::Value;
-
 use crate::{error::EngineError, vm::VM};
 
 pub fn i32_const(vm: &mut VM, value: i32) -> Result<(), EngineError> {
-    vm.stack.push(Value::I32(value));
+    vm.stack.push_i32(value);
     Ok(())
 }
 
 pub fn i64_const(vm: &mut VM, value: i64) -> Result<(), EngineError> {
-    vm.stack.push(Value::I64(value));
+    vm.stack.push_i64(value);
     Ok(())
 }
 
 pub fn f32_const(vm: &mut VM, value: f32) -> Result<(), EngineError> {
-    vm.stack.push(Value::F32(value));
+    vm.stack.push_f32(value);
     Ok(())
 }
 
 pub fn f64_const(vm: &mut VM, value: f64) -> Result<(), EngineError> {
-    vm.stack.push(Value::F64(value));
+    vm.stack.push_f64(value);
     Ok(())
 }
@@ -8,15 +8,13 @@
 //!
 //! ## end 指令
 
-use anvm_ast::{
-    instruction::BlockType,
-    types::{check_value_types, ValueType, ValueTypeCheckError},
-};
+use anvm_ast::instruction::BlockType;
 
 use crate::{
-    error::{EngineError, InvalidOperation, TypeMismatch},
-    vm::{INITIAL_FRAME_POINTER, VM},
-    vm_stack::INFO_SEGMENT_ITEM_COUNT,
+    error::{EngineError, InvalidOperation},
+    trap::{make_trap_engine_error, TrapCode},
+    vm::VM,
+    vm_stack::FrameKind,
 };
 
 pub enum ControlResult {
@@ -68,31 +66,27 @@ pub fn process_end(
     let vm_module_index = vm.status.vm_module_index;
     let function_index = vm.status.function_index;
 
-    // 如果 fp 和 lp 的值相同，则说明当前是调用帧，否则则是流程控制的结构块帧
-    let frame_pointer = vm.status.frame_pointer;
-    let local_pointer = vm.status.local_pointer;
-    let is_call_frame = frame_pointer == local_pointer;
-
-    // 获取当前帧的返回值类型
-    let result_types = {
+    // 获取当前帧的返回值的数量
+    let results_count = {
         match frame_type {
-            BlockType::ResultEmpty => vec![],
-            BlockType::ResultI32 => vec![ValueType::I32],
-            BlockType::ResultI64 => vec![ValueType::I64],
-            BlockType::ResultF32 => vec![ValueType::F32],
-            BlockType::ResultF64 => vec![ValueType::F64],
+            BlockType::ResultEmpty => 0,
+            BlockType::ResultI32
+            | BlockType::ResultI64
+            | BlockType::ResultF32
+            | BlockType::ResultF64
+            | BlockType::ResultFuncRef
+            | BlockType::ResultExternRef => 1,
             BlockType::TypeIndex(type_index) => {
                 let vm_module = &vm.resource.vm_modules[vm_module_index];
                 let function_type = &vm_module.function_types[*type_index as usize];
-                function_type.results.clone()
+                function_type.results.len()
             }
         }
     };
 
     // 判断操作数是否足够当前函数或结构块用于返回
-    let results_count = result_types.len();
-    let stack_size = vm.stack.get_size();
-    let operands_count = stack_size - vm.status.base_pointer - INFO_SEGMENT_ITEM_COUNT;
+    // 返回值的数据类型已经由验证器检查过，所以这里只检查数量
+    let operands_count = vm.get_operands_count();
     if operands_count < results_count {
         if let Some(block_index) = option_block_index {
             return Err(EngineError::InvalidOperation(
@@ -116,62 +110,34 @@ pub fn process_end(
         }
     }
 
-    // 判断返回值的数据类型
-    let results = vm.stack.peek_values(results_count);
-    match check_value_types(results, &result_types) {
-        Err(ValueTypeCheckError::LengthMismatch) => unreachable!("argument count should be match"),
-        Err(ValueTypeCheckError::DataTypeMismatch(result_index)) => {
-            if let Some(block_index) = option_block_index {
-                return Err(EngineError::TypeMismatch(
-                    TypeMismatch::BlockResultTypeMismatch {
-                        vm_module_index,
-                        function_index,
-                        block_index: *block_index,
-                        result_index,
-                        result_type: result_types[result_index].clone(),
-                        value_type: results[result_index].get_type(),
-                    },
-                ));
-            } else {
-                return Err(EngineError::TypeMismatch(
-                    TypeMismatch::FunctionResultTypeMismatch {
-                        vm_module_index,
-                        function_index,
-                        result_index,
-                        result_type: result_types[result_index].clone(),
-                        value_type: results[result_index].get_type(),
-                    },
-                ));
-            }
-        }
-        _ => {
-            // pass
-        }
-    }
-
-    let (vm_module_index, function_index, frame_type, address) = vm.pop_frame(results_count);
+    let frame = vm.pop_frame(results_count);
 
-    // 上一句 vm.pop_frame() 调用已经更新了部分 vm.status。
-    // 如果 vm.status.frame_pointer 的值等于 0，说明刚才弹出的栈帧是
-    // 首个函数调用的栈帧，也就是说，当这个帧弹出之后，所有栈帧都已经弹出，
-    // 意味着所有函数调用已经执行完毕，即程序已经结束。
-    let is_program_end = vm.status.frame_pointer == INITIAL_FRAME_POINTER;
+    // 宿主调用帧是由 VM::eval_function_by_index 等方法压入的首个调用帧，
+    // 当这个帧弹出之后，意味着这次宿主调用已经执行完毕，即程序已经结束。
+    //
+    // 因为本地函数在执行期间也可能会调用模块内部的函数，所以
+    // 这里需要把 VM 的状态恢复为宿主调用之前的值。
+    if frame.kind == FrameKind::HostCall {
+        let status = &mut vm.status;
+        status.vm_module_index = frame.return_vm_module_index;
+        status.function_index = frame.return_function_index;
+        status.frame_type = frame.return_frame_type;
+        status.address = frame.return_address;
 
-    if is_program_end {
         Ok(ControlResult::ProgramEnd)
     } else {
         Ok(ControlResult::PopStackFrame {
-            is_call_frame: is_call_frame,
-            vm_module_index,
-            function_index,
-            frame_type,
-            address,
+            is_call_frame: frame.is_call_frame(),
+            vm_module_index: frame.return_vm_module_index,
+            function_index: frame.return_function_index,
+            frame_type: frame.return_frame_type,
+            address: frame.return_address,
         })
     }
 }
 
-pub fn process_unreachable(_vm: &mut VM) -> Result<ControlResult, EngineError> {
-    Err(EngineError::InvalidOperation(InvalidOperation::Unreachable))
+pub fn process_unreachable(vm: &mut VM) -> Result<ControlResult, EngineError> {
+    Err(make_trap_engine_error(vm, TrapCode::Unreachable))
 }
 
 pub fn process_nop(_vm: &mut VM) -> Result<ControlResult, EngineError> {
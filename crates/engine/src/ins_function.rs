@@ -126,20 +126,15 @@
 //! - f1
 //! - f0  <-- 栈底
 
-use anvm_ast::{
-    instruction::BlockType,
-    types::{check_types, check_value_types, Value, ValueType, ValueTypeCheckError},
-};
+use anvm_ast::{instruction::BlockType, types::check_types};
 
 use crate::{
-    error::{
-        make_operand_data_types_mismatch_engine_error, EngineError, InvalidOperation,
-        ObjectNotFound, TypeMismatch, Unsupported,
-    },
+    error::{EngineError, InvalidOperation, NativeError, NativeTerminate},
     ins_control::ControlResult,
     object::FunctionItem,
+    trap::{make_trap_engine_error, TrapCode},
     vm::VM,
-    vm_stack::INFO_SEGMENT_ITEM_COUNT,
+    vm_stack::FrameKind,
 };
 
 pub fn call(
@@ -160,8 +155,7 @@ pub fn call(
 
     // 判断操作数是否足够当前函数或结构块用于返回
     let parameters_count = parameter_types.len();
-    let stack_size = vm.stack.get_size();
-    let operands_count = stack_size - vm.status.base_pointer - INFO_SEGMENT_ITEM_COUNT;
+    let operands_count = vm.get_operands_count();
     if operands_count < parameters_count {
         return Err(EngineError::InvalidOperation(
             InvalidOperation::NotEnoughOperandForFunctionCall {
@@ -173,31 +167,17 @@ pub fn call(
         ));
     }
 
-    let arguments = vm.stack.peek_values(parameters_count);
-
-    // 核对实参的数据类型和数量
-    match check_value_types(arguments, parameter_types) {
-        Err(ValueTypeCheckError::LengthMismatch) => unreachable!("argument count should be match"),
-        Err(ValueTypeCheckError::DataTypeMismatch(parameter_index)) => {
-            return Err(EngineError::TypeMismatch(
-                TypeMismatch::FunctionCallArgumentTypeMismatch {
-                    vm_module_index,
-                    function_index,
-                    parameter_index,
-                    parameter_type: parameter_types[parameter_index].clone(),
-                    value_type: arguments[parameter_index].get_type(),
-                },
-            ));
-        }
-        _ => {
-            // pass
-        }
-    }
+    // 实参的数据类型已经由验证器检查过
 
     // 压入调用栈
     // 返回地址应该是 `call 指令` 的下一个指令
     let return_address = vm.status.address + 1;
-    vm.push_call_frame(parameters_count, &local_variable_types, return_address);
+    vm.push_call_frame(
+        FrameKind::Call,
+        parameters_count,
+        &local_variable_types,
+        return_address,
+    )?;
 
     // 返回新的状态信息，让调用者更新虚拟机状态
     let control_result = ControlResult::PushStackFrame {
@@ -244,15 +224,14 @@ pub fn call_native(
     let (parameter_types, native_function) = {
         let native_module = &vm.resource.native_modules[native_module_index];
         let function_type = &native_module.function_types[type_index];
-        let native_function = native_module.native_functions[function_index];
+        let native_function = native_module.native_functions[function_index].clone();
 
         (function_type.params.to_owned(), native_function)
     };
 
     // 判断操作数是否足够当前函数的调用
     let parameters_count = parameter_types.len();
-    let stack_size = vm.stack.get_size();
-    let operands_count = stack_size - vm.status.base_pointer - INFO_SEGMENT_ITEM_COUNT;
+    let operands_count = vm.get_operands_count();
     if operands_count < parameters_count {
         return Err(EngineError::InvalidOperation(
             InvalidOperation::NotEnoughOperandForNativeFunctionCall {
@@ -264,29 +243,18 @@ pub fn call_native(
         ));
     }
 
-    // 从栈弹出数据，作为函数调用的参数
-    let arguments = vm.stack.pop_values(parameters_count);
-
-    // 核对实参的数据类型和数量
-    match check_value_types(&arguments, &parameter_types) {
-        Err(ValueTypeCheckError::DataTypeMismatch(parameter_index)) => {
-            return Err(EngineError::TypeMismatch(
-                TypeMismatch::NativeFunctionCallArgumentTypeMismatch {
-                    native_module_index,
-                    function_index,
-                    parameter_index,
-                    parameter_type: parameter_types[parameter_index].clone(),
-                    value_type: arguments[parameter_index].get_type(),
-                },
-            ));
-        }
-        _ => {
-            // pass
-        }
-    }
+    // 从栈弹出数据，按照本地函数的参数类型转换为数值，作为函数调用的参数
+    let arguments = vm.stack.pop_values(&parameter_types)?;
 
     // 调用本地函数
-    let result = native_function(vm, native_module_index, &arguments);
+    let result = native_function
+        .call(vm, native_module_index, &arguments)
+        .ok_or(EngineError::InvalidOperation(
+            InvalidOperation::ReentrantNativeFunctionCall {
+                native_module_index,
+                function_index,
+            },
+        ))?;
 
     match result {
         Ok(result_values) => {
@@ -297,6 +265,10 @@ pub fn call_native(
             // 执行下一个指令即可。
             Ok(ControlResult::Sequence)
         }
+        Err(NativeTerminate {
+            native_error: NativeError::Trap(trap_code),
+            ..
+        }) => Err(make_trap_engine_error(vm, trap_code)),
         Err(e) => Err(EngineError::NativeTerminate(e)),
     }
 }
@@ -306,110 +278,60 @@ pub fn call_indirect(
     type_index: usize,
     table_index: usize,
 ) -> Result<ControlResult, EngineError> {
-    if table_index != 0 {
-        return Err(EngineError::Unsupported(
-            Unsupported::UnsupportedMultipleTable,
-        ));
-    }
-
-    let element_index = {
-        let element_index_value = vm.stack.pop();
-        match element_index_value {
-            Value::I32(index) => index as usize,
-            _ => {
-                return Err(make_operand_data_types_mismatch_engine_error(
-                    "call_indirect",
-                    vec![ValueType::I32],
-                    vec![&element_index_value],
-                ));
-            }
-        }
-    };
+    let element_index = vm.stack.pop_i32()? as u32 as usize;
 
-    let (/* vm_module_index, function_index, */ function_item, expected_function_type) = {
+    let option_function_item_and_type = {
         let vm_module_index = vm.status.vm_module_index;
         let vm_module = &vm.resource.vm_modules[vm_module_index];
-        let table_index = vm_module.table_index;
-        let table = &vm.resource.tables[table_index];
+        let instance_table_index = vm_module.table_indexes[table_index];
+        let table = &vm.resource.tables[instance_table_index];
 
-        let element_item = table.get_element(element_index)?;
-        let function_index = match element_item {
-            Some(index) => index as usize,
-            _ => {
-                return Err(EngineError::ObjectNotFound(
-                    ObjectNotFound::ElementItemNotFound(
-                        vm_module_index,
-                        table_index,
-                        element_index,
-                    ),
-                ))
-            }
-        };
-
-        let function_item = &vm_module.function_items[function_index];
-        let expected_function_type = &vm_module.function_types[type_index];
+        // 元素的索引超出范围、元素为空，或者元素不是有效的函数引用，
+        // 都属于 `undefined element` 陷阱
+        match table.get_element(element_index) {
+            Ok(Some(function_reference)) => vm
+                .resource
+                .resolve_function_reference(function_reference)
+                .map(|function_item| {
+                    let expected_function_type = &vm_module.function_types[type_index];
+                    (function_item.to_owned(), expected_function_type.to_owned())
+                }),
+            _ => None,
+        }
+    };
 
-        (function_item.to_owned(), expected_function_type.to_owned())
+    let (function_item, expected_function_type) = match option_function_item_and_type {
+        Some(item_and_type) => item_and_type,
+        None => return Err(make_trap_engine_error(vm, TrapCode::UndefinedElement)),
     };
 
     // 核对函数的签名
-    match &function_item {
+    let function_type = match &function_item {
         FunctionItem::Normal {
             vm_module_index,
             type_index,
-            function_index,
-            internal_function_index: _,
-            start_address: _,
-            end_address: _,
-            block_items: _,
+            ..
         } => {
             let vm_module = &vm.resource.vm_modules[*vm_module_index];
-            let function_type = &vm_module.function_types[*type_index];
-
-            if let Err(_) = check_types(&expected_function_type.params, &function_type.params) {
-                return Err(EngineError::TypeMismatch(
-                    TypeMismatch::DynamicCallFunctionTypeMismatch(
-                        *vm_module_index,
-                        *function_index,
-                    ),
-                ));
-            }
-
-            if let Err(_) = check_types(&expected_function_type.results, &function_type.results) {
-                return Err(EngineError::TypeMismatch(
-                    TypeMismatch::DynamicCallFunctionTypeMismatch(
-                        *vm_module_index,
-                        *function_index,
-                    ),
-                ));
-            }
+            &vm_module.function_types[*type_index]
         }
         FunctionItem::Native {
             native_module_index,
             type_index,
-            function_index,
+            ..
         } => {
             let native_module = &vm.resource.native_modules[*native_module_index];
-            let function_type = &native_module.function_types[*type_index];
-
-            if let Err(_) = check_types(&expected_function_type.params, &function_type.params) {
-                return Err(EngineError::TypeMismatch(
-                    TypeMismatch::DynamicCallNativeFunctionTypeMismatch(
-                        *native_module_index,
-                        *function_index,
-                    ),
-                ));
-            }
-
-            if let Err(_) = check_types(&expected_function_type.results, &function_type.results) {
-                return Err(EngineError::TypeMismatch(
-                    TypeMismatch::DynamicCallNativeFunctionTypeMismatch(
-                        *native_module_index,
-                        *function_index,
-                    ),
-                ));
-            }
+            &native_module.function_types[*type_index]
         }
+    };
+
+    if check_types(&expected_function_type.params, &function_type.params).is_err()
+        || check_types(&expected_function_type.results, &function_type.results).is_err()
+    {
+        return Err(make_trap_engine_error(
+            vm,
+            TrapCode::IndirectCallTypeMismatch,
+        ));
     }
 
     // 调用处理函数
@@ -17,8 +17,19 @@
 //!   成功则返回旧的页面数量
 //!   失败（比如超出限制值的 max）则返回 -1:uint32
 //!
-//! 内存还有其他几个操作：
+//! ## 批量内存指令
 //!
+//! - memory.init data_idx:uint32 mem_block_idx:uint32
+//! - data.drop data_idx:uint32
+//! - memory.copy dest_mem_block_idx:uint32 source_mem_block_idx:uint32
+//! - memory.fill mem_block_idx:uint32
+//!
+//! 除了 `data.drop`，其余指令都从操作数栈依次弹出 3 个 uint32，分别是
+//! 长度（n）、源地址或者填充值（s）以及目标地址（d），即在压入操作数时的顺序为 d, s, n。
+//! 如果访问的范围超出了内存块（或者数据段）的范围，则抛出 `out of bounds memory access` 陷阱，
+//! 注意即使长度为 0，源地址或者目标地址超出了范围也会抛出陷阱。
+//!
+//! 其中：
 //! - The `memory.fill` instruction sets all values in a region to a given byte.
 //! - The `memory.copy` instruction copies data from a source memory region to
 //!   a possibly overlapping destination region.
@@ -35,6 +46,10 @@
 //! 二进制格式
 //!
 //! i32.load align:uint32 offset:uint32
+//! i32.load align_with_flag:uint32 mem_block_idx:uint32 offset:uint32
+//!
+//! 当 align 的第 6 位为 1 时，表示访问的是指定索引的内存块（multi-memory），
+//! 否则访问的是索引为 0 的内存块。
 //!
 //! 文本格式
 //!
@@ -59,7 +74,8 @@
 //! 加载过程：
 //!
 //! 1. 从操作数栈弹出一个 uint32，作为目标地址（address）
-//! 2. 计算有效地址
+//! 2. 计算有效地址，如果有效地址加上数据的字节数超出了内存块的范围，
+//!    则抛出 `out of bounds memory access` 陷阱
 //! 3. 将指定地址内存的数值（多个字节使用小端格式 litte-endian 编码）压入操作数栈
 //!
 //! 指令列表
@@ -91,7 +107,7 @@
 //!
 //! 1. 从操作数栈弹出一个操作数，这个操作数将作为被存储的数据（data）
 //! 2. 从操作数栈弹出一个 uint32，作为目标地址（addr）
-//! 3. 计算有效地址
+//! 3. 计算有效地址，同样需要检查访问的范围
 //! 4. 将 data 写入指定地址的内存
 //!
 //! 指令列表
@@ -108,587 +124,410 @@
 //! - f32.store
 //! - f64.store
 
-use anvm_ast::{instruction::MemoryArgument, types::Value, types::ValueType};
+use anvm_ast::instruction::MemoryArgument;
 
 use crate::{
-    error::{EngineError, TypeMismatch, Unsupported, make_operand_data_types_mismatch_engine_error},
+    error::EngineError,
+    trap::{make_trap_engine_error, TrapCode},
     vm::VM,
     vm_memory::VMMemory,
-    vm_stack::VMStack,
+    vm_stack::{StackUnderflow, VMStack},
 };
 
 pub fn memory_size(vm: &mut VM, memory_block_index: u32) -> Result<(), EngineError> {
-    if memory_block_index != 0 {
-        return Err(EngineError::Unsupported(
-            Unsupported::UnsupportedMultipleMemoryBlock,
-        ));
-    }
-
-    let instance_memory_block_index =
-        vm.resource.vm_modules[vm.status.vm_module_index].memory_index;
+    let instance_memory_block_index = get_instance_memory_block_index(vm, memory_block_index);
     let memory_block = &mut vm.resource.memory_blocks[instance_memory_block_index];
     let page_count = memory_block.get_page_count();
 
     let stack = &mut vm.stack;
-    stack.push(Value::I32(page_count as i32));
+    stack.push_i32(page_count as i32);
 
     Ok(())
 }
 
 pub fn memory_grow(vm: &mut VM, memory_block_index: u32) -> Result<(), EngineError> {
-    if memory_block_index != 0 {
-        return Err(EngineError::Unsupported(
-            Unsupported::UnsupportedMultipleMemoryBlock,
-        ));
-    }
+    let instance_memory_block_index = get_instance_memory_block_index(vm, memory_block_index);
 
     let stack = &mut vm.stack;
-    let increase_number = stack.pop();
+    let increase_number = stack.pop_i32()?;
 
-    let instance_memory_block_index =
-        vm.resource.vm_modules[vm.status.vm_module_index].memory_index;
     let memory_block = &mut vm.resource.memory_blocks[instance_memory_block_index];
-
-    if let Value::I32(value) = increase_number {
-        let result = memory_block.increase_page(value as u32);
-        match result {
-            Ok(previous_page_count) => {
-                stack.push(Value::I32(previous_page_count as i32));
-            }
-            _ => {
-                stack.push(Value::I32(-1));
-            }
+    let result = memory_block.increase_page(increase_number as u32);
+    match result {
+        Ok(previous_page_count) => {
+            stack.push_i32(previous_page_count as i32);
         }
+        _ => {
+            stack.push_i32(-1);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn memory_init(
+    vm: &mut VM,
+    data_index: u32,
+    memory_block_index: u32,
+) -> Result<(), EngineError> {
+    let (dest_address, source_offset, count) = pop_bulk_memory_operands(vm)?;
+
+    let instance_memory_block_index = get_instance_memory_block_index(vm, memory_block_index);
+    let data_segment_index =
+        vm.resource.vm_modules[vm.status.vm_module_index].data_segment_indexes[data_index as usize];
+
+    let data = vm.resource.data_segments[data_segment_index].get_data();
+    let byte_count = vm.resource.memory_blocks[instance_memory_block_index].get_byte_count();
+
+    if source_offset + count > data.len() || dest_address + count > byte_count {
+        return Err(make_trap_engine_error(vm, TrapCode::MemoryOutOfBounds));
+    }
+
+    let bytes = data[source_offset..(source_offset + count)].to_vec();
+    vm.resource.memory_blocks[instance_memory_block_index].write_bytes(dest_address, &bytes);
+
+    Ok(())
+}
+
+pub fn data_drop(vm: &mut VM, data_index: u32) -> Result<(), EngineError> {
+    let data_segment_index =
+        vm.resource.vm_modules[vm.status.vm_module_index].data_segment_indexes[data_index as usize];
+    vm.resource.data_segments[data_segment_index].drop_data();
+
+    Ok(())
+}
 
-        Ok(())
+pub fn memory_copy(
+    vm: &mut VM,
+    source_memory_block_index: u32,
+    dest_memory_block_index: u32,
+) -> Result<(), EngineError> {
+    let (dest_address, source_address, count) = pop_bulk_memory_operands(vm)?;
+
+    let instance_source_memory_block_index =
+        get_instance_memory_block_index(vm, source_memory_block_index);
+    let instance_dest_memory_block_index =
+        get_instance_memory_block_index(vm, dest_memory_block_index);
+
+    let source_byte_count =
+        vm.resource.memory_blocks[instance_source_memory_block_index].get_byte_count();
+    let dest_byte_count =
+        vm.resource.memory_blocks[instance_dest_memory_block_index].get_byte_count();
+
+    if source_address + count > source_byte_count || dest_address + count > dest_byte_count {
+        return Err(make_trap_engine_error(vm, TrapCode::MemoryOutOfBounds));
+    }
+
+    if instance_source_memory_block_index == instance_dest_memory_block_index {
+        vm.resource.memory_blocks[instance_dest_memory_block_index].copy_bytes(
+            source_address,
+            dest_address,
+            count,
+        );
     } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "memory.grow",
-            vec![ValueType::I32],
-            vec![&increase_number],
-        ))
+        // 在不同的内存块之间复制数据
+        let bytes = vm.resource.memory_blocks[instance_source_memory_block_index]
+            .read_bytes(source_address, count)
+            .to_vec();
+        vm.resource.memory_blocks[instance_dest_memory_block_index]
+            .write_bytes(dest_address, &bytes);
     }
+
+    Ok(())
+}
+
+pub fn memory_fill(vm: &mut VM, memory_block_index: u32) -> Result<(), EngineError> {
+    let (dest_address, value, count) = pop_bulk_memory_operands(vm)?;
+
+    let instance_memory_block_index = get_instance_memory_block_index(vm, memory_block_index);
+    let byte_count = vm.resource.memory_blocks[instance_memory_block_index].get_byte_count();
+
+    if dest_address + count > byte_count {
+        return Err(make_trap_engine_error(vm, TrapCode::MemoryOutOfBounds));
+    }
+
+    // 填充值只取低 8 位
+    vm.resource.memory_blocks[instance_memory_block_index].fill_bytes(
+        dest_address,
+        value as u8,
+        count,
+    );
+
+    Ok(())
+}
+
+/// 获取当前模块的指定内存块在 VM 资源里的索引
+fn get_instance_memory_block_index(vm: &VM, memory_block_index: u32) -> usize {
+    vm.resource.vm_modules[vm.status.vm_module_index].memory_block_indexes
+        [memory_block_index as usize]
+}
+
+/// 从操作数栈弹出批量内存指令的 3 个 uint32 操作数
+///
+/// 返回 (d, s, n)，因为 d, s, n 均为 uint32，所以它们相加不会超出 usize 的范围。
+fn pop_bulk_memory_operands(vm: &mut VM) -> Result<(usize, usize, usize), StackUnderflow> {
+    let stack = &mut vm.stack;
+    let count = stack.pop_i32()?;
+    let source = stack.pop_i32()?;
+    let dest = stack.pop_i32()?;
+
+    Ok((
+        dest as u32 as usize,
+        source as u32 as usize,
+        count as u32 as usize,
+    ))
 }
 
 /// 计算有效内存地址，即内存读写指令最终所访问内存的实际地址。
 ///
 /// 注意，
 /// 因为指令中的 offset 立即数是 u32，而操作数栈弹出的值也是 i32（实际是 u32），
-/// 所以有效地址是一个 33 位（u32 + u32）的无符号整数，实际的值有可能会超出了 u32 的范围。
+/// 所以有效地址是一个 33 位（u32 + u32）的无符号整数，实际的值有可能会超出了 u32 的范围，
+/// 因此需要先转换为 usize（64 位）再相加，以免发生回绕。
 fn get_effective_address(immediate_offset: u32, operand_address: i32) -> usize {
-    (immediate_offset + operand_address as u32) as usize
+    immediate_offset as usize + operand_address as u32 as usize
 }
 
+/// 获取加载指令所需的内存块、操作数栈以及有效地址
+///
+/// 如果访问的范围超出了内存块的范围，则返回 `out of bounds memory access` 陷阱。
 fn get_memory_load_access_meterial<'a>(
     vm: &'a mut VM,
     memory_args: &MemoryArgument,
-) -> Result<(&'a mut VMMemory, &'a mut VMStack, usize), Value> {
-    let stack = &mut vm.stack;
+    access_length: usize,
+) -> Result<(&'a mut VMMemory, &'a mut VMStack, usize), EngineError> {
     let instance_memory_block_index =
-        vm.resource.vm_modules[vm.status.vm_module_index].memory_index;
-    let memory_block = &mut vm.resource.memory_blocks[instance_memory_block_index];
+        get_instance_memory_block_index(vm, memory_args.memory_block_index);
 
     // MemoryArg 里头的 align 暂时无用
     let immediate_offset = memory_args.offset;
-    let address_value = stack.pop();
+    let address = vm.stack.pop_i32()?;
+    let effective_address = get_effective_address(immediate_offset, address);
 
-    if let Value::I32(address) = address_value {
-        let effective_address = get_effective_address(immediate_offset, address);
-        Ok((memory_block, stack, effective_address))
-    } else {
-        // 参数错误时，返回参数值
-        Err(address_value)
+    if !vm.resource.memory_blocks[instance_memory_block_index]
+        .is_valid_range(effective_address, access_length)
+    {
+        return Err(make_trap_engine_error(vm, TrapCode::MemoryOutOfBounds));
     }
+
+    let stack = &mut vm.stack;
+    let memory_block = &mut vm.resource.memory_blocks[instance_memory_block_index];
+    Ok((memory_block, stack, effective_address))
 }
 
+/// 获取存储指令所需的内存块以及有效地址
+///
+/// 调用此函数之前，存储指令应该已经从栈顶弹出待储存的数据。
+///
+/// 如果访问的范围超出了内存块的范围，则返回 `out of bounds memory access` 陷阱。
 fn get_memory_store_access_meterial<'a>(
     vm: &'a mut VM,
     memory_args: &MemoryArgument,
-) -> Result<(&'a mut VMMemory, usize, Value), (Value, Value)> {
-    let stack = &mut vm.stack;
+    access_length: usize,
+) -> Result<(&'a mut VMMemory, usize), EngineError> {
     let instance_memory_block_index =
-        vm.resource.vm_modules[vm.status.vm_module_index].memory_index;
-    let memory_block = &mut vm.resource.memory_blocks[instance_memory_block_index];
-
-    // 待储存的数据
-    let data_value = stack.pop();
+        get_instance_memory_block_index(vm, memory_args.memory_block_index);
 
     // MemoryArg 里头的 align 暂时无用
     let immediate_offset = memory_args.offset;
-    let address_value = stack.pop();
+    let address = vm.stack.pop_i32()?;
+    let effective_address = get_effective_address(immediate_offset, address);
 
-    if let Value::I32(address) = address_value {
-        let effective_address = get_effective_address(immediate_offset, address);
-        Ok((memory_block, effective_address, data_value))
-    } else {
-        // 参数错误时，返回参数值
-        Err((data_value, address_value))
+    if !vm.resource.memory_blocks[instance_memory_block_index]
+        .is_valid_range(effective_address, access_length)
+    {
+        return Err(make_trap_engine_error(vm, TrapCode::MemoryOutOfBounds));
     }
-}
 
-fn make_memory_load_operand_data_type_mismatch_engine_error(
-    instruction_name: &str,
-    address_value: &Value,
-) -> EngineError {
-    EngineError::TypeMismatch(TypeMismatch::OperandDataTypeMismatch(
-        instruction_name.to_owned(),
-        vec![ValueType::I32],
-        vec![address_value.get_type()],
-    ))
-}
-
-fn make_memory_store_operand_data_type_mismatch_engine_error(
-    instruction_name: &str,
-    expected_data_type: ValueType,
-    data_value: &Value,
-) -> EngineError {
-    EngineError::TypeMismatch(TypeMismatch::OperandDataTypeMismatch(
-        instruction_name.to_owned(),
-        vec![expected_data_type, ValueType::I32],
-        vec![data_value.get_type(), ValueType::I32],
-    ))
-}
-
-fn make_memory_store_operand_data_types_mismatch_engine_error(
-    instruction_name: &str,
-    expected_data_type: ValueType,
-    data_value: &Value,
-    address_value: &Value,
-) -> EngineError {
-    EngineError::TypeMismatch(TypeMismatch::OperandDataTypeMismatch(
-        instruction_name.to_owned(),
-        vec![expected_data_type, ValueType::I32],
-        vec![data_value.get_type(), address_value.get_type()],
-    ))
+    let memory_block = &mut vm.resource.memory_blocks[instance_memory_block_index];
+    Ok((memory_block, effective_address))
 }
 
 // i32 load
 
 pub fn i32_load(vm: &mut VM, memory_args: &MemoryArgument) -> Result<(), EngineError> {
-    match get_memory_load_access_meterial(vm, memory_args) {
-        Ok((memory_block, stack, address)) => {
-            let value = memory_block.read_i32(address);
-            stack.push(Value::I32(value));
-            Ok(())
-        }
-        Err(address_value) => Err(make_memory_load_operand_data_type_mismatch_engine_error(
-            "i32_load",
-            &address_value,
-        )),
-    }
+    let (memory_block, stack, address) = get_memory_load_access_meterial(vm, memory_args, 4)?;
+    let value = memory_block.read_i32(address);
+    stack.push_i32(value);
+    Ok(())
 }
 
 pub fn i32_load16_s(vm: &mut VM, memory_args: &MemoryArgument) -> Result<(), EngineError> {
-    match get_memory_load_access_meterial(vm, memory_args) {
-        Ok((memory_block, stack, address)) => {
-            let value = memory_block.read_i16(address);
-            stack.push(Value::I32(value as i32));
-            Ok(())
-        }
-        Err(address_value) => Err(make_memory_load_operand_data_type_mismatch_engine_error(
-            "i32_load16_s",
-            &address_value,
-        )),
-    }
+    let (memory_block, stack, address) = get_memory_load_access_meterial(vm, memory_args, 2)?;
+    let value = memory_block.read_i16(address);
+    stack.push_i32(value as i32);
+    Ok(())
 }
 
 pub fn i32_load16_u(vm: &mut VM, memory_args: &MemoryArgument) -> Result<(), EngineError> {
-    match get_memory_load_access_meterial(vm, memory_args) {
-        Ok((memory_block, stack, address)) => {
-            let value = memory_block.read_i16(address);
-            stack.push(Value::I32((value as u16) as i32));
-            Ok(())
-        }
-        Err(address_value) => Err(make_memory_load_operand_data_type_mismatch_engine_error(
-            "i32_load16_u",
-            &address_value,
-        )),
-    }
+    let (memory_block, stack, address) = get_memory_load_access_meterial(vm, memory_args, 2)?;
+    let value = memory_block.read_i16(address);
+    stack.push_i32((value as u16) as i32);
+    Ok(())
 }
 
 pub fn i32_load8_s(vm: &mut VM, memory_args: &MemoryArgument) -> Result<(), EngineError> {
-    match get_memory_load_access_meterial(vm, memory_args) {
-        Ok((memory_block, stack, address)) => {
-            let value = memory_block.read_i8(address);
-            stack.push(Value::I32(value as i32));
-            Ok(())
-        }
-        Err(address_value) => Err(make_memory_load_operand_data_type_mismatch_engine_error(
-            "i32_load8_s",
-            &address_value,
-        )),
-    }
+    let (memory_block, stack, address) = get_memory_load_access_meterial(vm, memory_args, 1)?;
+    let value = memory_block.read_i8(address);
+    stack.push_i32(value as i32);
+    Ok(())
 }
 
 pub fn i32_load8_u(vm: &mut VM, memory_args: &MemoryArgument) -> Result<(), EngineError> {
-    match get_memory_load_access_meterial(vm, memory_args) {
-        Ok((memory_block, stack, address)) => {
-            let value = memory_block.read_i8(address);
-            stack.push(Value::I32((value as u8) as i32));
-            Ok(())
-        }
-        Err(address_value) => Err(make_memory_load_operand_data_type_mismatch_engine_error(
-            "i32_load8_u",
-            &address_value,
-        )),
-    }
+    let (memory_block, stack, address) = get_memory_load_access_meterial(vm, memory_args, 1)?;
+    let value = memory_block.read_i8(address);
+    stack.push_i32((value as u8) as i32);
+    Ok(())
 }
 
 // i64 load
 
 pub fn i64_load(vm: &mut VM, memory_args: &MemoryArgument) -> Result<(), EngineError> {
-    match get_memory_load_access_meterial(vm, memory_args) {
-        Ok((memory_block, stack, address)) => {
-            let value = memory_block.read_i64(address);
-            stack.push(Value::I64(value));
-            Ok(())
-        }
-        Err(address_value) => Err(make_memory_load_operand_data_type_mismatch_engine_error(
-            "i64_load",
-            &address_value,
-        )),
-    }
+    let (memory_block, stack, address) = get_memory_load_access_meterial(vm, memory_args, 8)?;
+    let value = memory_block.read_i64(address);
+    stack.push_i64(value);
+    Ok(())
 }
 
 pub fn i64_load32_s(vm: &mut VM, memory_args: &MemoryArgument) -> Result<(), EngineError> {
-    match get_memory_load_access_meterial(vm, memory_args) {
-        Ok((memory_block, stack, address)) => {
-            let value = memory_block.read_i32(address);
-            stack.push(Value::I64(value as i64));
-            Ok(())
-        }
-        Err(address_value) => Err(make_memory_load_operand_data_type_mismatch_engine_error(
-            "i64_load32_s",
-            &address_value,
-        )),
-    }
+    let (memory_block, stack, address) = get_memory_load_access_meterial(vm, memory_args, 4)?;
+    let value = memory_block.read_i32(address);
+    stack.push_i64(value as i64);
+    Ok(())
 }
 
 pub fn i64_load32_u(vm: &mut VM, memory_args: &MemoryArgument) -> Result<(), EngineError> {
-    match get_memory_load_access_meterial(vm, memory_args) {
-        Ok((memory_block, stack, address)) => {
-            let value = memory_block.read_i32(address);
-            stack.push(Value::I64((value as u32) as i64));
-            Ok(())
-        }
-        Err(address_value) => Err(make_memory_load_operand_data_type_mismatch_engine_error(
-            "i64_load32_u",
-            &address_value,
-        )),
-    }
+    let (memory_block, stack, address) = get_memory_load_access_meterial(vm, memory_args, 4)?;
+    let value = memory_block.read_i32(address);
+    stack.push_i64((value as u32) as i64);
+    Ok(())
 }
 
 pub fn i64_load16_s(vm: &mut VM, memory_args: &MemoryArgument) -> Result<(), EngineError> {
-    match get_memory_load_access_meterial(vm, memory_args) {
-        Ok((memory_block, stack, address)) => {
-            let value = memory_block.read_i16(address);
-            stack.push(Value::I64(value as i64));
-            Ok(())
-        }
-        Err(address_value) => Err(make_memory_load_operand_data_type_mismatch_engine_error(
-            "i64_load16_s",
-            &address_value,
-        )),
-    }
+    let (memory_block, stack, address) = get_memory_load_access_meterial(vm, memory_args, 2)?;
+    let value = memory_block.read_i16(address);
+    stack.push_i64(value as i64);
+    Ok(())
 }
 
 pub fn i64_load16_u(vm: &mut VM, memory_args: &MemoryArgument) -> Result<(), EngineError> {
-    match get_memory_load_access_meterial(vm, memory_args) {
-        Ok((memory_block, stack, address)) => {
-            let value = memory_block.read_i16(address);
-            stack.push(Value::I64((value as u16) as i64));
-            Ok(())
-        }
-        Err(address_value) => Err(make_memory_load_operand_data_type_mismatch_engine_error(
-            "i64_load16_u",
-            &address_value,
-        )),
-    }
+    let (memory_block, stack, address) = get_memory_load_access_meterial(vm, memory_args, 2)?;
+    let value = memory_block.read_i16(address);
+    stack.push_i64((value as u16) as i64);
+    Ok(())
 }
 
 pub fn i64_load8_s(vm: &mut VM, memory_args: &MemoryArgument) -> Result<(), EngineError> {
-    match get_memory_load_access_meterial(vm, memory_args) {
-        Ok((memory_block, stack, address)) => {
-            let value = memory_block.read_i8(address);
-            stack.push(Value::I64(value as i64));
-            Ok(())
-        }
-        Err(address_value) => Err(make_memory_load_operand_data_type_mismatch_engine_error(
-            "i64_load8_s",
-            &address_value,
-        )),
-    }
+    let (memory_block, stack, address) = get_memory_load_access_meterial(vm, memory_args, 1)?;
+    let value = memory_block.read_i8(address);
+    stack.push_i64(value as i64);
+    Ok(())
 }
 
 pub fn i64_load8_u(vm: &mut VM, memory_args: &MemoryArgument) -> Result<(), EngineError> {
-    match get_memory_load_access_meterial(vm, memory_args) {
-        Ok((memory_block, stack, address)) => {
-            let value = memory_block.read_i8(address);
-            stack.push(Value::I64((value as u8) as i64));
-            Ok(())
-        }
-        Err(address_value) => Err(make_memory_load_operand_data_type_mismatch_engine_error(
-            "i64_load8_u",
-            &address_value,
-        )),
-    }
+    let (memory_block, stack, address) = get_memory_load_access_meterial(vm, memory_args, 1)?;
+    let value = memory_block.read_i8(address);
+    stack.push_i64((value as u8) as i64);
+    Ok(())
 }
 
 // float load
 
 pub fn f32_load(vm: &mut VM, memory_args: &MemoryArgument) -> Result<(), EngineError> {
-    match get_memory_load_access_meterial(vm, memory_args) {
-        Ok((memory_block, stack, address)) => {
-            let value = memory_block.read_f32(address);
-            stack.push(Value::F32(value));
-            Ok(())
-        }
-        Err(address_value) => Err(make_memory_load_operand_data_type_mismatch_engine_error(
-            "f32_load",
-            &address_value,
-        )),
-    }
+    let (memory_block, stack, address) = get_memory_load_access_meterial(vm, memory_args, 4)?;
+    let value = memory_block.read_f32(address);
+    stack.push_f32(value);
+    Ok(())
 }
 
 pub fn f64_load(vm: &mut VM, memory_args: &MemoryArgument) -> Result<(), EngineError> {
-    match get_memory_load_access_meterial(vm, memory_args) {
-        Ok((memory_block, stack, address)) => {
-            let value = memory_block.read_f64(address);
-            stack.push(Value::F64(value));
-            Ok(())
-        }
-        Err(address_value) => Err(make_memory_load_operand_data_type_mismatch_engine_error(
-            "f64_load",
-            &address_value,
-        )),
-    }
+    let (memory_block, stack, address) = get_memory_load_access_meterial(vm, memory_args, 8)?;
+    let value = memory_block.read_f64(address);
+    stack.push_f64(value);
+    Ok(())
 }
 
 // i32 store
 
 pub fn i32_store(vm: &mut VM, memory_args: &MemoryArgument) -> Result<(), EngineError> {
-    match get_memory_store_access_meterial(vm, memory_args) {
-        Ok((memory_block, address, data_value)) => {
-            if let Value::I32(value) = data_value {
-                memory_block.write_i32(address, value);
-                Ok(())
-            } else {
-                Err(make_memory_store_operand_data_type_mismatch_engine_error(
-                    "i32.store",
-                    ValueType::I32,
-                    &data_value,
-                ))
-            }
-        }
-        Err((data_value, address_value)) => {
-            Err(make_memory_store_operand_data_types_mismatch_engine_error(
-                "i32.store",
-                ValueType::I32,
-                &data_value,
-                &address_value,
-            ))
-        }
-    }
+    // 待储存的数据
+    let value = vm.stack.pop_i32()?;
+
+    let (memory_block, address) = get_memory_store_access_meterial(vm, memory_args, 4)?;
+    memory_block.write_i32(address, value);
+    Ok(())
 }
 
 pub fn i32_store_16(vm: &mut VM, memory_args: &MemoryArgument) -> Result<(), EngineError> {
-    match get_memory_store_access_meterial(vm, memory_args) {
-        Ok((memory_block, address, data_value)) => {
-            if let Value::I32(value) = data_value {
-                memory_block.write_i16(address, value as i16);
-                Ok(())
-            } else {
-                Err(make_memory_store_operand_data_type_mismatch_engine_error(
-                    "i32.store_16",
-                    ValueType::I32,
-                    &data_value,
-                ))
-            }
-        }
-        Err((data_value, address_value)) => {
-            Err(make_memory_store_operand_data_types_mismatch_engine_error(
-                "i32.store_16",
-                ValueType::I32,
-                &data_value,
-                &address_value,
-            ))
-        }
-    }
+    // 待储存的数据
+    let value = vm.stack.pop_i32()?;
+
+    let (memory_block, address) = get_memory_store_access_meterial(vm, memory_args, 2)?;
+    memory_block.write_i16(address, value as i16);
+    Ok(())
 }
 
 pub fn i32_store_8(vm: &mut VM, memory_args: &MemoryArgument) -> Result<(), EngineError> {
-    match get_memory_store_access_meterial(vm, memory_args) {
-        Ok((memory_block, address, data_value)) => {
-            if let Value::I32(value) = data_value {
-                memory_block.write_i8(address, value as i8);
-                Ok(())
-            } else {
-                Err(make_memory_store_operand_data_type_mismatch_engine_error(
-                    "i32.store_8",
-                    ValueType::I32,
-                    &data_value,
-                ))
-            }
-        }
-        Err((data_value, address_value)) => {
-            Err(make_memory_store_operand_data_types_mismatch_engine_error(
-                "i32.store_8",
-                ValueType::I32,
-                &data_value,
-                &address_value,
-            ))
-        }
-    }
+    // 待储存的数据
+    let value = vm.stack.pop_i32()?;
+
+    let (memory_block, address) = get_memory_store_access_meterial(vm, memory_args, 1)?;
+    memory_block.write_i8(address, value as i8);
+    Ok(())
 }
 
 pub fn i64_store(vm: &mut VM, memory_args: &MemoryArgument) -> Result<(), EngineError> {
-    match get_memory_store_access_meterial(vm, memory_args) {
-        Ok((memory_block, address, data_value)) => {
-            if let Value::I64(value) = data_value {
-                memory_block.write_i64(address, value);
-                Ok(())
-            } else {
-                Err(make_memory_store_operand_data_type_mismatch_engine_error(
-                    "i64.store",
-                    ValueType::I64,
-                    &data_value,
-                ))
-            }
-        }
-        Err((data_value, address_value)) => {
-            Err(make_memory_store_operand_data_types_mismatch_engine_error(
-                "i64.store",
-                ValueType::I64,
-                &data_value,
-                &address_value,
-            ))
-        }
-    }
+    // 待储存的数据
+    let value = vm.stack.pop_i64()?;
+
+    let (memory_block, address) = get_memory_store_access_meterial(vm, memory_args, 8)?;
+    memory_block.write_i64(address, value);
+    Ok(())
 }
 
 pub fn i64_store_32(vm: &mut VM, memory_args: &MemoryArgument) -> Result<(), EngineError> {
-    match get_memory_store_access_meterial(vm, memory_args) {
-        Ok((memory_block, address, data_value)) => {
-            if let Value::I64(value) = data_value {
-                memory_block.write_i32(address, value as i32);
-                Ok(())
-            } else {
-                Err(make_memory_store_operand_data_type_mismatch_engine_error(
-                    "i64.store_32",
-                    ValueType::I64,
-                    &data_value,
-                ))
-            }
-        }
-        Err((data_value, address_value)) => {
-            Err(make_memory_store_operand_data_types_mismatch_engine_error(
-                "i64.store_32",
-                ValueType::I64,
-                &data_value,
-                &address_value,
-            ))
-        }
-    }
+    // 待储存的数据
+    let value = vm.stack.pop_i64()?;
+
+    let (memory_block, address) = get_memory_store_access_meterial(vm, memory_args, 4)?;
+    memory_block.write_i32(address, value as i32);
+    Ok(())
 }
 
 pub fn i64_store_16(vm: &mut VM, memory_args: &MemoryArgument) -> Result<(), EngineError> {
-    match get_memory_store_access_meterial(vm, memory_args) {
-        Ok((memory_block, address, data_value)) => {
-            if let Value::I64(value) = data_value {
-                memory_block.write_i16(address, value as i16);
-                Ok(())
-            } else {
-                Err(make_memory_store_operand_data_type_mismatch_engine_error(
-                    "i64.store_16",
-                    ValueType::I64,
-                    &data_value,
-                ))
-            }
-        }
-        Err((data_value, address_value)) => {
-            Err(make_memory_store_operand_data_types_mismatch_engine_error(
-                "i64.store_16",
-                ValueType::I64,
-                &data_value,
-                &address_value,
-            ))
-        }
-    }
+    // 待储存的数据
+    let value = vm.stack.pop_i64()?;
+
+    let (memory_block, address) = get_memory_store_access_meterial(vm, memory_args, 2)?;
+    memory_block.write_i16(address, value as i16);
+    Ok(())
 }
 
 pub fn i64_store_8(vm: &mut VM, memory_args: &MemoryArgument) -> Result<(), EngineError> {
-    match get_memory_store_access_meterial(vm, memory_args) {
-        Ok((memory_block, address, data_value)) => {
-            if let Value::I64(value) = data_value {
-                memory_block.write_i8(address, value as i8);
-                Ok(())
-            } else {
-                Err(make_memory_store_operand_data_type_mismatch_engine_error(
-                    "i64.store_8",
-                    ValueType::I64,
-                    &data_value,
-                ))
-            }
-        }
-        Err((data_value, address_value)) => {
-            Err(make_memory_store_operand_data_types_mismatch_engine_error(
-                "i64.store_8",
-                ValueType::I64,
-                &data_value,
-                &address_value,
-            ))
-        }
-    }
+    // 待储存的数据
+    let value = vm.stack.pop_i64()?;
+
+    let (memory_block, address) = get_memory_store_access_meterial(vm, memory_args, 1)?;
+    memory_block.write_i8(address, value as i8);
+    Ok(())
 }
 
 // float store
 
 pub fn f32_store(vm: &mut VM, memory_args: &MemoryArgument) -> Result<(), EngineError> {
-    match get_memory_store_access_meterial(vm, memory_args) {
-        Ok((memory_block, address, data_value)) => {
-            if let Value::F32(value) = data_value {
-                memory_block.write_f32(address, value);
-                Ok(())
-            } else {
-                Err(make_memory_store_operand_data_type_mismatch_engine_error(
-                    "f32.store",
-                    ValueType::F32,
-                    &data_value,
-                ))
-            }
-        }
-        Err((data_value, address_value)) => {
-            Err(make_memory_store_operand_data_types_mismatch_engine_error(
-                "f32.store",
-                ValueType::F32,
-                &data_value,
-                &address_value,
-            ))
-        }
-    }
+    // 待储存的数据
+    let value = vm.stack.pop_f32()?;
+
+    let (memory_block, address) = get_memory_store_access_meterial(vm, memory_args, 4)?;
+    memory_block.write_f32(address, value);
+    Ok(())
 }
 
 pub fn f64_store(vm: &mut VM, memory_args: &MemoryArgument) -> Result<(), EngineError> {
-    match get_memory_store_access_meterial(vm, memory_args) {
-        Ok((memory_block, address, data_value)) => {
-            if let Value::F64(value) = data_value {
-                memory_block.write_f64(address, value);
-                Ok(())
-            } else {
-                Err(make_memory_store_operand_data_type_mismatch_engine_error(
-                    "f64.store",
-                    ValueType::F64,
-                    &data_value,
-                ))
-            }
-        }
-        Err((data_value, address_value)) => {
-            Err(make_memory_store_operand_data_types_mismatch_engine_error(
-                "f64.store",
-                ValueType::F64,
-                &data_value,
-                &address_value,
-            ))
-        }
-    }
+    // 待储存的数据
+    let value = vm.stack.pop_f64()?;
+
+    let (memory_block, address) = get_memory_store_access_meterial(vm, memory_args, 8)?;
+    memory_block.write_f64(address, value);
+    Ok(())
 }
@@ -48,10 +48,9 @@
 //!
 //! f64 有跟 f32 一样的二元运算指令
 
-use anvm_ast::types::{Value, ValueType};
-
 use crate::{
-    error::{make_operand_data_types_mismatch_engine_error, EngineError},
+    error::EngineError,
+    trap::{make_trap_engine_error, TrapCode},
     vm::VM,
 };
 
@@ -59,775 +58,379 @@ use crate::{
 
 pub fn i32_add(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I32(left), Value::I32(right)) => {
-            stack.push(Value::I32(left + right));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i32.add",
-            vec![ValueType::I32, ValueType::I32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i32()?, stack.pop_i32()?);
+    stack.push_i32(left + right);
+    Ok(())
 }
 
 pub fn i32_sub(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I32(left), Value::I32(right)) => {
-            stack.push(Value::I32(left - right));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i32.sub",
-            vec![ValueType::I32, ValueType::I32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i32()?, stack.pop_i32()?);
+    stack.push_i32(left - right);
+    Ok(())
 }
 
 pub fn i32_mul(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I32(left), Value::I32(right)) => {
-            stack.push(Value::I32(left * right));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i32.mul",
-            vec![ValueType::I32, ValueType::I32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i32()?, stack.pop_i32()?);
+    stack.push_i32(left * right);
+    Ok(())
 }
 
 pub fn i32_div_s(vm: &mut VM) -> Result<(), EngineError> {
-    let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
+    let (right, left) = (vm.stack.pop_i32()?, vm.stack.pop_i32()?);
 
-    match (lhs, rhs) {
-        (Value::I32(left), Value::I32(right)) => {
-            stack.push(Value::I32(left / right));
+    match (left, right) {
+        (_, 0) => Err(make_trap_engine_error(vm, TrapCode::IntegerDivideByZero)),
+        (i32::MIN, -1) => Err(make_trap_engine_error(vm, TrapCode::IntegerOverflow)),
+        _ => {
+            vm.stack.push_i32(left / right);
             Ok(())
         }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i32.div_s",
-            vec![ValueType::I32, ValueType::I32],
-            vec![&lhs, &rhs],
-        )),
     }
 }
 
 pub fn i32_div_u(vm: &mut VM) -> Result<(), EngineError> {
-    let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
+    let (right, left) = (vm.stack.pop_i32()?, vm.stack.pop_i32()?);
 
-    match (lhs, rhs) {
-        (Value::I32(left), Value::I32(right)) => {
-            stack.push(Value::I32(((left as u32) / (right as u32)) as i32));
+    match (left, right) {
+        (_, 0) => Err(make_trap_engine_error(vm, TrapCode::IntegerDivideByZero)),
+        _ => {
+            vm.stack.push_i32(((left as u32) / (right as u32)) as i32);
             Ok(())
         }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i32.div_u",
-            vec![ValueType::I32, ValueType::I32],
-            vec![&lhs, &rhs],
-        )),
     }
 }
 
 pub fn i32_rem_s(vm: &mut VM) -> Result<(), EngineError> {
-    let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
+    let (right, left) = (vm.stack.pop_i32()?, vm.stack.pop_i32()?);
 
-    match (lhs, rhs) {
-        (Value::I32(left), Value::I32(right)) => {
-            stack.push(Value::I32(left % right));
+    match (left, right) {
+        (_, 0) => Err(make_trap_engine_error(vm, TrapCode::IntegerDivideByZero)),
+        _ => {
+            // 规范规定 -2^31 rem_s -1 的结果为 0，而 Rust 的 `%` 运算会溢出
+            vm.stack.push_i32(left.wrapping_rem(right));
             Ok(())
         }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i32.rem_s",
-            vec![ValueType::I32, ValueType::I32],
-            vec![&lhs, &rhs],
-        )),
     }
 }
 
 pub fn i32_rem_u(vm: &mut VM) -> Result<(), EngineError> {
-    let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
+    let (right, left) = (vm.stack.pop_i32()?, vm.stack.pop_i32()?);
 
-    match (lhs, rhs) {
-        (Value::I32(left), Value::I32(right)) => {
-            stack.push(Value::I32(((left as u32) % (right as u32)) as i32));
+    match (left, right) {
+        (_, 0) => Err(make_trap_engine_error(vm, TrapCode::IntegerDivideByZero)),
+        _ => {
+            vm.stack.push_i32(((left as u32) % (right as u32)) as i32);
             Ok(())
         }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i32.rem_u",
-            vec![ValueType::I32, ValueType::I32],
-            vec![&lhs, &rhs],
-        )),
     }
 }
 
 pub fn i32_and(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I32(left), Value::I32(right)) => {
-            stack.push(Value::I32(left & right));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i32.and",
-            vec![ValueType::I32, ValueType::I32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i32()?, stack.pop_i32()?);
+    stack.push_i32(left & right);
+    Ok(())
 }
 
 pub fn i32_or(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I32(left), Value::I32(right)) => {
-            stack.push(Value::I32(left | right));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i32.or",
-            vec![ValueType::I32, ValueType::I32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i32()?, stack.pop_i32()?);
+    stack.push_i32(left | right);
+    Ok(())
 }
 
 pub fn i32_xor(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I32(left), Value::I32(right)) => {
-            stack.push(Value::I32(left ^ right));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i32.xor",
-            vec![ValueType::I32, ValueType::I32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i32()?, stack.pop_i32()?);
+    stack.push_i32(left ^ right);
+    Ok(())
 }
 
 pub fn i32_shl(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I32(left), Value::I32(right)) => {
-            stack.push(Value::I32(left << right));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i32.shl",
-            vec![ValueType::I32, ValueType::I32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i32()?, stack.pop_i32()?);
+    stack.push_i32(left << right);
+    Ok(())
 }
 
 pub fn i32_shr_s(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I32(left), Value::I32(right)) => {
-            stack.push(Value::I32(left >> right));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i32.shr_s",
-            vec![ValueType::I32, ValueType::I32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i32()?, stack.pop_i32()?);
+    stack.push_i32(left >> right);
+    Ok(())
 }
 
 pub fn i32_shr_u(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I32(left), Value::I32(right)) => {
-            stack.push(Value::I32(((left as u32) >> right) as i32));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i32.shr_u",
-            vec![ValueType::I32, ValueType::I32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i32()?, stack.pop_i32()?);
+    stack.push_i32(((left as u32) >> right) as i32);
+    Ok(())
 }
 
 pub fn i32_rotl(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I32(left), Value::I32(right)) => {
-            stack.push(Value::I32(i32::rotate_left(left, right as u32)));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i32.rotl",
-            vec![ValueType::I32, ValueType::I32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i32()?, stack.pop_i32()?);
+    stack.push_i32(i32::rotate_left(left, right as u32));
+    Ok(())
 }
 
 pub fn i32_rotr(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I32(left), Value::I32(right)) => {
-            stack.push(Value::I32(i32::rotate_right(left, right as u32)));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i32.rotr",
-            vec![ValueType::I32, ValueType::I32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i32()?, stack.pop_i32()?);
+    stack.push_i32(i32::rotate_right(left, right as u32));
+    Ok(())
 }
 
 // i64
 
 pub fn i64_add(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I64(left), Value::I64(right)) => {
-            stack.push(Value::I64(left + right));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i64.add",
-            vec![ValueType::I64, ValueType::I64],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i64()?, stack.pop_i64()?);
+    stack.push_i64(left + right);
+    Ok(())
 }
 
 pub fn i64_sub(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I64(left), Value::I64(right)) => {
-            stack.push(Value::I64(left - right));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i64.sub",
-            vec![ValueType::I64, ValueType::I64],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i64()?, stack.pop_i64()?);
+    stack.push_i64(left - right);
+    Ok(())
 }
 
 pub fn i64_mul(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I64(left), Value::I64(right)) => {
-            stack.push(Value::I64(left * right));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i64.mul",
-            vec![ValueType::I64, ValueType::I64],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i64()?, stack.pop_i64()?);
+    stack.push_i64(left * right);
+    Ok(())
 }
 
 pub fn i64_div_s(vm: &mut VM) -> Result<(), EngineError> {
-    let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
+    let (right, left) = (vm.stack.pop_i64()?, vm.stack.pop_i64()?);
 
-    match (lhs, rhs) {
-        (Value::I64(left), Value::I64(right)) => {
-            stack.push(Value::I64(left / right));
+    match (left, right) {
+        (_, 0) => Err(make_trap_engine_error(vm, TrapCode::IntegerDivideByZero)),
+        (i64::MIN, -1) => Err(make_trap_engine_error(vm, TrapCode::IntegerOverflow)),
+        _ => {
+            vm.stack.push_i64(left / right);
             Ok(())
         }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i64.div_s",
-            vec![ValueType::I64, ValueType::I64],
-            vec![&lhs, &rhs],
-        )),
     }
 }
 
 pub fn i64_div_u(vm: &mut VM) -> Result<(), EngineError> {
-    let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
+    let (right, left) = (vm.stack.pop_i64()?, vm.stack.pop_i64()?);
 
-    match (lhs, rhs) {
-        (Value::I64(left), Value::I64(right)) => {
-            stack.push(Value::I64(((left as u64) / (right as u64)) as i64));
+    match (left, right) {
+        (_, 0) => Err(make_trap_engine_error(vm, TrapCode::IntegerDivideByZero)),
+        _ => {
+            vm.stack.push_i64(((left as u64) / (right as u64)) as i64);
             Ok(())
         }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i64.div_u",
-            vec![ValueType::I64, ValueType::I64],
-            vec![&lhs, &rhs],
-        )),
     }
 }
 
 pub fn i64_rem_s(vm: &mut VM) -> Result<(), EngineError> {
-    let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
+    let (right, left) = (vm.stack.pop_i64()?, vm.stack.pop_i64()?);
 
-    match (lhs, rhs) {
-        (Value::I64(left), Value::I64(right)) => {
-            stack.push(Value::I64(left % right));
+    match (left, right) {
+        (_, 0) => Err(make_trap_engine_error(vm, TrapCode::IntegerDivideByZero)),
+        _ => {
+            // 规范规定 -2^63 rem_s -1 的结果为 0，而 Rust 的 `%` 运算会溢出
+            vm.stack.push_i64(left.wrapping_rem(right));
             Ok(())
         }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i64.rem_s",
-            vec![ValueType::I64, ValueType::I64],
-            vec![&lhs, &rhs],
-        )),
     }
 }
 
 pub fn i64_rem_u(vm: &mut VM) -> Result<(), EngineError> {
-    let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
+    let (right, left) = (vm.stack.pop_i64()?, vm.stack.pop_i64()?);
 
-    match (lhs, rhs) {
-        (Value::I64(left), Value::I64(right)) => {
-            stack.push(Value::I64(((left as u64) % (right as u64)) as i64));
+    match (left, right) {
+        (_, 0) => Err(make_trap_engine_error(vm, TrapCode::IntegerDivideByZero)),
+        _ => {
+            vm.stack.push_i64(((left as u64) % (right as u64)) as i64);
             Ok(())
         }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i64.rem_u",
-            vec![ValueType::I64, ValueType::I64],
-            vec![&lhs, &rhs],
-        )),
     }
 }
 
 pub fn i64_and(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I64(left), Value::I64(right)) => {
-            stack.push(Value::I64(left & right));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i64.and",
-            vec![ValueType::I64, ValueType::I64],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i64()?, stack.pop_i64()?);
+    stack.push_i64(left & right);
+    Ok(())
 }
 
 pub fn i64_or(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I64(left), Value::I64(right)) => {
-            stack.push(Value::I64(left | right));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i64.or",
-            vec![ValueType::I64, ValueType::I64],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i64()?, stack.pop_i64()?);
+    stack.push_i64(left | right);
+    Ok(())
 }
 
 pub fn i64_xor(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I64(left), Value::I64(right)) => {
-            stack.push(Value::I64(left ^ right));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i64.xor",
-            vec![ValueType::I64, ValueType::I64],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i64()?, stack.pop_i64()?);
+    stack.push_i64(left ^ right);
+    Ok(())
 }
 
 pub fn i64_shl(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I64(left), Value::I32(right)) => {
-            // RHS 的类型必须是 i32
-            stack.push(Value::I64(left << right));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i64.shl",
-            vec![ValueType::I64, ValueType::I32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i32()?, stack.pop_i64()?);
+    // RHS 的类型必须是 i32
+    stack.push_i64(left << right);
+    Ok(())
 }
 
 pub fn i64_shr_s(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I64(left), Value::I32(right)) => {
-            // RHS 的类型必须是 i32
-            stack.push(Value::I64(left >> right));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i64.shr_s",
-            vec![ValueType::I64, ValueType::I32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i32()?, stack.pop_i64()?);
+    // RHS 的类型必须是 i32
+    stack.push_i64(left >> right);
+    Ok(())
 }
 
 pub fn i64_shr_u(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I64(left), Value::I32(right)) => {
-            // RHS 的类型必须是 i32
-            stack.push(Value::I64(((left as u64) >> right) as i64));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i64.shr_u",
-            vec![ValueType::I64, ValueType::I32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i32()?, stack.pop_i64()?);
+    // RHS 的类型必须是 i32
+    stack.push_i64(((left as u64) >> right) as i64);
+    Ok(())
 }
 
 pub fn i64_rotl(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I64(left), Value::I32(right)) => {
-            // RHS 的类型必须是 i32
-            stack.push(Value::I64(i64::rotate_left(left, right as u32)));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i64.rotl",
-            vec![ValueType::I64, ValueType::I32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i32()?, stack.pop_i64()?);
+    // RHS 的类型必须是 i32
+    stack.push_i64(i64::rotate_left(left, right as u32));
+    Ok(())
 }
 
 pub fn i64_rotr(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I64(left), Value::I32(right)) => {
-            // RHS 的类型必须是 i32
-            stack.push(Value::I64(i64::rotate_right(left, right as u32)));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i64.rotr",
-            vec![ValueType::I64, ValueType::I32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i32()?, stack.pop_i64()?);
+    // RHS 的类型必须是 i32
+    stack.push_i64(i64::rotate_right(left, right as u32));
+    Ok(())
 }
 
 // f32
 
 pub fn f32_add(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::F32(left), Value::F32(right)) => {
-            stack.push(Value::F32(left + right));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "f32.add",
-            vec![ValueType::F32, ValueType::F32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_f32()?, stack.pop_f32()?);
+    stack.push_f32(left + right);
+    Ok(())
 }
 
 pub fn f32_sub(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::F32(left), Value::F32(right)) => {
-            stack.push(Value::F32(left - right));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "f32.sub",
-            vec![ValueType::F32, ValueType::F32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_f32()?, stack.pop_f32()?);
+    stack.push_f32(left - right);
+    Ok(())
 }
 
 pub fn f32_mul(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::F32(left), Value::F32(right)) => {
-            stack.push(Value::F32(left * right));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "f32.mul",
-            vec![ValueType::F32, ValueType::F32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_f32()?, stack.pop_f32()?);
+    stack.push_f32(left * right);
+    Ok(())
 }
 
 pub fn f32_div(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::F32(left), Value::F32(right)) => {
-            stack.push(Value::F32(left / right));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "f32.div",
-            vec![ValueType::F32, ValueType::F32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_f32()?, stack.pop_f32()?);
+    stack.push_f32(left / right);
+    Ok(())
 }
 
 pub fn f32_min(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::F32(left), Value::F32(right)) => {
-            if left < right {
-                stack.push(lhs)
-            } else {
-                stack.push(rhs)
-            }
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "f32.min",
-            vec![ValueType::F32, ValueType::F32],
-            vec![&lhs, &rhs],
-        )),
+    let (right, left) = (stack.pop_f32()?, stack.pop_f32()?);
+    if left < right {
+        stack.push_f32(left)
+    } else {
+        stack.push_f32(right)
     }
+    Ok(())
 }
 
 pub fn f32_max(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::F32(left), Value::F32(right)) => {
-            if left > right {
-                stack.push(lhs)
-            } else {
-                stack.push(rhs)
-            }
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "f32.max",
-            vec![ValueType::F32, ValueType::F32],
-            vec![&lhs, &rhs],
-        )),
+    let (right, left) = (stack.pop_f32()?, stack.pop_f32()?);
+    if left > right {
+        stack.push_f32(left)
+    } else {
+        stack.push_f32(right)
     }
+    Ok(())
 }
 
 pub fn f32_copysign(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::F32(left), Value::F32(right)) => {
-            stack.push(Value::F32(f32::copysign(right, left)));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "f32.copysign",
-            vec![ValueType::F32, ValueType::F32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_f32()?, stack.pop_f32()?);
+    stack.push_f32(f32::copysign(right, left));
+    Ok(())
 }
 
 // f64
 
 pub fn f64_add(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::F64(left), Value::F64(right)) => {
-            stack.push(Value::F64(left + right));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "f64.add",
-            vec![ValueType::F64, ValueType::F64],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_f64()?, stack.pop_f64()?);
+    stack.push_f64(left + right);
+    Ok(())
 }
 
 pub fn f64_sub(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::F64(left), Value::F64(right)) => {
-            stack.push(Value::F64(left - right));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "f64.sub",
-            vec![ValueType::F64, ValueType::F64],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_f64()?, stack.pop_f64()?);
+    stack.push_f64(left - right);
+    Ok(())
 }
 
 pub fn f64_mul(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::F64(left), Value::F64(right)) => {
-            stack.push(Value::F64(left * right));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "f64.mul",
-            vec![ValueType::F64, ValueType::F64],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_f64()?, stack.pop_f64()?);
+    stack.push_f64(left * right);
+    Ok(())
 }
 
 pub fn f64_div(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::F64(left), Value::F64(right)) => {
-            stack.push(Value::F64(left / right));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "f64.div",
-            vec![ValueType::F64, ValueType::F64],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_f64()?, stack.pop_f64()?);
+    stack.push_f64(left / right);
+    Ok(())
 }
 
 pub fn f64_min(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::F64(left), Value::F64(right)) => {
-            if left < right {
-                stack.push(lhs)
-            } else {
-                stack.push(rhs)
-            }
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "f64.min",
-            vec![ValueType::F64, ValueType::F64],
-            vec![&lhs, &rhs],
-        )),
+    let (right, left) = (stack.pop_f64()?, stack.pop_f64()?);
+    if left < right {
+        stack.push_f64(left)
+    } else {
+        stack.push_f64(right)
     }
+    Ok(())
 }
 
 pub fn f64_max(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::F64(left), Value::F64(right)) => {
-            if left > right {
-                stack.push(lhs)
-            } else {
-                stack.push(rhs)
-            }
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "f64.max",
-            vec![ValueType::F64, ValueType::F64],
-            vec![&lhs, &rhs],
-        )),
+    let (right, left) = (stack.pop_f64()?, stack.pop_f64()?);
+    if left > right {
+        stack.push_f64(left)
+    } else {
+        stack.push_f64(right)
     }
+    Ok(())
 }
 
 pub fn f64_copysign(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::F64(left), Value::F64(right)) => {
-            stack.push(Value::F64(f64::copysign(right, left)));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "f64.copysign",
-            vec![ValueType::F64, ValueType::F64],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_f64()?, stack.pop_f64()?);
+    stack.push_f64(f64::copysign(right, left));
+    Ok(())
 }
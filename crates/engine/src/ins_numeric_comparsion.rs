@@ -38,561 +38,236 @@
 //!
 //! 注，f64 有跟 f32 一样的比较指令，这里省略列出
 
-use anvm_ast::types::{Value, ValueType};
-
-use crate::{
-    error::{make_operand_data_types_mismatch_engine_error, EngineError},
-    vm::VM,
-};
+use crate::{error::EngineError, vm::VM};
 
 // i32
 
 pub fn i32_eq(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I32(left), Value::I32(right)) => {
-            stack.push_bool(left == right);
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i32.eq",
-            vec![ValueType::I32, ValueType::I32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i32()?, stack.pop_i32()?);
+    stack.push_bool(left == right);
+    Ok(())
 }
 
 pub fn i32_ne(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I32(left), Value::I32(right)) => {
-            stack.push_bool(left != right);
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i32.ne",
-            vec![ValueType::I32, ValueType::I32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i32()?, stack.pop_i32()?);
+    stack.push_bool(left != right);
+    Ok(())
 }
 
 pub fn i32_lt_s(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I32(left), Value::I32(right)) => {
-            stack.push_bool(left < right);
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i32.lt_s",
-            vec![ValueType::I32, ValueType::I32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i32()?, stack.pop_i32()?);
+    stack.push_bool(left < right);
+    Ok(())
 }
 
 pub fn i32_lt_u(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I32(left), Value::I32(right)) => {
-            stack.push_bool((left as u32) < (right as u32));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i32.lt_u",
-            vec![ValueType::I32, ValueType::I32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i32()?, stack.pop_i32()?);
+    stack.push_bool((left as u32) < (right as u32));
+    Ok(())
 }
 
 pub fn i32_gt_s(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I32(left), Value::I32(right)) => {
-            stack.push_bool(left > right);
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i32.gt_s",
-            vec![ValueType::I32, ValueType::I32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i32()?, stack.pop_i32()?);
+    stack.push_bool(left > right);
+    Ok(())
 }
 
 pub fn i32_gt_u(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I32(left), Value::I32(right)) => {
-            stack.push_bool((left as u32) > (right as u32));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i32.gt_u",
-            vec![ValueType::I32, ValueType::I32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i32()?, stack.pop_i32()?);
+    stack.push_bool((left as u32) > (right as u32));
+    Ok(())
 }
 
 pub fn i32_le_s(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I32(left), Value::I32(right)) => {
-            stack.push_bool(left <= right);
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i32.le_s",
-            vec![ValueType::I32, ValueType::I32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i32()?, stack.pop_i32()?);
+    stack.push_bool(left <= right);
+    Ok(())
 }
 
 pub fn i32_le_u(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I32(left), Value::I32(right)) => {
-            stack.push_bool((left as u32) <= (right as u32));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i32.le_u",
-            vec![ValueType::I32, ValueType::I32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i32()?, stack.pop_i32()?);
+    stack.push_bool((left as u32) <= (right as u32));
+    Ok(())
 }
 
 pub fn i32_ge_s(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I32(left), Value::I32(right)) => {
-            stack.push_bool(left >= right);
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i32.ge_s",
-            vec![ValueType::I32, ValueType::I32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i32()?, stack.pop_i32()?);
+    stack.push_bool(left >= right);
+    Ok(())
 }
 
 pub fn i32_ge_u(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I32(left), Value::I32(right)) => {
-            stack.push_bool((left as u32) >= (right as u32));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i32.ge_u",
-            vec![ValueType::I32, ValueType::I32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i32()?, stack.pop_i32()?);
+    stack.push_bool((left as u32) >= (right as u32));
+    Ok(())
 }
 
 // i64
 
 pub fn i64_eq(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I64(left), Value::I64(right)) => {
-            stack.push_bool(left == right);
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i64.eq",
-            vec![ValueType::I64, ValueType::I64],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i64()?, stack.pop_i64()?);
+    stack.push_bool(left == right);
+    Ok(())
 }
 
 pub fn i64_ne(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I64(left), Value::I64(right)) => {
-            stack.push_bool(left != right);
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i64.ne",
-            vec![ValueType::I64, ValueType::I64],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i64()?, stack.pop_i64()?);
+    stack.push_bool(left != right);
+    Ok(())
 }
 
 pub fn i64_lt_s(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I64(left), Value::I64(right)) => {
-            stack.push_bool(left < right);
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i64.lt_s",
-            vec![ValueType::I64, ValueType::I64],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i64()?, stack.pop_i64()?);
+    stack.push_bool(left < right);
+    Ok(())
 }
 
 pub fn i64_lt_u(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I64(left), Value::I64(right)) => {
-            stack.push_bool((left as u64) < (right as u64));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i64.lt_u",
-            vec![ValueType::I64, ValueType::I64],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i64()?, stack.pop_i64()?);
+    stack.push_bool((left as u64) < (right as u64));
+    Ok(())
 }
 
 pub fn i64_gt_s(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I64(left), Value::I64(right)) => {
-            stack.push_bool(left > right);
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i64.gt_s",
-            vec![ValueType::I64, ValueType::I64],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i64()?, stack.pop_i64()?);
+    stack.push_bool(left > right);
+    Ok(())
 }
 
 pub fn i64_gt_u(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I64(left), Value::I64(right)) => {
-            stack.push_bool((left as u64) > (right as u64));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i64.gt_u",
-            vec![ValueType::I64, ValueType::I64],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i64()?, stack.pop_i64()?);
+    stack.push_bool((left as u64) > (right as u64));
+    Ok(())
 }
 
 pub fn i64_le_s(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I64(left), Value::I64(right)) => {
-            stack.push_bool(left <= right);
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i64.le_s",
-            vec![ValueType::I64, ValueType::I64],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i64()?, stack.pop_i64()?);
+    stack.push_bool(left <= right);
+    Ok(())
 }
 
 pub fn i64_le_u(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I64(left), Value::I64(right)) => {
-            stack.push_bool((left as u64) <= (right as u64));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i64.le_u",
-            vec![ValueType::I64, ValueType::I64],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i64()?, stack.pop_i64()?);
+    stack.push_bool((left as u64) <= (right as u64));
+    Ok(())
 }
 
 pub fn i64_ge_s(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I64(left), Value::I64(right)) => {
-            stack.push_bool(left >= right);
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i64.ge_s",
-            vec![ValueType::I64, ValueType::I64],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i64()?, stack.pop_i64()?);
+    stack.push_bool(left >= right);
+    Ok(())
 }
 
 pub fn i64_ge_u(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::I64(left), Value::I64(right)) => {
-            stack.push_bool((left as u64) >= (right as u64));
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "i64.ge_u",
-            vec![ValueType::I64, ValueType::I64],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_i64()?, stack.pop_i64()?);
+    stack.push_bool((left as u64) >= (right as u64));
+    Ok(())
 }
 
 // f32
 
 pub fn f32_eq(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::F32(left), Value::F32(right)) => {
-            stack.push_bool(left == right);
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "f32.eq",
-            vec![ValueType::F32, ValueType::F32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_f32()?, stack.pop_f32()?);
+    stack.push_bool(left == right);
+    Ok(())
 }
 
 pub fn f32_ne(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::F32(left), Value::F32(right)) => {
-            stack.push_bool(left != right);
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "f32.ne",
-            vec![ValueType::F32, ValueType::F32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_f32()?, stack.pop_f32()?);
+    stack.push_bool(left != right);
+    Ok(())
 }
 
 pub fn f32_lt(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::F32(left), Value::F32(right)) => {
-            stack.push_bool(left < right);
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "f32.lt",
-            vec![ValueType::F32, ValueType::F32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_f32()?, stack.pop_f32()?);
+    stack.push_bool(left < right);
+    Ok(())
 }
 
 pub fn f32_gt(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::F32(left), Value::F32(right)) => {
-            stack.push_bool(left > right);
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "f32.gt",
-            vec![ValueType::F32, ValueType::F32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_f32()?, stack.pop_f32()?);
+    stack.push_bool(left > right);
+    Ok(())
 }
 
 pub fn f32_le(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::F32(left), Value::F32(right)) => {
-            stack.push_bool(left <= right);
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "f32.le",
-            vec![ValueType::F32, ValueType::F32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_f32()?, stack.pop_f32()?);
+    stack.push_bool(left <= right);
+    Ok(())
 }
 
 pub fn f32_ge(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::F32(left), Value::F32(right)) => {
-            stack.push_bool(left >= right);
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "f32.ge",
-            vec![ValueType::F32, ValueType::F32],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_f32()?, stack.pop_f32()?);
+    stack.push_bool(left >= right);
+    Ok(())
 }
 
 // f64
 
 pub fn f64_eq(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::F64(left), Value::F64(right)) => {
-            stack.push_bool(left == right);
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "f64.eq",
-            vec![ValueType::F64, ValueType::F64],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_f64()?, stack.pop_f64()?);
+    stack.push_bool(left == right);
+    Ok(())
 }
 
 pub fn f64_ne(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::F64(left), Value::F64(right)) => {
-            stack.push_bool(left != right);
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "f64.ne",
-            vec![ValueType::F64, ValueType::F64],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_f64()?, stack.pop_f64()?);
+    stack.push_bool(left != right);
+    Ok(())
 }
 
 pub fn f64_lt(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::F64(left), Value::F64(right)) => {
-            stack.push_bool(left < right);
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "f64.lt",
-            vec![ValueType::F64, ValueType::F64],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_f64()?, stack.pop_f64()?);
+    stack.push_bool(left < right);
+    Ok(())
 }
 
 pub fn f64_gt(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::F64(left), Value::F64(right)) => {
-            stack.push_bool(left > right);
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "f64.gt",
-            vec![ValueType::F64, ValueType::F64],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_f64()?, stack.pop_f64()?);
+    stack.push_bool(left > right);
+    Ok(())
 }
 
 pub fn f64_le(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::F64(left), Value::F64(right)) => {
-            stack.push_bool(left <= right);
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "f64.le",
-            vec![ValueType::F64, ValueType::F64],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_f64()?, stack.pop_f64()?);
+    stack.push_bool(left <= right);
+    Ok(())
 }
 
 pub fn f64_ge(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (rhs, lhs) = (stack.pop(), stack.pop());
-
-    match (lhs, rhs) {
-        (Value::F64(left), Value::F64(right)) => {
-            stack.push_bool(left >= right);
-            Ok(())
-        }
-        _ => Err(make_operand_data_types_mismatch_engine_error(
-            "f64.ge",
-            vec![ValueType::F64, ValueType::F64],
-            vec![&lhs, &rhs],
-        )),
-    }
+    let (right, left) = (stack.pop_f64()?, stack.pop_f64()?);
+    stack.push_bool(left >= right);
+    Ok(())
 }
@@ -104,10 +104,9 @@
 //!
 //! 不改变操作数的比特位，仅重新解释成其他类型
 
-use anvm_ast::types::{Value, ValueType};
-
 use crate::{
-    error::{make_operand_data_types_mismatch_engine_error, EngineError},
+    error::EngineError,
+    trap::{make_trap_engine_error, TrapCode},
     vm::VM,
 };
 
@@ -115,524 +114,369 @@ use crate::{
 
 pub fn i32_wrap_i64(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::I64(value) = operand {
-        let result = value as i32;
-        stack.push(Value::I32(result));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "i32.wrap_i64",
-            vec![ValueType::I64],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_i64()?;
+    let result = value as i32;
+    stack.push_i32(result);
+    Ok(())
 }
 
 // 整数提升
 
 pub fn i32_extend8_s(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::I32(value) = operand {
-        let result = (value as i8) as i32;
-        stack.push(Value::I32(result));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "i32.extend8_s",
-            vec![ValueType::I32],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_i32()?;
+    let result = (value as i8) as i32;
+    stack.push_i32(result);
+    Ok(())
 }
 
 pub fn i32_extend16_s(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::I32(value) = operand {
-        let result = (value as i16) as i32;
-        stack.push(Value::I32(result));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "i32.extend16_s",
-            vec![ValueType::I32],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_i32()?;
+    let result = (value as i16) as i32;
+    stack.push_i32(result);
+    Ok(())
 }
 
 pub fn i64_extend_i32_s(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::I32(value) = operand {
-        let result = value as i64;
-        stack.push(Value::I64(result));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "i64.extend_i32_s",
-            vec![ValueType::I32],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_i32()?;
+    let result = value as i64;
+    stack.push_i64(result);
+    Ok(())
 }
 
 pub fn i64_extend_i32_u(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::I32(value) = operand {
-        let result = (value as u32) as i64;
-        stack.push(Value::I64(result));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "i64.extend_i32_u",
-            vec![ValueType::I32],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_i32()?;
+    let result = (value as u32) as i64;
+    stack.push_i64(result);
+    Ok(())
 }
 
 pub fn i64_extend8_s(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::I64(value) = operand {
-        let result = (value as i8) as i64;
-        stack.push(Value::I64(result));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "i64.extend8_s",
-            vec![ValueType::I64],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_i64()?;
+    let result = (value as i8) as i64;
+    stack.push_i64(result);
+    Ok(())
 }
 
 pub fn i64_extend16_s(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::I64(value) = operand {
-        let result = (value as i16) as i64;
-        stack.push(Value::I64(result));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "i64.extend16_s",
-            vec![ValueType::I64],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_i64()?;
+    let result = (value as i16) as i64;
+    stack.push_i64(result);
+    Ok(())
 }
 
 pub fn i64_extend32_s(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
+    let value = stack.pop_i64()?;
+    let result = (value as i32) as i64;
+    stack.push_i64(result);
+    Ok(())
+}
 
-    if let Value::I64(value) = operand {
-        let result = (value as i32) as i64;
-        stack.push(Value::I64(result));
-        Ok(())
+// 浮点数转整数（截断运算）
+
+/// 检查浮点数截断为整数之后是否在目标整数类型的范围之内
+///
+/// 参数 min 和 max 是目标类型范围的开区间边界，即截断之后能落在目标类型范围之内的
+/// 浮点数必须满足 `min < value < max`。
+///
+/// - NaN 返回 InvalidConversionToInteger 陷阱
+/// - 超出范围（包括正负无穷）返回 IntegerOverflow 陷阱
+fn check_truncate_range(value: f64, min: f64, max: f64) -> Result<(), TrapCode> {
+    if value.is_nan() {
+        Err(TrapCode::InvalidConversionToInteger)
+    } else if value <= min || value >= max {
+        Err(TrapCode::IntegerOverflow)
     } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "i64.extend32_s",
-            vec![ValueType::I64],
-            vec![&operand],
-        ))
+        Ok(())
     }
 }
 
-// 浮点数转整数（截断运算）
-
 pub fn i32_trunc_f32_s(vm: &mut VM) -> Result<(), EngineError> {
-    let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::F32(value) = operand {
-        let result = value as i32;
-        stack.push(Value::I32(result));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "i32.trunc_f32_s",
-            vec![ValueType::F32],
-            vec![&operand],
-        ))
+    let value = vm.stack.pop_f32()?;
+
+    match check_truncate_range(value as f64, -2147483649.0, 2147483648.0) {
+        Ok(_) => {
+            vm.stack.push_i32(value as i32);
+            Ok(())
+        }
+        Err(trap_code) => Err(make_trap_engine_error(vm, trap_code)),
     }
 }
 
 pub fn i32_trunc_f32_u(vm: &mut VM) -> Result<(), EngineError> {
-    let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::F32(value) = operand {
-        let result = value as u32;
-        stack.push(Value::I32(result as i32));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "i32.trunc_f32_u",
-            vec![ValueType::F32],
-            vec![&operand],
-        ))
+    let value = vm.stack.pop_f32()?;
+
+    match check_truncate_range(value as f64, -1.0, 4294967296.0) {
+        Ok(_) => {
+            vm.stack.push_i32((value as u32) as i32);
+            Ok(())
+        }
+        Err(trap_code) => Err(make_trap_engine_error(vm, trap_code)),
     }
 }
 
 pub fn i64_trunc_f32_s(vm: &mut VM) -> Result<(), EngineError> {
-    let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::F32(value) = operand {
-        let result = value as i64;
-        stack.push(Value::I64(result));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "i64.trunc_f32_s",
-            vec![ValueType::F32],
-            vec![&operand],
-        ))
+    let value = vm.stack.pop_f32()?;
+
+    match check_truncate_range(value as f64, -9223372036854777856.0, 9223372036854775808.0) {
+        Ok(_) => {
+            vm.stack.push_i64(value as i64);
+            Ok(())
+        }
+        Err(trap_code) => Err(make_trap_engine_error(vm, trap_code)),
     }
 }
 
 pub fn i64_trunc_f32_u(vm: &mut VM) -> Result<(), EngineError> {
-    let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::F32(value) = operand {
-        let result = value as u64;
-        stack.push(Value::I64(result as i64));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "i64.trunc_f32_u",
-            vec![ValueType::F32],
-            vec![&operand],
-        ))
+    let value = vm.stack.pop_f32()?;
+
+    match check_truncate_range(value as f64, -1.0, 18446744073709551616.0) {
+        Ok(_) => {
+            vm.stack.push_i64((value as u64) as i64);
+            Ok(())
+        }
+        Err(trap_code) => Err(make_trap_engine_error(vm, trap_code)),
     }
 }
 
 pub fn i32_trunc_f64_s(vm: &mut VM) -> Result<(), EngineError> {
-    let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::F64(value) = operand {
-        let result = value as i32;
-        stack.push(Value::I32(result));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "i32.trunc_f64_s",
-            vec![ValueType::F64],
-            vec![&operand],
-        ))
+    let value = vm.stack.pop_f64()?;
+
+    match check_truncate_range(value, -2147483649.0, 2147483648.0) {
+        Ok(_) => {
+            vm.stack.push_i32(value as i32);
+            Ok(())
+        }
+        Err(trap_code) => Err(make_trap_engine_error(vm, trap_code)),
     }
 }
 
 pub fn i32_trunc_f64_u(vm: &mut VM) -> Result<(), EngineError> {
-    let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::F64(value) = operand {
-        let result = value as u32;
-        stack.push(Value::I32(result as i32));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "i32.trunc_f64_u",
-            vec![ValueType::F64],
-            vec![&operand],
-        ))
+    let value = vm.stack.pop_f64()?;
+
+    match check_truncate_range(value, -1.0, 4294967296.0) {
+        Ok(_) => {
+            vm.stack.push_i32((value as u32) as i32);
+            Ok(())
+        }
+        Err(trap_code) => Err(make_trap_engine_error(vm, trap_code)),
     }
 }
 
 pub fn i64_trunc_f64_s(vm: &mut VM) -> Result<(), EngineError> {
-    let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::F64(value) = operand {
-        let result = value as i64;
-        stack.push(Value::I64(result));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "i64.trunc_f64_s",
-            vec![ValueType::F64],
-            vec![&operand],
-        ))
+    let value = vm.stack.pop_f64()?;
+
+    match check_truncate_range(value, -9223372036854777856.0, 9223372036854775808.0) {
+        Ok(_) => {
+            vm.stack.push_i64(value as i64);
+            Ok(())
+        }
+        Err(trap_code) => Err(make_trap_engine_error(vm, trap_code)),
     }
 }
 
 pub fn i64_trunc_f64_u(vm: &mut VM) -> Result<(), EngineError> {
-    let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::F64(value) = operand {
-        let result = value as u64;
-        stack.push(Value::I64(result as i64));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "i64.trunc_f64_u",
-            vec![ValueType::F64],
-            vec![&operand],
-        ))
+    let value = vm.stack.pop_f64()?;
+
+    match check_truncate_range(value, -1.0, 18446744073709551616.0) {
+        Ok(_) => {
+            vm.stack.push_i64((value as u64) as i64);
+            Ok(())
+        }
+        Err(trap_code) => Err(make_trap_engine_error(vm, trap_code)),
     }
 }
 
 // 饱和截断
+//
+// Rust 的浮点数到整数的 `as` 转换本身就是饱和的：
+// NaN 转为 0，超出范围的值（包括正负无穷）转为目标类型的最大/最小值，
+// 跟 WebAssembly 规范的语义一致。
+
+pub fn i32_trunc_sat_f32_s(vm: &mut VM) -> Result<(), EngineError> {
+    let stack = &mut vm.stack;
+    let value = stack.pop_f32()?;
+    let result = value as i32;
+    stack.push_i32(result);
+    Ok(())
+}
+
+pub fn i32_trunc_sat_f32_u(vm: &mut VM) -> Result<(), EngineError> {
+    let stack = &mut vm.stack;
+    let value = stack.pop_f32()?;
+    let result = (value as u32) as i32;
+    stack.push_i32(result);
+    Ok(())
+}
+
+pub fn i32_trunc_sat_f64_s(vm: &mut VM) -> Result<(), EngineError> {
+    let stack = &mut vm.stack;
+    let value = stack.pop_f64()?;
+    let result = value as i32;
+    stack.push_i32(result);
+    Ok(())
+}
+
+pub fn i32_trunc_sat_f64_u(vm: &mut VM) -> Result<(), EngineError> {
+    let stack = &mut vm.stack;
+    let value = stack.pop_f64()?;
+    let result = (value as u32) as i32;
+    stack.push_i32(result);
+    Ok(())
+}
 
-// todo::
+pub fn i64_trunc_sat_f32_s(vm: &mut VM) -> Result<(), EngineError> {
+    let stack = &mut vm.stack;
+    let value = stack.pop_f32()?;
+    let result = value as i64;
+    stack.push_i64(result);
+    Ok(())
+}
+
+pub fn i64_trunc_sat_f32_u(vm: &mut VM) -> Result<(), EngineError> {
+    let stack = &mut vm.stack;
+    let value = stack.pop_f32()?;
+    let result = (value as u64) as i64;
+    stack.push_i64(result);
+    Ok(())
+}
+
+pub fn i64_trunc_sat_f64_s(vm: &mut VM) -> Result<(), EngineError> {
+    let stack = &mut vm.stack;
+    let value = stack.pop_f64()?;
+    let result = value as i64;
+    stack.push_i64(result);
+    Ok(())
+}
+
+pub fn i64_trunc_sat_f64_u(vm: &mut VM) -> Result<(), EngineError> {
+    let stack = &mut vm.stack;
+    let value = stack.pop_f64()?;
+    let result = (value as u64) as i64;
+    stack.push_i64(result);
+    Ok(())
+}
 
 // 整数转浮点数（转换运算）
 
 pub fn f32_convert_i32_s(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::I32(value) = operand {
-        let result = value as f32;
-        stack.push(Value::F32(result));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "f32.convert_i32_s",
-            vec![ValueType::I32],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_i32()?;
+    let result = value as f32;
+    stack.push_f32(result);
+    Ok(())
 }
 
 pub fn f32_convert_i32_u(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::I32(value) = operand {
-        let result = (value as u32) as f32;
-        stack.push(Value::F32(result));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "f32.convert_i32_u",
-            vec![ValueType::I32],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_i32()?;
+    let result = (value as u32) as f32;
+    stack.push_f32(result);
+    Ok(())
 }
 
 pub fn f64_convert_i32_s(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::I32(value) = operand {
-        let result = value as f64;
-        stack.push(Value::F64(result));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "f64.convert_i32_s",
-            vec![ValueType::I32],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_i32()?;
+    let result = value as f64;
+    stack.push_f64(result);
+    Ok(())
 }
 
 pub fn f64_convert_i32_u(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::I32(value) = operand {
-        let result = (value as u32) as f64;
-        stack.push(Value::F64(result));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "f64.convert_i32_u",
-            vec![ValueType::I32],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_i32()?;
+    let result = (value as u32) as f64;
+    stack.push_f64(result);
+    Ok(())
 }
 
 pub fn f32_convert_i64_s(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::I64(value) = operand {
-        let result = value as f32;
-        stack.push(Value::F32(result));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "f32.convert_i64_s",
-            vec![ValueType::I64],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_i64()?;
+    let result = value as f32;
+    stack.push_f32(result);
+    Ok(())
 }
 
 pub fn f32_convert_i64_u(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::I64(value) = operand {
-        let result = (value as u64) as f32;
-        stack.push(Value::F32(result));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "f32.convert_i64_u",
-            vec![ValueType::I64],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_i64()?;
+    let result = (value as u64) as f32;
+    stack.push_f32(result);
+    Ok(())
 }
 
 pub fn f64_convert_i64_s(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::I64(value) = operand {
-        let result = value as f64;
-        stack.push(Value::F64(result));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "f64.convert_i64_s",
-            vec![ValueType::I64],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_i64()?;
+    let result = value as f64;
+    stack.push_f64(result);
+    Ok(())
 }
 
 pub fn f64_convert_i64_u(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::I64(value) = operand {
-        let result = (value as u64) as f64;
-        stack.push(Value::F64(result));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "f64.convert_i64_u",
-            vec![ValueType::I64],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_i64()?;
+    let result = (value as u64) as f64;
+    stack.push_f64(result);
+    Ok(())
 }
 
 // 浮点数精度调整
 
 pub fn f32_demote_f64_s(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::F64(value) = operand {
-        let result = value as f32;
-        stack.push(Value::F32(result));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "f32.demote_f64_s",
-            vec![ValueType::F64],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_f64()?;
+    let result = value as f32;
+    stack.push_f32(result);
+    Ok(())
 }
 
 pub fn f64_promote_f32(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::F32(value) = operand {
-        let result = value as f64;
-        stack.push(Value::F64(result));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "f64.promote_f32",
-            vec![ValueType::F32],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_f32()?;
+    let result = value as f64;
+    stack.push_f64(result);
+    Ok(())
 }
 
 // 比特位重新解释
 
 pub fn i32_reinterpret_f32(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::F32(value) = operand {
-        let result = i32::from_le_bytes(value.to_le_bytes());
-        stack.push(Value::I32(result));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "i32.reinterpret_f32",
-            vec![ValueType::F32],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_f32()?;
+    let result = i32::from_le_bytes(value.to_le_bytes());
+    stack.push_i32(result);
+    Ok(())
 }
 
 pub fn i64_reinterpret_f64(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::F64(value) = operand {
-        let result = i64::from_le_bytes(value.to_le_bytes());
-        stack.push(Value::I64(result));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "i64.reinterpret_f64",
-            vec![ValueType::F64],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_f64()?;
+    let result = i64::from_le_bytes(value.to_le_bytes());
+    stack.push_i64(result);
+    Ok(())
 }
 
 pub fn f32_reinterpret_i32(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::I32(value) = operand {
-        let result = f32::from_le_bytes(value.to_le_bytes());
-        stack.push(Value::F32(result));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "f32.reinterpret_i32",
-            vec![ValueType::I32],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_i32()?;
+    let result = f32::from_le_bytes(value.to_le_bytes());
+    stack.push_f32(result);
+    Ok(())
 }
 
 pub fn f64_reinterpret_i64(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::I64(value) = operand {
-        let result = f64::from_le_bytes(value.to_le_bytes());
-        stack.push(Value::F64(result));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "f64.reinterpret_i64",
-            vec![ValueType::I64],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_i64()?;
+    let result = f64::from_le_bytes(value.to_le_bytes());
+    stack.push_f64(result);
+    Ok(())
 }
@@ -38,203 +38,99 @@
 //!
 //! Rust 的 f32::round() 函数是 4 舍 5 入，并不一样。
 
-use anvm_ast::types::{Value, ValueType};
-
-use crate::{
-    error::{make_operand_data_types_mismatch_engine_error, EngineError},
-    vm::VM,
-};
+use crate::{error::EngineError, vm::VM};
 
 // i32
 
 pub fn i32_clz(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::I32(value) = operand {
-        let result = i32::leading_zeros(value);
-        stack.push(Value::I32(result as i32));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "i32.clz",
-            vec![ValueType::I32],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_i32()?;
+    let result = i32::leading_zeros(value);
+    stack.push_i32(result as i32);
+    Ok(())
 }
 
 pub fn i32_ctz(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::I32(value) = operand {
-        let result = i32::trailing_zeros(value);
-        stack.push(Value::I32(result as i32));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "i32.ctz",
-            vec![ValueType::I32],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_i32()?;
+    let result = i32::trailing_zeros(value);
+    stack.push_i32(result as i32);
+    Ok(())
 }
 
 pub fn i32_popcnt(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::I32(value) = operand {
-        let result = i32::count_ones(value);
-        stack.push(Value::I32(result as i32));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "i32.popcnt",
-            vec![ValueType::I32],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_i32()?;
+    let result = i32::count_ones(value);
+    stack.push_i32(result as i32);
+    Ok(())
 }
 
 // i64
 
 pub fn i64_clz(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::I64(value) = operand {
-        let result = i64::leading_zeros(value);
-        stack.push(Value::I64(result as i64));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "i64.clz",
-            vec![ValueType::I64],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_i64()?;
+    let result = i64::leading_zeros(value);
+    stack.push_i64(result as i64);
+    Ok(())
 }
 
 pub fn i64_ctz(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::I64(value) = operand {
-        let result = i64::trailing_zeros(value);
-        stack.push(Value::I64(result as i64));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "i64.ctz",
-            vec![ValueType::I64],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_i64()?;
+    let result = i64::trailing_zeros(value);
+    stack.push_i64(result as i64);
+    Ok(())
 }
 
 pub fn i64_popcnt(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::I64(value) = operand {
-        let result = i64::count_ones(value);
-        stack.push(Value::I64(result as i64));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "i64.popcnt",
-            vec![ValueType::I64],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_i64()?;
+    let result = i64::count_ones(value);
+    stack.push_i64(result as i64);
+    Ok(())
 }
 
 // f32
 
 pub fn f32_abs(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::F32(value) = operand {
-        let result = f32::abs(value);
-        stack.push(Value::F32(result as f32));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "f32.abs",
-            vec![ValueType::F32],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_f32()?;
+    let result = f32::abs(value);
+    stack.push_f32(result as f32);
+    Ok(())
 }
 
 pub fn f32_neg(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::F32(value) = operand {
-        stack.push(Value::F32(-value));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "f32.neg",
-            vec![ValueType::F32],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_f32()?;
+    stack.push_f32(-value);
+    Ok(())
 }
 
 pub fn f32_ceil(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::F32(value) = operand {
-        let result = f32::ceil(value);
-        stack.push(Value::F32(result as f32));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "f32.ceil",
-            vec![ValueType::F32],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_f32()?;
+    let result = f32::ceil(value);
+    stack.push_f32(result as f32);
+    Ok(())
 }
 
 pub fn f32_floor(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::F32(value) = operand {
-        let result = f32::floor(value);
-        stack.push(Value::F32(result as f32));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "f32.floor",
-            vec![ValueType::F32],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_f32()?;
+    let result = f32::floor(value);
+    stack.push_f32(result as f32);
+    Ok(())
 }
 
 pub fn f32_trunc(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::F32(value) = operand {
-        let result = f32::trunc(value);
-        stack.push(Value::F32(result as f32));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "f32.trunc",
-            vec![ValueType::F32],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_f32()?;
+    let result = f32::trunc(value);
+    stack.push_f32(result as f32);
+    Ok(())
 }
 
 /// nearest 指令实现就近取整（4 舍 6 入，5 奇进偶不进）
@@ -243,19 +139,10 @@ pub fn f32_trunc(vm: &mut VM) -> Result<(), EngineError> {
 /// https://developer.mozilla.org/en-US/docs/WebAssembly/Reference/Numeric/Nearest
 pub fn f32_nearest(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::F32(value) = operand {
-        let result = round_half_to_even_f32(value);
-        stack.push(Value::F32(result));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "f32.nearest",
-            vec![ValueType::F32],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_f32()?;
+    let result = round_half_to_even_f32(value);
+    stack.push_f32(result);
+    Ok(())
 }
 
 /// 土制 "4 舍 6 入，奇进偶不进"
@@ -284,105 +171,51 @@ fn round_half_to_even_f32(value: f32) -> f32 {
 
 pub fn f32_sqrt(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::F32(value) = operand {
-        let result = f32::sqrt(value);
-        stack.push(Value::F32(result as f32));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "f32.sqrt",
-            vec![ValueType::F32],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_f32()?;
+    let result = f32::sqrt(value);
+    stack.push_f32(result as f32);
+    Ok(())
 }
 
 // f64
 
 pub fn f64_abs(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::F64(value) = operand {
-        let result = f64::abs(value);
-        stack.push(Value::F64(result as f64));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "f64.abs",
-            vec![ValueType::F64],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_f64()?;
+    let result = f64::abs(value);
+    stack.push_f64(result as f64);
+    Ok(())
 }
 
 pub fn f64_neg(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::F64(value) = operand {
-        stack.push(Value::F64(-value));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "f64.neg",
-            vec![ValueType::F64],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_f64()?;
+    stack.push_f64(-value);
+    Ok(())
 }
 
 pub fn f64_ceil(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::F64(value) = operand {
-        let result = f64::ceil(value);
-        stack.push(Value::F64(result as f64));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "f64.ceil",
-            vec![ValueType::F64],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_f64()?;
+    let result = f64::ceil(value);
+    stack.push_f64(result as f64);
+    Ok(())
 }
 
 pub fn f64_floor(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::F64(value) = operand {
-        let result = f64::floor(value);
-        stack.push(Value::F64(result as f64));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "f64.floor",
-            vec![ValueType::F64],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_f64()?;
+    let result = f64::floor(value);
+    stack.push_f64(result as f64);
+    Ok(())
 }
 
 pub fn f64_trunc(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::F64(value) = operand {
-        let result = f64::trunc(value);
-        stack.push(Value::F64(result as f64));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "f64.trunc",
-            vec![ValueType::F64],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_f64()?;
+    let result = f64::trunc(value);
+    stack.push_f64(result as f64);
+    Ok(())
 }
 
 /// nearest 指令实现就近取整（4 舍 6 入，5 奇进偶不进）
@@ -391,19 +224,10 @@ pub fn f64_trunc(vm: &mut VM) -> Result<(), EngineError> {
 /// https://developer.mozilla.org/en-US/docs/WebAssembly/Reference/Numeric/Nearest
 pub fn f64_nearest(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::F64(value) = operand {
-        let result = round_half_to_even_f64(value);
-        stack.push(Value::F64(result));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "f64.nearest",
-            vec![ValueType::F64],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_f64()?;
+    let result = round_half_to_even_f64(value);
+    stack.push_f64(result);
+    Ok(())
 }
 
 /// 土制 "4 舍 6 入，奇进偶不进"
@@ -432,17 +256,8 @@ fn round_half_to_even_f64(value: f64) -> f64 {
 
 pub fn f64_sqrt(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let operand = stack.pop();
-
-    if let Value::F64(value) = operand {
-        let result = f64::sqrt(value);
-        stack.push(Value::F64(result as f64));
-        Ok(())
-    } else {
-        Err(make_operand_data_types_mismatch_engine_error(
-            "f64.sqrt",
-            vec![ValueType::F64],
-            vec![&operand],
-        ))
-    }
+    let value = stack.pop_f64()?;
+    let result = f64::sqrt(value);
+    stack.push_f64(result as f64);
+    Ok(())
 }
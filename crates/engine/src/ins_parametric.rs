@@ -10,19 +10,15 @@
 //!
 //! - drop
 //! - select
+//! - select t（typed select，指定了操作数的类型，引用类型的操作数只能使用这种形式）
 
-use anvm_ast::types::{Value, ValueType};
-
-use crate::{
-    error::{make_operand_data_types_mismatch_engine_error, EngineError, TypeMismatch},
-    vm::VM,
-};
+use crate::{error::EngineError, vm::VM};
 
 /// # drop
 ///
 /// 弹出栈顶的一个操作数并扔掉
 pub fn drop(vm: &mut VM) -> Result<(), EngineError> {
-    vm.stack.pop();
+    vm.stack.pop_slot()?;
     Ok(())
 }
 
@@ -46,28 +42,17 @@ pub fn drop(vm: &mut VM) -> Result<(), EngineError> {
 /// 其中：
 /// - 栈顶元素（第一个操作数）必须是 int32，
 /// - 第二个和第三个操作数的类型必须相同
+///
+/// 操作数的数据类型已经由验证器检查过，所以这里直接按原始数据选择即可。
 pub fn select(vm: &mut VM) -> Result<(), EngineError> {
     let stack = &mut vm.stack;
-    let (testing, consequent, alternate) = (stack.pop(), stack.pop(), stack.pop());
+    let (testing, consequent, alternate) =
+        (stack.pop_bool()?, stack.pop_slot()?, stack.pop_slot()?);
 
-    if consequent.get_type() != alternate.get_type() {
-        Err(EngineError::TypeMismatch(
-            TypeMismatch::SelectInstructionConsequentTypeMismatch(consequent.get_type(), alternate.get_type()),
-        ))
+    if testing {
+        stack.push_slot(alternate);
     } else {
-        if let Value::I32(value) = testing {
-            if value == 0 {
-                stack.push(consequent);
-            } else {
-                stack.push(alternate);
-            }
-            Ok(())
-        } else {
-            Err(make_operand_data_types_mismatch_engine_error(
-                "select",
-                vec![ValueType::I32],
-                vec![&testing],
-            ))
-        }
+        stack.push_slot(consequent);
     }
+    Ok(())
 }
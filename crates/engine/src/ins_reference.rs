@@ -0,0 +1,50 @@
+// Copyright (c) 2022 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! # 引用指令
+//!
+//! - ref.null ref_type
+//! - ref.is_null
+//! - ref.func func_idx:uint32
+//!
+//! 引用类型的值有 `funcref` 和 `externref` 两种，前者的值是函数引用，即函数在
+//! 所有模块当中的统一编号（参见 `vm::get_function_reference_bases`），
+//! 后者的值是宿主传入的不透明句柄，VM 不解析其含义。
+//!
+//! https://webassembly.github.io/spec/core/syntax/instructions.html#reference-instructions
+
+use crate::{error::EngineError, vm::VM};
+
+/// # ref.null
+///
+/// 压入指定引用类型的空引用
+///
+/// 空引用在槽里的表示跟引用类型无关，所以这里无需用到引用类型。
+pub fn ref_null(vm: &mut VM) -> Result<(), EngineError> {
+    vm.stack.push_reference(None);
+    Ok(())
+}
+
+/// # ref.is_null
+///
+/// 从栈顶弹出一个引用，如果为空引用则压入 1（int32），否则压入 0（int32）
+pub fn ref_is_null(vm: &mut VM) -> Result<(), EngineError> {
+    let stack = &mut vm.stack;
+    let element = stack.pop_reference()?;
+    stack.push_bool(element.is_none());
+    Ok(())
+}
+
+/// # ref.func
+///
+/// 压入指向指定函数的引用
+pub fn ref_func(vm: &mut VM, function_index: u32) -> Result<(), EngineError> {
+    let function_reference = vm
+        .resource
+        .get_function_reference(vm.status.vm_module_index, function_index as usize);
+    vm.stack.push_reference(Some(function_reference));
+    Ok(())
+}
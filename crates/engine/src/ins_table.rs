@@ -0,0 +1,208 @@
+// Copyright (c) 2022 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! # 表指令
+//!
+//! ## 基本表指令
+//!
+//! - table.get table_idx:uint32
+//! - table.set table_idx:uint32
+//! - table.grow table_idx:uint32
+//! - table.size table_idx:uint32
+//! - table.fill table_idx:uint32
+//!
+//! 表元素的值为引用类型，即 `funcref` 或者 `externref`，具体是哪一种由表的类型决定。
+//! `table.get`、`table.set` 和 `table.fill` 访问的位置超出了表的范围时，
+//! 抛出 `out of bounds table access` 陷阱；`table.grow` 失败时不会抛出陷阱，
+//! 而是压入 -1。
+//!
+//! ## 批量表指令
+//!
+//! - table.init elem_idx:uint32 table_idx:uint32
+//! - elem.drop elem_idx:uint32
+//! - table.copy dest_table_idx:uint32 source_table_idx:uint32
+//!
+//! `table.init` 和 `table.copy` 从操作数栈依次弹出 3 个 uint32，分别是
+//! 长度（n）、源位置（s）以及目标位置（d），即在压入操作数时的顺序为 d, s, n。
+//! 如果访问的范围超出了表（或者元素段）的范围，则抛出 `out of bounds table access` 陷阱，
+//! 注意即使长度为 0，源位置或者目标位置超出了范围也会抛出陷阱。
+//!
+//! https://webassembly.github.io/spec/core/syntax/instructions.html#table-instructions
+
+use crate::{
+    error::EngineError,
+    trap::{make_trap_engine_error, TrapCode},
+    vm::VM,
+    vm_stack::StackUnderflow,
+};
+
+/// # table.get
+///
+/// 从操作数栈弹出一个 i32 作为元素的索引，然后压入该元素的值
+pub fn table_get(vm: &mut VM, table_index: u32) -> Result<(), EngineError> {
+    let instance_table_index = get_instance_table_index(vm, table_index);
+
+    let index = vm.stack.pop_i32()? as u32 as usize;
+
+    let table = &vm.resource.tables[instance_table_index];
+    if index >= table.get_size() as usize {
+        return Err(make_trap_engine_error(vm, TrapCode::TableOutOfBounds));
+    }
+
+    let element = table.get_element(index)?;
+    vm.stack.push_reference(element);
+
+    Ok(())
+}
+
+/// # table.set
+///
+/// 从操作数栈依次弹出元素的值以及元素的索引，即在压入操作数时的顺序为 index, value
+pub fn table_set(vm: &mut VM, table_index: u32) -> Result<(), EngineError> {
+    let instance_table_index = get_instance_table_index(vm, table_index);
+
+    let element = vm.stack.pop_reference()?;
+    let index = vm.stack.pop_i32()? as u32 as usize;
+
+    if index >= vm.resource.tables[instance_table_index].get_size() as usize {
+        return Err(make_trap_engine_error(vm, TrapCode::TableOutOfBounds));
+    }
+
+    vm.resource.tables[instance_table_index].set_element_value(index, element)
+}
+
+/// # table.grow
+///
+/// 从操作数栈依次弹出增加的数量（n）以及新元素的初始值，
+/// 即在压入操作数时的顺序为 value, n。
+/// 成功时压入表原先的大小，失败时压入 -1
+pub fn table_grow(vm: &mut VM, table_index: u32) -> Result<(), EngineError> {
+    let instance_table_index = get_instance_table_index(vm, table_index);
+
+    let count = vm.stack.pop_i32()? as u32;
+    let element = vm.stack.pop_reference()?;
+
+    let result = vm.resource.tables[instance_table_index]
+        .increase_size_with_element(count, element)
+        .map_or(-1, |old_size| old_size as i32);
+    vm.stack.push_i32(result);
+
+    Ok(())
+}
+
+/// # table.size
+///
+/// 压入表的当前大小（元素的数量）
+pub fn table_size(vm: &mut VM, table_index: u32) -> Result<(), EngineError> {
+    let instance_table_index = get_instance_table_index(vm, table_index);
+    let size = vm.resource.tables[instance_table_index].get_size();
+    vm.stack.push_i32(size as i32);
+
+    Ok(())
+}
+
+/// # table.fill
+///
+/// 从操作数栈依次弹出数量（n）、元素的值以及开始位置（i），
+/// 即在压入操作数时的顺序为 i, value, n。
+pub fn table_fill(vm: &mut VM, table_index: u32) -> Result<(), EngineError> {
+    let instance_table_index = get_instance_table_index(vm, table_index);
+
+    let count = vm.stack.pop_i32()? as u32 as usize;
+    let element = vm.stack.pop_reference()?;
+    let offset = vm.stack.pop_i32()? as u32 as usize;
+
+    if offset + count > vm.resource.tables[instance_table_index].get_size() as usize {
+        return Err(make_trap_engine_error(vm, TrapCode::TableOutOfBounds));
+    }
+
+    vm.resource.tables[instance_table_index].fill_elements(offset, element, count);
+
+    Ok(())
+}
+
+pub fn table_init(vm: &mut VM, element_index: u32, table_index: u32) -> Result<(), EngineError> {
+    let (dest_offset, source_offset, count) = pop_bulk_table_operands(vm)?;
+
+    let instance_table_index = get_instance_table_index(vm, table_index);
+    let element_segment_index = vm.resource.vm_modules[vm.status.vm_module_index]
+        .element_segment_indexes[element_index as usize];
+
+    let elements = vm.resource.element_segments[element_segment_index].get_elements();
+    let table_size = vm.resource.tables[instance_table_index].get_size() as usize;
+
+    if source_offset + count > elements.len() || dest_offset + count > table_size {
+        return Err(make_trap_engine_error(vm, TrapCode::TableOutOfBounds));
+    }
+
+    let elements = elements[source_offset..(source_offset + count)].to_vec();
+    vm.resource.tables[instance_table_index].set_elements(dest_offset, &elements);
+
+    Ok(())
+}
+
+pub fn element_drop(vm: &mut VM, element_index: u32) -> Result<(), EngineError> {
+    let element_segment_index = vm.resource.vm_modules[vm.status.vm_module_index]
+        .element_segment_indexes[element_index as usize];
+    vm.resource.element_segments[element_segment_index].drop_elements();
+
+    Ok(())
+}
+
+pub fn table_copy(
+    vm: &mut VM,
+    source_table_index: u32,
+    dest_table_index: u32,
+) -> Result<(), EngineError> {
+    let (dest_offset, source_offset, count) = pop_bulk_table_operands(vm)?;
+
+    let instance_source_table_index = get_instance_table_index(vm, source_table_index);
+    let instance_dest_table_index = get_instance_table_index(vm, dest_table_index);
+
+    let source_table_size = vm.resource.tables[instance_source_table_index].get_size() as usize;
+    let dest_table_size = vm.resource.tables[instance_dest_table_index].get_size() as usize;
+
+    if source_offset + count > source_table_size || dest_offset + count > dest_table_size {
+        return Err(make_trap_engine_error(vm, TrapCode::TableOutOfBounds));
+    }
+
+    if instance_source_table_index == instance_dest_table_index {
+        vm.resource.tables[instance_dest_table_index].copy_elements(
+            source_offset,
+            dest_offset,
+            count,
+        );
+    } else {
+        // 在不同的表之间复制元素
+        let elements = vm.resource.tables[instance_source_table_index].get_elements()
+            [source_offset..(source_offset + count)]
+            .to_vec();
+        vm.resource.tables[instance_dest_table_index].set_elements(dest_offset, &elements);
+    }
+
+    Ok(())
+}
+
+/// 获取当前模块的指定表在 VM 资源里的索引
+fn get_instance_table_index(vm: &VM, table_index: u32) -> usize {
+    vm.resource.vm_modules[vm.status.vm_module_index].table_indexes[table_index as usize]
+}
+
+/// 从操作数栈弹出批量表指令的 3 个 uint32 操作数
+///
+/// 返回 (d, s, n)
+fn pop_bulk_table_operands(vm: &mut VM) -> Result<(usize, usize, usize), StackUnderflow> {
+    let stack = &mut vm.stack;
+    let count = stack.pop_i32()?;
+    let source = stack.pop_i32()?;
+    let dest = stack.pop_i32()?;
+
+    Ok((
+        dest as u32 as usize,
+        source as u32 as usize,
+        count as u32 as usize,
+    ))
+}
@@ -17,18 +17,14 @@
 //! - global.get global_idx:uint32  ;; 读取指定索引的全局变量的值，压入操作数栈
 //! - global.set global_idx:uint32  ;; 从操作数栈弹出一个数，写入到指定索引的全局变量；弹出的数的类型必须跟全局变量的一致
 
-use crate::{
-    error::{EngineError, InvalidOperation, TypeMismatch},
-    vm::VM,
-    vm_global_variable::SetGlobalVariableError,
-};
+use crate::{error::EngineError, vm::VM};
 
 pub fn local_get(vm: &mut VM, index: u32) -> Result<(), EngineError> {
     let offset = vm.status.local_pointer + (index as usize);
 
     let stack = &mut vm.stack;
-    let value = stack.get_value(offset);
-    stack.push(value);
+    let slot = stack.get_slot(offset);
+    stack.push_slot(slot);
     Ok(())
 }
 
@@ -36,8 +32,8 @@ pub fn local_set(vm: &mut VM, index: u32) -> Result<(), EngineError> {
     let offset = vm.status.local_pointer + (index as usize);
 
     let stack = &mut vm.stack;
-    let value = stack.pop();
-    stack.set_value(offset, value);
+    let slot = stack.pop_slot()?;
+    stack.set_slot(offset, slot);
     Ok(())
 }
 
@@ -45,8 +41,8 @@ pub fn local_tee(vm: &mut VM, index: u32) -> Result<(), EngineError> {
     let offset = vm.status.local_pointer + (index as usize);
 
     let stack = &mut vm.stack;
-    let value = stack.peek();
-    stack.set_value(offset, value);
+    let slot = stack.peek_slot()?;
+    stack.set_slot(offset, slot);
     Ok(())
 }
 
@@ -56,31 +52,21 @@ pub fn global_get(vm: &mut VM, index: u32) -> Result<(), EngineError> {
     let value = vm.resource.global_variables[instance_global_variable_index].get_value();
 
     let stack = &mut vm.stack;
-    stack.push(value);
+    stack.push_value(&value);
     Ok(())
 }
 
 pub fn global_set(vm: &mut VM, index: u32) -> Result<(), EngineError> {
-    let stack = &mut vm.stack;
-    let value = stack.pop();
-
     let vm_module_index = vm.status.vm_module_index;
     let instance_global_variable_index =
         vm.resource.vm_modules[vm_module_index].global_variable_indexes[index as usize];
-    let vm_global_variable = &mut vm.resource.global_variables[instance_global_variable_index];
+    let value_type = vm.resource.global_variables[instance_global_variable_index]
+        .get_global_type()
+        .value_type
+        .clone();
+
+    // 按照全局变量的数据类型弹出操作数
+    let value = vm.stack.pop_value(&value_type)?;
 
-    match vm_global_variable.set_value(value) {
-        Err(SetGlobalVariableError::Immutable) => Err(EngineError::InvalidOperation(
-            InvalidOperation::ImmutableGlobalVariable(vm_module_index, index as usize),
-        )),
-        Err(SetGlobalVariableError::TypeMismatch) => Err(EngineError::TypeMismatch(
-            TypeMismatch::SetGlobalVariableValueTypeMismatch(
-                vm_module_index,
-                index as usize,
-                vm_global_variable.get_global_type().value_type.clone(),
-                value.get_type(),
-            ),
-        )),
-        _ => Ok(()),
-    }
+    vm.set_global_variable_value(vm_module_index, index as usize, value)
 }
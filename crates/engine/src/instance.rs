@@ -4,50 +4,154 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use std::sync::Arc;
+
 use anvm_ast::{
-    ast::{self, FunctionType, TypeItem},
+    ast::{self, DataMode, ElementItems, ElementMode, FunctionType, TypeItem},
+    instruction,
+    name_package::NamePackage,
     types::{Value, ValueType},
 };
 
 use crate::{
     decoder::{decode, decode_constant_expression},
-    error::{EngineError, TypeMismatch, Unsupported},
+    error::{EngineError, TypeMismatch},
     linker::{link_functions, link_global_variables, link_memorys, link_tables},
     native_module::NativeModule,
     object::NamedAstModule,
-    vm::{Resource, Status, VM},
+    trap::{make_trap_engine_error, TrapCode},
+    validator::validate,
+    vm::{get_function_reference_bases, Resource, Status, VM},
     vm_module::VMModule,
+    vm_segment::{VMDataSegment, VMElementSegment},
     vm_stack::VMStack,
 };
 
+/// 实例化配置
+///
+/// 配置在执行模块的 start 函数之前应用到 VM，所以 start 函数同样受资源限制以及
+/// 燃料的约束。值为 None 的项使用 VM 的默认值。
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct InstanceConfig {
+    /// 调用帧数量的上限
+    pub max_call_depth: Option<usize>,
+
+    /// 栈总大小的上限，单位为槽（slot）
+    pub max_stack_slots: Option<usize>,
+
+    /// 燃料预算，设置之后开启燃料计量
+    pub fuel: Option<u64>,
+}
+
+impl InstanceConfig {
+    fn apply(&self, vm: &mut VM) {
+        if let Some(max_call_depth) = self.max_call_depth {
+            vm.set_max_call_depth(max_call_depth);
+        }
+
+        if let Some(max_stack_slots) = self.max_stack_slots {
+            vm.set_max_stack_slots(max_stack_slots);
+        }
+
+        if let Some(fuel) = self.fuel {
+            vm.set_fuel(fuel);
+        }
+    }
+}
+
+/// 验证并实例化模块
+///
+/// 实例化之前会先验证所有 AST 模块，遇到第一个无效的模块时返回
+/// `EngineError::Validation` 错误。
 pub fn create_instance(
     native_modules: Vec<NativeModule>,
     named_ast_modules: &[NamedAstModule],
 ) -> Result<VM, EngineError> {
+    create_instance_with_config(
+        native_modules,
+        named_ast_modules,
+        &InstanceConfig::default(),
+    )
+}
+
+/// 按照指定的配置验证并实例化模块
+///
+/// 如果 start 函数耗尽了燃料，则返回 `EngineError::Interrupt(Interrupt::OutOfFuel)` 错误；
+/// 如果超出了调用帧数量或者栈总大小的上限，则返回 `EngineError::StartFunctionTrap` 错误。
+pub fn create_instance_with_config(
+    native_modules: Vec<NativeModule>,
+    named_ast_modules: &[NamedAstModule],
+    config: &InstanceConfig,
+) -> Result<VM, EngineError> {
+    for named_ast_module in named_ast_modules {
+        validate(&named_ast_module.module).map_err(|validation_error| {
+            EngineError::Validation(named_ast_module.name.clone(), validation_error)
+        })?;
+    }
+
+    create_instance_without_validation(native_modules, named_ast_modules, config)
+}
+
+/// 实例化模块，但不验证模块
+///
+/// 只能用于已经验证过的模块。操作数栈只储存无类型的槽，指令执行时不再检查
+/// 操作数的数据类型，所以这个函数不对外公开，外部只能通过 `create_instance`
+/// 或者 `engine::Engine` 创建 VM 实例。
+///
+/// 如果需要多次实例化同一组模块，应该使用 `engine::Engine` 编译一次模块，
+/// 然后通过 `engine::Module::instantiate` 创建 VM 实例，以避免重复链接和解码。
+pub(crate) fn create_instance_without_validation(
+    native_modules: Vec<NativeModule>,
+    named_ast_modules: &[NamedAstModule],
+    config: &InstanceConfig,
+) -> Result<VM, EngineError> {
+    let vm_modules = create_vm_modules(&native_modules, named_ast_modules)?;
+    instantiate_vm_modules(
+        native_modules,
+        named_ast_modules,
+        Arc::new(vm_modules),
+        config,
+    )
+}
+
+/// 链接函数、解码指令，创建各个 AST 模块对应的 VMModule
+///
+/// VMModule 只包含不可变的数据（指令、函数信息、类型等），可以被多个 VM 实例共享。
+pub(crate) fn create_vm_modules(
+    native_modules: &[NativeModule],
+    named_ast_modules: &[NamedAstModule],
+) -> Result<Vec<VMModule>, EngineError> {
     // 获取指令列表
     // 指令列表跟 AST 模块列表是一一对应的，所以无需映射表
-    let mut function_items_list = link_functions(&native_modules, named_ast_modules)?;
+    let mut function_items_list = link_functions(native_modules, named_ast_modules)?;
     let mut instructions_list = decode(named_ast_modules, &function_items_list)?;
 
-    // 获取 "表" 实例列表，以及 "AST 模块 - 表" 映射表
-    let (tables, mut module_to_table_index_list) = link_tables(named_ast_modules)?;
-
-    // 获取内存块实例列表，以及 "AST 模块 - 内存块" 映射表
-    let (memory_blocks, mut module_to_memory_block_index_list) = link_memorys(named_ast_modules)?;
-
-    // 获取全局变量实例列表，以及 "AST 模块 - 全局变量列表" 映射表
-    let (global_variables, mut module_to_global_variables_list) =
-        link_global_variables(named_ast_modules)?;
+    // 获取 "AST 模块 - 表"、"AST 模块 - 内存块" 以及 "AST 模块 - 全局变量列表" 映射表
+    //
+    // 映射表只由 AST 模块决定，这里创建的表、内存块以及全局变量实例会被丢弃，
+    // 每个 VM 实例在实例化时会重新创建属于自己的实例。
+    let (_, mut module_to_table_indexes_list) = link_tables(named_ast_modules)?;
+    let (_, mut module_to_memory_block_indexes_list) = link_memorys(named_ast_modules)?;
+    let function_reference_bases =
+        get_function_reference_bases(function_items_list.iter().map(|items| items.len()));
+    let (_, mut module_to_global_variables_list) =
+        link_global_variables(named_ast_modules, &function_reference_bases)?;
+
+    // 获取 "AST 模块 - 数据段列表" 以及 "AST 模块 - 元素段列表" 映射表
+    let (_, mut module_to_data_segment_indexes_list) = create_data_segments(named_ast_modules);
+    let (_, mut module_to_element_segment_indexes_list) =
+        create_element_segments(named_ast_modules);
 
     let ast_module_count = named_ast_modules.len();
     let mut vm_modules: Vec<VMModule> = vec![];
-
     for reverse_index in 0..ast_module_count {
         let function_items = function_items_list.pop().unwrap();
         let instructions = instructions_list.pop().unwrap();
-        let table_index = module_to_table_index_list.pop().unwrap();
-        let memory_index = module_to_memory_block_index_list.pop().unwrap();
+        let table_indexes = module_to_table_indexes_list.pop().unwrap();
+        let memory_block_indexes = module_to_memory_block_indexes_list.pop().unwrap();
         let global_variable_indexes = module_to_global_variables_list.pop().unwrap();
+        let data_segment_indexes = module_to_data_segment_indexes_list.pop().unwrap();
+        let element_segment_indexes = module_to_element_segment_indexes_list.pop().unwrap();
 
         let ast_module_index = ast_module_count - reverse_index - 1;
         let named_ast_module = &named_ast_modules[ast_module_index];
@@ -79,13 +183,18 @@ pub fn create_instance(
 
         let vm_module = VMModule::new(
             name,
-            table_index,
-            memory_index,
+            table_indexes,
+            memory_block_indexes,
             global_variable_indexes,
+            data_segment_indexes,
+            element_segment_indexes,
             function_types,
             internal_function_local_variable_types_list,
             function_items,
             instructions,
+            ast_module.export_items.clone(),
+            NamePackage::new(ast_module),
+            named_ast_module.content_hash,
         );
 
         vm_modules.push(vm_module);
@@ -94,6 +203,33 @@ pub fn create_instance(
     // 因为 vm_modules 的元素是反序添加的，所以这里需要翻转一次
     vm_modules.reverse();
 
+    Ok(vm_modules)
+}
+
+/// 创建 VM 实例
+///
+/// 只创建属于 VM 实例自身的状态，即表、内存块、全局变量、数据段、元素段以及栈，
+/// 指令等不可变的数据则跟其他 VM 实例共享同一份 vm_modules。
+///
+/// vm_modules 必须是由同一组 AST 模块以及（结构相同的）本地模块创建的。
+pub(crate) fn instantiate_vm_modules(
+    native_modules: Vec<NativeModule>,
+    named_ast_modules: &[NamedAstModule],
+    vm_modules: Arc<Vec<VMModule>>,
+    config: &InstanceConfig,
+) -> Result<VM, EngineError> {
+    // 获取 "表"、内存块以及全局变量的实例列表
+    let (tables, _) = link_tables(named_ast_modules)?;
+    let (memory_blocks, _) = link_memorys(named_ast_modules)?;
+    let function_reference_bases =
+        get_function_reference_bases(vm_modules.iter().map(|item| item.function_items.len()));
+    let (global_variables, _) =
+        link_global_variables(named_ast_modules, &function_reference_bases)?;
+
+    // 获取数据段以及元素段的实例列表
+    let (data_segments, _) = create_data_segments(named_ast_modules);
+    let (element_segments, _) = create_element_segments(named_ast_modules);
+
     // 构建 VM 实例
 
     let stack = VMStack::new();
@@ -102,96 +238,287 @@ pub fn create_instance(
         memory_blocks,
         tables,
         global_variables,
+        data_segments,
+        element_segments,
         native_modules,
         vm_modules,
     );
 
     let status = Status::new();
 
-    let mut vm = VM {
-        stack,
-        status,
-        resource,
-    };
+    let mut vm = VM::new(stack, status, resource);
+
+    // 资源限制以及燃料需要在执行 start 函数之前设置
+    config.apply(&mut vm);
 
-    // 填充 data 和 element 到 memory 和 table
+    // 填充 element 到 table，以及填充 data 到 memory，然后执行模块的 start 函数
     //
     // 因为 data 和 element 的常量表达式里可能存在引用数据，所以需要先构造了 vm 之后
     // 再对表达式进行求值。
     // https://webassembly.github.io/spec/core/valid/instructions.html#constant-expressions
-    for ast_module_index in 0..ast_module_count {
+    //
+    // 按照 WebAssembly 规范，对于每个模块，先填充所有（主动模式的）element，然后再
+    // 填充所有（主动模式的）data，填充完毕之后对应的段会被丢弃（而元素段只有被动模式的
+    // 才会被赋值，其他模式的元素段一直保持为已丢弃的状态）。如果填充的范围超出了
+    // table 或者 memory 的范围，则抛出陷阱，实例化失败。
+    //
+    // 模块的 start 函数在该模块的 element 和 data 填充完毕之后执行，
+    // 因为 start 函数可能会调用导入的函数，或者访问导入的表和内存块，所以
+    // 各个模块按照导入的依赖次序（被导入的模块先于导入者）实例化。
+    // https://webassembly.github.io/spec/core/exec/modules.html#instantiation
+    for ast_module_index in get_module_instantiation_order(named_ast_modules) {
         let named_ast_module = &named_ast_modules[ast_module_index];
         let ast_module = &named_ast_module.module;
 
-        let instance_memory_index = vm.resource.vm_modules[ast_module_index].memory_index;
-        let instance_table_index = vm.resource.vm_modules[ast_module_index].table_index;
+        // 填充 element 到 table
+        for (element_index, element_item) in ast_module.element_items.iter().enumerate() {
+            let element_segment_index =
+                vm.resource.vm_modules[ast_module_index].element_segment_indexes[element_index];
+
+            let elements = eval_element_items(&vm, ast_module_index, &element_item.items)?;
+
+            match &element_item.mode {
+                ElementMode::Active {
+                    table_index,
+                    offset_instruction_items,
+                } => {
+                    let instance_table_index = vm.resource.vm_modules[ast_module_index]
+                        .table_indexes[*table_index as usize];
+                    let offset =
+                        eval_offset_expression(&vm, ast_module_index, offset_instruction_items)?;
+
+                    let table_size = vm.resource.tables[instance_table_index].get_size() as usize;
+                    if offset + elements.len() > table_size {
+                        return Err(make_trap_engine_error(&vm, TrapCode::TableOutOfBounds));
+                    }
+
+                    vm.resource.tables[instance_table_index].set_elements(offset, &elements);
+                }
+                ElementMode::Passive => {
+                    // 被动模式的元素段由 `table.init` 指令使用
+                    vm.resource.element_segments[element_segment_index] =
+                        VMElementSegment::new(elements);
+                }
+                ElementMode::Declarative => {
+                    // 声明模式的元素段不会被使用
+                }
+            }
+        }
 
         // 填充 data 到 memory
-        for data_item in &ast_module.data_items {
-            // 内存块索引，目前 WebAssembly 标准只支持 0
-            if data_item.memory_block_index != 0 {
-                return Err(EngineError::Unsupported(
-                    Unsupported::UnsupportedMultipleMemoryBlock,
-                ));
+        for (data_index, data_item) in ast_module.data_items.iter().enumerate() {
+            let data_segment_index =
+                vm.resource.vm_modules[ast_module_index].data_segment_indexes[data_index];
+
+            match &data_item.mode {
+                DataMode::Active {
+                    memory_block_index,
+                    offset_instruction_items,
+                } => {
+                    let instance_memory_index = vm.resource.vm_modules[ast_module_index]
+                        .memory_block_indexes[*memory_block_index as usize];
+                    let address =
+                        eval_offset_expression(&vm, ast_module_index, offset_instruction_items)?;
+                    let data = &data_item.data;
+
+                    let byte_count =
+                        vm.resource.memory_blocks[instance_memory_index].get_byte_count();
+                    if address + data.len() > byte_count {
+                        return Err(make_trap_engine_error(&vm, TrapCode::MemoryOutOfBounds));
+                    }
+
+                    vm.resource.memory_blocks[instance_memory_index].write_bytes(address, data);
+                    vm.resource.data_segments[data_segment_index].drop_data();
+                }
+                DataMode::Passive => {
+                    // 被动模式的数据段由 `memory.init` 指令使用
+                }
             }
+        }
 
-            let offset_instruction_items = &data_item.offset_instruction_items;
-            let constant_expression = decode_constant_expression(offset_instruction_items)?;
-            let offset_value = vm.eval_constant_expression(&constant_expression)?;
-
-            let address = match offset_value {
-                Value::I32(v) => v as usize,
-                _ => {
-                    return Err(EngineError::TypeMismatch(
-                        TypeMismatch::ConstantExpressionValueTypeMismatch(
-                            ValueType::I32,
-                            offset_value.get_type(),
-                        ),
+        // 执行 start 函数
+        if let Some(start_function_index) = ast_module.start_function_index {
+            match vm.eval_function_by_index(ast_module_index, start_function_index as usize, &[]) {
+                Ok(_) => {}
+                Err(EngineError::Trap(trap)) => {
+                    return Err(EngineError::StartFunctionTrap(
+                        named_ast_module.name.clone(),
+                        trap,
                     ));
                 }
-            };
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    Ok(vm)
+}
+
+/// 获取模块的实例化次序
+///
+/// 被导入的模块排在导入者之前，没有依赖关系的模块则保持原有的次序。
+/// 如果模块之间存在循环导入，则循环内的模块按照原有的次序实例化。
+fn get_module_instantiation_order(named_ast_modules: &[NamedAstModule]) -> Vec<usize> {
+    let mut order: Vec<usize> = vec![];
+    let mut visited = vec![false; named_ast_modules.len()];
+
+    for ast_module_index in 0..named_ast_modules.len() {
+        visit_module_dependencies(
+            named_ast_modules,
+            ast_module_index,
+            &mut visited,
+            &mut order,
+        );
+    }
+
+    order
+}
+
+/// 深度优先遍历模块的导入依赖，先添加被导入的模块，再添加模块自身
+fn visit_module_dependencies(
+    named_ast_modules: &[NamedAstModule],
+    ast_module_index: usize,
+    visited: &mut Vec<bool>,
+    order: &mut Vec<usize>,
+) {
+    if visited[ast_module_index] {
+        return;
+    }
+
+    visited[ast_module_index] = true;
 
-            let data = &data_item.data;
-            vm.resource.memory_blocks[instance_memory_index].write_bytes(address, data);
+    for import_item in &named_ast_modules[ast_module_index].module.import_items {
+        // 导入本地模块的项目没有依赖次序的问题，找不到对应的 AST 模块时忽略即可
+        if let Some(target_ast_module_index) = named_ast_modules
+            .iter()
+            .position(|item| item.name == import_item.module_name)
+        {
+            visit_module_dependencies(named_ast_modules, target_ast_module_index, visited, order);
         }
+    }
 
-        // 填充 element 到 table
-        for element_item in &ast_module.element_items {
-            // 表索引，目前 WebAssembly 标准只支持 0
-            if element_item.table_index != 0 {
-                return Err(EngineError::Unsupported(
-                    Unsupported::UnsupportedMultipleTable,
-                ));
-            }
+    order.push(ast_module_index);
+}
+
+/// 求 data 和 element 的偏移值常量表达式的值
+///
+/// 偏移值是 i32 类型，但应该被视为无符号整数
+fn eval_offset_expression(
+    vm: &VM,
+    vm_module_index: usize,
+    offset_instruction_items: &[instruction::Instruction],
+) -> Result<usize, EngineError> {
+    let constant_expression = decode_constant_expression(offset_instruction_items)?;
+    let offset_value = vm.eval_constant_expression(vm_module_index, &constant_expression)?;
+
+    match offset_value {
+        Value::I32(v) => Ok(v as u32 as usize),
+        _ => Err(EngineError::TypeMismatch(
+            TypeMismatch::ConstantExpressionValueTypeMismatch(
+                ValueType::I32,
+                offset_value.get_type(),
+            ),
+        )),
+    }
+}
+
+/// 创建数据段实例列表，以及 "AST 模块 - 数据段列表" 映射表
+fn create_data_segments(
+    named_ast_modules: &[NamedAstModule],
+) -> (Vec<VMDataSegment>, Vec<Vec<usize>>) {
+    let mut data_segments: Vec<VMDataSegment> = vec![];
+    let mut module_to_data_segment_indexes_list: Vec<Vec<usize>> = vec![];
+
+    for named_ast_module in named_ast_modules {
+        let data_segment_indexes = named_ast_module
+            .module
+            .data_items
+            .iter()
+            .map(|data_item| {
+                data_segments.push(VMDataSegment::new(data_item.data.clone()));
+                data_segments.len() - 1
+            })
+            .collect::<Vec<usize>>();
+
+        module_to_data_segment_indexes_list.push(data_segment_indexes);
+    }
+
+    (data_segments, module_to_data_segment_indexes_list)
+}
+
+/// 创建元素段实例列表，以及 "AST 模块 - 元素段列表" 映射表
+///
+/// 因为元素的常量表达式需要在构造了 vm 之后才能求值，所以这里创建的元素段都是空的。
+fn create_element_segments(
+    named_ast_modules: &[NamedAstModule],
+) -> (Vec<VMElementSegment>, Vec<Vec<usize>>) {
+    let mut element_segments: Vec<VMElementSegment> = vec![];
+    let mut module_to_element_segment_indexes_list: Vec<Vec<usize>> = vec![];
+
+    for named_ast_module in named_ast_modules {
+        let element_segment_indexes = named_ast_module
+            .module
+            .element_items
+            .iter()
+            .map(|_| {
+                element_segments.push(VMElementSegment::new(vec![]));
+                element_segments.len() - 1
+            })
+            .collect::<Vec<usize>>();
+
+        module_to_element_segment_indexes_list.push(element_segment_indexes);
+    }
 
-            let offset_instruction_items = &element_item.offset_instruction_items;
-            let constant_expression = decode_constant_expression(offset_instruction_items)?;
-            let offset_value = vm.eval_constant_expression(&constant_expression)?;
+    (element_segments, module_to_element_segment_indexes_list)
+}
 
-            let offset = match offset_value {
-                Value::I32(v) => v as usize,
-                _ => {
-                    return Err(EngineError::TypeMismatch(
+/// 求元素项的各个元素的值
+///
+/// 元素的值是函数引用（funcref）或者宿主的句柄（externref），None 表示空引用
+fn eval_element_items(
+    vm: &VM,
+    vm_module_index: usize,
+    element_items: &ElementItems,
+) -> Result<Vec<Option<u32>>, EngineError> {
+    match element_items {
+        ElementItems::FunctionIndices(function_indices) => Ok(function_indices
+            .iter()
+            .map(|function_index| {
+                Some(
+                    vm.resource
+                        .get_function_reference(vm_module_index, *function_index as usize),
+                )
+            })
+            .collect::<Vec<Option<u32>>>()),
+        ElementItems::Expressions(ref_type, expressions) => expressions
+            .iter()
+            .map(|instruction_items| {
+                let constant_expression = decode_constant_expression(instruction_items)?;
+                let value = vm.eval_constant_expression(vm_module_index, &constant_expression)?;
+
+                match value {
+                    Value::FuncRef(element) | Value::ExternRef(element)
+                        if &value.get_type() == ref_type =>
+                    {
+                        Ok(element)
+                    }
+                    _ => Err(EngineError::TypeMismatch(
                         TypeMismatch::ConstantExpressionValueTypeMismatch(
-                            ValueType::I32,
-                            offset_value.get_type(),
+                            ref_type.clone(),
+                            value.get_type(),
                         ),
-                    ));
+                    )),
                 }
-            };
-
-            for (index, function_index) in element_item.function_indices.iter().enumerate() {
-                vm.resource.tables[instance_table_index]
-                    .set_element(offset + index, *function_index)?;
-            }
-        }
+            })
+            .collect::<Result<Vec<Option<u32>>, EngineError>>(),
     }
-
-    Ok(vm)
 }
 
 /// 从 named_ast_modules 的最后一个元素开始，寻找 ast module 当中
-/// `start` 段指定的函数或者导出名称为 `_start` 的函数的索引。
+/// 导出名称为 `_start` 的函数的索引。
+///
+/// 注意 `start` 段指定的函数会在模块实例化时（即 `create_instance` 函数里）执行，
+/// 所以不作为入口函数。
 pub fn get_entry_module_and_function_index(
     named_ast_modules: &[NamedAstModule],
 ) -> Option<(usize, usize)> {
@@ -199,10 +526,7 @@ pub fn get_entry_module_and_function_index(
 
     for (module_index, named_ast_module) in named_ast_modules.iter().enumerate().rev() {
         let ast_module = &named_ast_module.module;
-        if let Some(i) = ast_module.start_function_index {
-            option_mod_and_func_index = Some((module_index, i as usize));
-            break;
-        } else if let Some(i) = find_ast_module_export_function(ast_module, "_start") {
+        if let Some(i) = find_ast_module_export_function(ast_module, "_start") {
             // 查找导出函数当中，名字为 `_start` 的函数的索引
             option_mod_and_func_index = Some((module_index, i as usize));
             break;
@@ -230,10 +554,11 @@ pub fn find_ast_module_export_function(
 
 #[cfg(test)]
 mod tests {
-    use std::{env, fs};
+    use std::{cell::Cell, env, fs, rc::Rc, sync::Arc, thread, time::Duration};
 
     use anvm_ast::{
-        ast,
+        ast::ElementItems,
+        instruction,
         types::{Value, ValueType},
     };
     use anvm_binary_parser::parser;
@@ -241,13 +566,24 @@ mod tests {
     use pretty_assertions::assert_eq;
 
     use crate::{
-        error::{EngineError, NativeTerminate},
+        engine::Engine,
+        error::{
+            EngineError, Interrupt, InvalidOperation, NativeTerminate, ObjectNotFound, OutOfRange,
+            TypeMismatch,
+        },
+        interpreter::Operand,
         native_module::{EmptyModuleContext, NativeModule},
-        object::NamedAstModule,
-        vm::VM,
+        object::{self, Control, FunctionItem, NamedAstModule},
+        snapshot::SnapshotError,
+        trap::{BacktraceFrame, Trap, TrapCode},
+        validator::{ValidationError, ValidationLocation},
+        vm::{Breakpoint, CallFunctionResult, ExecutionResult, VM},
     };
 
-    use super::{create_instance, find_ast_module_export_function};
+    use super::{
+        create_instance, create_instance_with_config, create_instance_without_validation,
+        find_ast_module_export_function, InstanceConfig,
+    };
 
     // 辅助方法
     fn get_test_binary_resource(filename: &str) -> Vec<u8> {
@@ -275,9 +611,10 @@ mod tests {
         ))
     }
 
-    fn get_test_ast_module(filename: &str) -> ast::Module {
+    fn get_test_named_ast_module(name: &str, filename: &str) -> NamedAstModule {
         let bytes = get_test_binary_resource(filename);
-        parser::parse(&bytes).unwrap()
+        let ast_module = parser::parse(&bytes).unwrap();
+        NamedAstModule::new(name, ast_module)
     }
 
     fn native_function_add_i32(
@@ -291,6 +628,25 @@ mod tests {
         }
     }
 
+    /// 通过函数 $guest_countdown 递归调用自身，返回递归的层数
+    fn native_function_countdown(
+        vm: &mut VM,
+        _native_module_index: usize,
+        params: &[Value],
+    ) -> Result<Vec<Value>, NativeTerminate> {
+        match params[0] {
+            Value::I32(0) => Ok(vec![Value::I32(0)]),
+            Value::I32(n) => match vm.eval_function_by_index(0, 1, &vec![Value::I32(n - 1)]) {
+                Ok(results) => match results[0] {
+                    Value::I32(count) => Ok(vec![Value::I32(count + 1)]),
+                    _ => panic!("incorrect data type of the function results"),
+                },
+                Err(e) => panic!("failed to reenter the native function: {}", e),
+            },
+            _ => panic!("incorrect data type of the native function arguments"),
+        }
+    }
+
     fn native_function_sub_i32(
         _vm: &mut VM,
         _native_module_index: usize,
@@ -330,8 +686,7 @@ mod tests {
         function_index: usize,
         args: &[Value],
     ) -> Result<Vec<Value>, EngineError> {
-        let ast_module = get_test_ast_module(filename);
-        let named_ast_module = NamedAstModule::new("test", ast_module);
+        let named_ast_module = get_test_named_ast_module("test", filename);
         let mut vm = create_instance(vec![], &vec![named_ast_module])?;
 
         vm.eval_function_by_index(0, function_index, args)
@@ -342,11 +697,11 @@ mod tests {
         export_function_name: &str,
         args: &[Value],
     ) -> Result<Vec<Value>, EngineError> {
-        let ast_module = get_test_ast_module(filename);
-        let function_index = find_ast_module_export_function(&ast_module, export_function_name)
-            .expect("function not found");
+        let named_ast_module = get_test_named_ast_module("test", filename);
+        let function_index =
+            find_ast_module_export_function(&named_ast_module.module, export_function_name)
+                .expect("function not found");
 
-        let named_ast_module = NamedAstModule::new("test", ast_module);
         let mut vm = create_instance(vec![], &vec![named_ast_module])?;
 
         vm.eval_function_by_index(0, function_index as usize, args)
@@ -358,8 +713,7 @@ mod tests {
         args: &[Value],
         initial_memory_data: &[u8],
     ) -> Result<Vec<Value>, EngineError> {
-        let ast_module = get_test_ast_module(filename);
-        let named_ast_module = NamedAstModule::new("test", ast_module);
+        let named_ast_module = get_test_named_ast_module("test", filename);
         let mut vm = create_instance(vec![], &vec![named_ast_module])?;
 
         vm.resource.memory_blocks[0].write_bytes(0, initial_memory_data);
@@ -373,8 +727,7 @@ mod tests {
         address: usize,
         length: usize,
     ) -> Result<(Vec<Value>, Vec<u8>), EngineError> {
-        let ast_module = get_test_ast_module(filename);
-        let named_ast_module = NamedAstModule::new("test", ast_module);
+        let named_ast_module = get_test_named_ast_module("test", filename);
         let mut vm = create_instance(vec![], &vec![named_ast_module])?;
 
         let result = vm.eval_function_by_index(0, function_index, args)?;
@@ -390,20 +743,16 @@ mod tests {
     ) -> Result<Vec<Value>, EngineError> {
         let native_module = get_test_native_module();
 
-        let named_ast_module_callee = NamedAstModule::new(
-            "callee",
-            get_test_ast_module("test-function-call-callee.wasm"),
-        );
+        let named_ast_module_callee =
+            get_test_named_ast_module("callee", "test-function-call-callee.wasm");
 
-        let named_ast_module_callee_intermediate = NamedAstModule::new(
+        let named_ast_module_callee_intermediate = get_test_named_ast_module(
             "intermediate",
-            get_test_ast_module("test-function-call-callee-intermediate.wasm"),
+            "test-function-call-callee-intermediate.wasm",
         );
 
-        let named_ast_module_caller = NamedAstModule::new(
-            "caller",
-            get_test_ast_module("test-function-call-caller.wasm"),
-        );
+        let named_ast_module_caller =
+            get_test_named_ast_module("caller", "test-function-call-caller.wasm");
 
         let mut vm = create_instance(
             vec![native_module],
@@ -417,6 +766,44 @@ mod tests {
         vm.eval_function_by_index(2, function_index, args)
     }
 
+    /// 创建测试用的 VM 实例
+    ///
+    /// named_filenames 为模块名称以及模块文件名称的列表
+    fn create_test_instance(
+        named_filenames: &[(&str, &str)],
+        native_modules: Vec<NativeModule>,
+    ) -> VM {
+        let named_ast_modules = named_filenames
+            .iter()
+            .map(|(name, filename)| get_test_named_ast_module(name, filename))
+            .collect::<Vec<NamedAstModule>>();
+
+        create_instance(native_modules, &named_ast_modules).unwrap()
+    }
+
+    fn get_function_start_address(vm: &VM, vm_module_index: usize, function_index: usize) -> usize {
+        match &vm.resource.vm_modules[vm_module_index].function_items[function_index] {
+            FunctionItem::Normal { start_address, .. } => *start_address,
+            _ => panic!("not a normal function"),
+        }
+    }
+
+    /// 每次调用都使用新的 VM 实例，因为陷阱发生之后 VM 的状态会停留在出错的位置
+    fn eval_trap_test_function(
+        function_index: usize,
+        arguments: &[Value],
+    ) -> Result<Vec<Value>, EngineError> {
+        let mut vm = create_test_instance(&[("test", "test-trap.wasm")], vec![]);
+        vm.eval_function_by_index(0, function_index, arguments)
+    }
+
+    fn get_trap_code(result: Result<Vec<Value>, EngineError>) -> TrapCode {
+        match result {
+            Err(EngineError::Trap(trap)) => trap.code,
+            _ => panic!("expected a trap"),
+        }
+    }
+
     fn convert_i32_list(values: &[i32]) -> Vec<Value> {
         values
             .iter()
@@ -1026,6 +1413,281 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_function_call_native_closure() {
+        let total = Rc::new(Cell::new(0));
+        let total_clone = Rc::clone(&total);
+
+        let mut native_module = NativeModule::new("host", Box::new(EmptyModuleContext::new()));
+
+        native_module.add_native_function(
+            "accumulate",
+            vec![ValueType::I32],
+            vec!["n"],
+            vec![ValueType::I32],
+            move |_vm, _native_module_index, args| {
+                if let Value::I32(n) = args[0] {
+                    total_clone.set(total_clone.get() + n);
+                }
+                Ok(vec![Value::I32(total_clone.get())])
+            },
+        );
+
+        // `FnMut` 闭包无法通过 VM 再次调用自身
+        native_module.add_native_function_mut(
+            "reenter",
+            vec![],
+            vec![],
+            vec![ValueType::I32],
+            move |vm, _native_module_index, _args| {
+                let result = vm.eval_function_by_index(0, 1, &vec![]);
+                assert!(matches!(
+                    result,
+                    Err(EngineError::InvalidOperation(
+                        InvalidOperation::ReentrantNativeFunctionCall {
+                            native_module_index: 0,
+                            function_index: 1
+                        }
+                    ))
+                ));
+                Ok(vec![Value::I32(0)])
+            },
+        );
+
+        let named_ast_module = get_test_named_ast_module("test", "test-native-closure.wasm");
+        let mut vm = create_instance(vec![native_module], &vec![named_ast_module]).unwrap();
+
+        assert_eq!(
+            vm.eval_function_by_index(0, 2, &vec![Value::I32(10)])
+                .unwrap(),
+            vec![Value::I32(20)]
+        );
+        assert_eq!(
+            vm.eval_function_by_index(0, 2, &vec![Value::I32(5)])
+                .unwrap(),
+            vec![Value::I32(30)]
+        );
+        assert_eq!(total.get(), 30);
+
+        // 直接调用导入的本地函数
+        assert_eq!(
+            vm.eval_function_by_index(0, 0, &vec![Value::I32(1)])
+                .unwrap(),
+            vec![Value::I32(31)]
+        );
+
+        assert_eq!(
+            vm.eval_function_by_index(0, 1, &vec![]).unwrap(),
+            vec![Value::I32(0)]
+        );
+    }
+
+    #[test]
+    fn test_function_call_native_reentrant() {
+        let mut native_module = NativeModule::new("host", Box::new(EmptyModuleContext::new()));
+        native_module.add_native_function(
+            "countdown",
+            vec![ValueType::I32],
+            vec!["n"],
+            vec![ValueType::I32],
+            native_function_countdown,
+        );
+
+        let named_ast_module = get_test_named_ast_module("test", "test-native-reentrant.wasm");
+        let mut vm = create_instance(vec![native_module], &vec![named_ast_module]).unwrap();
+
+        // 本地函数 -> 函数 $guest_countdown -> 本地函数 -> ...
+        assert_eq!(
+            vm.eval_function_by_index(0, 1, &vec![Value::I32(10)])
+                .unwrap(),
+            vec![Value::I32(10)]
+        );
+
+        // 直接调用本地函数
+        assert_eq!(
+            vm.eval_function_by_index(0, 0, &vec![Value::I32(3)])
+                .unwrap(),
+            vec![Value::I32(3)]
+        );
+        assert_eq!(vm.get_call_depth(), 0);
+    }
+
+    #[test]
+    fn test_typed_function() {
+        let mut vm = create_test_instance(&[("test", "test-typed-function.wasm")], vec![]);
+
+        let mix = vm
+            .get_typed_function::<(i32, i64), f64>("test", "mix")
+            .unwrap();
+        assert_eq!(mix.call(&mut vm, (3, 4)).unwrap(), 7.0);
+        assert_eq!(mix.call(&mut vm, (-10, 2)).unwrap(), -8.0);
+
+        let swap = vm
+            .get_typed_function::<(i32, f32), (f32, i32)>("test", "swap")
+            .unwrap();
+        assert_eq!(swap.call(&mut vm, (1, 2.5)).unwrap(), (2.5, 1));
+
+        let answer = vm.get_typed_function::<(), i64>("test", "answer").unwrap();
+        assert_eq!(answer.call(&mut vm, ()).unwrap(), 42);
+
+        let nothing = vm.get_typed_function::<(), ()>("test", "nothing").unwrap();
+        assert_eq!(nothing.call(&mut vm, ()).unwrap(), ());
+
+        // 签名不符合
+        assert!(matches!(
+            vm.get_typed_function::<(i32, i32), f64>("test", "mix"),
+            Err(EngineError::TypeMismatch(
+                TypeMismatch::TypedFunctionSignatureMismatch(_, _)
+            ))
+        ));
+        assert!(matches!(
+            vm.get_typed_function::<(i32, i64), (f64, f64)>("test", "mix"),
+            Err(EngineError::TypeMismatch(
+                TypeMismatch::TypedFunctionSignatureMismatch(_, _)
+            ))
+        ));
+
+        // 函数或者模块不存在
+        assert!(matches!(
+            vm.get_typed_function::<(), ()>("test", "unknown"),
+            Err(EngineError::ObjectNotFound(
+                ObjectNotFound::FunctionNotFound(_, _)
+            ))
+        ));
+        assert!(matches!(
+            vm.get_typed_function::<(), ()>("unknown", "nothing"),
+            Err(EngineError::ObjectNotFound(ObjectNotFound::ModuleNotFound(
+                _
+            )))
+        ));
+    }
+
+    #[test]
+    fn test_typed_function_from_another_vm() {
+        let vm0 = create_test_instance(&[("test", "test-fuel.wasm")], vec![]);
+        let forever = vm0.get_typed_function::<(), ()>("test", "forever").unwrap();
+
+        // 另外一个 VM 里同一个索引的函数返回了一个 i32
+        let mut vm1 = create_test_instance(&[("test", "test-const.wasm")], vec![]);
+        match forever.call(&mut vm1, ()) {
+            Err(EngineError::TypeMismatch(TypeMismatch::TypedFunctionResultTypeMismatch {
+                vm_module_index,
+                function_index,
+                result_types,
+                value_types,
+            })) => {
+                assert_eq!(vm_module_index, 0);
+                assert_eq!(function_index, 0);
+                assert_eq!(result_types, vec![]);
+                assert_eq!(value_types, vec![ValueType::I32]);
+            }
+            _ => panic!("expected a typed function result type mismatch"),
+        }
+    }
+
+    #[test]
+    fn test_typed_function_native() {
+        let native_module = get_test_native_module();
+        let named_ast_modules = vec![
+            get_test_named_ast_module("callee", "test-function-call-callee.wasm"),
+            get_test_named_ast_module(
+                "intermediate",
+                "test-function-call-callee-intermediate.wasm",
+            ),
+        ];
+        let mut vm = create_instance(vec![native_module], &named_ast_modules).unwrap();
+
+        // 重新导出的函数（包括本地函数）
+        let re_sub = vm
+            .get_typed_function::<(i32, i32), i32>("intermediate", "re_sub")
+            .unwrap();
+        assert_eq!(re_sub.call(&mut vm, (11, 22)).unwrap(), -11);
+
+        let re_mul = vm
+            .get_typed_function::<(i32, i32), i32>("intermediate", "re_mul")
+            .unwrap();
+        assert_eq!(re_mul.call(&mut vm, (3, 4)).unwrap(), 12);
+    }
+
+    #[test]
+    fn test_export_items() {
+        let mut vm = create_test_instance(&[("test", "test-export.wasm")], vec![]);
+
+        // 全局变量
+        let counter = vm.get_export_global("test", "counter").unwrap();
+        assert_eq!(counter.get_value(&vm), Value::I32(10));
+        assert!(counter.get_global_type(&vm).mutable);
+
+        counter.set_value(&mut vm, Value::I32(20)).unwrap();
+        assert_eq!(counter.get_value(&vm), Value::I32(20));
+        assert_eq!(
+            vm.eval_function_by_index(0, 0, &vec![]).unwrap(),
+            vec![Value::I32(20)]
+        );
+
+        assert!(matches!(
+            counter.set_value(&mut vm, Value::I64(30)),
+            Err(EngineError::TypeMismatch(
+                TypeMismatch::SetGlobalVariableValueTypeMismatch(
+                    0,
+                    0,
+                    ValueType::I32,
+                    ValueType::I64
+                )
+            ))
+        ));
+
+        let limit = vm.get_export_global("test", "limit").unwrap();
+        assert_eq!(limit.get_value(&vm), Value::I64(100));
+        assert!(matches!(
+            limit.set_value(&mut vm, Value::I64(200)),
+            Err(EngineError::InvalidOperation(
+                InvalidOperation::ImmutableGlobalVariable(0, 1)
+            ))
+        ));
+        assert_eq!(limit.get_value(&vm), Value::I64(100));
+
+        // 内存块
+        let memory = vm.get_export_memory("test", "memory").unwrap();
+        assert_eq!(memory.get(&vm).get_page_count(), 1);
+        assert_eq!(memory.get(&vm).read_bytes(16, 5), b"hello");
+
+        memory.get_mut(&mut vm).write_i32(32, 0x1234);
+        assert_eq!(
+            vm.eval_function_by_index(0, 1, &vec![Value::I32(32)])
+                .unwrap(),
+            vec![Value::I32(0x1234)]
+        );
+
+        // 表
+        let table = vm.get_export_table("test", "table").unwrap();
+        assert_eq!(table.get(&vm).get_size(), 2);
+        assert_eq!(table.get(&vm).get_elements(), &[Some(0), None]);
+
+        table.get_mut(&mut vm).set_element(1, 1).unwrap();
+        assert_eq!(table.get(&vm).get_element(1).unwrap(), Some(1));
+
+        // 导出项不存在，或者导出项的种类不符合
+        assert!(matches!(
+            vm.get_export_global("test", "memory"),
+            Err(EngineError::ObjectNotFound(
+                ObjectNotFound::GlobalVariableNotFound(_, _)
+            ))
+        ));
+        assert!(matches!(
+            vm.get_export_memory("test", "unknown"),
+            Err(EngineError::ObjectNotFound(
+                ObjectNotFound::MemoryBlockFound(_, _)
+            ))
+        ));
+        assert!(matches!(
+            vm.get_export_table("unknown", "table"),
+            Err(EngineError::ObjectNotFound(ObjectNotFound::ModuleNotFound(
+                _
+            )))
+        ));
+    }
+
     #[test]
     fn test_function_call_external() {
         // 测试 $ex_mul
@@ -1197,6 +1859,34 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_block_recursion_and_loop() {
+        // 递归调用以及 if 结构块
+        assert_eq!(
+            eval_by_export_function_name("test-fib.wasm", "fib", &vec![Value::I32(10)]).unwrap(),
+            vec![Value::I32(55)]
+        );
+
+        // loop 结构块以及跨层跳转
+        let named_ast_module = get_test_named_ast_module("test", "test-loop.wasm");
+        let mut vm = create_instance(vec![], &vec![named_ast_module]).unwrap();
+
+        assert_eq!(
+            vm.eval_function_by_index(0, 0, &vec![Value::I32(100)])
+                .unwrap(),
+            vec![Value::I64(5050)]
+        );
+        assert_eq!(
+            vm.eval_function_by_index(0, 1, &vec![Value::I32(10), Value::I32(20)])
+                .unwrap(),
+            vec![Value::I32(100)]
+        );
+
+        // 执行完毕之后，操作数栈和控制栈均为空
+        assert_eq!(vm.stack.get_size(), 0);
+        assert_eq!(vm.stack.get_frame_count(), 0);
+    }
+
     #[test]
     fn test_lib_c() {
         let module_name = "test-lib-c.wasm";
@@ -1230,4 +1920,2319 @@ mod tests {
             vec![Value::I32(726)]
         );
     }
+
+    #[test]
+    fn test_lib_rust_trunc_sat() {
+        // 由 rustc 编译而得，浮点数到整数的 `as` 转换被编译为 `trunc_sat` 指令
+        let module_name = "test-lib-rust-trunc-sat.wasm";
+
+        // f32_to_i32
+        assert_eq!(
+            eval_by_export_function_name(module_name, "f32_to_i32", &vec![Value::F32(1.9)])
+                .unwrap(),
+            vec![Value::I32(1)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(module_name, "f32_to_i32", &vec![Value::F32(-1.9)])
+                .unwrap(),
+            vec![Value::I32(-1)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(module_name, "f32_to_i32", &vec![Value::F32(f32::NAN)])
+                .unwrap(),
+            vec![Value::I32(0)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(
+                module_name,
+                "f32_to_i32",
+                &vec![Value::F32(f32::INFINITY)]
+            )
+            .unwrap(),
+            vec![Value::I32(i32::MAX)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(
+                module_name,
+                "f32_to_i32",
+                &vec![Value::F32(f32::NEG_INFINITY)]
+            )
+            .unwrap(),
+            vec![Value::I32(i32::MIN)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(module_name, "f32_to_i32", &vec![Value::F32(3e9)])
+                .unwrap(),
+            vec![Value::I32(i32::MAX)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(module_name, "f32_to_i32", &vec![Value::F32(-3e9)])
+                .unwrap(),
+            vec![Value::I32(i32::MIN)]
+        );
+
+        // f32_to_u32
+        assert_eq!(
+            eval_by_export_function_name(module_name, "f32_to_u32", &vec![Value::F32(1.9)])
+                .unwrap(),
+            vec![Value::I32(1)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(module_name, "f32_to_u32", &vec![Value::F32(-1.9)])
+                .unwrap(),
+            vec![Value::I32(0)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(module_name, "f32_to_u32", &vec![Value::F32(f32::NAN)])
+                .unwrap(),
+            vec![Value::I32(0)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(
+                module_name,
+                "f32_to_u32",
+                &vec![Value::F32(f32::INFINITY)]
+            )
+            .unwrap(),
+            vec![Value::I32(u32::MAX as i32)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(
+                module_name,
+                "f32_to_u32",
+                &vec![Value::F32(f32::NEG_INFINITY)]
+            )
+            .unwrap(),
+            vec![Value::I32(0)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(module_name, "f32_to_u32", &vec![Value::F32(3e9)])
+                .unwrap(),
+            vec![Value::I32(3000000000u32 as i32)]
+        );
+
+        // f64_to_i32
+        assert_eq!(
+            eval_by_export_function_name(
+                module_name,
+                "f64_to_i32",
+                &vec![Value::F64(-2147483648.9)]
+            )
+            .unwrap(),
+            vec![Value::I32(i32::MIN)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(
+                module_name,
+                "f64_to_i32",
+                &vec![Value::F64(2147483647.9)]
+            )
+            .unwrap(),
+            vec![Value::I32(i32::MAX)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(
+                module_name,
+                "f64_to_i32",
+                &vec![Value::F64(2147483648.0)]
+            )
+            .unwrap(),
+            vec![Value::I32(i32::MAX)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(module_name, "f64_to_i32", &vec![Value::F64(f64::NAN)])
+                .unwrap(),
+            vec![Value::I32(0)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(
+                module_name,
+                "f64_to_i32",
+                &vec![Value::F64(f64::NEG_INFINITY)]
+            )
+            .unwrap(),
+            vec![Value::I32(i32::MIN)]
+        );
+
+        // f64_to_u32
+        assert_eq!(
+            eval_by_export_function_name(
+                module_name,
+                "f64_to_u32",
+                &vec![Value::F64(4294967295.9)]
+            )
+            .unwrap(),
+            vec![Value::I32(u32::MAX as i32)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(
+                module_name,
+                "f64_to_u32",
+                &vec![Value::F64(4294967296.0)]
+            )
+            .unwrap(),
+            vec![Value::I32(u32::MAX as i32)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(module_name, "f64_to_u32", &vec![Value::F64(-0.9)])
+                .unwrap(),
+            vec![Value::I32(0)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(module_name, "f64_to_u32", &vec![Value::F64(f64::NAN)])
+                .unwrap(),
+            vec![Value::I32(0)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(
+                module_name,
+                "f64_to_u32",
+                &vec![Value::F64(f64::INFINITY)]
+            )
+            .unwrap(),
+            vec![Value::I32(u32::MAX as i32)]
+        );
+
+        // f32_to_i64
+        assert_eq!(
+            eval_by_export_function_name(module_name, "f32_to_i64", &vec![Value::F32(-1.5)])
+                .unwrap(),
+            vec![Value::I64(-1)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(module_name, "f32_to_i64", &vec![Value::F32(f32::NAN)])
+                .unwrap(),
+            vec![Value::I64(0)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(
+                module_name,
+                "f32_to_i64",
+                &vec![Value::F32(f32::INFINITY)]
+            )
+            .unwrap(),
+            vec![Value::I64(i64::MAX)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(module_name, "f32_to_i64", &vec![Value::F32(-1e19)])
+                .unwrap(),
+            vec![Value::I64(i64::MIN)]
+        );
+
+        // f32_to_u64
+        assert_eq!(
+            eval_by_export_function_name(module_name, "f32_to_u64", &vec![Value::F32(1e10)])
+                .unwrap(),
+            vec![Value::I64(10000000000)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(module_name, "f32_to_u64", &vec![Value::F32(-1.5)])
+                .unwrap(),
+            vec![Value::I64(0)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(module_name, "f32_to_u64", &vec![Value::F32(f32::NAN)])
+                .unwrap(),
+            vec![Value::I64(0)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(
+                module_name,
+                "f32_to_u64",
+                &vec![Value::F32(f32::INFINITY)]
+            )
+            .unwrap(),
+            vec![Value::I64(u64::MAX as i64)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(module_name, "f32_to_u64", &vec![Value::F32(1e20)])
+                .unwrap(),
+            vec![Value::I64(u64::MAX as i64)]
+        );
+
+        // f64_to_i64
+        assert_eq!(
+            eval_by_export_function_name(
+                module_name,
+                "f64_to_i64",
+                &vec![Value::F64(-9223372036854775808.0)]
+            )
+            .unwrap(),
+            vec![Value::I64(i64::MIN)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(
+                module_name,
+                "f64_to_i64",
+                &vec![Value::F64(9223372036854775808.0)]
+            )
+            .unwrap(),
+            vec![Value::I64(i64::MAX)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(module_name, "f64_to_i64", &vec![Value::F64(f64::NAN)])
+                .unwrap(),
+            vec![Value::I64(0)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(
+                module_name,
+                "f64_to_i64",
+                &vec![Value::F64(f64::NEG_INFINITY)]
+            )
+            .unwrap(),
+            vec![Value::I64(i64::MIN)]
+        );
+
+        // f64_to_u64
+        assert_eq!(
+            eval_by_export_function_name(
+                module_name,
+                "f64_to_u64",
+                &vec![Value::F64(18446744073709549568.0)]
+            )
+            .unwrap(),
+            vec![Value::I64(18446744073709549568u64 as i64)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(
+                module_name,
+                "f64_to_u64",
+                &vec![Value::F64(18446744073709551616.0)]
+            )
+            .unwrap(),
+            vec![Value::I64(u64::MAX as i64)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(module_name, "f64_to_u64", &vec![Value::F64(-1.0)])
+                .unwrap(),
+            vec![Value::I64(0)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(module_name, "f64_to_u64", &vec![Value::F64(f64::NAN)])
+                .unwrap(),
+            vec![Value::I64(0)]
+        );
+        assert_eq!(
+            eval_by_export_function_name(
+                module_name,
+                "f64_to_u64",
+                &vec![Value::F64(f64::INFINITY)]
+            )
+            .unwrap(),
+            vec![Value::I64(u64::MAX as i64)]
+        );
+    }
+
+    #[test]
+    fn test_breakpoint() {
+        let mut vm = create_test_instance(
+            &[("test", "test-debug.wasm")],
+            vec![get_test_native_module()],
+        );
+
+        // 函数索引：
+        // 0 -> $sub (native)
+        // 1 -> $add
+        // 2 -> $inc
+        // 3 -> $calc
+        let add_start_address = get_function_start_address(&vm, 0, 1);
+        let calc_start_address = get_function_start_address(&vm, 0, 3);
+
+        let breakpoint_add = vm
+            .add_breakpoint_by_export_function_name("test", "add")
+            .unwrap();
+        assert_eq!(breakpoint_add, Breakpoint::new(0, 1, add_start_address));
+
+        // 在 $calc 的 `i32.mul 指令` 处设置断点
+        let breakpoint_mul = vm.add_breakpoint(0, 3, calc_start_address + 7).unwrap();
+
+        // 重复添加的断点会被忽略
+        vm.add_breakpoint(0, 3, calc_start_address + 7).unwrap();
+        assert_eq!(vm.breakpoints.len(), 2);
+
+        let result = vm.call_function_by_index(0, 3, &vec![Value::I32(10)]);
+        assert!(matches!(
+            result,
+            Ok(CallFunctionResult::Standby(result_types)) if result_types == vec![ValueType::I32]
+        ));
+
+        // 中断在 $add 的第一条指令，此时该指令尚未执行
+        assert_eq!(
+            vm.recur().unwrap(),
+            ExecutionResult::Breakpoint(breakpoint_add)
+        );
+        assert_eq!(vm.status.function_index, 1);
+        assert_eq!(vm.status.address, add_start_address);
+        assert_eq!(
+            vm.stack
+                .read_slots(vm.status.local_pointer, vm.status.local_pointer + 2),
+            &[10, 1]
+        );
+        assert_eq!(
+            vm.get_local_variable_values(),
+            convert_i32_list(&vec![10, 1])
+        );
+
+        // 从断点处恢复执行，中断在 `i32.mul 指令`
+        assert_eq!(
+            vm.recur().unwrap(),
+            ExecutionResult::Breakpoint(breakpoint_mul)
+        );
+        assert_eq!(vm.status.function_index, 3);
+        assert_eq!(
+            vm.stack
+                .peek_values(&vec![ValueType::I32, ValueType::I32])
+                .unwrap(),
+            convert_i32_list(&vec![8, 2])
+        );
+
+        assert_eq!(vm.recur().unwrap(), ExecutionResult::ProgramEnd);
+        assert_eq!(
+            vm.stack.pop_values(&vec![ValueType::I32]).unwrap(),
+            vec![Value::I32(16)]
+        );
+
+        // 移除断点之后不再中断
+        assert!(vm.remove_breakpoint(&breakpoint_add));
+        assert!(!vm.remove_breakpoint(&breakpoint_add));
+
+        vm.call_function_by_index(0, 3, &vec![Value::I32(20)])
+            .unwrap();
+        assert_eq!(
+            vm.recur().unwrap(),
+            ExecutionResult::Breakpoint(breakpoint_mul)
+        );
+
+        vm.clear_breakpoints();
+        assert_eq!(vm.recur().unwrap(), ExecutionResult::ProgramEnd);
+        assert_eq!(
+            vm.stack.pop_values(&vec![ValueType::I32]).unwrap(),
+            vec![Value::I32(36)]
+        );
+    }
+
+    #[test]
+    fn test_breakpoint_invalid() {
+        let mut vm = create_test_instance(
+            &[("test", "test-debug.wasm")],
+            vec![get_test_native_module()],
+        );
+
+        // 本地函数不能设置断点
+        assert!(matches!(
+            vm.add_breakpoint(0, 0, 0),
+            Err(EngineError::InvalidOperation(
+                InvalidOperation::InvalidBreakpointAddress { .. }
+            ))
+        ));
+
+        // 地址超出函数的范围
+        let calc_start_address = get_function_start_address(&vm, 0, 3);
+        assert!(matches!(
+            vm.add_breakpoint(0, 3, calc_start_address + 9),
+            Err(EngineError::InvalidOperation(
+                InvalidOperation::InvalidBreakpointAddress { .. }
+            ))
+        ));
+
+        assert!(matches!(
+            vm.add_breakpoint_by_export_function_name("test", "foo"),
+            Err(EngineError::ObjectNotFound(
+                ObjectNotFound::FunctionNotFound(..)
+            ))
+        ));
+
+        assert!(matches!(
+            vm.add_breakpoint_by_export_function_name("foo", "add"),
+            Err(EngineError::ObjectNotFound(ObjectNotFound::ModuleNotFound(
+                ..
+            )))
+        ));
+    }
+
+    #[test]
+    fn test_step_without_into() {
+        let mut vm = create_test_instance(
+            &[("test", "test-debug.wasm")],
+            vec![get_test_native_module()],
+        );
+        let calc_start_address = get_function_start_address(&vm, 0, 3);
+
+        vm.call_function_by_index(0, 3, &vec![Value::I32(10)])
+            .unwrap();
+
+        // local.get 0
+        assert_eq!(vm.step_without_into().unwrap(), ExecutionResult::Paused);
+        assert_eq!(vm.status.address, calc_start_address + 1);
+
+        // call $inc，跳过整个函数 $inc（以及它所调用的函数 $add）
+        assert_eq!(vm.step_without_into().unwrap(), ExecutionResult::Paused);
+        assert_eq!(vm.status.function_index, 3);
+        assert_eq!(vm.status.address, calc_start_address + 2);
+        assert_eq!(
+            vm.stack.peek_value(&ValueType::I32).unwrap(),
+            Value::I32(11)
+        );
+
+        // block，进入结构块之后仍处于当前函数
+        assert_eq!(vm.step_without_into().unwrap(), ExecutionResult::Paused);
+        assert_eq!(vm.status.address, calc_start_address + 3);
+
+        // i32.const 3, end
+        vm.step_without_into().unwrap();
+        vm.step_without_into().unwrap();
+        assert_eq!(vm.status.address, calc_start_address + 5);
+
+        // call $sub (native)
+        assert_eq!(vm.step_without_into().unwrap(), ExecutionResult::Paused);
+        assert_eq!(vm.status.address, calc_start_address + 6);
+        assert_eq!(vm.stack.peek_value(&ValueType::I32).unwrap(), Value::I32(8));
+
+        // i32.const 2, i32.mul, end
+        vm.step_without_into().unwrap();
+        vm.step_without_into().unwrap();
+        assert_eq!(vm.step_without_into().unwrap(), ExecutionResult::ProgramEnd);
+        assert_eq!(
+            vm.stack.pop_values(&vec![ValueType::I32]).unwrap(),
+            vec![Value::I32(16)]
+        );
+    }
+
+    #[test]
+    fn test_step_without_into_breakpoint() {
+        let mut vm = create_test_instance(
+            &[("test", "test-debug.wasm")],
+            vec![get_test_native_module()],
+        );
+        let add_start_address = get_function_start_address(&vm, 0, 1);
+
+        let breakpoint_add = vm
+            .add_breakpoint_by_export_function_name("test", "add")
+            .unwrap();
+
+        vm.call_function_by_index(0, 3, &vec![Value::I32(10)])
+            .unwrap();
+        vm.step_without_into().unwrap();
+
+        // 跳过 call $inc 时，遇到函数 $add 里的断点
+        assert_eq!(
+            vm.step_without_into().unwrap(),
+            ExecutionResult::Breakpoint(breakpoint_add)
+        );
+        assert_eq!(vm.status.function_index, 1);
+        assert_eq!(vm.status.address, add_start_address);
+
+        // 断点所在的指令会被执行
+        assert_eq!(vm.step_without_into().unwrap(), ExecutionResult::Paused);
+        assert_eq!(vm.status.address, add_start_address + 1);
+    }
+
+    #[test]
+    fn test_jump_out() {
+        let mut vm = create_test_instance(
+            &[("test", "test-debug.wasm")],
+            vec![get_test_native_module()],
+        );
+        let inc_start_address = get_function_start_address(&vm, 0, 2);
+        let calc_start_address = get_function_start_address(&vm, 0, 3);
+
+        let breakpoint_add = vm
+            .add_breakpoint_by_export_function_name("test", "add")
+            .unwrap();
+
+        vm.call_function_by_index(0, 3, &vec![Value::I32(10)])
+            .unwrap();
+
+        // 跳出函数 $calc 时，遇到函数 $add 里的断点
+        assert_eq!(
+            vm.jump_out().unwrap(),
+            ExecutionResult::Breakpoint(breakpoint_add)
+        );
+
+        // 跳出函数 $add，停留在函数 $inc 的 `call 指令` 的下一条指令
+        assert_eq!(vm.jump_out().unwrap(), ExecutionResult::Paused);
+        assert_eq!(vm.status.function_index, 2);
+        assert_eq!(vm.status.address, inc_start_address + 3);
+        assert_eq!(
+            vm.stack.peek_value(&ValueType::I32).unwrap(),
+            Value::I32(11)
+        );
+
+        // 跳出函数 $inc
+        assert_eq!(vm.jump_out().unwrap(), ExecutionResult::Paused);
+        assert_eq!(vm.status.function_index, 3);
+        assert_eq!(vm.status.address, calc_start_address + 2);
+
+        // 进入结构块之后再跳出，会执行到所在函数结束为止，而不仅仅是跳出结构块
+        vm.step().unwrap();
+        assert_eq!(vm.status.address, calc_start_address + 3);
+        assert_eq!(vm.jump_out().unwrap(), ExecutionResult::ProgramEnd);
+        assert_eq!(
+            vm.stack.pop_values(&vec![ValueType::I32]).unwrap(),
+            vec![Value::I32(16)]
+        );
+    }
+
+    #[test]
+    fn test_compiled_module() {
+        let named_ast_module = get_test_named_ast_module("test", "test-global-variable.wasm");
+
+        let engine = Engine::new();
+        let module = engine.compile(&[], vec![named_ast_module]).unwrap();
+
+        let mut vm0 = module.instantiate(vec![]).unwrap();
+        let mut vm1 = module.instantiate(vec![]).unwrap();
+
+        // 各个 VM 实例共享同一份指令等数据
+        assert!(Arc::ptr_eq(
+            &vm0.resource.vm_modules,
+            &vm1.resource.vm_modules
+        ));
+        assert!(Arc::ptr_eq(
+            &vm0.resource.vm_modules,
+            module.get_vm_modules()
+        ));
+
+        // 但全局变量等状态是各自独立的
+        assert_eq!(
+            vm0.eval_function_by_index(0, 2, &vec![Value::I32(7)])
+                .unwrap(),
+            vec![Value::I32(114)]
+        );
+        assert_eq!(
+            vm0.eval_function_by_index(0, 0, &vec![]).unwrap(),
+            vec![Value::I32(55), Value::I32(7)]
+        );
+        assert_eq!(
+            vm1.eval_function_by_index(0, 0, &vec![]).unwrap(),
+            vec![Value::I32(55), Value::I32(66)]
+        );
+
+        // 克隆的模块实例化之后，状态仍然是初始值
+        let mut vm2 = module.clone().instantiate(vec![]).unwrap();
+        assert_eq!(
+            vm2.eval_function_by_index(0, 0, &vec![]).unwrap(),
+            vec![Value::I32(55), Value::I32(66)]
+        );
+    }
+
+    #[test]
+    fn test_compiled_module_with_native_module() {
+        let named_ast_modules = vec![
+            get_test_named_ast_module("callee", "test-function-call-callee.wasm"),
+            get_test_named_ast_module(
+                "intermediate",
+                "test-function-call-callee-intermediate.wasm",
+            ),
+            get_test_named_ast_module("caller", "test-function-call-caller.wasm"),
+        ];
+
+        let engine = Engine::new();
+        let module = engine
+            .compile(&[get_test_native_module()], named_ast_modules)
+            .unwrap();
+
+        for _ in 0..2 {
+            let mut vm = module.instantiate(vec![get_test_native_module()]).unwrap();
+            assert_eq!(
+                vm.eval_function_by_index(2, 0, &vec![Value::I32(55), Value::I32(66)])
+                    .unwrap(),
+                vec![Value::I32(121)]
+            );
+        }
+
+        // 实例化时缺少本地模块
+        assert!(matches!(
+            module.instantiate(vec![]),
+            Err(EngineError::InvalidOperation(
+                InvalidOperation::NativeModuleMismatch(0)
+            ))
+        ));
+
+        // 实例化时提供的本地模块的函数跟编译时的不一致
+        let empty_module_context = EmptyModuleContext::new();
+        let mut other_native_module = NativeModule::new("math", Box::new(empty_module_context));
+        other_native_module.add_native_function(
+            "add",
+            vec![ValueType::I32, ValueType::I32],
+            vec!["left", "right"],
+            vec![ValueType::I32],
+            native_function_add_i32,
+        );
+
+        assert!(matches!(
+            module.instantiate(vec![other_native_module]),
+            Err(EngineError::InvalidOperation(
+                InvalidOperation::NativeModuleMismatch(0)
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_superinstructions() {
+        let mut vm = create_test_instance(
+            &[("test", "test-debug.wasm")],
+            vec![get_test_native_module()],
+        );
+        let add_start_address = get_function_start_address(&vm, 0, 1);
+
+        // $add 的 `local.get 0; local.get 1; i32.add` 被合并为一条超级指令，
+        // 超级指令位于原指令序列的第一条指令的位置，其余位置的指令保持不变。
+        let vm_module = &vm.resource.vm_modules[0];
+        assert_eq!(
+            vm_module.fused_instructions[add_start_address].operand,
+            Operand::IndexPair(0, 1)
+        );
+        assert_eq!(
+            vm_module.fused_instructions[add_start_address + 1].operand,
+            vm_module.compiled_instructions[add_start_address + 1].operand
+        );
+
+        // 单步执行总是只执行一条原指令，即使该位置是一条超级指令
+        vm.call_function_by_index(0, 1, &convert_i32_list(&vec![3, 4]))
+            .unwrap();
+        assert!(!vm.step().unwrap());
+        assert_eq!(vm.status.address, add_start_address + 1);
+        assert_eq!(vm.stack.peek_value(&ValueType::I32).unwrap(), Value::I32(3));
+
+        assert_eq!(vm.step_without_into().unwrap(), ExecutionResult::Paused);
+        assert_eq!(vm.status.address, add_start_address + 2);
+        assert_eq!(vm.stack.peek_value(&ValueType::I32).unwrap(), Value::I32(4));
+
+        assert!(!vm.step().unwrap());
+        assert_eq!(vm.status.address, add_start_address + 3);
+        assert_eq!(vm.stack.peek_value(&ValueType::I32).unwrap(), Value::I32(7));
+        vm.recur_without_break().unwrap();
+        assert_eq!(
+            vm.stack.pop_values(&vec![ValueType::I32]).unwrap(),
+            vec![Value::I32(7)]
+        );
+
+        // 在被合并的指令序列的中间设置断点，VM 逐条执行原指令，所以仍然能中断
+        let breakpoint = vm.add_breakpoint(0, 1, add_start_address + 2).unwrap();
+        vm.call_function_by_index(0, 1, &convert_i32_list(&vec![3, 4]))
+            .unwrap();
+        assert_eq!(vm.recur().unwrap(), ExecutionResult::Breakpoint(breakpoint));
+        assert_eq!(vm.status.address, add_start_address + 2);
+        assert_eq!(
+            vm.stack
+                .peek_values(&vec![ValueType::I32, ValueType::I32])
+                .unwrap(),
+            convert_i32_list(&vec![3, 4])
+        );
+        assert_eq!(vm.recur().unwrap(), ExecutionResult::ProgramEnd);
+        assert_eq!(
+            vm.stack.pop_values(&vec![ValueType::I32]).unwrap(),
+            vec![Value::I32(7)]
+        );
+
+        // 开启和关闭超级指令的运算结果一致
+        let mut vm = create_test_instance(&[("test", "test-loop.wasm")], vec![]);
+
+        for enabled in [true, false] {
+            vm.set_superinstructions_enabled(enabled);
+            assert_eq!(
+                vm.eval_function_by_index(0, 0, &vec![Value::I32(100)])
+                    .unwrap(),
+                vec![Value::I64(5050)]
+            );
+            assert_eq!(
+                vm.eval_function_by_index(0, 1, &convert_i32_list(&vec![10, 20]))
+                    .unwrap(),
+                vec![Value::I32(100)]
+            );
+        }
+    }
+
+    #[test]
+    fn test_fuel() {
+        let named_ast_module = get_test_named_ast_module("test", "test-fuel.wasm");
+        let forever_function_index =
+            find_ast_module_export_function(&named_ast_module.module, "forever").unwrap() as usize;
+        let sum_function_index =
+            find_ast_module_export_function(&named_ast_module.module, "sum").unwrap() as usize;
+
+        let mut vm = create_instance(vec![], &vec![named_ast_module]).unwrap();
+
+        // 默认不限制燃料
+        assert_eq!(vm.get_fuel(), None);
+        assert_eq!(
+            vm.eval_function_by_index(0, sum_function_index, &vec![Value::I32(10)])
+                .unwrap(),
+            vec![Value::I32(55)]
+        );
+
+        // 死循环会因燃料耗尽而中断
+        vm.set_fuel(1000);
+        assert!(matches!(
+            vm.eval_function_by_index(0, forever_function_index, &vec![]),
+            Err(EngineError::Interrupt(Interrupt::OutOfFuel {
+                fuel_cost: 1,
+                fuel_remaining: 0,
+                ..
+            }))
+        ));
+        assert_eq!(vm.get_fuel(), Some(0));
+    }
+
+    #[test]
+    fn test_fuel_resume() {
+        let named_ast_module = get_test_named_ast_module("test", "test-fuel.wasm");
+        let sum_function_index =
+            find_ast_module_export_function(&named_ast_module.module, "sum").unwrap() as usize;
+
+        let mut vm = create_instance(vec![], &vec![named_ast_module]).unwrap();
+
+        vm.set_fuel(50);
+        vm.call_function_by_index(0, sum_function_index, &vec![Value::I32(100)])
+            .unwrap();
+
+        let mut interrupt_count = 0;
+
+        loop {
+            match vm.recur_without_break() {
+                Ok(_) => break,
+                Err(EngineError::Interrupt(Interrupt::OutOfFuel { address, .. })) => {
+                    interrupt_count += 1;
+                    assert_eq!(vm.status.address, address);
+
+                    // 燃料不足时再次执行，VM 的状态以及栈均保持不变
+                    let status = vm.status.clone();
+                    let stack_size = vm.stack.get_size();
+                    let slots = vm.stack.read_slots(0, stack_size).to_vec();
+
+                    assert!(vm.step().is_err());
+                    assert_eq!(vm.status, status);
+                    assert_eq!(vm.stack.read_slots(0, vm.stack.get_size()), slots);
+
+                    vm.add_fuel(50);
+                }
+                Err(e) => panic!("unexpected error: {}", e),
+            }
+        }
+
+        assert!(interrupt_count > 1);
+        assert_eq!(
+            vm.stack.pop_values(&vec![ValueType::I32]).unwrap(),
+            vec![Value::I32(5050)]
+        );
+    }
+
+    #[test]
+    fn test_fuel_instruction_cost_function() {
+        let named_ast_module = get_test_named_ast_module("test", "test-fuel.wasm");
+        let forever_function_index =
+            find_ast_module_export_function(&named_ast_module.module, "forever").unwrap() as usize;
+
+        let mut vm = create_instance(vec![], &vec![named_ast_module]).unwrap();
+
+        // 只有 `br 指令`（跳转到 loop 结构块）消耗燃料
+        fn recur_cost(instruction: &object::Instruction) -> u64 {
+            match instruction {
+                object::Instruction::Control(Control::Recur { .. }) => 100,
+                _ => 0,
+            }
+        }
+
+        vm.set_instruction_cost_function(recur_cost);
+        vm.set_fuel(250);
+
+        assert!(matches!(
+            vm.eval_function_by_index(0, forever_function_index, &vec![]),
+            Err(EngineError::Interrupt(Interrupt::OutOfFuel {
+                fuel_cost: 100,
+                fuel_remaining: 50,
+                ..
+            }))
+        ));
+
+        // 关闭燃料计量之后，add_fuel 不起作用
+        vm.disable_fuel();
+        vm.add_fuel(100);
+        assert_eq!(vm.get_fuel(), None);
+    }
+
+    #[test]
+    fn test_interrupt() {
+        let named_ast_module = get_test_named_ast_module("test", "test-interrupt.wasm");
+        let mut vm = create_instance(vec![], &vec![named_ast_module]).unwrap();
+
+        // 在开始执行之前请求中断
+        let interrupt_handle = vm.get_interrupt_handle();
+        interrupt_handle.interrupt();
+
+        vm.call_function_by_index(0, 0, &vec![Value::I32(0)])
+            .unwrap();
+        let status = vm.status.clone();
+
+        assert!(matches!(
+            vm.recur_without_break(),
+            Err(EngineError::Interrupt(Interrupt::Interrupted { .. }))
+        ));
+        assert_eq!(vm.status, status);
+
+        // 中断标记已被清除，可以继续执行
+        assert!(vm.step().is_ok());
+    }
+
+    #[test]
+    fn test_interrupt_from_other_thread() {
+        let named_ast_module = get_test_named_ast_module("test", "test-interrupt.wasm");
+        let mut vm = create_instance(vec![], &vec![named_ast_module]).unwrap();
+
+        let interrupt_handle = vm.get_interrupt_handle();
+        let watchdog = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            interrupt_handle.interrupt();
+        });
+
+        // VM 在递归调用 100 层之后，在多层结构块当中进入死循环
+        let result = vm.eval_function_by_index(0, 0, &vec![Value::I32(100)]);
+        watchdog.join().unwrap();
+
+        assert!(matches!(
+            result,
+            Err(EngineError::Interrupt(Interrupt::Interrupted {
+                vm_module_index: 0,
+                function_index: 0,
+                ..
+            }))
+        ));
+
+        // 中断之后 VM 仍然停留在死循环之内，调用栈保持不变
+        let stack_size = vm.stack.get_size();
+        let frame_count = vm.stack.get_frame_count();
+        assert!(frame_count > 100);
+
+        let interrupt_handle = vm.get_interrupt_handle();
+        let watchdog = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(10));
+            interrupt_handle.interrupt();
+        });
+
+        let result = vm.recur_without_break();
+        watchdog.join().unwrap();
+
+        assert!(matches!(
+            result,
+            Err(EngineError::Interrupt(Interrupt::Interrupted { .. }))
+        ));
+        assert_eq!(vm.stack.get_size(), stack_size);
+        assert_eq!(vm.stack.get_frame_count(), frame_count);
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let named_ast_module = get_test_named_ast_module("test", "test-snapshot.wasm");
+        let sum_function_index =
+            find_ast_module_export_function(&named_ast_module.module, "sum").unwrap() as usize;
+
+        let named_ast_modules = vec![named_ast_module];
+        let mut vm0 = create_instance(vec![], &named_ast_modules).unwrap();
+
+        // 燃料耗尽时 VM 暂停在函数的中间
+        vm0.set_fuel(500);
+        vm0.call_function_by_index(0, sum_function_index, &vec![Value::I32(100)])
+            .unwrap();
+        assert!(matches!(
+            vm0.recur_without_break(),
+            Err(EngineError::Interrupt(Interrupt::OutOfFuel { .. }))
+        ));
+
+        let counter = vm0.resource.global_variables[0].get_value();
+        assert!(matches!(counter, Value::I32(c) if c > 0 && c < 101));
+
+        let snapshot = vm0.save_snapshot();
+
+        // 恢复到一个新创建的 VM
+        let mut vm1 = create_instance(vec![], &named_ast_modules).unwrap();
+        vm1.restore_snapshot(&snapshot).unwrap();
+
+        assert_eq!(vm1.status, vm0.status);
+        assert_eq!(vm1.get_call_depth(), vm0.get_call_depth());
+        assert_eq!(vm1.get_fuel(), vm0.get_fuel());
+        assert_eq!(vm1.resource.global_variables[0].get_value(), counter);
+        assert_eq!(vm1.resource.memory_blocks[0].get_page_count(), 2);
+        assert_eq!(
+            vm1.resource.tables[0].get_elements(),
+            &[None, Some(sum_function_index as u32)]
+        );
+        assert!(vm1.resource.data_segments[0].get_data().is_empty());
+        assert_eq!(vm1.save_snapshot(), snapshot);
+
+        // 两个 VM 从暂停的位置继续执行，得到相同的结果
+        for vm in [&mut vm0, &mut vm1] {
+            vm.disable_fuel();
+            vm.recur_without_break().unwrap();
+
+            assert_eq!(
+                vm.stack.pop_values(&vec![ValueType::I32]).unwrap(),
+                vec![Value::I32(5050)]
+            );
+            assert_eq!(vm.resource.memory_blocks[0].read_i32(65536 + 400), 5050);
+            assert_eq!(vm.resource.global_variables[0].get_value(), Value::I32(101));
+            assert_eq!(vm.get_call_depth(), 0);
+        }
+    }
+
+    #[test]
+    fn test_snapshot_idle() {
+        let named_ast_module = get_test_named_ast_module("test", "test-snapshot.wasm");
+        let sum_function_index =
+            find_ast_module_export_function(&named_ast_module.module, "sum").unwrap() as usize;
+        let get_counter_function_index =
+            find_ast_module_export_function(&named_ast_module.module, "get_counter").unwrap()
+                as usize;
+
+        let named_ast_modules = vec![named_ast_module];
+        let mut vm0 = create_instance(vec![], &named_ast_modules).unwrap();
+
+        assert_eq!(
+            vm0.eval_function_by_index(0, sum_function_index, &vec![Value::I32(10)])
+                .unwrap(),
+            vec![Value::I32(55)]
+        );
+
+        let snapshot = vm0.save_snapshot();
+
+        let mut vm1 = create_instance(vec![], &named_ast_modules).unwrap();
+        vm1.restore_snapshot(&snapshot).unwrap();
+
+        assert_eq!(
+            vm1.eval_function_by_index(0, get_counter_function_index, &vec![])
+                .unwrap(),
+            vec![Value::I32(11)]
+        );
+        assert_eq!(vm1.resource.memory_blocks[0].read_i32(65536 + 40), 55);
+
+        // 快照也可以恢复到原来的 VM，即把 VM 的状态回退到保存快照时的状态
+        assert_eq!(
+            vm0.eval_function_by_index(0, sum_function_index, &vec![Value::I32(10)])
+                .unwrap(),
+            vec![Value::I32(55)]
+        );
+        assert_eq!(vm0.resource.memory_blocks[0].get_page_count(), 3);
+
+        vm0.restore_snapshot(&snapshot).unwrap();
+        assert_eq!(vm0.resource.memory_blocks[0].get_page_count(), 2);
+        assert_eq!(
+            vm0.eval_function_by_index(0, get_counter_function_index, &vec![])
+                .unwrap(),
+            vec![Value::I32(11)]
+        );
+    }
+
+    #[test]
+    fn test_snapshot_mismatch() {
+        let vm0 = create_test_instance(&[("test", "test-snapshot.wasm")], vec![]);
+        let snapshot = vm0.save_snapshot();
+
+        // 模块的名称相同但内容不同
+        let mut vm1 = create_test_instance(&[("test", "test-fuel.wasm")], vec![]);
+        assert!(matches!(
+            vm1.restore_snapshot(&snapshot),
+            Err(EngineError::Snapshot(SnapshotError::ModuleMismatch(module_name))) if module_name == "test"
+        ));
+
+        // 模块的内容相同但名称不同
+        let named_ast_module = get_test_named_ast_module("other", "test-snapshot.wasm");
+        let mut vm2 = create_instance(vec![], &vec![named_ast_module]).unwrap();
+        assert!(matches!(
+            vm2.restore_snapshot(&snapshot),
+            Err(EngineError::Snapshot(SnapshotError::ModuleMismatch(_)))
+        ));
+
+        // 本地模块不同
+        let named_ast_module = get_test_named_ast_module("test", "test-snapshot.wasm");
+        let mut vm3 =
+            create_instance(vec![get_test_native_module()], &vec![named_ast_module]).unwrap();
+        assert!(matches!(
+            vm3.restore_snapshot(&snapshot),
+            Err(EngineError::Snapshot(SnapshotError::NativeModuleMismatch(
+                _
+            )))
+        ));
+
+        // 无效的快照数据
+        let mut vm4 = create_test_instance(&[("test", "test-snapshot.wasm")], vec![]);
+
+        assert!(matches!(
+            vm4.restore_snapshot(b"hello"),
+            Err(EngineError::Snapshot(SnapshotError::UnexpectedEnd))
+        ));
+        assert!(matches!(
+            vm4.restore_snapshot(b"not a snapshot"),
+            Err(EngineError::Snapshot(SnapshotError::InvalidMagicNumber))
+        ));
+
+        let mut data = snapshot.clone();
+        data[8] = 99;
+        assert!(matches!(
+            vm4.restore_snapshot(&data),
+            Err(EngineError::Snapshot(SnapshotError::UnsupportedVersion(99)))
+        ));
+
+        assert!(matches!(
+            vm4.restore_snapshot(&snapshot[..snapshot.len() - 1]),
+            Err(EngineError::Snapshot(SnapshotError::UnexpectedEnd))
+        ));
+
+        let mut data = snapshot.clone();
+        data.push(0);
+        assert!(matches!(
+            vm4.restore_snapshot(&data),
+            Err(EngineError::Snapshot(SnapshotError::InvalidData))
+        ));
+
+        // 出错时 VM 保持不变
+        assert_eq!(vm4.save_snapshot(), snapshot);
+    }
+
+    #[test]
+    fn test_call_stack_exhausted() {
+        let named_ast_module = get_test_named_ast_module("test", "test-call-stack.wasm");
+        let mut vm = create_instance(vec![], &vec![named_ast_module]).unwrap();
+
+        // 函数 $count 递归 50 层，加上第一层调用，共 51 个调用帧
+        vm.set_max_call_depth(51);
+        assert_eq!(
+            vm.eval_function_by_index(0, 1, &vec![Value::I32(50)])
+                .unwrap(),
+            vec![Value::I32(50)]
+        );
+        assert_eq!(vm.get_call_depth(), 0);
+
+        assert!(matches!(
+            vm.eval_function_by_index(0, 1, &vec![Value::I32(51)]),
+            Err(EngineError::Trap(Trap {
+                code: TrapCode::StackExhausted,
+                backtrace,
+            })) if backtrace.frames.len() == 51
+        ));
+        assert_eq!(vm.get_call_depth(), 0);
+    }
+
+    #[test]
+    fn test_call_function_after_call_stack_exhausted() {
+        let named_ast_module = get_test_named_ast_module("test", "test-call-stack.wasm");
+        let mut vm = create_instance(vec![], &vec![named_ast_module]).unwrap();
+
+        vm.set_max_call_depth(100);
+
+        // 陷阱发生之后，栈、状态以及调用帧数量均恢复到调用之前的样子，
+        // 所以再次发生陷阱时调用帧的数量仍然是 100，而不会累积
+        for _ in 0..3 {
+            assert!(matches!(
+                vm.eval_function_by_index(0, 0, &vec![Value::I32(0)]),
+                Err(EngineError::Trap(Trap {
+                    code: TrapCode::StackExhausted,
+                    backtrace,
+                })) if backtrace.frames.len() == 100
+            ));
+
+            assert_eq!(vm.get_call_depth(), 0);
+            assert_eq!(vm.stack.get_size(), 0);
+            assert_eq!(vm.stack.get_frame_count(), 0);
+
+            assert_eq!(
+                vm.eval_function_by_index(0, 1, &vec![Value::I32(1)])
+                    .unwrap(),
+                vec![Value::I32(1)]
+            );
+            assert_eq!(
+                vm.eval_function_by_index(0, 1, &vec![Value::I32(99)])
+                    .unwrap(),
+                vec![Value::I32(99)]
+            );
+        }
+    }
+
+    #[test]
+    fn test_call_stack_exhausted_inspect() {
+        let named_ast_module = get_test_named_ast_module("test", "test-call-stack.wasm");
+        let mut vm = create_instance(vec![], &vec![named_ast_module]).unwrap();
+
+        // 通过 call_function_by_index 以及 recur 系列方法执行函数时，
+        // 陷阱发生之后 VM 会保留执行现场
+        vm.set_max_call_depth(100);
+        vm.call_function_by_index(0, 0, &vec![Value::I32(0)])
+            .unwrap();
+        assert!(matches!(
+            vm.recur_without_break(),
+            Err(EngineError::Trap(Trap {
+                code: TrapCode::StackExhausted,
+                backtrace,
+            })) if backtrace.frames.len() == 100
+        ));
+
+        // VM 停留在最后一次调用的 `call 指令`，且状态和栈均可以被检查
+        let recur_start_address = get_function_start_address(&vm, 0, 0);
+        assert_eq!(vm.get_call_depth(), 100);
+        assert_eq!(vm.status.function_index, 0);
+        assert_eq!(vm.status.address, recur_start_address + 3);
+        assert_eq!(
+            vm.stack.get_value(vm.status.local_pointer, &ValueType::I32),
+            Value::I32(99)
+        );
+        assert_eq!(
+            vm.stack.peek_value(&ValueType::I32).unwrap(),
+            Value::I32(100)
+        );
+
+        // 检查完毕之后重置 VM
+        vm.reset();
+        assert_eq!(vm.get_call_depth(), 0);
+        assert_eq!(vm.stack.get_size(), 0);
+        assert_eq!(
+            vm.eval_function_by_index(0, 1, &vec![Value::I32(10)])
+                .unwrap(),
+            vec![Value::I32(10)]
+        );
+    }
+
+    #[test]
+    fn test_call_stack_exhausted_by_stack_size() {
+        let named_ast_module = get_test_named_ast_module("test", "test-call-stack.wasm");
+        let mut vm = create_instance(vec![], &vec![named_ast_module]).unwrap();
+
+        // 控制帧不占用操作数栈的槽，函数 $count 的每一层调用只占用一个槽（即参数）
+        vm.set_max_stack_slots(50);
+
+        vm.call_function_by_index(0, 1, &vec![Value::I32(100)])
+            .unwrap();
+        let result = vm.recur_without_break();
+        assert!(matches!(
+            result,
+            Err(EngineError::Trap(Trap {
+                code: TrapCode::StackExhausted,
+                ..
+            }))
+        ));
+        assert!(vm.get_call_depth() < 100);
+        assert!(vm.stack.get_size() > 50 - 16);
+    }
+
+    #[test]
+    fn test_trap_backtrace() {
+        let mut vm = create_test_instance(&[("test", "test-trap.wasm")], vec![]);
+
+        let trap = match vm.eval_function_by_index(0, 3, &vec![Value::I32(1)]) {
+            Err(EngineError::Trap(trap)) => trap,
+            _ => panic!("expected a trap"),
+        };
+
+        assert_eq!(trap.code, TrapCode::Unreachable);
+
+        // 陷阱发生在函数 $inner 的 `unreachable 指令`，
+        // 其余的帧停留在各自的 `call 指令`
+        let inner_start_address = get_function_start_address(&vm, 0, 1);
+        let middle_start_address = get_function_start_address(&vm, 0, 2);
+        let outer_start_address = get_function_start_address(&vm, 0, 3);
+
+        assert_eq!(
+            trap.backtrace.frames,
+            vec![
+                BacktraceFrame {
+                    vm_module_index: 0,
+                    function_index: 1,
+                    address: inner_start_address + 3,
+                    module_name: "test".to_string(),
+                    function_name: Some("inner".to_string()),
+                    instruction_offset: 3
+                },
+                BacktraceFrame {
+                    vm_module_index: 0,
+                    function_index: 2,
+                    address: middle_start_address + 1,
+                    module_name: "test".to_string(),
+                    function_name: Some("middle".to_string()),
+                    instruction_offset: 1
+                },
+                BacktraceFrame {
+                    vm_module_index: 0,
+                    function_index: 3,
+                    address: outer_start_address + 3,
+                    module_name: "test".to_string(),
+                    function_name: Some("outer".to_string()),
+                    instruction_offset: 3
+                },
+            ]
+        );
+
+        assert_eq!(
+            trap.to_string(),
+            "trap: unreachable, in test::inner (function #1) at instruction #3"
+        );
+        assert_eq!(
+            trap.backtrace.to_string(),
+            "\
+backtrace:
+  #0 test::inner (function #1) at instruction #3
+  #1 test::middle (function #2) at instruction #1
+  #2 test::outer (function #3) at instruction #3"
+        );
+
+        // 陷阱发生之后 VM 恢复到调用之前的状态，可以继续调用函数
+        assert_eq!(vm.get_call_depth(), 0);
+        assert_eq!(vm.stack.get_size(), 0);
+        assert_eq!(vm.stack.get_frame_count(), 0);
+        assert_eq!(
+            vm.eval_function_by_index(0, 0, &vec![]).unwrap(),
+            vec![Value::I32(1)]
+        );
+    }
+
+    #[test]
+    fn test_trap_integer_division() {
+        // 没有名称的函数使用导出名称
+        match eval_trap_test_function(4, &vec![Value::I32(1), Value::I32(0)]) {
+            Err(EngineError::Trap(trap)) => {
+                assert_eq!(trap.code, TrapCode::IntegerDivideByZero);
+                assert_eq!(trap.backtrace.frames.len(), 1);
+                assert_eq!(
+                    trap.backtrace.frames[0].function_name,
+                    Some("i32_div_s".to_string())
+                );
+                assert_eq!(trap.backtrace.frames[0].instruction_offset, 2);
+            }
+            _ => panic!("expected a trap"),
+        }
+
+        assert_eq!(
+            get_trap_code(eval_trap_test_function(
+                4,
+                &vec![Value::I32(i32::MIN), Value::I32(-1)]
+            )),
+            TrapCode::IntegerOverflow
+        );
+        assert_eq!(
+            eval_trap_test_function(4, &vec![Value::I32(-7), Value::I32(2)]).unwrap(),
+            vec![Value::I32(-3)]
+        );
+
+        // rem_s 不会溢出
+        assert_eq!(
+            eval_trap_test_function(5, &vec![Value::I32(i32::MIN), Value::I32(-1)]).unwrap(),
+            vec![Value::I32(0)]
+        );
+        assert_eq!(
+            get_trap_code(eval_trap_test_function(
+                5,
+                &vec![Value::I32(1), Value::I32(0)]
+            )),
+            TrapCode::IntegerDivideByZero
+        );
+
+        assert_eq!(
+            get_trap_code(eval_trap_test_function(
+                6,
+                &vec![Value::I64(1), Value::I64(0)]
+            )),
+            TrapCode::IntegerDivideByZero
+        );
+        assert_eq!(
+            eval_trap_test_function(7, &vec![Value::I64(i64::MIN), Value::I64(-1)]).unwrap(),
+            vec![Value::I64(0)]
+        );
+        assert_eq!(
+            get_trap_code(eval_trap_test_function(
+                7,
+                &vec![Value::I64(7), Value::I64(0)]
+            )),
+            TrapCode::IntegerDivideByZero
+        );
+    }
+
+    #[test]
+    fn test_trap_truncate() {
+        // i32.trunc_f32_s
+        assert_eq!(
+            get_trap_code(eval_trap_test_function(8, &vec![Value::F32(f32::NAN)])),
+            TrapCode::InvalidConversionToInteger
+        );
+        assert_eq!(
+            get_trap_code(eval_trap_test_function(8, &vec![Value::F32(f32::INFINITY)])),
+            TrapCode::IntegerOverflow
+        );
+        assert_eq!(
+            get_trap_code(eval_trap_test_function(8, &vec![Value::F32(2147483648.0)])),
+            TrapCode::IntegerOverflow
+        );
+        assert_eq!(
+            get_trap_code(eval_trap_test_function(8, &vec![Value::F32(-2147483904.0)])),
+            TrapCode::IntegerOverflow
+        );
+        assert_eq!(
+            eval_trap_test_function(8, &vec![Value::F32(-2147483648.0)]).unwrap(),
+            vec![Value::I32(i32::MIN)]
+        );
+        assert_eq!(
+            eval_trap_test_function(8, &vec![Value::F32(-1.5)]).unwrap(),
+            vec![Value::I32(-1)]
+        );
+
+        // i64.trunc_f64_u
+        assert_eq!(
+            get_trap_code(eval_trap_test_function(9, &vec![Value::F64(f64::NAN)])),
+            TrapCode::InvalidConversionToInteger
+        );
+        assert_eq!(
+            get_trap_code(eval_trap_test_function(9, &vec![Value::F64(-1.0)])),
+            TrapCode::IntegerOverflow
+        );
+        assert_eq!(
+            get_trap_code(eval_trap_test_function(
+                9,
+                &vec![Value::F64(18446744073709551616.0)]
+            )),
+            TrapCode::IntegerOverflow
+        );
+        assert_eq!(
+            eval_trap_test_function(9, &vec![Value::F64(-0.9)]).unwrap(),
+            vec![Value::I64(0)]
+        );
+        assert_eq!(
+            eval_trap_test_function(9, &vec![Value::F64(18446744073709549568.0)]).unwrap(),
+            vec![Value::I64(18446744073709549568u64 as i64)]
+        );
+    }
+
+    #[test]
+    fn test_trap_call_indirect() {
+        assert_eq!(
+            eval_trap_test_function(10, &vec![Value::I32(0)]).unwrap(),
+            vec![Value::I32(1)]
+        );
+
+        // 表的元素 #1 是函数 $inner，其类型跟指令指定的不一致
+        assert_eq!(
+            get_trap_code(eval_trap_test_function(10, &vec![Value::I32(1)])),
+            TrapCode::IndirectCallTypeMismatch
+        );
+
+        // 表的元素 #2 为空
+        assert_eq!(
+            get_trap_code(eval_trap_test_function(10, &vec![Value::I32(2)])),
+            TrapCode::UndefinedElement
+        );
+
+        // 元素索引超出表的范围
+        assert_eq!(
+            get_trap_code(eval_trap_test_function(10, &vec![Value::I32(3)])),
+            TrapCode::UndefinedElement
+        );
+    }
+
+    #[test]
+    fn test_trap_memory_out_of_bounds() {
+        let module_name = "test-memory-bounds.wasm";
+
+        // 访问内存块的最后几个字节
+        assert_eq!(
+            eval(module_name, 0, &vec![Value::I32(65532)]).unwrap(),
+            vec![Value::I32(0)]
+        );
+        assert_eq!(
+            eval(module_name, 1, &vec![Value::I32(65528)]).unwrap(),
+            vec![Value::I64(0)]
+        );
+        assert_eq!(
+            eval(module_name, 2, &vec![Value::I32(65535)]).unwrap(),
+            vec![Value::I32(0)]
+        );
+
+        // 数据的一部分超出了内存块的范围
+        assert_eq!(
+            get_trap_code(eval(module_name, 0, &vec![Value::I32(65533)])),
+            TrapCode::MemoryOutOfBounds
+        );
+        assert_eq!(
+            get_trap_code(eval(module_name, 1, &vec![Value::I32(65529)])),
+            TrapCode::MemoryOutOfBounds
+        );
+        assert_eq!(
+            get_trap_code(eval(module_name, 2, &vec![Value::I32(65536)])),
+            TrapCode::MemoryOutOfBounds
+        );
+
+        // 负数的地址被视为 u32
+        assert_eq!(
+            get_trap_code(eval(module_name, 0, &vec![Value::I32(-1)])),
+            TrapCode::MemoryOutOfBounds
+        );
+
+        // 有效地址（offset + addr）超出了 u32 的范围，不能回绕
+        assert_eq!(
+            eval(module_name, 3, &vec![Value::I32(65534)]).unwrap(),
+            vec![Value::I32(0)]
+        );
+        assert_eq!(
+            get_trap_code(eval(module_name, 3, &vec![Value::I32(-1)])),
+            TrapCode::MemoryOutOfBounds
+        );
+        assert_eq!(
+            get_trap_code(eval(module_name, 4, &vec![Value::I32(1)])),
+            TrapCode::MemoryOutOfBounds
+        );
+
+        // 存储指令
+        assert_eq!(
+            eval(module_name, 5, &vec![Value::I32(65535)]).unwrap(),
+            vec![]
+        );
+        assert_eq!(
+            get_trap_code(eval(module_name, 5, &vec![Value::I32(65536)])),
+            TrapCode::MemoryOutOfBounds
+        );
+        assert_eq!(
+            eval(module_name, 6, &vec![Value::I32(65524)]).unwrap(),
+            vec![]
+        );
+        assert_eq!(
+            get_trap_code(eval(module_name, 6, &vec![Value::I32(65525)])),
+            TrapCode::MemoryOutOfBounds
+        );
+        assert_eq!(
+            get_trap_code(eval(module_name, 7, &vec![Value::I32(-1)])),
+            TrapCode::MemoryOutOfBounds
+        );
+
+        // 增加页面之后，原先超出范围的地址变为有效
+        let named_ast_module = get_test_named_ast_module("test", module_name);
+        let mut vm = create_instance(vec![], &vec![named_ast_module]).unwrap();
+
+        assert_eq!(
+            vm.eval_function_by_index(0, 8, &vec![]).unwrap(),
+            vec![Value::I32(1)]
+        );
+        assert_eq!(
+            vm.eval_function_by_index(0, 1, &vec![Value::I32(65536)])
+                .unwrap(),
+            vec![Value::I64(0)]
+        );
+    }
+
+    #[test]
+    fn test_bulk_memory_segments() {
+        let vm = create_test_instance(&[("test", "test-bulk-memory.wasm")], vec![]);
+
+        // 只有被动模式的段会被保留
+        assert_eq!(vm.resource.data_segments[0].get_data(), b"hello");
+        assert!(vm.resource.data_segments[1].get_data().is_empty());
+
+        assert_eq!(
+            vm.resource.element_segments[0].get_elements(),
+            &[Some(0), Some(1), Some(2)]
+        );
+        assert!(vm.resource.element_segments[1].get_elements().is_empty());
+        assert!(vm.resource.element_segments[2].get_elements().is_empty());
+
+        // 主动模式的数据段超出内存块的范围
+        let mut named_ast_module = get_test_named_ast_module("test", "test-bulk-memory.wasm");
+        named_ast_module.module.data_items[1].data = vec![0u8; 65537];
+        assert!(matches!(
+            create_instance(vec![], &vec![named_ast_module]),
+            Err(EngineError::Trap(Trap {
+                code: TrapCode::MemoryOutOfBounds,
+                ..
+            }))
+        ));
+
+        // 主动模式的元素段超出表的范围
+        let mut named_ast_module = get_test_named_ast_module("test", "test-bulk-memory.wasm");
+        named_ast_module.module.element_items[1].items =
+            ElementItems::FunctionIndices(vec![0, 1, 2, 0, 1]);
+        assert!(matches!(
+            create_instance(vec![], &vec![named_ast_module]),
+            Err(EngineError::Trap(Trap {
+                code: TrapCode::TableOutOfBounds,
+                ..
+            }))
+        ));
+    }
+
+    #[test]
+    fn test_bulk_memory() {
+        let mut vm = create_test_instance(&[("test", "test-bulk-memory.wasm")], vec![]);
+
+        // 主动模式的数据段 "ABCDEFGH"
+        assert_eq!(
+            vm.eval_function_by_index(0, 3, &vec![Value::I32(0)])
+                .unwrap(),
+            vec![Value::I64(0x4847464544434241)]
+        );
+
+        // memory.init，将 "hello" 的 "ell" 复制到地址 2，得到 "ABellFGH"
+        vm.eval_function_by_index(0, 4, &convert_i32_list(&vec![2, 1, 3]))
+            .unwrap();
+        assert_eq!(
+            vm.eval_function_by_index(0, 3, &vec![Value::I32(0)])
+                .unwrap(),
+            vec![Value::I64(0x4847466c6c654241)]
+        );
+
+        // memory.copy，源范围和目标范围重叠，得到 "AABelFGH"
+        vm.eval_function_by_index(0, 6, &convert_i32_list(&vec![1, 0, 4]))
+            .unwrap();
+        assert_eq!(
+            vm.eval_function_by_index(0, 3, &vec![Value::I32(0)])
+                .unwrap(),
+            vec![Value::I64(0x4847466c65424141)]
+        );
+
+        // memory.fill，得到 "AABelFzz"
+        vm.eval_function_by_index(0, 7, &convert_i32_list(&vec![6, 0x7a, 2]))
+            .unwrap();
+        assert_eq!(
+            vm.eval_function_by_index(0, 3, &vec![Value::I32(0)])
+                .unwrap(),
+            vec![Value::I64(0x7a7a466c65424141)]
+        );
+
+        // 长度为 0 时，地址可以等于内存块的大小
+        vm.eval_function_by_index(0, 4, &convert_i32_list(&vec![65536, 5, 0]))
+            .unwrap();
+        vm.eval_function_by_index(0, 6, &convert_i32_list(&vec![65536, 65536, 0]))
+            .unwrap();
+
+        // data.drop 之后，只有长度为 0 的 memory.init 不会引发陷阱
+        vm.eval_function_by_index(0, 5, &vec![]).unwrap();
+        vm.eval_function_by_index(0, 4, &convert_i32_list(&vec![0, 0, 0]))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_bulk_memory_out_of_bounds() {
+        let test_items = vec![
+            // memory.init 的源范围超出数据段
+            (4, vec![0, 4, 2]),
+            // memory.init 的目标范围超出内存块
+            (4, vec![65535, 0, 2]),
+            // 即使长度为 0，地址超出范围也会引发陷阱
+            (4, vec![65537, 0, 0]),
+            (4, vec![0, 6, 0]),
+            // memory.copy
+            (6, vec![65535, 0, 2]),
+            (6, vec![0, 65535, 2]),
+            (6, vec![0, -1, 1]),
+            // memory.fill
+            (7, vec![65530, 0, 7]),
+            (7, vec![65537, 0, 0]),
+        ];
+
+        for (function_index, args) in test_items {
+            let mut vm = create_test_instance(&[("test", "test-bulk-memory.wasm")], vec![]);
+            assert_eq!(
+                get_trap_code(vm.eval_function_by_index(
+                    0,
+                    function_index,
+                    &convert_i32_list(&args)
+                )),
+                TrapCode::MemoryOutOfBounds
+            );
+        }
+
+        // 引发陷阱时内存不会被修改
+        let mut vm = create_test_instance(&[("test", "test-bulk-memory.wasm")], vec![]);
+        assert!(vm
+            .eval_function_by_index(0, 7, &convert_i32_list(&vec![0, 0, 65537]))
+            .is_err());
+        assert_eq!(vm.resource.memory_blocks[0].read_bytes(0, 8), b"ABCDEFGH");
+
+        // data.drop 之后
+        let mut vm = create_test_instance(&[("test", "test-bulk-memory.wasm")], vec![]);
+        vm.eval_function_by_index(0, 5, &vec![]).unwrap();
+        assert_eq!(
+            get_trap_code(vm.eval_function_by_index(0, 4, &convert_i32_list(&vec![0, 0, 1]))),
+            TrapCode::MemoryOutOfBounds
+        );
+    }
+
+    #[test]
+    fn test_bulk_table() {
+        let mut vm = create_test_instance(&[("test", "test-bulk-memory.wasm")], vec![]);
+
+        // 主动模式的元素段只初始化了表的元素 #0
+        assert_eq!(
+            vm.eval_function_by_index(0, 11, &vec![Value::I32(0)])
+                .unwrap(),
+            vec![Value::I32(10)]
+        );
+
+        // table.init，表的元素为 [$ten, $ten, $twenty, $thirty]
+        vm.eval_function_by_index(0, 8, &convert_i32_list(&vec![1, 0, 3]))
+            .unwrap();
+        assert_eq!(
+            vm.eval_function_by_index(0, 11, &vec![Value::I32(1)])
+                .unwrap(),
+            vec![Value::I32(10)]
+        );
+        assert_eq!(
+            vm.eval_function_by_index(0, 11, &vec![Value::I32(3)])
+                .unwrap(),
+            vec![Value::I32(30)]
+        );
+
+        // table.copy，表的元素为 [$twenty, $thirty, $twenty, $thirty]
+        vm.eval_function_by_index(0, 10, &convert_i32_list(&vec![0, 2, 2]))
+            .unwrap();
+        assert_eq!(
+            vm.eval_function_by_index(0, 11, &vec![Value::I32(0)])
+                .unwrap(),
+            vec![Value::I32(20)]
+        );
+        assert_eq!(
+            vm.eval_function_by_index(0, 11, &vec![Value::I32(1)])
+                .unwrap(),
+            vec![Value::I32(30)]
+        );
+
+        // elem.drop 之后，只有长度为 0 的 table.init 不会引发陷阱
+        vm.eval_function_by_index(0, 9, &vec![]).unwrap();
+        vm.eval_function_by_index(0, 8, &convert_i32_list(&vec![0, 0, 0]))
+            .unwrap();
+        assert_eq!(
+            get_trap_code(vm.eval_function_by_index(0, 8, &convert_i32_list(&vec![0, 0, 1]))),
+            TrapCode::TableOutOfBounds
+        );
+
+        let test_items = vec![
+            // table.init 的目标范围超出表
+            (8, vec![2, 0, 3]),
+            // table.init 的源范围超出元素段
+            (8, vec![0, 2, 2]),
+            (8, vec![0, 4, 0]),
+            // table.copy
+            (10, vec![3, 0, 2]),
+            (10, vec![0, 3, 2]),
+            (10, vec![5, 0, 0]),
+        ];
+
+        for (function_index, args) in test_items {
+            let mut vm = create_test_instance(&[("test", "test-bulk-memory.wasm")], vec![]);
+            assert_eq!(
+                get_trap_code(vm.eval_function_by_index(
+                    0,
+                    function_index,
+                    &convert_i32_list(&args)
+                )),
+                TrapCode::TableOutOfBounds
+            );
+        }
+    }
+
+    #[test]
+    fn test_reference_types() {
+        let mut vm = create_test_instance(&[("test", "test-reference-types.wasm")], vec![]);
+
+        // 宿主传入的 externref 句柄原样返回
+        assert_eq!(
+            vm.eval_function_by_index(0, 3, &vec![Value::ExternRef(Some(123))])
+                .unwrap(),
+            vec![Value::ExternRef(Some(123))]
+        );
+        assert_eq!(
+            vm.eval_function_by_index(0, 3, &vec![Value::ExternRef(None)])
+                .unwrap(),
+            vec![Value::ExternRef(None)]
+        );
+
+        // ref.is_null
+        assert_eq!(
+            vm.eval_function_by_index(0, 4, &vec![Value::ExternRef(None)])
+                .unwrap(),
+            vec![Value::I32(1)]
+        );
+        assert_eq!(
+            vm.eval_function_by_index(0, 4, &vec![Value::ExternRef(Some(0))])
+                .unwrap(),
+            vec![Value::I32(0)]
+        );
+        assert_eq!(
+            vm.eval_function_by_index(0, 5, &vec![]).unwrap(),
+            vec![Value::I32(1), Value::I32(0)]
+        );
+
+        // select (result externref)
+        let args = vec![Value::ExternRef(Some(1)), Value::ExternRef(None)];
+        assert_eq!(
+            vm.eval_function_by_index(0, 6, &[args.clone(), vec![Value::I32(1)]].concat())
+                .unwrap(),
+            vec![Value::ExternRef(Some(1))]
+        );
+        assert_eq!(
+            vm.eval_function_by_index(0, 6, &[args, vec![Value::I32(0)]].concat())
+                .unwrap(),
+            vec![Value::ExternRef(None)]
+        );
+
+        // 参数的类型不一致
+        assert!(matches!(
+            vm.eval_function_by_index(0, 3, &vec![Value::FuncRef(Some(0))]),
+            Err(EngineError::TypeMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn test_table_instructions() {
+        let mut vm = create_test_instance(&[("test", "test-reference-types.wasm")], vec![]);
+
+        // 元素段使用表达式初始化，表的元素为 [$ten, null, $thirty]
+        assert_eq!(
+            vm.eval_function_by_index(0, 7, &vec![]).unwrap(),
+            vec![Value::I32(3)]
+        );
+        assert_eq!(
+            vm.eval_function_by_index(0, 8, &vec![Value::I32(0)])
+                .unwrap(),
+            vec![Value::I32(10)]
+        );
+        assert_eq!(
+            vm.eval_function_by_index(0, 8, &vec![Value::I32(1)])
+                .unwrap(),
+            vec![Value::I32(-1)]
+        );
+        assert_eq!(
+            vm.eval_function_by_index(0, 8, &vec![Value::I32(2)])
+                .unwrap(),
+            vec![Value::I32(30)]
+        );
+
+        // table.set，表的元素为 [null, $twenty, $thirty]
+        vm.eval_function_by_index(0, 9, &vec![Value::I32(1)])
+            .unwrap();
+        vm.eval_function_by_index(0, 10, &vec![Value::I32(0)])
+            .unwrap();
+        assert_eq!(
+            vm.eval_function_by_index(0, 8, &vec![Value::I32(0)])
+                .unwrap(),
+            vec![Value::I32(-1)]
+        );
+        assert_eq!(
+            vm.eval_function_by_index(0, 8, &vec![Value::I32(1)])
+                .unwrap(),
+            vec![Value::I32(20)]
+        );
+
+        // table.grow，表的元素为 [null, $twenty, $thirty, $thirty]
+        assert_eq!(
+            vm.eval_function_by_index(0, 11, &vec![Value::I32(1)])
+                .unwrap(),
+            vec![Value::I32(3)]
+        );
+        assert_eq!(
+            vm.eval_function_by_index(0, 8, &vec![Value::I32(3)])
+                .unwrap(),
+            vec![Value::I32(30)]
+        );
+
+        // 超出表的最大值时 table.grow 返回 -1，表的大小不变
+        assert_eq!(
+            vm.eval_function_by_index(0, 11, &vec![Value::I32(2)])
+                .unwrap(),
+            vec![Value::I32(-1)]
+        );
+        assert_eq!(
+            vm.eval_function_by_index(0, 7, &vec![]).unwrap(),
+            vec![Value::I32(4)]
+        );
+
+        // table.fill，表的元素为 [null, $ten, $ten, $ten]
+        vm.eval_function_by_index(0, 12, &convert_i32_list(&vec![1, 3]))
+            .unwrap();
+        assert_eq!(
+            vm.eval_function_by_index(0, 8, &vec![Value::I32(3)])
+                .unwrap(),
+            vec![Value::I32(10)]
+        );
+
+        let test_items = vec![
+            // table.get
+            (8, vec![3]),
+            (8, vec![-1]),
+            // table.set
+            (9, vec![3]),
+            (10, vec![3]),
+            // table.fill，注意即使长度为 0，开始位置超出了范围也会引发陷阱
+            (12, vec![2, 2]),
+            (12, vec![4, 0]),
+        ];
+
+        for (function_index, args) in test_items {
+            let mut vm = create_test_instance(&[("test", "test-reference-types.wasm")], vec![]);
+            assert_eq!(
+                get_trap_code(vm.eval_function_by_index(
+                    0,
+                    function_index,
+                    &convert_i32_list(&args)
+                )),
+                TrapCode::TableOutOfBounds
+            );
+        }
+    }
+
+    #[test]
+    fn test_multiple_memories() {
+        let mut vm = create_test_instance(
+            &[
+                ("lib", "test-multiple-memories-tables.wasm"),
+                ("import", "test-multiple-memories-tables-import.wasm"),
+            ],
+            vec![],
+        );
+
+        // 数据段写入到内存块 $m1
+        assert_eq!(
+            vm.eval_function_by_index(0, 2, &vec![Value::I32(0)])
+                .unwrap(),
+            convert_i32_list(&vec![0, 65])
+        );
+
+        // 写入内存块 $m0 不影响 $m1
+        vm.eval_function_by_index(0, 3, &convert_i32_list(&vec![1, 99]))
+            .unwrap();
+        assert_eq!(
+            vm.eval_function_by_index(0, 2, &vec![Value::I32(1)])
+                .unwrap(),
+            convert_i32_list(&vec![99, 66])
+        );
+
+        // 在不同的内存块之间复制数据
+        vm.eval_function_by_index(0, 4, &convert_i32_list(&vec![8, 2, 2]))
+            .unwrap();
+        assert_eq!(
+            vm.eval_function_by_index(0, 2, &vec![Value::I32(9)])
+                .unwrap(),
+            convert_i32_list(&vec![68, 0])
+        );
+        assert_eq!(
+            get_trap_code(vm.eval_function_by_index(0, 4, &convert_i32_list(&vec![0, 65535, 2]))),
+            TrapCode::MemoryOutOfBounds
+        );
+
+        // memory.size 和 table.size
+        let mut vm = create_test_instance(
+            &[
+                ("lib", "test-multiple-memories-tables.wasm"),
+                ("import", "test-multiple-memories-tables-import.wasm"),
+            ],
+            vec![],
+        );
+        assert_eq!(
+            vm.eval_function_by_index(0, 5, &vec![]).unwrap(),
+            convert_i32_list(&vec![1, 2, 1, 2])
+        );
+
+        // 导入的内存块的索引为 0，模块内定义的内存块的索引为 1
+        assert_eq!(
+            vm.eval_function_by_index(1, 0, &vec![Value::I32(2)])
+                .unwrap(),
+            convert_i32_list(&vec![67, 99])
+        );
+    }
+
+    #[test]
+    fn test_multiple_tables() {
+        let mut vm = create_test_instance(
+            &[
+                ("lib", "test-multiple-memories-tables.wasm"),
+                ("import", "test-multiple-memories-tables-import.wasm"),
+            ],
+            vec![],
+        );
+
+        // 元素段写入到表 $t1
+        assert_eq!(
+            vm.eval_function_by_index(0, 6, &vec![Value::I32(1)])
+                .unwrap(),
+            vec![Value::I32(20)]
+        );
+        assert_eq!(
+            vm.eval_function_by_index(0, 8, &vec![Value::I32(0)])
+                .unwrap(),
+            vec![Value::I32(10)]
+        );
+        assert_eq!(
+            get_trap_code(vm.eval_function_by_index(0, 6, &vec![Value::I32(0)])),
+            TrapCode::UndefinedElement
+        );
+
+        // 在不同的表之间复制元素
+        let mut vm = create_test_instance(
+            &[
+                ("lib", "test-multiple-memories-tables.wasm"),
+                ("import", "test-multiple-memories-tables-import.wasm"),
+            ],
+            vec![],
+        );
+        vm.eval_function_by_index(0, 7, &convert_i32_list(&vec![0, 1, 1]))
+            .unwrap();
+        assert_eq!(
+            vm.eval_function_by_index(0, 8, &vec![Value::I32(0)])
+                .unwrap(),
+            vec![Value::I32(20)]
+        );
+        assert_eq!(
+            get_trap_code(vm.eval_function_by_index(0, 7, &convert_i32_list(&vec![0, 0, 2]))),
+            TrapCode::TableOutOfBounds
+        );
+
+        // 导入的表跟被导入的表是同一张表
+        let mut vm = create_test_instance(
+            &[
+                ("lib", "test-multiple-memories-tables.wasm"),
+                ("import", "test-multiple-memories-tables-import.wasm"),
+            ],
+            vec![],
+        );
+        vm.eval_function_by_index(1, 1, &vec![Value::I32(1)])
+            .unwrap();
+        assert_eq!(
+            get_trap_code(vm.eval_function_by_index(0, 6, &vec![Value::I32(1)])),
+            TrapCode::UndefinedElement
+        );
+    }
+
+    #[test]
+    fn test_function_reference_across_modules() {
+        let mut vm = create_test_instance(
+            &[
+                ("lib", "test-function-reference-lib.wasm"),
+                ("app", "test-function-reference.wasm"),
+            ],
+            vec![],
+        );
+
+        // 模块 lib 写入共享表的函数引用，在模块 app 里仍然指向 lib 的函数，
+        // 而不是 app 里索引相同的函数
+        vm.eval_function_by_index(0, 1, &vec![Value::I32(0)])
+            .unwrap();
+        assert_eq!(
+            vm.eval_function_by_index(1, 1, &vec![Value::I32(0)])
+                .unwrap(),
+            vec![Value::I32(42)]
+        );
+
+        // 经过宿主传递的函数引用
+        let function_reference = vm.eval_function_by_index(0, 2, &vec![]).unwrap()[0];
+        vm.eval_function_by_index(1, 2, &vec![Value::I32(1), function_reference])
+            .unwrap();
+        assert_eq!(
+            vm.eval_function_by_index(1, 1, &vec![Value::I32(1)])
+                .unwrap(),
+            vec![Value::I32(42)]
+        );
+
+        // 无效的函数引用
+        let table = vm.get_export_table("lib", "table").unwrap();
+        table.get_mut(&mut vm).set_element(1, 1000).unwrap();
+        assert_eq!(
+            get_trap_code(vm.eval_function_by_index(1, 1, &vec![Value::I32(1)])),
+            TrapCode::UndefinedElement
+        );
+    }
+
+    #[test]
+    fn test_constant_expression() {
+        let named_ast_module_lib =
+            get_test_named_ast_module("lib", "test-constant-expression-lib.wasm");
+        let named_ast_module_test =
+            get_test_named_ast_module("test", "test-constant-expression.wasm");
+
+        // 导入全局变量的模块排在被导入的模块之前
+        let mut vm =
+            create_instance(vec![], &vec![named_ast_module_test, named_ast_module_lib]).unwrap();
+
+        // 全局变量的初始值
+        assert_eq!(
+            vm.eval_function_by_index(0, 0, &vec![]).unwrap(),
+            convert_i32_list(&[1024, 1040, 1000, 6])
+        );
+        assert_eq!(
+            vm.eval_function_by_index(0, 1, &vec![]).unwrap(),
+            vec![Value::I64(201), Value::I64(-100)]
+        );
+
+        // 数据段的偏移值
+        assert_eq!(
+            vm.eval_function_by_index(0, 2, &vec![]).unwrap(),
+            convert_i32_list(&[0x0201, 0x0403])
+        );
+
+        // 元素段的偏移值
+        assert_eq!(
+            vm.eval_function_by_index(0, 3, &vec![Value::I32(2)])
+                .unwrap(),
+            vec![Value::I32(10)]
+        );
+        assert_eq!(
+            vm.eval_function_by_index(0, 3, &vec![Value::I32(3)])
+                .unwrap(),
+            vec![Value::I32(11)]
+        );
+
+        // 全局变量的初始化常量表达式存在循环依赖
+        let named_ast_module_circular =
+            get_test_named_ast_module("circular", "test-constant-expression-circular.wasm");
+        assert!(matches!(
+            create_instance(vec![], &vec![named_ast_module_circular]),
+            Err(EngineError::InvalidOperation(
+                InvalidOperation::CircularGlobalVariableInitialization(_, _)
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_constant_expression_evaluation() {
+        let global_values = vec![Value::I32(100), Value::I64(200)];
+
+        assert_eq!(
+            VM::get_constant_instruction_value(
+                &vec![
+                    instruction::Instruction::I32Const(7),
+                    instruction::Instruction::GlobalGet(0),
+                    instruction::Instruction::I32Sub,
+                    instruction::Instruction::End
+                ],
+                &global_values,
+                0
+            )
+            .unwrap(),
+            Value::I32(-93)
+        );
+
+        // 整数运算溢出时回绕
+        assert_eq!(
+            VM::get_constant_instruction_value(
+                &vec![
+                    instruction::Instruction::I32Const(i32::MAX),
+                    instruction::Instruction::I32Const(1),
+                    instruction::Instruction::I32Add,
+                    instruction::Instruction::End
+                ],
+                &global_values,
+                0
+            )
+            .unwrap(),
+            Value::I32(i32::MIN)
+        );
+
+        // 全局变量索引超出范围
+        assert!(matches!(
+            VM::get_constant_instruction_value(
+                &vec![
+                    instruction::Instruction::GlobalGet(2),
+                    instruction::Instruction::End
+                ],
+                &global_values,
+                0
+            ),
+            Err(EngineError::OutOfRange(
+                OutOfRange::GlobalVariableIndexOutOfRange(2, 2)
+            ))
+        ));
+
+        // 操作数的数据类型不正确
+        assert!(matches!(
+            VM::get_constant_instruction_value(
+                &vec![
+                    instruction::Instruction::GlobalGet(1),
+                    instruction::Instruction::I32Const(1),
+                    instruction::Instruction::I32Mul,
+                    instruction::Instruction::End
+                ],
+                &global_values,
+                0
+            ),
+            Err(EngineError::TypeMismatch(_))
+        ));
+
+        // 表达式的结果不是一个值
+        assert!(matches!(
+            VM::get_constant_instruction_value(
+                &vec![
+                    instruction::Instruction::I32Const(1),
+                    instruction::Instruction::I32Const(2),
+                    instruction::Instruction::End
+                ],
+                &global_values,
+                0
+            ),
+            Err(EngineError::InvalidOperation(
+                InvalidOperation::IncorrectConstantExpressionResultCount(2)
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_start_function() {
+        let named_ast_module_app = get_test_named_ast_module("app", "test-start-function.wasm");
+        let named_ast_module_lib = get_test_named_ast_module("lib", "test-start-function-lib.wasm");
+
+        // 模块 "app" 排在被导入的模块 "lib" 之前，但 "lib" 应该先实例化，
+        // 即计数器先被设置为 10，然后再增加 1
+        let mut vm =
+            create_instance(vec![], &vec![named_ast_module_app, named_ast_module_lib]).unwrap();
+
+        assert_eq!(
+            vm.eval_function_by_index(1, 2, &vec![]).unwrap(),
+            vec![Value::I32(11)]
+        );
+
+        // start 函数执行时 data 已经填充到内存
+        assert_eq!(
+            vm.eval_function_by_index(0, 2, &vec![]).unwrap(),
+            vec![Value::I32(42)]
+        );
+
+        // start 函数引发陷阱时实例化失败
+        let named_ast_module_trap =
+            get_test_named_ast_module("trap", "test-start-function-trap.wasm");
+        let result = create_instance(vec![], &vec![named_ast_module_trap]);
+
+        assert!(matches!(
+            &result,
+            Err(EngineError::StartFunctionTrap(
+                module_name,
+                Trap {
+                    code: TrapCode::Unreachable,
+                    ..
+                }
+            )) if module_name == "trap"
+        ));
+    }
+
+    #[test]
+    fn test_start_function_with_config() {
+        // start 函数递归 100 层，即需要 101 个调用帧
+        let named_ast_modules = vec![get_test_named_ast_module(
+            "test",
+            "test-start-function-limits.wasm",
+        )];
+
+        let mut vm = create_instance(vec![], &named_ast_modules).unwrap();
+        assert_eq!(
+            vm.eval_function_by_index(0, 2, &vec![]).unwrap(),
+            vec![Value::I32(101)]
+        );
+
+        // 调用帧数量的上限对 start 函数有效
+        let config = InstanceConfig {
+            max_call_depth: Some(50),
+            ..InstanceConfig::default()
+        };
+        let result = create_instance_with_config(vec![], &named_ast_modules, &config);
+
+        assert!(matches!(
+            &result,
+            Err(EngineError::StartFunctionTrap(
+                module_name,
+                Trap {
+                    code: TrapCode::StackExhausted,
+                    ..
+                }
+            )) if module_name == "test"
+        ));
+
+        // 燃料对 start 函数有效
+        let config = InstanceConfig {
+            fuel: Some(100),
+            ..InstanceConfig::default()
+        };
+        let result = create_instance_with_config(vec![], &named_ast_modules, &config);
+
+        assert!(matches!(
+            result,
+            Err(EngineError::Interrupt(Interrupt::OutOfFuel { .. }))
+        ));
+
+        // start 函数执行完毕之后，剩余的燃料留给之后调用的函数
+        let config = InstanceConfig {
+            max_call_depth: Some(200),
+            fuel: Some(10000),
+            ..InstanceConfig::default()
+        };
+        let mut vm = create_instance_with_config(vec![], &named_ast_modules, &config).unwrap();
+        let fuel = vm.get_fuel().unwrap();
+        assert!(fuel < 10000);
+
+        assert_eq!(
+            vm.eval_function_by_index(0, 2, &vec![]).unwrap(),
+            vec![Value::I32(101)]
+        );
+        assert!(vm.get_fuel().unwrap() < fuel);
+
+        // 通过 Engine 实例化时同样有效
+        let module = Engine::new()
+            .compile(&[], named_ast_modules.clone())
+            .unwrap();
+        let config = InstanceConfig {
+            max_call_depth: Some(50),
+            ..InstanceConfig::default()
+        };
+
+        assert!(matches!(
+            module.instantiate_with_config(vec![], &config),
+            Err(EngineError::StartFunctionTrap(
+                _,
+                Trap {
+                    code: TrapCode::StackExhausted,
+                    ..
+                }
+            ))
+        ));
+    }
+
+    #[test]
+    fn test_validate_before_instantiation() {
+        // 无效的模块无法实例化
+        let named_ast_module = get_test_named_ast_module("test", "test-invalid-block.wasm");
+        let result = create_instance(vec![], &vec![named_ast_module]);
+
+        assert!(matches!(
+            &result,
+            Err(EngineError::Validation(
+                module_name,
+                ValidationError {
+                    location: ValidationLocation::Function {
+                        function_index: 0,
+                        instruction_index: 2
+                    },
+                    ..
+                }
+            )) if module_name == "test"
+        ));
+
+        // 跳过验证则可以实例化
+        let named_ast_module = get_test_named_ast_module("test", "test-invalid-block.wasm");
+        assert!(create_instance_without_validation(
+            vec![],
+            &vec![named_ast_module],
+            &InstanceConfig::default(),
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn test_stack_underflow_trap() {
+        // 未经验证的模块弹出操作数时如果操作数不足，会产生陷阱而不是 panic
+        let named_ast_module =
+            get_test_named_ast_module("test", "test-invalid-stack-underflow.wasm");
+        let mut vm = create_instance_without_validation(
+            vec![],
+            &vec![named_ast_module],
+            &InstanceConfig::default(),
+        )
+        .unwrap();
+
+        match vm.eval_function_by_index(0, 0, &vec![]) {
+            Err(EngineError::Trap(trap)) => {
+                assert_eq!(trap.code, TrapCode::StackUnderflow);
+                assert_eq!(trap.backtrace.frames.len(), 1);
+                assert_eq!(trap.backtrace.frames[0].instruction_offset, 1);
+            }
+            _ => panic!("expected a trap"),
+        }
+    }
 }
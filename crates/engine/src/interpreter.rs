@@ -4,279 +4,503 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use anvm_ast::instruction::Instruction;
+//! # 指令的编译及执行
+//!
+//! 模块的指令列表由 decoder 解码之后，在创建 VM 模块时再被编译为
+//! "处理函数 + 立即数" 的列表（即 direct-threaded code），每条指令的
+//! 处理函数在编译时已经确定，执行时无需再逐条匹配指令的种类。
+//!
+//! 编译后的指令（CompiledInstruction）只包含一个函数指针及一个可复制的立即数，
+//! 所以 VM 执行指令时只需复制这两个值，而不用克隆整条指令。
+//!
+//! `br_table 指令` 的跳转目标列表长度不固定，所以不放在立即数里，
+//! 执行时从模块的原指令列表读取。
+
+use anvm_ast::instruction::{BlockType, Instruction, MemoryArgument};
 
 use crate::{
     error::EngineError,
     ins_block, ins_const,
     ins_control::{self, ControlResult},
-    ins_function::{self},
-    ins_memory, ins_numeric_binary, ins_numeric_comparsion, ins_numeric_convert, ins_numeric_eqz,
-    ins_numeric_unary, ins_parametric, ins_variable,
-    object::{self, Control},
+    ins_function, ins_memory, ins_numeric_binary, ins_numeric_comparsion, ins_numeric_convert,
+    ins_numeric_eqz, ins_numeric_unary, ins_parametric, ins_reference, ins_table, ins_variable,
+    object::{self, Control, Fused, I32Comparison},
     vm::VM,
 };
 
-pub fn exec_instruction(
-    vm: &mut VM,
-    instruction: &object::Instruction,
-) -> Result<bool, EngineError> {
-    match instruction {
-        object::Instruction::Sequence(instruction) => {
-            let sequence_result = match instruction {
-                // 常量指令
-                Instruction::I32Const(value) => ins_const::i32_const(vm, *value),
-                Instruction::I64Const(value) => ins_const::i64_const(vm, *value),
-                Instruction::F32Const(value) => ins_const::f32_const(vm, *value),
-                Instruction::F64Const(value) => ins_const::f64_const(vm, *value),
-
-                // 操作数（参数，parametric）指令
-                Instruction::Drop => ins_parametric::drop(vm),
-                Instruction::Select => ins_parametric::select(vm),
-
-                // 零值测试指令
-                Instruction::I32Eqz => ins_numeric_eqz::i32_eqz(vm),
-                Instruction::I64Eqz => ins_numeric_eqz::i64_eqz(vm),
-
-                // 数值比较指令
-                Instruction::I32Eq => ins_numeric_comparsion::i32_eq(vm),
-                Instruction::I32Ne => ins_numeric_comparsion::i32_ne(vm),
-                Instruction::I32LtS => ins_numeric_comparsion::i32_lt_s(vm),
-                Instruction::I32LtU => ins_numeric_comparsion::i32_lt_u(vm),
-                Instruction::I32GtS => ins_numeric_comparsion::i32_gt_s(vm),
-                Instruction::I32GtU => ins_numeric_comparsion::i32_gt_u(vm),
-                Instruction::I32LeS => ins_numeric_comparsion::i32_le_s(vm),
-                Instruction::I32LeU => ins_numeric_comparsion::i32_le_u(vm),
-                Instruction::I32GeS => ins_numeric_comparsion::i32_ge_s(vm),
-                Instruction::I32GeU => ins_numeric_comparsion::i32_ge_u(vm),
-
-                Instruction::I64Eq => ins_numeric_comparsion::i64_eq(vm),
-                Instruction::I64Ne => ins_numeric_comparsion::i64_ne(vm),
-                Instruction::I64LtS => ins_numeric_comparsion::i64_lt_s(vm),
-                Instruction::I64LtU => ins_numeric_comparsion::i64_lt_u(vm),
-                Instruction::I64GtS => ins_numeric_comparsion::i64_gt_s(vm),
-                Instruction::I64GtU => ins_numeric_comparsion::i64_gt_u(vm),
-                Instruction::I64LeS => ins_numeric_comparsion::i64_le_s(vm),
-                Instruction::I64LeU => ins_numeric_comparsion::i64_le_u(vm),
-                Instruction::I64GeS => ins_numeric_comparsion::i64_ge_s(vm),
-                Instruction::I64GeU => ins_numeric_comparsion::i64_ge_u(vm),
-
-                Instruction::F32Eq => ins_numeric_comparsion::f32_eq(vm),
-                Instruction::F32Ne => ins_numeric_comparsion::f32_ne(vm),
-                Instruction::F32Lt => ins_numeric_comparsion::f32_lt(vm),
-                Instruction::F32Gt => ins_numeric_comparsion::f32_gt(vm),
-                Instruction::F32Le => ins_numeric_comparsion::f32_le(vm),
-                Instruction::F32Ge => ins_numeric_comparsion::f32_ge(vm),
-
-                Instruction::F64Eq => ins_numeric_comparsion::f64_eq(vm),
-                Instruction::F64Ne => ins_numeric_comparsion::f64_ne(vm),
-                Instruction::F64Lt => ins_numeric_comparsion::f64_lt(vm),
-                Instruction::F64Gt => ins_numeric_comparsion::f64_gt(vm),
-                Instruction::F64Le => ins_numeric_comparsion::f64_le(vm),
-                Instruction::F64Ge => ins_numeric_comparsion::f64_ge(vm),
-
-                // 一元运算
-                Instruction::I32Clz => ins_numeric_unary::i32_clz(vm),
-                Instruction::I32Ctz => ins_numeric_unary::i32_ctz(vm),
-                Instruction::I32PopCnt => ins_numeric_unary::i32_popcnt(vm),
-
-                Instruction::I64Clz => ins_numeric_unary::i64_clz(vm),
-                Instruction::I64Ctz => ins_numeric_unary::i64_ctz(vm),
-                Instruction::I64PopCnt => ins_numeric_unary::i64_popcnt(vm),
-
-                Instruction::F32Abs => ins_numeric_unary::f32_abs(vm),
-                Instruction::F32Neg => ins_numeric_unary::f32_neg(vm),
-                Instruction::F32Ceil => ins_numeric_unary::f32_ceil(vm),
-                Instruction::F32Floor => ins_numeric_unary::f32_floor(vm),
-                Instruction::F32Trunc => ins_numeric_unary::f32_trunc(vm),
-                Instruction::F32Nearest => ins_numeric_unary::f32_nearest(vm),
-                Instruction::F32Sqrt => ins_numeric_unary::f32_sqrt(vm),
-
-                Instruction::F64Abs => ins_numeric_unary::f64_abs(vm),
-                Instruction::F64Neg => ins_numeric_unary::f64_neg(vm),
-                Instruction::F64Ceil => ins_numeric_unary::f64_ceil(vm),
-                Instruction::F64Floor => ins_numeric_unary::f64_floor(vm),
-                Instruction::F64Trunc => ins_numeric_unary::f64_trunc(vm),
-                Instruction::F64Nearest => ins_numeric_unary::f64_nearest(vm),
-                Instruction::F64Sqrt => ins_numeric_unary::f64_sqrt(vm),
-
-                // 二元运算
-                Instruction::I32Add => ins_numeric_binary::i32_add(vm),
-                Instruction::I32Sub => ins_numeric_binary::i32_sub(vm),
-                Instruction::I32Mul => ins_numeric_binary::i32_mul(vm),
-                Instruction::I32DivS => ins_numeric_binary::i32_div_s(vm),
-                Instruction::I32DivU => ins_numeric_binary::i32_div_u(vm),
-                Instruction::I32RemS => ins_numeric_binary::i32_rem_s(vm),
-                Instruction::I32RemU => ins_numeric_binary::i32_rem_u(vm),
-                Instruction::I32And => ins_numeric_binary::i32_and(vm),
-                Instruction::I32Or => ins_numeric_binary::i32_or(vm),
-                Instruction::I32Xor => ins_numeric_binary::i32_xor(vm),
-                Instruction::I32Shl => ins_numeric_binary::i32_shl(vm),
-                Instruction::I32ShrS => ins_numeric_binary::i32_shr_s(vm),
-                Instruction::I32ShrU => ins_numeric_binary::i32_shr_u(vm),
-                Instruction::I32Rotl => ins_numeric_binary::i32_rotl(vm),
-                Instruction::I32Rotr => ins_numeric_binary::i32_rotr(vm),
-
-                Instruction::I64Add => ins_numeric_binary::i64_add(vm),
-                Instruction::I64Sub => ins_numeric_binary::i64_sub(vm),
-                Instruction::I64Mul => ins_numeric_binary::i64_mul(vm),
-                Instruction::I64DivS => ins_numeric_binary::i64_div_s(vm),
-                Instruction::I64DivU => ins_numeric_binary::i64_div_u(vm),
-                Instruction::I64RemS => ins_numeric_binary::i64_rem_s(vm),
-                Instruction::I64RemU => ins_numeric_binary::i64_rem_u(vm),
-                Instruction::I64And => ins_numeric_binary::i64_and(vm),
-                Instruction::I64Or => ins_numeric_binary::i64_or(vm),
-                Instruction::I64Xor => ins_numeric_binary::i64_xor(vm),
-                Instruction::I64Shl => ins_numeric_binary::i64_shl(vm),
-                Instruction::I64ShrS => ins_numeric_binary::i64_shr_s(vm),
-                Instruction::I64ShrU => ins_numeric_binary::i64_shr_u(vm),
-                Instruction::I64Rotl => ins_numeric_binary::i64_rotl(vm),
-                Instruction::I64Rotr => ins_numeric_binary::i64_rotr(vm),
-
-                Instruction::F32Add => ins_numeric_binary::f32_add(vm),
-                Instruction::F32Sub => ins_numeric_binary::f32_sub(vm),
-                Instruction::F32Mul => ins_numeric_binary::f32_mul(vm),
-                Instruction::F32Div => ins_numeric_binary::f32_div(vm),
-                Instruction::F32Min => ins_numeric_binary::f32_min(vm),
-                Instruction::F32Max => ins_numeric_binary::f32_max(vm),
-                Instruction::F32CopySign => ins_numeric_binary::f32_copysign(vm),
-
-                Instruction::F64Add => ins_numeric_binary::f64_add(vm),
-                Instruction::F64Sub => ins_numeric_binary::f64_sub(vm),
-                Instruction::F64Mul => ins_numeric_binary::f64_mul(vm),
-                Instruction::F64Div => ins_numeric_binary::f64_div(vm),
-                Instruction::F64Min => ins_numeric_binary::f64_min(vm),
-                Instruction::F64Max => ins_numeric_binary::f64_max(vm),
-                Instruction::F64CopySign => ins_numeric_binary::f64_copysign(vm),
-
-                // 类型转换指令
-                Instruction::I32WrapI64 => ins_numeric_convert::i32_wrap_i64(vm),
-
-                Instruction::I32Extend8S => ins_numeric_convert::i32_extend8_s(vm),
-                Instruction::I32Extend16S => ins_numeric_convert::i32_extend16_s(vm),
-                Instruction::I64ExtendI32S => ins_numeric_convert::i64_extend_i32_s(vm),
-                Instruction::I64ExtendI32U => ins_numeric_convert::i64_extend_i32_u(vm),
-                Instruction::I64Extend8S => ins_numeric_convert::i64_extend8_s(vm),
-                Instruction::I64Extend16S => ins_numeric_convert::i64_extend16_s(vm),
-                Instruction::I64Extend32S => ins_numeric_convert::i64_extend32_s(vm),
-
-                Instruction::I32TruncF32S => ins_numeric_convert::i32_trunc_f32_s(vm),
-                Instruction::I32TruncF32U => ins_numeric_convert::i32_trunc_f32_u(vm),
-                Instruction::I64TruncF32S => ins_numeric_convert::i64_trunc_f32_s(vm),
-                Instruction::I64TruncF32U => ins_numeric_convert::i64_trunc_f32_u(vm),
-                Instruction::I32TruncF64S => ins_numeric_convert::i32_trunc_f64_s(vm),
-                Instruction::I32TruncF64U => ins_numeric_convert::i32_trunc_f64_u(vm),
-                Instruction::I64TruncF64S => ins_numeric_convert::i64_trunc_f64_s(vm),
-                Instruction::I64TruncF64U => ins_numeric_convert::i64_trunc_f64_u(vm),
-
-                Instruction::I32TruncSatF32S => todo!(),
-                Instruction::I32TruncSatF32U => todo!(),
-                Instruction::I32TruncSatF64S => todo!(),
-                Instruction::I32TruncSatF64U => todo!(),
-
-                Instruction::I64TruncSatF32S => todo!(),
-                Instruction::I64TruncSatF32U => todo!(),
-                Instruction::I64TruncSatF64S => todo!(),
-                Instruction::I64TruncSatF64U => todo!(),
-
-                Instruction::F32ConvertI32S => ins_numeric_convert::f32_convert_i32_s(vm),
-                Instruction::F32ConvertI32U => ins_numeric_convert::f32_convert_i32_u(vm),
-                Instruction::F64ConvertI32S => ins_numeric_convert::f64_convert_i32_s(vm),
-                Instruction::F64ConvertI32U => ins_numeric_convert::f64_convert_i32_u(vm),
-                Instruction::F32ConvertI64S => ins_numeric_convert::f32_convert_i64_s(vm),
-                Instruction::F32ConvertI64U => ins_numeric_convert::f32_convert_i64_u(vm),
-                Instruction::F64ConvertI64S => ins_numeric_convert::f64_convert_i64_s(vm),
-                Instruction::F64ConvertI64U => ins_numeric_convert::f64_convert_i64_u(vm),
-
-                Instruction::F32DemoteF64 => ins_numeric_convert::f32_demote_f64_s(vm),
-                Instruction::F64PromoteF32 => ins_numeric_convert::f64_promote_f32(vm),
-
-                Instruction::I32ReinterpretF32 => ins_numeric_convert::i32_reinterpret_f32(vm),
-                Instruction::I64ReinterpretF64 => ins_numeric_convert::i64_reinterpret_f64(vm),
-                Instruction::F32ReinterpretI32 => ins_numeric_convert::f32_reinterpret_i32(vm),
-                Instruction::F64ReinterpretI64 => ins_numeric_convert::f64_reinterpret_i64(vm),
-
-                // 变量指令
-                Instruction::LocalGet(index) => ins_variable::local_get(vm, *index),
-                Instruction::LocalSet(index) => ins_variable::local_set(vm, *index),
-                Instruction::LocalTee(index) => ins_variable::local_tee(vm, *index),
-                Instruction::GlobalGet(index) => ins_variable::global_get(vm, *index),
-                Instruction::GlobalSet(index) => ins_variable::global_set(vm, *index),
-
-                // 内存指令
-                Instruction::MemorySize(memory_block_index) => {
-                    ins_memory::memory_size(vm, *memory_block_index)
-                }
-                Instruction::MemoryGrow(memory_block_index) => {
-                    ins_memory::memory_grow(vm, *memory_block_index)
-                }
+/// 指令的处理函数
+///
+/// 返回值表示程序（即第一个被调用的函数）是否已经执行完毕。
+pub type Handler = fn(&mut VM, &Operand) -> Result<bool, EngineError>;
 
-                Instruction::MemoryInit(data_index, memory_block_index) => todo!(),
-                Instruction::DataDrop(data_index) => todo!(),
-                Instruction::MemoryCopy(source_memory_block_index, dest_memory_block_index) => {
-                    todo!()
-                }
-                Instruction::MemoryFill(memory_block_index) => todo!(),
-
-                Instruction::I32Load(memory_args) => ins_memory::i32_load(vm, memory_args),
-                Instruction::I32Load16S(memory_args) => ins_memory::i32_load16_s(vm, memory_args),
-                Instruction::I32Load16U(memory_args) => ins_memory::i32_load16_u(vm, memory_args),
-                Instruction::I32Load8S(memory_args) => ins_memory::i32_load8_s(vm, memory_args),
-                Instruction::I32Load8U(memory_args) => ins_memory::i32_load8_u(vm, memory_args),
-
-                Instruction::I64Load(memory_args) => ins_memory::i64_load(vm, memory_args),
-                Instruction::I64Load32S(memory_args) => ins_memory::i64_load32_s(vm, memory_args),
-                Instruction::I64Load32U(memory_args) => ins_memory::i64_load32_u(vm, memory_args),
-                Instruction::I64Load16S(memory_args) => ins_memory::i64_load16_s(vm, memory_args),
-                Instruction::I64Load16U(memory_args) => ins_memory::i64_load16_u(vm, memory_args),
-                Instruction::I64Load8S(memory_args) => ins_memory::i64_load8_s(vm, memory_args),
-                Instruction::I64Load8U(memory_args) => ins_memory::i64_load8_u(vm, memory_args),
-
-                Instruction::F32Load(memory_args) => ins_memory::f32_load(vm, memory_args),
-                Instruction::F64Load(memory_args) => ins_memory::f64_load(vm, memory_args),
-
-                Instruction::I32Store(memory_args) => ins_memory::i32_store(vm, memory_args),
-                Instruction::I32Store16(memory_args) => ins_memory::i32_store_16(vm, memory_args),
-                Instruction::I32Store8(memory_args) => ins_memory::i32_store_8(vm, memory_args),
-                Instruction::I64Store(memory_args) => ins_memory::i64_store(vm, memory_args),
-                Instruction::I64Store32(memory_args) => ins_memory::i64_store_32(vm, memory_args),
-                Instruction::I64Store16(memory_args) => ins_memory::i64_store_16(vm, memory_args),
-                Instruction::I64Store8(memory_args) => ins_memory::i64_store_8(vm, memory_args),
-
-                Instruction::F32Store(memory_args) => ins_memory::f32_store(vm, memory_args),
-                Instruction::F64Store(memory_args) => ins_memory::f64_store(vm, memory_args),
-
-                // 表指令
-                Instruction::TableGet(table_index) => todo!(),
-                Instruction::TableSet(table_index) => todo!(),
-                Instruction::TableInit(element_index, table_index) => todo!(),
-                Instruction::ElementDrop(element_index) => todo!(),
-                Instruction::TableCopy(source_table_index, dest_table_index) => todo!(),
-                Instruction::TableGrow(table_index) => todo!(),
-                Instruction::TableSize(table_index) => todo!(),
-                Instruction::TableFill(table_index) => todo!(),
-
-                // 其他指令已经被替换成 Instruction::Control，所以
-                // 程序不应该来到这个分支
-                _ => {
-                    unreachable!("should no this instruction")
-                }
-            };
+/// 编译后的指令
+#[derive(Debug, Clone, Copy)]
+pub struct CompiledInstruction {
+    pub handler: Handler,
+    pub operand: Operand,
+}
 
-            match sequence_result {
-                Ok(_) => {
-                    vm.status.address += 1;
-                    Ok(false)
-                }
-                Err(e) => Err(e),
+/// 指令的立即数
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Operand {
+    None,
+    I32(i32),
+    I64(i64),
+    F32(f32),
+    F64(f64),
+
+    /// 局部变量、全局变量、内存块、表、数据段、元素段或者函数的索引
+    Index(u32),
+    IndexPair(u32, u32),
+    Memory(MemoryArgument),
+
+    /// 原 `end 指令` 所在的结构块索引
+    End(Option<usize>),
+    Call {
+        vm_module_index: usize,
+        type_index: usize,
+        function_index: usize,
+        internal_function_index: usize,
+        address: usize,
+    },
+    CallNative {
+        native_module_index: usize,
+        type_index: usize,
+        function_index: usize,
+    },
+    CallIndirect {
+        type_index: usize,
+        table_index: usize,
+    },
+    Block {
+        block_type: BlockType,
+        block_index: usize,
+        end_address: usize,
+    },
+    BlockAndJumpWhenEqZero {
+        block_type: BlockType,
+        block_index: usize,
+        option_alternate_address: Option<usize>,
+        end_address: usize,
+    },
+    Address(usize),
+    Break {
+        option_block_index: Option<usize>,
+        relative_depth: usize,
+        address: usize,
+    },
+    Recur {
+        block_index: usize,
+        relative_depth: usize,
+        address: usize,
+    },
+
+    /// 原 `br_table 指令` 所在的结构块索引
+    Branch(Option<usize>),
+
+    /// 局部变量的索引以及 i32 常量
+    LocalI32(u32, i32),
+
+    /// 源局部变量的索引、i32 常量以及目标局部变量的索引
+    LocalI32Local(u32, i32, u32),
+}
+
+/// 生成顺序执行的指令的处理函数，处理函数执行完指令之后，
+/// 把 pc 指向下一条指令。
+macro_rules! sequence {
+    ($function:path) => {{
+        fn handler(vm: &mut VM, _operand: &Operand) -> Result<bool, EngineError> {
+            $function(vm)?;
+            vm.status.address += 1;
+            Ok(false)
+        }
+        CompiledInstruction {
+            handler,
+            operand: Operand::None,
+        }
+    }};
+    ($function:path, Memory($value:expr)) => {{
+        fn handler(vm: &mut VM, operand: &Operand) -> Result<bool, EngineError> {
+            match operand {
+                Operand::Memory(memory_args) => $function(vm, memory_args)?,
+                _ => unreachable!(),
+            }
+            vm.status.address += 1;
+            Ok(false)
+        }
+        CompiledInstruction {
+            handler,
+            operand: Operand::Memory($value),
+        }
+    }};
+    ($function:path, IndexPair($first:expr, $second:expr)) => {{
+        fn handler(vm: &mut VM, operand: &Operand) -> Result<bool, EngineError> {
+            match operand {
+                Operand::IndexPair(first, second) => $function(vm, *first, *second)?,
+                _ => unreachable!(),
             }
+            vm.status.address += 1;
+            Ok(false)
         }
+        CompiledInstruction {
+            handler,
+            operand: Operand::IndexPair($first, $second),
+        }
+    }};
+    ($function:path, $variant:ident($value:expr)) => {{
+        fn handler(vm: &mut VM, operand: &Operand) -> Result<bool, EngineError> {
+            match operand {
+                Operand::$variant(value) => $function(vm, *value)?,
+                _ => unreachable!(),
+            }
+            vm.status.address += 1;
+            Ok(false)
+        }
+        CompiledInstruction {
+            handler,
+            operand: Operand::$variant($value),
+        }
+    }};
+}
+
+/// 编译一个模块的指令列表
+///
+/// 编译后的指令列表跟原指令列表一一对应，即指令的地址保持不变。
+pub fn compile(instructions: &[object::Instruction]) -> Vec<CompiledInstruction> {
+    instructions.iter().map(compile_instruction).collect()
+}
+
+fn compile_instruction(instruction: &object::Instruction) -> CompiledInstruction {
+    match instruction {
+        object::Instruction::Sequence(instruction) => match instruction {
+            // 常量指令
+            Instruction::I32Const(value) => sequence!(ins_const::i32_const, I32(*value)),
+            Instruction::I64Const(value) => sequence!(ins_const::i64_const, I64(*value)),
+            Instruction::F32Const(value) => sequence!(ins_const::f32_const, F32(*value)),
+            Instruction::F64Const(value) => sequence!(ins_const::f64_const, F64(*value)),
+
+            // 操作数（参数，parametric）指令
+            Instruction::Drop => sequence!(ins_parametric::drop),
+            Instruction::Select => sequence!(ins_parametric::select),
+            // `select t 指令` 的类型标注只用于验证，执行时跟 `select 指令` 一样
+            Instruction::SelectTyped(_) => sequence!(ins_parametric::select),
+
+            // 零值测试指令
+            Instruction::I32Eqz => sequence!(ins_numeric_eqz::i32_eqz),
+            Instruction::I64Eqz => sequence!(ins_numeric_eqz::i64_eqz),
+
+            // 数值比较指令
+            Instruction::I32Eq => sequence!(ins_numeric_comparsion::i32_eq),
+            Instruction::I32Ne => sequence!(ins_numeric_comparsion::i32_ne),
+            Instruction::I32LtS => sequence!(ins_numeric_comparsion::i32_lt_s),
+            Instruction::I32LtU => sequence!(ins_numeric_comparsion::i32_lt_u),
+            Instruction::I32GtS => sequence!(ins_numeric_comparsion::i32_gt_s),
+            Instruction::I32GtU => sequence!(ins_numeric_comparsion::i32_gt_u),
+            Instruction::I32LeS => sequence!(ins_numeric_comparsion::i32_le_s),
+            Instruction::I32LeU => sequence!(ins_numeric_comparsion::i32_le_u),
+            Instruction::I32GeS => sequence!(ins_numeric_comparsion::i32_ge_s),
+            Instruction::I32GeU => sequence!(ins_numeric_comparsion::i32_ge_u),
+
+            Instruction::I64Eq => sequence!(ins_numeric_comparsion::i64_eq),
+            Instruction::I64Ne => sequence!(ins_numeric_comparsion::i64_ne),
+            Instruction::I64LtS => sequence!(ins_numeric_comparsion::i64_lt_s),
+            Instruction::I64LtU => sequence!(ins_numeric_comparsion::i64_lt_u),
+            Instruction::I64GtS => sequence!(ins_numeric_comparsion::i64_gt_s),
+            Instruction::I64GtU => sequence!(ins_numeric_comparsion::i64_gt_u),
+            Instruction::I64LeS => sequence!(ins_numeric_comparsion::i64_le_s),
+            Instruction::I64LeU => sequence!(ins_numeric_comparsion::i64_le_u),
+            Instruction::I64GeS => sequence!(ins_numeric_comparsion::i64_ge_s),
+            Instruction::I64GeU => sequence!(ins_numeric_comparsion::i64_ge_u),
+
+            Instruction::F32Eq => sequence!(ins_numeric_comparsion::f32_eq),
+            Instruction::F32Ne => sequence!(ins_numeric_comparsion::f32_ne),
+            Instruction::F32Lt => sequence!(ins_numeric_comparsion::f32_lt),
+            Instruction::F32Gt => sequence!(ins_numeric_comparsion::f32_gt),
+            Instruction::F32Le => sequence!(ins_numeric_comparsion::f32_le),
+            Instruction::F32Ge => sequence!(ins_numeric_comparsion::f32_ge),
+
+            Instruction::F64Eq => sequence!(ins_numeric_comparsion::f64_eq),
+            Instruction::F64Ne => sequence!(ins_numeric_comparsion::f64_ne),
+            Instruction::F64Lt => sequence!(ins_numeric_comparsion::f64_lt),
+            Instruction::F64Gt => sequence!(ins_numeric_comparsion::f64_gt),
+            Instruction::F64Le => sequence!(ins_numeric_comparsion::f64_le),
+            Instruction::F64Ge => sequence!(ins_numeric_comparsion::f64_ge),
+
+            // 一元运算
+            Instruction::I32Clz => sequence!(ins_numeric_unary::i32_clz),
+            Instruction::I32Ctz => sequence!(ins_numeric_unary::i32_ctz),
+            Instruction::I32PopCnt => sequence!(ins_numeric_unary::i32_popcnt),
+
+            Instruction::I64Clz => sequence!(ins_numeric_unary::i64_clz),
+            Instruction::I64Ctz => sequence!(ins_numeric_unary::i64_ctz),
+            Instruction::I64PopCnt => sequence!(ins_numeric_unary::i64_popcnt),
+
+            Instruction::F32Abs => sequence!(ins_numeric_unary::f32_abs),
+            Instruction::F32Neg => sequence!(ins_numeric_unary::f32_neg),
+            Instruction::F32Ceil => sequence!(ins_numeric_unary::f32_ceil),
+            Instruction::F32Floor => sequence!(ins_numeric_unary::f32_floor),
+            Instruction::F32Trunc => sequence!(ins_numeric_unary::f32_trunc),
+            Instruction::F32Nearest => sequence!(ins_numeric_unary::f32_nearest),
+            Instruction::F32Sqrt => sequence!(ins_numeric_unary::f32_sqrt),
+
+            Instruction::F64Abs => sequence!(ins_numeric_unary::f64_abs),
+            Instruction::F64Neg => sequence!(ins_numeric_unary::f64_neg),
+            Instruction::F64Ceil => sequence!(ins_numeric_unary::f64_ceil),
+            Instruction::F64Floor => sequence!(ins_numeric_unary::f64_floor),
+            Instruction::F64Trunc => sequence!(ins_numeric_unary::f64_trunc),
+            Instruction::F64Nearest => sequence!(ins_numeric_unary::f64_nearest),
+            Instruction::F64Sqrt => sequence!(ins_numeric_unary::f64_sqrt),
+
+            // 二元运算
+            Instruction::I32Add => sequence!(ins_numeric_binary::i32_add),
+            Instruction::I32Sub => sequence!(ins_numeric_binary::i32_sub),
+            Instruction::I32Mul => sequence!(ins_numeric_binary::i32_mul),
+            Instruction::I32DivS => sequence!(ins_numeric_binary::i32_div_s),
+            Instruction::I32DivU => sequence!(ins_numeric_binary::i32_div_u),
+            Instruction::I32RemS => sequence!(ins_numeric_binary::i32_rem_s),
+            Instruction::I32RemU => sequence!(ins_numeric_binary::i32_rem_u),
+            Instruction::I32And => sequence!(ins_numeric_binary::i32_and),
+            Instruction::I32Or => sequence!(ins_numeric_binary::i32_or),
+            Instruction::I32Xor => sequence!(ins_numeric_binary::i32_xor),
+            Instruction::I32Shl => sequence!(ins_numeric_binary::i32_shl),
+            Instruction::I32ShrS => sequence!(ins_numeric_binary::i32_shr_s),
+            Instruction::I32ShrU => sequence!(ins_numeric_binary::i32_shr_u),
+            Instruction::I32Rotl => sequence!(ins_numeric_binary::i32_rotl),
+            Instruction::I32Rotr => sequence!(ins_numeric_binary::i32_rotr),
+
+            Instruction::I64Add => sequence!(ins_numeric_binary::i64_add),
+            Instruction::I64Sub => sequence!(ins_numeric_binary::i64_sub),
+            Instruction::I64Mul => sequence!(ins_numeric_binary::i64_mul),
+            Instruction::I64DivS => sequence!(ins_numeric_binary::i64_div_s),
+            Instruction::I64DivU => sequence!(ins_numeric_binary::i64_div_u),
+            Instruction::I64RemS => sequence!(ins_numeric_binary::i64_rem_s),
+            Instruction::I64RemU => sequence!(ins_numeric_binary::i64_rem_u),
+            Instruction::I64And => sequence!(ins_numeric_binary::i64_and),
+            Instruction::I64Or => sequence!(ins_numeric_binary::i64_or),
+            Instruction::I64Xor => sequence!(ins_numeric_binary::i64_xor),
+            Instruction::I64Shl => sequence!(ins_numeric_binary::i64_shl),
+            Instruction::I64ShrS => sequence!(ins_numeric_binary::i64_shr_s),
+            Instruction::I64ShrU => sequence!(ins_numeric_binary::i64_shr_u),
+            Instruction::I64Rotl => sequence!(ins_numeric_binary::i64_rotl),
+            Instruction::I64Rotr => sequence!(ins_numeric_binary::i64_rotr),
+
+            Instruction::F32Add => sequence!(ins_numeric_binary::f32_add),
+            Instruction::F32Sub => sequence!(ins_numeric_binary::f32_sub),
+            Instruction::F32Mul => sequence!(ins_numeric_binary::f32_mul),
+            Instruction::F32Div => sequence!(ins_numeric_binary::f32_div),
+            Instruction::F32Min => sequence!(ins_numeric_binary::f32_min),
+            Instruction::F32Max => sequence!(ins_numeric_binary::f32_max),
+            Instruction::F32CopySign => sequence!(ins_numeric_binary::f32_copysign),
+
+            Instruction::F64Add => sequence!(ins_numeric_binary::f64_add),
+            Instruction::F64Sub => sequence!(ins_numeric_binary::f64_sub),
+            Instruction::F64Mul => sequence!(ins_numeric_binary::f64_mul),
+            Instruction::F64Div => sequence!(ins_numeric_binary::f64_div),
+            Instruction::F64Min => sequence!(ins_numeric_binary::f64_min),
+            Instruction::F64Max => sequence!(ins_numeric_binary::f64_max),
+            Instruction::F64CopySign => sequence!(ins_numeric_binary::f64_copysign),
+
+            // 类型转换指令
+            Instruction::I32WrapI64 => sequence!(ins_numeric_convert::i32_wrap_i64),
+
+            Instruction::I32Extend8S => sequence!(ins_numeric_convert::i32_extend8_s),
+            Instruction::I32Extend16S => sequence!(ins_numeric_convert::i32_extend16_s),
+            Instruction::I64ExtendI32S => sequence!(ins_numeric_convert::i64_extend_i32_s),
+            Instruction::I64ExtendI32U => sequence!(ins_numeric_convert::i64_extend_i32_u),
+            Instruction::I64Extend8S => sequence!(ins_numeric_convert::i64_extend8_s),
+            Instruction::I64Extend16S => sequence!(ins_numeric_convert::i64_extend16_s),
+            Instruction::I64Extend32S => sequence!(ins_numeric_convert::i64_extend32_s),
+
+            Instruction::I32TruncF32S => sequence!(ins_numeric_convert::i32_trunc_f32_s),
+            Instruction::I32TruncF32U => sequence!(ins_numeric_convert::i32_trunc_f32_u),
+            Instruction::I64TruncF32S => sequence!(ins_numeric_convert::i64_trunc_f32_s),
+            Instruction::I64TruncF32U => sequence!(ins_numeric_convert::i64_trunc_f32_u),
+            Instruction::I32TruncF64S => sequence!(ins_numeric_convert::i32_trunc_f64_s),
+            Instruction::I32TruncF64U => sequence!(ins_numeric_convert::i32_trunc_f64_u),
+            Instruction::I64TruncF64S => sequence!(ins_numeric_convert::i64_trunc_f64_s),
+            Instruction::I64TruncF64U => sequence!(ins_numeric_convert::i64_trunc_f64_u),
+
+            Instruction::I32TruncSatF32S => sequence!(ins_numeric_convert::i32_trunc_sat_f32_s),
+            Instruction::I32TruncSatF32U => sequence!(ins_numeric_convert::i32_trunc_sat_f32_u),
+            Instruction::I32TruncSatF64S => sequence!(ins_numeric_convert::i32_trunc_sat_f64_s),
+            Instruction::I32TruncSatF64U => sequence!(ins_numeric_convert::i32_trunc_sat_f64_u),
+
+            Instruction::I64TruncSatF32S => sequence!(ins_numeric_convert::i64_trunc_sat_f32_s),
+            Instruction::I64TruncSatF32U => sequence!(ins_numeric_convert::i64_trunc_sat_f32_u),
+            Instruction::I64TruncSatF64S => sequence!(ins_numeric_convert::i64_trunc_sat_f64_s),
+            Instruction::I64TruncSatF64U => sequence!(ins_numeric_convert::i64_trunc_sat_f64_u),
+
+            Instruction::F32ConvertI32S => sequence!(ins_numeric_convert::f32_convert_i32_s),
+            Instruction::F32ConvertI32U => sequence!(ins_numeric_convert::f32_convert_i32_u),
+            Instruction::F64ConvertI32S => sequence!(ins_numeric_convert::f64_convert_i32_s),
+            Instruction::F64ConvertI32U => sequence!(ins_numeric_convert::f64_convert_i32_u),
+            Instruction::F32ConvertI64S => sequence!(ins_numeric_convert::f32_convert_i64_s),
+            Instruction::F32ConvertI64U => sequence!(ins_numeric_convert::f32_convert_i64_u),
+            Instruction::F64ConvertI64S => sequence!(ins_numeric_convert::f64_convert_i64_s),
+            Instruction::F64ConvertI64U => sequence!(ins_numeric_convert::f64_convert_i64_u),
+
+            Instruction::F32DemoteF64 => sequence!(ins_numeric_convert::f32_demote_f64_s),
+            Instruction::F64PromoteF32 => sequence!(ins_numeric_convert::f64_promote_f32),
+
+            Instruction::I32ReinterpretF32 => sequence!(ins_numeric_convert::i32_reinterpret_f32),
+            Instruction::I64ReinterpretF64 => sequence!(ins_numeric_convert::i64_reinterpret_f64),
+            Instruction::F32ReinterpretI32 => sequence!(ins_numeric_convert::f32_reinterpret_i32),
+            Instruction::F64ReinterpretI64 => sequence!(ins_numeric_convert::f64_reinterpret_i64),
+
+            // 变量指令
+            Instruction::LocalGet(index) => sequence!(ins_variable::local_get, Index(*index)),
+            Instruction::LocalSet(index) => sequence!(ins_variable::local_set, Index(*index)),
+            Instruction::LocalTee(index) => sequence!(ins_variable::local_tee, Index(*index)),
+            Instruction::GlobalGet(index) => sequence!(ins_variable::global_get, Index(*index)),
+            Instruction::GlobalSet(index) => sequence!(ins_variable::global_set, Index(*index)),
+
+            // 内存指令
+            Instruction::MemorySize(memory_block_index) => {
+                sequence!(ins_memory::memory_size, Index(*memory_block_index))
+            }
+            Instruction::MemoryGrow(memory_block_index) => {
+                sequence!(ins_memory::memory_grow, Index(*memory_block_index))
+            }
+
+            Instruction::MemoryInit(data_index, memory_block_index) => sequence!(
+                ins_memory::memory_init,
+                IndexPair(*data_index, *memory_block_index)
+            ),
+            Instruction::DataDrop(data_index) => {
+                sequence!(ins_memory::data_drop, Index(*data_index))
+            }
+            Instruction::MemoryCopy(source_memory_block_index, dest_memory_block_index) => {
+                sequence!(
+                    ins_memory::memory_copy,
+                    IndexPair(*source_memory_block_index, *dest_memory_block_index)
+                )
+            }
+            Instruction::MemoryFill(memory_block_index) => {
+                sequence!(ins_memory::memory_fill, Index(*memory_block_index))
+            }
+
+            Instruction::I32Load(memory_args) => {
+                sequence!(ins_memory::i32_load, Memory(*memory_args))
+            }
+            Instruction::I32Load16S(memory_args) => {
+                sequence!(ins_memory::i32_load16_s, Memory(*memory_args))
+            }
+            Instruction::I32Load16U(memory_args) => {
+                sequence!(ins_memory::i32_load16_u, Memory(*memory_args))
+            }
+            Instruction::I32Load8S(memory_args) => {
+                sequence!(ins_memory::i32_load8_s, Memory(*memory_args))
+            }
+            Instruction::I32Load8U(memory_args) => {
+                sequence!(ins_memory::i32_load8_u, Memory(*memory_args))
+            }
+
+            Instruction::I64Load(memory_args) => {
+                sequence!(ins_memory::i64_load, Memory(*memory_args))
+            }
+            Instruction::I64Load32S(memory_args) => {
+                sequence!(ins_memory::i64_load32_s, Memory(*memory_args))
+            }
+            Instruction::I64Load32U(memory_args) => {
+                sequence!(ins_memory::i64_load32_u, Memory(*memory_args))
+            }
+            Instruction::I64Load16S(memory_args) => {
+                sequence!(ins_memory::i64_load16_s, Memory(*memory_args))
+            }
+            Instruction::I64Load16U(memory_args) => {
+                sequence!(ins_memory::i64_load16_u, Memory(*memory_args))
+            }
+            Instruction::I64Load8S(memory_args) => {
+                sequence!(ins_memory::i64_load8_s, Memory(*memory_args))
+            }
+            Instruction::I64Load8U(memory_args) => {
+                sequence!(ins_memory::i64_load8_u, Memory(*memory_args))
+            }
+
+            Instruction::F32Load(memory_args) => {
+                sequence!(ins_memory::f32_load, Memory(*memory_args))
+            }
+            Instruction::F64Load(memory_args) => {
+                sequence!(ins_memory::f64_load, Memory(*memory_args))
+            }
+
+            Instruction::I32Store(memory_args) => {
+                sequence!(ins_memory::i32_store, Memory(*memory_args))
+            }
+            Instruction::I32Store16(memory_args) => {
+                sequence!(ins_memory::i32_store_16, Memory(*memory_args))
+            }
+            Instruction::I32Store8(memory_args) => {
+                sequence!(ins_memory::i32_store_8, Memory(*memory_args))
+            }
+            Instruction::I64Store(memory_args) => {
+                sequence!(ins_memory::i64_store, Memory(*memory_args))
+            }
+            Instruction::I64Store32(memory_args) => {
+                sequence!(ins_memory::i64_store_32, Memory(*memory_args))
+            }
+            Instruction::I64Store16(memory_args) => {
+                sequence!(ins_memory::i64_store_16, Memory(*memory_args))
+            }
+            Instruction::I64Store8(memory_args) => {
+                sequence!(ins_memory::i64_store_8, Memory(*memory_args))
+            }
+
+            Instruction::F32Store(memory_args) => {
+                sequence!(ins_memory::f32_store, Memory(*memory_args))
+            }
+            Instruction::F64Store(memory_args) => {
+                sequence!(ins_memory::f64_store, Memory(*memory_args))
+            }
+
+            // 表指令
+            Instruction::TableGet(table_index) => {
+                sequence!(ins_table::table_get, Index(*table_index))
+            }
+            Instruction::TableSet(table_index) => {
+                sequence!(ins_table::table_set, Index(*table_index))
+            }
+            Instruction::TableInit(element_index, table_index) => sequence!(
+                ins_table::table_init,
+                IndexPair(*element_index, *table_index)
+            ),
+            Instruction::ElementDrop(element_index) => {
+                sequence!(ins_table::element_drop, Index(*element_index))
+            }
+            Instruction::TableCopy(source_table_index, dest_table_index) => sequence!(
+                ins_table::table_copy,
+                IndexPair(*source_table_index, *dest_table_index)
+            ),
+            Instruction::TableGrow(table_index) => {
+                sequence!(ins_table::table_grow, Index(*table_index))
+            }
+            Instruction::TableSize(table_index) => {
+                sequence!(ins_table::table_size, Index(*table_index))
+            }
+            Instruction::TableFill(table_index) => {
+                sequence!(ins_table::table_fill, Index(*table_index))
+            }
+
+            // 引用指令
+            // 空引用在槽里的表示跟引用类型无关
+            Instruction::RefNull(_) => sequence!(ins_reference::ref_null),
+            Instruction::RefIsNull => sequence!(ins_reference::ref_is_null),
+            Instruction::RefFunc(function_index) => {
+                sequence!(ins_reference::ref_func, Index(*function_index))
+            }
+
+            // 其他指令已经被替换成 Instruction::Control，所以
+            // 程序不应该来到这个分支
+            _ => {
+                unreachable!("should no this instruction")
+            }
+        },
         object::Instruction::Control(control) => {
-            let control_result = match control {
+            let (handler, operand): (Handler, Operand) = match control {
                 // 控制指令
-                Control::Unreachable => ins_control::process_unreachable(vm),
-                Control::Nop => ins_control::process_nop(vm),
-                Control::End(block_index) => ins_control::process_end(vm, block_index),
+                Control::Unreachable => (unreachable, Operand::None),
+                Control::Nop => (nop, Operand::None),
+                Control::End(option_block_index) => (end, Operand::End(*option_block_index)),
 
                 // 函数调用指令
                 Control::Call {
@@ -285,147 +509,613 @@ pub fn exec_instruction(
                     function_index,
                     internal_function_index,
                     address,
-                } => ins_function::call(
-                    vm,
-                    *vm_module_index,
-                    *type_index,
-                    *function_index,
-                    *internal_function_index,
-                    *address,
+                } => (
+                    call,
+                    Operand::Call {
+                        vm_module_index: *vm_module_index,
+                        type_index: *type_index,
+                        function_index: *function_index,
+                        internal_function_index: *internal_function_index,
+                        address: *address,
+                    },
                 ),
                 Control::CallNative {
                     native_module_index,
                     type_index,
                     function_index,
-                } => ins_function::call_native(
-                    vm,
-                    *native_module_index,
-                    *type_index,
-                    *function_index,
+                } => (
+                    call_native,
+                    Operand::CallNative {
+                        native_module_index: *native_module_index,
+                        type_index: *type_index,
+                        function_index: *function_index,
+                    },
                 ),
                 Control::CallIndirect {
                     type_index,
                     table_index,
-                } => ins_function::call_indirect(vm, *type_index, *table_index),
+                } => (
+                    call_indirect,
+                    Operand::CallIndirect {
+                        type_index: *type_index,
+                        table_index: *table_index,
+                    },
+                ),
 
                 // 流程结构控制指令
                 Control::Block {
                     block_type,
                     block_index,
                     end_address,
-                } => ins_block::block(vm, block_type, *block_index, *end_address),
+                } => (
+                    block,
+                    Operand::Block {
+                        block_type: *block_type,
+                        block_index: *block_index,
+                        end_address: *end_address,
+                    },
+                ),
                 Control::BlockAndJumpWhenEqZero {
                     block_type,
                     block_index,
                     option_alternate_address,
                     end_address,
-                } => ins_block::block_and_jump_when_eq_zero(
-                    vm,
-                    block_type,
-                    *block_index,
-                    *option_alternate_address,
-                    *end_address,
+                } => (
+                    block_and_jump_when_eq_zero,
+                    Operand::BlockAndJumpWhenEqZero {
+                        block_type: *block_type,
+                        block_index: *block_index,
+                        option_alternate_address: *option_alternate_address,
+                        end_address: *end_address,
+                    },
                 ),
-                Control::JumpWithinBlock(address) => ins_block::jump_within_block(vm, *address),
-
+                Control::JumpWithinBlock(address) => {
+                    (jump_within_block, Operand::Address(*address))
+                }
                 Control::Break {
                     option_block_index,
                     relative_depth,
                     address,
-                } => ins_block::process_break(vm, *option_block_index, *relative_depth, *address),
+                } => (
+                    process_break,
+                    Operand::Break {
+                        option_block_index: *option_block_index,
+                        relative_depth: *relative_depth,
+                        address: *address,
+                    },
+                ),
                 Control::BreakWhenNotEqZero {
                     option_block_index,
                     relative_depth,
                     address,
-                } => ins_block::process_break_when_not_eq_zero(
-                    vm,
-                    *option_block_index,
-                    *relative_depth,
-                    *address,
+                } => (
+                    process_break_when_not_eq_zero,
+                    Operand::Break {
+                        option_block_index: *option_block_index,
+                        relative_depth: *relative_depth,
+                        address: *address,
+                    },
                 ),
                 Control::Recur {
                     block_index,
                     relative_depth,
                     address,
-                } => ins_block::recur(vm, *block_index, *relative_depth, *address),
+                } => (
+                    recur,
+                    Operand::Recur {
+                        block_index: *block_index,
+                        relative_depth: *relative_depth,
+                        address: *address,
+                    },
+                ),
                 Control::RecurWhenNotEqZero {
                     block_index,
                     relative_depth,
                     address,
-                } => ins_block::recur_when_not_eq_zero(vm, *block_index, *relative_depth, *address),
+                } => (
+                    recur_when_not_eq_zero,
+                    Operand::Recur {
+                        block_index: *block_index,
+                        relative_depth: *relative_depth,
+                        address: *address,
+                    },
+                ),
                 Control::Branch {
+                    option_block_index, ..
+                } => (branch, Operand::Branch(*option_block_index)),
+            };
+
+            CompiledInstruction { handler, operand }
+        }
+        object::Instruction::Fused(fused) => {
+            let (handler, operand): (Handler, Operand) = match fused {
+                Fused::LocalGetLocalGetI32Add(first_index, second_index) => (
+                    local_get_local_get_i32_add,
+                    Operand::IndexPair(*first_index, *second_index),
+                ),
+                Fused::LocalGetI32ConstI32Add(index, value) => (
+                    local_get_i32_const_i32_add,
+                    Operand::LocalI32(*index, *value),
+                ),
+                Fused::LocalGetI32ConstI32AddLocalSet(source_index, value, dest_index) => (
+                    local_get_i32_const_i32_add_local_set,
+                    Operand::LocalI32Local(*source_index, *value, *dest_index),
+                ),
+                Fused::I32CompareBreakWhenNotEqZero {
+                    comparison,
                     option_block_index,
-                    branch_targets,
-                    default_branch_target,
-                } => ins_block::branch(
-                    vm,
-                    *option_block_index,
-                    branch_targets,
-                    default_branch_target,
+                    relative_depth,
+                    address,
+                } => (
+                    get_i32_compare_and_branch_handlers(comparison).0,
+                    Operand::Break {
+                        option_block_index: *option_block_index,
+                        relative_depth: *relative_depth,
+                        address: *address,
+                    },
+                ),
+                Fused::I32CompareRecurWhenNotEqZero {
+                    comparison,
+                    block_index,
+                    relative_depth,
+                    address,
+                } => (
+                    get_i32_compare_and_branch_handlers(comparison).1,
+                    Operand::Recur {
+                        block_index: *block_index,
+                        relative_depth: *relative_depth,
+                        address: *address,
+                    },
                 ),
             };
 
-            match control_result {
-                Ok(ControlResult::ProgramEnd) => Ok(true),
-                Ok(ControlResult::Sequence) => {
-                    // 更新虚拟机的 pc 值
-                    let status = &mut vm.status;
-                    status.address += 1;
+            CompiledInstruction { handler, operand }
+        }
+    }
+}
 
-                    Ok(false)
-                }
-                Ok(ControlResult::PushStackFrame {
-                    is_call_frame: _,
-                    vm_module_index,
-                    function_index,
-                    frame_type,
-                    address,
-                }) => {
-                    // 更新虚拟机的 pc 值
-                    let status = &mut vm.status;
-                    status.vm_module_index = vm_module_index;
-                    status.function_index = function_index;
-                    status.frame_type = frame_type;
-                    status.address = address;
-
-                    Ok(false)
-                }
-                Ok(ControlResult::PopStackFrame {
-                    is_call_frame: _,
-                    vm_module_index,
-                    function_index,
-                    frame_type,
-                    address,
-                }) => {
-                    // 更新虚拟机的 pc 值
-                    let status = &mut vm.status;
-                    status.vm_module_index = vm_module_index;
-                    status.function_index = function_index;
-                    status.frame_type = frame_type;
-                    status.address = address;
-
-                    Ok(false)
-                }
-                Ok(ControlResult::JumpWithinFunction {
-                    frame_type,
-                    address,
-                }) => {
-                    // 更新虚拟机的 pc 值
-                    let status = &mut vm.status;
-                    status.frame_type = frame_type;
-                    status.address = address;
+fn unreachable(vm: &mut VM, _operand: &Operand) -> Result<bool, EngineError> {
+    let control_result = ins_control::process_unreachable(vm);
+    apply_control_result(vm, control_result)
+}
 
-                    Ok(false)
-                }
-                Ok(ControlResult::JumpWithinBlock(address)) => {
-                    // 更新虚拟机的 pc 值
-                    let status = &mut vm.status;
-                    status.address = address;
+fn nop(vm: &mut VM, _operand: &Operand) -> Result<bool, EngineError> {
+    let control_result = ins_control::process_nop(vm);
+    apply_control_result(vm, control_result)
+}
 
-                    Ok(false)
-                }
-                Err(e) => Err(e),
-            }
+fn end(vm: &mut VM, operand: &Operand) -> Result<bool, EngineError> {
+    let control_result = match operand {
+        Operand::End(option_block_index) => ins_control::process_end(vm, option_block_index),
+        _ => unreachable!(),
+    };
+    apply_control_result(vm, control_result)
+}
+
+fn call(vm: &mut VM, operand: &Operand) -> Result<bool, EngineError> {
+    let control_result = match *operand {
+        Operand::Call {
+            vm_module_index,
+            type_index,
+            function_index,
+            internal_function_index,
+            address,
+        } => ins_function::call(
+            vm,
+            vm_module_index,
+            type_index,
+            function_index,
+            internal_function_index,
+            address,
+        ),
+        _ => unreachable!(),
+    };
+    apply_control_result(vm, control_result)
+}
+
+fn call_native(vm: &mut VM, operand: &Operand) -> Result<bool, EngineError> {
+    let control_result = match *operand {
+        Operand::CallNative {
+            native_module_index,
+            type_index,
+            function_index,
+        } => ins_function::call_native(vm, native_module_index, type_index, function_index),
+        _ => unreachable!(),
+    };
+    apply_control_result(vm, control_result)
+}
+
+fn call_indirect(vm: &mut VM, operand: &Operand) -> Result<bool, EngineError> {
+    let control_result = match *operand {
+        Operand::CallIndirect {
+            type_index,
+            table_index,
+        } => ins_function::call_indirect(vm, type_index, table_index),
+        _ => unreachable!(),
+    };
+    apply_control_result(vm, control_result)
+}
+
+fn block(vm: &mut VM, operand: &Operand) -> Result<bool, EngineError> {
+    let control_result = match operand {
+        Operand::Block {
+            block_type,
+            block_index,
+            end_address,
+        } => ins_block::block(vm, block_type, *block_index, *end_address),
+        _ => unreachable!(),
+    };
+    apply_control_result(vm, control_result)
+}
+
+fn block_and_jump_when_eq_zero(vm: &mut VM, operand: &Operand) -> Result<bool, EngineError> {
+    let control_result = match operand {
+        Operand::BlockAndJumpWhenEqZero {
+            block_type,
+            block_index,
+            option_alternate_address,
+            end_address,
+        } => ins_block::block_and_jump_when_eq_zero(
+            vm,
+            block_type,
+            *block_index,
+            *option_alternate_address,
+            *end_address,
+        ),
+        _ => unreachable!(),
+    };
+    apply_control_result(vm, control_result)
+}
+
+fn jump_within_block(vm: &mut VM, operand: &Operand) -> Result<bool, EngineError> {
+    let control_result = match operand {
+        Operand::Address(address) => ins_block::jump_within_block(vm, *address),
+        _ => unreachable!(),
+    };
+    apply_control_result(vm, control_result)
+}
+
+fn process_break(vm: &mut VM, operand: &Operand) -> Result<bool, EngineError> {
+    let control_result = match *operand {
+        Operand::Break {
+            option_block_index,
+            relative_depth,
+            address,
+        } => ins_block::process_break(vm, option_block_index, relative_depth, address),
+        _ => unreachable!(),
+    };
+    apply_control_result(vm, control_result)
+}
+
+fn process_break_when_not_eq_zero(vm: &mut VM, operand: &Operand) -> Result<bool, EngineError> {
+    let control_result = match *operand {
+        Operand::Break {
+            option_block_index,
+            relative_depth,
+            address,
+        } => ins_block::process_break_when_not_eq_zero(
+            vm,
+            option_block_index,
+            relative_depth,
+            address,
+        ),
+        _ => unreachable!(),
+    };
+    apply_control_result(vm, control_result)
+}
+
+fn recur(vm: &mut VM, operand: &Operand) -> Result<bool, EngineError> {
+    let control_result = match *operand {
+        Operand::Recur {
+            block_index,
+            relative_depth,
+            address,
+        } => ins_block::recur(vm, block_index, relative_depth, address),
+        _ => unreachable!(),
+    };
+    apply_control_result(vm, control_result)
+}
+
+fn recur_when_not_eq_zero(vm: &mut VM, operand: &Operand) -> Result<bool, EngineError> {
+    let control_result = match *operand {
+        Operand::Recur {
+            block_index,
+            relative_depth,
+            address,
+        } => ins_block::recur_when_not_eq_zero(vm, block_index, relative_depth, address),
+        _ => unreachable!(),
+    };
+    apply_control_result(vm, control_result)
+}
+
+fn branch(vm: &mut VM, operand: &Operand) -> Result<bool, EngineError> {
+    let control_result = match operand {
+        Operand::Branch(option_block_index) => ins_block::branch(vm, *option_block_index),
+        _ => unreachable!(),
+    };
+    apply_control_result(vm, control_result)
+}
+
+// 超级指令
+//
+// 超级指令的处理函数直接读写局部变量的槽，执行完毕之后，pc 指向被合并的
+// 原指令序列之后的一条指令。
+
+fn local_get_local_get_i32_add(vm: &mut VM, operand: &Operand) -> Result<bool, EngineError> {
+    match *operand {
+        Operand::IndexPair(first_index, second_index) => {
+            let local_pointer = vm.status.local_pointer;
+            let stack = &mut vm.stack;
+            let left = stack.get_slot(local_pointer + first_index as usize) as i32;
+            let right = stack.get_slot(local_pointer + second_index as usize) as i32;
+            stack.push_i32(left.wrapping_add(right));
+        }
+        _ => unreachable!(),
+    }
+
+    vm.status.address += 3;
+    Ok(false)
+}
+
+fn local_get_i32_const_i32_add(vm: &mut VM, operand: &Operand) -> Result<bool, EngineError> {
+    match *operand {
+        Operand::LocalI32(index, value) => {
+            let local_pointer = vm.status.local_pointer;
+            let stack = &mut vm.stack;
+            let left = stack.get_slot(local_pointer + index as usize) as i32;
+            stack.push_i32(left.wrapping_add(value));
+        }
+        _ => unreachable!(),
+    }
+
+    vm.status.address += 3;
+    Ok(false)
+}
+
+fn local_get_i32_const_i32_add_local_set(
+    vm: &mut VM,
+    operand: &Operand,
+) -> Result<bool, EngineError> {
+    match *operand {
+        Operand::LocalI32Local(source_index, value, dest_index) => {
+            let local_pointer = vm.status.local_pointer;
+            let stack = &mut vm.stack;
+            let left = stack.get_slot(local_pointer + source_index as usize) as i32;
+            let result = left.wrapping_add(value);
+            stack.set_slot(local_pointer + dest_index as usize, result as u32 as u64);
+        }
+        _ => unreachable!(),
+    }
+
+    vm.status.address += 4;
+    Ok(false)
+}
+
+/// 生成 "i32 比较指令 + br_if" 超级指令的处理函数
+///
+/// 返回 (跳转到 block/if 结构块结尾的处理函数, 跳转到 loop 结构块开始的处理函数)
+macro_rules! i32_compare_and_branch {
+    ($value:ident => $testing:expr) => {{
+        fn break_handler(vm: &mut VM, operand: &Operand) -> Result<bool, EngineError> {
+            let $value = vm.stack.pop_i32()?;
+            break_when(vm, operand, $testing)
+        }
+        fn recur_handler(vm: &mut VM, operand: &Operand) -> Result<bool, EngineError> {
+            let $value = vm.stack.pop_i32()?;
+            recur_when(vm, operand, $testing)
+        }
+        (break_handler as Handler, recur_handler as Handler)
+    }};
+    ($left:ident, $right:ident => $testing:expr) => {{
+        fn break_handler(vm: &mut VM, operand: &Operand) -> Result<bool, EngineError> {
+            let stack = &mut vm.stack;
+            let ($right, $left) = (stack.pop_i32()?, stack.pop_i32()?);
+            break_when(vm, operand, $testing)
+        }
+        fn recur_handler(vm: &mut VM, operand: &Operand) -> Result<bool, EngineError> {
+            let stack = &mut vm.stack;
+            let ($right, $left) = (stack.pop_i32()?, stack.pop_i32()?);
+            recur_when(vm, operand, $testing)
+        }
+        (break_handler as Handler, recur_handler as Handler)
+    }};
+}
+
+fn get_i32_compare_and_branch_handlers(comparison: &I32Comparison) -> (Handler, Handler) {
+    match comparison {
+        I32Comparison::Eqz => i32_compare_and_branch!(value => value == 0),
+        I32Comparison::Eq => i32_compare_and_branch!(left, right => left == right),
+        I32Comparison::Ne => i32_compare_and_branch!(left, right => left != right),
+        I32Comparison::LtS => i32_compare_and_branch!(left, right => left < right),
+        I32Comparison::LtU => {
+            i32_compare_and_branch!(left, right => (left as u32) < (right as u32))
+        }
+        I32Comparison::GtS => i32_compare_and_branch!(left, right => left > right),
+        I32Comparison::GtU => {
+            i32_compare_and_branch!(left, right => (left as u32) > (right as u32))
+        }
+        I32Comparison::LeS => i32_compare_and_branch!(left, right => left <= right),
+        I32Comparison::LeU => {
+            i32_compare_and_branch!(left, right => (left as u32) <= (right as u32))
+        }
+        I32Comparison::GeS => i32_compare_and_branch!(left, right => left >= right),
+        I32Comparison::GeU => {
+            i32_compare_and_branch!(left, right => (left as u32) >= (right as u32))
+        }
+    }
+}
+
+fn break_when(vm: &mut VM, operand: &Operand, testing: bool) -> Result<bool, EngineError> {
+    // 让 pc 指向原 `br_if 指令`，跳转不成立时执行该指令的下一条指令，
+    // 出错时也能报告原 `br_if 指令` 的位置。
+    vm.status.address += 1;
+
+    let control_result = if testing {
+        match *operand {
+            Operand::Break {
+                option_block_index,
+                relative_depth,
+                address,
+            } => ins_block::process_break(vm, option_block_index, relative_depth, address),
+            _ => unreachable!(),
         }
+    } else {
+        Ok(ControlResult::Sequence)
+    };
+    apply_control_result(vm, control_result)
+}
+
+fn recur_when(vm: &mut VM, operand: &Operand, testing: bool) -> Result<bool, EngineError> {
+    // 让 pc 指向原 `br_if 指令`
+    vm.status.address += 1;
+
+    let control_result = if testing {
+        match *operand {
+            Operand::Recur {
+                block_index,
+                relative_depth,
+                address,
+            } => ins_block::recur(vm, block_index, relative_depth, address),
+            _ => unreachable!(),
+        }
+    } else {
+        Ok(ControlResult::Sequence)
+    };
+    apply_control_result(vm, control_result)
+}
+
+/// 根据控制指令的执行结果更新虚拟机的状态
+fn apply_control_result(
+    vm: &mut VM,
+    control_result: Result<ControlResult, EngineError>,
+) -> Result<bool, EngineError> {
+    match control_result {
+        Ok(ControlResult::ProgramEnd) => Ok(true),
+        Ok(ControlResult::Sequence) => {
+            // 更新虚拟机的 pc 值
+            let status = &mut vm.status;
+            status.address += 1;
+
+            Ok(false)
+        }
+        Ok(ControlResult::PushStackFrame {
+            is_call_frame: _,
+            vm_module_index,
+            function_index,
+            frame_type,
+            address,
+        }) => {
+            // 更新虚拟机的 pc 值
+            let status = &mut vm.status;
+            status.vm_module_index = vm_module_index;
+            status.function_index = function_index;
+            status.frame_type = frame_type;
+            status.address = address;
+
+            Ok(false)
+        }
+        Ok(ControlResult::PopStackFrame {
+            is_call_frame: _,
+            vm_module_index,
+            function_index,
+            frame_type,
+            address,
+        }) => {
+            // 更新虚拟机的 pc 值
+            let status = &mut vm.status;
+            status.vm_module_index = vm_module_index;
+            status.function_index = function_index;
+            status.frame_type = frame_type;
+            status.address = address;
+
+            Ok(false)
+        }
+        Ok(ControlResult::JumpWithinFunction {
+            frame_type,
+            address,
+        }) => {
+            // 更新虚拟机的 pc 值
+            let status = &mut vm.status;
+            status.frame_type = frame_type;
+            status.address = address;
+
+            Ok(false)
+        }
+        Ok(ControlResult::JumpWithinBlock(address)) => {
+            // 更新虚拟机的 pc 值
+            let status = &mut vm.status;
+            status.address = address;
+
+            Ok(false)
+        }
+        Err(e) => Err(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use anvm_ast::instruction::{self, BlockType, MemoryArgument};
+
+    use super::{compile, Operand};
+    use crate::object::{BranchTarget, Control, Instruction};
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_compile() {
+        let memory_args = MemoryArgument {
+            align: 2,
+            offset: 8,
+            memory_block_index: 0,
+        };
+
+        let instructions = vec![
+            Instruction::Control(Control::Block {
+                block_type: BlockType::ResultEmpty,
+                block_index: 1,
+                end_address: 6,
+            }),
+            Instruction::Sequence(instruction::Instruction::I32Const(11)),
+            Instruction::Sequence(instruction::Instruction::I32Load(memory_args)),
+            Instruction::Sequence(instruction::Instruction::LocalGet(0)),
+            Instruction::Sequence(instruction::Instruction::I32Add),
+            Instruction::Control(Control::Branch {
+                option_block_index: Some(1),
+                branch_targets: vec![BranchTarget::Break(0, 6), BranchTarget::Break(1, 7)],
+                default_branch_target: BranchTarget::Break(1, 7),
+            }),
+            Instruction::Control(Control::End(Some(1))),
+            Instruction::Control(Control::End(None)),
+        ];
+
+        let compiled_instructions = compile(&instructions);
+
+        // 编译后的指令跟原指令一一对应
+        assert_eq!(
+            compiled_instructions
+                .iter()
+                .map(|item| item.operand)
+                .collect::<Vec<Operand>>(),
+            vec![
+                Operand::Block {
+                    block_type: BlockType::ResultEmpty,
+                    block_index: 1,
+                    end_address: 6
+                },
+                Operand::I32(11),
+                Operand::Memory(memory_args),
+                Operand::Index(0),
+                Operand::None,
+                Operand::Branch(Some(1)),
+                Operand::End(Some(1)),
+                Operand::End(None),
+            ]
+        );
+
+        // 相同的指令使用相同的处理函数
+        assert_eq!(
+            compiled_instructions[6].handler as usize,
+            compiled_instructions[7].handler as usize
+        );
     }
 }
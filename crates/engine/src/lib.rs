@@ -11,10 +11,19 @@ pub mod native_module;
 pub mod vm_stack;
 pub mod vm_memory;
 pub mod vm_table;
+pub mod vm_segment;
 pub mod vm_global_variable;
 pub mod vm_module;
 pub mod interpreter;
+pub mod fuel;
+pub mod trap;
 pub mod vm;
+pub mod engine;
+pub mod typed_function;
+pub mod export;
+pub mod memory_view;
+pub mod snapshot;
+pub mod validator;
 
 mod linker;
 mod decoder;
@@ -28,6 +37,8 @@ mod ins_numeric_binary;
 mod ins_numeric_convert;
 mod ins_variable;
 mod ins_memory;
+mod ins_table;
+mod ins_reference;
 mod ins_control;
 mod ins_function;
 mod ins_block;
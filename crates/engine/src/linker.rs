@@ -6,7 +6,7 @@
 
 use crate::{
     decoder::decode_constant_expression,
-    error::{EngineError, ObjectNotFound, TypeMismatch, Unsupported},
+    error::{EngineError, InvalidOperation, ObjectNotFound, TypeMismatch},
     native_module::NativeModule,
     object::{BlockItem, FunctionItem, NamedAstModule},
     vm::VM,
@@ -15,8 +15,9 @@ use crate::{
     vm_table::VMTable,
 };
 use anvm_ast::{
-    ast::{self, ExportDescriptor, GlobalType, ImportDescriptor, TypeItem},
+    ast::{self, ExportDescriptor, GlobalType, ImportDescriptor, MemoryType, TableType, TypeItem},
     instruction,
+    types::Value,
 };
 
 /// AST 模块的函数的指令序列位置信息
@@ -511,94 +512,97 @@ fn get_ast_module_function_index_by_export_name(
 
 /// 解决模块间的表链接，并创建相应的表对象。
 ///
-/// 注，对于没有指定表信息的模块，将会创建一个
-/// 最小值为 0 的表对象
-///
 /// 返回值当中
 /// - Vec<VMTable> 是虚拟机当中所有实例表的列表
-/// - Vec<usize> 是每个 AST Module 对应的实例表的索引列表，
-///   注：目前 WebAssembly 限制一个 Module 只能有一张表；
+/// - Vec<Vec<usize>> 是每个 AST Module 对应的实例表的索引列表
+///   注：一个 Module 可以有多张表（包括导入的表和模块内定义的表），
 ///   存在多个 Module 对应同一张表的情况。
 pub fn link_tables(
     named_ast_modules: &[NamedAstModule],
-) -> Result<(Vec<VMTable>, Vec<usize>), EngineError> {
-    // "AST 模块 - 表格实例的索引" 的临时映射表，
-    // 将元素的初始值设置为 None，以表示该项尚未设置。
-    let mut module_to_table_index_list: Vec<Option<usize>> = vec![None; named_ast_modules.len()];
+) -> Result<(Vec<VMTable>, Vec<Vec<usize>>), EngineError> {
+    // "AST 模块 - 表格实例的索引" 的临时映射表
+    let mut module_to_tables_list: Vec<Vec<Option<usize>>> = vec![];
 
     // 所有实例表
     let mut instance_tables: Vec<VMTable> = vec![];
 
-    // 先创建非导入的表
-    for (ast_module_index, ast_module) in named_ast_modules
-        .iter()
-        .map(|item| &item.module)
-        .enumerate()
-    {
-        // 先检查是否存在导入表
-        let option_import_table_item = ast_module
+    for ast_module in named_ast_modules.iter().map(|item| &item.module) {
+        let mut module_table_map_item: Vec<Option<usize>> = vec![];
+
+        // 先以 None 为值，填充模块的导入表
+        let import_table_count = ast_module
             .import_items
             .iter()
-            .find(|item| matches!(item.import_descriptor, ImportDescriptor::TableType(_)));
-
-        if option_import_table_item == None {
-            // 无导入表，创建新表
+            .filter(|item| matches!(item.import_descriptor, ImportDescriptor::TableType(_)))
+            .count();
 
-            let instance_table = if let Some(first) = ast_module.tables.first() {
-                // 根据定义创建新表
-                VMTable::new(first.clone())
-            } else {
-                // 创建默认表（容量最小值为 0，最大值也是 0，相当于无表）
-                VMTable::new_by_page_range(0, 0)
-            };
+        for _ in 0..import_table_count {
+            module_table_map_item.push(None);
+        }
 
+        // 再根据定义创建模块内的表
+        for table_type in &ast_module.tables {
             let instance_table_index = instance_tables.len();
-            instance_tables.push(instance_table);
+            instance_tables.push(VMTable::new(table_type.clone()));
 
-            module_to_table_index_list[ast_module_index] = Some(instance_table_index);
+            module_table_map_item.push(Some(instance_table_index));
         }
+
+        module_to_tables_list.push(module_table_map_item);
     }
 
     // 解决导入表格
     for ast_module_index in 0..named_ast_modules.len() {
-        if module_to_table_index_list[ast_module_index] == None {
-            resolve_ast_module_table(
-                named_ast_modules,
-                &instance_tables,
-                &mut module_to_table_index_list,
-                ast_module_index,
-            )?;
+        for module_table_index in 0..module_to_tables_list[ast_module_index].len() {
+            if module_to_tables_list[ast_module_index][module_table_index].is_none() {
+                resolve_ast_module_table(
+                    named_ast_modules,
+                    &instance_tables,
+                    &mut module_to_tables_list,
+                    ast_module_index,
+                    module_table_index,
+                )?;
+            }
         }
     }
 
     // 转换临时映射表
-    let list = module_to_table_index_list
+    let list = module_to_tables_list
         .iter()
-        .map(|item| item.unwrap())
-        .collect::<Vec<usize>>();
+        .map(|item| {
+            item.iter()
+                .map(|sub_item| sub_item.unwrap())
+                .collect::<Vec<usize>>()
+        })
+        .collect::<Vec<Vec<usize>>>();
 
     Ok((instance_tables, list))
 }
 
+/// 解决指定模块的一个导入表
+///
+/// 返回目标表实例的索引
 fn resolve_ast_module_table(
     named_ast_modules: &[NamedAstModule],
     instance_tables: &Vec<VMTable>,
-    module_table_map: &mut Vec<Option<usize>>,
+    module_table_map: &mut Vec<Vec<Option<usize>>>,
     ast_module_index: usize,
+    module_table_index: usize,
 ) -> Result<usize, EngineError> {
     let ast_module = &named_ast_modules[ast_module_index].module;
 
+    // 导入的表的索引排在模块内定义的表之前，所以 module_table_index 即是导入表的序号
     let (target_module_name, target_export_item_name, target_table_type) = ast_module
         .import_items
         .iter()
-        .find_map(|item| {
+        .filter_map(|item| {
             if let ImportDescriptor::TableType(table_type) = &item.import_descriptor {
                 Some((&item.module_name, &item.item_name, table_type))
             } else {
                 None
             }
         })
-        .expect("unreachable"); // 仅当 AST Module 声明了一个导入表格才会来到这里，所以不存在找不到导入项的情况
+        .collect::<Vec<(&String, &String, &TableType)>>()[module_table_index];
 
     let (target_ast_module_index, target_ast_module) = named_ast_modules
         .iter()
@@ -609,12 +613,12 @@ fn resolve_ast_module_table(
             target_module_name.to_owned(),
         )))?;
 
-    let target_table_index = target_ast_module
+    let target_module_table_index = target_ast_module
         .export_items
         .iter()
         .find_map(|item| match item.export_descriptor {
             ExportDescriptor::TableIndex(table_index) if &item.name == target_export_item_name => {
-                Some(table_index)
+                Some(table_index as usize)
             }
             _ => None,
         })
@@ -623,13 +627,8 @@ fn resolve_ast_module_table(
             target_export_item_name.to_owned(),
         )))?;
 
-    if target_table_index != 0 {
-        return Err(EngineError::Unsupported(
-            Unsupported::UnsupportedMultipleTable,
-        ));
-    }
-
-    let option_target_instance_table_index = module_table_map[target_ast_module_index];
+    let option_target_instance_table_index =
+        module_table_map[target_ast_module_index][target_module_table_index];
 
     let target_instance_table_index = if let Some(index) = option_target_instance_table_index {
         index
@@ -641,6 +640,7 @@ fn resolve_ast_module_table(
             instance_tables,
             module_table_map,
             target_ast_module_index,
+            target_module_table_index,
         )?
     };
 
@@ -657,102 +657,105 @@ fn resolve_ast_module_table(
     }
 
     // 更新映射表
-    module_table_map[ast_module_index] = Some(target_instance_table_index);
+    module_table_map[ast_module_index][module_table_index] = Some(target_instance_table_index);
 
-    Ok(target_ast_module_index)
+    Ok(target_instance_table_index)
 }
 
 /// 解决模块间的内存块链接，并创建相应的内存块对象。
 ///
-/// 注，对于没有指定内存信息的模块，将会创建一个
-/// 最小值为 0 的内存块对象
-///
 /// 返回值当中
 /// - Vec<VMMemory> 是虚拟机当中所有内存块实例的列表
-/// - Vec<usize> 是每个 AST Module 对应的内存块实例的索引列表，
-///   注：目前 WebAssembly 限制一个 Module 只能有一个内存块；
+/// - Vec<Vec<usize>> 是每个 AST Module 对应的内存块实例的索引列表
+///   注：一个 Module 可以有多个内存块（multi-memory），
 ///   存在多个 Module 对应同一个内存块的情况。
 pub fn link_memorys(
     named_ast_modules: &[NamedAstModule],
-) -> Result<(Vec<VMMemory>, Vec<usize>), EngineError> {
-    // "AST 模块 - 内存块实例的索引" 的临时映射表，
-    // 将元素的初始值设置为 None，以表示该项尚未设置。
-    let mut module_to_memory_block_index_list: Vec<Option<usize>> =
-        vec![None; named_ast_modules.len()];
+) -> Result<(Vec<VMMemory>, Vec<Vec<usize>>), EngineError> {
+    // "AST 模块 - 内存块实例的索引" 的临时映射表
+    let mut module_to_memory_blocks_list: Vec<Vec<Option<usize>>> = vec![];
 
-    // 所有实例表
+    // 所有内存块实例
     let mut instance_memory_blocks: Vec<VMMemory> = vec![];
 
-    // 先创建非导入的内存块实例
-    for (ast_module_index, ast_module) in named_ast_modules
-        .iter()
-        .map(|item| &item.module)
-        .enumerate()
-    {
-        // 先检查是否存在导入内存块
-        let option_import_memory_item = ast_module
+    for ast_module in named_ast_modules.iter().map(|item| &item.module) {
+        let mut module_memory_block_map_item: Vec<Option<usize>> = vec![];
+
+        // 先以 None 为值，填充模块的导入内存块
+        let import_memory_block_count = ast_module
             .import_items
             .iter()
-            .find(|item| matches!(item.import_descriptor, ImportDescriptor::MemoryType(_)));
-
-        if option_import_memory_item == None {
-            // 无导入内存块，创建新内存块
+            .filter(|item| matches!(item.import_descriptor, ImportDescriptor::MemoryType(_)))
+            .count();
 
-            let instance_memory = if let Some(first) = ast_module.memory_blocks.first() {
-                // 根据定义创建新内存块
-                VMMemory::new(first.clone())
-            } else {
-                // 创建默认内存块（容量最小值为 0，最大值也是 0，相当于无内存块定义）
-                VMMemory::new_by_page_range(0, 0)
-            };
+        for _ in 0..import_memory_block_count {
+            module_memory_block_map_item.push(None);
+        }
 
+        // 再根据定义创建模块内的内存块
+        for memory_type in &ast_module.memory_blocks {
             let instance_memory_block_index = instance_memory_blocks.len();
-            instance_memory_blocks.push(instance_memory);
+            instance_memory_blocks.push(VMMemory::new(memory_type.clone()));
 
-            module_to_memory_block_index_list[ast_module_index] = Some(instance_memory_block_index);
+            module_memory_block_map_item.push(Some(instance_memory_block_index));
         }
+
+        module_to_memory_blocks_list.push(module_memory_block_map_item);
     }
 
     // 解决导入内存块
     for ast_module_index in 0..named_ast_modules.len() {
-        if module_to_memory_block_index_list[ast_module_index] == None {
-            resolve_ast_module_memory_block(
-                named_ast_modules,
-                &instance_memory_blocks,
-                &mut module_to_memory_block_index_list,
-                ast_module_index,
-            )?;
+        for module_memory_block_index in 0..module_to_memory_blocks_list[ast_module_index].len() {
+            if module_to_memory_blocks_list[ast_module_index][module_memory_block_index].is_none() {
+                resolve_ast_module_memory_block(
+                    named_ast_modules,
+                    &instance_memory_blocks,
+                    &mut module_to_memory_blocks_list,
+                    ast_module_index,
+                    module_memory_block_index,
+                )?;
+            }
         }
     }
 
     // 转换临时映射表
-    let list = module_to_memory_block_index_list
+    let list = module_to_memory_blocks_list
         .iter()
-        .map(|item| item.unwrap())
-        .collect::<Vec<usize>>();
+        .map(|item| {
+            item.iter()
+                .map(|sub_item| sub_item.unwrap())
+                .collect::<Vec<usize>>()
+        })
+        .collect::<Vec<Vec<usize>>>();
 
     Ok((instance_memory_blocks, list))
 }
 
+/// 解决指定模块的一个导入内存块
+///
+/// 返回目标内存块实例的索引
 fn resolve_ast_module_memory_block(
     named_ast_modules: &[NamedAstModule],
     instance_memory_blocks: &Vec<VMMemory>,
-    module_memory_block_map: &mut Vec<Option<usize>>,
+    module_memory_block_map: &mut Vec<Vec<Option<usize>>>,
     ast_module_index: usize,
+    module_memory_block_index: usize,
 ) -> Result<usize, EngineError> {
     let ast_module = &named_ast_modules[ast_module_index].module;
 
+    // 导入的内存块的索引排在模块内定义的内存块之前，
+    // 所以 module_memory_block_index 即是导入内存块的序号
     let (target_module_name, target_export_item_name, target_memory_type) = ast_module
         .import_items
         .iter()
-        .find_map(|item| {
+        .filter_map(|item| {
             if let ImportDescriptor::MemoryType(memory_type) = &item.import_descriptor {
                 Some((&item.module_name, &item.item_name, memory_type))
             } else {
                 None
             }
         })
-        .expect("unreachable"); // 仅当 AST Module 声明了一个导入内存块才会来到这里，所以不存在找不到导入项的情况
+        .collect::<Vec<(&String, &String, &MemoryType)>>()[module_memory_block_index];
 
     let (target_ast_module_index, target_ast_module) = named_ast_modules
         .iter()
@@ -763,14 +766,14 @@ fn resolve_ast_module_memory_block(
             target_module_name.to_owned(),
         )))?;
 
-    let target_memory_block_index = target_ast_module
+    let target_module_memory_block_index = target_ast_module
         .export_items
         .iter()
         .find_map(|item| match item.export_descriptor {
             ExportDescriptor::MemoryBlockIndex(memory_block_index)
                 if &item.name == target_export_item_name =>
             {
-                Some(memory_block_index)
+                Some(memory_block_index as usize)
             }
             _ => None,
         })
@@ -781,14 +784,8 @@ fn resolve_ast_module_memory_block(
             ),
         ))?;
 
-    if target_memory_block_index != 0 {
-        return Err(EngineError::Unsupported(
-            Unsupported::UnsupportedMultipleMemoryBlock,
-        ));
-    }
-
     let option_target_instance_memory_block_index =
-        module_memory_block_map[target_ast_module_index];
+        module_memory_block_map[target_ast_module_index][target_module_memory_block_index];
 
     let target_instance_memory_block_index =
         if let Some(index) = option_target_instance_memory_block_index {
@@ -801,6 +798,7 @@ fn resolve_ast_module_memory_block(
                 instance_memory_blocks,
                 module_memory_block_map,
                 target_ast_module_index,
+                target_module_memory_block_index,
             )?
         };
 
@@ -817,19 +815,24 @@ fn resolve_ast_module_memory_block(
     }
 
     // 更新映射表
-    module_memory_block_map[ast_module_index] = Some(target_instance_memory_block_index);
+    module_memory_block_map[ast_module_index][module_memory_block_index] =
+        Some(target_instance_memory_block_index);
 
-    Ok(target_ast_module_index)
+    Ok(target_instance_memory_block_index)
 }
 
 /// 解决模块间的全局变量链接
 ///
+/// function_reference_bases 是各个模块的函数引用的起始编号，用于求
+/// 初始值常量表达式里的 `ref.func` 指令的值。
+///
 /// 返回值当中
 /// - Vec<VMGlobalVariable> 是虚拟机当中所有全局变量实例的列表
 /// - Vec<Vec<usize>> 是每个 AST Module 对应的全局变量实例的索引列表
 ///   注：一个 Module 可以有多个全局变量
 pub fn link_global_variables(
     named_ast_modules: &[NamedAstModule],
+    function_reference_bases: &[u32],
 ) -> Result<(Vec<VMGlobalVariable>, Vec<Vec<usize>>), EngineError> {
     // "AST 模块 - 全局变量实例的索引" 的临时映射表
     let mut module_to_global_variables_list: Vec<Vec<Option<usize>>> = vec![];
@@ -854,24 +857,12 @@ pub fn link_global_variables(
         }
 
         // 再创建模块内定义的所有全局变量
+        //
+        // 因为初始化常量表达式可能通过 `global.get` 指令引用导入的全局变量，
+        // 所以需要等导入全局变量解决之后才能求值，这里先使用数据类型的默认值。
         for global_item in &ast_module.global_items {
             let global_type = global_item.global_type.clone();
-
-            // 求值 global_item 的初始化常量表达式
-            let constant_expression =
-                decode_constant_expression(&global_item.initialize_instruction_items)?;
-            let value = VM::get_constant_instruction_value(&constant_expression)?;
-
-            // 检查数据类型是否匹配
-            if value.get_type() != global_type.value_type {
-                return Err(EngineError::TypeMismatch(
-                    TypeMismatch::ConstantExpressionValueTypeMismatch(
-                        global_type.value_type,
-                        value.get_type(),
-                    ),
-                ));
-            }
-
+            let value = Value::default_of(&global_type.value_type);
             let instance_global_variable = VMGlobalVariable::new(global_type, value);
 
             // 创建全局变量实例
@@ -920,9 +911,125 @@ pub fn link_global_variables(
         })
         .collect::<Vec<Vec<usize>>>();
 
+    initialize_global_variables(
+        named_ast_modules,
+        &mut instance_global_variables,
+        &list,
+        function_reference_bases,
+    )?;
+
     Ok((instance_global_variables, list))
 }
 
+/// 求值所有模块内定义的全局变量的初始化常量表达式
+///
+/// 一个全局变量的初始值可能依赖于另一个模块的全局变量（即导入的全局变量），
+/// 所以需要按照依赖的次序求值：每一轮只求值所依赖的全局变量均已初始化的那些，
+/// 直到全部求值完毕。如果某一轮没有任何进展，说明存在循环依赖。
+fn initialize_global_variables(
+    named_ast_modules: &[NamedAstModule],
+    instance_global_variables: &mut [VMGlobalVariable],
+    module_to_global_variable_indexes_list: &[Vec<usize>],
+    function_reference_bases: &[u32],
+) -> Result<(), EngineError> {
+    // 待求值的全局变量列表：(AST 模块索引, 模块内的全局变量索引, 常量表达式)
+    let mut pending_items: Vec<(usize, usize, Vec<instruction::Instruction>)> = vec![];
+
+    for (ast_module_index, named_ast_module) in named_ast_modules.iter().enumerate() {
+        let ast_module = &named_ast_module.module;
+        let import_global_variable_count = module_to_global_variable_indexes_list[ast_module_index]
+            .len()
+            - ast_module.global_items.len();
+
+        for (internal_global_variable_index, global_item) in
+            ast_module.global_items.iter().enumerate()
+        {
+            let constant_expression =
+                decode_constant_expression(&global_item.initialize_instruction_items)?;
+            pending_items.push((
+                ast_module_index,
+                import_global_variable_count + internal_global_variable_index,
+                constant_expression,
+            ));
+        }
+    }
+
+    // 记录每个全局变量实例是否已经初始化，所有导入的全局变量最终都指向
+    // 模块内定义的全局变量，所以一开始都是未初始化的。
+    let mut initialized_flags = vec![false; instance_global_variables.len()];
+
+    while !pending_items.is_empty() {
+        let pending_items_count = pending_items.len();
+        let mut remaining_items: Vec<(usize, usize, Vec<instruction::Instruction>)> = vec![];
+
+        for (ast_module_index, global_variable_index, constant_expression) in pending_items {
+            let global_variable_indexes = &module_to_global_variable_indexes_list[ast_module_index];
+
+            let is_ready = constant_expression.iter().all(|inst| match inst {
+                instruction::Instruction::GlobalGet(index) => {
+                    match global_variable_indexes.get(*index as usize) {
+                        Some(instance_index) => initialized_flags[*instance_index],
+                        // 索引超出范围的错误留给求值时处理
+                        None => true,
+                    }
+                }
+                _ => true,
+            });
+
+            if !is_ready {
+                remaining_items.push((
+                    ast_module_index,
+                    global_variable_index,
+                    constant_expression,
+                ));
+                continue;
+            }
+
+            let global_values = global_variable_indexes
+                .iter()
+                .map(|instance_index| instance_global_variables[*instance_index].get_value())
+                .collect::<Vec<Value>>();
+            let value = VM::get_constant_instruction_value(
+                &constant_expression,
+                &global_values,
+                function_reference_bases[ast_module_index],
+            )?;
+
+            let instance_index = global_variable_indexes[global_variable_index];
+            let global_type = instance_global_variables[instance_index]
+                .get_global_type()
+                .clone();
+
+            // 检查数据类型是否匹配
+            if value.get_type() != global_type.value_type {
+                return Err(EngineError::TypeMismatch(
+                    TypeMismatch::ConstantExpressionValueTypeMismatch(
+                        global_type.value_type,
+                        value.get_type(),
+                    ),
+                ));
+            }
+
+            instance_global_variables[instance_index] = VMGlobalVariable::new(global_type, value);
+            initialized_flags[instance_index] = true;
+        }
+
+        if remaining_items.len() == pending_items_count {
+            let (ast_module_index, global_variable_index, _) = &remaining_items[0];
+            return Err(EngineError::InvalidOperation(
+                InvalidOperation::CircularGlobalVariableInitialization(
+                    named_ast_modules[*ast_module_index].name.clone(),
+                    *global_variable_index,
+                ),
+            ));
+        }
+
+        pending_items = remaining_items;
+    }
+
+    Ok(())
+}
+
 fn resolve_ast_module_global_variable(
     named_ast_modules: &[NamedAstModule],
     instance_global_variables: &Vec<VMGlobalVariable>,
@@ -0,0 +1,578 @@
+// Copyright (c) 2022 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! # 模块内存的视图
+//!
+//! 本地函数的参数当中的指针（即模块内存的地址）是由模块程序传入的，它们有可能
+//! 超出内存块的范围。`WasmPtr<T>` 和 `WasmSlice<T>` 在读写之前先检查访问的范围，
+//! 范围无效时返回 `MemoryAccessError`，而不是像 `VMMemory::read_*` 和
+//! `VMMemory::write_*` 那样 panic。
+//!
+//! 数据按照 WebAssembly 的约定以小端序储存，结构体可以通过实现 `MemoryValue`
+//! 特性来读写，比如 WASI 的 `ciovec` 对应本模块的 `IOVec`。
+//!
+//! `MemoryAccessError` 可以通过 `into_native_terminate` 方法转换为本地函数的错误，
+//! 其中越界访问会被 VM 转换为 `TrapCode::MemoryOutOfBounds` 陷阱。
+
+use std::{any::Any, fmt::Display, marker::PhantomData};
+
+use crate::{
+    error::{InternalError, NativeError, NativeTerminate},
+    trap::TrapCode,
+    vm_memory::VMMemory,
+};
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum MemoryAccessError {
+    /// 访问的范围超出了内存块的范围，或者地址的计算发生了溢出，
+    /// 也包括 NUL 结尾的字符串一直到内存块的末尾都没有遇到 NUL 字符。
+    OutOfBounds,
+
+    /// 字符串不是有效的 UTF-8 数据
+    InvalidUtf8,
+}
+
+impl MemoryAccessError {
+    /// 转换为本地函数的错误
+    ///
+    /// 越界访问转换为陷阱 `TrapCode::MemoryOutOfBounds`，其他错误则转换为
+    /// `NativeError::Internal`。
+    pub fn into_native_terminate(self, module_name: &str) -> NativeTerminate {
+        let native_error = match self {
+            MemoryAccessError::OutOfBounds => NativeError::Trap(TrapCode::MemoryOutOfBounds),
+            _ => NativeError::Internal(Box::new(self)),
+        };
+
+        NativeTerminate {
+            module_name: module_name.to_owned(),
+            native_error,
+        }
+    }
+}
+
+impl Display for MemoryAccessError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MemoryAccessError::OutOfBounds => write!(f, "out of bounds memory access"),
+            MemoryAccessError::InvalidUtf8 => write!(f, "invalid UTF-8 string"),
+        }
+    }
+}
+
+impl InternalError for MemoryAccessError {
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+}
+
+/// 可以储存在模块内存里的数据类型
+///
+/// 数据按照小端序储存，所占的字节数是固定的。
+pub trait MemoryValue: Sized {
+    /// 数据在内存里所占的字节数
+    const SIZE: usize;
+
+    /// 参数 bytes 的长度等于 `SIZE`
+    fn read_from(bytes: &[u8]) -> Self;
+
+    /// 参数 bytes 的长度等于 `SIZE`
+    fn write_to(&self, bytes: &mut [u8]);
+}
+
+macro_rules! impl_memory_value {
+    ($type:ty) => {
+        impl MemoryValue for $type {
+            const SIZE: usize = std::mem::size_of::<$type>();
+
+            fn read_from(bytes: &[u8]) -> Self {
+                <$type>::from_le_bytes(bytes.try_into().unwrap())
+            }
+
+            fn write_to(&self, bytes: &mut [u8]) {
+                bytes.copy_from_slice(&self.to_le_bytes());
+            }
+        }
+    };
+}
+
+impl_memory_value!(u8);
+impl_memory_value!(i8);
+impl_memory_value!(u16);
+impl_memory_value!(i16);
+impl_memory_value!(u32);
+impl_memory_value!(i32);
+impl_memory_value!(u64);
+impl_memory_value!(i64);
+impl_memory_value!(f32);
+impl_memory_value!(f64);
+
+/// 指向模块内存里的一个 T 类型数据的指针
+pub struct WasmPtr<T> {
+    address: u32,
+    _marker: PhantomData<T>,
+}
+
+impl<T> Clone for WasmPtr<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for WasmPtr<T> {}
+
+impl<T> PartialEq for WasmPtr<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.address == other.address
+    }
+}
+
+impl<T> std::fmt::Debug for WasmPtr<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "WasmPtr({})", self.address)
+    }
+}
+
+impl<T: MemoryValue> WasmPtr<T> {
+    pub fn new(address: u32) -> Self {
+        Self {
+            address,
+            _marker: PhantomData,
+        }
+    }
+
+    /// 从本地函数的 i32 类型参数创建指针，参数的值被视为无符号整数
+    pub fn from_i32(address: i32) -> Self {
+        Self::new(address as u32)
+    }
+
+    pub fn get_address(&self) -> u32 {
+        self.address
+    }
+
+    pub fn read(&self, memory: &VMMemory) -> Result<T, MemoryAccessError> {
+        let bytes = checked_read(memory, self.address as usize, T::SIZE)?;
+        Ok(T::read_from(bytes))
+    }
+
+    pub fn write(&self, memory: &mut VMMemory, value: &T) -> Result<(), MemoryAccessError> {
+        let mut bytes = vec![0u8; T::SIZE];
+        value.write_to(&mut bytes);
+        checked_write(memory, self.address as usize, &bytes)
+    }
+
+    /// 获取向后移动 count 个 T 之后的指针
+    pub fn offset(&self, count: u32) -> Result<WasmPtr<T>, MemoryAccessError> {
+        (count as u64)
+            .checked_mul(T::SIZE as u64)
+            .and_then(|delta| (self.address as u64).checked_add(delta))
+            .and_then(|address| u32::try_from(address).ok())
+            .map(WasmPtr::new)
+            .ok_or(MemoryAccessError::OutOfBounds)
+    }
+
+    /// 获取从当前位置开始、长度为 len 个 T 的切片
+    pub fn slice(&self, len: u32) -> WasmSlice<T> {
+        WasmSlice::new(self.address, len)
+    }
+}
+
+impl WasmPtr<u8> {
+    /// 读取以 NUL 字符结尾的字符串（不包括 NUL 字符）
+    pub fn read_c_string(&self, memory: &VMMemory) -> Result<String, MemoryAccessError> {
+        let address = self.address as usize;
+        if address > memory.get_byte_count() {
+            return Err(MemoryAccessError::OutOfBounds);
+        }
+
+        let remain = memory.read_bytes(address, memory.get_byte_count() - address);
+        let length = remain
+            .iter()
+            .position(|b| *b == 0)
+            .ok_or(MemoryAccessError::OutOfBounds)?;
+
+        bytes_to_string(&remain[..length])
+    }
+
+    /// 读取以 u32 长度值为前缀的字符串
+    ///
+    /// 内存里先储存 4 个字节的字符串长度（小端序），然后紧跟着字符串的内容。
+    pub fn read_length_prefixed_string(
+        &self,
+        memory: &VMMemory,
+    ) -> Result<String, MemoryAccessError> {
+        let length = WasmPtr::<u32>::new(self.address).read(memory)?;
+        let content_address = self.offset(u32::SIZE as u32)?.get_address();
+        WasmSlice::<u8>::new(content_address, length).read_string(memory)
+    }
+}
+
+/// 模块内存里连续的 len 个 T 类型数据
+pub struct WasmSlice<T> {
+    address: u32,
+    len: u32,
+    _marker: PhantomData<T>,
+}
+
+impl<T> Clone for WasmSlice<T> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<T> Copy for WasmSlice<T> {}
+
+impl<T> PartialEq for WasmSlice<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.address == other.address && self.len == other.len
+    }
+}
+
+impl<T> std::fmt::Debug for WasmSlice<T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "WasmSlice({}, {})", self.address, self.len)
+    }
+}
+
+impl<T: MemoryValue> WasmSlice<T> {
+    pub fn new(address: u32, len: u32) -> Self {
+        Self {
+            address,
+            len,
+            _marker: PhantomData,
+        }
+    }
+
+    /// 从本地函数的两个 i32 类型参数（地址以及数量）创建切片，参数的值被视为无符号整数
+    pub fn from_i32(address: i32, len: i32) -> Self {
+        Self::new(address as u32, len as u32)
+    }
+
+    pub fn get_address(&self) -> u32 {
+        self.address
+    }
+
+    pub fn len(&self) -> u32 {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// 切片所占的字节数
+    pub fn get_byte_count(&self) -> Result<usize, MemoryAccessError> {
+        (self.len as usize)
+            .checked_mul(T::SIZE)
+            .ok_or(MemoryAccessError::OutOfBounds)
+    }
+
+    /// 检查切片是否在内存块的范围之内
+    pub fn check(&self, memory: &VMMemory) -> Result<(), MemoryAccessError> {
+        if memory.is_valid_range(self.address as usize, self.get_byte_count()?) {
+            Ok(())
+        } else {
+            Err(MemoryAccessError::OutOfBounds)
+        }
+    }
+
+    /// 获取第 index 个元素的指针，index 超出切片的范围时返回 None
+    pub fn get(&self, index: u32) -> Option<WasmPtr<T>> {
+        if index < self.len {
+            WasmPtr::new(self.address).offset(index).ok()
+        } else {
+            None
+        }
+    }
+
+    pub fn read_all(&self, memory: &VMMemory) -> Result<Vec<T>, MemoryAccessError> {
+        let bytes = checked_read(memory, self.address as usize, self.get_byte_count()?)?;
+        Ok(bytes.chunks_exact(T::SIZE).map(T::read_from).collect())
+    }
+
+    /// 从切片的开始位置写入数据，数据的数量不能超过切片的长度
+    pub fn write_all(&self, memory: &mut VMMemory, values: &[T]) -> Result<(), MemoryAccessError> {
+        if values.len() > self.len as usize {
+            return Err(MemoryAccessError::OutOfBounds);
+        }
+
+        let mut bytes = vec![0u8; values.len() * T::SIZE];
+        for (value, chunk) in values.iter().zip(bytes.chunks_exact_mut(T::SIZE)) {
+            value.write_to(chunk);
+        }
+
+        checked_write(memory, self.address as usize, &bytes)
+    }
+}
+
+impl WasmSlice<u8> {
+    pub fn read_bytes<'a>(&self, memory: &'a VMMemory) -> Result<&'a [u8], MemoryAccessError> {
+        checked_read(memory, self.address as usize, self.len as usize)
+    }
+
+    /// 写入字节，数据的长度不能超过切片的长度
+    pub fn write_bytes(&self, memory: &mut VMMemory, data: &[u8]) -> Result<(), MemoryAccessError> {
+        if data.len() > self.len as usize {
+            return Err(MemoryAccessError::OutOfBounds);
+        }
+
+        checked_write(memory, self.address as usize, data)
+    }
+
+    /// 读取由地址和长度所指定的字符串
+    pub fn read_string(&self, memory: &VMMemory) -> Result<String, MemoryAccessError> {
+        bytes_to_string(self.read_bytes(memory)?)
+    }
+}
+
+/// 分散/聚集读写（scatter/gather I/O）的缓冲区描述
+///
+/// 跟 WASI 的 `iovec` 以及 `ciovec` 结构体的内存布局一致：
+///
+/// - buf: u32，缓冲区的地址，偏移 0
+/// - buf_len: u32，缓冲区的长度，偏移 4
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct IOVec {
+    pub buf: u32,
+    pub buf_len: u32,
+}
+
+impl IOVec {
+    pub fn get_buffer(&self) -> WasmSlice<u8> {
+        WasmSlice::new(self.buf, self.buf_len)
+    }
+}
+
+impl MemoryValue for IOVec {
+    const SIZE: usize = 8;
+
+    fn read_from(bytes: &[u8]) -> Self {
+        Self {
+            buf: u32::read_from(&bytes[0..4]),
+            buf_len: u32::read_from(&bytes[4..8]),
+        }
+    }
+
+    fn write_to(&self, bytes: &mut [u8]) {
+        self.buf.write_to(&mut bytes[0..4]);
+        self.buf_len.write_to(&mut bytes[4..8]);
+    }
+}
+
+impl WasmSlice<IOVec> {
+    /// 读取 iovec 数组，并检查每个 iovec 所指向的缓冲区都在内存块的范围之内
+    pub fn read_iovecs(&self, memory: &VMMemory) -> Result<Vec<IOVec>, MemoryAccessError> {
+        let iovecs = self.read_all(memory)?;
+
+        for iovec in &iovecs {
+            iovec.get_buffer().check(memory)?;
+        }
+
+        Ok(iovecs)
+    }
+
+    /// 读取 iovec 数组所指向的各个缓冲区的数据
+    pub fn read_buffers<'a>(
+        &self,
+        memory: &'a VMMemory,
+    ) -> Result<Vec<&'a [u8]>, MemoryAccessError> {
+        self.read_iovecs(memory)?
+            .iter()
+            .map(|iovec| iovec.get_buffer().read_bytes(memory))
+            .collect()
+    }
+}
+
+fn checked_read(
+    memory: &VMMemory,
+    address: usize,
+    length: usize,
+) -> Result<&[u8], MemoryAccessError> {
+    memory
+        .checked_read_bytes(address, length)
+        .map_err(|_| MemoryAccessError::OutOfBounds)
+}
+
+fn checked_write(
+    memory: &mut VMMemory,
+    address: usize,
+    data: &[u8],
+) -> Result<(), MemoryAccessError> {
+    memory
+        .checked_write_bytes(address, data)
+        .map_err(|_| MemoryAccessError::OutOfBounds)
+}
+
+fn bytes_to_string(bytes: &[u8]) -> Result<String, MemoryAccessError> {
+    String::from_utf8(bytes.to_vec()).map_err(|_| MemoryAccessError::InvalidUtf8)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{error::NativeError, trap::TrapCode, vm_memory::VMMemory};
+
+    use super::{IOVec, MemoryAccessError, WasmPtr, WasmSlice};
+
+    #[test]
+    fn test_ptr_read_write() {
+        let mut memory = VMMemory::new_by_min_page(1);
+        let byte_count = memory.get_byte_count() as u32;
+
+        let p0 = WasmPtr::<i32>::new(8);
+        p0.write(&mut memory, &-123).unwrap();
+        assert_eq!(p0.read(&memory).unwrap(), -123);
+        assert_eq!(memory.read_i32(8), -123);
+
+        let p1 = p0.offset(1).unwrap();
+        assert_eq!(p1.get_address(), 12);
+        p1.write(&mut memory, &456).unwrap();
+        assert_eq!(p0.slice(2).read_all(&memory).unwrap(), vec![-123, 456]);
+
+        let p2 = WasmPtr::<f64>::new(16);
+        p2.write(&mut memory, &3.5).unwrap();
+        assert_eq!(p2.read(&memory).unwrap(), 3.5);
+
+        // 越界
+        let p3 = WasmPtr::<i64>::new(byte_count - 4);
+        assert_eq!(p3.read(&memory), Err(MemoryAccessError::OutOfBounds));
+        assert_eq!(
+            p3.write(&mut memory, &1),
+            Err(MemoryAccessError::OutOfBounds)
+        );
+        assert_eq!(
+            WasmPtr::<i32>::from_i32(-1).read(&memory),
+            Err(MemoryAccessError::OutOfBounds)
+        );
+        assert_eq!(
+            WasmPtr::<i32>::new(u32::MAX).offset(1),
+            Err(MemoryAccessError::OutOfBounds)
+        );
+    }
+
+    #[test]
+    fn test_slice() {
+        let mut memory = VMMemory::new_by_min_page(1);
+        let byte_count = memory.get_byte_count() as u32;
+
+        let s0 = WasmSlice::<u16>::new(100, 3);
+        s0.write_all(&mut memory, &[1, 2, 3]).unwrap();
+        assert_eq!(s0.read_all(&memory).unwrap(), vec![1, 2, 3]);
+        assert_eq!(s0.get(2).unwrap().read(&memory).unwrap(), 3);
+        assert_eq!(s0.get(3), None);
+        assert_eq!(
+            s0.write_all(&mut memory, &[1, 2, 3, 4]),
+            Err(MemoryAccessError::OutOfBounds)
+        );
+
+        let s1 = WasmSlice::<u8>::new(200, 5);
+        s1.write_bytes(&mut memory, b"hello").unwrap();
+        assert_eq!(s1.read_bytes(&memory).unwrap(), b"hello");
+        assert_eq!(s1.read_string(&memory).unwrap(), "hello");
+
+        // 越界以及长度溢出
+        assert_eq!(
+            WasmSlice::<u8>::new(byte_count - 2, 3).check(&memory),
+            Err(MemoryAccessError::OutOfBounds)
+        );
+        assert_eq!(
+            WasmSlice::<u64>::from_i32(0, -1).read_all(&memory),
+            Err(MemoryAccessError::OutOfBounds)
+        );
+    }
+
+    #[test]
+    fn test_strings() {
+        let mut memory = VMMemory::new_by_min_page(1);
+        let byte_count = memory.get_byte_count();
+
+        memory.write_bytes(0, b"abc\0def");
+        assert_eq!(WasmPtr::<u8>::new(0).read_c_string(&memory).unwrap(), "abc");
+        assert_eq!(WasmPtr::<u8>::new(3).read_c_string(&memory).unwrap(), "");
+
+        // 一直到内存块的末尾都没有 NUL 字符
+        memory.fill_bytes(byte_count - 4, b'x', 4);
+        assert_eq!(
+            WasmPtr::<u8>::new(byte_count as u32 - 4).read_c_string(&memory),
+            Err(MemoryAccessError::OutOfBounds)
+        );
+
+        // 长度前缀
+        memory.write_i32(16, 5);
+        memory.write_bytes(20, b"world");
+        assert_eq!(
+            WasmPtr::<u8>::new(16)
+                .read_length_prefixed_string(&memory)
+                .unwrap(),
+            "world"
+        );
+
+        memory.write_i32(16, 1000000);
+        assert_eq!(
+            WasmPtr::<u8>::new(16).read_length_prefixed_string(&memory),
+            Err(MemoryAccessError::OutOfBounds)
+        );
+
+        // 无效的 UTF-8
+        memory.write_bytes(32, &[0xff, 0xfe, 0]);
+        assert_eq!(
+            WasmPtr::<u8>::new(32).read_c_string(&memory),
+            Err(MemoryAccessError::InvalidUtf8)
+        );
+    }
+
+    #[test]
+    fn test_iovecs() {
+        let mut memory = VMMemory::new_by_min_page(1);
+
+        memory.write_bytes(100, b"hello");
+        memory.write_bytes(200, b"world");
+
+        let iovecs = WasmSlice::<IOVec>::new(0, 2);
+        iovecs
+            .write_all(
+                &mut memory,
+                &[
+                    IOVec {
+                        buf: 100,
+                        buf_len: 5,
+                    },
+                    IOVec {
+                        buf: 200,
+                        buf_len: 3,
+                    },
+                ],
+            )
+            .unwrap();
+
+        assert_eq!(memory.read_i32(0), 100);
+        assert_eq!(memory.read_i32(4), 5);
+        assert_eq!(
+            iovecs.read_buffers(&memory).unwrap(),
+            vec![&b"hello"[..], &b"wor"[..]]
+        );
+
+        // iovec 所指向的缓冲区越界
+        memory.write_i32(12, 0x10000);
+        assert_eq!(
+            iovecs.read_buffers(&memory),
+            Err(MemoryAccessError::OutOfBounds)
+        );
+    }
+
+    #[test]
+    fn test_into_native_terminate() {
+        let out_of_bounds = MemoryAccessError::OutOfBounds.into_native_terminate("test");
+        assert!(matches!(
+            out_of_bounds.native_error,
+            NativeError::Trap(TrapCode::MemoryOutOfBounds)
+        ));
+
+        let invalid_utf8 = MemoryAccessError::InvalidUtf8.into_native_terminate("test");
+        assert!(matches!(
+            invalid_utf8.native_error,
+            NativeError::Internal(_)
+        ));
+    }
+}
@@ -4,7 +4,7 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use std::any::Any;
+use std::{any::Any, cell::RefCell, rc::Rc};
 
 use anvm_ast::{
     ast::FunctionType,
@@ -13,8 +13,56 @@ use anvm_ast::{
 
 use crate::{error::NativeTerminate, vm::VM};
 
-pub type NativeFunction =
-    fn(&mut VM, native_module_index: usize, &[Value]) -> Result<Vec<Value>, NativeTerminate>;
+/// 本地函数的返回值
+pub type NativeFunctionResult = Result<Vec<Value>, NativeTerminate>;
+
+/// 本地函数
+///
+/// 本地函数既可以是普通的函数（函数指针），也可以是捕获了宿主状态的闭包，
+/// 闭包可以直接持有宿主的状态，无需通过 `ModuleContext` 获取。
+///
+/// 调用本地函数时需要同时借用函数以及 VM，所以函数被放在 `Rc` 里，调用之前先复制一份 Rc。
+#[derive(Clone)]
+pub struct NativeFunction(NativeFunctionBody);
+
+/// 本地函数的实现
+///
+/// 参数依次为 VM、本地函数所在的本地模块的索引以及实参
+#[derive(Clone)]
+enum NativeFunctionBody {
+    /// 普通的函数以及 `Fn` 闭包，调用时无需可变借用，所以可以重入，
+    /// 即本地函数可以通过 VM 直接或者间接地调用自身
+    Shared(Rc<SharedNativeFunction>),
+
+    /// `FnMut` 闭包，调用时需要可变借用，所以无法重入
+    Mutable(Rc<RefCell<MutableNativeFunction>>),
+}
+
+type SharedNativeFunction = dyn Fn(&mut VM, usize, &[Value]) -> NativeFunctionResult;
+type MutableNativeFunction = dyn FnMut(&mut VM, usize, &[Value]) -> NativeFunctionResult;
+
+impl NativeFunction {
+    /// 调用本地函数
+    ///
+    /// 如果函数是 `FnMut` 闭包并且正在执行（即函数通过 VM 直接或者间接地调用了自身），
+    /// 则无法再次调用，此时返回 None。
+    pub fn call(
+        &self,
+        vm: &mut VM,
+        native_module_index: usize,
+        arguments: &[Value],
+    ) -> Option<NativeFunctionResult> {
+        match &self.0 {
+            NativeFunctionBody::Shared(native_function) => {
+                Some(native_function(vm, native_module_index, arguments))
+            }
+            NativeFunctionBody::Mutable(native_function) => {
+                let mut native_function = native_function.try_borrow_mut().ok()?;
+                Some(native_function(vm, native_module_index, arguments))
+            }
+        }
+    }
+}
 
 /// 本地函数的本地模块
 ///
@@ -62,18 +110,82 @@ impl NativeModule {
         }
     }
 
-    pub fn add_native_function(
+    /// 添加本地函数
+    ///
+    /// native_function 可以是普通的函数，也可以是闭包，闭包可以捕获宿主的状态，比如：
+    ///
+    /// ```ignore
+    /// let counter = Rc::new(Cell::new(0));
+    /// let counter_clone = Rc::clone(&counter);
+    ///
+    /// native_module.add_native_function(
+    ///     "count",
+    ///     vec![],
+    ///     vec![],
+    ///     vec![ValueType::I32],
+    ///     move |_vm, _native_module_index, _args| {
+    ///         counter_clone.set(counter_clone.get() + 1);
+    ///         Ok(vec![Value::I32(counter_clone.get())])
+    ///     },
+    /// );
+    /// ```
+    ///
+    /// 本地函数可以通过 VM 直接或者间接地调用自身。
+    pub fn add_native_function<F>(
+        &mut self,
+        name: &str,
+        params: Vec<ValueType>,
+        param_names: Vec<&str>,
+        results: Vec<ValueType>,
+        native_function: F,
+    ) where
+        F: Fn(&mut VM, usize, &[Value]) -> NativeFunctionResult + 'static,
+    {
+        self.push_native_function(
+            name,
+            params,
+            param_names,
+            results,
+            NativeFunctionBody::Shared(Rc::new(native_function)),
+        );
+    }
+
+    /// 添加需要修改所捕获的状态的（即 `FnMut`）闭包作为本地函数
+    ///
+    /// 这种本地函数无法重入，如果它通过 VM 直接或者间接地调用自身，
+    /// 则该次调用返回 `InvalidOperation::ReentrantNativeFunctionCall` 错误。
+    pub fn add_native_function_mut<F>(
+        &mut self,
+        name: &str,
+        params: Vec<ValueType>,
+        param_names: Vec<&str>,
+        results: Vec<ValueType>,
+        native_function: F,
+    ) where
+        F: FnMut(&mut VM, usize, &[Value]) -> NativeFunctionResult + 'static,
+    {
+        self.push_native_function(
+            name,
+            params,
+            param_names,
+            results,
+            NativeFunctionBody::Mutable(Rc::new(RefCell::new(native_function))),
+        );
+    }
+
+    fn push_native_function(
         &mut self,
         name: &str,
         params: Vec<ValueType>,
         param_names: Vec<&str>,
         results: Vec<ValueType>,
-        native_function: NativeFunction,
+        native_function_body: NativeFunctionBody,
     ) {
         let function_type_index = self.add_function_type(params, results);
 
         self.function_to_type_index_list.push(function_type_index);
-        self.native_functions.push(native_function);
+        self.native_functions
+            .push(NativeFunction(native_function_body));
         self.function_names.push(name.to_string());
         self.local_variable_names.push(
             param_names
@@ -7,17 +7,26 @@
 use anvm_ast::ast;
 use anvm_ast::instruction::{self, BlockType};
 
+use crate::snapshot::compute_module_hash;
+
 #[derive(Debug, PartialEq, Clone)]
 pub struct NamedAstModule {
     pub name: String,
     pub module: ast::Module,
+
+    /// 模块内容的哈希值
+    ///
+    /// 由 module 计算得出，VM 快照通过该值检查恢复快照时所使用的模块是否跟保存快照时的一致。
+    pub(crate) content_hash: u64,
 }
 
 impl NamedAstModule {
     pub fn new(name: &str, module: ast::Module) -> Self {
+        let content_hash = compute_module_hash(&module);
         Self {
             name: name.to_string(),
             module,
+            content_hash,
         }
     }
 }
@@ -267,4 +276,86 @@ pub enum Instruction {
     /// 控制指令
     /// 会控制或者会改变程序执行顺序的指令
     Control(Control),
+
+    /// 超级指令
+    /// 由 decoder 的优化过程把若干条连续的指令合并而成，见 `Fused`
+    Fused(Fused),
+}
+
+/// 超级指令
+///
+/// 超级指令替换被合并的指令序列当中的第一条指令，指令序列当中的其余指令
+/// 保持不变，即超级指令的地址就是它对应的原指令序列的第一条指令的地址，
+/// 被合并的原指令的地址为 `超级指令的地址 + 0..get_instruction_count()`。
+/// 因此优化之后的指令列表的长度以及所有跳转目标的地址均跟原指令列表一致。
+///
+/// 执行超级指令之后，pc 直接指向原指令序列之后的一条指令。
+#[derive(Debug, PartialEq, Clone)]
+pub enum Fused {
+    /// `local.get a; local.get b; i32.add`
+    LocalGetLocalGetI32Add(/* local_index */ u32, /* local_index */ u32),
+
+    /// `local.get a; i32.const c; i32.add` 或者 `local.get a; i32.const c; i32.sub`
+    ///
+    /// 对于后者，常量的值为原常量取反（即 c 的 wrapping_neg）。
+    LocalGetI32ConstI32Add(/* local_index */ u32, /* value */ i32),
+
+    /// `local.get a; i32.const c; i32.add; local.set b`
+    /// 或者 `local.get a; i32.const c; i32.sub; local.set b`
+    ///
+    /// 对于后者，常量的值为原常量取反。
+    LocalGetI32ConstI32AddLocalSet(
+        /* local_index */ u32,
+        /* value */ i32,
+        /* local_index */ u32,
+    ),
+
+    /// i32 比较指令（包括 `i32.eqz`）之后紧跟着 br_if 跳转到 block/if 结构块的结尾
+    ///
+    /// 参数跟 `Control::BreakWhenNotEqZero` 一致。
+    I32CompareBreakWhenNotEqZero {
+        comparison: I32Comparison,
+        option_block_index: Option<usize>,
+        relative_depth: usize,
+        address: usize,
+    },
+
+    /// i32 比较指令（包括 `i32.eqz`）之后紧跟着 br_if 跳转到 loop 结构块的开始
+    ///
+    /// 参数跟 `Control::RecurWhenNotEqZero` 一致。
+    I32CompareRecurWhenNotEqZero {
+        comparison: I32Comparison,
+        block_index: usize,
+        relative_depth: usize,
+        address: usize,
+    },
+}
+
+impl Fused {
+    /// 超级指令所合并的原指令的数量
+    pub fn get_instruction_count(&self) -> usize {
+        match self {
+            Fused::LocalGetLocalGetI32Add(..) => 3,
+            Fused::LocalGetI32ConstI32Add(..) => 3,
+            Fused::LocalGetI32ConstI32AddLocalSet(..) => 4,
+            Fused::I32CompareBreakWhenNotEqZero { .. } => 2,
+            Fused::I32CompareRecurWhenNotEqZero { .. } => 2,
+        }
+    }
+}
+
+/// 可以跟 br_if 合并的 i32 比较指令
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum I32Comparison {
+    Eqz,
+    Eq,
+    Ne,
+    LtS,
+    LtU,
+    GtS,
+    GtU,
+    LeS,
+    LeU,
+    GeS,
+    GeU,
 }
@@ -0,0 +1,775 @@
+// Copyright (c) 2022 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! # VM 快照
+//!
+//! 快照保存了 VM 实例的运行状态，VM 可以处于待命状态（没有正在执行的函数），
+//! 也可以处于暂停状态（比如因断点、燃料耗尽或者中断而停下，以及单步执行之后）。
+//! 通过 `VM::save_snapshot` 保存快照，然后通过 `VM::restore_snapshot` 把快照恢复到
+//! 一个由相同模块新创建的 VM，恢复之后 VM 可以从暂停的位置继续执行。
+//!
+//! 快照包括：
+//!
+//! - VM 的状态（`Status`）、操作数栈、控制栈以及最近一次命中的断点；
+//! - 剩余的燃料；
+//! - 内存块的数据、表的元素以及全局变量的值；
+//! - 数据段和元素段是否已被丢弃。
+//!
+//! 快照不包括模块的指令、类型等不可变的数据，所以恢复快照时需要先使用相同的模块
+//! 创建 VM。快照记录了各个模块的名称以及内容的哈希值，还有各个本地模块的名称以及
+//! 函数签名的哈希值，恢复时如果跟目标 VM 不一致，则返回 `SnapshotError::ModuleMismatch`
+//! 或者 `SnapshotError::NativeModuleMismatch` 错误。
+//!
+//! 注意快照也不包括本地模块的上下文（比如 WASI 模块已打开的文件），断点列表、
+//! 调用帧数量上限等设置也属于宿主，不会被保存。
+//!
+//! ## 格式
+//!
+//! 快照是一段二进制数据，所有整数均以小端序储存，`usize` 储存为 u64：
+//!
+//! - 魔数 `ANVMSNAP`（8 个字节），格式版本号（u32）；
+//! - 模块列表：数量（u32），每项为名称以及模块内容的哈希值（u64）；
+//! - 本地模块列表：数量（u32），每项为名称以及签名哈希值（u64）；
+//! - VM 的状态、剩余的燃料以及最近一次命中的断点；
+//! - 操作数栈的槽以及控制帧；
+//! - 内存块、表、全局变量、数据段以及元素段。
+//!
+//! 字符串储存为长度（u32）加 UTF-8 数据，列表储存为数量加各个元素，
+//! 可选值储存为标记（u8，0 或者 1）加数据。
+
+use std::fmt::{Display, Write};
+
+use anvm_ast::{
+    ast,
+    instruction::BlockType,
+    types::{Value, ValueType},
+};
+
+use crate::{
+    error::EngineError,
+    native_module::NativeModule,
+    vm::{Breakpoint, Status, VM},
+    vm_memory::PAGE_SIZE,
+    vm_stack::{slot_to_value, value_to_slot, ControlFrame, FrameKind, VMStack},
+};
+
+/// 快照数据开头的魔数
+const MAGIC_NUMBER: &[u8; 8] = b"ANVMSNAP";
+
+/// 当前的快照格式版本号
+///
+/// 快照格式发生不兼容的变化时需要增加版本号
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum SnapshotError {
+    /// 数据不是 VM 快照
+    InvalidMagicNumber,
+
+    /// 不支持的快照格式版本
+    UnsupportedVersion(/* version */ u32),
+
+    /// 数据不完整
+    UnexpectedEnd,
+
+    /// 数据无效，比如未知的标记、无效的 UTF-8 字符串，以及超出范围的索引等
+    InvalidData,
+
+    /// 模块的数量、名称或者内容跟目标 VM 的模块不一致
+    ModuleMismatch(/* module_name */ String),
+
+    /// 本地模块的数量、名称或者函数签名跟目标 VM 的本地模块不一致
+    NativeModuleMismatch(/* native_module_name */ String),
+
+    /// 内存块、表、全局变量或者段的数量或者大小跟目标 VM 不一致
+    StateMismatch,
+}
+
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapshotError::InvalidMagicNumber => write!(f, "the data is not a VM snapshot"),
+            SnapshotError::UnsupportedVersion(version) => {
+                write!(f, "unsupported snapshot version: {}", version)
+            }
+            SnapshotError::UnexpectedEnd => write!(f, "unexpected end of snapshot data"),
+            SnapshotError::InvalidData => write!(f, "invalid snapshot data"),
+            SnapshotError::ModuleMismatch(module_name) => write!(
+                f,
+                "module \"{}\" does not match the module of the snapshot",
+                module_name
+            ),
+            SnapshotError::NativeModuleMismatch(native_module_name) => write!(
+                f,
+                "native module \"{}\" does not match the native module of the snapshot",
+                native_module_name
+            ),
+            SnapshotError::StateMismatch => write!(
+                f,
+                "the memory blocks, tables, global variables or segments do not match the snapshot"
+            ),
+        }
+    }
+}
+
+/// 计算数据的哈希值（64 位 FNV-1a）
+///
+/// 哈希值仅用于检查数据是否一致，不能用于防篡改。
+pub fn compute_content_hash(data: &[u8]) -> u64 {
+    let mut hasher = ContentHasher::new();
+    hasher.update(data);
+    hasher.finish()
+}
+
+/// 计算模块内容的哈希值
+///
+/// 哈希值由解析之后的模块计算得出，而不是模块的原始二进制数据，
+/// 所以跟模块本身必定一致。
+pub(crate) fn compute_module_hash(module: &ast::Module) -> u64 {
+    let mut hasher = ContentHasher::new();
+    write!(hasher, "{:?}", module).unwrap();
+    hasher.finish()
+}
+
+/// 计算本地模块的函数名称以及函数类型的哈希值
+fn compute_native_module_hash(native_module: &NativeModule) -> u64 {
+    let mut hasher = ContentHasher::new();
+    write!(hasher, "{:?}", native_module.function_names).unwrap();
+    for type_index in &native_module.function_to_type_index_list {
+        write!(hasher, "{:?}", native_module.function_types[*type_index]).unwrap();
+    }
+    hasher.finish()
+}
+
+struct ContentHasher {
+    hash: u64,
+}
+
+impl ContentHasher {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0100_0000_01b3;
+
+    fn new() -> Self {
+        Self {
+            hash: Self::OFFSET_BASIS,
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        for byte in data {
+            self.hash ^= *byte as u64;
+            self.hash = self.hash.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+}
+
+impl Write for ContentHasher {
+    fn write_str(&mut self, s: &str) -> std::fmt::Result {
+        self.update(s.as_bytes());
+        Ok(())
+    }
+}
+
+impl VM {
+    /// 保存 VM 的运行状态
+    pub fn save_snapshot(&self) -> Vec<u8> {
+        let mut writer = SnapshotWriter::new();
+
+        writer.write_bytes(MAGIC_NUMBER);
+        writer.write_u32(SNAPSHOT_VERSION);
+
+        writer.write_u32(self.resource.vm_modules.len() as u32);
+        for vm_module in self.resource.vm_modules.iter() {
+            writer.write_string(&vm_module.name);
+            writer.write_u64(vm_module.content_hash);
+        }
+
+        writer.write_u32(self.resource.native_modules.len() as u32);
+        for native_module in &self.resource.native_modules {
+            writer.write_string(&native_module.name);
+            writer.write_u64(compute_native_module_hash(native_module));
+        }
+
+        writer.write_usize(self.status.local_pointer);
+        writer.write_usize(self.status.base_pointer);
+        writer.write_usize(self.status.vm_module_index);
+        writer.write_usize(self.status.function_index);
+        writer.write_block_type(&self.status.frame_type);
+        writer.write_usize(self.status.address);
+
+        match self.get_fuel() {
+            Some(fuel) => {
+                writer.write_u8(1);
+                writer.write_u64(fuel);
+            }
+            None => writer.write_u8(0),
+        }
+
+        match self.get_last_hit_breakpoint() {
+            Some(breakpoint) => {
+                writer.write_u8(1);
+                writer.write_usize(breakpoint.vm_module_index);
+                writer.write_usize(breakpoint.function_index);
+                writer.write_usize(breakpoint.address);
+            }
+            None => writer.write_u8(0),
+        }
+
+        let slots = self.stack.read_slots(0, self.stack.get_size());
+        writer.write_usize(slots.len());
+        for slot in slots {
+            writer.write_u64(*slot);
+        }
+
+        let frames = self.stack.get_frames();
+        writer.write_usize(frames.len());
+        for frame in frames {
+            writer.write_u8(match frame.kind {
+                FrameKind::HostCall => 0,
+                FrameKind::Call => 1,
+                FrameKind::Block => 2,
+            });
+            writer.write_usize(frame.frame_pointer);
+            writer.write_usize(frame.return_local_pointer);
+            writer.write_usize(frame.return_base_pointer);
+            writer.write_usize(frame.return_vm_module_index);
+            writer.write_usize(frame.return_function_index);
+            writer.write_block_type(&frame.return_frame_type);
+            writer.write_usize(frame.return_address);
+        }
+
+        writer.write_u32(self.resource.memory_blocks.len() as u32);
+        for memory_block in &self.resource.memory_blocks {
+            let data = memory_block.read_bytes(0, memory_block.get_byte_count());
+            writer.write_usize(data.len());
+            writer.write_bytes(data);
+        }
+
+        writer.write_u32(self.resource.tables.len() as u32);
+        for table in &self.resource.tables {
+            let elements = table.get_elements();
+            writer.write_u32(elements.len() as u32);
+            for element in elements {
+                writer.write_reference(*element);
+            }
+        }
+
+        writer.write_u32(self.resource.global_variables.len() as u32);
+        for global_variable in &self.resource.global_variables {
+            writer.write_value(&global_variable.get_value());
+        }
+
+        writer.write_u32(self.resource.data_segments.len() as u32);
+        for data_segment in &self.resource.data_segments {
+            writer.write_u8(data_segment.get_data().is_empty() as u8);
+        }
+
+        writer.write_u32(self.resource.element_segments.len() as u32);
+        for element_segment in &self.resource.element_segments {
+            writer.write_u8(element_segment.get_elements().is_empty() as u8);
+        }
+
+        writer.finish()
+    }
+
+    /// 恢复 VM 的运行状态
+    ///
+    /// 目标 VM 应该由跟保存快照时相同的模块以及本地模块新创建，
+    /// 恢复之前会先检查整个快照，出错时 VM 保持不变。
+    pub fn restore_snapshot(&mut self, data: &[u8]) -> Result<(), EngineError> {
+        let snapshot = Snapshot::read(data).map_err(EngineError::Snapshot)?;
+        snapshot.check(self).map_err(EngineError::Snapshot)?;
+        snapshot.apply(self);
+        Ok(())
+    }
+}
+
+/// 从快照数据解析出来的 VM 状态
+struct Snapshot {
+    modules: Vec<(String, u64)>,
+    native_modules: Vec<(String, u64)>,
+    status: Status,
+    fuel: Option<u64>,
+    last_hit_breakpoint: Option<Breakpoint>,
+    slots: Vec<u64>,
+    frames: Vec<ControlFrame>,
+    memory_blocks: Vec<Vec<u8>>,
+    tables: Vec<Vec<Option<u32>>>,
+    global_variables: Vec<Value>,
+    dropped_data_segments: Vec<bool>,
+    dropped_element_segments: Vec<bool>,
+}
+
+impl Snapshot {
+    fn read(data: &[u8]) -> Result<Self, SnapshotError> {
+        let mut reader = SnapshotReader::new(data);
+
+        if reader.read_bytes(MAGIC_NUMBER.len())? != MAGIC_NUMBER {
+            return Err(SnapshotError::InvalidMagicNumber);
+        }
+
+        let version = reader.read_u32()?;
+        if version != SNAPSHOT_VERSION {
+            return Err(SnapshotError::UnsupportedVersion(version));
+        }
+
+        let modules = reader.read_list(|reader| Ok((reader.read_string()?, reader.read_u64()?)))?;
+        let native_modules =
+            reader.read_list(|reader| Ok((reader.read_string()?, reader.read_u64()?)))?;
+
+        let status = Status {
+            local_pointer: reader.read_usize()?,
+            base_pointer: reader.read_usize()?,
+            vm_module_index: reader.read_usize()?,
+            function_index: reader.read_usize()?,
+            frame_type: reader.read_block_type()?,
+            address: reader.read_usize()?,
+        };
+
+        let fuel = reader.read_option(|reader| reader.read_u64())?;
+        let last_hit_breakpoint = reader.read_option(|reader| {
+            Ok(Breakpoint::new(
+                reader.read_usize()?,
+                reader.read_usize()?,
+                reader.read_usize()?,
+            ))
+        })?;
+
+        let slot_count = reader.read_usize()?;
+        let slots = (0..slot_count)
+            .map(|_| reader.read_u64())
+            .collect::<Result<Vec<u64>, SnapshotError>>()?;
+
+        let frame_count = reader.read_usize()?;
+        let frames = (0..frame_count)
+            .map(|_| reader.read_control_frame())
+            .collect::<Result<Vec<ControlFrame>, SnapshotError>>()?;
+
+        let memory_blocks = reader.read_list(|reader| {
+            let length = reader.read_usize()?;
+            Ok(reader.read_bytes(length)?.to_vec())
+        })?;
+
+        let tables =
+            reader.read_list(|reader| reader.read_list(|reader| reader.read_reference()))?;
+        let global_variables = reader.read_list(|reader| reader.read_value())?;
+        let dropped_data_segments = reader.read_list(|reader| reader.read_bool())?;
+        let dropped_element_segments = reader.read_list(|reader| reader.read_bool())?;
+
+        if !reader.is_end() {
+            return Err(SnapshotError::InvalidData);
+        }
+
+        Ok(Self {
+            modules,
+            native_modules,
+            status,
+            fuel,
+            last_hit_breakpoint,
+            slots,
+            frames,
+            memory_blocks,
+            tables,
+            global_variables,
+            dropped_data_segments,
+            dropped_element_segments,
+        })
+    }
+
+    /// 检查快照是否能恢复到指定的 VM
+    fn check(&self, vm: &VM) -> Result<(), SnapshotError> {
+        let vm_modules = &vm.resource.vm_modules;
+
+        for (index, (name, content_hash)) in self.modules.iter().enumerate() {
+            match vm_modules.get(index) {
+                Some(vm_module)
+                    if &vm_module.name == name && vm_module.content_hash == *content_hash => {}
+                _ => return Err(SnapshotError::ModuleMismatch(name.clone())),
+            }
+        }
+
+        if let Some(vm_module) = vm_modules.get(self.modules.len()) {
+            return Err(SnapshotError::ModuleMismatch(vm_module.name.clone()));
+        }
+
+        let native_modules = &vm.resource.native_modules;
+
+        for (index, (name, signature_hash)) in self.native_modules.iter().enumerate() {
+            match native_modules.get(index) {
+                Some(native_module)
+                    if &native_module.name == name
+                        && compute_native_module_hash(native_module) == *signature_hash => {}
+                _ => return Err(SnapshotError::NativeModuleMismatch(name.clone())),
+            }
+        }
+
+        if let Some(native_module) = native_modules.get(self.native_modules.len()) {
+            return Err(SnapshotError::NativeModuleMismatch(
+                native_module.name.clone(),
+            ));
+        }
+
+        // 模块一致时以下的检查都应该通过，检查它们是为了避免损坏的快照导致 VM 在执行时 panic
+        self.check_execution_state(vm)?;
+
+        let resource = &vm.resource;
+
+        if self.memory_blocks.len() != resource.memory_blocks.len()
+            || self.tables.len() != resource.tables.len()
+            || self.global_variables.len() != resource.global_variables.len()
+            || self.dropped_data_segments.len() != resource.data_segments.len()
+            || self.dropped_element_segments.len() != resource.element_segments.len()
+        {
+            return Err(SnapshotError::StateMismatch);
+        }
+
+        let page_size = PAGE_SIZE as usize;
+        for (data, memory_block) in self.memory_blocks.iter().zip(&resource.memory_blocks) {
+            if data.len() % page_size != 0
+                || !memory_block.is_valid_page_count((data.len() / page_size) as u32)
+            {
+                return Err(SnapshotError::StateMismatch);
+            }
+        }
+
+        for (elements, table) in self.tables.iter().zip(&resource.tables) {
+            if !table.is_valid_size(elements.len() as u32) {
+                return Err(SnapshotError::StateMismatch);
+            }
+        }
+
+        for (value, global_variable) in self.global_variables.iter().zip(&resource.global_variables)
+        {
+            if value.get_type() != global_variable.get_global_type().value_type {
+                return Err(SnapshotError::StateMismatch);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn check_execution_state(&self, vm: &VM) -> Result<(), SnapshotError> {
+        let slot_count = self.slots.len();
+        let is_valid_location =
+            |vm_module_index: usize, function_index: usize, address: usize| match vm
+                .resource
+                .vm_modules
+                .get(vm_module_index)
+            {
+                Some(vm_module) => {
+                    function_index < vm_module.function_items.len()
+                        && address <= vm_module.instructions.len()
+                }
+                None => false,
+            };
+
+        let status = &self.status;
+        if status.local_pointer > slot_count || status.base_pointer > slot_count {
+            return Err(SnapshotError::InvalidData);
+        }
+
+        // 待命状态的 VM 的 pc 不指向任何指令
+        if !self.frames.is_empty()
+            && !is_valid_location(
+                status.vm_module_index,
+                status.function_index,
+                status.address,
+            )
+        {
+            return Err(SnapshotError::InvalidData);
+        }
+
+        for frame in &self.frames {
+            if frame.frame_pointer > slot_count
+                || frame.return_local_pointer > slot_count
+                || frame.return_base_pointer > slot_count
+            {
+                return Err(SnapshotError::InvalidData);
+            }
+
+            // 宿主调用帧的返回状态是调用之前的 VM 状态，有可能不指向任何指令
+            if frame.kind != FrameKind::HostCall
+                && !is_valid_location(
+                    frame.return_vm_module_index,
+                    frame.return_function_index,
+                    frame.return_address,
+                )
+            {
+                return Err(SnapshotError::InvalidData);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn apply(self, vm: &mut VM) {
+        match self.fuel {
+            Some(fuel) => vm.set_fuel(fuel),
+            None => vm.disable_fuel(),
+        }
+
+        vm.replace_execution_state(
+            VMStack::from_slots_and_frames(self.slots, self.frames),
+            self.status,
+            self.last_hit_breakpoint,
+        );
+
+        let resource = &mut vm.resource;
+
+        for (data, memory_block) in self
+            .memory_blocks
+            .into_iter()
+            .zip(resource.memory_blocks.iter_mut())
+        {
+            memory_block.replace_data(data);
+        }
+
+        for (elements, table) in self.tables.into_iter().zip(resource.tables.iter_mut()) {
+            table.replace_elements(elements);
+        }
+
+        // 不可变的全局变量的值由模块决定，无需恢复
+        for (value, global_variable) in self
+            .global_variables
+            .into_iter()
+            .zip(resource.global_variables.iter_mut())
+        {
+            if global_variable.get_global_type().mutable {
+                let _ = global_variable.set_value(value);
+            }
+        }
+
+        for (dropped, data_segment) in self
+            .dropped_data_segments
+            .into_iter()
+            .zip(resource.data_segments.iter_mut())
+        {
+            if dropped {
+                data_segment.drop_data();
+            }
+        }
+
+        for (dropped, element_segment) in self
+            .dropped_element_segments
+            .into_iter()
+            .zip(resource.element_segments.iter_mut())
+        {
+            if dropped {
+                element_segment.drop_elements();
+            }
+        }
+    }
+}
+
+struct SnapshotWriter {
+    buffer: Vec<u8>,
+}
+
+impl SnapshotWriter {
+    fn new() -> Self {
+        Self { buffer: vec![] }
+    }
+
+    fn finish(self) -> Vec<u8> {
+        self.buffer
+    }
+
+    fn write_bytes(&mut self, data: &[u8]) {
+        self.buffer.extend_from_slice(data);
+    }
+
+    fn write_u8(&mut self, value: u8) {
+        self.buffer.push(value);
+    }
+
+    fn write_u32(&mut self, value: u32) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.write_bytes(&value.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, value: usize) {
+        self.write_u64(value as u64);
+    }
+
+    fn write_string(&mut self, value: &str) {
+        self.write_u32(value.len() as u32);
+        self.write_bytes(value.as_bytes());
+    }
+
+    fn write_reference(&mut self, option_index: Option<u32>) {
+        match option_index {
+            Some(index) => {
+                self.write_u8(1);
+                self.write_u32(index);
+            }
+            None => self.write_u8(0),
+        }
+    }
+
+    fn write_value(&mut self, value: &Value) {
+        self.write_u8(match value.get_type() {
+            ValueType::I32 => 0,
+            ValueType::I64 => 1,
+            ValueType::F32 => 2,
+            ValueType::F64 => 3,
+            ValueType::FuncRef => 4,
+            ValueType::ExternRef => 5,
+        });
+        self.write_u64(value_to_slot(value));
+    }
+
+    fn write_block_type(&mut self, block_type: &BlockType) {
+        match block_type {
+            BlockType::ResultI32 => self.write_u8(0),
+            BlockType::ResultI64 => self.write_u8(1),
+            BlockType::ResultF32 => self.write_u8(2),
+            BlockType::ResultF64 => self.write_u8(3),
+            BlockType::ResultFuncRef => self.write_u8(4),
+            BlockType::ResultExternRef => self.write_u8(5),
+            BlockType::ResultEmpty => self.write_u8(6),
+            BlockType::TypeIndex(type_index) => {
+                self.write_u8(7);
+                self.write_u32(*type_index);
+            }
+        }
+    }
+}
+
+struct SnapshotReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> SnapshotReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self { data, position: 0 }
+    }
+
+    fn is_end(&self) -> bool {
+        self.position == self.data.len()
+    }
+
+    fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], SnapshotError> {
+        if length > self.data.len() - self.position {
+            return Err(SnapshotError::UnexpectedEnd);
+        }
+
+        let bytes = &self.data[self.position..self.position + length];
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn read_u8(&mut self) -> Result<u8, SnapshotError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    fn read_bool(&mut self) -> Result<bool, SnapshotError> {
+        match self.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(SnapshotError::InvalidData),
+        }
+    }
+
+    fn read_u32(&mut self) -> Result<u32, SnapshotError> {
+        Ok(u32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, SnapshotError> {
+        Ok(u64::from_le_bytes(self.read_bytes(8)?.try_into().unwrap()))
+    }
+
+    fn read_usize(&mut self) -> Result<usize, SnapshotError> {
+        usize::try_from(self.read_u64()?).map_err(|_| SnapshotError::InvalidData)
+    }
+
+    fn read_string(&mut self) -> Result<String, SnapshotError> {
+        let length = self.read_u32()? as usize;
+        let bytes = self.read_bytes(length)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| SnapshotError::InvalidData)
+    }
+
+    /// 读取数量（u32）以及各个元素
+    fn read_list<T, F>(&mut self, mut read_item: F) -> Result<Vec<T>, SnapshotError>
+    where
+        F: FnMut(&mut Self) -> Result<T, SnapshotError>,
+    {
+        let count = self.read_u32()?;
+        (0..count).map(|_| read_item(self)).collect()
+    }
+
+    fn read_option<T, F>(&mut self, read_value: F) -> Result<Option<T>, SnapshotError>
+    where
+        F: FnOnce(&mut Self) -> Result<T, SnapshotError>,
+    {
+        if self.read_bool()? {
+            Ok(Some(read_value(self)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn read_reference(&mut self) -> Result<Option<u32>, SnapshotError> {
+        self.read_option(|reader| reader.read_u32())
+    }
+
+    fn read_value(&mut self) -> Result<Value, SnapshotError> {
+        let value_type = match self.read_u8()? {
+            0 => ValueType::I32,
+            1 => ValueType::I64,
+            2 => ValueType::F32,
+            3 => ValueType::F64,
+            4 => ValueType::FuncRef,
+            5 => ValueType::ExternRef,
+            _ => return Err(SnapshotError::InvalidData),
+        };
+
+        Ok(slot_to_value(self.read_u64()?, &value_type))
+    }
+
+    fn read_block_type(&mut self) -> Result<BlockType, SnapshotError> {
+        let block_type = match self.read_u8()? {
+            0 => BlockType::ResultI32,
+            1 => BlockType::ResultI64,
+            2 => BlockType::ResultF32,
+            3 => BlockType::ResultF64,
+            4 => BlockType::ResultFuncRef,
+            5 => BlockType::ResultExternRef,
+            6 => BlockType::ResultEmpty,
+            7 => BlockType::TypeIndex(self.read_u32()?),
+            _ => return Err(SnapshotError::InvalidData),
+        };
+
+        Ok(block_type)
+    }
+
+    fn read_control_frame(&mut self) -> Result<ControlFrame, SnapshotError> {
+        let kind = match self.read_u8()? {
+            0 => FrameKind::HostCall,
+            1 => FrameKind::Call,
+            2 => FrameKind::Block,
+            _ => return Err(SnapshotError::InvalidData),
+        };
+
+        Ok(ControlFrame {
+            kind,
+            frame_pointer: self.read_usize()?,
+            return_local_pointer: self.read_usize()?,
+            return_base_pointer: self.read_usize()?,
+            return_vm_module_index: self.read_usize()?,
+            return_function_index: self.read_usize()?,
+            return_frame_type: self.read_block_type()?,
+            return_address: self.read_usize()?,
+        })
+    }
+}
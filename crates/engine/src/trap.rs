@@ -0,0 +1,247 @@
+// Copyright (c) 2022 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! # 陷阱（trap）
+//!
+//! 当指令的执行出现 WebAssembly 规范所定义的运行时错误时（比如执行了 `unreachable 指令`、
+//! 整数除以 0 等），VM 会中止执行并抛出陷阱，即返回 `EngineError::Trap(Trap)`。
+//!
+//! 陷阱除了包含错误的种类（TrapCode），还包含陷阱发生时的调用栈回溯信息（Backtrace）。
+//! 回溯信息通过遍历控制栈的调用帧而获得，并借助模块的 `名称段` 以及导出项将函数索引
+//! 转换为函数名称。
+//!
+//! https://webassembly.github.io/spec/core/intro/overview.html#trap
+
+use std::fmt::Display;
+
+use crate::{
+    error::EngineError,
+    object::FunctionItem,
+    vm::VM,
+    vm_stack::{FrameKind, StackUnderflow},
+};
+
+/// 陷阱的种类
+///
+/// 跟 WebAssembly 规范（及其测试用例）所定义的陷阱种类一一对应
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum TrapCode {
+    /// 执行了 `unreachable 指令`
+    Unreachable,
+
+    /// 整数除以 0（包括求余数运算）
+    IntegerDivideByZero,
+
+    /// 整数运算溢出，比如 `i32.div_s` 当中 -2^31 / -1，以及浮点数截断为整数时超出范围
+    IntegerOverflow,
+
+    /// 将 NaN 截断为整数
+    InvalidConversionToInteger,
+
+    /// 内存访问越界
+    MemoryOutOfBounds,
+
+    /// 表访问越界，比如 `table.init` 和 `table.copy` 指令的范围超出了表或者元素段
+    TableOutOfBounds,
+
+    /// 间接函数调用时，表的元素为空或者元素的索引超出范围
+    UndefinedElement,
+
+    /// 间接函数调用时，目标函数的类型跟指令所指定的类型不一致
+    IndirectCallTypeMismatch,
+
+    /// 调用帧的数量或者栈的总大小超出了限制
+    StackExhausted,
+
+    /// 弹出或者查看操作数时操作数栈的操作数不足，通过验证的模块不会出现这种陷阱
+    StackUnderflow,
+}
+
+impl Display for TrapCode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let message = match self {
+            TrapCode::Unreachable => "unreachable",
+            TrapCode::IntegerDivideByZero => "integer divide by zero",
+            TrapCode::IntegerOverflow => "integer overflow",
+            TrapCode::InvalidConversionToInteger => "invalid conversion to integer",
+            TrapCode::MemoryOutOfBounds => "out of bounds memory access",
+            TrapCode::TableOutOfBounds => "out of bounds table access",
+            TrapCode::UndefinedElement => "undefined element",
+            TrapCode::IndirectCallTypeMismatch => "indirect call type mismatch",
+            TrapCode::StackExhausted => "call stack exhausted",
+            TrapCode::StackUnderflow => "operand stack underflow",
+        };
+
+        write!(f, "{}", message)
+    }
+}
+
+/// 调用栈回溯信息当中的一帧
+///
+/// 只记录函数调用帧，结构块的栈帧会被忽略。
+#[derive(Debug, PartialEq, Clone)]
+pub struct BacktraceFrame {
+    pub vm_module_index: usize,
+    pub function_index: usize,
+
+    /// 指令在模块指令列表里的位置
+    /// 对于最顶端的一帧，它是引发陷阱的指令，对于其他帧，它是 `call 指令`。
+    pub address: usize,
+
+    pub module_name: String,
+
+    /// 函数的名称
+    /// 先从 `名称段` 里查找，如果找不到则使用函数的导出名称
+    pub function_name: Option<String>,
+
+    /// 指令在函数内部的位置，即相对函数第一条指令的偏移值
+    pub instruction_offset: usize,
+}
+
+impl Display for BacktraceFrame {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.function_name {
+            Some(function_name) => write!(
+                f,
+                "{}::{} (function #{}) at instruction #{}",
+                self.module_name, function_name, self.function_index, self.instruction_offset
+            ),
+            None => write!(
+                f,
+                "{}::#{} at instruction #{}",
+                self.module_name, self.function_index, self.instruction_offset
+            ),
+        }
+    }
+}
+
+/// 调用栈回溯信息
+///
+/// 第一帧是陷阱发生时所在的函数，最后一帧是宿主调用的函数
+#[derive(Debug, PartialEq, Clone)]
+pub struct Backtrace {
+    pub frames: Vec<BacktraceFrame>,
+}
+
+impl Display for Backtrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let lines = self
+            .frames
+            .iter()
+            .enumerate()
+            .map(|(index, frame)| format!("  #{} {}", index, frame))
+            .collect::<Vec<String>>();
+
+        write!(f, "backtrace:\n{}", lines.join("\n"))
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Trap {
+    pub code: TrapCode,
+    pub backtrace: Backtrace,
+}
+
+impl Display for Trap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.backtrace.frames.first() {
+            Some(frame) => write!(f, "trap: {}, in {}", self.code, frame),
+            None => write!(f, "trap: {}", self.code),
+        }
+    }
+}
+
+/// 操作数栈的操作数不足时所产生的陷阱
+///
+/// VMStack 无法获取调用栈回溯信息，所以这里的陷阱不包含回溯信息，
+/// 由 `VM::step` 在指令返回之后补上。
+impl From<StackUnderflow> for EngineError {
+    fn from(_: StackUnderflow) -> Self {
+        EngineError::Trap(Trap {
+            code: TrapCode::StackUnderflow,
+            backtrace: Backtrace { frames: vec![] },
+        })
+    }
+}
+
+/// 构造陷阱错误
+///
+/// 注意需要在 VM 的 pc 值更新之前调用此函数，即 pc 应该指向引发陷阱的指令。
+pub fn make_trap_engine_error(vm: &VM, trap_code: TrapCode) -> EngineError {
+    EngineError::Trap(Trap {
+        code: trap_code,
+        backtrace: get_backtrace(vm),
+    })
+}
+
+/// 通过遍历控制栈的调用帧，获取当前的调用栈回溯信息
+pub fn get_backtrace(vm: &VM) -> Backtrace {
+    let status = &vm.status;
+    let control_frames = vm.stack.get_frames();
+
+    let mut frames: Vec<BacktraceFrame> = vec![];
+
+    // VM 尚未开始任何函数调用
+    if control_frames.is_empty() {
+        return Backtrace { frames };
+    }
+
+    frames.push(create_backtrace_frame(
+        vm,
+        status.vm_module_index,
+        status.function_index,
+        status.address,
+    ));
+
+    for control_frame in control_frames.iter().rev() {
+        match control_frame.kind {
+            // 由宿主调用的函数的调用帧，回溯到此为止
+            FrameKind::HostCall => break,
+            FrameKind::Call => {
+                // 返回地址是 `call 指令` 的下一条指令
+                frames.push(create_backtrace_frame(
+                    vm,
+                    control_frame.return_vm_module_index,
+                    control_frame.return_function_index,
+                    control_frame.return_address - 1,
+                ));
+            }
+            // 结构块帧不产生回溯记录
+            FrameKind::Block => {}
+        }
+    }
+
+    Backtrace { frames }
+}
+
+fn create_backtrace_frame(
+    vm: &VM,
+    vm_module_index: usize,
+    function_index: usize,
+    address: usize,
+) -> BacktraceFrame {
+    let vm_module = &vm.resource.vm_modules[vm_module_index];
+
+    let function_name = vm_module
+        .name_package
+        .get_function_name(&(function_index as u32))
+        .cloned()
+        .or_else(|| vm_module.find_export_function_name(function_index));
+
+    let instruction_offset = match &vm_module.function_items[function_index] {
+        FunctionItem::Normal { start_address, .. } => address - start_address,
+        FunctionItem::Native { .. } => 0,
+    };
+
+    BacktraceFrame {
+        vm_module_index,
+        function_index,
+        address,
+        module_name: vm_module.name.clone(),
+        function_name,
+        instruction_offset,
+    }
+}
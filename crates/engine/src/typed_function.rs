@@ -0,0 +1,208 @@
+// Copyright (c) 2022 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! # 带类型的函数句柄
+//!
+//! 通过 `VM::get_typed_function` 获取模块的导出函数，获取时检查一次函数的签名，
+//! 之后调用时直接使用 Rust 的数值以及元组作为实参和返回值，比如：
+//!
+//! ```ignore
+//! let add = vm.get_typed_function::<(i32, i64), f64>("module", "add")?;
+//! let result: f64 = add.call(&mut vm, (1, 2))?;
+//! ```
+//!
+//! - 单个参数或者单个返回值可以直接使用数据类型，比如 `i32`；
+//! - 没有参数或者没有返回值时使用 `()`；
+//! - 多个参数或者多个返回值使用元组，元组最多支持 8 个元素。
+
+use std::marker::PhantomData;
+
+use anvm_ast::types::{Value, ValueType};
+
+use crate::{
+    error::{EngineError, TypeMismatch},
+    vm::VM,
+};
+
+/// 可以跟 `Value` 互相转换的 Rust 数据类型
+pub trait WasmType: Sized {
+    fn value_type() -> ValueType;
+    fn into_value(self) -> Value;
+
+    /// 数值的数据类型不符合时返回 None
+    fn from_value(value: Value) -> Option<Self>;
+}
+
+macro_rules! impl_wasm_type {
+    ($type:ty, $variant:ident) => {
+        impl WasmType for $type {
+            fn value_type() -> ValueType {
+                ValueType::$variant
+            }
+
+            fn into_value(self) -> Value {
+                Value::$variant(self)
+            }
+
+            fn from_value(value: Value) -> Option<Self> {
+                match value {
+                    Value::$variant(v) => Some(v),
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+impl_wasm_type!(i32, I32);
+impl_wasm_type!(i64, I64);
+impl_wasm_type!(f32, F32);
+impl_wasm_type!(f64, F64);
+
+/// 函数的参数列表
+pub trait WasmParams {
+    fn value_types() -> Vec<ValueType>;
+    fn into_values(self) -> Vec<Value>;
+}
+
+/// 函数的返回值列表
+pub trait WasmResults: Sized {
+    fn value_types() -> Vec<ValueType>;
+
+    /// 数值的数量或者数据类型不符合时返回 None
+    fn from_values(values: Vec<Value>) -> Option<Self>;
+}
+
+impl<T: WasmType> WasmParams for T {
+    fn value_types() -> Vec<ValueType> {
+        vec![T::value_type()]
+    }
+
+    fn into_values(self) -> Vec<Value> {
+        vec![self.into_value()]
+    }
+}
+
+impl<T: WasmType> WasmResults for T {
+    fn value_types() -> Vec<ValueType> {
+        vec![T::value_type()]
+    }
+
+    fn from_values(values: Vec<Value>) -> Option<Self> {
+        match values[..] {
+            [value] => T::from_value(value),
+            _ => None,
+        }
+    }
+}
+
+macro_rules! impl_wasm_tuple {
+    ($($name:ident),*) => {
+        impl<$($name: WasmType),*> WasmParams for ($($name,)*) {
+            fn value_types() -> Vec<ValueType> {
+                vec![$($name::value_type()),*]
+            }
+
+            #[allow(non_snake_case)]
+            fn into_values(self) -> Vec<Value> {
+                let ($($name,)*) = self;
+                vec![$($name.into_value()),*]
+            }
+        }
+
+        impl<$($name: WasmType),*> WasmResults for ($($name,)*) {
+            fn value_types() -> Vec<ValueType> {
+                vec![$($name::value_type()),*]
+            }
+
+            #[allow(non_snake_case, unused_mut, unused_variables)]
+            fn from_values(values: Vec<Value>) -> Option<Self> {
+                let names: &[&str] = &[$(stringify!($name)),*];
+                if values.len() != names.len() {
+                    return None;
+                }
+
+                let mut iter = values.into_iter();
+                Some(($($name::from_value(iter.next()?)?,)*))
+            }
+        }
+    };
+}
+
+impl_wasm_tuple!();
+impl_wasm_tuple!(A);
+impl_wasm_tuple!(A, B);
+impl_wasm_tuple!(A, B, C);
+impl_wasm_tuple!(A, B, C, D);
+impl_wasm_tuple!(A, B, C, D, E);
+impl_wasm_tuple!(A, B, C, D, E, F);
+impl_wasm_tuple!(A, B, C, D, E, F, G);
+impl_wasm_tuple!(A, B, C, D, E, F, G, H);
+
+/// 带类型的函数句柄
+///
+/// 句柄只记录函数所在的模块索引以及函数索引，不持有 VM，所以调用时需要传入 VM。
+/// 句柄只能用于创建它的 VM（或者由同一个已编译模块实例化的其他 VM）。
+pub struct TypedFunction<Params, Results> {
+    vm_module_index: usize,
+    function_index: usize,
+    _marker: PhantomData<fn(Params) -> Results>,
+}
+
+impl<Params, Results> Clone for TypedFunction<Params, Results> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Params, Results> Copy for TypedFunction<Params, Results> {}
+
+impl<Params, Results> TypedFunction<Params, Results>
+where
+    Params: WasmParams,
+    Results: WasmResults,
+{
+    /// 创建函数句柄，调用者需要确保函数的签名跟 Params 和 Results 一致
+    pub(crate) fn new(vm_module_index: usize, function_index: usize) -> Self {
+        Self {
+            vm_module_index,
+            function_index,
+            _marker: PhantomData,
+        }
+    }
+
+    pub fn call(&self, vm: &mut VM, params: Params) -> Result<Results, EngineError> {
+        let values = vm.eval_function_by_index(
+            self.vm_module_index,
+            self.function_index,
+            &params.into_values(),
+        )?;
+
+        // 函数的签名虽然已经在获取句柄时检查过，但句柄有可能被用在另外一个 VM 上，
+        // 所以仍需检查返回值的数量以及数据类型
+        let value_types = values
+            .iter()
+            .map(|value| value.get_type())
+            .collect::<Vec<ValueType>>();
+
+        Results::from_values(values).ok_or_else(|| {
+            EngineError::TypeMismatch(TypeMismatch::TypedFunctionResultTypeMismatch {
+                vm_module_index: self.vm_module_index,
+                function_index: self.function_index,
+                result_types: Results::value_types(),
+                value_types,
+            })
+        })
+    }
+
+    pub fn get_vm_module_index(&self) -> usize {
+        self.vm_module_index
+    }
+
+    pub fn get_function_index(&self) -> usize {
+        self.function_index
+    }
+}
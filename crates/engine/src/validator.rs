@@ -0,0 +1,2051 @@
+// Copyright (c) 2022 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! # 模块验证器
+//!
+//! 在模块实例化（以及执行）之前检查模块是否有效，以便尽早发现错误，
+//! 而不是等到执行到错误的指令时才发现。验证的内容包括：
+//!
+//! - 各种索引（类型、函数、表、内存块、全局变量、局部变量、标签、元素段以及数据段）
+//!   是否超出范围；
+//! - 表和内存块的限制值（limit）；
+//! - 常量表达式，即全局变量的初始值、元素段和数据段的偏移值，以及元素段的元素表达式；
+//! - 导出项的名称是否重复，以及 start 函数的类型；
+//! - 函数的指令序列，即模拟操作数栈以及控制栈（control stack），检查每条指令的
+//!   操作数类型，以及结构块的参数和结果的类型。
+//!
+//! 验证失败时返回第一个错误，如果错误位于函数体内，则同时返回函数的索引以及
+//! 指令在函数指令序列里的索引。
+//!
+//! 函数指令序列的验证算法跟 WebAssembly 规范附录所描述的一致：
+//! 操作数栈里的值的类型可能是 "未知的"（即这里的 None），未知类型出现在
+//! `unreachable`、`br`、`br_table` 和 `return` 等指令之后，此时操作数栈
+//! 可以弹出任意类型的值（栈多态，stack-polymorphic）。
+//!
+//! https://webassembly.github.io/spec/core/valid/index.html
+//! https://webassembly.github.io/spec/core/appendix/algorithm.html
+
+use std::{collections::HashSet, fmt::Display};
+
+use anvm_ast::{
+    ast::{
+        self, DataMode, ElementItems, ElementMode, ExportDescriptor, FunctionType, GlobalType,
+        ImportDescriptor, Limit, TableType, TypeItem,
+    },
+    instruction::{BlockType, Instruction, MemoryArgument},
+    types::ValueType,
+};
+
+/// WebAssembly 约定内存块最大只能有 65536 个页面
+const MAX_MEMORY_PAGES: u32 = 65536;
+
+/// 验证错误
+#[derive(Debug, PartialEq, Clone)]
+pub struct ValidationError {
+    pub location: ValidationLocation,
+    pub kind: ValidationErrorKind,
+}
+
+/// 验证错误所在的位置
+///
+/// 除了函数，其余项目的索引均为项目在其所在段里的索引（即不包括导入项）。
+#[derive(Debug, PartialEq, Clone)]
+pub enum ValidationLocation {
+    Import(usize),
+    Table(usize),
+    MemoryBlock(usize),
+    Global(usize),
+    Export(usize),
+    Start,
+    Element(usize),
+    Data(usize),
+
+    /// 内部函数的数量跟代码项目的数量不一致时，无法确定具体的位置
+    Code,
+
+    /// 函数的索引包括导入的函数，指令的索引是指令在函数的指令序列当中的索引
+    Function {
+        function_index: usize,
+        instruction_index: usize,
+    },
+}
+
+/// 索引的种类
+#[derive(Debug, PartialEq, Clone)]
+pub enum IndexKind {
+    Type,
+    Function,
+    Table,
+    MemoryBlock,
+    Global,
+    Local,
+    Label,
+    Element,
+    Data,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ValidationErrorKind {
+    IndexOutOfRange {
+        index_kind: IndexKind,
+        index: u32,
+        count: usize,
+    },
+    LimitMinExceedsMax(/* min */ u32, /* max */ u32),
+    LimitExceedsMaximum(/* value */ u32, /* max allowed */ u32),
+    FunctionCountMismatch {
+        functions_count: usize,
+        codes_count: usize,
+    },
+    DuplicateExportName(String),
+    InvalidStartFunctionType,
+
+    /// 操作数的类型跟指令所期望的不一致
+    TypeMismatch {
+        expected: ValueType,
+        actual: ValueType,
+    },
+
+    /// 操作数栈的值不足
+    NotEnoughOperands,
+
+    /// 结构块（或者函数、常量表达式）结束时，操作数栈剩余的值的数量跟结果的数量不一致
+    ResultCountMismatch {
+        expected: usize,
+        actual: usize,
+    },
+
+    /// 没有 `else` 分支的 `if` 结构块，其参数类型和结果类型必须一致
+    IfWithoutElseTypeMismatch,
+
+    ImmutableGlobalVariable(/* global_variable_index */ u32),
+    InvalidAlignment {
+        align: u32,
+        max_align: u32,
+    },
+
+    /// 不带类型的 `select` 指令的操作数只能是数值类型
+    InvalidSelectOperandType(ValueType),
+
+    /// 带类型的 `select` 指令必须有且只有一个类型
+    InvalidSelectTypeCount(usize),
+    BranchTableArityMismatch,
+
+    /// 函数体里的 `ref.func` 指令所引用的函数必须事先在元素段、导出项或者
+    /// 全局变量的初始值里声明过
+    UndeclaredFunctionReference(/* function_index */ u32),
+
+    TableElementTypeMismatch {
+        expected: ValueType,
+        actual: ValueType,
+    },
+    ElseWithoutIf,
+
+    /// 函数的 `end` 指令之后还有其他指令
+    InstructionAfterEnd,
+    MissingEnd,
+
+    /// 常量表达式里出现了不允许的指令
+    NonConstantInstruction(Instruction),
+
+    /// 常量表达式里的 `global.get` 指令只能读取不可变的全局变量
+    MutableGlobalInConstantExpression(/* global_variable_index */ u32),
+}
+
+impl Display for ValidationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}, {}", self.location, self.kind)
+    }
+}
+
+impl Display for ValidationLocation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationLocation::Import(index) => write!(f, "import item #{}", index),
+            ValidationLocation::Table(index) => write!(f, "table #{}", index),
+            ValidationLocation::MemoryBlock(index) => write!(f, "memory block #{}", index),
+            ValidationLocation::Global(index) => write!(f, "global item #{}", index),
+            ValidationLocation::Export(index) => write!(f, "export item #{}", index),
+            ValidationLocation::Start => write!(f, "start function"),
+            ValidationLocation::Element(index) => write!(f, "element item #{}", index),
+            ValidationLocation::Data(index) => write!(f, "data item #{}", index),
+            ValidationLocation::Code => write!(f, "code section"),
+            ValidationLocation::Function {
+                function_index,
+                instruction_index,
+            } => write!(
+                f,
+                "function #{}, instruction #{}",
+                function_index, instruction_index
+            ),
+        }
+    }
+}
+
+impl Display for IndexKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            IndexKind::Type => "type",
+            IndexKind::Function => "function",
+            IndexKind::Table => "table",
+            IndexKind::MemoryBlock => "memory block",
+            IndexKind::Global => "global variable",
+            IndexKind::Local => "local variable",
+            IndexKind::Label => "label",
+            IndexKind::Element => "element",
+            IndexKind::Data => "data",
+        };
+
+        write!(f, "{}", name)
+    }
+}
+
+impl Display for ValidationErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ValidationErrorKind::IndexOutOfRange {
+                index_kind,
+                index,
+                count,
+            } => write!(
+                f,
+                "the {} index {} is out of range, total: {}",
+                index_kind, index, count
+            ),
+            ValidationErrorKind::LimitMinExceedsMax(min, max) => write!(
+                f,
+                "the minimum {} of the limit is greater than the maximum {}",
+                min, max
+            ),
+            ValidationErrorKind::LimitExceedsMaximum(value, max_allowed) => write!(
+                f,
+                "the limit {} exceeds the maximum allowed {}",
+                value, max_allowed
+            ),
+            ValidationErrorKind::FunctionCountMismatch {
+                functions_count,
+                codes_count,
+            } => write!(
+                f,
+                "the number of functions {} does not match the number of codes {}",
+                functions_count, codes_count
+            ),
+            ValidationErrorKind::DuplicateExportName(name) => {
+                write!(f, "duplicate export name \"{}\"", name)
+            }
+            ValidationErrorKind::InvalidStartFunctionType => write!(
+                f,
+                "the start function should have no parameters and no results"
+            ),
+            ValidationErrorKind::TypeMismatch { expected, actual } => write!(
+                f,
+                "type mismatch, expected: {}, actual: {}",
+                expected, actual
+            ),
+            ValidationErrorKind::NotEnoughOperands => write!(f, "not enough operands"),
+            ValidationErrorKind::ResultCountMismatch { expected, actual } => write!(
+                f,
+                "the number of results mismatch, expected: {}, actual: {}",
+                expected, actual
+            ),
+            ValidationErrorKind::IfWithoutElseTypeMismatch => write!(
+                f,
+                "the parameters and results of \"if\" block without \"else\" should be the same"
+            ),
+            ValidationErrorKind::ImmutableGlobalVariable(global_variable_index) => write!(
+                f,
+                "the global variable {} is immutable",
+                global_variable_index
+            ),
+            ValidationErrorKind::InvalidAlignment { align, max_align } => write!(
+                f,
+                "the alignment {} exceeds the natural alignment {}",
+                align, max_align
+            ),
+            ValidationErrorKind::InvalidSelectOperandType(value_type) => write!(
+                f,
+                "the operand of untyped \"select\" should be numeric, actual: {}",
+                value_type
+            ),
+            ValidationErrorKind::InvalidSelectTypeCount(count) => write!(
+                f,
+                "typed \"select\" should have exactly one type, actual: {}",
+                count
+            ),
+            ValidationErrorKind::BranchTableArityMismatch => write!(
+                f,
+                "the target blocks of \"br_table\" should have the same number of results"
+            ),
+            ValidationErrorKind::UndeclaredFunctionReference(function_index) => write!(
+                f,
+                "the function {} is not declared as a reference",
+                function_index
+            ),
+            ValidationErrorKind::TableElementTypeMismatch { expected, actual } => write!(
+                f,
+                "table element type mismatch, expected: {}, actual: {}",
+                expected, actual
+            ),
+            ValidationErrorKind::ElseWithoutIf => write!(f, "\"else\" without matching \"if\""),
+            ValidationErrorKind::InstructionAfterEnd => {
+                write!(f, "instruction after the end of function")
+            }
+            ValidationErrorKind::MissingEnd => write!(f, "missing \"end\" instruction"),
+            ValidationErrorKind::NonConstantInstruction(instruction) => write!(
+                f,
+                "instruction \"{:?}\" is not allowed in the constant expression",
+                instruction
+            ),
+            ValidationErrorKind::MutableGlobalInConstantExpression(global_variable_index) => {
+                write!(
+                    f,
+                    "the mutable global variable {} is not allowed in the constant expression",
+                    global_variable_index
+                )
+            }
+        }
+    }
+}
+
+/// 验证模块
+pub fn validate(ast_module: &ast::Module) -> Result<(), ValidationError> {
+    let function_types = ast_module
+        .type_items
+        .iter()
+        .map(|item| match item {
+            TypeItem::FunctionType(function_type) => function_type,
+        })
+        .collect::<Vec<&FunctionType>>();
+
+    // 构建模块的上下文，即各种对象的列表（导入的对象在前，模块内定义的对象在后）
+    let mut context = ModuleContext {
+        function_types,
+        function_type_indexes: vec![],
+        tables: vec![],
+        memory_blocks_count: 0,
+        global_types: vec![],
+        element_types: vec![],
+        data_count: ast_module.data_items.len(),
+        declared_function_references: HashSet::new(),
+    };
+
+    // 导入项
+    for (import_index, import_item) in ast_module.import_items.iter().enumerate() {
+        let location = ValidationLocation::Import(import_index);
+
+        match &import_item.import_descriptor {
+            ImportDescriptor::FunctionTypeIndex(type_index) => {
+                context
+                    .check_type_index(*type_index)
+                    .map_err(|kind| make_error(location, kind))?;
+                context.function_type_indexes.push(*type_index);
+            }
+            ImportDescriptor::TableType(table_type) => {
+                check_limit(&table_type.limit, u32::MAX)
+                    .map_err(|kind| make_error(location, kind))?;
+                context.tables.push(table_type);
+            }
+            ImportDescriptor::MemoryType(memory_type) => {
+                check_limit(&memory_type.limit, MAX_MEMORY_PAGES)
+                    .map_err(|kind| make_error(location, kind))?;
+                context.memory_blocks_count += 1;
+            }
+            ImportDescriptor::GlobalType(global_type) => {
+                context.global_types.push(global_type);
+            }
+        }
+    }
+
+    let import_function_count = context.function_type_indexes.len();
+    let import_global_variable_count = context.global_types.len();
+
+    // 内部函数
+    if ast_module.internal_function_to_type_index_list.len() != ast_module.code_items.len() {
+        return Err(make_error(
+            ValidationLocation::Code,
+            ValidationErrorKind::FunctionCountMismatch {
+                functions_count: ast_module.internal_function_to_type_index_list.len(),
+                codes_count: ast_module.code_items.len(),
+            },
+        ));
+    }
+
+    for (internal_function_index, type_index) in ast_module
+        .internal_function_to_type_index_list
+        .iter()
+        .enumerate()
+    {
+        context.check_type_index(*type_index).map_err(|kind| {
+            make_error(
+                ValidationLocation::Function {
+                    function_index: import_function_count + internal_function_index,
+                    instruction_index: 0,
+                },
+                kind,
+            )
+        })?;
+        context.function_type_indexes.push(*type_index);
+    }
+
+    // 表
+    for (table_index, table_type) in ast_module.tables.iter().enumerate() {
+        check_limit(&table_type.limit, u32::MAX)
+            .map_err(|kind| make_error(ValidationLocation::Table(table_index), kind))?;
+        context.tables.push(table_type);
+    }
+
+    // 内存块
+    for (memory_block_index, memory_type) in ast_module.memory_blocks.iter().enumerate() {
+        check_limit(&memory_type.limit, MAX_MEMORY_PAGES).map_err(|kind| {
+            make_error(ValidationLocation::MemoryBlock(memory_block_index), kind)
+        })?;
+        context.memory_blocks_count += 1;
+    }
+
+    // 元素段的类型
+    for element_item in &ast_module.element_items {
+        let element_type = match &element_item.items {
+            ElementItems::FunctionIndices(_) => ValueType::FuncRef,
+            ElementItems::Expressions(ref_type, _) => ref_type.clone(),
+        };
+        context.element_types.push(element_type);
+    }
+
+    // 收集已声明的函数引用，即出现在元素段、导出项以及全局变量初始值里的函数
+    collect_declared_function_references(ast_module, &mut context);
+
+    // 全局变量
+    //
+    // 初始值常量表达式里的 `global.get` 指令只能读取在当前全局变量之前
+    // 定义（包括导入）的全局变量
+    for (global_item_index, global_item) in ast_module.global_items.iter().enumerate() {
+        let location = ValidationLocation::Global(global_item_index);
+
+        validate_constant_expression(
+            &context,
+            &global_item.initialize_instruction_items,
+            &global_item.global_type.value_type,
+            import_global_variable_count + global_item_index,
+        )
+        .map_err(|kind| make_error(location, kind))?;
+
+        context.global_types.push(&global_item.global_type);
+    }
+
+    // 导出项
+    let mut export_names: HashSet<&str> = HashSet::new();
+    for (export_index, export_item) in ast_module.export_items.iter().enumerate() {
+        let location = ValidationLocation::Export(export_index);
+
+        match export_item.export_descriptor {
+            ExportDescriptor::FunctionIndex(function_index) => {
+                context.check_function_index(function_index)
+            }
+            ExportDescriptor::TableIndex(table_index) => context.check_table_index(table_index),
+            ExportDescriptor::MemoryBlockIndex(memory_block_index) => {
+                context.check_memory_block_index(memory_block_index)
+            }
+            ExportDescriptor::GlobalItemIndex(global_variable_index) => context
+                .check_global_variable_index(global_variable_index)
+                .map(|_| ()),
+        }
+        .map_err(|kind| make_error(location.clone(), kind))?;
+
+        if !export_names.insert(&export_item.name) {
+            return Err(make_error(
+                location,
+                ValidationErrorKind::DuplicateExportName(export_item.name.clone()),
+            ));
+        }
+    }
+
+    // start 函数
+    if let Some(start_function_index) = ast_module.start_function_index {
+        let location = ValidationLocation::Start;
+        let function_type = context
+            .get_function_type(start_function_index)
+            .map_err(|kind| make_error(location.clone(), kind))?;
+
+        if !function_type.params.is_empty() || !function_type.results.is_empty() {
+            return Err(make_error(
+                location,
+                ValidationErrorKind::InvalidStartFunctionType,
+            ));
+        }
+    }
+
+    let global_variables_count = context.global_types.len();
+
+    // 元素段
+    for (element_index, element_item) in ast_module.element_items.iter().enumerate() {
+        validate_element_item(&context, element_item, global_variables_count)
+            .map_err(|kind| make_error(ValidationLocation::Element(element_index), kind))?;
+    }
+
+    // 数据段
+    for (data_index, data_item) in ast_module.data_items.iter().enumerate() {
+        if let DataMode::Active {
+            memory_block_index,
+            offset_instruction_items,
+        } = &data_item.mode
+        {
+            context
+                .check_memory_block_index(*memory_block_index)
+                .and_then(|_| {
+                    validate_constant_expression(
+                        &context,
+                        offset_instruction_items,
+                        &ValueType::I32,
+                        global_variables_count,
+                    )
+                })
+                .map_err(|kind| make_error(ValidationLocation::Data(data_index), kind))?;
+        }
+    }
+
+    // 函数体
+    for (internal_function_index, code_item) in ast_module.code_items.iter().enumerate() {
+        let function_index = import_function_count + internal_function_index;
+        let type_index = context.function_type_indexes[function_index];
+        let function_type = context.function_types[type_index as usize];
+
+        // 局部变量包括函数的参数
+        let mut local_variable_types = function_type.params.clone();
+        for local_group in &code_item.local_groups {
+            for _ in 0..local_group.variable_count {
+                local_variable_types.push(local_group.value_type.clone());
+            }
+        }
+
+        let mut code_validator = CodeValidator::new(&context, local_variable_types, false);
+        code_validator
+            .validate_function(&function_type.results, &code_item.instruction_items)
+            .map_err(|(instruction_index, kind)| {
+                make_error(
+                    ValidationLocation::Function {
+                        function_index,
+                        instruction_index,
+                    },
+                    kind,
+                )
+            })?;
+    }
+
+    Ok(())
+}
+
+fn make_error(location: ValidationLocation, kind: ValidationErrorKind) -> ValidationError {
+    ValidationError { location, kind }
+}
+
+fn make_index_out_of_range_error(
+    index_kind: IndexKind,
+    index: u32,
+    count: usize,
+) -> ValidationErrorKind {
+    ValidationErrorKind::IndexOutOfRange {
+        index_kind,
+        index,
+        count,
+    }
+}
+
+fn check_limit(limit: &Limit, max_allowed: u32) -> Result<(), ValidationErrorKind> {
+    match *limit {
+        Limit::AtLeast(min) => {
+            if min > max_allowed {
+                return Err(ValidationErrorKind::LimitExceedsMaximum(min, max_allowed));
+            }
+        }
+        Limit::Range(min, max) => {
+            if max > max_allowed {
+                return Err(ValidationErrorKind::LimitExceedsMaximum(max, max_allowed));
+            }
+
+            if min > max {
+                return Err(ValidationErrorKind::LimitMinExceedsMax(min, max));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn collect_declared_function_references(ast_module: &ast::Module, context: &mut ModuleContext) {
+    let references = &mut context.declared_function_references;
+
+    for element_item in &ast_module.element_items {
+        match &element_item.items {
+            ElementItems::FunctionIndices(function_indices) => {
+                references.extend(function_indices.iter().copied());
+            }
+            ElementItems::Expressions(_, expressions) => {
+                for instructions in expressions {
+                    collect_function_references(instructions, references);
+                }
+            }
+        }
+    }
+
+    for global_item in &ast_module.global_items {
+        collect_function_references(&global_item.initialize_instruction_items, references);
+    }
+
+    for export_item in &ast_module.export_items {
+        if let ExportDescriptor::FunctionIndex(function_index) = export_item.export_descriptor {
+            references.insert(function_index);
+        }
+    }
+}
+
+fn collect_function_references(instructions: &[Instruction], references: &mut HashSet<u32>) {
+    for instruction in instructions {
+        if let Instruction::RefFunc(function_index) = instruction {
+            references.insert(*function_index);
+        }
+    }
+}
+
+fn validate_element_item(
+    context: &ModuleContext,
+    element_item: &ast::ElementItem,
+    global_variables_count: usize,
+) -> Result<(), ValidationErrorKind> {
+    let element_type = match &element_item.items {
+        ElementItems::FunctionIndices(function_indices) => {
+            for function_index in function_indices {
+                context.check_function_index(*function_index)?;
+            }
+
+            ValueType::FuncRef
+        }
+        ElementItems::Expressions(ref_type, expressions) => {
+            for instructions in expressions {
+                validate_constant_expression(
+                    context,
+                    instructions,
+                    ref_type,
+                    global_variables_count,
+                )?;
+            }
+
+            ref_type.clone()
+        }
+    };
+
+    if let ElementMode::Active {
+        table_index,
+        offset_instruction_items,
+    } = &element_item.mode
+    {
+        let table_type = context.get_table_type(*table_index)?;
+        if table_type.ref_type != element_type {
+            return Err(ValidationErrorKind::TableElementTypeMismatch {
+                expected: table_type.ref_type.clone(),
+                actual: element_type,
+            });
+        }
+
+        validate_constant_expression(
+            context,
+            offset_instruction_items,
+            &ValueType::I32,
+            global_variables_count,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// 验证常量表达式
+///
+/// - expected_type 是表达式结果的类型；
+/// - visible_global_variables_count 是 `global.get` 指令可以读取的全局变量的数量，
+///   对于全局变量的初始值，只能读取在它之前定义（包括导入）的全局变量。
+fn validate_constant_expression(
+    context: &ModuleContext,
+    instructions: &[Instruction],
+    expected_type: &ValueType,
+    visible_global_variables_count: usize,
+) -> Result<(), ValidationErrorKind> {
+    for instruction in instructions {
+        match instruction {
+            Instruction::I32Const(_)
+            | Instruction::I64Const(_)
+            | Instruction::F32Const(_)
+            | Instruction::F64Const(_)
+            | Instruction::RefNull(_)
+            | Instruction::I32Add
+            | Instruction::I32Sub
+            | Instruction::I32Mul
+            | Instruction::I64Add
+            | Instruction::I64Sub
+            | Instruction::I64Mul
+            | Instruction::End => {}
+            Instruction::RefFunc(function_index) => {
+                context.check_function_index(*function_index)?;
+            }
+            Instruction::GlobalGet(global_variable_index) => {
+                if *global_variable_index as usize >= visible_global_variables_count {
+                    return Err(make_index_out_of_range_error(
+                        IndexKind::Global,
+                        *global_variable_index,
+                        visible_global_variables_count,
+                    ));
+                }
+
+                if context.global_types[*global_variable_index as usize].mutable {
+                    return Err(ValidationErrorKind::MutableGlobalInConstantExpression(
+                        *global_variable_index,
+                    ));
+                }
+            }
+            _ => {
+                return Err(ValidationErrorKind::NonConstantInstruction(
+                    instruction.clone(),
+                ));
+            }
+        }
+    }
+
+    // 常量表达式跟函数体一样以 `end` 指令结束，所以可以使用同样的方法检查操作数的类型
+    let mut code_validator = CodeValidator::new(context, vec![], true);
+    code_validator
+        .validate_function(std::slice::from_ref(expected_type), instructions)
+        .map_err(|(_, kind)| kind)
+}
+
+/// 模块的上下文
+///
+/// 即规范里的 `context`，包括各种对象（导入的对象在前，模块内定义的对象在后）的类型
+struct ModuleContext<'a> {
+    function_types: Vec<&'a FunctionType>,
+
+    /// 所有函数（包括导入的）的类型索引
+    function_type_indexes: Vec<u32>,
+    tables: Vec<&'a TableType>,
+    memory_blocks_count: usize,
+    global_types: Vec<&'a GlobalType>,
+
+    /// 元素段的元素类型
+    element_types: Vec<ValueType>,
+    data_count: usize,
+
+    /// 已声明的函数引用
+    declared_function_references: HashSet<u32>,
+}
+
+impl<'a> ModuleContext<'a> {
+    fn check_type_index(&self, type_index: u32) -> Result<(), ValidationErrorKind> {
+        self.get_type(type_index).map(|_| ())
+    }
+
+    fn get_type(&self, type_index: u32) -> Result<&'a FunctionType, ValidationErrorKind> {
+        self.function_types
+            .get(type_index as usize)
+            .copied()
+            .ok_or_else(|| {
+                make_index_out_of_range_error(
+                    IndexKind::Type,
+                    type_index,
+                    self.function_types.len(),
+                )
+            })
+    }
+
+    fn check_function_index(&self, function_index: u32) -> Result<(), ValidationErrorKind> {
+        self.get_function_type(function_index).map(|_| ())
+    }
+
+    fn get_function_type(
+        &self,
+        function_index: u32,
+    ) -> Result<&'a FunctionType, ValidationErrorKind> {
+        match self.function_type_indexes.get(function_index as usize) {
+            Some(type_index) => self.get_type(*type_index),
+            None => Err(make_index_out_of_range_error(
+                IndexKind::Function,
+                function_index,
+                self.function_type_indexes.len(),
+            )),
+        }
+    }
+
+    fn check_table_index(&self, table_index: u32) -> Result<(), ValidationErrorKind> {
+        self.get_table_type(table_index).map(|_| ())
+    }
+
+    fn get_table_type(&self, table_index: u32) -> Result<&'a TableType, ValidationErrorKind> {
+        self.tables
+            .get(table_index as usize)
+            .copied()
+            .ok_or_else(|| {
+                make_index_out_of_range_error(IndexKind::Table, table_index, self.tables.len())
+            })
+    }
+
+    fn check_memory_block_index(&self, memory_block_index: u32) -> Result<(), ValidationErrorKind> {
+        if (memory_block_index as usize) < self.memory_blocks_count {
+            Ok(())
+        } else {
+            Err(make_index_out_of_range_error(
+                IndexKind::MemoryBlock,
+                memory_block_index,
+                self.memory_blocks_count,
+            ))
+        }
+    }
+
+    fn check_global_variable_index(
+        &self,
+        global_variable_index: u32,
+    ) -> Result<&'a GlobalType, ValidationErrorKind> {
+        self.global_types
+            .get(global_variable_index as usize)
+            .copied()
+            .ok_or_else(|| {
+                make_index_out_of_range_error(
+                    IndexKind::Global,
+                    global_variable_index,
+                    self.global_types.len(),
+                )
+            })
+    }
+
+    fn get_element_type(&self, element_index: u32) -> Result<&ValueType, ValidationErrorKind> {
+        self.element_types
+            .get(element_index as usize)
+            .ok_or_else(|| {
+                make_index_out_of_range_error(
+                    IndexKind::Element,
+                    element_index,
+                    self.element_types.len(),
+                )
+            })
+    }
+
+    fn check_data_index(&self, data_index: u32) -> Result<(), ValidationErrorKind> {
+        if (data_index as usize) < self.data_count {
+            Ok(())
+        } else {
+            Err(make_index_out_of_range_error(
+                IndexKind::Data,
+                data_index,
+                self.data_count,
+            ))
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum ControlKind {
+    Function,
+    Block,
+    Loop,
+    If,
+    Else,
+}
+
+/// 控制栈的栈帧
+struct ControlFrame {
+    kind: ControlKind,
+    start_types: Vec<ValueType>,
+    end_types: Vec<ValueType>,
+
+    /// 进入结构块时（已压入参数）操作数栈的高度，注意这里不包括结构块的参数
+    height: usize,
+
+    /// 结构块的剩余部分是否不可到达
+    unreachable: bool,
+}
+
+impl ControlFrame {
+    /// 跳转到当前结构块时所需的操作数的类型
+    ///
+    /// 对于 `loop` 结构块，跳转的目标是结构块的开始位置，所以需要参数；
+    /// 对于其他结构块，跳转的目标是结构块的结束位置，所以需要结果。
+    fn get_label_types(&self) -> &[ValueType] {
+        if self.kind == ControlKind::Loop {
+            &self.start_types
+        } else {
+            &self.end_types
+        }
+    }
+}
+
+/// 函数指令序列的验证器
+///
+/// 操作数栈的值为 None 时表示未知类型
+struct CodeValidator<'a, 'b> {
+    context: &'b ModuleContext<'a>,
+    local_variable_types: Vec<ValueType>,
+    operands: Vec<Option<ValueType>>,
+    controls: Vec<ControlFrame>,
+
+    /// 是否正在验证常量表达式
+    is_constant_expression: bool,
+}
+
+impl<'a, 'b> CodeValidator<'a, 'b> {
+    fn new(
+        context: &'b ModuleContext<'a>,
+        local_variable_types: Vec<ValueType>,
+        is_constant_expression: bool,
+    ) -> Self {
+        Self {
+            context,
+            local_variable_types,
+            operands: vec![],
+            controls: vec![],
+            is_constant_expression,
+        }
+    }
+
+    /// 验证函数的指令序列
+    ///
+    /// 验证失败时返回（指令的索引, 错误）
+    fn validate_function(
+        &mut self,
+        result_types: &[ValueType],
+        instructions: &[Instruction],
+    ) -> Result<(), (usize, ValidationErrorKind)> {
+        self.push_control(ControlKind::Function, vec![], result_types.to_vec());
+
+        for (instruction_index, instruction) in instructions.iter().enumerate() {
+            if self.controls.is_empty() {
+                return Err((instruction_index, ValidationErrorKind::InstructionAfterEnd));
+            }
+
+            self.validate_instruction(instruction)
+                .map_err(|kind| (instruction_index, kind))?;
+        }
+
+        if !self.controls.is_empty() {
+            return Err((instructions.len(), ValidationErrorKind::MissingEnd));
+        }
+
+        Ok(())
+    }
+
+    fn push_operand(&mut self, value_type: Option<ValueType>) {
+        self.operands.push(value_type);
+    }
+
+    fn push_operands(&mut self, value_types: &[ValueType]) {
+        for value_type in value_types {
+            self.operands.push(Some(value_type.clone()));
+        }
+    }
+
+    /// 弹出任意类型的操作数
+    fn pop_operand(&mut self) -> Result<Option<ValueType>, ValidationErrorKind> {
+        let frame = self.controls.last().unwrap();
+
+        if self.operands.len() == frame.height {
+            if frame.unreachable {
+                // 不可到达的代码可以弹出任意类型的值
+                return Ok(None);
+            } else {
+                return Err(ValidationErrorKind::NotEnoughOperands);
+            }
+        }
+
+        Ok(self.operands.pop().unwrap())
+    }
+
+    /// 弹出指定类型的操作数
+    fn pop_expected_operand(
+        &mut self,
+        expected: &ValueType,
+    ) -> Result<Option<ValueType>, ValidationErrorKind> {
+        let actual = self.pop_operand()?;
+
+        match &actual {
+            Some(actual_type) if actual_type != expected => {
+                Err(ValidationErrorKind::TypeMismatch {
+                    expected: expected.clone(),
+                    actual: actual_type.clone(),
+                })
+            }
+            _ => Ok(actual),
+        }
+    }
+
+    /// 弹出指定类型的多个操作数，返回实际弹出的操作数
+    fn pop_expected_operands(
+        &mut self,
+        expected_types: &[ValueType],
+    ) -> Result<Vec<Option<ValueType>>, ValidationErrorKind> {
+        let mut popped: Vec<Option<ValueType>> = vec![];
+
+        for expected in expected_types.iter().rev() {
+            popped.push(self.pop_expected_operand(expected)?);
+        }
+
+        popped.reverse();
+        Ok(popped)
+    }
+
+    fn push_control(
+        &mut self,
+        kind: ControlKind,
+        start_types: Vec<ValueType>,
+        end_types: Vec<ValueType>,
+    ) {
+        let frame = ControlFrame {
+            kind,
+            start_types: start_types.clone(),
+            end_types,
+            height: self.operands.len(),
+            unreachable: false,
+        };
+
+        self.controls.push(frame);
+        self.push_operands(&start_types);
+    }
+
+    fn pop_control(&mut self) -> Result<ControlFrame, ValidationErrorKind> {
+        let end_types = self.controls.last().unwrap().end_types.clone();
+        self.pop_expected_operands(&end_types)?;
+
+        let frame = self.controls.last().unwrap();
+        if self.operands.len() != frame.height {
+            return Err(ValidationErrorKind::ResultCountMismatch {
+                expected: end_types.len(),
+                actual: end_types.len() + self.operands.len() - frame.height,
+            });
+        }
+
+        Ok(self.controls.pop().unwrap())
+    }
+
+    /// 将当前结构块的剩余部分标记为不可到达
+    fn set_unreachable(&mut self) {
+        let frame = self.controls.last_mut().unwrap();
+        self.operands.truncate(frame.height);
+        frame.unreachable = true;
+    }
+
+    /// 获取跳转目标结构块的标签类型
+    fn get_label_types(&self, relative_depth: u32) -> Result<Vec<ValueType>, ValidationErrorKind> {
+        let depth = relative_depth as usize;
+        if depth >= self.controls.len() {
+            return Err(make_index_out_of_range_error(
+                IndexKind::Label,
+                relative_depth,
+                self.controls.len(),
+            ));
+        }
+
+        Ok(self.controls[self.controls.len() - 1 - depth]
+            .get_label_types()
+            .to_vec())
+    }
+
+    fn get_block_types(
+        &self,
+        block_type: &BlockType,
+    ) -> Result<(Vec<ValueType>, Vec<ValueType>), ValidationErrorKind> {
+        let types = match block_type {
+            BlockType::ResultI32 => (vec![], vec![ValueType::I32]),
+            BlockType::ResultI64 => (vec![], vec![ValueType::I64]),
+            BlockType::ResultF32 => (vec![], vec![ValueType::F32]),
+            BlockType::ResultF64 => (vec![], vec![ValueType::F64]),
+            BlockType::ResultFuncRef => (vec![], vec![ValueType::FuncRef]),
+            BlockType::ResultExternRef => (vec![], vec![ValueType::ExternRef]),
+            BlockType::ResultEmpty => (vec![], vec![]),
+            BlockType::TypeIndex(type_index) => {
+                let function_type = self.context.get_type(*type_index)?;
+                (function_type.params.clone(), function_type.results.clone())
+            }
+        };
+
+        Ok(types)
+    }
+
+    fn get_local_variable_type(
+        &self,
+        local_variable_index: u32,
+    ) -> Result<ValueType, ValidationErrorKind> {
+        self.local_variable_types
+            .get(local_variable_index as usize)
+            .cloned()
+            .ok_or_else(|| {
+                make_index_out_of_range_error(
+                    IndexKind::Local,
+                    local_variable_index,
+                    self.local_variable_types.len(),
+                )
+            })
+    }
+
+    /// 检查内存指令的内存块索引以及对齐值
+    ///
+    /// 对齐的字节数不能大于数据的宽度（即自然对齐）
+    fn check_memory_argument(
+        &self,
+        memory_args: &MemoryArgument,
+        data_width: u32,
+    ) -> Result<(), ValidationErrorKind> {
+        self.context
+            .check_memory_block_index(memory_args.memory_block_index)?;
+
+        let max_align = data_width.trailing_zeros();
+        if memory_args.align > max_align {
+            return Err(ValidationErrorKind::InvalidAlignment {
+                align: memory_args.align,
+                max_align,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn validate_load(
+        &mut self,
+        memory_args: &MemoryArgument,
+        data_width: u32,
+        value_type: ValueType,
+    ) -> Result<(), ValidationErrorKind> {
+        self.check_memory_argument(memory_args, data_width)?;
+        self.pop_expected_operand(&ValueType::I32)?;
+        self.push_operand(Some(value_type));
+        Ok(())
+    }
+
+    fn validate_store(
+        &mut self,
+        memory_args: &MemoryArgument,
+        data_width: u32,
+        value_type: ValueType,
+    ) -> Result<(), ValidationErrorKind> {
+        self.check_memory_argument(memory_args, data_width)?;
+        self.pop_expected_operand(&value_type)?;
+        self.pop_expected_operand(&ValueType::I32)?;
+        Ok(())
+    }
+
+    fn validate_instruction(
+        &mut self,
+        instruction: &Instruction,
+    ) -> Result<(), ValidationErrorKind> {
+        // 先处理数值指令，它们的操作数和结果都是固定的
+        if let Some((param_types, result_type)) = get_numeric_instruction_type(instruction) {
+            self.pop_expected_operands(param_types)?;
+            self.push_operand(Some(result_type));
+            return Ok(());
+        }
+
+        match instruction {
+            Instruction::Unreachable => {
+                self.set_unreachable();
+            }
+            Instruction::Nop => {}
+            Instruction::Block(block_type, _) => {
+                let (start_types, end_types) = self.get_block_types(block_type)?;
+                self.pop_expected_operands(&start_types)?;
+                self.push_control(ControlKind::Block, start_types, end_types);
+            }
+            Instruction::Loop(block_type, _) => {
+                let (start_types, end_types) = self.get_block_types(block_type)?;
+                self.pop_expected_operands(&start_types)?;
+                self.push_control(ControlKind::Loop, start_types, end_types);
+            }
+            Instruction::If(block_type, _) => {
+                let (start_types, end_types) = self.get_block_types(block_type)?;
+                self.pop_expected_operand(&ValueType::I32)?;
+                self.pop_expected_operands(&start_types)?;
+                self.push_control(ControlKind::If, start_types, end_types);
+            }
+            Instruction::Else => {
+                if self.controls.last().unwrap().kind != ControlKind::If {
+                    return Err(ValidationErrorKind::ElseWithoutIf);
+                }
+
+                let frame = self.pop_control()?;
+                self.push_control(ControlKind::Else, frame.start_types, frame.end_types);
+            }
+            Instruction::End => {
+                let frame = self.pop_control()?;
+
+                if frame.kind == ControlKind::If && frame.start_types != frame.end_types {
+                    return Err(ValidationErrorKind::IfWithoutElseTypeMismatch);
+                }
+
+                self.push_operands(&frame.end_types);
+            }
+            Instruction::Br(relative_depth) => {
+                let label_types = self.get_label_types(*relative_depth)?;
+                self.pop_expected_operands(&label_types)?;
+                self.set_unreachable();
+            }
+            Instruction::BrIf(relative_depth) => {
+                let label_types = self.get_label_types(*relative_depth)?;
+                self.pop_expected_operand(&ValueType::I32)?;
+                self.pop_expected_operands(&label_types)?;
+                self.push_operands(&label_types);
+            }
+            Instruction::BrTable(relative_depths, default_relative_depth) => {
+                self.pop_expected_operand(&ValueType::I32)?;
+
+                let default_label_types = self.get_label_types(*default_relative_depth)?;
+                let arity = default_label_types.len();
+
+                for relative_depth in relative_depths {
+                    let label_types = self.get_label_types(*relative_depth)?;
+                    if label_types.len() != arity {
+                        return Err(ValidationErrorKind::BranchTableArityMismatch);
+                    }
+
+                    // 检查操作数是否符合每一个目标的类型，然后再压回操作数栈
+                    let popped = self.pop_expected_operands(&label_types)?;
+                    for value_type in popped {
+                        self.push_operand(value_type);
+                    }
+                }
+
+                self.pop_expected_operands(&default_label_types)?;
+                self.set_unreachable();
+            }
+            Instruction::Return => {
+                let result_types = self.controls[0].end_types.clone();
+                self.pop_expected_operands(&result_types)?;
+                self.set_unreachable();
+            }
+            Instruction::Call(function_index) => {
+                let function_type = self.context.get_function_type(*function_index)?;
+                self.pop_expected_operands(&function_type.params)?;
+                self.push_operands(&function_type.results);
+            }
+            Instruction::CallIndirect(type_index, table_index) => {
+                let table_type = self.context.get_table_type(*table_index)?;
+                if table_type.ref_type != ValueType::FuncRef {
+                    return Err(ValidationErrorKind::TableElementTypeMismatch {
+                        expected: ValueType::FuncRef,
+                        actual: table_type.ref_type.clone(),
+                    });
+                }
+
+                let function_type = self.context.get_type(*type_index)?;
+                self.pop_expected_operand(&ValueType::I32)?;
+                self.pop_expected_operands(&function_type.params)?;
+                self.push_operands(&function_type.results);
+            }
+            Instruction::Drop => {
+                self.pop_operand()?;
+            }
+            Instruction::Select => {
+                self.pop_expected_operand(&ValueType::I32)?;
+                let first = self.pop_operand()?;
+                let second = self.pop_operand()?;
+
+                for value_type in [&first, &second].into_iter().flatten() {
+                    if value_type.is_ref_type() {
+                        return Err(ValidationErrorKind::InvalidSelectOperandType(
+                            value_type.clone(),
+                        ));
+                    }
+                }
+
+                match (first, second) {
+                    (Some(first_type), Some(second_type)) => {
+                        if first_type != second_type {
+                            return Err(ValidationErrorKind::TypeMismatch {
+                                expected: second_type,
+                                actual: first_type,
+                            });
+                        }
+
+                        self.push_operand(Some(first_type));
+                    }
+                    (Some(value_type), None) | (None, Some(value_type)) => {
+                        self.push_operand(Some(value_type));
+                    }
+                    (None, None) => {
+                        self.push_operand(None);
+                    }
+                }
+            }
+            Instruction::SelectTyped(value_types) => {
+                if value_types.len() != 1 {
+                    return Err(ValidationErrorKind::InvalidSelectTypeCount(
+                        value_types.len(),
+                    ));
+                }
+
+                let value_type = &value_types[0];
+                self.pop_expected_operand(&ValueType::I32)?;
+                self.pop_expected_operand(value_type)?;
+                self.pop_expected_operand(value_type)?;
+                self.push_operand(Some(value_type.clone()));
+            }
+            Instruction::LocalGet(local_variable_index) => {
+                let value_type = self.get_local_variable_type(*local_variable_index)?;
+                self.push_operand(Some(value_type));
+            }
+            Instruction::LocalSet(local_variable_index) => {
+                let value_type = self.get_local_variable_type(*local_variable_index)?;
+                self.pop_expected_operand(&value_type)?;
+            }
+            Instruction::LocalTee(local_variable_index) => {
+                let value_type = self.get_local_variable_type(*local_variable_index)?;
+                self.pop_expected_operand(&value_type)?;
+                self.push_operand(Some(value_type));
+            }
+            Instruction::GlobalGet(global_variable_index) => {
+                let global_type = self
+                    .context
+                    .check_global_variable_index(*global_variable_index)?;
+                self.push_operand(Some(global_type.value_type.clone()));
+            }
+            Instruction::GlobalSet(global_variable_index) => {
+                let global_type = self
+                    .context
+                    .check_global_variable_index(*global_variable_index)?;
+                if !global_type.mutable {
+                    return Err(ValidationErrorKind::ImmutableGlobalVariable(
+                        *global_variable_index,
+                    ));
+                }
+
+                self.pop_expected_operand(&global_type.value_type)?;
+            }
+
+            Instruction::I32Load(memory_args) => {
+                self.validate_load(memory_args, 4, ValueType::I32)?
+            }
+            Instruction::I64Load(memory_args) => {
+                self.validate_load(memory_args, 8, ValueType::I64)?
+            }
+            Instruction::F32Load(memory_args) => {
+                self.validate_load(memory_args, 4, ValueType::F32)?
+            }
+            Instruction::F64Load(memory_args) => {
+                self.validate_load(memory_args, 8, ValueType::F64)?
+            }
+            Instruction::I32Load8S(memory_args) | Instruction::I32Load8U(memory_args) => {
+                self.validate_load(memory_args, 1, ValueType::I32)?
+            }
+            Instruction::I32Load16S(memory_args) | Instruction::I32Load16U(memory_args) => {
+                self.validate_load(memory_args, 2, ValueType::I32)?
+            }
+            Instruction::I64Load8S(memory_args) | Instruction::I64Load8U(memory_args) => {
+                self.validate_load(memory_args, 1, ValueType::I64)?
+            }
+            Instruction::I64Load16S(memory_args) | Instruction::I64Load16U(memory_args) => {
+                self.validate_load(memory_args, 2, ValueType::I64)?
+            }
+            Instruction::I64Load32S(memory_args) | Instruction::I64Load32U(memory_args) => {
+                self.validate_load(memory_args, 4, ValueType::I64)?
+            }
+            Instruction::I32Store(memory_args) => {
+                self.validate_store(memory_args, 4, ValueType::I32)?
+            }
+            Instruction::I64Store(memory_args) => {
+                self.validate_store(memory_args, 8, ValueType::I64)?
+            }
+            Instruction::F32Store(memory_args) => {
+                self.validate_store(memory_args, 4, ValueType::F32)?
+            }
+            Instruction::F64Store(memory_args) => {
+                self.validate_store(memory_args, 8, ValueType::F64)?
+            }
+            Instruction::I32Store8(memory_args) => {
+                self.validate_store(memory_args, 1, ValueType::I32)?
+            }
+            Instruction::I32Store16(memory_args) => {
+                self.validate_store(memory_args, 2, ValueType::I32)?
+            }
+            Instruction::I64Store8(memory_args) => {
+                self.validate_store(memory_args, 1, ValueType::I64)?
+            }
+            Instruction::I64Store16(memory_args) => {
+                self.validate_store(memory_args, 2, ValueType::I64)?
+            }
+            Instruction::I64Store32(memory_args) => {
+                self.validate_store(memory_args, 4, ValueType::I64)?
+            }
+
+            Instruction::MemorySize(memory_block_index) => {
+                self.context.check_memory_block_index(*memory_block_index)?;
+                self.push_operand(Some(ValueType::I32));
+            }
+            Instruction::MemoryGrow(memory_block_index) => {
+                self.context.check_memory_block_index(*memory_block_index)?;
+                self.pop_expected_operand(&ValueType::I32)?;
+                self.push_operand(Some(ValueType::I32));
+            }
+            Instruction::MemoryInit(data_index, memory_block_index) => {
+                self.context.check_memory_block_index(*memory_block_index)?;
+                self.context.check_data_index(*data_index)?;
+                self.pop_expected_operands(&[ValueType::I32, ValueType::I32, ValueType::I32])?;
+            }
+            Instruction::DataDrop(data_index) => {
+                self.context.check_data_index(*data_index)?;
+            }
+            Instruction::MemoryCopy(source_memory_block_index, dest_memory_block_index) => {
+                self.context
+                    .check_memory_block_index(*source_memory_block_index)?;
+                self.context
+                    .check_memory_block_index(*dest_memory_block_index)?;
+                self.pop_expected_operands(&[ValueType::I32, ValueType::I32, ValueType::I32])?;
+            }
+            Instruction::MemoryFill(memory_block_index) => {
+                self.context.check_memory_block_index(*memory_block_index)?;
+                self.pop_expected_operands(&[ValueType::I32, ValueType::I32, ValueType::I32])?;
+            }
+
+            Instruction::TableGet(table_index) => {
+                let ref_type = self.context.get_table_type(*table_index)?.ref_type.clone();
+                self.pop_expected_operand(&ValueType::I32)?;
+                self.push_operand(Some(ref_type));
+            }
+            Instruction::TableSet(table_index) => {
+                let ref_type = self.context.get_table_type(*table_index)?.ref_type.clone();
+                self.pop_expected_operand(&ref_type)?;
+                self.pop_expected_operand(&ValueType::I32)?;
+            }
+            Instruction::TableInit(element_index, table_index) => {
+                let ref_type = self.context.get_table_type(*table_index)?.ref_type.clone();
+                let element_type = self.context.get_element_type(*element_index)?;
+                if &ref_type != element_type {
+                    return Err(ValidationErrorKind::TableElementTypeMismatch {
+                        expected: ref_type,
+                        actual: element_type.clone(),
+                    });
+                }
+
+                self.pop_expected_operands(&[ValueType::I32, ValueType::I32, ValueType::I32])?;
+            }
+            Instruction::ElementDrop(element_index) => {
+                self.context.get_element_type(*element_index)?;
+            }
+            Instruction::TableCopy(source_table_index, dest_table_index) => {
+                let source_ref_type = &self.context.get_table_type(*source_table_index)?.ref_type;
+                let dest_ref_type = &self.context.get_table_type(*dest_table_index)?.ref_type;
+                if source_ref_type != dest_ref_type {
+                    return Err(ValidationErrorKind::TableElementTypeMismatch {
+                        expected: dest_ref_type.clone(),
+                        actual: source_ref_type.clone(),
+                    });
+                }
+
+                self.pop_expected_operands(&[ValueType::I32, ValueType::I32, ValueType::I32])?;
+            }
+            Instruction::TableGrow(table_index) => {
+                let ref_type = self.context.get_table_type(*table_index)?.ref_type.clone();
+                self.pop_expected_operands(&[ref_type, ValueType::I32])?;
+                self.push_operand(Some(ValueType::I32));
+            }
+            Instruction::TableSize(table_index) => {
+                self.context.check_table_index(*table_index)?;
+                self.push_operand(Some(ValueType::I32));
+            }
+            Instruction::TableFill(table_index) => {
+                let ref_type = self.context.get_table_type(*table_index)?.ref_type.clone();
+                self.pop_expected_operands(&[ValueType::I32, ref_type, ValueType::I32])?;
+            }
+
+            Instruction::RefNull(ref_type) => {
+                self.push_operand(Some(ref_type.clone()));
+            }
+            Instruction::RefIsNull => {
+                if let Some(value_type) = self.pop_operand()? {
+                    if !value_type.is_ref_type() {
+                        return Err(ValidationErrorKind::TypeMismatch {
+                            expected: ValueType::FuncRef,
+                            actual: value_type,
+                        });
+                    }
+                }
+
+                self.push_operand(Some(ValueType::I32));
+            }
+            Instruction::RefFunc(function_index) => {
+                self.context.check_function_index(*function_index)?;
+
+                // 常量表达式里的 `ref.func` 指令本身就是声明，只有函数体里的才需要检查
+                if !self.is_constant_expression
+                    && !self
+                        .context
+                        .declared_function_references
+                        .contains(function_index)
+                {
+                    return Err(ValidationErrorKind::UndeclaredFunctionReference(
+                        *function_index,
+                    ));
+                }
+
+                self.push_operand(Some(ValueType::FuncRef));
+            }
+
+            _ => {
+                // 数值指令已经在前面处理
+                unreachable!("unexpected instruction {:?}", instruction)
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// 获取数值指令的参数类型和结果类型
+///
+/// 数值指令包括常量、比较、一元和二元运算以及类型转换等指令，它们的操作数和结果都是固定的。
+fn get_numeric_instruction_type(
+    instruction: &Instruction,
+) -> Option<(&'static [ValueType], ValueType)> {
+    const EMPTY: &[ValueType] = &[];
+    const I32: &[ValueType] = &[ValueType::I32];
+    const I64: &[ValueType] = &[ValueType::I64];
+    const F32: &[ValueType] = &[ValueType::F32];
+    const F64: &[ValueType] = &[ValueType::F64];
+    const I32_I32: &[ValueType] = &[ValueType::I32, ValueType::I32];
+    const I64_I64: &[ValueType] = &[ValueType::I64, ValueType::I64];
+    const F32_F32: &[ValueType] = &[ValueType::F32, ValueType::F32];
+    const F64_F64: &[ValueType] = &[ValueType::F64, ValueType::F64];
+
+    let instruction_type = match instruction {
+        Instruction::I32Const(_) => (EMPTY, ValueType::I32),
+        Instruction::I64Const(_) => (EMPTY, ValueType::I64),
+        Instruction::F32Const(_) => (EMPTY, ValueType::F32),
+        Instruction::F64Const(_) => (EMPTY, ValueType::F64),
+
+        Instruction::I32Eqz => (I32, ValueType::I32),
+        Instruction::I32Eq
+        | Instruction::I32Ne
+        | Instruction::I32LtS
+        | Instruction::I32LtU
+        | Instruction::I32GtS
+        | Instruction::I32GtU
+        | Instruction::I32LeS
+        | Instruction::I32LeU
+        | Instruction::I32GeS
+        | Instruction::I32GeU => (I32_I32, ValueType::I32),
+
+        Instruction::I64Eqz => (I64, ValueType::I32),
+        Instruction::I64Eq
+        | Instruction::I64Ne
+        | Instruction::I64LtS
+        | Instruction::I64LtU
+        | Instruction::I64GtS
+        | Instruction::I64GtU
+        | Instruction::I64LeS
+        | Instruction::I64LeU
+        | Instruction::I64GeS
+        | Instruction::I64GeU => (I64_I64, ValueType::I32),
+
+        Instruction::F32Eq
+        | Instruction::F32Ne
+        | Instruction::F32Lt
+        | Instruction::F32Gt
+        | Instruction::F32Le
+        | Instruction::F32Ge => (F32_F32, ValueType::I32),
+
+        Instruction::F64Eq
+        | Instruction::F64Ne
+        | Instruction::F64Lt
+        | Instruction::F64Gt
+        | Instruction::F64Le
+        | Instruction::F64Ge => (F64_F64, ValueType::I32),
+
+        Instruction::I32Clz
+        | Instruction::I32Ctz
+        | Instruction::I32PopCnt
+        | Instruction::I32Extend8S
+        | Instruction::I32Extend16S => (I32, ValueType::I32),
+
+        Instruction::I32Add
+        | Instruction::I32Sub
+        | Instruction::I32Mul
+        | Instruction::I32DivS
+        | Instruction::I32DivU
+        | Instruction::I32RemS
+        | Instruction::I32RemU
+        | Instruction::I32And
+        | Instruction::I32Or
+        | Instruction::I32Xor
+        | Instruction::I32Shl
+        | Instruction::I32ShrS
+        | Instruction::I32ShrU
+        | Instruction::I32Rotl
+        | Instruction::I32Rotr => (I32_I32, ValueType::I32),
+
+        Instruction::I64Clz
+        | Instruction::I64Ctz
+        | Instruction::I64PopCnt
+        | Instruction::I64Extend8S
+        | Instruction::I64Extend16S
+        | Instruction::I64Extend32S => (I64, ValueType::I64),
+
+        Instruction::I64Add
+        | Instruction::I64Sub
+        | Instruction::I64Mul
+        | Instruction::I64DivS
+        | Instruction::I64DivU
+        | Instruction::I64RemS
+        | Instruction::I64RemU
+        | Instruction::I64And
+        | Instruction::I64Or
+        | Instruction::I64Xor
+        | Instruction::I64Shl
+        | Instruction::I64ShrS
+        | Instruction::I64ShrU
+        | Instruction::I64Rotl
+        | Instruction::I64Rotr => (I64_I64, ValueType::I64),
+
+        Instruction::F32Abs
+        | Instruction::F32Neg
+        | Instruction::F32Ceil
+        | Instruction::F32Floor
+        | Instruction::F32Trunc
+        | Instruction::F32Nearest
+        | Instruction::F32Sqrt => (F32, ValueType::F32),
+
+        Instruction::F32Add
+        | Instruction::F32Sub
+        | Instruction::F32Mul
+        | Instruction::F32Div
+        | Instruction::F32Min
+        | Instruction::F32Max
+        | Instruction::F32CopySign => (F32_F32, ValueType::F32),
+
+        Instruction::F64Abs
+        | Instruction::F64Neg
+        | Instruction::F64Ceil
+        | Instruction::F64Floor
+        | Instruction::F64Trunc
+        | Instruction::F64Nearest
+        | Instruction::F64Sqrt => (F64, ValueType::F64),
+
+        Instruction::F64Add
+        | Instruction::F64Sub
+        | Instruction::F64Mul
+        | Instruction::F64Div
+        | Instruction::F64Min
+        | Instruction::F64Max
+        | Instruction::F64CopySign => (F64_F64, ValueType::F64),
+
+        Instruction::I32WrapI64 => (I64, ValueType::I32),
+        Instruction::I32TruncF32S
+        | Instruction::I32TruncF32U
+        | Instruction::I32TruncSatF32S
+        | Instruction::I32TruncSatF32U
+        | Instruction::I32ReinterpretF32 => (F32, ValueType::I32),
+        Instruction::I32TruncF64S
+        | Instruction::I32TruncF64U
+        | Instruction::I32TruncSatF64S
+        | Instruction::I32TruncSatF64U => (F64, ValueType::I32),
+
+        Instruction::I64ExtendI32S | Instruction::I64ExtendI32U => (I32, ValueType::I64),
+        Instruction::I64TruncF32S
+        | Instruction::I64TruncF32U
+        | Instruction::I64TruncSatF32S
+        | Instruction::I64TruncSatF32U => (F32, ValueType::I64),
+        Instruction::I64TruncF64S
+        | Instruction::I64TruncF64U
+        | Instruction::I64TruncSatF64S
+        | Instruction::I64TruncSatF64U
+        | Instruction::I64ReinterpretF64 => (F64, ValueType::I64),
+
+        Instruction::F32ConvertI32S
+        | Instruction::F32ConvertI32U
+        | Instruction::F32ReinterpretI32 => (I32, ValueType::F32),
+        Instruction::F32ConvertI64S | Instruction::F32ConvertI64U => (I64, ValueType::F32),
+        Instruction::F32DemoteF64 => (F64, ValueType::F32),
+
+        Instruction::F64ConvertI32S | Instruction::F64ConvertI32U => (I32, ValueType::F64),
+        Instruction::F64ConvertI64S
+        | Instruction::F64ConvertI64U
+        | Instruction::F64ReinterpretI64 => (I64, ValueType::F64),
+        Instruction::F64PromoteF32 => (F32, ValueType::F64),
+
+        _ => return None,
+    };
+
+    Some(instruction_type)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs};
+
+    use anvm_ast::{
+        ast::{self, Limit},
+        instruction::{Instruction, MemoryArgument},
+        types::ValueType,
+    };
+    use anvm_binary_parser::parser;
+    use pretty_assertions::assert_eq;
+
+    use super::{validate, IndexKind, ValidationError, ValidationErrorKind, ValidationLocation};
+
+    fn get_test_resource_path() -> std::path::PathBuf {
+        let mut path_buf = env::current_dir().unwrap();
+
+        if !path_buf.ends_with("engine") {
+            path_buf.push("crates");
+            path_buf.push("engine");
+        }
+
+        path_buf.join("resources")
+    }
+
+    fn get_test_ast_module(filename: &str) -> ast::Module {
+        let bytes = fs::read(get_test_resource_path().join(filename)).unwrap();
+        parser::parse(&bytes).unwrap()
+    }
+
+    /// 将指定函数的指令序列替换为新的指令序列，然后验证模块
+    ///
+    /// 测试模块 `test-validation.wasm` 有 1 个导入函数，所以内部函数的索引从 1 开始
+    fn validate_function_instructions(
+        function_index: usize,
+        instructions: Vec<Instruction>,
+    ) -> Result<(), ValidationError> {
+        let mut ast_module = get_test_ast_module("test-validation.wasm");
+        ast_module.code_items[function_index - 1].instruction_items = instructions;
+        validate(&ast_module)
+    }
+
+    fn make_function_error(
+        function_index: usize,
+        instruction_index: usize,
+        kind: ValidationErrorKind,
+    ) -> ValidationError {
+        ValidationError {
+            location: ValidationLocation::Function {
+                function_index,
+                instruction_index,
+            },
+            kind,
+        }
+    }
+
+    #[test]
+    fn test_validate_resource_modules() {
+        // 以 `test-invalid-` 开头的是故意构造的无效模块
+        for entry in fs::read_dir(get_test_resource_path()).unwrap() {
+            let path = entry.unwrap().path();
+            let filename = path.file_name().unwrap().to_str().unwrap().to_owned();
+
+            if !filename.ends_with(".wasm") || filename.starts_with("test-invalid-") {
+                continue;
+            }
+
+            let ast_module = get_test_ast_module(&filename);
+            assert_eq!(validate(&ast_module), Ok(()), "module: {}", filename);
+        }
+
+        assert_eq!(
+            validate(&get_test_ast_module("test-invalid-block.wasm")),
+            Err(make_function_error(
+                0,
+                2,
+                ValidationErrorKind::ResultCountMismatch {
+                    expected: 0,
+                    actual: 1
+                }
+            ))
+        );
+
+        assert_eq!(
+            validate(&get_test_ast_module("test-invalid-branch-table.wasm")),
+            Err(make_function_error(
+                0,
+                3,
+                ValidationErrorKind::BranchTableArityMismatch
+            ))
+        );
+    }
+
+    #[test]
+    fn test_validate_function_instructions() {
+        // 栈多态，`unreachable` 之后可以弹出任意类型的操作数
+        assert_eq!(
+            validate_function_instructions(
+                1,
+                vec![
+                    Instruction::Unreachable,
+                    Instruction::I32Add,
+                    Instruction::End
+                ]
+            ),
+            Ok(())
+        );
+
+        // 已声明的函数引用（函数 2 位于元素段）
+        assert_eq!(
+            validate_function_instructions(
+                1,
+                vec![
+                    Instruction::RefFunc(2),
+                    Instruction::Drop,
+                    Instruction::I32Const(0),
+                    Instruction::End
+                ]
+            ),
+            Ok(())
+        );
+
+        assert_eq!(
+            validate_function_instructions(
+                1,
+                vec![
+                    Instruction::LocalGet(0),
+                    Instruction::I64Const(1),
+                    Instruction::I32Add,
+                    Instruction::End
+                ]
+            ),
+            Err(make_function_error(
+                1,
+                2,
+                ValidationErrorKind::TypeMismatch {
+                    expected: ValueType::I32,
+                    actual: ValueType::I64
+                }
+            ))
+        );
+
+        assert_eq!(
+            validate_function_instructions(1, vec![Instruction::I32Add, Instruction::End]),
+            Err(make_function_error(
+                1,
+                0,
+                ValidationErrorKind::NotEnoughOperands
+            ))
+        );
+
+        assert_eq!(
+            validate_function_instructions(
+                1,
+                vec![
+                    Instruction::LocalGet(0),
+                    Instruction::LocalGet(1),
+                    Instruction::End
+                ]
+            ),
+            Err(make_function_error(
+                1,
+                2,
+                ValidationErrorKind::ResultCountMismatch {
+                    expected: 1,
+                    actual: 2
+                }
+            ))
+        );
+
+        assert_eq!(
+            validate_function_instructions(1, vec![Instruction::LocalGet(5), Instruction::End]),
+            Err(make_function_error(
+                1,
+                0,
+                ValidationErrorKind::IndexOutOfRange {
+                    index_kind: IndexKind::Local,
+                    index: 5,
+                    count: 2
+                }
+            ))
+        );
+
+        assert_eq!(
+            validate_function_instructions(
+                2,
+                vec![
+                    Instruction::I32Const(1),
+                    Instruction::GlobalSet(0),
+                    Instruction::I32Const(0),
+                    Instruction::End
+                ]
+            ),
+            Err(make_function_error(
+                2,
+                1,
+                ValidationErrorKind::ImmutableGlobalVariable(0)
+            ))
+        );
+
+        assert_eq!(
+            validate_function_instructions(
+                2,
+                vec![
+                    Instruction::I32Const(0),
+                    Instruction::I32Load(MemoryArgument {
+                        align: 3,
+                        offset: 0,
+                        memory_block_index: 0
+                    }),
+                    Instruction::End
+                ]
+            ),
+            Err(make_function_error(
+                2,
+                1,
+                ValidationErrorKind::InvalidAlignment {
+                    align: 3,
+                    max_align: 2
+                }
+            ))
+        );
+
+        assert_eq!(
+            validate_function_instructions(
+                2,
+                vec![
+                    Instruction::I32Const(0),
+                    Instruction::Br(1),
+                    Instruction::End
+                ]
+            ),
+            Err(make_function_error(
+                2,
+                1,
+                ValidationErrorKind::IndexOutOfRange {
+                    index_kind: IndexKind::Label,
+                    index: 1,
+                    count: 1
+                }
+            ))
+        );
+
+        assert_eq!(
+            validate_function_instructions(2, vec![Instruction::I32Const(0), Instruction::Else]),
+            Err(make_function_error(
+                2,
+                1,
+                ValidationErrorKind::ElseWithoutIf
+            ))
+        );
+
+        assert_eq!(
+            validate_function_instructions(2, vec![Instruction::I32Const(0)]),
+            Err(make_function_error(2, 1, ValidationErrorKind::MissingEnd))
+        );
+
+        assert_eq!(
+            validate_function_instructions(
+                2,
+                vec![Instruction::I32Const(0), Instruction::End, Instruction::Nop]
+            ),
+            Err(make_function_error(
+                2,
+                2,
+                ValidationErrorKind::InstructionAfterEnd
+            ))
+        );
+
+        // 函数 0 是导入函数，既没有导出也没有出现在元素段
+        assert_eq!(
+            validate_function_instructions(
+                3,
+                vec![
+                    Instruction::RefFunc(0),
+                    Instruction::Drop,
+                    Instruction::I64Const(0),
+                    Instruction::End
+                ]
+            ),
+            Err(make_function_error(
+                3,
+                0,
+                ValidationErrorKind::UndeclaredFunctionReference(0)
+            ))
+        );
+    }
+
+    #[test]
+    fn test_validate_module_items() {
+        let ast_module = get_test_ast_module("test-validation.wasm");
+        assert_eq!(validate(&ast_module), Ok(()));
+
+        // 常量表达式只能读取不可变的全局变量
+        let mut m0 = ast_module.clone();
+        m0.global_items[1].initialize_instruction_items =
+            vec![Instruction::GlobalGet(1), Instruction::End];
+        assert_eq!(
+            validate(&m0),
+            Err(ValidationError {
+                location: ValidationLocation::Global(1),
+                kind: ValidationErrorKind::MutableGlobalInConstantExpression(1)
+            })
+        );
+
+        let mut m1 = ast_module.clone();
+        m1.global_items[0].initialize_instruction_items = vec![
+            Instruction::I32Const(1),
+            Instruction::I32Const(2),
+            Instruction::I32DivS,
+            Instruction::End,
+        ];
+        assert_eq!(
+            validate(&m1),
+            Err(ValidationError {
+                location: ValidationLocation::Global(0),
+                kind: ValidationErrorKind::NonConstantInstruction(Instruction::I32DivS)
+            })
+        );
+
+        let mut m2 = ast_module.clone();
+        m2.export_items[1].name = "add".to_owned();
+        assert_eq!(
+            validate(&m2),
+            Err(ValidationError {
+                location: ValidationLocation::Export(1),
+                kind: ValidationErrorKind::DuplicateExportName("add".to_owned())
+            })
+        );
+
+        let mut m3 = ast_module.clone();
+        m3.memory_blocks[0].limit = Limit::Range(3, 2);
+        assert_eq!(
+            validate(&m3),
+            Err(ValidationError {
+                location: ValidationLocation::MemoryBlock(0),
+                kind: ValidationErrorKind::LimitMinExceedsMax(3, 2)
+            })
+        );
+
+        let mut m4 = ast_module.clone();
+        m4.start_function_index = Some(1);
+        assert_eq!(
+            validate(&m4),
+            Err(ValidationError {
+                location: ValidationLocation::Start,
+                kind: ValidationErrorKind::InvalidStartFunctionType
+            })
+        );
+
+        let mut m5 = ast_module.clone();
+        m5.element_items[0].mode = ast::ElementMode::Active {
+            table_index: 0,
+            offset_instruction_items: vec![Instruction::I64Const(0), Instruction::End],
+        };
+        assert_eq!(
+            validate(&m5),
+            Err(ValidationError {
+                location: ValidationLocation::Element(0),
+                kind: ValidationErrorKind::TypeMismatch {
+                    expected: ValueType::I32,
+                    actual: ValueType::I64
+                }
+            })
+        );
+
+        let mut m6 = ast_module;
+        m6.code_items.pop();
+        assert_eq!(
+            validate(&m6),
+            Err(ValidationError {
+                location: ValidationLocation::Code,
+                kind: ValidationErrorKind::FunctionCountMismatch {
+                    functions_count: 3,
+                    codes_count: 2
+                }
+            })
+        );
+    }
+}
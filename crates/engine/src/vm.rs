@@ -4,39 +4,52 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc,
+};
+
 use anvm_ast::{
     instruction::{self, BlockType},
     types::{check_value_types, Value, ValueType, ValueTypeCheckError},
 };
 
 use crate::{
-    error::{EngineError, InvalidOperation, TypeMismatch, Unsupported},
-    interpreter,
+    error::{
+        make_operand_data_types_mismatch_engine_error, EngineError, Interrupt, InvalidOperation,
+        NativeError, NativeTerminate, ObjectNotFound, OutOfRange, TypeMismatch, Unsupported,
+    },
+    export::{ExportGlobal, ExportMemory, ExportTable},
+    fuel::{default_instruction_cost, InstructionCostFunction},
     native_module::NativeModule,
     object::FunctionItem,
-    vm_global_variable::VMGlobalVariable,
+    trap::{make_trap_engine_error, Trap, TrapCode},
+    typed_function::{TypedFunction, WasmParams, WasmResults},
+    vm_global_variable::{SetGlobalVariableError, VMGlobalVariable},
     vm_memory::VMMemory,
     vm_module::VMModule,
-    vm_stack::VMStack,
+    vm_segment::{VMDataSegment, VMElementSegment},
+    vm_stack::{ControlFrame, FrameKind, VMStack},
     vm_table::VMTable,
 };
 
+/// 默认的调用帧数量上限
+pub const DEFAULT_MAX_CALL_DEPTH: usize = 100_000;
+
+/// 默认的栈（VMStack）总大小上限，单位为槽（slot）
+pub const DEFAULT_MAX_STACK_SLOTS: usize = 16 * 1024 * 1024;
+
 /// VM 的当前状态信息
 ///
 /// VM 的状态信息主要有两个：
 ///
-/// - 当前栈帧的位置信息（fp, lp, bp）
+/// - 当前栈帧的位置信息（lp, bp）
 /// - 当前指令的位置信息（即 pc，这里由 vm_module_index，function_index，program_counter 3 个变量组成）
 ///
-/// 当创建一个新的栈帧时，VM 的状态会保存到栈帧的信息段里。
-/// 当弹出一个栈帧时，栈帧的信息段里的数据会恢复到 VM 的状态。
+/// 当创建一个新的栈帧时，VM 的状态会保存到控制栈的控制帧里。
+/// 当弹出一个栈帧时，控制帧里的数据会恢复到 VM 的状态。
 #[derive(Debug, PartialEq, Clone)]
 pub struct Status {
-    /// 当前栈帧的开始地址
-    ///
-    /// 对于调用帧来说，它也是第 0 个实参的地址
-    pub frame_pointer: usize,
-
     /// 局部变量段的开始地址
     ///
     /// 也就是当前调用帧的地址
@@ -45,23 +58,16 @@ pub struct Status {
     /// 当前的解析器都会创建一个新的栈帧。
     ///
     /// 但结构块没有自己的局部变量，它的局部变量来自最近一次函数调用所创建的帧，
-    /// - 当新的栈帧是因为函数调用而创建时，local_pointer 和 frame_pointer
-    ///   的值是相等的，
+    /// - 当新的栈帧是因为函数调用而创建时，local_pointer 的值是第 0 个实参的地址，
     /// - 当新的栈帧是由进入结构块而创建时，则 local_pointer
     ///   保持跟上一个栈帧的 local_pointer 相同。
     pub local_pointer: usize,
 
-    /// 信息段开始的地址
+    /// 运算操作数段开始的地址
     ///
-    /// 当前栈帧开始的一段是函数调用的实参以及局部变量，然后
-    /// 是一段固定大小的关于上一帧的信息，诸如上一个栈帧的
-    /// fp, lp, bp, ra (包括 vm module index, function index, address) 等信息，
-    /// 最后才是一段运算操作数。
-    ///
-    /// base_pointer 记录局部变量列表的结束位置，从而可以让
-    /// 栈维护程序较方便地读取信息段数据。
-    ///
-    /// `base_pointer = frame_pointer + 局部变量数量`
+    /// 对于调用帧，运算操作数段位于局部变量（包括实参）之后，即
+    /// `base_pointer = local_pointer + 局部变量数量`；
+    /// 对于结构块帧，运算操作数段从结构块的第 0 个参数开始。
     pub base_pointer: usize,
 
     /// 当前指令所在的模块，指令执行完毕之后，则是下一个待执行指令所在的模块
@@ -99,15 +105,11 @@ pub struct Status {
     pub address: usize,
 }
 
-// 设定一个特殊的初始值，表示 vm 尚未开始任何函数调用
-pub const INITIAL_FRAME_POINTER: usize = (0 - 1) as usize;
-
 impl Status {
     pub fn new() -> Self {
         Self {
-            frame_pointer: INITIAL_FRAME_POINTER, // 设定一个特殊的初始值，表示 vm 尚未开始任何函数调用
-            local_pointer: INITIAL_FRAME_POINTER,
-            base_pointer: INITIAL_FRAME_POINTER,
+            local_pointer: 0,
+            base_pointer: 0,
             vm_module_index: 0,
             function_index: 0,
             frame_type: BlockType::TypeIndex(0),
@@ -120,9 +122,15 @@ pub struct Resource {
     pub memory_blocks: Vec<VMMemory>,
     pub tables: Vec<VMTable>,
     pub global_variables: Vec<VMGlobalVariable>,
+    pub data_segments: Vec<VMDataSegment>,
+    pub element_segments: Vec<VMElementSegment>,
 
     pub native_modules: Vec<NativeModule>,
-    pub vm_modules: Vec<VMModule>,
+    /// 各个 VM 实例共享的模块指令、函数信息以及类型等不可变的数据
+    pub vm_modules: Arc<Vec<VMModule>>,
+
+    /// 各个模块的函数引用的起始编号，参见 `get_function_reference_bases`
+    function_reference_bases: Vec<u32>,
 }
 
 impl Resource {
@@ -130,23 +138,152 @@ impl Resource {
         memory_blocks: Vec<VMMemory>,
         tables: Vec<VMTable>,
         global_variables: Vec<VMGlobalVariable>,
+        data_segments: Vec<VMDataSegment>,
+        element_segments: Vec<VMElementSegment>,
         native_modules: Vec<NativeModule>,
-        vm_modules: Vec<VMModule>,
+        vm_modules: Arc<Vec<VMModule>>,
     ) -> Self {
+        let function_reference_bases =
+            get_function_reference_bases(vm_modules.iter().map(|item| item.function_items.len()));
+
         Self {
             memory_blocks,
             tables,
             global_variables,
+            data_segments,
+            element_segments,
             native_modules,
             vm_modules,
+            function_reference_bases,
         }
     }
+
+    /// 获取指向指定模块的指定函数（函数索引包括导入的函数）的函数引用
+    pub fn get_function_reference(&self, vm_module_index: usize, function_index: usize) -> u32 {
+        self.function_reference_bases[vm_module_index] + function_index as u32
+    }
+
+    /// 获取函数引用所指向的函数，函数引用无效时返回 None
+    pub fn resolve_function_reference(&self, function_reference: u32) -> Option<&FunctionItem> {
+        // 函数数量为 0 的模块跟下一个模块的起始编号相同，所以这里取起始编号
+        // 不大于函数引用的最后一个模块
+        let vm_module_index = self
+            .function_reference_bases
+            .partition_point(|base| *base <= function_reference)
+            .checked_sub(1)?;
+        let function_index = function_reference - self.function_reference_bases[vm_module_index];
+
+        self.vm_modules[vm_module_index]
+            .function_items
+            .get(function_index as usize)
+    }
+}
+
+/// 计算各个模块的函数引用的起始编号
+///
+/// 函数引用（funcref）的值是函数在所有模块当中的统一编号，即模块的起始编号加上
+/// 函数在模块内的索引，所以经过共享的表、全局变量或者宿主传递到其他模块的函数引用
+/// 仍然指向原来的函数。
+///
+/// function_counts 为各个模块的函数数量（包括导入的函数）。
+pub(crate) fn get_function_reference_bases(
+    function_counts: impl Iterator<Item = usize>,
+) -> Vec<u32> {
+    let mut base = 0;
+    function_counts
+        .map(|function_count| {
+            let current = base;
+            base += function_count as u32;
+            current
+        })
+        .collect()
+}
+
+/// 断点
+///
+/// 断点由指令所在的模块索引、函数索引以及指令的地址（即指令在模块指令列表里的位置）共同确定。
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Breakpoint {
+    pub vm_module_index: usize,
+    pub function_index: usize,
+    pub address: usize,
+}
+
+impl Breakpoint {
+    pub fn new(vm_module_index: usize, function_index: usize, address: usize) -> Self {
+        Self {
+            vm_module_index,
+            function_index,
+            address,
+        }
+    }
+}
+
+/// 中断句柄
+///
+/// 用于从其他线程（比如看门狗线程）中断正在执行指令的 VM。
+///
+/// 句柄与 VM 共享一个原子标记，调用 `interrupt` 方法之后，VM 会在执行下一条指令
+/// 之前停止，并返回 `EngineError::Interrupt(Interrupt::Interrupted {...})` 错误，
+/// 此时 VM 的状态以及栈均保持不变，中断标记会被自动清除，所以之后仍可以继续执行。
+///
+/// 注意：
+/// 如果 VM 正在执行本地函数，则需要等到本地函数返回之后才能中断。
+#[derive(Debug, Clone)]
+pub struct InterruptHandle {
+    interrupt_flag: Arc<AtomicBool>,
+}
+
+impl InterruptHandle {
+    /// 请求中断 VM
+    pub fn interrupt(&self) {
+        self.interrupt_flag.store(true, Ordering::Relaxed);
+    }
 }
 
 pub struct VM {
     pub stack: VMStack,
     pub status: Status,
     pub resource: Resource,
+
+    /// 断点列表
+    pub breakpoints: Vec<Breakpoint>,
+
+    /// 最近一次因断点而中断时所命中的断点
+    ///
+    /// 用于从断点处恢复执行时，避免在同一个断点上重复中断。
+    /// 执行任意一条指令之后，该值会被清除。
+    last_hit_breakpoint: Option<Breakpoint>,
+
+    /// 剩余的燃料
+    ///
+    /// 值为 None 时表示不限制燃料（默认值）
+    fuel: Option<u64>,
+
+    /// 指令燃料消耗函数
+    instruction_cost_function: InstructionCostFunction,
+
+    /// 中断标记，与中断句柄共享
+    interrupt_flag: Arc<AtomicBool>,
+
+    /// 当前调用帧的数量（不包括结构块的栈帧）
+    call_depth: usize,
+
+    /// 调用帧数量的上限
+    max_call_depth: usize,
+
+    /// 栈总大小的上限，即操作数栈（不包括控制栈）的槽数量上限
+    ///
+    /// 注意该上限仅在压入调用帧时检查，因为函数内的操作数数量是有限的，
+    /// 所以栈的实际大小最多只会比上限多出一个函数的操作数的数量。
+    max_stack_slots: usize,
+
+    /// 是否执行合并了超级指令的指令列表（默认开启）
+    ///
+    /// 超级指令只用于持续执行指令的 recur 系列方法，单步执行（step）总是执行原指令。
+    /// 设有断点或者开启了燃料计量时，VM 也逐条执行原指令，
+    /// 以便能在每一条原指令处中断，以及按原指令计算燃料。
+    superinstructions_enabled: bool,
 }
 
 pub enum CallFunctionResult {
@@ -155,41 +292,395 @@ pub enum CallFunctionResult {
     /// 需要执行 recur/recur_without_break/step/step_without_into/step_out 等
     /// 方法执行函数的指令
     ///
-    /// 参数是最终结果的数据的数量
-    Standby(usize),
+    /// 参数是最终结果的数据类型列表，操作数栈只储存无类型的槽，
+    /// 弹出结果时需要根据数据类型转换为数值。
+    Standby(Vec<ValueType>),
 
     /// 调用的是本地函数，直接得出结果
     Immediate(Vec<Value>),
 }
 
+/// 执行一系列指令（recur/step_without_into/jump_out 等方法）的结果
+#[derive(Debug, PartialEq, Clone)]
+pub enum ExecutionResult {
+    /// 程序（或者说第一个被调用的函数）的所有指令已执行完毕
+    ProgramEnd,
+
+    /// 遇到断点而中断
+    ///
+    /// VM 停留在断点所在的指令，该指令尚未被执行。
+    Breakpoint(Breakpoint),
+
+    /// 完成了单步执行（step_without_into）或者跳出函数（jump_out）的操作
+    ///
+    /// VM 暂停在下一条待执行的指令。
+    Paused,
+}
+
 impl VM {
+    pub fn new(stack: VMStack, status: Status, resource: Resource) -> Self {
+        Self {
+            stack,
+            status,
+            resource,
+            breakpoints: vec![],
+            last_hit_breakpoint: None,
+            fuel: None,
+            instruction_cost_function: default_instruction_cost,
+            interrupt_flag: Arc::new(AtomicBool::new(false)),
+            call_depth: 0,
+            max_call_depth: DEFAULT_MAX_CALL_DEPTH,
+            max_stack_slots: DEFAULT_MAX_STACK_SLOTS,
+            superinstructions_enabled: true,
+        }
+    }
+
+    /// 设置调用帧数量的上限
+    pub fn set_max_call_depth(&mut self, max_call_depth: usize) {
+        self.max_call_depth = max_call_depth;
+    }
+
+    /// 设置栈总大小的上限，单位为槽（slot）
+    pub fn set_max_stack_slots(&mut self, max_stack_slots: usize) {
+        self.max_stack_slots = max_stack_slots;
+    }
+
+    /// 开启或者关闭超级指令
+    pub fn set_superinstructions_enabled(&mut self, enabled: bool) {
+        self.superinstructions_enabled = enabled;
+    }
+
+    /// 获取当前调用帧的数量
+    pub fn get_call_depth(&self) -> usize {
+        self.call_depth
+    }
+
+    /// 清空栈以及执行状态
+    ///
+    /// 用于放弃因陷阱或者中断而未完成的函数调用，内存、表以及全局变量等资源保持不变。
+    /// 不能在本地函数里调用，因为此时宿主正在执行模块的函数。
+    pub fn reset(&mut self) {
+        self.stack = VMStack::new();
+        self.status = Status::new();
+        self.call_depth = 0;
+        self.last_hit_breakpoint = None;
+    }
+
+    pub(crate) fn get_last_hit_breakpoint(&self) -> Option<Breakpoint> {
+        self.last_hit_breakpoint
+    }
+
+    /// 替换栈以及状态，用于恢复 VM 快照
+    ///
+    /// 调用帧的数量根据控制栈重新计算。
+    pub(crate) fn replace_execution_state(
+        &mut self,
+        stack: VMStack,
+        status: Status,
+        last_hit_breakpoint: Option<Breakpoint>,
+    ) {
+        self.call_depth = stack
+            .get_frames()
+            .iter()
+            .filter(|frame| frame.is_call_frame())
+            .count();
+        self.stack = stack;
+        self.status = status;
+        self.last_hit_breakpoint = last_hit_breakpoint;
+    }
+
+    /// 检查压入一个新的调用帧之后，是否会超出调用帧数量或者栈总大小的上限
+    ///
+    /// 参数 slots_count 是新栈帧需要额外占用的槽的数量
+    fn check_call_stack_limit(&self, slots_count: usize) -> Result<(), EngineError> {
+        let call_depth = self.call_depth + 1;
+        let stack_size = self.stack.get_size() + slots_count;
+
+        if call_depth > self.max_call_depth || stack_size > self.max_stack_slots {
+            Err(make_trap_engine_error(self, TrapCode::StackExhausted))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// 获取中断句柄
+    ///
+    /// 句柄可以被复制以及发送到其他线程。
+    pub fn get_interrupt_handle(&self) -> InterruptHandle {
+        InterruptHandle {
+            interrupt_flag: Arc::clone(&self.interrupt_flag),
+        }
+    }
+
+    /// 设置燃料预算，并开启燃料计量
+    pub fn set_fuel(&mut self, fuel: u64) {
+        self.fuel = Some(fuel);
+    }
+
+    /// 补充燃料
+    ///
+    /// 如果尚未开启燃料计量（即燃料不受限制），则此方法不起作用。
+    pub fn add_fuel(&mut self, fuel: u64) {
+        if let Some(remaining) = self.fuel {
+            self.fuel = Some(remaining.saturating_add(fuel));
+        }
+    }
+
+    /// 获取剩余的燃料
+    ///
+    /// 返回 None 表示燃料不受限制
+    pub fn get_fuel(&self) -> Option<u64> {
+        self.fuel
+    }
+
+    /// 关闭燃料计量，即燃料不再受限制
+    pub fn disable_fuel(&mut self) {
+        self.fuel = None;
+    }
+
+    /// 设置指令燃料消耗函数
+    pub fn set_instruction_cost_function(
+        &mut self,
+        instruction_cost_function: InstructionCostFunction,
+    ) {
+        self.instruction_cost_function = instruction_cost_function;
+    }
+
+    /// 扣除执行指定位置的指令所需的燃料
+    ///
+    /// 当剩余的燃料不足时，不扣除燃料，并返回 Interrupt::OutOfFuel 错误。
+    fn consume_fuel(&mut self, vm_module_index: usize, address: usize) -> Result<(), EngineError> {
+        if let Some(remaining) = self.fuel {
+            let instruction = &self.resource.vm_modules[vm_module_index].instructions[address];
+            let fuel_cost = (self.instruction_cost_function)(instruction);
+            if fuel_cost > remaining {
+                return Err(EngineError::Interrupt(Interrupt::OutOfFuel {
+                    vm_module_index: self.status.vm_module_index,
+                    function_index: self.status.function_index,
+                    address: self.status.address,
+                    fuel_cost,
+                    fuel_remaining: remaining,
+                }));
+            }
+
+            self.fuel = Some(remaining - fuel_cost);
+        }
+
+        Ok(())
+    }
+
     /// 从 vm 外部（即宿主）调用函数，并进行求值
     /// 直到函数所有指令执行完毕。
+    ///
+    /// 如果发生陷阱或者其他错误，VM 的栈、状态以及调用帧数量会恢复到调用之前的样子，
+    /// 以便 VM 可以继续调用其他函数；中断（比如燃料耗尽）则保留执行现场，以便恢复执行。
+    ///
+    /// 如果需要在陷阱发生之后检查栈以及状态（比如调试器），应该使用
+    /// `call_function_by_index` 以及 `recur` 系列方法，检查完毕之后再调用 `reset`。
     pub fn eval_function_by_index(
         &mut self,
         vm_module_index: usize,
         function_index: usize,
         arguments: &[Value],
+    ) -> Result<Vec<Value>, EngineError> {
+        let stack_size = self.stack.get_size();
+        let frame_count = self.stack.get_frame_count();
+        let call_depth = self.call_depth;
+        let status = self.status.clone();
+
+        let result =
+            self.eval_function_by_index_without_unwind(vm_module_index, function_index, arguments);
+
+        match &result {
+            Ok(_) | Err(EngineError::Interrupt(_)) => {}
+            Err(_) => {
+                self.stack.truncate(stack_size, frame_count);
+                self.call_depth = call_depth;
+                self.status = status;
+            }
+        }
+
+        result
+    }
+
+    fn eval_function_by_index_without_unwind(
+        &mut self,
+        vm_module_index: usize,
+        function_index: usize,
+        arguments: &[Value],
     ) -> Result<Vec<Value>, EngineError> {
         let result = self.call_function_by_index(vm_module_index, function_index, arguments)?;
 
         match result {
             CallFunctionResult::Immediate(values) => Ok(values),
-            CallFunctionResult::Standby(results_count) => {
+            CallFunctionResult::Standby(result_types) => {
                 self.recur_without_break()?;
 
                 // 从 vm 内部返回结果给外部（即宿主）函数调用者
                 // 先弹出的数值放置在结果数组的右边（大索引端）。
-                let results = self.stack.pop_values(results_count);
+                let results = self.stack.pop_values(&result_types)?;
                 Ok(results)
             }
         }
     }
 
+    /// 获取指定模块的导出函数的带类型的句柄
+    ///
+    /// 获取时检查函数的签名是否跟 Params 和 Results 一致，不一致时返回
+    /// `TypeMismatch::TypedFunctionSignatureMismatch` 错误，
+    /// 之后调用句柄时不再检查签名。
+    pub fn get_typed_function<Params, Results>(
+        &self,
+        module_name: &str,
+        export_function_name: &str,
+    ) -> Result<TypedFunction<Params, Results>, EngineError>
+    where
+        Params: WasmParams,
+        Results: WasmResults,
+    {
+        let vm_module_index = self.find_vm_module_index(module_name)?;
+        let vm_module = &self.resource.vm_modules[vm_module_index];
+
+        let function_index = vm_module
+            .find_export_function_index(export_function_name)
+            .ok_or(EngineError::ObjectNotFound(
+                ObjectNotFound::FunctionNotFound(
+                    module_name.to_string(),
+                    export_function_name.to_string(),
+                ),
+            ))?;
+
+        // 导出的函数有可能是导入的本地函数或者其他模块的函数，
+        // 所以需要从函数实际所在的模块获取函数类型
+        let function_type = match &vm_module.function_items[function_index] {
+            FunctionItem::Native {
+                native_module_index,
+                type_index,
+                ..
+            } => &self.resource.native_modules[*native_module_index].function_types[*type_index],
+            FunctionItem::Normal {
+                vm_module_index: target_vm_module_index,
+                type_index,
+                ..
+            } => &self.resource.vm_modules[*target_vm_module_index].function_types[*type_index],
+        };
+
+        if function_type.params != Params::value_types()
+            || function_type.results != Results::value_types()
+        {
+            return Err(EngineError::TypeMismatch(
+                TypeMismatch::TypedFunctionSignatureMismatch(
+                    module_name.to_string(),
+                    export_function_name.to_string(),
+                ),
+            ));
+        }
+
+        Ok(TypedFunction::new(vm_module_index, function_index))
+    }
+
+    /// 设置全局变量的值
+    ///
+    /// global_variable_index 是全局变量在模块内的索引（包括导入的全局变量），
+    /// 全局变量不可变或者数值的数据类型不符合时返回错误。
+    pub fn set_global_variable_value(
+        &mut self,
+        vm_module_index: usize,
+        global_variable_index: usize,
+        value: Value,
+    ) -> Result<(), EngineError> {
+        let instance_global_variable_index = self.resource.vm_modules[vm_module_index]
+            .global_variable_indexes[global_variable_index];
+        let vm_global_variable =
+            &mut self.resource.global_variables[instance_global_variable_index];
+
+        match vm_global_variable.set_value(value) {
+            Err(SetGlobalVariableError::Immutable) => Err(EngineError::InvalidOperation(
+                InvalidOperation::ImmutableGlobalVariable(vm_module_index, global_variable_index),
+            )),
+            Err(SetGlobalVariableError::TypeMismatch) => Err(EngineError::TypeMismatch(
+                TypeMismatch::SetGlobalVariableValueTypeMismatch(
+                    vm_module_index,
+                    global_variable_index,
+                    vm_global_variable.get_global_type().value_type.clone(),
+                    value.get_type(),
+                ),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// 获取指定模块的导出全局变量的句柄
+    pub fn get_export_global(
+        &self,
+        module_name: &str,
+        export_name: &str,
+    ) -> Result<ExportGlobal, EngineError> {
+        let vm_module_index = self.find_vm_module_index(module_name)?;
+        let global_variable_index = self.resource.vm_modules[vm_module_index]
+            .find_export_global_variable_index(export_name)
+            .ok_or(EngineError::ObjectNotFound(
+                ObjectNotFound::GlobalVariableNotFound(
+                    module_name.to_string(),
+                    export_name.to_string(),
+                ),
+            ))?;
+
+        Ok(ExportGlobal::new(vm_module_index, global_variable_index))
+    }
+
+    /// 获取指定模块的导出内存块的句柄
+    pub fn get_export_memory(
+        &self,
+        module_name: &str,
+        export_name: &str,
+    ) -> Result<ExportMemory, EngineError> {
+        let vm_module_index = self.find_vm_module_index(module_name)?;
+        let vm_module = &self.resource.vm_modules[vm_module_index];
+        let memory_block_index = vm_module
+            .find_export_memory_block_index(export_name)
+            .ok_or(EngineError::ObjectNotFound(
+                ObjectNotFound::MemoryBlockFound(module_name.to_string(), export_name.to_string()),
+            ))?;
+
+        Ok(ExportMemory::new(
+            vm_module.memory_block_indexes[memory_block_index],
+        ))
+    }
+
+    /// 获取指定模块的导出表的句柄
+    pub fn get_export_table(
+        &self,
+        module_name: &str,
+        export_name: &str,
+    ) -> Result<ExportTable, EngineError> {
+        let vm_module_index = self.find_vm_module_index(module_name)?;
+        let vm_module = &self.resource.vm_modules[vm_module_index];
+        let table_index =
+            vm_module
+                .find_export_table_index(export_name)
+                .ok_or(EngineError::ObjectNotFound(ObjectNotFound::TableNotFound(
+                    module_name.to_string(),
+                    export_name.to_string(),
+                )))?;
+
+        Ok(ExportTable::new(vm_module.table_indexes[table_index]))
+    }
+
+    fn find_vm_module_index(&self, module_name: &str) -> Result<usize, EngineError> {
+        self.resource
+            .vm_modules
+            .iter()
+            .position(|vm_module| vm_module.name == module_name)
+            .ok_or(EngineError::ObjectNotFound(ObjectNotFound::ModuleNotFound(
+                module_name.to_string(),
+            )))
+    }
+
     /// 将指定实参压入栈，并将 pc 的值指向函数的
     /// 第一个指令，但并不会开始执行指令。
     ///
-    /// 返回函数返回值的数量
+    /// 返回函数返回值的数据类型列表
     pub fn call_function_by_index(
         &mut self,
         vm_module_index: usize,
@@ -270,8 +761,8 @@ impl VM {
         internal_function_index: usize,
         address: usize,
         arguments: &[Value],
-    ) -> Result<usize, EngineError> {
-        let (parameter_types, local_variable_types, results_count) = {
+    ) -> Result<Vec<ValueType>, EngineError> {
+        let (parameter_types, local_variable_types, result_types) = {
             let vm_module = &self.resource.vm_modules[vm_module_index];
             let function_type = &vm_module.function_types[type_index];
             let local_variable_types =
@@ -279,7 +770,7 @@ impl VM {
             (
                 &function_type.params,
                 local_variable_types.to_owned(),
-                function_type.results.len(),
+                function_type.results.clone(),
             )
         };
 
@@ -317,8 +808,19 @@ impl VM {
         self.stack.push_values(arguments);
 
         // 压入调用栈
-        // 第一个调用栈的 previous_frame_pointer 等信息的值都为 0。
-        self.push_call_frame(parameters_count, &local_variable_types, 0);
+        // 宿主调用帧的返回地址是当前的地址，当函数执行完毕时，VM 的状态会恢复原样，
+        // 以便本地函数在执行期间也能调用模块内部的函数。
+        let return_address = self.status.address;
+        if let Err(e) = self.push_call_frame(
+            FrameKind::HostCall,
+            parameters_count,
+            &local_variable_types,
+            return_address,
+        ) {
+            // 撤销已压入的实参
+            self.stack.drop_slots(parameters_count);
+            return Err(e);
+        }
 
         // 更新状态，
         let status = &mut self.status;
@@ -328,8 +830,8 @@ impl VM {
         status.frame_type = BlockType::TypeIndex(type_index as u32);
         status.address = address;
 
-        // 返回函数返回值的数量
-        Ok(results_count)
+        // 返回函数返回值的数据类型列表
+        Ok(result_types)
     }
 
     fn call_native_function(
@@ -342,7 +844,7 @@ impl VM {
         let (parameter_types, native_function) = {
             let native_module = &self.resource.native_modules[native_module_index];
             let function_type = &native_module.function_types[type_index];
-            let native_function = native_module.native_functions[function_index];
+            let native_function = native_module.native_functions[function_index].clone();
 
             (&function_type.params, native_function)
         };
@@ -377,23 +879,198 @@ impl VM {
             }
         }
 
-        let result = native_function(self, native_module_index, &arguments);
+        let result = native_function
+            .call(self, native_module_index, arguments)
+            .ok_or(EngineError::InvalidOperation(
+                InvalidOperation::ReentrantNativeFunctionCall {
+                    native_module_index,
+                    function_index,
+                },
+            ))?;
 
         match result {
             Ok(result_values) => Ok(result_values),
+            Err(NativeTerminate {
+                native_error: NativeError::Trap(trap_code),
+                ..
+            }) => Err(make_trap_engine_error(self, trap_code)),
             Err(e) => Err(EngineError::NativeTerminate(e)),
         }
     }
 
+    /// 添加断点
+    ///
+    /// 参数 address 是指令在模块指令列表里的位置，它必须位于指定函数的
+    /// 第一条指令与最后一条指令（即 `end 指令`）之间。
+    pub fn add_breakpoint(
+        &mut self,
+        vm_module_index: usize,
+        function_index: usize,
+        address: usize,
+    ) -> Result<Breakpoint, EngineError> {
+        let option_function_item = self
+            .resource
+            .vm_modules
+            .get(vm_module_index)
+            .and_then(|vm_module| vm_module.function_items.get(function_index));
+
+        // 只有当前模块内部定义的普通函数才能设置断点，
+        // 导入的函数需要在它所在的模块里设置。
+        let is_valid = match option_function_item {
+            Some(FunctionItem::Normal {
+                vm_module_index: target_vm_module_index,
+                function_index: target_function_index,
+                start_address,
+                end_address,
+                ..
+            }) => {
+                *target_vm_module_index == vm_module_index
+                    && *target_function_index == function_index
+                    && address >= *start_address
+                    && address <= *end_address
+            }
+            _ => false,
+        };
+
+        if !is_valid {
+            return Err(EngineError::InvalidOperation(
+                InvalidOperation::InvalidBreakpointAddress {
+                    vm_module_index,
+                    function_index,
+                    address,
+                },
+            ));
+        }
+
+        let breakpoint = Breakpoint::new(vm_module_index, function_index, address);
+        if !self.breakpoints.contains(&breakpoint) {
+            self.breakpoints.push(breakpoint);
+        }
+
+        Ok(breakpoint)
+    }
+
+    /// 在指定模块的导出函数的第一条指令处添加断点
+    ///
+    /// 如果导出的函数是从其他模块导入的，则断点会设置在函数实际所在的模块。
+    pub fn add_breakpoint_by_export_function_name(
+        &mut self,
+        module_name: &str,
+        export_function_name: &str,
+    ) -> Result<Breakpoint, EngineError> {
+        let vm_module = self
+            .resource
+            .vm_modules
+            .iter()
+            .find(|vm_module| vm_module.name == module_name)
+            .ok_or(EngineError::ObjectNotFound(ObjectNotFound::ModuleNotFound(
+                module_name.to_string(),
+            )))?;
+
+        let function_index = vm_module
+            .find_export_function_index(export_function_name)
+            .ok_or(EngineError::ObjectNotFound(
+                ObjectNotFound::FunctionNotFound(
+                    module_name.to_string(),
+                    export_function_name.to_string(),
+                ),
+            ))?;
+
+        match &vm_module.function_items[function_index] {
+            FunctionItem::Normal {
+                vm_module_index: target_vm_module_index,
+                function_index: target_function_index,
+                start_address,
+                ..
+            } => {
+                let (target_vm_module_index, target_function_index, start_address) = (
+                    *target_vm_module_index,
+                    *target_function_index,
+                    *start_address,
+                );
+                self.add_breakpoint(target_vm_module_index, target_function_index, start_address)
+            }
+            FunctionItem::Native { .. } => Err(EngineError::ObjectNotFound(
+                ObjectNotFound::FunctionNotFound(
+                    module_name.to_string(),
+                    export_function_name.to_string(),
+                ),
+            )),
+        }
+    }
+
+    /// 移除断点
+    ///
+    /// 如果断点存在则返回 true，否则返回 false
+    pub fn remove_breakpoint(&mut self, breakpoint: &Breakpoint) -> bool {
+        let original_count = self.breakpoints.len();
+        self.breakpoints.retain(|item| item != breakpoint);
+        self.breakpoints.len() != original_count
+    }
+
+    /// 移除所有断点
+    pub fn clear_breakpoints(&mut self) {
+        self.breakpoints.clear();
+    }
+
+    /// 检查下一条待执行的指令是否设有断点
+    ///
+    /// 如果 VM 刚刚因为该断点而中断（即中断之后尚未执行任何指令），
+    /// 则视为从断点处恢复执行，不再重复中断。
+    fn check_breakpoint(&mut self) -> Option<Breakpoint> {
+        let status = &self.status;
+        let option_breakpoint = self
+            .breakpoints
+            .iter()
+            .find(|breakpoint| {
+                breakpoint.address == status.address
+                    && breakpoint.vm_module_index == status.vm_module_index
+                    && breakpoint.function_index == status.function_index
+            })
+            .copied();
+
+        match option_breakpoint {
+            Some(breakpoint) if self.last_hit_breakpoint != Some(breakpoint) => {
+                self.last_hit_breakpoint = Some(breakpoint);
+                Some(breakpoint)
+            }
+            _ => None,
+        }
+    }
+
     /// 执行剩余的指令，遇到断点时中断
-    pub fn recur(&mut self) -> Result<bool, EngineError> {
-        todo!()
+    ///
+    /// 中断时 VM 停留在断点所在的指令（该指令尚未执行），再次调用
+    /// 此方法将会从该指令开始继续执行。
+    pub fn recur(&mut self) -> Result<ExecutionResult, EngineError> {
+        self.recur_while(|_| true)
+    }
+
+    /// 当 VM 的状态满足指定的条件时，持续执行指令，遇到断点时中断
+    ///
+    /// 当条件不再满足时，返回 ExecutionResult::Paused。
+    fn recur_while(
+        &mut self,
+        predicate: impl Fn(&VM) -> bool,
+    ) -> Result<ExecutionResult, EngineError> {
+        while predicate(self) {
+            if let Some(breakpoint) = self.check_breakpoint() {
+                return Ok(ExecutionResult::Breakpoint(breakpoint));
+            }
+
+            let is_program_end = self.step_fused()?;
+            if is_program_end {
+                return Ok(ExecutionResult::ProgramEnd);
+            }
+        }
+
+        Ok(ExecutionResult::Paused)
     }
 
     /// 执行剩余的指令，无视断点
     pub fn recur_without_break(&mut self) -> Result<(), EngineError> {
         loop {
-            let is_program_end = self.step()?;
+            let is_program_end = self.step_fused()?;
             if is_program_end {
                 break;
             }
@@ -407,15 +1084,59 @@ impl VM {
     ///
     /// 当程序（或者说第一个被调用的函数）的最后一条指令（即 `end 指令`）执行
     /// 之后，函数返回 true，否则返回 false。
+    ///
+    /// 单步执行总是只执行一条原指令，即使超级指令处于开启状态。
     pub fn step(&mut self) -> Result<bool, EngineError> {
+        self.execute_instruction(false)
+    }
+
+    /// 执行当前一条指令，超级指令开启并且没有设置断点以及燃料时执行超级指令
+    ///
+    /// 超级指令不包含函数调用指令，而跳出函数的 `br_if` 只会位于超级指令的末尾，
+    /// 所以 recur_while 在每一条超级指令之后检查调用帧的数量，仍然能够在正确的位置停下。
+    fn step_fused(&mut self) -> Result<bool, EngineError> {
+        let is_fused =
+            self.superinstructions_enabled && self.fuel.is_none() && self.breakpoints.is_empty();
+        self.execute_instruction(is_fused)
+    }
+
+    fn execute_instruction(&mut self, is_fused: bool) -> Result<bool, EngineError> {
         let vm_module_index = self.status.vm_module_index;
         let address = self.status.address;
 
-        let instruction =
-            self.resource.vm_modules[vm_module_index].instructions[address].to_owned();
-        let is_program_end = interpreter::exec_instruction(self, &instruction)?;
+        // 检查中断标记
+        // 中断发生在执行指令之前，中断之后清除标记，以便之后继续执行。
+        if self.interrupt_flag.load(Ordering::Relaxed) {
+            self.interrupt_flag.store(false, Ordering::Relaxed);
+
+            return Err(EngineError::Interrupt(Interrupt::Interrupted {
+                vm_module_index,
+                function_index: self.status.function_index,
+                address,
+            }));
+        }
+
+        // 在执行指令之前扣除燃料，
+        // 燃料不足时指令不会被执行，VM 的状态以及栈均保持不变。
+        self.consume_fuel(vm_module_index, address)?;
+
+        // 编译后的指令只包含处理函数的指针及立即数，复制的开销很小
+        let vm_module = &self.resource.vm_modules[vm_module_index];
+        let compiled_instruction = if is_fused {
+            vm_module.fused_instructions[address]
+        } else {
+            vm_module.compiled_instructions[address]
+        };
 
-        Ok(is_program_end)
+        self.last_hit_breakpoint = None;
+        match (compiled_instruction.handler)(self, &compiled_instruction.operand) {
+            // 补上操作数栈操作数不足时的陷阱的回溯信息
+            Err(EngineError::Trap(Trap {
+                code: TrapCode::StackUnderflow,
+                ..
+            })) => Err(make_trap_engine_error(self, TrapCode::StackUnderflow)),
+            result => result,
+        }
     }
 
     /// 执行当前一条指令
@@ -424,386 +1145,337 @@ impl VM {
     /// 也就是说，遇到普通函数调用时，不跟踪进入函数的内部。
     ///
     /// 如果遇到断点，仍然会优先中断
-    pub fn step_without_into(&mut self) -> Result<bool, EngineError> {
-        todo!()
+    ///
+    /// 注意：
+    /// 当前的这一条指令总是会被执行，即使它所在的位置设有断点。
+    pub fn step_without_into(&mut self) -> Result<ExecutionResult, EngineError> {
+        // 进入结构块不会改变调用帧的数量，而每进入一层函数调用，调用帧的数量都会加 1。
+        // 因此只要调用帧的数量大于当前值，说明 VM 仍处于被调用的函数（及其
+        // 进一步调用的函数）之中。
+        let call_depth = self.call_depth;
+
+        let is_program_end = self.step()?;
+        if is_program_end {
+            return Ok(ExecutionResult::ProgramEnd);
+        }
+
+        self.recur_while(|vm| vm.call_depth > call_depth)
     }
 
     /// 执行一系列指令，直到当前函数的所有指令结束为止，
     /// 即把函数的最后一条指令，`end 指令` 执行完毕，并停留在调用该函数的指令的下一条指令。
     ///
+    /// 如果当前处于结构块之中，仍然是执行到所在函数结束为止，而不是仅仅跳出当前结构块。
+    ///
     /// 如果遇到断点，仍然会优先中断
-    pub fn jump_out(&mut self) -> Result<bool, EngineError> {
-        todo!()
+    pub fn jump_out(&mut self) -> Result<ExecutionResult, EngineError> {
+        // 当前函数的调用帧被弹出之后，调用帧的数量会减 1。
+        let call_depth = self.call_depth;
+
+        let is_program_end = self.step()?;
+        if is_program_end {
+            return Ok(ExecutionResult::ProgramEnd);
+        }
+
+        self.recur_while(|vm| vm.call_depth >= call_depth)
     }
 
     /// 压入函数调用帧
     ///
+    /// 参数 frame_kind 为 FrameKind::HostCall 或者 FrameKind::Call
+    ///
     /// 额外操作
     /// - 分配局部变量空槽
     /// - 更新跟栈帧部分相关的 status
+    ///
+    /// 如果超出调用帧数量或者栈总大小的上限，则返回 TrapCode::StackExhausted 陷阱，
+    /// 此时 VM 的状态以及栈均保持不变。
     pub fn push_call_frame(
         &mut self,
+        frame_kind: FrameKind,
         parameters_count: usize,
         local_variable_types: &[ValueType],
         return_address: usize,
-    ) {
-        let status = &mut self.status;
-
-        // 读取旧的 status
-        let previous_frame_pointer = status.frame_pointer;
-        let previous_local_pointer = status.local_pointer;
-        let previous_base_pointer = status.base_pointer;
-        let return_vm_module_index = status.vm_module_index;
-        let return_function_index = status.function_index;
-        let previous_frame_type = status.frame_type.clone();
+    ) -> Result<(), EngineError> {
+        self.check_call_stack_limit(local_variable_types.len())?;
+        self.call_depth += 1;
 
+        let status = &mut self.status;
         let stack = &mut self.stack;
-        let previous_stack_pointer = stack.get_size();
 
         // 栈帧的起始位置为：
         //
         // 原栈顶（sp）- 参数数量
-        let frame_pointer = previous_stack_pointer - parameters_count;
-
-        // 对于 call frame，local pointer 的值跟 frame pointer 一致
-        let local_pointer = frame_pointer;
-
-        // 信息段的开始位置为：
-        //
-        // - 原栈顶（sp） + 函数内局部变量（不包括参数）数量
-        // - 当前栈帧的开始位置（fp）+ 局部变量（包括参数）数量
-        //
-        // 两个数值是一样的
-        let base_pointer = previous_stack_pointer + local_variable_types.len();
+        let frame_pointer = stack.get_size() - parameters_count;
+
+        stack.push_frame(ControlFrame {
+            kind: frame_kind,
+            frame_pointer,
+            return_local_pointer: status.local_pointer,
+            return_base_pointer: status.base_pointer,
+            return_vm_module_index: status.vm_module_index,
+            return_function_index: status.function_index,
+            return_frame_type: status.frame_type,
+            return_address,
+        });
 
         // 分配局部变量空槽
+        // 数值类型的局部变量初始值为 0，引用类型的初始值为空引用
         for variable_type in local_variable_types {
-            match variable_type {
-                ValueType::I32 => stack.push(Value::I32(0)),
-                ValueType::I64 => stack.push(Value::I64(0)),
-                ValueType::F32 => stack.push(Value::F32(0.0)),
-                ValueType::F64 => stack.push(Value::F64(0.0)),
-            }
+            stack.push_value(&Value::default_of(variable_type));
         }
 
-        let (frame_type_class, frame_type_value) = convert_from_frame_type(&previous_frame_type);
-
-        // 写信息段
-        stack.push(previous_frame_pointer.into());
-        stack.push(previous_local_pointer.into());
-        stack.push(previous_base_pointer.into());
-        stack.push(return_vm_module_index.into());
-        stack.push(return_function_index.into());
-        stack.push(frame_type_class.into()); // 使用两个 Value::I64 来记录 frame_type
-        stack.push(frame_type_value.into());
-        stack.push(return_address.into());
-
         // 更新跟栈帧位置信息相关的部分 status
-        status.frame_pointer = frame_pointer;
-        status.local_pointer = local_pointer;
-        status.base_pointer = base_pointer;
+        //
+        // - 对于调用帧，local pointer 的值跟 frame pointer 一致
+        // - 运算操作数段紧跟在局部变量（包括参数）之后
+        status.local_pointer = frame_pointer;
+        status.base_pointer = stack.get_size();
+
+        Ok(())
     }
 
-    /// 压入控制块帧
+    /// 压入结构块帧
+    ///
+    /// 结构块的参数（如果有的话）应该已经位于栈顶，它们将成为新栈帧的第一批操作数。
     ///
     /// 额外操作：
     /// - 更新跟栈帧部分相关的 status
-    pub fn push_control_frame(&mut self, return_address: usize) {
+    ///
+    /// 因为结构块帧没有局部变量段，也不占用操作数栈的空间，所以无需检查栈的上限。
+    pub fn push_control_frame(&mut self, parameters_count: usize, return_address: usize) {
         let status = &mut self.status;
-
-        // 读取旧的 status
-        let previous_frame_pointer = status.frame_pointer;
-        let previous_local_pointer = status.local_pointer;
-        let previous_base_pointer = status.base_pointer;
-        let return_vm_module_index = status.vm_module_index;
-        let return_function_index = status.function_index;
-        let previous_frame_type = status.frame_type.clone();
-
         let stack = &mut self.stack;
-        let previous_stack_pointer = stack.get_size();
-
-        // 因为控制帧没有自己的局部变量段，所以
-        // 栈帧的起始位置为当前栈顶。
-        let frame_pointer = previous_stack_pointer;
-
-        // 对于 control frame，local pointer 的值跟上一次的 local pointer 一致
-        let local_pointer = previous_local_pointer;
-
-        // 信息段的开始位置为：
-        //
-        // - 原栈顶（sp）
-        // - 当前栈帧的开始位置（fp）+ 参数数量（结构块没有自己的局部变量）
-        //
-        // 两个数值是一样的
-        let base_pointer = previous_stack_pointer;
-
-        let (frame_type_class, frame_type_value) = convert_from_frame_type(&previous_frame_type);
 
-        // 写信息段
-        stack.push(previous_frame_pointer.into());
-        stack.push(previous_local_pointer.into());
-        stack.push(previous_base_pointer.into());
-        stack.push(return_vm_module_index.into());
-        stack.push(return_function_index.into());
-        stack.push(frame_type_class.into()); // 使用两个 Value::I64 来记录 frame_type
-        stack.push(frame_type_value.into());
-        stack.push(return_address.into());
+        // 因为结构块帧没有自己的局部变量段，所以
+        // 栈帧的起始位置（同时也是运算操作数段的开始位置）为第 0 个参数的位置。
+        let frame_pointer = stack.get_size() - parameters_count;
+
+        stack.push_frame(ControlFrame {
+            kind: FrameKind::Block,
+            frame_pointer,
+            return_local_pointer: status.local_pointer,
+            return_base_pointer: status.base_pointer,
+            return_vm_module_index: status.vm_module_index,
+            return_function_index: status.function_index,
+            return_frame_type: status.frame_type,
+            return_address,
+        });
 
-        // 更新跟栈帧位置信息相关的部分 status
-        status.frame_pointer = frame_pointer;
-        status.local_pointer = local_pointer;
-        status.base_pointer = base_pointer;
+        // 对于结构块帧，local pointer 的值跟上一次的 local pointer 一致
+        status.base_pointer = frame_pointer;
     }
 
     /// 弹出栈帧
     ///
     /// 此函数不会改变 VM 的 program counter，而是将
-    /// 待更新的 pc 值返回，然后让调用者决定如何更新 pc 值。
+    /// 被弹出的控制帧返回，然后让调用者决定如何更新 pc 值。
     ///
     /// 额外操作：
-    /// - 函数或结构块的返回值将会暂存，并在弹出栈帧后重新压入栈
-    /// - 更新但跟栈帧部分相关的 status
-    pub fn pop_frame(
-        &mut self,
-        results_count: usize,
-    ) -> (
-        /* vm_module_index */ usize,
-        /* function_index */ usize,
-        /* frame_type */ BlockType,
-        /* address */ usize,
-    ) {
-        let status = &mut self.status;
-        let stack = &mut self.stack;
-
-        // 读取当前的 status
-        let frame_pointer = status.frame_pointer;
-        let base_pointer = status.base_pointer;
-
-        // 读取信息段
-        let previous_frame_pointer: usize = stack.get_value(base_pointer).into();
-        let previous_local_pointer: usize = stack.get_value(base_pointer + 1).into();
-        let previous_base_pointer: usize = stack.get_value(base_pointer + 2).into();
-        let return_vm_module_index: usize = stack.get_value(base_pointer + 3).into();
-        let return_function_index: usize = stack.get_value(base_pointer + 4).into();
-        let frame_type_class: usize = stack.get_value(base_pointer + 5).into(); // 使用两个 Value::I64 来还原 frame_type
-        let frame_type_value: usize = stack.get_value(base_pointer + 6).into();
-        let return_address: usize = stack.get_value(base_pointer + 7).into();
-
-        // 先保存一份返回值
-        let results = stack.pop_values(results_count);
-
-        // 删除当前栈帧
-        stack.drop_values_at(frame_pointer);
-
-        // 重新压入返回值
-        stack.push_values(&results);
-
-        // 更新跟栈帧位置信息相关的部分 status
-        status.frame_pointer = previous_frame_pointer;
-        status.local_pointer = previous_local_pointer;
-        status.base_pointer = previous_base_pointer;
+    /// - 保留栈顶的返回值，丢弃栈帧的其余数据
+    /// - 更新跟栈帧部分相关的 status
+    pub fn pop_frame(&mut self, results_count: usize) -> ControlFrame {
+        let frame = self.stack.pop_frame();
 
-        let frame_type = convert_to_frame_type(frame_type_class, frame_type_value);
+        if frame.is_call_frame() {
+            self.call_depth -= 1;
+        }
 
-        (
-            return_vm_module_index,
-            return_function_index,
-            frame_type,
-            return_address,
-        )
+        self.restore_frame(&frame, results_count);
+        frame
     }
 
-    /// 弹出指定层数的栈帧
+    /// 弹出指定层数的结构块帧，即跳转到外层的结构块
     ///
-    /// 注意该方法不会更新任何 status 数据，因为一般在调用这个方法之前
-    /// 调用者会先调用 get_status_by_relative_depth() 方法获取目标
-    /// 栈帧的资料，所以这里就不再重复获取并维护这些资料。
-    pub fn pop_frames(
-        &mut self,
-        relative_depth: usize,
-        results_count: usize,
-    ) -> (
-        /* vm_module_index */ usize,
-        /* function_index */ usize,
-        /* frame_type */ BlockType,
-        /* address */ usize,
-    ) {
-        if relative_depth == 0 {
-            // 没有弹出任何栈帧，直接返回当前栈帧信息
-            let status = &self.status;
-            (
-                status.vm_module_index,
-                status.function_index,
-                status.frame_type.clone(),
-                status.address,
-            )
-        } else {
-            let status = &mut self.status;
-            let stack = &mut self.stack;
-
-            // 读取当前的 status
-            let mut frame_pointer = status.frame_pointer; // frame_pointer 为将要丢弃的栈帧的位置
-            let mut base_pointer = status.base_pointer;
-
-            // 从栈回溯，找到指定层级的状态
-            for _ in 0..(relative_depth - 1) {
-                let previous_frame_pointer: usize = stack.get_value(base_pointer).into();
-                let previous_base_pointer: usize = stack.get_value(base_pointer + 2).into();
-                frame_pointer = previous_frame_pointer;
-                base_pointer = previous_base_pointer;
-            }
-
-            let previous_frame_pointer: usize = stack.get_value(base_pointer).into();
-            let previous_local_pointer: usize = stack.get_value(base_pointer + 1).into();
-            let previous_base_pointer: usize = stack.get_value(base_pointer + 2).into();
-            let return_vm_module_index: usize = stack.get_value(base_pointer + 3).into();
-            let return_function_index: usize = stack.get_value(base_pointer + 4).into();
-            // 使用两个 Value::I64 来还原 frame_type
-            let frame_type_class: usize = stack.get_value(base_pointer + 5).into();
-            let frame_type_value: usize = stack.get_value(base_pointer + 6).into();
-            let return_address: usize = stack.get_value(base_pointer + 7).into();
-
-            // 先保存一份返回值
-            let results = stack.pop_values(results_count);
-
-            // 丢弃自 frame_pointer 之后的所有数据
-            stack.drop_values_at(frame_pointer);
-
-            // 重新压入返回值
-            stack.push_values(&results);
-
-            // 更新跟栈帧位置信息相关的部分 status
-            status.frame_pointer = previous_frame_pointer;
-            status.local_pointer = previous_local_pointer;
-            status.base_pointer = previous_base_pointer;
-
-            let frame_type = convert_to_frame_type(frame_type_class, frame_type_value);
-
-            (
-                return_vm_module_index,
-                return_function_index,
-                frame_type,
-                return_address,
-            )
-        }
+    /// 返回最外层被弹出的控制帧，其中的 return_* 字段即为跳转目标的状态。
+    ///
+    /// 因为跳转指令不会跨越函数，所以被弹出的只会是结构块的栈帧，
+    /// 调用帧的数量不需要更新。
+    pub fn pop_frames(&mut self, relative_depth: usize, results_count: usize) -> ControlFrame {
+        let frame = self.stack.pop_frames(relative_depth);
+        self.restore_frame(&frame, results_count);
+        frame
     }
 
-    pub fn get_status_by_relative_depth(&self, relative_depth: usize) -> Status {
-        if relative_depth == 0 {
-            // 当相对层级为 0 时，
-            // 克隆一份当前的虚拟机状态即可。
-            self.status.clone()
-        } else {
-            let status = &self.status;
-            let stack = &self.stack;
+    /// 丢弃被弹出的栈帧的数据（保留栈顶的返回值），并恢复上一帧的状态
+    fn restore_frame(&mut self, frame: &ControlFrame, results_count: usize) {
+        self.stack
+            .drop_values_keep_top(frame.frame_pointer, results_count);
 
-            let mut base_pointer = status.base_pointer;
+        let status = &mut self.status;
+        status.local_pointer = frame.return_local_pointer;
+        status.base_pointer = frame.return_base_pointer;
+    }
 
-            // 从栈回溯，找到指定层级的状态
-            for _ in 0..(relative_depth - 1) {
-                let previous_base_pointer: usize = stack.get_value(base_pointer + 2).into();
-                base_pointer = previous_base_pointer;
-            }
+    /// 获取当前栈帧运算操作数段的操作数的数量
+    pub fn get_operands_count(&self) -> usize {
+        self.stack.get_size() - self.status.base_pointer
+    }
 
-            // 读取信息段
-            let previous_frame_pointer: usize = stack.get_value(base_pointer).into();
-            let previous_local_pointer: usize = stack.get_value(base_pointer + 1).into();
-            let previous_base_pointer: usize = stack.get_value(base_pointer + 2).into();
-            let return_vm_module_index: usize = stack.get_value(base_pointer + 3).into();
-            let return_function_index: usize = stack.get_value(base_pointer + 4).into();
-            // 使用两个 Value::I64 来还原 frame_type
-            let frame_type_class: usize = stack.get_value(base_pointer + 5).into();
-            let frame_type_value: usize = stack.get_value(base_pointer + 6).into();
-            let return_address: usize = stack.get_value(base_pointer + 7).into();
-
-            let frame_type = convert_to_frame_type(frame_type_class, frame_type_value);
-
-            Status {
-                frame_pointer: previous_frame_pointer,
-                local_pointer: previous_local_pointer,
-                base_pointer: previous_base_pointer,
-
-                vm_module_index: return_vm_module_index,
-                function_index: return_function_index,
-                frame_type: frame_type,
-                address: return_address,
+    /// 获取当前函数的局部变量（包括参数）的值
+    ///
+    /// 操作数栈只储存无类型的槽，该方法根据当前函数的参数和局部变量的
+    /// 数据类型将槽转换为数值，供调试器等宿主查看。
+    pub fn get_local_variable_values(&self) -> Vec<Value> {
+        let vm_module = &self.resource.vm_modules[self.status.vm_module_index];
+        match &vm_module.function_items[self.status.function_index] {
+            FunctionItem::Normal {
+                type_index,
+                internal_function_index,
+                ..
+            } => {
+                let function_type = &vm_module.function_types[*type_index];
+                let local_variable_types = &vm_module.internal_function_local_variable_types_list
+                    [*internal_function_index];
+
+                function_type
+                    .params
+                    .iter()
+                    .chain(local_variable_types.iter())
+                    .enumerate()
+                    .map(|(index, value_type)| {
+                        self.stack
+                            .get_value(self.status.local_pointer + index, value_type)
+                    })
+                    .collect()
             }
+            FunctionItem::Native { .. } => vec![],
         }
     }
 
-    /// 对一个常量表达式求值
-    /// 目前只支持一个 `t.const 指令` 和一个 `end 指令` 这种常量表达式
+    /// 对指定模块内的一个常量表达式求值
+    ///
+    /// 常量表达式里的 `global.get` 指令读取的是该模块的全局变量
     pub fn eval_constant_expression(
-        &mut self,
+        &self,
+        vm_module_index: usize,
         instructions: &[instruction::Instruction],
     ) -> Result<Value, EngineError> {
-        VM::get_constant_instruction_value(instructions)
+        let global_values = self.resource.vm_modules[vm_module_index]
+            .global_variable_indexes
+            .iter()
+            .map(|index| self.resource.global_variables[*index].get_value())
+            .collect::<Vec<Value>>();
+
+        VM::get_constant_instruction_value(
+            instructions,
+            &global_values,
+            self.resource.function_reference_bases[vm_module_index],
+        )
     }
 
+    /// 对一个常量表达式求值
+    ///
+    /// 常量表达式的指令序列跟普通指令序列一样使用操作数栈求值，
+    /// 表达式结束时操作数栈必须有且只有一个值。
+    ///
+    /// global_values 是模块的全局变量（包括导入的）的值列表，
+    /// 供 `global.get` 指令读取。
+    ///
+    /// function_reference_base 是模块的函数引用的起始编号，`ref.func` 指令
+    /// 的结果为起始编号加上函数索引。
     pub fn get_constant_instruction_value(
         instructions: &[instruction::Instruction],
+        global_values: &[Value],
+        function_reference_base: u32,
     ) -> Result<Value, EngineError> {
-        if let Some(first) = instructions.first() {
-            let value = match first {
+        let mut stack: Vec<Value> = vec![];
+
+        for inst in instructions {
+            let value = match inst {
                 instruction::Instruction::I32Const(v) => Value::I32(*v),
                 instruction::Instruction::I64Const(v) => Value::I64(*v),
                 instruction::Instruction::F32Const(v) => Value::F32(*v),
                 instruction::Instruction::F64Const(v) => Value::F64(*v),
+                instruction::Instruction::RefNull(ref_type) => Value::default_of(ref_type),
+                instruction::Instruction::RefFunc(function_index) => {
+                    Value::FuncRef(Some(function_reference_base + *function_index))
+                }
+                instruction::Instruction::GlobalGet(global_variable_index) => {
+                    *global_values.get(*global_variable_index as usize).ok_or(
+                        EngineError::OutOfRange(OutOfRange::GlobalVariableIndexOutOfRange(
+                            *global_variable_index as usize,
+                            global_values.len(),
+                        )),
+                    )?
+                }
+                instruction::Instruction::I32Add
+                | instruction::Instruction::I32Sub
+                | instruction::Instruction::I32Mul
+                | instruction::Instruction::I64Add
+                | instruction::Instruction::I64Sub
+                | instruction::Instruction::I64Mul => {
+                    let right = stack.pop();
+                    let left = stack.pop();
+                    eval_constant_binary_instruction(inst, left, right)?
+                }
+                instruction::Instruction::End => {
+                    break;
+                }
                 _ => {
                     return Err(EngineError::Unsupported(
-                        Unsupported::UnsupportedConstantExpressionInstruction(first.to_owned()),
+                        Unsupported::UnsupportedConstantExpressionInstruction(inst.to_owned()),
                     ))
                 }
             };
-            Ok(value)
+
+            stack.push(value);
+        }
+
+        if stack.len() == 1 {
+            Ok(stack.remove(0))
         } else {
-            // parser 应该事先检查常量表示式不为空
-            unreachable!("empty constant expression")
+            Err(EngineError::InvalidOperation(
+                InvalidOperation::IncorrectConstantExpressionResultCount(stack.len()),
+            ))
         }
     }
 }
 
-fn convert_from_frame_type(
-    block_type: &BlockType,
-) -> (
-    /* frame_type_class */ usize,
-    /* frame_type_value */ usize,
-) {
-    match block_type {
-        BlockType::ResultEmpty => {
-            (1, 0) // class 1
+/// 求扩展常量表达式（extended-const）里的整数算术指令的值
+///
+/// 跟 `ins_numeric_binary` 里的指令一样，整数运算溢出时回绕。
+fn eval_constant_binary_instruction(
+    inst: &instruction::Instruction,
+    left: Option<Value>,
+    right: Option<Value>,
+) -> Result<Value, EngineError> {
+    let value = match (inst, &left, &right) {
+        (instruction::Instruction::I32Add, Some(Value::I32(l)), Some(Value::I32(r))) => {
+            Value::I32(l.wrapping_add(*r))
         }
-        BlockType::ResultI32 => {
-            (1, 1) // class 1
+        (instruction::Instruction::I32Sub, Some(Value::I32(l)), Some(Value::I32(r))) => {
+            Value::I32(l.wrapping_sub(*r))
         }
-        BlockType::ResultI64 => {
-            (1, 2) // class 1
+        (instruction::Instruction::I32Mul, Some(Value::I32(l)), Some(Value::I32(r))) => {
+            Value::I32(l.wrapping_mul(*r))
         }
-        BlockType::ResultF32 => {
-            (1, 3) // class 1
+        (instruction::Instruction::I64Add, Some(Value::I64(l)), Some(Value::I64(r))) => {
+            Value::I64(l.wrapping_add(*r))
         }
-        BlockType::ResultF64 => {
-            (1, 4) // class 1
+        (instruction::Instruction::I64Sub, Some(Value::I64(l)), Some(Value::I64(r))) => {
+            Value::I64(l.wrapping_sub(*r))
         }
-        BlockType::TypeIndex(value) => {
-            (0, *value as usize) // class 0
+        (instruction::Instruction::I64Mul, Some(Value::I64(l)), Some(Value::I64(r))) => {
+            Value::I64(l.wrapping_mul(*r))
         }
-    }
-}
+        _ => {
+            let (instruction_name, value_type) = match inst {
+                instruction::Instruction::I32Add => ("i32.add", ValueType::I32),
+                instruction::Instruction::I32Sub => ("i32.sub", ValueType::I32),
+                instruction::Instruction::I32Mul => ("i32.mul", ValueType::I32),
+                instruction::Instruction::I64Add => ("i64.add", ValueType::I64),
+                instruction::Instruction::I64Sub => ("i64.sub", ValueType::I64),
+                _ => ("i64.mul", ValueType::I64),
+            };
 
-fn convert_to_frame_type(frame_type_class: usize, frame_type_value: usize) -> BlockType {
-    match frame_type_class {
-        // class 1
-        1 => match frame_type_value {
-            0 => BlockType::ResultEmpty,
-            1 => BlockType::ResultI32,
-            2 => BlockType::ResultI64,
-            3 => BlockType::ResultF32,
-            4 => BlockType::ResultF64,
-            _ => unreachable!("no this frame type value"),
-        },
-        // class 0
-        0 => BlockType::TypeIndex(frame_type_value as u32),
-        _ => unreachable!("no this frame type value"),
-    }
+            return Err(make_operand_data_types_mismatch_engine_error(
+                instruction_name,
+                vec![value_type.clone(), value_type],
+                [&left, &right].iter().filter_map(|v| v.as_ref()).collect(),
+            ));
+        }
+    };
+
+    Ok(value)
 }
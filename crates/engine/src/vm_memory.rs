@@ -6,11 +6,14 @@
 
 use anvm_ast::ast::{Limit, MemoryType};
 
-use crate::error::{EngineError, Overflow};
+use crate::{
+    error::{EngineError, Overflow},
+    trap::TrapCode,
+};
 
 /// 内存的容量单位是 `页`（`page`）
 /// 一页内存为 65536 个字节
-const PAGE_SIZE: u32 = 65536;
+pub(crate) const PAGE_SIZE: u32 = 65536;
 
 /// WebAssembly 约定内存块最大只能有 65536 个页面
 const MAX_PAGES: u32 = 65536;
@@ -60,6 +63,11 @@ impl VMMemory {
         self.data.len() as u32 / PAGE_SIZE
     }
 
+    /// 内存块的总字节数
+    pub fn get_byte_count(&self) -> usize {
+        self.data.len()
+    }
+
     /// 返回原先的页面数
     pub fn increase_page(&mut self, increase_page_number: u32) -> Result<u32, EngineError> {
         let old_page_count = self.get_page_count();
@@ -88,10 +96,58 @@ impl VMMemory {
         Ok(old_page_count)
     }
 
+    /// 检查内存块是否允许拥有指定的页面数
+    pub(crate) fn is_valid_page_count(&self, page_count: u32) -> bool {
+        let max_page = match self.memory_type.limit {
+            Limit::Range(_, max_page) => max_page.min(MAX_PAGES),
+            Limit::AtLeast(_) => MAX_PAGES,
+        };
+
+        page_count >= self.memory_type.limit.get_min() && page_count <= max_page
+    }
+
+    /// 替换内存块的全部数据，用于恢复 VM 快照
+    ///
+    /// 调用者需要确保数据的长度是页面大小的整数倍，且页面数是有效的。
+    pub(crate) fn replace_data(&mut self, data: Vec<u8>) {
+        self.data = data;
+    }
+
     pub fn get_memory_type(&self) -> &MemoryType {
         &self.memory_type
     }
 
+    /// 检查从 address 开始、长度为 length 的范围是否在内存块的范围之内
+    pub fn is_valid_range(&self, address: usize, length: usize) -> bool {
+        match address.checked_add(length) {
+            Some(end) => end <= self.data.len(),
+            None => false,
+        }
+    }
+
+    /// 读取指定范围的字节，超出内存块的范围时返回 `MemoryOutOfBounds`
+    ///
+    /// 用于读取由外部（比如本地函数的参数）所指定的地址
+    pub fn checked_read_bytes(&self, address: usize, length: usize) -> Result<&[u8], TrapCode> {
+        if self.is_valid_range(address, length) {
+            Ok(self.read_bytes(address, length))
+        } else {
+            Err(TrapCode::MemoryOutOfBounds)
+        }
+    }
+
+    /// 写入字节到指定的地址，超出内存块的范围时返回 `MemoryOutOfBounds`
+    pub fn checked_write_bytes(&mut self, address: usize, data: &[u8]) -> Result<(), TrapCode> {
+        if self.is_valid_range(address, data.len()) {
+            self.write_bytes(address, data);
+            Ok(())
+        } else {
+            Err(TrapCode::MemoryOutOfBounds)
+        }
+    }
+
+    /// 注意，`read_*` 和 `write_*` 等方法不检查访问的范围，
+    /// 调用者需要事先使用 `is_valid_range` 方法检查。
     pub fn read_bytes(&self, address: usize, length: usize) -> &[u8] {
         &self.data[address..(address + length)]
     }
@@ -102,6 +158,17 @@ impl VMMemory {
         }
     }
 
+    /// 将指定范围内的字节全部设置为 value
+    pub fn fill_bytes(&mut self, address: usize, value: u8, length: usize) {
+        self.data[address..(address + length)].fill(value)
+    }
+
+    /// 复制指定范围内的字节，源范围和目标范围允许重叠
+    pub fn copy_bytes(&mut self, source_address: usize, dest_address: usize, length: usize) {
+        self.data
+            .copy_within(source_address..(source_address + length), dest_address)
+    }
+
     pub fn read_i8(&self, address: usize) -> i8 {
         let bytes = self.read_bytes(address, 1);
         bytes[0] as i8
@@ -165,7 +232,10 @@ impl VMMemory {
 
 #[cfg(test)]
 mod tests {
-    use crate::error::{EngineError, Overflow};
+    use crate::{
+        error::{EngineError, Overflow},
+        trap::TrapCode,
+    };
 
     use super::VMMemory;
 
@@ -212,6 +282,32 @@ mod tests {
         assert_eq!(m0.read_bytes(0, 8), vec![11, 22, 33, 44, 55, 66, 0, 0]);
     }
 
+    #[test]
+    fn test_checked_read_write_bytes() {
+        let mut m0 = VMMemory::new_by_min_page(1);
+
+        assert!(m0.is_valid_range(0, 65536));
+        assert!(m0.is_valid_range(65536, 0));
+        assert!(!m0.is_valid_range(65535, 2));
+        assert!(!m0.is_valid_range(65537, 0));
+        assert!(!m0.is_valid_range(usize::MAX, 2));
+
+        assert_eq!(m0.checked_write_bytes(65534, &vec![11, 22]), Ok(()));
+        assert_eq!(m0.checked_read_bytes(65534, 2), Ok(&[11u8, 22][..]));
+
+        assert_eq!(
+            m0.checked_write_bytes(65535, &vec![11, 22]),
+            Err(TrapCode::MemoryOutOfBounds)
+        );
+        assert_eq!(
+            m0.checked_read_bytes(65535, 2),
+            Err(TrapCode::MemoryOutOfBounds)
+        );
+
+        // 写入失败时不应该修改任何数据
+        assert_eq!(m0.read_bytes(65534, 2), vec![11, 22]);
+    }
+
     #[test]
     fn test_read_write_numbers() {
         let mut m0 = VMMemory::new_by_min_page(1);
@@ -4,25 +4,39 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
-use anvm_ast::{ast::FunctionType, types::ValueType};
+use anvm_ast::{
+    ast::{ExportDescriptor, ExportItem, FunctionType},
+    name_package::NamePackage,
+    types::ValueType,
+};
 
-use crate::object::{FunctionItem, Instruction};
+use crate::{
+    decoder::fuse_instructions,
+    interpreter::{compile, CompiledInstruction},
+    object::{FunctionItem, Instruction},
+};
 
 pub struct VMModule {
     /// 模块的名称
     pub name: String,
 
-    /// 当前模块的表在 VM 表实例里的索引
-    /// 目前 WebAssembly 只允许定义一张表
-    pub table_index: usize,
+    /// 当前模块的表在 VM 表实例列表里的索引
+    /// 包括导入的表和模块内定义的表
+    pub table_indexes: Vec<usize>,
 
-    /// 当前模块的内存块在 VM 内存块实例里的索引
-    /// 目前 WebAssembly 只允许定义一块内存
-    pub memory_index: usize,
+    /// 当前模块的内存块在 VM 内存块实例列表里的索引
+    /// 包括导入的内存块和模块内定义的内存块
+    pub memory_block_indexes: Vec<usize>,
 
     /// 当前模块的全局变量在 VM 全局变量实例列表里的索引
     pub global_variable_indexes: Vec<usize>,
 
+    /// 当前模块的数据段在 VM 数据段实例列表里的索引
+    pub data_segment_indexes: Vec<usize>,
+
+    /// 当前模块的元素段在 VM 元素段实例列表里的索引
+    pub element_segment_indexes: Vec<usize>,
+
     /// 复制一份 `类型列表`
     /// 调用函数时，需要这个函数类型表来确定实参和返回值的数量
     pub function_types: Vec<FunctionType>,
@@ -40,29 +54,125 @@ pub struct VMModule {
     pub function_items: Vec<FunctionItem>,
 
     /// 指令列表
+    ///
+    /// 用于计算燃料消耗、调试器显示指令，以及读取 `br_table 指令` 的跳转目标列表
     pub instructions: Vec<Instruction>,
+
+    /// 编译后的指令列表，跟指令列表一一对应
+    ///
+    /// VM 实际执行的是编译后的指令
+    pub compiled_instructions: Vec<CompiledInstruction>,
+
+    /// 合并了超级指令之后再编译的指令列表，跟指令列表一一对应
+    ///
+    /// 超级指令位于被合并的原指令序列的第一条指令的位置，其余位置的指令
+    /// 跟 compiled_instructions 一致。
+    pub fused_instructions: Vec<CompiledInstruction>,
+
+    /// 复制一份 `导出项列表`
+    /// 用于从 vm 外部通过导出名称查找函数等对象
+    pub export_items: Vec<ExportItem>,
+
+    /// 模块的 `名称段` 信息
+    /// 用于在陷阱的调用栈回溯信息里显示函数的名称
+    pub name_package: NamePackage,
+
+    /// 模块内容的哈希值，即 `NamedAstModule::content_hash`
+    ///
+    /// 用于在恢复 VM 快照时检查 VM 是否由相同的模块创建，参见 `snapshot` 模块
+    pub content_hash: u64,
 }
 
 impl VMModule {
     pub fn new(
         name: String,
-        table_index: usize,
-        memory_index: usize,
+        table_indexes: Vec<usize>,
+        memory_block_indexes: Vec<usize>,
         global_variable_indexes: Vec<usize>,
+        data_segment_indexes: Vec<usize>,
+        element_segment_indexes: Vec<usize>,
         function_types: Vec<FunctionType>,
         internal_function_local_variable_types_list: Vec<Vec<ValueType>>,
         function_items: Vec<FunctionItem>,
         instructions: Vec<Instruction>,
+        export_items: Vec<ExportItem>,
+        name_package: NamePackage,
+        content_hash: u64,
     ) -> Self {
         Self {
             name,
-            table_index,
-            memory_index,
+            table_indexes,
+            memory_block_indexes,
             global_variable_indexes,
+            data_segment_indexes,
+            element_segment_indexes,
             function_types,
             internal_function_local_variable_types_list,
             function_items,
+            compiled_instructions: compile(&instructions),
+            fused_instructions: compile(&fuse_instructions(&instructions)),
             instructions,
+            export_items,
+            name_package,
+            content_hash,
         }
     }
+
+    /// 查找指定名称的导出函数的索引
+    pub fn find_export_function_index(&self, export_function_name: &str) -> Option<usize> {
+        self.export_items
+            .iter()
+            .find_map(|item| match &item.export_descriptor {
+                ExportDescriptor::FunctionIndex(i) if item.name == export_function_name => {
+                    Some(*i as usize)
+                }
+                _ => None,
+            })
+    }
+
+    /// 查找指定索引的函数的导出名称
+    pub fn find_export_function_name(&self, function_index: usize) -> Option<String> {
+        self.export_items
+            .iter()
+            .find_map(|item| match &item.export_descriptor {
+                ExportDescriptor::FunctionIndex(i) if *i as usize == function_index => {
+                    Some(item.name.clone())
+                }
+                _ => None,
+            })
+    }
+
+    /// 查找指定名称的导出全局变量在模块内的索引
+    pub fn find_export_global_variable_index(&self, export_name: &str) -> Option<usize> {
+        self.export_items
+            .iter()
+            .find_map(|item| match &item.export_descriptor {
+                ExportDescriptor::GlobalItemIndex(i) if item.name == export_name => {
+                    Some(*i as usize)
+                }
+                _ => None,
+            })
+    }
+
+    /// 查找指定名称的导出内存块在模块内的索引
+    pub fn find_export_memory_block_index(&self, export_name: &str) -> Option<usize> {
+        self.export_items
+            .iter()
+            .find_map(|item| match &item.export_descriptor {
+                ExportDescriptor::MemoryBlockIndex(i) if item.name == export_name => {
+                    Some(*i as usize)
+                }
+                _ => None,
+            })
+    }
+
+    /// 查找指定名称的导出表在模块内的索引
+    pub fn find_export_table_index(&self, export_name: &str) -> Option<usize> {
+        self.export_items
+            .iter()
+            .find_map(|item| match &item.export_descriptor {
+                ExportDescriptor::TableIndex(i) if item.name == export_name => Some(*i as usize),
+                _ => None,
+            })
+    }
 }
@@ -0,0 +1,59 @@
+// Copyright (c) 2022 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! # 数据段和元素段实例
+//!
+//! 被动模式（passive）的数据项和元素项在模块实例化之后仍然保留，
+//! 以供 `memory.init` 和 `table.init` 指令使用，直到被
+//! `data.drop` 和 `elem.drop` 指令丢弃。
+//!
+//! 主动模式（active）的数据项和元素项在模块实例化时被复制到内存块和表之后，
+//! 以及声明模式（declarative）的元素项，都会被立即丢弃。
+//!
+//! 被丢弃的段相当于一个长度为 0 的段，对其执行 `memory.init` 或者 `table.init`
+//! 指令时，只有复制长度为 0 才不会引发陷阱。
+//!
+//! https://webassembly.github.io/spec/core/exec/runtime.html#data-instances
+
+pub struct VMDataSegment {
+    data: Vec<u8>,
+}
+
+impl VMDataSegment {
+    pub fn new(data: Vec<u8>) -> Self {
+        Self { data }
+    }
+
+    pub fn get_data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// 丢弃数据，释放其占用的内存
+    pub fn drop_data(&mut self) {
+        self.data = vec![];
+    }
+}
+
+pub struct VMElementSegment {
+    /// 元素列表
+    /// 元素的值是函数索引（funcref）或者宿主的句柄（externref），None 表示空引用
+    elements: Vec<Option<u32>>,
+}
+
+impl VMElementSegment {
+    pub fn new(elements: Vec<Option<u32>>) -> Self {
+        Self { elements }
+    }
+
+    pub fn get_elements(&self) -> &[Option<u32>] {
+        &self.elements
+    }
+
+    /// 丢弃元素，释放其占用的内存
+    pub fn drop_elements(&mut self) {
+        self.elements = vec![];
+    }
+}
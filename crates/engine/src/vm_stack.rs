@@ -9,18 +9,19 @@
 //! 栈式的 VM 在逻辑上存在：
 //!
 //! 1. 记录了字节码以及程序计数器（pc）等状态信息的结构；
-//! 2. 栈，用于记录函数调用路径；
+//! 2. 控制栈，用于记录函数调用路径以及结构块的嵌套路径；
 //! 3. 运算栈，用于操作数运算，相当于体系结构里的寄存器；
 //! 4. 局部变量表；
 //!
-//! 由于后三者具有较高的相关性，所以当前的 VM 实现将后 3 者合并在一起，
-//! 由一个 VMStack 来实现，具体来说：
+//! 当前的 VM 实现将后 3 者放在 VMStack 里，其中局部变量表和运算栈合并在一起，
+//! 由一组 `槽`（slot）来实现；控制栈则单独由一组带类型的 `控制帧`（control frame）来实现。
 //!
-//! VMStack 由 `栈帧`（stack frame） 组成，一个 `栈帧` 由以下 3 个段组成：
+//! ## 操作数栈
+//!
+//! 操作数栈的每一个 `栈帧`（stack frame） 由以下 2 个段组成：
 //!
 //! 1. 局部变量段
-//! 2. 之前一帧（previous）的信息段，用于函数调用的返回
-//! 3. 运算操作数段（其中包括要传给下一个函数的实参）
+//! 2. 运算操作数段（其中包括要传给下一个函数的实参）
 //!
 //! 下面是一个函数调用所创建的 `栈帧` 示意图：
 //!
@@ -30,10 +31,6 @@
 //!                              |                    |
 //!                              | 运算操作数段         |
 //!                              |                    |
-//!                              | .................. |
-//!                              |                    |
-//!                              | 旧帧的 fp, bp 等信息 | <-- 栈帧信息段
-//!                              |                    |
 //!                              | ---- index N ----- | <-- 新 bp
 //!                              | 第 N 个局部变量空槽。 |
 //!                              | 第 0 个局部变量空槽。 | -|
@@ -43,9 +40,6 @@
 //! | ....... 原栈顶 ...... |     | ---- index 0 ----- | <-- 新 fp, lp
 //! |                      |
 //! | 运算操作数段           |
-//! | -------------------- |
-//! |                      |
-//! | 栈帧信息段。           |
 //! |                      |
 //! | -------------------- | <-- 旧 bp
 //! |                      |
@@ -63,85 +57,156 @@
 //! - 一般体系结构当中栈底一般是内存的最高位，然后栈往下（往低处）增长，
 //!   但虚拟机里使用数组来实现栈，所以这里的栈底的索引为 0，栈顶的索引比栈底的大。
 //!
-//! ## 控制帧
+//! WebAssembly 的流程控制结构块的工作方式跟函数调用非常相近，所以当前的 VM 也会因为
+//! 进入一个流程控制块而创建一个新的栈帧，跟函数调用帧不同的是，结构块的栈帧没有局部变量段，
+//! 它共享最近一次函数调用所创建的帧的局部变量，它的运算操作数段从结构块的第 0 个参数开始。
 //!
-//! 栈帧因函数调用而产生，所以栈帧信息段记录的自然是函数调用的信息，所以排除局部变量段
-//! 以及运算操作数段之后，栈也可以称为 `函数调用栈`（`call stack`），栈帧也可以称为
-//! `函数调用帧`（`call frame`），但因为 WebAssembly 的流程控制结构块的工作方式
-//! 跟函数调用非常相近，所以当前的 VM 也会因为进入一个流程控制块而创建一个新的栈帧，当离开
-//! 控制块时，也会弹出一个栈帧，因此当前的栈帧其实也包含了 `流程控制结构块的帧`（`flow control frame`），
-//! 下面简称为 `控制帧`（`control frame`）。
+//! ## 控制栈
 //!
-//! `控制帧` 跟 `调用帧` 几乎是一样的，除了：
+//! 每一个栈帧都对应着控制栈里的一个 `控制帧`，控制帧记录了：
 //!
-//! 1. 控制帧没有局部变量段，控制帧共享最近一次函数调用所创建的帧的局部变量；
-//! 2. 返回地址是结构块的最后一条指令（即 `end` 指令）所处的位置，而并非调用指令的下一条指令的位置。
+//! - 帧的种类，即宿主调用帧、函数调用帧或者结构块帧；
+//! - 栈帧在操作数栈里的开始位置（fp），弹出栈帧时，自此位置开始的数据都会被丢弃；
+//! - 帧弹出之后需要恢复的 VM 状态，即上一帧的 lp、bp、类型，以及返回地址。
 //!
-//! 下面是进入一个流程控制结构块所创建的 `栈帧` 示意图：
+//! 跳转指令（比如 `br`）跳出多层结构块时，只需从控制栈弹出相应数量的控制帧，
+//! 然后丢弃最外层被弹出的帧的操作数即可，无需遍历操作数栈。
 //!
-//! ```diagram
+//! ## 槽
 //!
-//!                              | ------------------ | <-- sp (stack pointer)
-//!                              |                    |
-//!                              | 运算操作数段         |
-//!                              |                    |
-//!                              | .................. |
-//!                              |                    |
-//!                              | 旧帧的 fp, bp 等信息 | <-- 栈帧信息段
-//!                              |                    |
-//! | ------- 栈顶。------- |     | .................. | <-- 新 bp
-//! | 第 N 个参数值。        | --> | 第 N 个参数值。      |
-//! | 第 0 个参数值。        | --> | 第 0 个参数值。      |
-//! | ....... 原栈顶 ...... |     | ---- index 0 ----- | <-- 新 fp
-//! |                      |
-//! | 运算操作数段           |
-//! | -------------------- |
-//! |                      |
-//! | 栈帧信息段。           |
-//! |                      |
-//! | -------------------- | <-- 旧 bp
-//! |                      |
-//! | 局部变量段。           |
-//! | .................... |
-//! | 来自上一个栈帧的实参。。 |
-//! |                      |
-//! | ------- 栈底。 ------ | <-- 旧 fp, 新旧 lp 均在此
+//! 操作数栈的每一个槽都是一个不带类型信息的 64 位原始数据（u64）：
 //!
-//! ## 信息段的数据
+//! - i32 和 i64 按位存放（i32 零扩展为 64 位）；
+//! - f32 和 f64 存放它们的比特位（即 `to_bits()` 的结果）；
+//! - 引用类型存放它所引用的项目的索引，空引用为 `NULL_REFERENCE_SLOT`。
 //!
-//! 信息段里存放着上一个栈帧及程序的状态信息，具体有：
+//! 因为模块在实例化之前已经通过验证，所以每一条指令的操作数的数据类型都是确定的，
+//! 指令只需按照自己所需的数据类型读写槽即可，无需在运行时逐个检查操作数的类型。
 //!
-//! 0. previous_frame_pointer
-//! 1. previous_local_pointer
-//! 2. previous_base_pointer
-//! 3. return_vm_module_index
-//! 4. return_function_index
-//! 5. frame_type_class
-//! 6. frame_type_value
-//! 7. return_address
+//! 不过弹出或者查看操作数时仍然会检查操作数栈是否为空，操作数不足时返回
+//! `StackUnderflow`，VM 会将其转换为 `TrapCode::StackUnderflow` 陷阱，而不是 panic。
 //!
-//! 其中第 5 和第 6 项合并起来表示上一帧的函数或者结构块的类型（即参数和返回值的情况）
+//! 在 VM 与外部（即宿主、本地函数以及调试器）之间传递数据时，再根据函数签名、
+//! 局部变量类型等信息，将槽转换为带类型的 `Value`。
+
+use anvm_ast::{
+    instruction::BlockType,
+    types::{Value, ValueType},
+};
+
+/// 空引用在槽里的表示
+pub const NULL_REFERENCE_SLOT: u64 = u64::MAX;
+
+/// 将数值转换为槽的原始数据
+pub fn value_to_slot(value: &Value) -> u64 {
+    match value {
+        Value::I32(v) => *v as u32 as u64,
+        Value::I64(v) => *v as u64,
+        Value::F32(v) => v.to_bits() as u64,
+        Value::F64(v) => v.to_bits(),
+        Value::FuncRef(option_index) | Value::ExternRef(option_index) => {
+            reference_to_slot(*option_index)
+        }
+    }
+}
 
-use anvm_ast::types::Value;
+/// 按照指定的数据类型，将槽的原始数据转换为数值
+pub fn slot_to_value(slot: u64, value_type: &ValueType) -> Value {
+    match value_type {
+        ValueType::I32 => Value::I32(slot as u32 as i32),
+        ValueType::I64 => Value::I64(slot as i64),
+        ValueType::F32 => Value::F32(f32::from_bits(slot as u32)),
+        ValueType::F64 => Value::F64(f64::from_bits(slot)),
+        ValueType::FuncRef => Value::FuncRef(slot_to_reference(slot)),
+        ValueType::ExternRef => Value::ExternRef(slot_to_reference(slot)),
+    }
+}
 
-/// 信息段的项目（数值）的数量
-pub const INFO_SEGMENT_ITEM_COUNT: usize = 8;
+fn reference_to_slot(option_index: Option<u32>) -> u64 {
+    match option_index {
+        Some(index) => index as u64,
+        None => NULL_REFERENCE_SLOT,
+    }
+}
 
-/// # VMStack 同时肩负作为运算栈（操作数栈）和记录栈帧信息的任务
+fn slot_to_reference(slot: u64) -> Option<u32> {
+    if slot == NULL_REFERENCE_SLOT {
+        None
+    } else {
+        Some(slot as u32)
+    }
+}
+
+/// 弹出或者查看操作数时，操作数栈的操作数不足
 ///
-/// 也就是说，除了用于记录栈帧信息，同时供给运算指令（诸如 i32.add 等）
-/// 作为运算栈，实际上这两个栈可以分开来实现，之所以将它们合并，是为了
-/// 简化程序（它们俩有很多相似的方法）。
+/// 通过验证的模块不会出现这种情况。
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct StackUnderflow;
+
+/// 控制帧的种类
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum FrameKind {
+    /// 由宿主调用函数（比如 `VM::call_function_by_index` 方法）所创建的调用帧，
+    /// 这种帧被弹出时，意味着宿主所调用的函数已经执行完毕。
+    HostCall,
+
+    /// 由 `call` 和 `call_indirect` 指令所创建的调用帧
+    Call,
+
+    /// 进入流程控制结构块（`block`、`loop` 和 `if`）所创建的帧
+    Block,
+}
+
+/// 控制帧
+#[derive(Debug, PartialEq, Clone)]
+pub struct ControlFrame {
+    pub kind: FrameKind,
+
+    /// 栈帧在操作数栈里的开始位置
+    ///
+    /// 对于调用帧，它是第 0 个实参的位置，对于结构块帧，它是结构块第 0 个参数的位置。
+    pub frame_pointer: usize,
+
+    /// 帧弹出之后需要恢复的 VM 状态
+    pub return_local_pointer: usize,
+    pub return_base_pointer: usize,
+    pub return_vm_module_index: usize,
+    pub return_function_index: usize,
+    pub return_frame_type: BlockType,
+
+    /// 返回地址
+    ///
+    /// 对于调用帧，它是调用指令的下一条指令的位置；对于结构块帧，
+    /// 它是结构块最后一条指令（即 `end` 指令）的下一条指令的位置。
+    pub return_address: usize,
+}
+
+impl ControlFrame {
+    pub fn is_call_frame(&self) -> bool {
+        self.kind != FrameKind::Block
+    }
+}
+
+/// # VMStack 同时肩负作为运算栈（操作数栈）和记录控制帧的任务
 ///
-/// 当前使用偷懒的方法 -- 数组来实现操作数栈，让 Rust 底层库
+/// 当前使用偷懒的方法 -- 数组来实现操作数栈和控制栈，让 Rust 底层库
 /// 自动管理栈的分配和容量。
 pub struct VMStack {
-    slots: Vec<Value>,
+    slots: Vec<u64>,
+    frames: Vec<ControlFrame>,
 }
 
 impl VMStack {
     pub fn new() -> Self {
-        VMStack { slots: vec![] }
+        VMStack {
+            slots: vec![],
+            frames: vec![],
+        }
+    }
+
+    /// 使用指定的槽以及控制帧创建栈，用于恢复 VM 快照
+    pub(crate) fn from_slots_and_frames(slots: Vec<u64>, frames: Vec<ControlFrame>) -> Self {
+        VMStack { slots, frames }
     }
 
     /// 获取栈的总大小
@@ -151,62 +216,115 @@ impl VMStack {
         self.slots.len()
     }
 
-    /// 压入
-    pub fn push(&mut self, value: Value) {
-        self.slots.push(value);
+    /// 压入槽的原始数据
+    pub fn push_slot(&mut self, slot: u64) {
+        self.slots.push(slot);
     }
 
-    /// 弹出
-    pub fn pop(&mut self) -> Value {
-        self.slots.pop().expect("operand stack is empty")
+    /// 弹出槽的原始数据
+    pub fn pop_slot(&mut self) -> Result<u64, StackUnderflow> {
+        self.slots.pop().ok_or(StackUnderflow)
     }
 
-    /// 查看最后一个操作数
-    pub fn peek(&self) -> Value {
-        *self.slots.last().expect("operand stack is empty")
+    /// 查看最后一个槽的原始数据
+    pub fn peek_slot(&self) -> Result<u64, StackUnderflow> {
+        self.slots.last().copied().ok_or(StackUnderflow)
+    }
+
+    /// 按索引来获取槽的原始数据
+    ///
+    /// 用于读写局部变量（局部变量包括函数调用的实参）
+    pub fn get_slot(&self, index: usize) -> u64 {
+        self.slots[index]
+    }
+
+    /// 按索引来设置槽的原始数据
+    ///
+    /// 用于读写局部变量（局部变量包括函数调用的实参）
+    pub fn set_slot(&mut self, index: usize, slot: u64) {
+        self.slots[index] = slot;
+    }
+
+    pub fn push_i32(&mut self, value: i32) {
+        self.slots.push(value as u32 as u64);
+    }
+
+    pub fn pop_i32(&mut self) -> Result<i32, StackUnderflow> {
+        Ok(self.pop_slot()? as u32 as i32)
+    }
+
+    pub fn push_i64(&mut self, value: i64) {
+        self.slots.push(value as u64);
+    }
+
+    pub fn pop_i64(&mut self) -> Result<i64, StackUnderflow> {
+        Ok(self.pop_slot()? as i64)
+    }
+
+    pub fn push_f32(&mut self, value: f32) {
+        self.slots.push(value.to_bits() as u64);
+    }
+
+    pub fn pop_f32(&mut self) -> Result<f32, StackUnderflow> {
+        Ok(f32::from_bits(self.pop_slot()? as u32))
+    }
+
+    pub fn push_f64(&mut self, value: f64) {
+        self.slots.push(value.to_bits());
+    }
+
+    pub fn pop_f64(&mut self) -> Result<f64, StackUnderflow> {
+        Ok(f64::from_bits(self.pop_slot()?))
     }
 
     /// 对于压入 bool 值的约定：
     /// 使用 i32 0 表示 false，
     /// 使用 i32 1 表示 true。
     pub fn push_bool(&mut self, value: bool) {
-        if value {
-            self.slots.push(Value::I32(1));
-        } else {
-            self.slots.push(Value::I32(0));
-        }
+        self.push_i32(value as i32);
     }
 
     /// 对于弹出 bool 值的约定：
     /// 如果数值为 i32 0，则表示 false，
     /// 如果数值为 i32 非零，则表示 true。
-    pub fn pop_bool(&mut self) -> Result<bool, Value> {
-        let value = self.pop();
-        match value {
-            Value::I32(0) => Ok(false),
-            Value::I64(_) | Value::F32(_) | Value::F64(_) => Err(value),
-            _ => Ok(true),
-        }
+    pub fn pop_bool(&mut self) -> Result<bool, StackUnderflow> {
+        Ok(self.pop_i32()? != 0)
     }
 
-    /// 按索引来获取栈的操作数
-    ///
-    /// 用于读写局部变量（局部变量包括函数调用的实参）
-    /// 以及栈信息
-    pub fn get_value(&self, index: usize) -> Value {
-        self.slots[index]
+    /// 压入引用，None 表示空引用
+    pub fn push_reference(&mut self, option_index: Option<u32>) {
+        self.slots.push(reference_to_slot(option_index));
+    }
+
+    /// 弹出引用，None 表示空引用
+    pub fn pop_reference(&mut self) -> Result<Option<u32>, StackUnderflow> {
+        Ok(slot_to_reference(self.pop_slot()?))
+    }
+
+    /// 压入一个带类型的数值
+    pub fn push_value(&mut self, value: &Value) {
+        self.slots.push(value_to_slot(value));
+    }
+
+    /// 按照指定的数据类型弹出一个数值
+    pub fn pop_value(&mut self, value_type: &ValueType) -> Result<Value, StackUnderflow> {
+        Ok(slot_to_value(self.pop_slot()?, value_type))
+    }
+
+    /// 按照指定的数据类型查看最后一个操作数
+    pub fn peek_value(&self, value_type: &ValueType) -> Result<Value, StackUnderflow> {
+        Ok(slot_to_value(self.peek_slot()?, value_type))
     }
 
-    /// 按索引来设置栈的操作数
+    /// 按索引和指定的数据类型来获取栈的操作数
     ///
-    /// 用于读写局部变量（局部变量包括函数调用的实参）
-    /// 以及栈信息
-    pub fn set_value(&mut self, index: usize, value: Value) {
-        self.slots[index] = value;
+    /// 用于调试器以及宿主查看局部变量（局部变量包括函数调用的实参）
+    pub fn get_value(&self, index: usize, value_type: &ValueType) -> Value {
+        slot_to_value(self.slots[index], value_type)
     }
 
-    /// 将一组数值原样压入栈
-    /// 此方法用于批量读写函数调用的实参以及返回值
+    /// 将一组数值压入栈
+    /// 此方法用于批量写入函数调用的实参以及返回值
     ///
     /// 小索引端的数据先压入，即靠近栈底
     /// 大索引端的数据后压入，即靠近栈顶
@@ -219,11 +337,11 @@ impl VMStack {
     ///                  |栈底。|
     /// ```
     pub fn push_values(&mut self, values: &[Value]) {
-        self.slots.extend_from_slice(values)
+        self.slots.extend(values.iter().map(value_to_slot))
     }
 
-    /// 将一组数值原样弹出栈
-    /// 此方法用于批量读写函数调用的实参以及返回值
+    /// 按照指定的一组数据类型，将一组数值弹出栈
+    /// 此方法用于批量读取函数调用的实参以及返回值
     ///
     /// 靠近栈底的数据会放置在结果的小索引端
     /// 靠近栈顶的数据会放置在结果的大索引端
@@ -235,56 +353,157 @@ impl VMStack {
     /// | 0   |
     /// |栈底。|
     /// ```
-    pub fn pop_values(&mut self, count: usize) -> Vec<Value> {
+    pub fn pop_values(&mut self, value_types: &[ValueType]) -> Result<Vec<Value>, StackUnderflow> {
+        let values = self.peek_values(value_types)?;
+        self.drop_slots(value_types.len());
+        Ok(values)
+    }
+
+    /// 按照指定的一组数据类型，查看栈顶的一组数值
+    pub fn peek_values(&self, value_types: &[ValueType]) -> Result<Vec<Value>, StackUnderflow> {
+        let index = self
+            .slots
+            .len()
+            .checked_sub(value_types.len())
+            .ok_or(StackUnderflow)?;
+
+        Ok(self.slots[index..]
+            .iter()
+            .zip(value_types)
+            .map(|(slot, value_type)| slot_to_value(*slot, value_type))
+            .collect())
+    }
+
+    /// 丢弃栈顶的指定数量的槽
+    pub fn drop_slots(&mut self, count: usize) {
         let index = self.slots.len() - count;
-        let values: Vec<Value> = self.slots.drain(index..).collect();
-        values
+        self.slots.truncate(index);
     }
 
     pub fn drop_values_at(&mut self, index: usize) {
         self.slots.drain(index..);
     }
 
-    pub fn read_slots(&self, start: usize, end: usize) -> &[Value] {
+    /// 丢弃从指定位置开始、直到栈顶最后 `keep_count` 个数值之前的所有数据，
+    /// 即把栈顶的 `keep_count` 个数值移动到指定位置。
+    ///
+    /// 用于弹出栈帧时保留函数或者结构块的返回值。
+    ///
+    /// ```diagram
+    /// |栈顶。|
+    /// | y   | -----\
+    /// | x   | ---\  |
+    /// | ... |    |  |     |栈顶。|
+    /// | ... |    |  \---> | y   |
+    /// | ... |    \-------> | x   |
+    /// | ... | <-- index   | ... |
+    /// |栈底。|             |栈底。|
+    /// ```
+    pub fn drop_values_keep_top(&mut self, index: usize, keep_count: usize) {
+        let keep_start = self.slots.len() - keep_count;
+        if keep_start != index {
+            self.slots.drain(index..keep_start);
+        }
+    }
+
+    pub fn read_slots(&self, start: usize, end: usize) -> &[u64] {
         &self.slots[start..end]
     }
 
-    pub fn peek_values(&self, count: usize) -> &[Value] {
-        let index = self.slots.len() - count;
-        &self.slots[index..]
+    /// 压入控制帧
+    pub fn push_frame(&mut self, frame: ControlFrame) {
+        self.frames.push(frame);
+    }
+
+    /// 弹出控制帧
+    pub fn pop_frame(&mut self) -> ControlFrame {
+        self.frames.pop().expect("control stack is empty")
+    }
+
+    /// 弹出指定数量的控制帧，返回最后被弹出的（即最外层的）控制帧
+    pub fn pop_frames(&mut self, count: usize) -> ControlFrame {
+        let index = self.frames.len() - count;
+        self.frames
+            .drain(index..)
+            .next()
+            .expect("control stack is empty")
+    }
+
+    /// 按相对深度获取控制帧
+    ///
+    /// 相对深度为 0 时表示当前（即最后压入的）控制帧
+    pub fn get_frame(&self, relative_depth: usize) -> &ControlFrame {
+        &self.frames[self.frames.len() - relative_depth - 1]
+    }
+
+    /// 获取所有控制帧，靠近栈底的控制帧位于结果的小索引端
+    pub fn get_frames(&self) -> &[ControlFrame] {
+        &self.frames
+    }
+
+    /// 获取控制帧的数量
+    pub fn get_frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// 丢弃超出指定大小的槽以及超出指定数量的控制帧
+    pub(crate) fn truncate(&mut self, size: usize, frame_count: usize) {
+        self.slots.truncate(size);
+        self.frames.truncate(frame_count);
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use anvm_ast::types::Value;
+    use anvm_ast::{
+        instruction::BlockType,
+        types::{Value, ValueType},
+    };
 
-    use super::VMStack;
+    use super::{slot_to_value, value_to_slot, ControlFrame, FrameKind, StackUnderflow, VMStack};
 
     #[test]
     fn test_push_pop_and_peek() {
         let mut s0 = VMStack::new();
 
         // 测试 push
-        s0.push(Value::I32(1));
-        s0.push(Value::I32(2));
+        s0.push_i32(1);
+        s0.push_i32(2);
         assert_eq!(s0.get_size(), 2);
 
         // 测试 pop
-        assert_eq!(s0.pop(), Value::I32(2));
+        assert_eq!(s0.pop_i32().unwrap(), 2);
         assert_eq!(s0.get_size(), 1);
 
         // 再次 push
-        s0.push(Value::F32(3.0));
-        s0.push(Value::F32(4.0));
+        s0.push_f32(3.0);
+        s0.push_f32(4.0);
         assert_eq!(s0.get_size(), 3);
 
         // 测试 peek 和 pop
-        assert_eq!(s0.peek(), Value::F32(4.0));
+        assert_eq!(s0.peek_value(&ValueType::F32).unwrap(), Value::F32(4.0));
         assert_eq!(s0.get_size(), 3); // peek 不会改变 slots 的内容
-        assert_eq!(s0.pop(), Value::F32(4.0));
+        assert_eq!(s0.pop_f32().unwrap(), 4.0);
         assert_eq!(s0.get_size(), 2);
-        assert_eq!(s0.peek(), Value::F32(3.0));
+        assert_eq!(s0.peek_value(&ValueType::F32).unwrap(), Value::F32(3.0));
+    }
+
+    #[test]
+    fn test_stack_underflow() {
+        let mut s0 = VMStack::new();
+
+        assert_eq!(s0.pop_i32(), Err(StackUnderflow));
+        assert_eq!(s0.peek_value(&ValueType::I32), Err(StackUnderflow));
+
+        s0.push_i32(1);
+        assert_eq!(
+            s0.pop_values(&vec![ValueType::I32, ValueType::I32]),
+            Err(StackUnderflow)
+        );
+
+        // 操作数不足时栈保持不变
+        assert_eq!(s0.get_size(), 1);
+        assert_eq!(s0.pop_i32(), Ok(1));
     }
 
     #[test]
@@ -292,44 +511,43 @@ mod tests {
         let mut s0 = VMStack::new();
 
         // 测试 push
-        s0.push(Value::F32(3.14));
-        s0.push(Value::I64(1));
+        s0.push_i32(-1);
         s0.push_bool(true);
         s0.push_bool(false);
         s0.push_bool(true);
 
-        assert_eq!(s0.pop_bool().unwrap(), true);
-        assert_eq!(s0.pop_bool().unwrap(), false);
-        assert_eq!(s0.pop_bool().unwrap(), true);
+        assert!(s0.pop_bool().unwrap());
+        assert!(!s0.pop_bool().unwrap());
+        assert!(s0.pop_bool().unwrap());
 
-        assert!(matches!(s0.pop_bool(), Err(Value::I64(_))));
-        assert!(matches!(s0.pop_bool(), Err(Value::F32(_))));
+        // 非 0 的整数均视为 true
+        assert!(s0.pop_bool().unwrap());
     }
 
     #[test]
     fn test_get_and_set() {
         let mut s0 = VMStack::new();
 
-        s0.push(Value::I32(1));
-        s0.push(Value::I32(2));
-        s0.push(Value::I32(3));
+        s0.push_i32(1);
+        s0.push_i32(2);
+        s0.push_i32(3);
 
         assert_eq!(s0.get_size(), 3);
-        assert_eq!(s0.get_value(0), Value::I32(1));
-        assert_eq!(s0.get_value(1), Value::I32(2));
-        assert_eq!(s0.get_value(2), Value::I32(3));
+        assert_eq!(s0.get_value(0, &ValueType::I32), Value::I32(1));
+        assert_eq!(s0.get_value(1, &ValueType::I32), Value::I32(2));
+        assert_eq!(s0.get_value(2, &ValueType::I32), Value::I32(3));
 
-        s0.set_value(0, Value::I64(11));
-        s0.set_value(2, Value::F64(3.3));
+        s0.set_slot(0, value_to_slot(&Value::I64(11)));
+        s0.set_slot(2, value_to_slot(&Value::F64(3.3)));
 
         assert_eq!(s0.get_size(), 3);
-        assert_eq!(s0.get_value(0), Value::I64(11));
-        assert_eq!(s0.get_value(1), Value::I32(2));
-        assert_eq!(s0.get_value(2), Value::F64(3.3));
+        assert_eq!(s0.get_value(0, &ValueType::I64), Value::I64(11));
+        assert_eq!(s0.get_value(1, &ValueType::I32), Value::I32(2));
+        assert_eq!(s0.get_value(2, &ValueType::F64), Value::F64(3.3));
 
-        assert_eq!(s0.pop(), Value::F64(3.3));
-        assert_eq!(s0.pop(), Value::I32(2));
-        assert_eq!(s0.pop(), Value::I64(11));
+        assert_eq!(s0.pop_f64().unwrap(), 3.3);
+        assert_eq!(s0.pop_i32().unwrap(), 2);
+        assert_eq!(s0.pop_i64().unwrap(), 11);
         assert_eq!(s0.get_size(), 0);
     }
 
@@ -337,31 +555,140 @@ mod tests {
     fn test_push_and_pop_values() {
         let mut s0 = VMStack::new();
 
-        s0.push(Value::I32(1));
-        s0.push(Value::I32(2));
-        s0.push(Value::I32(3));
+        s0.push_i32(1);
+        s0.push_i32(2);
+        s0.push_i32(3);
         assert_eq!(s0.get_size(), 3);
 
         // 测试 push_values
         s0.push_values(&vec![Value::I32(11), Value::I32(22)]);
         assert_eq!(s0.get_size(), 5);
-        assert_eq!(s0.get_value(0), Value::I32(1));
-        assert_eq!(s0.get_value(1), Value::I32(2));
-        assert_eq!(s0.get_value(2), Value::I32(3));
-        assert_eq!(s0.get_value(3), Value::I32(11));
-        assert_eq!(s0.get_value(4), Value::I32(22));
+        assert_eq!(s0.read_slots(0, 5), &[1, 2, 3, 11, 22]);
 
         // 测试 pop_values
         assert_eq!(
-            s0.pop_values(3),
+            s0.pop_values(&vec![ValueType::I32, ValueType::I32, ValueType::I32])
+                .unwrap(),
             vec![Value::I32(3), Value::I32(11), Value::I32(22),]
         );
 
         // 再次测试
         s0.push_values(&vec![Value::F32(1.1)]);
         assert_eq!(
-            s0.pop_values(3),
+            s0.pop_values(&vec![ValueType::I32, ValueType::I32, ValueType::F32])
+                .unwrap(),
             vec![Value::I32(1), Value::I32(2), Value::F32(1.1),]
         );
     }
+
+    #[test]
+    fn test_value_and_slot_conversion() {
+        let values = vec![
+            Value::I32(0),
+            Value::I32(-1),
+            Value::I32(i32::MIN),
+            Value::I64(-1),
+            Value::I64(i64::MAX),
+            Value::F32(-0.0),
+            Value::F32(f32::INFINITY),
+            Value::F64(-1.5),
+            Value::F64(f64::NEG_INFINITY),
+            Value::FuncRef(None),
+            Value::FuncRef(Some(0)),
+            Value::FuncRef(Some(7)),
+            Value::ExternRef(None),
+            Value::ExternRef(Some(u32::MAX)),
+        ];
+
+        for value in &values {
+            let slot = value_to_slot(value);
+            assert_eq!(&slot_to_value(slot, &value.get_type()), value);
+        }
+
+        // i32 以零扩展的方式储存
+        assert_eq!(value_to_slot(&Value::I32(-1)), 0xffff_ffff);
+
+        // NaN 的位模式需保持不变
+        let nan32 = f32::from_bits(0x7fc0_0001);
+        let slot = value_to_slot(&Value::F32(nan32));
+        assert!(matches!(slot_to_value(slot, &ValueType::F32),
+            Value::F32(v) if v.to_bits() == 0x7fc0_0001));
+
+        let nan64 = f64::from_bits(0xfff8_0000_0000_0001);
+        let slot = value_to_slot(&Value::F64(nan64));
+        assert!(matches!(slot_to_value(slot, &ValueType::F64),
+            Value::F64(v) if v.to_bits() == 0xfff8_0000_0000_0001));
+
+        // 空引用与索引为 0 的引用需能区分
+        assert_ne!(
+            value_to_slot(&Value::FuncRef(None)),
+            value_to_slot(&Value::FuncRef(Some(0)))
+        );
+    }
+
+    fn new_frame(kind: FrameKind, frame_pointer: usize, return_address: usize) -> ControlFrame {
+        ControlFrame {
+            kind,
+            frame_pointer,
+            return_local_pointer: 0,
+            return_base_pointer: 0,
+            return_vm_module_index: 0,
+            return_function_index: 0,
+            return_frame_type: BlockType::ResultEmpty,
+            return_address,
+        }
+    }
+
+    #[test]
+    fn test_drop_values_keep_top() {
+        let mut s0 = VMStack::new();
+
+        s0.push_values(&vec![
+            Value::I32(1),
+            Value::I32(2),
+            Value::I32(3),
+            Value::I32(4),
+            Value::I32(5),
+        ]);
+
+        // 丢弃索引 1 开始的数据，但保留栈顶的 2 个数据
+        s0.drop_values_keep_top(1, 2);
+        assert_eq!(
+            s0.peek_values(&vec![ValueType::I32, ValueType::I32, ValueType::I32])
+                .unwrap(),
+            vec![Value::I32(1), Value::I32(4), Value::I32(5)]
+        );
+
+        // 保留 0 个数据
+        s0.drop_values_keep_top(1, 0);
+        assert_eq!(s0.get_size(), 1);
+        assert_eq!(s0.peek_slot().unwrap(), 1);
+    }
+
+    #[test]
+    fn test_push_and_pop_frames() {
+        let mut s0 = VMStack::new();
+
+        s0.push_frame(new_frame(FrameKind::HostCall, 0, 0));
+        s0.push_frame(new_frame(FrameKind::Call, 1, 11));
+        s0.push_frame(new_frame(FrameKind::Block, 2, 22));
+        s0.push_frame(new_frame(FrameKind::Block, 3, 33));
+        assert_eq!(s0.get_frame_count(), 4);
+
+        // 测试 get_frame
+        assert_eq!(s0.get_frame(0).return_address, 33);
+        assert_eq!(s0.get_frame(2).return_address, 11);
+        assert!(!s0.get_frame(0).is_call_frame());
+        assert!(s0.get_frame(2).is_call_frame());
+        assert!(s0.get_frame(3).is_call_frame());
+
+        // 测试 pop_frames，返回最外层被弹出的帧
+        assert_eq!(s0.pop_frames(2).return_address, 22);
+        assert_eq!(s0.get_frame_count(), 2);
+
+        // 测试 pop_frame
+        assert_eq!(s0.pop_frame().kind, FrameKind::Call);
+        assert_eq!(s0.pop_frame().kind, FrameKind::HostCall);
+        assert_eq!(s0.get_frame_count(), 0);
+    }
 }
@@ -24,15 +24,27 @@
 // | ...         |       | #2 func#5   |     | #5 div     |
 // | -- 栈底。 -- |       | ...         |     | ...        |
 
-use anvm_ast::ast::{Limit, TableType};
+use anvm_ast::{
+    ast::{Limit, TableType},
+    types::ValueType,
+};
 
 use crate::error::{EngineError, Overflow, OutOfRange};
 
+/// 表的元素数量的上限
+///
+/// WebAssembly 规范允许表的元素数量达到 u32 的最大值，这里限制一个较小的值，
+/// 以避免 `table.grow` 指令占用过多的内存。
+pub const MAX_ELEMENTS: u32 = 10_000_000;
+
 pub struct VMTable {
-    /// TableType 的信息包含表的类型（目前只有函数引用类型）以及限制值（范围值）
+    /// TableType 的信息包含表的元素类型（funcref 或者 externref）以及限制值（范围值）
     table_type: TableType,
 
-    /// 函数索引列表
+    /// 元素列表
+    /// 对于 funcref 类型的表，元素的值是函数引用（参见 `Resource::get_function_reference`），
+    /// 对于 externref 类型的表，
+    /// 元素的值是宿主的句柄，None 表示空引用。
     elements: Vec<Option<u32>>,
 }
 
@@ -49,18 +61,20 @@ impl VMTable {
         }
     }
 
-    /// 创建指定项目数量（且不限最大值的）的表
+    /// 创建指定项目数量（且不限最大值的）的表，表的元素类型为 funcref
     pub fn new_by_min(min: u32) -> Self {
         let table_type = TableType {
+            ref_type: ValueType::FuncRef,
             limit: Limit::AtLeast(min),
         };
 
         VMTable::new(table_type)
     }
 
-    /// min 和 max 的值都是 `包括的`（`included`）
+    /// min 和 max 的值都是 `包括的`（`included`），表的元素类型为 funcref
     pub fn new_by_page_range(min_page: u32, max_page: u32) -> Self {
         let table_type = TableType {
+            ref_type: ValueType::FuncRef,
             limit: Limit::Range(min_page, max_page),
         };
 
@@ -73,8 +87,27 @@ impl VMTable {
 
     /// 返回原先的大小
     pub fn increase_size(&mut self, increase_number: u32) -> Result<u32, EngineError> {
+        self.increase_size_with_element(increase_number, None)
+    }
+
+    /// 增加表的大小，新增加的空槽的初始值都是 element
+    ///
+    /// 返回原先的大小
+    pub fn increase_size_with_element(
+        &mut self,
+        increase_number: u32,
+        element: Option<u32>,
+    ) -> Result<u32, EngineError> {
         let old_len = self.get_size();
-        let new_len = self.get_size() + increase_number;
+        let new_len = match old_len.checked_add(increase_number) {
+            Some(new_len) if new_len <= MAX_ELEMENTS => new_len,
+            _ => {
+                return Err(EngineError::Overflow(Overflow::TableSizeExceed(
+                    old_len.saturating_add(increase_number),
+                    MAX_ELEMENTS,
+                )));
+            }
+        };
 
         // 如果 TableType 的 limit 成员不指定 max 值，则可以
         // 增长到 u32 的最大值
@@ -86,8 +119,7 @@ impl VMTable {
             }
         }
 
-        // 新增加的空槽的初始值都是 None
-        self.elements.resize(new_len as usize, None);
+        self.elements.resize(new_len as usize, element);
         Ok(old_len)
     }
 
@@ -101,20 +133,78 @@ impl VMTable {
         Ok(self.elements[index])
     }
 
-    pub fn set_element(&mut self, index: usize, function_index: u32) -> Result<(), EngineError> {
+    pub fn set_element(&mut self, index: usize, function_reference: u32) -> Result<(), EngineError> {
         if index >= self.elements.len() {
             return Err(EngineError::OutOfRange(
                 OutOfRange::ElementIndexOutOfRange(index, self.elements.len())
             ));
         }
 
-        self.elements[index] = Some(function_index);
+        self.elements[index] = Some(function_reference);
         Ok(())
     }
 
+    /// 设置元素的值，element 为 None 时表示设置为空引用
+    pub fn set_element_value(
+        &mut self,
+        index: usize,
+        element: Option<u32>,
+    ) -> Result<(), EngineError> {
+        if index >= self.elements.len() {
+            return Err(EngineError::OutOfRange(
+                OutOfRange::ElementIndexOutOfRange(index, self.elements.len())
+            ));
+        }
+
+        self.elements[index] = element;
+        Ok(())
+    }
+
+    /// 将一组元素写入到从 offset 开始的位置
+    ///
+    /// 调用者需要事先检查范围
+    pub fn set_elements(&mut self, offset: usize, elements: &[Option<u32>]) {
+        self.elements[offset..(offset + elements.len())].copy_from_slice(elements);
+    }
+
+    /// 将从 offset 开始的 count 个元素设置为 element
+    ///
+    /// 调用者需要事先检查范围
+    pub fn fill_elements(&mut self, offset: usize, element: Option<u32>, count: usize) {
+        self.elements[offset..(offset + count)].fill(element);
+    }
+
+    /// 复制指定范围内的元素，源范围和目标范围允许重叠
+    ///
+    /// 调用者需要事先检查范围
+    pub fn copy_elements(&mut self, source_offset: usize, dest_offset: usize, count: usize) {
+        self.elements.copy_within(source_offset..(source_offset + count), dest_offset);
+    }
+
+    pub fn get_elements(&self) -> &[Option<u32>] {
+        &self.elements
+    }
+
     pub fn get_table_type(&self) -> &TableType {
         &self.table_type
     }
+
+    /// 检查表是否允许拥有指定数量的元素
+    pub(crate) fn is_valid_size(&self, size: u32) -> bool {
+        let max = match self.table_type.limit {
+            Limit::Range(_, max) => max.min(MAX_ELEMENTS),
+            Limit::AtLeast(_) => MAX_ELEMENTS,
+        };
+
+        size >= self.table_type.limit.get_min() && size <= max
+    }
+
+    /// 替换表的全部元素，用于恢复 VM 快照
+    ///
+    /// 调用者需要确保元素的数量是有效的。
+    pub(crate) fn replace_elements(&mut self, elements: Vec<Option<u32>>) {
+        self.elements = elements;
+    }
 }
 
 #[cfg(test)]
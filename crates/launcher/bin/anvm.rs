@@ -7,7 +7,8 @@
 use std::{env, process};
 
 use anvm_ast::types::Value;
-use anvm_launcher::{disassembly, execute_function};
+use anvm_launcher::image::ImageManifest;
+use anvm_launcher::{build_image, disassembly, execute_function, run_image, validate_modules};
 
 /// 编译之后将会得到程序 `./target/debug/anvm`
 /// 然后通过诸如 `$ anvm fib.wasm` （其中的 `fib.wasm` 是 WebAssembly
@@ -32,6 +33,15 @@ fn main() {
             "-d" | "--disassembly" => {
                 process_disassembly_command(&args[2..]);
             }
+            "validate" | "--validate" => {
+                process_validate_command(&args[2..]);
+            }
+            "build-image" => {
+                process_build_image_command(&args[2..]);
+            }
+            "run-image" => {
+                process_run_image_command(&args[2..]);
+            }
             _ => {
                 process_execute_function_command(&args[1..]);
             }
@@ -64,6 +74,9 @@ e.g.
     $ anvm console.wasm -- help
     $ anvm console.wasm -- convert -d 123 --format hex
     $ anvm --disassembly input.wasm output.wat
+    $ anvm validate lib.wasm app.wasm
+    $ anvm build-image app.img lib.wasm app.wasm [-f app::_start] [--arg value] [--env NAME=VALUE]
+    $ anvm run-image app.img [-- command -o --option]
 "
     );
 }
@@ -85,6 +98,179 @@ Please specify both the input and output file names, e.g.
     }
 }
 
+fn process_validate_command(fragments: &[String]) {
+    if fragments.is_empty() {
+        println!(
+            "\
+Please specify the module file names, e.g.
+
+    $ anvm validate lib.wasm app.wasm
+"
+        )
+    } else {
+        match validate_modules(fragments) {
+            Ok(_) => println!("ok"),
+            Err(e) => {
+                println!("validation failed, error message: {}", e);
+                process::exit(1);
+            }
+        }
+    }
+}
+
+fn process_build_image_command(fragments: &[String]) {
+    let usage = "\
+Please specify the image file name and the module file names, e.g.
+
+    $ anvm build-image app.img lib.wasm app.wasm
+
+the module name is the base name of the WASM file by default, \
+use \"module_name=file_name\" to specify another name. the options are:
+
+    -f, --function module_name::function_name   the entry function
+    --arg value                                 an application argument, repeatable
+    --env NAME=VALUE                            an environment variable, repeatable
+    --max-call-depth N                          the maximum call depth
+    --max-stack-slots N                         the maximum number of stack slots
+    --fuel N                                    the fuel of the application
+
+e.g.
+
+    $ anvm build-image app.img lib.wasm app.wasm -f app::main --arg help --env HOME=/home/yang
+";
+
+    match parse_build_image_arguments(fragments) {
+        Ok((image_filepath, module_filepaths, manifest)) => {
+            match build_image(&image_filepath, &module_filepaths, manifest) {
+                Ok(_) => println!("ok"),
+                Err(e) => {
+                    println!("failed to build image, error message: {}", e);
+                    process::exit(1);
+                }
+            }
+        }
+        Err(message) => {
+            println!("{}\n", message);
+            println!("{}", usage);
+        }
+    }
+}
+
+fn process_run_image_command(fragments: &[String]) {
+    match parse_run_image_arguments(fragments) {
+        Some((image_filepath, application_arguments)) => {
+            print_execute_result(run_image(image_filepath, application_arguments));
+        }
+        None => {
+            println!(
+                "\
+Please specify the image file name, and optionally the additional application arguments, e.g.
+
+    $ anvm run-image app.img
+    $ anvm run-image app.img -- convert -d 123 --format hex
+"
+            );
+        }
+    }
+}
+
+/// 解析 build-image 命令的参数
+///
+/// 返回 (image_filepath, module_filepaths, manifest)
+fn parse_build_image_arguments(
+    fragments: &[String],
+) -> Result<(String, Vec<String>, ImageManifest), String> {
+    // app.img lib.wasm app=app.wasm -f app::_start --arg help --env HOME=/home/yang
+    // ^-----^ ^--------------------^ ^-----------------------------------------------^
+
+    let (image_filepath, mut remains) = fragments
+        .split_first()
+        .ok_or("Missing the image file name".to_string())?;
+
+    let mut module_filepaths: Vec<String> = vec![];
+    let mut manifest = ImageManifest::default();
+
+    while let Some((first, rest)) = remains.split_first() {
+        if !first.starts_with('-') {
+            module_filepaths.push(first.to_owned());
+            remains = rest;
+            continue;
+        }
+
+        let value = rest
+            .first()
+            .ok_or(format!("Missing the value of option \"{}\"", first))?;
+
+        let result = match first.as_str() {
+            "-f" | "--function" => value
+                .split_once("::")
+                .map(|(module_name, function_name)| {
+                    manifest.entry = Some((module_name.to_owned(), function_name.to_owned()))
+                })
+                .ok_or(()),
+            "--arg" => {
+                manifest.arguments.push(value.to_owned());
+                Ok(())
+            }
+            "--env" => value
+                .split_once('=')
+                .map(|(name, value)| {
+                    manifest
+                        .environments
+                        .push((name.to_owned(), value.to_owned()))
+                })
+                .ok_or(()),
+            "--max-call-depth" => value
+                .parse()
+                .map(|n| manifest.limits.max_call_depth = Some(n))
+                .map_err(|_| ()),
+            "--max-stack-slots" => value
+                .parse()
+                .map(|n| manifest.limits.max_stack_slots = Some(n))
+                .map_err(|_| ()),
+            "--fuel" => value
+                .parse()
+                .map(|n| manifest.limits.fuel = Some(n))
+                .map_err(|_| ()),
+            _ => {
+                return Err(format!("Unexpected image option: \"{}\"", first));
+            }
+        };
+
+        if result.is_err() {
+            return Err(format!(
+                "Wrong format of the value of option \"{}\": \"{}\"",
+                first, value
+            ));
+        }
+
+        remains = &rest[1..];
+    }
+
+    if module_filepaths.is_empty() {
+        return Err("Missing the module file names".to_string());
+    }
+
+    Ok((image_filepath.to_owned(), module_filepaths, manifest))
+}
+
+/// 解析 run-image 命令的参数
+///
+/// 映像文件名之后只能是 `--` 以及追加的应用程序命令行参数，
+/// 返回 (image_filepath, application_arguments)
+fn parse_run_image_arguments(fragments: &[String]) -> Option<(&str, &[String])> {
+    // app.img -- command -o --option
+    // ^-----^    ^-----------------^
+
+    match fragments {
+        [image_filepath] => Some((image_filepath, &[])),
+        [image_filepath, separator, application_arguments @ ..] if separator == "--" => {
+            Some((image_filepath, application_arguments))
+        }
+        _ => None,
+    }
+}
+
 fn process_execute_function_command(fragments: &[String]) {
     let mut module_filepaths: Vec<String> = vec![];
     let mut entry_module_function_name: Option<(String, String)> = None;
@@ -148,12 +334,16 @@ Unexpected VM launcher argument: \"{}\"
         };
     }
 
-    match execute_function(
+    print_execute_result(execute_function(
         &module_filepaths,
         entry_module_function_name,
         &function_arguments,
         &application_arguments,
-    ) {
+    ));
+}
+
+fn print_execute_result(result: Result<(Vec<Value>, i32), String>) {
+    match result {
         Ok((results, exit_code)) => {
             if results.len() > 0 {
                 println!(
@@ -189,8 +379,9 @@ program exit normally with code: {}",
 program terminated unexpectedly, error message: {}",
                 e
             )
+            // 如果错误是陷阱，错误信息已包含调用栈回溯信息（call stack）
             // TODO::
-            // 打印 call stack、最后栈帧的内容（局部变量、操作数）、错误的位置（pc）
+            // 打印最后栈帧的内容（局部变量、操作数）
         }
     }
 }
@@ -347,3 +538,133 @@ fn continue_parse_application_arguments(
 
     Ok((application_args, remains))
 }
+
+#[cfg(test)]
+mod tests {
+    use anvm_launcher::image::{ImageManifest, ResourceLimits};
+    use pretty_assertions::assert_eq;
+
+    use super::{parse_build_image_arguments, parse_run_image_arguments};
+
+    // 辅助方法
+    fn to_strings(items: &[&str]) -> Vec<String> {
+        items.iter().map(|item| item.to_string()).collect()
+    }
+
+    #[test]
+    fn test_parse_build_image_arguments() {
+        let fragments = to_strings(&[
+            "app.img",
+            "lib.wasm",
+            "-f",
+            "app::_start",
+            "app=build/main.wasm",
+            "--arg",
+            "help",
+            "--arg",
+            "--verbose",
+            "--env",
+            "HOME=/home/yang",
+            "--max-call-depth",
+            "1000",
+            "--fuel",
+            "100000",
+        ]);
+
+        let (image_filepath, module_filepaths, manifest) =
+            parse_build_image_arguments(&fragments).unwrap();
+
+        assert_eq!(image_filepath, "app.img");
+        assert_eq!(
+            module_filepaths,
+            to_strings(&["lib.wasm", "app=build/main.wasm"])
+        );
+        assert_eq!(
+            manifest,
+            ImageManifest {
+                entry: Some(("app".to_string(), "_start".to_string())),
+                arguments: to_strings(&["help", "--verbose"]),
+                environments: vec![("HOME".to_string(), "/home/yang".to_string())],
+                limits: ResourceLimits {
+                    max_call_depth: Some(1000),
+                    max_stack_slots: None,
+                    fuel: Some(100000),
+                },
+            }
+        );
+
+        // 只有映像文件名以及模块文件
+        let (_, module_filepaths, manifest) =
+            parse_build_image_arguments(&to_strings(&["app.img", "app.wasm"])).unwrap();
+        assert_eq!(module_filepaths, to_strings(&["app.wasm"]));
+        assert_eq!(manifest, ImageManifest::default());
+    }
+
+    #[test]
+    fn test_parse_build_image_arguments_error() {
+        // 缺少映像文件名或者模块文件
+        assert!(parse_build_image_arguments(&[]).is_err());
+        assert!(parse_build_image_arguments(&to_strings(&["app.img"])).is_err());
+        assert!(parse_build_image_arguments(&to_strings(&["app.img", "--arg", "help"])).is_err());
+
+        // 缺少选项的值
+        assert!(parse_build_image_arguments(&to_strings(&["app.img", "app.wasm", "-f"])).is_err());
+
+        // 选项的值格式错误
+        assert!(
+            parse_build_image_arguments(&to_strings(&["app.img", "app.wasm", "-f", "app"]))
+                .is_err()
+        );
+        assert!(parse_build_image_arguments(&to_strings(&[
+            "app.img", "app.wasm", "--env", "HOME"
+        ]))
+        .is_err());
+        assert!(
+            parse_build_image_arguments(&to_strings(&["app.img", "app.wasm", "--fuel", "-1"]))
+                .is_err()
+        );
+
+        // 未知的选项
+        assert!(parse_build_image_arguments(&to_strings(&[
+            "app.img",
+            "app.wasm",
+            "--unknown",
+            "1"
+        ]))
+        .is_err());
+    }
+
+    #[test]
+    fn test_parse_run_image_arguments() {
+        let fragments = to_strings(&["app.img"]);
+        assert_eq!(
+            parse_run_image_arguments(&fragments),
+            Some(("app.img", &[][..]))
+        );
+
+        // `--` 之后的参数都是应用程序的命令行参数，包括 `--`
+        let fragments = to_strings(&["app.img", "--", "convert", "-d", "--", "123"]);
+        assert_eq!(
+            parse_run_image_arguments(&fragments),
+            Some(("app.img", &to_strings(&["convert", "-d", "--", "123"])[..]))
+        );
+
+        // 只有 `--` 而没有参数
+        let fragments = to_strings(&["app.img", "--"]);
+        assert_eq!(
+            parse_run_image_arguments(&fragments),
+            Some(("app.img", &[][..]))
+        );
+
+        // 缺少映像文件名，或者映像文件名之后不是 `--`
+        assert_eq!(parse_run_image_arguments(&[]), None);
+        assert_eq!(
+            parse_run_image_arguments(&to_strings(&["app.img", "convert"])),
+            None
+        );
+        assert_eq!(
+            parse_run_image_arguments(&to_strings(&["app.img", "other.img"])),
+            None
+        );
+    }
+}
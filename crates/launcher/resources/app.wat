@@ -12,6 +12,5 @@
         (call $pow)
     )
 
-    (start $main)
     (export "_start" (func $main))
 )
@@ -0,0 +1,385 @@
+// Copyright (c) 2022 Hemashushu <hippospark@gmail.com>, All rights reserved.
+//
+// This Source Code Form is subject to the terms of the Mozilla Public
+// License, v. 2.0. If a copy of the MPL was not distributed with this
+// file, You can obtain one at http://mozilla.org/MPL/2.0/.
+
+//! # 应用程序映像
+//!
+//! 映像是一个归档文件，包含了 WASM 应用程序的所有模块，以及一份清单（manifest），
+//! 清单记录了各个模块的名称和哈希值、入口函数、应用程序的命令行参数、环境变量
+//! 以及资源限制，运行映像时无需再从文件名推断模块的名称，
+//! 也无需在命令行里重复指定这些配置。
+//!
+//! ## 归档格式
+//!
+//! 所有整数均以小端序储存：
+//!
+//! - 魔数 `ANVMIMG\0`（8 个字节），格式版本号（u32）；
+//! - 清单：长度（u32）以及 UTF-8 文本；
+//! - 模块的数量（u32），每个模块为名称（u32 长度加 UTF-8 数据）以及
+//!   模块的二进制数据（u64 长度加数据）。
+//!
+//! ## 清单格式
+//!
+//! 清单是一段文本，每行一个 `key = value` 项，空行以及 `#` 开头的行会被忽略，
+//! 值两端的空白字符会被去除，可以重复的项按照出现的顺序组成列表：
+//!
+//! ```text
+//! module = lib 5d5a2b4c8e0f1a37
+//! module = app 0c1f9e2d3b4a5867
+//! entry = app::_start
+//! arg = --verbose
+//! env = HOME=/home/yang
+//! max_call_depth = 1000
+//! max_stack_slots = 1048576
+//! fuel = 100000000
+//! ```
+//!
+//! - `module`：模块的名称以及模块二进制数据的哈希值（16 位十六进制数），
+//!   模块按照出现的顺序实例化，必须跟归档里的模块一一对应；
+//! - `entry`：入口函数，格式为 `module_name::function_name`，函数名称也可以是函数的索引，
+//!   省略时搜索名称为 `_start` 的导出函数；
+//! - `arg`：应用程序的命令行参数（不包括程序名称）；
+//! - `env`：环境变量，格式为 `NAME=VALUE`；
+//! - `max_call_depth`、`max_stack_slots` 以及 `fuel`：资源限制，省略时使用 VM 的默认值。
+//!
+//! 预打开的目录暂未加入清单，待 WASI 模块实现了访问文件系统的函数
+//! （比如 `fd_prestat_get` 和 `path_open`）之后再支持。
+
+use anvm_engine::snapshot::compute_content_hash;
+
+/// 映像文件开头的魔数
+const MAGIC_NUMBER: &[u8; 8] = b"ANVMIMG\0";
+
+/// 当前的映像格式版本号
+pub const IMAGE_VERSION: u32 = 1;
+
+/// 资源限制
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ResourceLimits {
+    pub max_call_depth: Option<usize>,
+    pub max_stack_slots: Option<usize>,
+    pub fuel: Option<u64>,
+}
+
+/// 清单当中除模块列表之外的配置
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct ImageManifest {
+    /// 入口模块的名称以及函数的名称（或者索引）
+    pub entry: Option<(String, String)>,
+
+    pub arguments: Vec<String>,
+    pub environments: Vec<(String, String)>,
+
+    pub limits: ResourceLimits,
+}
+
+/// 映像里的模块
+#[derive(Debug, PartialEq, Clone)]
+pub struct ImageModule {
+    pub name: String,
+    pub bytes: Vec<u8>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub struct Image {
+    pub manifest: ImageManifest,
+    pub modules: Vec<ImageModule>,
+}
+
+impl Image {
+    pub fn new(manifest: ImageManifest, modules: Vec<ImageModule>) -> Self {
+        Self { manifest, modules }
+    }
+
+    /// 生成映像文件的数据
+    ///
+    /// 清单里的文本不能包含换行符，值两端也不能有空白字符，否则返回错误。
+    pub fn write(&self) -> Result<Vec<u8>, String> {
+        let manifest_text = self.write_manifest()?;
+
+        let mut buffer: Vec<u8> = vec![];
+        buffer.extend_from_slice(MAGIC_NUMBER);
+        buffer.extend_from_slice(&IMAGE_VERSION.to_le_bytes());
+
+        buffer.extend_from_slice(&(manifest_text.len() as u32).to_le_bytes());
+        buffer.extend_from_slice(manifest_text.as_bytes());
+
+        buffer.extend_from_slice(&(self.modules.len() as u32).to_le_bytes());
+        for module in &self.modules {
+            buffer.extend_from_slice(&(module.name.len() as u32).to_le_bytes());
+            buffer.extend_from_slice(module.name.as_bytes());
+            buffer.extend_from_slice(&(module.bytes.len() as u64).to_le_bytes());
+            buffer.extend_from_slice(&module.bytes);
+        }
+
+        Ok(buffer)
+    }
+
+    /// 解析映像文件的数据，并检查各个模块的哈希值
+    pub fn read(data: &[u8]) -> Result<Image, String> {
+        let mut reader = ImageReader { data, position: 0 };
+
+        if reader.read_bytes(MAGIC_NUMBER.len())? != MAGIC_NUMBER {
+            return Err("the file is not an application image".to_string());
+        }
+
+        let version = reader.read_u32()?;
+        if version != IMAGE_VERSION {
+            return Err(format!("unsupported image version: {}", version));
+        }
+
+        let manifest_length = reader.read_u32()? as usize;
+        let manifest_text = String::from_utf8(reader.read_bytes(manifest_length)?.to_vec())
+            .map_err(|_| "the image manifest is not valid UTF-8 text".to_string())?;
+        let (manifest, module_hashes) = read_manifest(&manifest_text)?;
+
+        let module_count = reader.read_u32()? as usize;
+        if module_count != module_hashes.len() {
+            return Err(format!(
+                "the image contains {} modules, but the manifest lists {}",
+                module_count,
+                module_hashes.len()
+            ));
+        }
+
+        let mut modules: Vec<ImageModule> = vec![];
+        for (expected_name, expected_hash) in module_hashes {
+            let name_length = reader.read_u32()? as usize;
+            let name = String::from_utf8(reader.read_bytes(name_length)?.to_vec())
+                .map_err(|_| "the module name is not valid UTF-8 text".to_string())?;
+            if name != expected_name {
+                return Err(format!(
+                    "expected module \"{}\" in the image, actual: \"{}\"",
+                    expected_name, name
+                ));
+            }
+
+            let bytes_length = usize::try_from(reader.read_u64()?)
+                .map_err(|_| "the image is corrupted".to_string())?;
+            let bytes = reader.read_bytes(bytes_length)?.to_vec();
+            if compute_content_hash(&bytes) != expected_hash {
+                return Err(format!(
+                    "the hash of module \"{}\" does not match the manifest, the image is corrupted",
+                    name
+                ));
+            }
+
+            modules.push(ImageModule { name, bytes });
+        }
+
+        if reader.position != data.len() {
+            return Err("unexpected data at the end of the image".to_string());
+        }
+
+        Ok(Image { manifest, modules })
+    }
+
+    fn write_manifest(&self) -> Result<String, String> {
+        let manifest = &self.manifest;
+        let mut lines: Vec<(&str, String)> = vec![];
+
+        for module in &self.modules {
+            if module.name.contains(char::is_whitespace) {
+                return Err(format!(
+                    "module name \"{}\" can not contain whitespace",
+                    module.name
+                ));
+            }
+
+            lines.push((
+                "module",
+                format!(
+                    "{} {:016x}",
+                    module.name,
+                    compute_content_hash(&module.bytes)
+                ),
+            ));
+        }
+
+        if let Some((module_name, function_name)) = &manifest.entry {
+            lines.push(("entry", format!("{}::{}", module_name, function_name)));
+        }
+
+        for argument in &manifest.arguments {
+            lines.push(("arg", argument.clone()));
+        }
+
+        for (name, value) in &manifest.environments {
+            lines.push(("env", format!("{}={}", name, value)));
+        }
+
+        let limits = &manifest.limits;
+        if let Some(max_call_depth) = limits.max_call_depth {
+            lines.push(("max_call_depth", max_call_depth.to_string()));
+        }
+
+        if let Some(max_stack_slots) = limits.max_stack_slots {
+            lines.push(("max_stack_slots", max_stack_slots.to_string()));
+        }
+
+        if let Some(fuel) = limits.fuel {
+            lines.push(("fuel", fuel.to_string()));
+        }
+
+        let mut text = String::new();
+        for (key, value) in lines {
+            if value.contains(['\r', '\n']) || value.trim() != value {
+                return Err(format!(
+                    "the value of manifest item \"{}\" can not contain line breaks or \
+                    leading/trailing whitespace: \"{}\"",
+                    key, value
+                ));
+            }
+
+            text.push_str(&format!("{} = {}\n", key, value));
+        }
+
+        Ok(text)
+    }
+}
+
+/// 解析清单文本，返回清单以及模块列表（名称，哈希值）
+fn read_manifest(text: &str) -> Result<(ImageManifest, Vec<(String, u64)>), String> {
+    let mut manifest = ImageManifest::default();
+    let mut module_hashes: Vec<(String, u64)> = vec![];
+
+    for (line_index, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let invalid_line = || format!("invalid manifest line #{}: \"{}\"", line_index + 1, line);
+
+        let (key, value) = line.split_once('=').ok_or_else(invalid_line)?;
+        let value = value.trim();
+
+        match key.trim() {
+            "module" => {
+                let (name, hash) = value.split_once(' ').ok_or_else(invalid_line)?;
+                let hash = u64::from_str_radix(hash.trim(), 16).map_err(|_| invalid_line())?;
+                module_hashes.push((name.to_string(), hash));
+            }
+            "entry" => {
+                let (module_name, function_name) =
+                    value.split_once("::").ok_or_else(invalid_line)?;
+                manifest.entry = Some((module_name.to_string(), function_name.to_string()));
+            }
+            "arg" => manifest.arguments.push(value.to_string()),
+            "env" => {
+                let (name, value) = value.split_once('=').ok_or_else(invalid_line)?;
+                manifest
+                    .environments
+                    .push((name.to_string(), value.to_string()));
+            }
+            "max_call_depth" => {
+                manifest.limits.max_call_depth = Some(value.parse().map_err(|_| invalid_line())?)
+            }
+            "max_stack_slots" => {
+                manifest.limits.max_stack_slots = Some(value.parse().map_err(|_| invalid_line())?)
+            }
+            "fuel" => manifest.limits.fuel = Some(value.parse().map_err(|_| invalid_line())?),
+            _ => return Err(invalid_line()),
+        }
+    }
+
+    Ok((manifest, module_hashes))
+}
+
+struct ImageReader<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ImageReader<'a> {
+    fn read_bytes(&mut self, length: usize) -> Result<&'a [u8], String> {
+        if length > self.data.len() - self.position {
+            return Err("unexpected end of the image".to_string());
+        }
+
+        let bytes = &self.data[self.position..self.position + length];
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn read_u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+
+    fn read_u64(&mut self) -> Result<u64, String> {
+        Ok(u64::from_le_bytes(self.read_bytes(8)?.try_into().unwrap()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+
+    use super::{read_manifest, Image, ImageManifest, ImageModule, ResourceLimits};
+
+    fn get_test_image() -> Image {
+        let manifest = ImageManifest {
+            entry: Some(("app".to_string(), "_start".to_string())),
+            arguments: vec!["-l".to_string(), "hello world".to_string()],
+            environments: vec![("USER".to_string(), "YANG=1".to_string())],
+            limits: ResourceLimits {
+                max_call_depth: Some(1000),
+                max_stack_slots: None,
+                fuel: Some(100_000),
+            },
+        };
+
+        let modules = vec![
+            ImageModule {
+                name: "lib".to_string(),
+                bytes: vec![0, 1, 2, 3],
+            },
+            ImageModule {
+                name: "app".to_string(),
+                bytes: vec![4, 5, 6],
+            },
+        ];
+
+        Image::new(manifest, modules)
+    }
+
+    #[test]
+    fn test_write_and_read() {
+        let image = get_test_image();
+        let data = image.write().unwrap();
+        assert_eq!(Image::read(&data).unwrap(), image);
+
+        // 空映像
+        let empty_image = Image::new(ImageManifest::default(), vec![]);
+        let data = empty_image.write().unwrap();
+        assert_eq!(Image::read(&data).unwrap(), empty_image);
+    }
+
+    #[test]
+    fn test_invalid_image() {
+        let data = get_test_image().write().unwrap();
+
+        // 模块的数据被修改
+        let mut corrupted = data.clone();
+        let last = corrupted.len() - 1;
+        corrupted[last] ^= 0xff;
+        assert!(Image::read(&corrupted).unwrap_err().contains("hash"));
+
+        // 数据不完整
+        assert!(Image::read(&data[..data.len() - 1]).is_err());
+        assert!(Image::read(b"ANVM").is_err());
+
+        // 不是映像文件
+        assert!(Image::read(b"not an image file").is_err());
+
+        // 值包含换行符
+        let mut image = get_test_image();
+        image.manifest.arguments.push("a\nb".to_string());
+        assert!(image.write().is_err());
+
+        // 暂不支持预打开的目录
+        assert!(read_manifest("preopen = /data:./data").is_err());
+    }
+}
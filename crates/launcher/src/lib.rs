@@ -4,17 +4,31 @@
 // License, v. 2.0. If a copy of the MPL was not distributed with this
 // file, You can obtain one at http://mozilla.org/MPL/2.0/.
 
+pub mod image;
+
+use std::cell::RefCell;
 use std::fs;
+use std::io;
 use std::path::Path;
+use std::rc::Rc;
 
+use anvm_ast::ast::ImportDescriptor;
 use anvm_ast::types::Value;
 use anvm_binary_parser::parser;
 use anvm_disassembly::disassembler::module_to_text;
 use anvm_engine::error::{EngineError, NativeTerminate, NativeError};
 use anvm_engine::instance::{
-    create_instance, find_ast_module_export_function, get_entry_module_and_function_index,
+    create_instance_with_config, find_ast_module_export_function,
+    get_entry_module_and_function_index, InstanceConfig,
 };
+use anvm_engine::native_module::NativeModule;
 use anvm_engine::object::NamedAstModule;
+use anvm_engine::validator::validate;
+use anvm_engine::vm::VM;
+use anvm_native_wasi::wasi::new_wasi_module;
+use anvm_native_wasi::wasi_module_context::WASIModuleContext;
+
+use image::{Image, ImageManifest, ImageModule};
 
 pub fn disassembly(input_filepath: &str, output_filepath: &str) {
     println!(
@@ -38,6 +52,21 @@ pub fn disassembly(input_filepath: &str, output_filepath: &str) {
     println!("ok");
 }
 
+/// 验证模块，但不执行
+///
+/// 返回第一个无效模块的错误信息
+pub fn validate_modules(module_filepaths: &[String]) -> Result<(), String> {
+    let named_ast_modules = load_ast_modules(module_filepaths)?;
+
+    for named_ast_module in &named_ast_modules {
+        validate(&named_ast_module.module).map_err(|e| {
+            EngineError::Validation(named_ast_module.name.clone(), e).to_string()
+        })?;
+    }
+
+    Ok(())
+}
+
 pub fn execute_function(
     module_filepaths: &[String],
     entry_module_function_name: Option<(String, String)>,
@@ -61,41 +90,195 @@ pub fn execute_function_by_modules(
     application_arguments: &[String],
 ) -> Result<(Vec<Value>, i32), String> {
     let (vm_module_index, function_index) =
-        // 用户指定了入口模块及函数
-        if let Some((target_module_name, target_function_name_or_index)) =
-            entry_module_function_name
-        {
-            let (target_module_index, target_ast_module) = named_ast_modules
+        match find_entry_function(named_ast_modules, entry_module_function_name)? {
+            Some(entry) => entry,
+            None => {
+                // 没有入口函数，但有 `start` 段，`start` 段指定的函数会在实例化时执行
+                create_vm_instance(vec![], named_ast_modules, &InstanceConfig::default())?;
+                return Ok((vec![], 0));
+            }
+        };
+
+    let mut vm = create_vm_instance(vec![], named_ast_modules, &InstanceConfig::default())?;
+    eval_entry_function(
+        &mut vm,
+        named_ast_modules,
+        vm_module_index,
+        function_index,
+        function_arguments,
+    )
+}
+
+/// 将多个模块打包为一个应用程序映像文件
+///
+/// 模块文件路径的格式为 `path` 或者 `module_name=path`，前者使用文件的基本名称
+/// （即不带扩展名的文件名）作为模块名称。
+/// 打包之前会先解析并验证所有模块，以及检查清单里指定的入口函数是否存在。
+pub fn build_image(
+    image_filepath: &str,
+    module_filepaths: &[String],
+    manifest: ImageManifest,
+) -> Result<(), String> {
+    let mut image_modules: Vec<ImageModule> = vec![];
+    let mut named_ast_modules: Vec<NamedAstModule> = vec![];
+
+    for module_filepath in module_filepaths {
+        let (name, filepath) = match module_filepath.split_once('=') {
+            Some((name, filepath)) => (name.to_owned(), filepath),
+            None => (get_module_name(module_filepath)?, module_filepath.as_str()),
+        };
+
+        if image_modules.iter().any(|item| item.name == name) {
+            return Err(format!("duplicate module name \"{}\"", name));
+        }
+
+        let bytes =
+            fs::read(filepath).map_err(|_| format!("failed to open file \"{}\"", filepath))?;
+        let ast_module = parser::parse(&bytes).map_err(|e| e.to_string())?;
+        validate(&ast_module)
+            .map_err(|e| EngineError::Validation(name.clone(), e).to_string())?;
+
+        named_ast_modules.push(NamedAstModule::new(&name, ast_module));
+        image_modules.push(ImageModule { name, bytes });
+    }
+
+    // 检查入口函数
+    find_entry_function(&named_ast_modules, manifest.entry.clone())?;
+
+    let data = Image::new(manifest, image_modules).write()?;
+    fs::write(image_filepath, data)
+        .map_err(|_| format!("failed to write file \"{}\"", image_filepath))
+}
+
+/// 运行应用程序映像
+///
+/// 按照映像的清单配置 WASI 模块（命令行参数以及环境变量），
+/// 按照清单里的资源限制实例化映像里的模块，然后执行入口函数。
+/// 资源限制在执行 `start` 段指定的函数之前生效，即 `start` 段指定的函数以及入口函数
+/// 共用同一份燃料。
+/// `application_arguments` 会追加到清单里的命令行参数之后。
+///
+/// 返回 (return_values, exit_code)
+pub fn run_image(
+    image_filepath: &str,
+    application_arguments: &[String],
+) -> Result<(Vec<Value>, i32), String> {
+    let data = fs::read(image_filepath)
+        .map_err(|_| format!("failed to open file \"{}\"", image_filepath))?;
+    let image = Image::read(&data)?;
+    let manifest = image.manifest;
+
+    let mut named_ast_modules: Vec<NamedAstModule> = vec![];
+    for image_module in &image.modules {
+        let ast_module = parser::parse(&image_module.bytes).map_err(|e| e.to_string())?;
+        named_ast_modules.push(NamedAstModule::new(&image_module.name, ast_module));
+    }
+
+    let entry = find_entry_function(&named_ast_modules, manifest.entry)?;
+
+    let mut arguments = manifest.arguments;
+    arguments.extend_from_slice(application_arguments);
+
+    let wasi_module_context = WASIModuleContext::new(
+        image_filepath,
+        arguments,
+        manifest.environments,
+        Rc::new(RefCell::new(io::stdin())),
+        Rc::new(RefCell::new(io::stdout())),
+        Rc::new(RefCell::new(io::stderr())),
+    );
+
+    let wasi_module = new_wasi_module(wasi_module_context);
+    let config = InstanceConfig {
+        max_call_depth: manifest.limits.max_call_depth,
+        max_stack_slots: manifest.limits.max_stack_slots,
+        fuel: manifest.limits.fuel,
+    };
+    let mut vm = create_vm_instance(vec![wasi_module], &named_ast_modules, &config)?;
+
+    match entry {
+        Some((vm_module_index, function_index)) => eval_entry_function(
+            &mut vm,
+            &named_ast_modules,
+            vm_module_index,
+            function_index,
+            &[],
+        ),
+        // 只有 `start` 段，`start` 段指定的函数已经在实例化时执行
+        None => Ok((vec![], 0)),
+    }
+}
+
+/// 查找入口函数，返回 (vm_module_index, function_index)
+///
+/// 如果没有入口函数但有 `start` 段，则返回 None。
+fn find_entry_function(
+    named_ast_modules: &[NamedAstModule],
+    entry_module_function_name: Option<(String, String)>,
+) -> Result<Option<(usize, usize)>, String> {
+    // 用户指定了入口模块及函数
+    if let Some((target_module_name, target_function_name_or_index)) = entry_module_function_name
+    {
+        let (target_module_index, target_ast_module) = named_ast_modules
+            .iter()
+            .enumerate()
+            .find_map(|(module_index, item)| {
+                if item.name == target_module_name {
+                    Some((module_index, &item.module))
+                } else {
+                    None
+                }
+            })
+            .ok_or(format!("no module \"{}\" found.", target_module_name))?;
+
+        if let Ok(i) = target_function_name_or_index.parse::<u32>() {
+            // 命令行当中的函数名称参数的值是一个整数
+            // 函数的索引包括导入函数以及模块内部定义的函数
+            let imported_function_count = target_ast_module
+                .import_items
                 .iter()
-                .enumerate()
-                .find_map(|(module_index, item)| {
-                    if item.name == target_module_name {
-                        Some((module_index, &item.module))
-                    } else {
-                        None
-                    }
+                .filter(|item| {
+                    matches!(
+                        item.import_descriptor,
+                        ImportDescriptor::FunctionTypeIndex(_)
+                    )
                 })
-                .ok_or(format!("no module \"{}\" found.", target_module_name))?;
-
-            if let Ok(i) = target_function_name_or_index.parse::<u32>() {
-                // 命令行当中的函数名称参数的值是一个整数
-                (target_module_index, i as usize)
-            } else {
-                // 命令行当中的函数名称参数的值是一个字符串
-                let target_function_index = find_ast_module_export_function(
-                    target_ast_module,
-                    &target_function_name_or_index,
-                )
-                .ok_or(format!(
-                    "can not found the specified exported function \"{}\" in module \"{}\"",
-                    target_function_name_or_index, target_module_name
-                ))?;
-
-                (target_module_index, target_function_index as usize)
+                .count();
+            let function_count = imported_function_count
+                + target_ast_module.internal_function_to_type_index_list.len();
+
+            if i as usize >= function_count {
+                return Err(format!(
+                    "function index {} is out of range, module \"{}\" has {} functions",
+                    i, target_module_name, function_count
+                ));
             }
+
+            Ok(Some((target_module_index, i as usize)))
         } else {
-            // 用户没有指定入口模块和函数，需要搜索 ast module 当中 `start` 段的值或者名称为 `_start` 的导出函数
-            get_entry_module_and_function_index(&named_ast_modules).ok_or("\
+            // 命令行当中的函数名称参数的值是一个字符串
+            let target_function_index = find_ast_module_export_function(
+                target_ast_module,
+                &target_function_name_or_index,
+            )
+            .ok_or(format!(
+                "can not found the specified exported function \"{}\" in module \"{}\"",
+                target_function_name_or_index, target_module_name
+            ))?;
+
+            Ok(Some((target_module_index, target_function_index as usize)))
+        }
+    } else if let Some(entry) = get_entry_module_and_function_index(named_ast_modules) {
+        // 用户没有指定入口模块和函数，需要搜索 ast module 当中名称为 `_start` 的导出函数
+        Ok(Some(entry))
+    } else if named_ast_modules
+        .iter()
+        .any(|item| item.module.start_function_index.is_some())
+    {
+        // 没有入口函数，但有 `start` 段
+        Ok(None)
+    } else {
+        Err("\
 cannot find the entry function.
 please specify the name of the entry module and function on the command line, e.g.
 
@@ -104,15 +287,23 @@ please specify the name of the entry module and function on the command line, e.
 function index is also supported, e.g.
 
     $ anvm app.wasm -f app::1
-".to_string())?
-        };
+".to_string())
+    }
+}
 
+/// 返回 (return_values, exit_code)
+fn eval_entry_function(
+    vm: &mut VM,
+    named_ast_modules: &[NamedAstModule],
+    vm_module_index: usize,
+    function_index: usize,
+    function_arguments: &[Value],
+) -> Result<(Vec<Value>, i32), String> {
     println!(
         "execute function \"{}::{}\"",
         named_ast_modules[vm_module_index].name, function_index
     );
 
-    let mut vm = create_instance(vec![], &named_ast_modules).map_err(|e| e.to_string())?;
     match vm.eval_function_by_index(vm_module_index, function_index, function_arguments) {
         Ok(results) => Ok((results, 0)),
         Err(e) => {
@@ -120,7 +311,12 @@ function index is also supported, e.g.
                 EngineError::NativeTerminate(ne) => match &ne.native_error {
                     NativeError::Exit(exit_code) => Ok((vec![], *exit_code)),
                     NativeError::Internal(internal_error) => Err(internal_error.to_string()),
+                    NativeError::Trap(_) => Err(ne.to_string()),
                 },
+                EngineError::Trap(trap) => {
+                    // 陷阱的错误信息附带调用栈回溯信息
+                    Err(format!("{}\n{}", trap, trap.backtrace))
+                }
                 _ => {
                     // todo::
                     // 将错误信息转换为可读的文本
@@ -131,31 +327,221 @@ function index is also supported, e.g.
     }
 }
 
+fn create_vm_instance(
+    native_modules: Vec<NativeModule>,
+    named_ast_modules: &[NamedAstModule],
+    config: &InstanceConfig,
+) -> Result<VM, String> {
+    create_instance_with_config(native_modules, named_ast_modules, config).map_err(|e| match &e {
+        EngineError::StartFunctionTrap(_, trap) => {
+            // 陷阱的错误信息附带调用栈回溯信息
+            format!("{}\n{}", e, trap.backtrace)
+        }
+        _ => e.to_string(),
+    })
+}
+
 fn load_ast_modules(module_filepaths: &[String]) -> Result<Vec<NamedAstModule>, String> {
     let mut named_ast_modules: Vec<NamedAstModule> = vec![];
 
     for filepath in module_filepaths {
-        let p = Path::new(filepath);
-        let basename = p
-            .file_stem()
-            .ok_or(format!(
-                "can not get the module name from file path \"{}\"",
-                filepath
-            ))?
-            .to_str()
-            .ok_or(format!(
-                "file path \"{}\" contains invalid Unicode char",
-                filepath
-            ))?;
+        let basename = get_module_name(filepath)?;
 
         let bytes =
             fs::read(filepath).map_err(|_| format!("failed to open file \"{}\"", filepath))?;
 
         let ast_module = parser::parse(&bytes).map_err(|e| e.to_string())?;
 
-        let named_ast_module = NamedAstModule::new(basename, ast_module);
+        let named_ast_module = NamedAstModule::new(&basename, ast_module);
         named_ast_modules.push(named_ast_module);
     }
 
     Ok(named_ast_modules)
 }
+
+/// 使用文件的基本名称（即不带扩展名的文件名）作为模块名称
+fn get_module_name(filepath: &str) -> Result<String, String> {
+    let p = Path::new(filepath);
+    let basename = p
+        .file_stem()
+        .ok_or(format!(
+            "can not get the module name from file path \"{}\"",
+            filepath
+        ))?
+        .to_str()
+        .ok_or(format!(
+            "file path \"{}\" contains invalid Unicode char",
+            filepath
+        ))?;
+
+    Ok(basename.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use anvm_ast::types::Value;
+    use pretty_assertions::assert_eq;
+
+    use crate::image::{Image, ImageManifest, ResourceLimits};
+
+    use super::{build_image, run_image};
+
+    // 辅助方法
+    fn get_test_resource_filepath(filename: &str) -> String {
+        let mut path_buf = env::current_dir().unwrap();
+
+        // 使用 `cargo test` 测试时，
+        // `env::current_dir()` 函数获得的当前目录为
+        // `./xiaoxuan-vm/crates/launcher`；
+        //
+        // 但如果使用 vscode 的源码编辑框里面的 `debug` 按钮开始调试，
+        // `env::current_dir()` 函数获得的当前目录为
+        // `./xiaoxuan-vm`。
+        //
+        // 下面语句用于处理这种情况。
+
+        if !path_buf.ends_with("launcher") {
+            path_buf.push("crates");
+            path_buf.push("launcher");
+        }
+        let fullname_buf = path_buf.join("resources").join(filename);
+        fullname_buf.to_str().unwrap().to_owned()
+    }
+
+    /// 映像文件储存在系统的临时目录，各个测试需要使用不同的名称
+    fn get_test_image_filepath(name: &str) -> String {
+        let filename = format!("anvm-launcher-test-{}-{}.img", process::id(), name);
+        env::temp_dir().join(filename).to_str().unwrap().to_owned()
+    }
+
+    #[test]
+    fn test_build_and_run_image() {
+        let image_filepath = get_test_image_filepath("app");
+
+        // 模块 "app" 导入了模块 "lib" 的函数，入口函数为 "app" 导出的 `_start` 函数
+        let module_filepaths = vec![
+            get_test_resource_filepath("lib.wasm"),
+            format!("main={}", get_test_resource_filepath("app.wasm")),
+        ];
+        let manifest = ImageManifest {
+            arguments: vec!["help".to_string()],
+            environments: vec![("HOME".to_string(), "/home/yang".to_string())],
+            ..ImageManifest::default()
+        };
+
+        build_image(&image_filepath, &module_filepaths, manifest.clone()).unwrap();
+
+        let image = Image::read(&fs::read(&image_filepath).unwrap()).unwrap();
+        assert_eq!(image.manifest, manifest);
+        assert_eq!(
+            image
+                .modules
+                .iter()
+                .map(|item| item.name.as_str())
+                .collect::<Vec<&str>>(),
+            vec!["lib", "main"]
+        );
+
+        assert_eq!(
+            run_image(&image_filepath, &["convert".to_string()]).unwrap(),
+            (vec![Value::I32(1024)], 0)
+        );
+
+        // 指定入口函数
+        let manifest = ImageManifest {
+            entry: Some(("lib".to_string(), "pow".to_string())),
+            ..ImageManifest::default()
+        };
+        build_image(&image_filepath, &module_filepaths, manifest).unwrap();
+
+        // 函数 `lib::pow` 需要参数，而映像无法为入口函数提供参数
+        assert!(run_image(&image_filepath, &[]).is_err());
+
+        fs::remove_file(&image_filepath).unwrap();
+    }
+
+    #[test]
+    fn test_build_image_error() {
+        let image_filepath = get_test_image_filepath("error");
+        let app_filepath = get_test_resource_filepath("app.wasm");
+        let lib_filepath = get_test_resource_filepath("lib.wasm");
+
+        // 模块名称重复
+        let module_filepaths = vec![lib_filepath.clone(), format!("lib={}", app_filepath)];
+        assert!(
+            build_image(&image_filepath, &module_filepaths, ImageManifest::default())
+                .unwrap_err()
+                .contains("duplicate module name")
+        );
+
+        // 入口函数不存在
+        let module_filepaths = vec![lib_filepath.clone(), app_filepath.clone()];
+        let manifest = ImageManifest {
+            entry: Some(("app".to_string(), "main".to_string())),
+            ..ImageManifest::default()
+        };
+        assert!(build_image(&image_filepath, &module_filepaths, manifest).is_err());
+
+        // 入口函数的索引超出范围（模块 "app" 有 1 个导入函数以及 2 个内部函数）
+        let manifest = ImageManifest {
+            entry: Some(("app".to_string(), "3".to_string())),
+            ..ImageManifest::default()
+        };
+        assert!(build_image(&image_filepath, &module_filepaths, manifest)
+            .unwrap_err()
+            .contains("out of range"));
+
+        // 模块文件不存在
+        let module_filepaths = vec![get_test_resource_filepath("none.wasm")];
+        assert!(build_image(&image_filepath, &module_filepaths, ImageManifest::default()).is_err());
+
+        // 以上的错误都不会生成映像文件
+        assert!(fs::metadata(&image_filepath).is_err());
+    }
+
+    #[test]
+    fn test_run_image_with_limits() {
+        let image_filepath = get_test_image_filepath("limits");
+
+        // 模块 "fib" 的 start 函数以及入口函数 `_start` 均计算 fib(10)
+        let module_filepaths = vec![get_test_resource_filepath("fib.wasm")];
+
+        let build_and_run_image = |limits: ResourceLimits| {
+            let manifest = ImageManifest {
+                limits,
+                ..ImageManifest::default()
+            };
+            build_image(&image_filepath, &module_filepaths, manifest).unwrap();
+            run_image(&image_filepath, &[])
+        };
+
+        assert_eq!(
+            build_and_run_image(ResourceLimits::default()).unwrap(),
+            (vec![], 0)
+        );
+
+        // 资源限制对 start 函数同样有效
+        let result = build_and_run_image(ResourceLimits {
+            max_call_depth: Some(5),
+            ..ResourceLimits::default()
+        });
+        assert!(result.unwrap_err().contains("call stack exhausted"));
+
+        let result = build_and_run_image(ResourceLimits {
+            fuel: Some(100),
+            ..ResourceLimits::default()
+        });
+        assert!(result.unwrap_err().contains("out of fuel"));
+
+        // 燃料足够执行 start 函数，但不足以再执行入口函数
+        let result = build_and_run_image(ResourceLimits {
+            fuel: Some(3000),
+            ..ResourceLimits::default()
+        });
+        assert!(result.unwrap_err().contains("out of fuel"));
+
+        fs::remove_file(&image_filepath).unwrap();
+    }
+}
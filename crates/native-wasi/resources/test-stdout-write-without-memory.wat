@@ -0,0 +1,20 @@
+(module
+    ;; 用于测试模块没有内存块时调用 WASI 函数
+
+    (import "wasi_snapshot_preview1" "fd_write"
+        (func $fd_write
+            (param $fd i32)
+            (param $iovs_addr i32)
+            (param $iovs_len i32)
+            (param $result.size i32)
+            (result (;$errno;) i32)))
+
+    (func (export "write_without_memory") (result i32)
+        (call $fd_write
+            (i32.const 1)   ;; fd
+            (i32.const 0)   ;; iovs_addr
+            (i32.const 0)   ;; iovs_len
+            (i32.const 0)   ;; result.size
+        )
+    )
+)
@@ -70,4 +70,22 @@
 
         ;; 返回 (errno, 已写入字节数)
     )
-)
\ No newline at end of file
+
+    ;; 测试 io vector 或者结果的位置超出内存块的范围
+    (func (export "write_out_of_bounds")
+        (param $iovs_addr i32) (param $buf_len i32) (param $result.size i32)
+        (result i32)
+
+        ;; new io vector
+        (i32.store (i32.const 400) (i32.const 1100))        ;; iov.offset
+        (i32.store (i32.const 404) (local.get $buf_len))    ;; iov.len
+
+        ;; call $fd_write
+        (call $fd_write
+            (i32.const 1)   ;; fd
+            (local.get $iovs_addr)
+            (i32.const 1)   ;; iovs_len
+            (local.get $result.size)
+        )
+    )
+)
@@ -24,11 +24,9 @@
 
 use std::io::{Seek, SeekFrom, Write};
 
-use anvm_engine::vm_memory::VMMemory;
-
 use crate::{
     error::Errno,
-    types::{CIOVec, FdStat, Filetype, Whence},
+    types::{FdStat, Filetype, Whence},
     wasi_module_context::WASIModuleContext,
 };
 
@@ -130,11 +128,12 @@ pub fn fd_seek(
 /// - nwritten: size The number of bytes written.
 ///
 /// https://github.com/WebAssembly/WASI/blob/snapshot-01/phases/snapshot/docs.md#-fd_writefd-fd-iovs-ciovec_array---errno-size
+///
+/// 参数 buffers 是各个 ciovec 所指向的数据，调用者需要事先检查它们是否在内存块的范围之内。
 pub fn fd_write(
-    memory_block: &mut VMMemory,
     module_context: &mut WASIModuleContext,
     fd: u32,
-    ciovecs: &[CIOVec],
+    buffers: &[&[u8]],
 ) -> Result<u32, Errno> {
     let option_file_entry = module_context.filesystem_context.get_file_mut(fd);
     if let Some(file_entry) = option_file_entry {
@@ -142,9 +141,7 @@ pub fn fd_write(
         match &mut file_entry.file_source {
             FileSource::File(file) => {
                 let mut wrote_bytes: usize = 0;
-                for ciovec in ciovecs {
-                    let data = memory_block
-                        .read_bytes(ciovec.buf_offset as usize, ciovec.buf_len as usize);
+                for data in buffers {
                     match file.write(data) {
                         Ok(n) => {
                             wrote_bytes += n;
@@ -160,9 +157,7 @@ pub fn fd_write(
             FileSource::Write(w) => {
                 let mut writer = w.as_ref().borrow_mut();
                 let mut wrote_bytes: usize = 0;
-                for ciovec in ciovecs {
-                    let data = memory_block
-                        .read_bytes(ciovec.buf_offset as usize, ciovec.buf_len as usize);
+                for data in buffers {
                     match writer.write(data) {
                         Ok(n) => {
                             wrote_bytes += n;
@@ -41,11 +41,6 @@ pub trait Serialize {
     fn write(&self, writer: &mut dyn Write);
 }
 
-pub trait Deserialize {
-    fn get_deserialize_size() -> usize;
-    fn deserialize(data: &[u8]) -> Self;
-}
-
 /// clockid: Enum(u32)
 /// Identifiers for clocks.
 pub enum ClockID {
@@ -225,38 +220,3 @@ impl TryFrom<u8> for Whence {
         }
     }
 }
-
-/// https://github.com/WebAssembly/WASI/blob/snapshot-01/phases/snapshot/docs.md#-ciovec-struct
-///
-/// ciovec: Struct
-/// A region of memory for scatter/gather writes.
-///
-/// Size: 8
-/// Alignment: 4
-/// Struct members
-/// - buf: ConstPointer<u8> The address of the buffer to be written.
-///   Offset: 0
-/// - buf_len: size The length of the buffer to be written.
-///   Offset: 4
-pub struct CIOVec {
-    /// 数据在内存中的开始位置（地址）
-    pub buf_offset: u32,
-
-    /// 有效数据的长度
-    pub buf_len: u32,
-}
-
-impl Deserialize for CIOVec {
-    fn get_deserialize_size() -> usize {
-        8
-    }
-
-    fn deserialize(data: &[u8]) -> Self {
-        let buf_offset = u32::from_le_bytes(data[0..4].try_into().unwrap());
-        let buf_len = u32::from_le_bytes(data[4..8].try_into().unwrap());
-        Self {
-            buf_offset,
-            buf_len,
-        }
-    }
-}
@@ -103,22 +103,27 @@
 //! - sock_send
 //! - sock_shutdown
 
+use std::{cell::RefCell, rc::Rc};
+
 use anvm_ast::types::{Value, ValueType};
 use anvm_engine::{
     error::{NativeError, NativeTerminate},
-    native_module::{ModuleContext, NativeModule},
+    memory_view::{IOVec, MemoryAccessError, WasmPtr, WasmSlice},
+    native_module::{EmptyModuleContext, NativeFunctionResult, NativeModule},
     vm::VM,
 };
 
 use crate::{
     error::Errno,
     native_fd,
-    types::{CIOVec, Deserialize, Serialize, Whence, MODULE_NAME},
+    types::{Serialize, Whence, MODULE_NAME},
     wasi_module_context::WASIModuleContext,
 };
 
 pub fn new_wasi_module(module_context: WASIModuleContext) -> NativeModule {
-    let mut native_module = NativeModule::new(MODULE_NAME, Box::new(module_context));
+    // 各个本地函数通过闭包共享同一个 WASI 模块上下文
+    let module_context = Rc::new(RefCell::new(module_context));
+    let mut native_module = NativeModule::new(MODULE_NAME, Box::new(EmptyModuleContext::new()));
 
     native_module.add_native_function(
         "fd_write",
@@ -130,7 +135,7 @@ pub fn new_wasi_module(module_context: WASIModuleContext) -> NativeModule {
         ],
         vec!["fd", "iovs", "iovs_len", "result.size"],
         vec![ValueType::I32],
-        fd_write,
+        with_module_context(&module_context, fd_write),
     );
 
     native_module.add_native_function(
@@ -138,7 +143,7 @@ pub fn new_wasi_module(module_context: WASIModuleContext) -> NativeModule {
         vec![ValueType::I32, ValueType::I32],
         vec!["fd", "result.fdstat"],
         vec![ValueType::I32],
-        fd_fdstat_get,
+        with_module_context(&module_context, fd_fdstat_get),
     );
 
     native_module.add_native_function(
@@ -151,7 +156,7 @@ pub fn new_wasi_module(module_context: WASIModuleContext) -> NativeModule {
         ],
         vec!["fd", "offset", "whence", "result.newoffset"],
         vec![ValueType::I32],
-        fd_seek,
+        with_module_context(&module_context, fd_seek),
     );
 
     native_module.add_native_function(
@@ -159,7 +164,7 @@ pub fn new_wasi_module(module_context: WASIModuleContext) -> NativeModule {
         vec![ValueType::I32],
         vec!["fd"],
         vec![ValueType::I32],
-        fd_close,
+        with_module_context(&module_context, fd_close),
     );
 
     native_module.add_native_function(
@@ -167,7 +172,7 @@ pub fn new_wasi_module(module_context: WASIModuleContext) -> NativeModule {
         vec![ValueType::I32],
         vec!["exit_code"],
         vec![],
-        fd_close,
+        proc_exit,
     );
 
     native_module
@@ -183,7 +188,7 @@ pub fn new_wasi_module(module_context: WASIModuleContext) -> NativeModule {
 /// - $result.size：函数的结果，即 `size`，储存在内存的位置
 fn fd_write(
     vm: &mut VM,
-    native_module_index: usize,
+    module_context: &mut WASIModuleContext,
     args: &[Value],
 ) -> Result<Vec<Value>, NativeTerminate> {
     let fd = if let Value::I32(fd) = args[0] {
@@ -192,53 +197,44 @@ fn fd_write(
         unreachable!()
     };
 
-    let iovecs_offset = if let Value::I32(iovecs_offset) = args[1] {
-        iovecs_offset as usize
+    let iovecs = if let (Value::I32(iovs), Value::I32(iovs_len)) = (args[1], args[2]) {
+        WasmSlice::<IOVec>::from_i32(iovs, iovs_len)
     } else {
         unreachable!()
     };
 
-    let iovecs_len = if let Value::I32(iovecs_len) = args[2] {
-        iovecs_len as usize
+    let result_size = if let Value::I32(result_size) = args[3] {
+        WasmPtr::<u32>::from_i32(result_size)
     } else {
         unreachable!()
     };
 
-    let result_size_offset = if let Value::I32(result_size_offset) = args[3] {
-        result_size_offset as usize
-    } else {
-        unreachable!()
+    let memory_block_index = match get_memory_block_index(vm) {
+        Ok(memory_block_index) => memory_block_index,
+        Err(memory_access_error) => return make_memory_access_error_result(memory_access_error),
     };
 
-    let mut ciovecs: Vec<CIOVec> = vec![];
-    let ciovec_data_size = CIOVec::get_deserialize_size();
+    let resource = &mut vm.resource;
+    let memory_block = &resource.memory_blocks[memory_block_index];
 
-    {
-        let memory_block = &vm.resource.memory_blocks[0];
-        for idx in 0..iovecs_len {
-            let data = memory_block.read_bytes(
-                (idx * ciovec_data_size + iovecs_offset) as usize,
-                ciovec_data_size,
-            );
+    // 先检查 IOVec 数组、每个 IOVec 所指向的数据以及储存结果的位置是否在内存块的范围之内
+    let buffers = match iovecs.read_buffers(memory_block) {
+        Ok(buffers) => buffers,
+        Err(memory_access_error) => return make_memory_access_error_result(memory_access_error),
+    };
 
-            let ciovec = CIOVec::deserialize(data);
-            ciovecs.push(ciovec);
-        }
+    if let Err(memory_access_error) = result_size.slice(1).check(memory_block) {
+        return make_memory_access_error_result(memory_access_error);
     }
 
-    let any_module_context = &mut vm.resource.native_modules[native_module_index].module_context;
-    let memory_block = &mut vm.resource.memory_blocks[0];
-
-    match native_fd::fd_write(
-        memory_block,
-        get_wasi_module_context(any_module_context),
-        fd,
-        &ciovecs,
-    ) {
-        Ok(wrote_bytes) => {
-            memory_block.write_i32(result_size_offset, wrote_bytes as i32);
-            make_success_result()
-        }
+    match native_fd::fd_write(module_context, fd, &buffers) {
+        Ok(wrote_bytes) => match result_size.write(
+            &mut resource.memory_blocks[memory_block_index],
+            &wrote_bytes,
+        ) {
+            Ok(_) => make_success_result(),
+            Err(memory_access_error) => make_memory_access_error_result(memory_access_error),
+        },
         Err(errno) => make_error_result(errno),
     }
 }
@@ -255,7 +251,7 @@ fn fd_write(
 /// 由调用者负责分配内存（及指定存放结果的位置）。
 fn fd_fdstat_get(
     vm: &mut VM,
-    native_module_index: usize,
+    module_context: &mut WASIModuleContext,
     args: &[Value],
 ) -> Result<Vec<Value>, NativeTerminate> {
     // 这里不需要检查参数的数量和数据类型，因为 engine 在调用本地函数时已经检查过，
@@ -267,24 +263,30 @@ fn fd_fdstat_get(
         unreachable!()
     };
 
-    let result_fdstat_offset = if let Value::I32(result_fdstat_offset) = args[1] {
-        result_fdstat_offset as usize
+    let result_fdstat = if let Value::I32(result_fdstat) = args[1] {
+        WasmPtr::<u8>::from_i32(result_fdstat)
     } else {
         unreachable!()
     };
 
-    let any_module_context = &mut vm.resource.native_modules[native_module_index].module_context;
+    let memory_block_index = match get_memory_block_index(vm) {
+        Ok(memory_block_index) => memory_block_index,
+        Err(memory_access_error) => return make_memory_access_error_result(memory_access_error),
+    };
 
-    match native_fd::fd_fdstat_get(get_wasi_module_context(any_module_context), fd) {
+    match native_fd::fd_fdstat_get(module_context, fd) {
         Ok(fd_stat) => {
-            let mut data = Vec::<u8>::with_capacity(fd_stat.get_serialize_size());
-            fd_stat.write(&mut data);
-
-            // 写 fdstat 到内存指定位置 `result_fdstat_offset`
-            let memory_block = &mut vm.resource.memory_blocks[0];
-            memory_block.write_bytes(result_fdstat_offset, &data);
-
-            make_success_result()
+            let data = fd_stat.serialize();
+
+            // 写 fdstat 到内存指定位置 `result_fdstat`
+            let memory_block = &mut vm.resource.memory_blocks[memory_block_index];
+            match result_fdstat
+                .slice(data.len() as u32)
+                .write_bytes(memory_block, &data)
+            {
+                Ok(_) => make_success_result(),
+                Err(memory_access_error) => make_memory_access_error_result(memory_access_error),
+            }
         }
         Err(errno) => make_error_result(errno),
     }
@@ -300,7 +302,7 @@ fn fd_fdstat_get(
 /// - $result.newoffset：函数正确运行之后得到的结果，即 `newoffset`，储存在内存的位置（地址）
 fn fd_seek(
     vm: &mut VM,
-    native_module_index: usize,
+    module_context: &mut WASIModuleContext,
     args: &[Value],
 ) -> Result<Vec<Value>, NativeTerminate> {
     let fd = if let Value::I32(fd) = args[0] {
@@ -321,27 +323,35 @@ fn fd_seek(
         unreachable!()
     };
 
-    let result_newoffset_offset = if let Value::I32(result_newoffset_offset) = args[3] {
-        result_newoffset_offset as usize
+    let result_newoffset = if let Value::I32(result_newoffset) = args[3] {
+        WasmPtr::<u64>::from_i32(result_newoffset)
     } else {
         unreachable!()
     };
 
+    let memory_block_index = match get_memory_block_index(vm) {
+        Ok(memory_block_index) => memory_block_index,
+        Err(memory_access_error) => return make_memory_access_error_result(memory_access_error),
+    };
+
+    // 在移动文件指针之前先检查储存结果的位置是否在内存块的范围之内
+    if let Err(memory_access_error) = result_newoffset
+        .slice(1)
+        .check(&vm.resource.memory_blocks[memory_block_index])
+    {
+        return make_memory_access_error_result(memory_access_error);
+    }
+
     if let Ok(whence) = Whence::try_from(whence_i32 as u8) {
-        let any_module_context =
-            &mut vm.resource.native_modules[native_module_index].module_context;
-
-        match native_fd::fd_seek(
-            get_wasi_module_context(any_module_context),
-            fd,
-            offset,
-            whence,
-        ) {
+        match native_fd::fd_seek(module_context, fd, offset, whence) {
             Ok(newoffset) => {
-                let memory_block = &mut vm.resource.memory_blocks[0];
-                memory_block.write_i64(result_newoffset_offset, newoffset as i64);
-
-                make_success_result()
+                let memory_block = &mut vm.resource.memory_blocks[memory_block_index];
+                match result_newoffset.write(memory_block, &newoffset) {
+                    Ok(_) => make_success_result(),
+                    Err(memory_access_error) => {
+                        make_memory_access_error_result(memory_access_error)
+                    }
+                }
             }
             Err(errno) => make_error_result(errno),
         }
@@ -356,8 +366,8 @@ fn fd_seek(
 ///
 /// - $fd：文件描述符
 fn fd_close(
-    vm: &mut VM,
-    native_module_index: usize,
+    _vm: &mut VM,
+    module_context: &mut WASIModuleContext,
     args: &[Value],
 ) -> Result<Vec<Value>, NativeTerminate> {
     let fd = if let Value::I32(fd) = args[0] {
@@ -366,8 +376,7 @@ fn fd_close(
         unreachable!()
     };
 
-    let any_module_context = &mut vm.resource.native_modules[native_module_index].module_context;
-    match native_fd::fd_close(get_wasi_module_context(any_module_context), fd) {
+    match native_fd::fd_close(module_context, fd) {
         Ok(_) => make_success_result(),
         Err(errno) => make_error_result(errno),
     }
@@ -400,16 +409,25 @@ fn proc_exit(
     })
 }
 
-fn get_wasi_module_context(
-    any_module_context: &mut Box<dyn ModuleContext>, /*vm: &mut VM, native_module_index: usize*/
-                                                     /* native_module: &mut NativeModule, */
-) -> &mut WASIModuleContext {
-    // let any_module_context = // &mut vm.resource.native_modules[native_module_index].
-    //     &mut native_module.module_context;
-    any_module_context
-        .as_any()
-        .downcast_mut::<WASIModuleContext>()
-        .unwrap()
+/// 把需要访问 WASI 模块上下文的函数包装为本地函数
+fn with_module_context(
+    module_context: &Rc<RefCell<WASIModuleContext>>,
+    function: fn(&mut VM, &mut WASIModuleContext, &[Value]) -> NativeFunctionResult,
+) -> impl Fn(&mut VM, usize, &[Value]) -> NativeFunctionResult {
+    let module_context = Rc::clone(module_context);
+    move |vm, _native_module_index, args| function(vm, &mut module_context.borrow_mut(), args)
+}
+
+/// 获取调用者所在模块的第 0 个内存块在 VM 里的索引
+///
+/// 模块有可能没有定义（也没有导入）内存块，这时返回越界错误，
+/// 让 VM 抛出对应的陷阱。
+fn get_memory_block_index(vm: &VM) -> Result<usize, MemoryAccessError> {
+    vm.resource
+        .vm_modules
+        .get(vm.status.vm_module_index)
+        .and_then(|vm_module| vm_module.memory_block_indexes.first().copied())
+        .ok_or(MemoryAccessError::OutOfBounds)
 }
 
 fn make_success_result() -> Result<Vec<Value>, NativeTerminate> {
@@ -420,6 +438,13 @@ fn make_error_result(errno: Errno) -> Result<Vec<Value>, NativeTerminate> {
     Ok(vec![Value::I32(u16::from(errno) as i32)])
 }
 
+/// 本地函数访问模块内存失败（比如地址超出了范围）时，中止执行并让 VM 抛出对应的陷阱
+fn make_memory_access_error_result(
+    memory_access_error: MemoryAccessError,
+) -> Result<Vec<Value>, NativeTerminate> {
+    Err(memory_access_error.into_native_terminate(MODULE_NAME))
+}
+
 #[cfg(test)]
 mod tests {
     use std::{
@@ -429,13 +454,14 @@ mod tests {
         rc::Rc,
     };
 
-    use anvm_ast::{ast, types::Value};
+    use anvm_ast::types::Value;
     use anvm_binary_parser::parser;
     use anvm_engine::{
         error::{EngineError, NativeError, NativeTerminate},
         instance::{create_instance, find_ast_module_export_function},
         native_module::NativeModule,
         object::NamedAstModule,
+        trap::{Trap, TrapCode},
     };
 
     use crate::{types::MODULE_NAME, wasi_module_context::WASIModuleContext};
@@ -469,9 +495,10 @@ mod tests {
         ))
     }
 
-    fn get_test_ast_module(filename: &str) -> ast::Module {
+    fn get_test_named_ast_module(filename: &str) -> NamedAstModule {
         let bytes = get_test_binary_resource(filename);
-        parser::parse(&bytes).unwrap()
+        let ast_module = parser::parse(&bytes).unwrap();
+        NamedAstModule::new("test", ast_module)
     }
 
     fn get_test_wasi_module_context(
@@ -509,12 +536,12 @@ mod tests {
         stdout: Rc<RefCell<dyn Write>>,
         stderr: Rc<RefCell<dyn Write>>,
     ) -> Result<Vec<Value>, EngineError> {
-        let ast_module = get_test_ast_module(filename);
+        let named_ast_module = get_test_named_ast_module(filename);
 
-        let function_index = find_ast_module_export_function(&ast_module, export_function_name)
-            .expect(&format!("function {} not found", export_function_name));
+        let function_index =
+            find_ast_module_export_function(&named_ast_module.module, export_function_name)
+                .expect(&format!("function {} not found", export_function_name));
 
-        let named_ast_module = NamedAstModule::new("test", ast_module);
         let wasi_native_module = get_test_native_module(stdin, stdout, stderr);
         let mut vm = create_instance(vec![wasi_native_module], &vec![named_ast_module])?;
         vm.eval_function_by_index(0, function_index as usize, args)
@@ -576,6 +603,71 @@ mod tests {
         assert_eq!(result3, vec![Value::I32(0), Value::I32(12)]);
     }
 
+    #[test]
+    fn test_stdout_write_out_of_bounds() {
+        let module_name = "test-stdout-write.wasm";
+
+        let eval_write = |args: &[i32]| {
+            let stdout = Rc::new(RefCell::new(Vec::<u8>::new()));
+            let clone_stdout = Rc::clone(&stdout);
+            let result = eval(
+                module_name,
+                "write_out_of_bounds",
+                &args.iter().map(|v| Value::I32(*v)).collect::<Vec<Value>>(),
+                Rc::new(RefCell::new(io::empty())),
+                stdout,
+                Rc::new(RefCell::new(io::sink())),
+            );
+            let output_data = clone_stdout.as_ref().borrow().clone();
+            (result, output_data)
+        };
+
+        let (result1, output_data1) = eval_write(&[400, 11, 10]);
+        assert_eq!(result1.unwrap(), vec![Value::I32(0)]);
+        assert_eq!(output_data1, "hello world".as_bytes());
+
+        // io vector 所指向的数据超出了内存块的范围
+        // io vector 本身超出了内存块的范围
+        // 储存结果的位置超出了内存块的范围
+        for args in [[400, 65536, 10], [65532, 11, 10], [400, 11, 65534]] {
+            let (result, output_data) = eval_write(&args);
+            assert!(matches!(
+                result,
+                Err(EngineError::Trap(Trap {
+                    code: TrapCode::MemoryOutOfBounds,
+                    ..
+                }))
+            ));
+
+            // 陷阱发生时不应该写入任何数据
+            assert!(output_data.is_empty());
+        }
+    }
+
+    #[test]
+    fn test_stdout_write_without_memory() {
+        let stdout = Rc::new(RefCell::new(Vec::<u8>::new()));
+        let clone_stdout = Rc::clone(&stdout);
+        let result = eval(
+            "test-stdout-write-without-memory.wasm",
+            "write_without_memory",
+            &vec![],
+            Rc::new(RefCell::new(io::empty())),
+            stdout,
+            Rc::new(RefCell::new(io::sink())),
+        );
+
+        // 模块没有内存块，应该抛出陷阱而不是让宿主程序崩溃
+        assert!(matches!(
+            result,
+            Err(EngineError::Trap(Trap {
+                code: TrapCode::MemoryOutOfBounds,
+                ..
+            }))
+        ));
+        assert!(clone_stdout.as_ref().borrow().is_empty());
+    }
+
     #[test]
     fn test_stdout_write_c() {
         // 该模块是由 C 语言程序编译而来
@@ -632,13 +724,13 @@ mod tests {
         assert_eq!(output_str1, "number: 123, string: foo\nend of stdout");
         assert_eq!(error_str1, "number: 456, string: bar\nend of stderr");
 
-        matches!(
+        assert!(matches!(
             result1,
             Err(EngineError::NativeTerminate(NativeTerminate {
                 module_name: _,
                 native_error: NativeError::Exit(66)
             }))
-        );
+        ));
     }
 
     fn test_args() {
@@ -32,8 +32,6 @@ use std::{
     rc::Rc,
 };
 
-use anvm_engine::native_module::ModuleContext;
-
 use crate::filesystem_context::FileSystemContext;
 
 pub struct WASIModuleContext {
@@ -46,12 +44,6 @@ pub struct WASIModuleContext {
     pub filesystem_context: FileSystemContext,
 }
 
-impl ModuleContext for WASIModuleContext {
-    fn as_any(&mut self) -> &mut dyn std::any::Any {
-        self
-    }
-}
-
 impl WASIModuleContext {
     pub fn new(
         app_path_name: &str,